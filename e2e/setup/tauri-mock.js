@@ -218,6 +218,14 @@
           note_count: db.notes.filter(function (n) {
             return !n.deleted && (n.tags || []).includes(t.name);
           }).length,
+          total_count: db.notes.filter(function (n) {
+            return !n.deleted && (n.tags || []).some(function (name) {
+              return name === t.name || name.indexOf(t.name + '/') === 0;
+            });
+          }).length,
+          description: t.description || '',
+          color: t.color || null,
+          icon: t.icon || null,
         };
       });
     },
@@ -33,6 +33,17 @@ function createTestDb(): Database.Database {
       note_count INTEGER NOT NULL DEFAULT 0
     );
 
+    CREATE TABLE IF NOT EXISTS tag_aliases (
+      alias TEXT PRIMARY KEY,
+      tag_name TEXT NOT NULL,
+      created_at TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS settings (
+      key TEXT PRIMARY KEY,
+      value TEXT NOT NULL
+    );
+
     CREATE TABLE IF NOT EXISTS note_tags (
       note_id TEXT NOT NULL REFERENCES notes(id) ON DELETE CASCADE,
       tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
@@ -224,6 +235,71 @@ describe("extractTags", () => {
     const { extractTags } = await getQueries();
     expect(extractTags("Hello world")).toEqual([]);
   });
+
+  it("extracts Unicode and Bear multi-word tags like the app", async () => {
+    const { extractTags } = await getQueries();
+    expect(extractTags("学习 #编程 和 #日本語/タグ")).toEqual(["日本語/タグ", "编程"]);
+    expect(extractTags("Voir #café et #naïve.")).toEqual(["café", "naïve"]);
+    expect(extractTags("「#タグ」、#标签，完")).toEqual(["タグ", "标签"]);
+    expect(extractTags("Done — #dash-tag.")).toEqual(["dash-tag"]);
+    expect(extractTags("#tag with spaces# and #single")).toEqual(["single", "tag with spaces"]);
+    expect(extractTags("#reading  list#.")).toEqual(["reading list"]);
+    expect(extractTags("#a b#c")).toEqual(["a"]);
+    expect(extractTags("#first line\nsecond#")).toEqual(["first"]);
+  });
+
+  it("skips code, links, HTML and frontmatter", async () => {
+    const { extractTags } = await getQueries();
+    expect(extractTags("Use `#not_tag` here #yes")).toEqual(["yes"]);
+    expect(extractTags("~~~\n#fenced\n~~~\n#after")).toEqual(["after"]);
+    expect(extractTags("Para\n\n    #indented code\n\n#real")).toEqual(["real"]);
+    expect(extractTags("See [#label](https://x.io/#frag) #ok")).toEqual(["ok"]);
+    expect(extractTags("Visit https://example.com/page#anchor now")).toEqual([]);
+    expect(extractTags("<https://example.com/#anchor>")).toEqual([]);
+    expect(extractTags("![#alt](img.png)")).toEqual([]);
+    expect(extractTags("<div>\n#inside html\n</div>")).toEqual([]);
+    expect(extractTags("Link to [[Note#Heading]] and [[#local]]")).toEqual([]);
+    expect(extractTags("---\ntitle: \"#nope\"\ncolor: #fff\n---\nBody #yes")).toEqual(["yes"]);
+  });
+
+  it("handles headings, numbers and punctuation", async () => {
+    const { extractTags } = await getQueries();
+    expect(extractTags("# Title\n## Sub #topic ##")).toEqual(["topic"]);
+    expect(extractTags("Issue #42 and #1")).toEqual([]);
+    expect(extractTags("#2024 #2024-plan")).toEqual(["2024-plan"]);
+    expect(extractTags("Escaped \\#hash and ##double")).toEqual([]);
+    expect(extractTags("C# and a#b and &#35;x")).toEqual([]);
+    expect(extractTags("(#paren) *#em* **#strong** _#under_")).toEqual(["em", "paren", "strong", "under"]);
+    expect(extractTags("#trail/ #dash-")).toEqual(["dash", "trail"]);
+    expect(extractTags("| #cell | x |\n|---|---|\n| #row | y |")).toEqual(["cell", "row"]);
+    expect(extractTags("- [ ] #todo item")).toEqual(["todo"]);
+  });
+});
+
+describe("findTagSpans", () => {
+  it("covers the whole tag, including a multi-word tag's closing #", async () => {
+    const { findTagSpans } = await import("../tags.js");
+    const content = "a #x and #y z#";
+    const spans = findTagSpans(content);
+    expect(spans.map((s) => content.slice(s.start, s.end))).toEqual(["#x", "#y z#"]);
+    expect(spans[1].name).toBe("y z");
+  });
+});
+
+describe("textStats", () => {
+  it("counts words the way the app does", async () => {
+    const { textStats } = await import("../stats.js");
+    expect(textStats("# Hello, world!\n\n- don't stop well-known 3.14")).toEqual({
+      words: 6,
+      characters: 37,
+      readingMinutes: 1,
+    });
+    expect(textStats("今日は良い天気です。Rust で書く").words).toBe(9 + 1 + 3);
+    expect(textStats("cafe\u0301 👍🏽").characters).toBe(5);
+    expect(textStats("नमस्ते दुनिया").words).toBe(2);
+    expect(textStats("中".repeat(2000)).readingMinutes).toBe(4);
+    expect(textStats(" \n").readingMinutes).toBe(0);
+  });
 });
 
 describe("createNote", () => {
@@ -384,6 +460,19 @@ describe("searchNotes", () => {
     const results = searchNotes("React");
     expect(results.length).toBe(1);
   });
+
+  it("scans notes for short terms under the trigram tokenizer", async () => {
+    const { createNote, searchNotes } = await getQueries();
+    testDb.prepare("INSERT INTO settings (key, value) VALUES ('search_tokenizer', 'trigram')").run();
+    createNote("数据库笔记", "关于数据的笔记");
+    createNote("Progress", "Done 100% of it");
+    createNote("Other", "Done 1000 of it");
+    const results = searchNotes("数据");
+    expect(results.map((r) => r.title)).toEqual(["数据库笔记"]);
+    expect(results[0].preview).toBe("关于<b>数据</b>的笔记");
+    // LIKE wildcards in the query match literally
+    expect(searchNotes("0%").map((r) => r.title)).toEqual(["Progress"]);
+  });
 });
 
 describe("getNoteByTitle", () => {
@@ -412,6 +501,31 @@ describe("listTags", () => {
     expect(jsTag).toBeDefined();
     expect(jsTag!.note_count).toBe(2);
   });
+
+  it("creates parent rows with the full parent path", async () => {
+    const { createNote } = await getQueries();
+    createNote("A", "Deep #a/b/c");
+    const rows = testDb.prepare("SELECT name, parent_name FROM tags ORDER BY name").all();
+    expect(rows).toEqual([
+      { name: "a", parent_name: null },
+      { name: "a/b", parent_name: "a" },
+      { name: "a/b/c", parent_name: "a/b" },
+    ]);
+  });
+
+  it("resolves tag aliases", async () => {
+    const { createNote, getNote, resolveTagAliases } = await getQueries();
+    testDb.prepare("INSERT INTO tag_aliases (alias, tag_name, created_at) VALUES (?, ?, ?)").run("ml", "machine-learning", "2024");
+    testDb.prepare("INSERT INTO tag_aliases (alias, tag_name, created_at) VALUES (?, ?, ?)").run("ml/dl", "deep-learning", "2024");
+    expect(resolveTagAliases(["ml", "ml/nlp", "ml/dl/cnn", "machine-learning", "mlx"])).toEqual([
+      "machine-learning",
+      "machine-learning/nlp",
+      "deep-learning/cnn",
+      "mlx",
+    ]);
+    const note = createNote("A", "Notes on #ml and #ml/nlp");
+    expect(getNote(note.id)!.tags.sort()).toEqual(["machine-learning", "machine-learning/nlp"]);
+  });
 });
 
 describe("batchCreateNotes", () => {
@@ -0,0 +1,122 @@
+/// Structured note edits, mirroring the app's `patch_note` command so agents
+/// can change part of a note without resending all of its content.
+
+import { findSection, splitLines, type Line } from "./sections.js";
+
+export type PatchOperation =
+  | { op: "append"; heading?: string; text: string }
+  | { op: "prepend"; heading?: string; text: string }
+  | { op: "replace_section"; heading: string; text: string }
+  | { op: "insert_after"; after: string; text: string }
+  | { op: "replace"; find: string; replace: string; regex?: boolean; count?: number }
+  | { op: "toggle_checklist"; item: string; checked?: boolean };
+
+const CHECKLIST_RE = /^(\s*(?:[-*+]|\d+[.)])\s+)\[( |x|X)\](\s+.*)$/;
+const PRIORITIES = ["low", "medium", "high", "urgent"];
+
+function block(text: string): string {
+  return text.replace(/\n+$/, "") + "\n";
+}
+
+function insertAt(content: string, pos: number, text: string): string {
+  let before = content.slice(0, pos);
+  if (pos > 0 && !before.endsWith("\n")) before += "\n";
+  return before + block(text) + content.slice(pos);
+}
+
+function checklistTitle(rest: string): string {
+  return rest
+    .trim()
+    .split(/\s+/)
+    .filter(
+      (word) =>
+        !/^@due\(\d{4}-\d{2}-\d{2}\)$/.test(word) &&
+        !(word.startsWith("!") && PRIORITIES.includes(word.slice(1).toLowerCase()))
+    )
+    .join(" ");
+}
+
+function applyOperation(content: string, op: PatchOperation): string {
+  switch (op.op) {
+    case "append": {
+      if (!op.heading) {
+        const body = content.replace(/\n+$/, "");
+        return body === "" ? block(op.text) : `${body}\n${block(op.text)}`;
+      }
+      const section = findSection(content, op.heading);
+      const body = content.slice(section.heading.bodyStart, section.end);
+      const last = section.heading.bodyStart + body.trimEnd().length;
+      const pos = last > section.heading.bodyStart && content[last] === "\n" ? last + 1 : last;
+      return insertAt(content, pos, op.text);
+    }
+    case "prepend": {
+      if (!op.heading) return insertAt(content, 0, op.text);
+      return insertAt(content, findSection(content, op.heading).heading.bodyStart, op.text);
+    }
+    case "replace_section": {
+      const section = findSection(content, op.heading);
+      let before = content.slice(0, section.heading.bodyStart);
+      if (!before.endsWith("\n")) before += "\n";
+      const gap = section.end < content.length ? "\n" : "";
+      return before + block(op.text) + gap + content.slice(section.end);
+    }
+    case "insert_after": {
+      const line = splitLines(content).find((l) => l.text.includes(op.after));
+      if (!line) throw new Error(`No line contains '${op.after}'`);
+      return insertAt(content, line.end, op.text);
+    }
+    case "replace": {
+      if (op.find === "") throw new Error("'find' is empty");
+      const limit = op.count ?? Infinity;
+      let n = 0;
+      if (op.regex) {
+        let re: RegExp;
+        try {
+          re = new RegExp(op.find, "g");
+        } catch (e) {
+          throw new Error(`Invalid regex: ${(e as Error).message}`);
+        }
+        if (!re.test(content)) throw new Error(`No match for /${op.find}/`);
+        re.lastIndex = 0;
+        return content.replace(re, (match: string, ...rest: unknown[]) => {
+          if (n++ >= limit) return match;
+          const groups = rest.slice(0, -2) as (string | undefined)[];
+          return op.replace.replace(/\$(\d+|&)/g, (_, g: string) =>
+            g === "&" ? match : groups[Number(g) - 1] ?? ""
+          );
+        });
+      }
+      if (!content.includes(op.find)) throw new Error(`No match for '${op.find}'`);
+      return content.split(op.find).reduce((acc, part, i) => {
+        if (i === 0) return part;
+        return acc + (n++ < limit ? op.replace : op.find) + part;
+      }, "");
+    }
+    case "toggle_checklist": {
+      const wanted = op.item.trim().toLowerCase();
+      const items = splitLines(content)
+        .filter((l) => !l.inCode)
+        .map((l) => ({ line: l, m: l.text.match(CHECKLIST_RE) }))
+        .filter((x): x is { line: Line; m: RegExpMatchArray } => x.m !== null)
+        .map(({ line, m }) => ({ line, m, title: checklistTitle(m[3]).toLowerCase() }));
+      const found =
+        items.find((i) => i.title === wanted) ?? items.find((i) => i.title.includes(wanted));
+      if (!found) throw new Error(`No checklist item matches '${op.item}'`);
+      const checked = op.checked ?? found.m[2] === " ";
+      const markerStart = found.line.start + found.m[1].length;
+      return content.slice(0, markerStart) + (checked ? "[x]" : "[ ]") + content.slice(markerStart + 3);
+    }
+  }
+}
+
+/// Apply `operations` in order. Throws if any operation does not apply, in
+/// which case nothing should be written.
+export function applyPatch(content: string, operations: PatchOperation[]): string {
+  return operations.reduce((current, op, i) => {
+    try {
+      return applyOperation(current, op);
+    } catch (e) {
+      throw new Error(`Operation ${i + 1} (${op.op}) failed: ${(e as Error).message}`);
+    }
+  }, content);
+}
@@ -3,8 +3,10 @@ import fs from "node:fs";
 import os from "node:os";
 import path from "node:path";
 import db from "./connection.js";
-
-const TAG_REGEX = /#([a-zA-Z0-9_]+(?:\/[a-zA-Z0-9_]+)*)/g;
+import { applyPatch, type PatchOperation } from "./patch.js";
+import { textStats } from "./stats.js";
+import { extractTags } from "./tags.js";
+import { findSection, findSectionByAnchor, findSections, sectionAnchors, type Section } from "./sections.js";
 
 /// Notify the Tauri app that the MCP server has written to the database.
 /// Writes a timestamp to a trigger file that the Tauri file watcher monitors.
@@ -218,18 +220,10 @@ export function getActivityFeed(
 }
 
 function wordCount(content: string): number {
-  return content.split(/\s+/).filter((s) => s.length > 0).length;
+  return textStats(content).words;
 }
 
-export function extractTags(content: string): string[] {
-  const tags = new Set<string>();
-  let match: RegExpExecArray | null;
-  const re = new RegExp(TAG_REGEX.source, "g");
-  while ((match = re.exec(content)) !== null) {
-    tags.add(match[1]);
-  }
-  return Array.from(tags);
-}
+export { extractTags };
 
 function getTagsForNote(noteId: string): string[] {
   const rows = db
@@ -242,20 +236,43 @@ function getTagsForNote(noteId: string): string[] {
   return rows.map((r) => r.name);
 }
 
-function getOrCreateTag(name: string): number {
-  const parentName = name.includes("/") ? name.split("/")[0] : null;
-
-  const existing = db
-    .prepare("SELECT id FROM tags WHERE name = ?")
-    .get(name) as { id: number } | undefined;
-
-  if (existing) return existing.id;
+/// Normalise tags through `tag_aliases` like the app, so `ml` and `ml/deep`
+/// become `machine-learning` and `machine-learning/deep`. Order is kept,
+/// duplicates created by the mapping are dropped.
+export function resolveTagAliases(tags: string[]): string[] {
+  const aliases = db
+    .prepare("SELECT alias, tag_name FROM tag_aliases")
+    .all() as { alias: string; tag_name: string }[];
+  const resolved: string[] = [];
+  for (const tag of tags) {
+    // Prefer the most specific alias when both "ml" and "ml/deep" are declared
+    let best: { alias: string; tag_name: string } | undefined;
+    for (const a of aliases) {
+      if ((tag === a.alias || tag.startsWith(a.alias + "/")) && (!best || a.alias.length > best.alias.length)) {
+        best = a;
+      }
+    }
+    const name = best ? best.tag_name + tag.slice(best.alias.length) : tag;
+    if (!resolved.includes(name)) resolved.push(name);
+  }
+  return resolved;
+}
 
-  const result = db
-    .prepare("INSERT INTO tags (name, parent_name, note_count) VALUES (?, ?, 0)")
-    .run(name, parentName);
+/// Parent of a nested tag: `a/b/c` has parent `a/b`.
+function parentTag(name: string): string | null {
+  const pos = name.lastIndexOf("/");
+  return pos === -1 ? null : name.slice(0, pos);
+}
 
-  return Number(result.lastInsertRowid);
+/// Id of the tag row for `name`, creating it and any missing ancestor rows.
+function getOrCreateTag(name: string): number {
+  for (let tag: string | null = name; tag !== null; tag = parentTag(tag)) {
+    db.prepare(
+      "INSERT INTO tags (name, parent_name, note_count) VALUES (?, ?, 0) ON CONFLICT(name) DO NOTHING"
+    ).run(tag, parentTag(tag));
+  }
+  const row = db.prepare("SELECT id FROM tags WHERE name = ?").get(name) as { id: number };
+  return row.id;
 }
 
 export function syncNoteTags(noteId: string, tags: string[]): void {
@@ -269,7 +286,7 @@ export function syncNoteTags(noteId: string, tags: string[]): void {
     ).run(oldTag);
   }
 
-  for (const tag of tags) {
+  for (const tag of resolveTagAliases(tags)) {
     const tagId = getOrCreateTag(tag);
     db.prepare("INSERT OR IGNORE INTO note_tags (note_id, tag_id) VALUES (?, ?)").run(
       noteId,
@@ -362,6 +379,105 @@ export function getNote(id: string): NoteWithTags | null {
   return { ...row, tags: getTagsForNote(id) };
 }
 
+export interface NoteQueryResultRow {
+  block_index: number;
+  query: string;
+  result: unknown;
+  evaluated_at: string;
+}
+
+/// Results of the note's ```bruin-query blocks, as last evaluated by the app.
+export function getNoteQueryResults(noteId: string): NoteQueryResultRow[] {
+  try {
+    const rows = db
+      .prepare(
+        "SELECT block_index, query, result, evaluated_at FROM note_query_results WHERE note_id = ? ORDER BY block_index"
+      )
+      .all(noteId) as (Omit<NoteQueryResultRow, "result"> & { result: string })[];
+    return rows.map((row) => ({ ...row, result: JSON.parse(row.result) }));
+  } catch {
+    // Table is created by the app's migrations; older databases lack it
+    return [];
+  }
+}
+
+export interface OutlineEntry {
+  level: number;
+  heading: string;
+  anchor: string;
+  start: number;
+  end: number;
+  word_count: number;
+}
+
+export interface NoteSection extends OutlineEntry {
+  note_id: string;
+  note_title: string;
+  markdown: string;
+}
+
+function sectionWordCount(content: string, section: Section): number {
+  return wordCount(content.slice(section.heading.bodyStart, section.end));
+}
+
+/// The note's heading outline. Offsets index into the content string and a
+/// section's word count includes its subsections.
+export function getNoteOutline(id: string): OutlineEntry[] | null {
+  const note = db.prepare("SELECT content FROM notes WHERE id = ?").get(id) as
+    | { content: string }
+    | undefined;
+  if (!note) return null;
+
+  const sections = findSections(note.content);
+  const anchors = sectionAnchors(sections);
+  return sections.map((section, i) => ({
+    level: section.heading.level,
+    heading: section.heading.text,
+    anchor: anchors[i],
+    start: section.heading.start,
+    end: section.end,
+    word_count: sectionWordCount(note.content, section),
+  }));
+}
+
+/// One section of a note, by anchor (as in [[Note#anchor]]) or heading path
+/// ("Projects > Alpha"). Throws if the section does not exist.
+export function getNoteSection(
+  id: string,
+  anchor?: string,
+  headingPath?: string
+): NoteSection | null {
+  const note = db.prepare("SELECT id, title, content FROM notes WHERE id = ?").get(id) as
+    | { id: string; title: string; content: string }
+    | undefined;
+  if (!note) return null;
+
+  let section: Section;
+  if (anchor) {
+    const found = findSectionByAnchor(note.content, anchor);
+    if (!found) throw new Error(`Section '${anchor}' not found in '${note.title}'`);
+    section = found.section;
+  } else if (headingPath) {
+    section = findSection(note.content, headingPath);
+  } else {
+    throw new Error("Either anchor or heading_path is required");
+  }
+
+  const sections = findSections(note.content);
+  const anchors = sectionAnchors(sections);
+  return {
+    note_id: note.id,
+    note_title: note.title,
+    level: section.heading.level,
+    heading: section.heading.text,
+    anchor: anchors[sections.findIndex((s) => s.heading.start === section.heading.start)] ?? "",
+    markdown: note.content.slice(section.heading.start, section.end),
+    start: section.heading.start,
+    end: section.end,
+    word_count: sectionWordCount(note.content, section),
+  };
+}
+
 export function getNoteByTitle(
   title: string,
   fuzzy = false
@@ -417,6 +533,42 @@ export function updateNote(
   return getNote(id);
 }
 
+/// Apply structured edits to a note's content. Throws on a version conflict
+/// or when an operation does not apply; nothing is written in that case.
+export function patchNote(
+  id: string,
+  operations: PatchOperation[],
+  expectedVersion?: number
+): NoteWithTags | null {
+  const existing = db.prepare("SELECT * FROM notes WHERE id = ?").get(id) as
+    | (NoteRow & { version: number })
+    | undefined;
+
+  if (!existing) return null;
+
+  if (expectedVersion !== undefined && existing.version !== expectedVersion) {
+    throw new Error(
+      `Version conflict: expected ${expectedVersion} but found ${existing.version}. Another agent may have updated this note.`
+    );
+  }
+  if (operations.length === 0) return getNote(id);
+
+  const newContent = applyPatch(existing.content, operations);
+  const timestamp = now();
+
+  db.prepare(
+    `UPDATE notes SET content = ?, updated_at = ?, word_count = ?, version = version + 1 WHERE id = ?`
+  ).run(newContent, timestamp, wordCount(newContent), id);
+
+  syncNoteTags(id, extractTags(newContent));
+  syncNoteLinks(id, newContent);
+
+  logActivity("agent", "note_patched", id, `Patched note '${existing.title}'`, JSON.stringify({ operations: operations.length }));
+  notifyTauri();
+
+  return getNote(id);
+}
+
 export function deleteNote(
   id: string,
   permanent = false
@@ -534,6 +686,13 @@ export function searchNotes(
   updated_at: string;
   tags: string[];
 }> {
+  // The trigram tokenizer cannot match anything shorter than three
+  // characters, such as most two-character Chinese words
+  const terms = plainTerms(query);
+  if (getSetting("search_tokenizer")?.trim() === "trigram" && terms.some((t) => [...t].length < 3)) {
+    return substringSearch(terms, limit, offset);
+  }
+
   const wsFilter = currentWorkspaceId !== null;
   const wsClause = wsFilter ? "AND n.workspace_id = ?" : "";
 
@@ -570,6 +729,63 @@ export function searchNotes(
   });
 }
 
+/** The words of a search query without its full-text syntax: quotes,
+ * prefix stars and AND/OR/NOT. */
+function plainTerms(query: string): string[] {
+  return query
+    .split(/\s+/)
+    .filter((t) => !["AND", "OR", "NOT"].includes(t))
+    .map((t) => t.replace(/^["*()]+|["*()]+$/g, ""))
+    .filter((t) => t.length > 0);
+}
+
+/** `text` with the LIKE wildcards escaped, for use with `ESCAPE '\'`. */
+function escapeLike(text: string): string {
+  return text.replace(/[\\%_]/g, (c) => `\\${c}`);
+}
+
+/** Notes containing every term, found by scanning rather than through the
+ * index. */
+function substringSearch(
+  terms: string[],
+  limit: number,
+  offset: number
+): Array<{ id: string; title: string; preview: string; updated_at: string; tags: string[] }> {
+  if (terms.length === 0) return [];
+  const wsFilter = currentWorkspaceId !== null;
+  const clauses = terms.map(() => "AND (title LIKE ? ESCAPE '\\' OR content LIKE ? ESCAPE '\\')");
+  const patterns = terms.flatMap((t) => {
+    const pattern = `%${escapeLike(t)}%`;
+    return [pattern, pattern];
+  });
+  const rows = db
+    .prepare(
+      `SELECT * FROM notes WHERE is_trashed = 0 ${wsFilter ? "AND workspace_id = ?" : ""} ${clauses.join(" ")}
+       ORDER BY is_pinned DESC, updated_at DESC LIMIT ? OFFSET ?`
+    )
+    .all(...(wsFilter ? [currentWorkspaceId] : []), ...patterns, limit, offset) as NoteRow[];
+  return rows.map((row) => ({
+    id: row.id,
+    title: row.title,
+    preview: substringSnippet(row.content, terms[0]),
+    updated_at: row.updated_at,
+    tags: getTagsForNote(row.id),
+  }));
+}
+
+/** Text around the first occurrence of `term`, marked the way `highlight()`
+ * marks matches. */
+function substringSnippet(content: string, term: string): string {
+  const lower = content.toLowerCase();
+  // Lowercasing can change lengths; then only exact case is found
+  const found = lower.length === content.length ? lower.indexOf(term.toLowerCase()) : content.indexOf(term);
+  if (found < 0) return content.slice(0, 200);
+  const end = found + term.length;
+  const start = Math.max(0, found - 20);
+  const stop = Math.min(content.length, end + 60);
+  return `${start > 0 ? "..." : ""}${content.slice(start, found)}<b>${content.slice(found, end)}</b>${content.slice(end, stop)}${stop < content.length ? "..." : ""}`;
+}
+
 export function listTags(): Array<{ name: string; note_count: number }> {
   const rows = db
     .prepare("SELECT name, note_count FROM tags WHERE note_count > 0 ORDER BY name")
@@ -621,11 +837,12 @@ export function getBacklinks(
   noteTitle: string
 ): Array<{ id: string; title: string; preview: string; updated_at: string; tags: string[] }> {
   const pattern = `%[[${noteTitle}]]%`;
+  const sectionPattern = `%[[${noteTitle}#%`;
   const rows = db
     .prepare(
-      `SELECT * FROM notes WHERE content LIKE ? AND is_trashed = 0`
+      `SELECT * FROM notes WHERE (content LIKE ? OR content LIKE ?) AND is_trashed = 0`
     )
-    .all(pattern) as NoteRow[];
+    .all(pattern, sectionPattern) as NoteRow[];
 
   return rows.map((row) => ({
     id: row.id,
@@ -1048,16 +1265,22 @@ export function syncNoteLinks(noteId: string, content: string): void {
   // Remove existing links from this source
   db.prepare("DELETE FROM note_links WHERE source_note_id = ?").run(noteId);
 
-  const linkedTitles = extractWikiLinks(content);
-  for (const title of linkedTitles) {
+  const re = new RegExp(WIKI_LINK_REGEX.source, "g");
+  let match: RegExpExecArray | null;
+  while ((match = re.exec(content)) !== null) {
+    // [[Note#Heading]] links to Note; [[#Heading]] stays within this note
+    const title = match[1].split("|")[0].split("#")[0].trim();
+    if (!title) continue;
     const target = db.prepare(
       "SELECT id FROM notes WHERE title = ? AND is_trashed = 0"
     ).get(title) as { id: string } | undefined;
 
     if (target && target.id !== noteId) {
+      // ![[Note]] transcludes the note rather than linking to it
+      const linkType = content[match.index - 1] === "!" ? "embed" : "wiki_link";
       db.prepare(
-        "INSERT OR IGNORE INTO note_links (source_note_id, target_note_id, link_type, created_at) VALUES (?, ?, 'wiki_link', ?)"
-      ).run(noteId, target.id, timestamp);
+        "INSERT OR IGNORE INTO note_links (source_note_id, target_note_id, link_type, created_at) VALUES (?, ?, ?, ?)"
+      ).run(noteId, target.id, linkType, timestamp);
     }
   }
 }
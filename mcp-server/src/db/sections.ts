@@ -0,0 +1,129 @@
+/// Headings and sections of a note's markdown, matching the app's outline
+/// (`get_note_outline`): same section bounds and the same anchors.
+
+export interface Line {
+  text: string;
+  start: number;
+  /// Offset just past the line's newline (or the end of the content)
+  end: number;
+  inCode: boolean;
+}
+
+export interface Heading {
+  level: number;
+  text: string;
+  start: number;
+  bodyStart: number;
+}
+
+export interface Section {
+  heading: Heading;
+  end: number;
+}
+
+const HEADING_RE = /^ {0,3}(#{1,6})(?:[ \t]+(.*?))?(?:[ \t]+#+)?[ \t]*$/;
+
+export function splitLines(content: string): Line[] {
+  const lines: Line[] = [];
+  let offset = 0;
+  let fence: string | null = null;
+  for (const raw of content.split(/(?<=\n)/)) {
+    if (raw === "") continue;
+    const text = raw.replace(/\r?\n$/, "");
+    const marker = text.trimStart().match(/^(`{3,}|~{3,})/)?.[1];
+    let inCode = fence !== null;
+    if (fence === null && marker) {
+      fence = marker;
+      inCode = true;
+    } else if (fence !== null && marker && marker[0] === fence[0] && marker.length >= fence.length) {
+      fence = null;
+    }
+    lines.push({ text, start: offset, end: offset + raw.length, inCode });
+    offset += raw.length;
+  }
+  return lines;
+}
+
+export function findSections(content: string): Section[] {
+  const headings: Heading[] = [];
+  for (const line of splitLines(content)) {
+    if (line.inCode) continue;
+    const m = line.text.match(HEADING_RE);
+    if (m) {
+      headings.push({ level: m[1].length, text: (m[2] ?? "").trim(), start: line.start, bodyStart: line.end });
+    }
+  }
+  return headings.map((heading, i) => ({
+    heading,
+    end: headings.slice(i + 1).find((h) => h.level <= heading.level)?.start ?? content.length,
+  }));
+}
+
+export function findSection(content: string, path: string): Section {
+  const segments = path
+    .split(">")
+    .map((s) => s.trim().replace(/^#+/, "").trim())
+    .filter((s) => s.length > 0);
+  if (segments.length === 0) throw new Error("Heading path is empty");
+
+  const sections = findSections(content);
+  let scopeStart = 0;
+  let scopeEnd = content.length;
+  let parentLevel = 0;
+  let found: Section | undefined;
+  for (const segment of segments) {
+    found = sections.find(
+      (s) =>
+        s.heading.level > parentLevel &&
+        s.heading.start >= scopeStart &&
+        s.end <= scopeEnd &&
+        s.heading.text.toLowerCase() === segment.toLowerCase()
+    );
+    if (!found) throw new Error(`Heading '${segments.join(" > ")}' not found`);
+    scopeStart = found.heading.bodyStart;
+    scopeEnd = found.end;
+    parentLevel = found.heading.level;
+  }
+  return found!;
+}
+
+/// Turn heading text into an anchor: lowercase, words joined by "-",
+/// punctuation dropped.
+export function slugify(text: string): string {
+  let slug = "";
+  for (const c of text.trim()) {
+    if (/[\p{L}\p{N}_-]/u.test(c)) {
+      slug += c.toLowerCase();
+    } else if (/\s/.test(c) && !slug.endsWith("-")) {
+      slug += "-";
+    }
+  }
+  slug = slug.replace(/^-+|-+$/g, "");
+  return slug === "" ? "section" : slug;
+}
+
+/// Unique anchors for `sections`; repeated headings get -1, -2, ... suffixes.
+export function sectionAnchors(sections: Section[]): string[] {
+  const seen: string[] = [];
+  return sections.map((section) => {
+    const base = slugify(section.heading.text);
+    let anchor = base;
+    for (let n = 1; seen.includes(anchor); n++) anchor = `${base}-${n}`;
+    seen.push(anchor);
+    return anchor;
+  });
+}
+
+/// Find a section by its anchor, or by its heading text as written.
+export function findSectionByAnchor(
+  content: string,
+  anchor: string
+): { section: Section; anchor: string } | null {
+  const sections = findSections(content);
+  const anchors = sectionAnchors(sections);
+  const wanted = anchor.trim().replace(/^#/, "");
+  let index = anchors.indexOf(wanted);
+  if (index < 0) index = sections.findIndex((s) => s.heading.text.toLowerCase() === wanted.toLowerCase());
+  if (index < 0) index = anchors.indexOf(slugify(wanted));
+  return index < 0 ? null : { section: sections[index], anchor: anchors[index] };
+}
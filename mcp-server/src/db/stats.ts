@@ -0,0 +1,102 @@
+/// Word, character and reading-time counts, matching the app's
+/// (`markdown::stats::text_stats`) so notes saved here get the same
+/// `word_count` as notes saved in the app.
+
+/// Reading speed for space-separated text, in words per minute.
+const WORDS_PER_MINUTE = 230;
+/// Reading speed for Chinese and Japanese, in characters per minute.
+const CJK_CHARACTERS_PER_MINUTE = 500;
+
+const WHITESPACE_RE = /^\p{White_Space}$/u;
+const ALPHANUMERIC_RE = /^[\p{Alphabetic}\p{N}]$/u;
+const JOINERS = new Set(["'", "’", "-", ".", "_"]);
+
+export interface TextStats {
+  /// Words of space-separated text, plus one for each Chinese or Japanese
+  /// character. Markdown punctuation is not a word.
+  words: number;
+  /// Characters other than whitespace, with combining marks counted as
+  /// part of the character before them.
+  characters: number;
+  /// Minutes to read it, rounded up; 0 only for empty text.
+  readingMinutes: number;
+}
+
+export function textStats(text: string): TextStats {
+  let words = 0;
+  let cjk = 0;
+  let characters = 0;
+  let inWord = false;
+  const chars = Array.from(text);
+  for (let i = 0; i < chars.length; i++) {
+    const c = chars[i];
+    if (WHITESPACE_RE.test(c)) {
+      inWord = false;
+      continue;
+    }
+    if (isMark(c)) continue;
+    characters++;
+    if (isCjk(c)) {
+      cjk++;
+      inWord = false;
+    } else if (ALPHANUMERIC_RE.test(c)) {
+      if (!inWord) {
+        words++;
+        inWord = true;
+      }
+    } else {
+      // "don't", "well-known" and "3.14" are one word each
+      const next = chars[i + 1];
+      const joins =
+        JOINERS.has(c) && next !== undefined && ALPHANUMERIC_RE.test(next) && !isCjk(next);
+      inWord = inWord && joins;
+    }
+  }
+  const minutes = words / WORDS_PER_MINUTE + cjk / CJK_CHARACTERS_PER_MINUTE;
+  return {
+    words: words + cjk,
+    characters,
+    readingMinutes: words + cjk === 0 ? 0 : Math.max(1, Math.ceil(minutes)),
+  };
+}
+
+/// Han ideographs, kana and bopomofo: scripts written without spaces, where
+/// each character counts as a word.
+function isCjk(c: string): boolean {
+  const code = c.codePointAt(0)!;
+  const inRange =
+    (code >= 0x3040 && code <= 0x30ff) || // Hiragana, Katakana
+    (code >= 0x3100 && code <= 0x312f) || // Bopomofo
+    (code >= 0x31f0 && code <= 0x31ff) || // Katakana extensions
+    (code >= 0x3400 && code <= 0x4dbf) || // CJK extension A
+    (code >= 0x4e00 && code <= 0x9fff) || // CJK unified ideographs
+    (code >= 0xf900 && code <= 0xfaff) || // Compatibility ideographs
+    (code >= 0xff66 && code <= 0xff9d) || // Halfwidth katakana
+    (code >= 0x20000 && code <= 0x3134f); // Extensions B to G
+  return inRange && !isMark(c) && c !== "・";
+}
+
+/// Characters that modify the one before them rather than standing alone.
+function isMark(c: string): boolean {
+  const code = c.codePointAt(0)!;
+  const ranges: [number, number][] = [
+    [0x0300, 0x036f], // Combining diacritics
+    [0x1ab0, 0x1aff],
+    [0x1dc0, 0x1dff],
+    [0x200c, 0x200d], // Zero-width (non-)joiners
+    [0x20d0, 0x20ff],
+    [0x3099, 0x309a], // Combining kana voicing marks
+    [0xfe00, 0xfe0f], // Variation selectors
+    [0xfe20, 0xfe2f],
+    [0x1f3fb, 0x1f3ff], // Emoji skin tones
+    [0xe0100, 0xe01ef],
+  ];
+  if (ranges.some(([lo, hi]) => code >= lo && code <= hi)) return true;
+  return (
+    code >= 0x0900 &&
+    code <= 0x0dff &&
+    !ALPHANUMERIC_RE.test(c) &&
+    code !== 0x0964 &&
+    code !== 0x0965
+  );
+}
@@ -0,0 +1,359 @@
+/// Hashtag extraction, matching the app's (`markdown::tags::find_tag_spans`)
+/// so notes saved here get the same tags as notes saved in the app.
+
+/// A hashtag found in note content. `start..end` covers the whole tag in the
+/// source, including the leading `#` and, for Bear multi-word tags, the
+/// closing `#`.
+export interface TagSpan {
+  start: number;
+  end: number;
+  name: string;
+}
+
+const ALPHANUMERIC_RE = /^[\p{Alphabetic}\p{N}]$/u;
+const WHITESPACE_RE = /^\p{White_Space}$/u;
+const CONTROL_RE = /^\p{Cc}$/u;
+const ASCII_PUNCTUATION = "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+const OPENING_PUNCTUATION = new Set(["(", "[", "{", '"', "'", "*", "_", "~", ",", ";", "|"]);
+
+const FENCE_RE = /^[ \t]*(?:>[ \t]?)*[ \t]*(`{3,}|~{3,})/;
+/// HTML blocks that can interrupt a paragraph: comments and block-level tags.
+const HTML_BLOCK_RE =
+  /^ {0,3}(?:<!--|<\/?(?:address|article|aside|blockquote|body|caption|center|col|colgroup|dd|details|dialog|dir|div|dl|dt|fieldset|figcaption|figure|footer|form|h[1-6]|head|header|hr|html|iframe|legend|li|link|main|menu|nav|ol|optgroup|option|p|pre|script|section|source|style|summary|table|tbody|td|textarea|tfoot|th|thead|title|tr|ul)(?:[\s/>]|$))/i;
+/// Any other tag alone on its line starts an HTML block after a blank line.
+const HTML_TAG_LINE_RE = /^ {0,3}<\/?[A-Za-z][A-Za-z0-9-]*(?:\s+[^<>]*)?\/?>\s*$/;
+const HTML_RAW_RE = /^ {0,3}<(pre|script|style|textarea)(?:[\s>]|$)/i;
+const LIST_ITEM_RE = /^ {0,3}(?:[-*+]|\d{1,9}[.)])(?:[ \t]|$)/;
+const LINK_DEFINITION_RE = /^ {0,3}\[[^\]\n]+\]:\s*\S+(?:\s+(?:"[^"]*"|'[^']*'|\([^)]*\)))?\s*$/;
+const WIKI_LINK_RE = /\[\[[^\]\n]*\]\]/g;
+const AUTOLINK_RE = /<(?:[A-Za-z][A-Za-z0-9+.-]{1,31}:[^\s<>]*|[^\s<>@]+@[^\s<>@]+)>/g;
+const INLINE_HTML_RE = /<!--[\s\S]*?-->|<\/?[A-Za-z][A-Za-z0-9-]*(?:\s+[^<>]*)?\/?>/g;
+
+/// Extract tags from markdown content, ignoring code, links, HTML and
+/// frontmatter. Sorted and deduplicated, like the app.
+export function extractTags(content: string): string[] {
+  const tags = new Set(findTagSpans(content).map((span) => span.name));
+  return Array.from(tags).sort();
+}
+
+/// Find every hashtag in `content`, skipping YAML frontmatter, fenced and
+/// indented code, HTML blocks, inline code, links, images, autolinks,
+/// inline HTML and `[[wiki links]]`.
+///
+/// A tag starts with `#` at the start of a line or after whitespace or
+/// opening punctuation, and runs over Unicode letters, digits, marks, `_`,
+/// `-` and `/`. Bear-style `#multi word tags#` are recognised when the
+/// closing `#` is on the same line and directly follows a non-space.
+/// Escaped `\#`, `##` and purely numeric tags such as `#1` are not tags.
+export function findTagSpans(content: string): TagSpan[] {
+  const skipped = skippedRanges(content);
+  const spans: TagSpan[] = [];
+  for (const [runStart, runEnd] of textRuns(content, skipped)) {
+    let pos = runStart;
+    while (pos < runEnd) {
+      const at = content.indexOf("#", pos);
+      if (at === -1 || at >= runEnd) break;
+      pos = at + 1;
+      if (!canStartTag(lastChar(content.slice(0, at)))) continue;
+      const rest = content.slice(at + 1, runEnd);
+      const match = multiWordTag(rest) ?? closeEmphasis(singleWordTag(rest), content.slice(runStart, at));
+      if (match) {
+        const [len, name] = match;
+        spans.push({ start: at, end: at + 1 + len, name });
+        pos = at + 1 + len;
+      }
+    }
+  }
+  return spans;
+}
+
+/// Source ranges that never hold tags, sorted by start.
+function skippedRanges(content: string): [number, number][] {
+  const ranges: [number, number][] = [];
+  const lines: [number, number][] = [];
+  let offset = 0;
+  for (const line of content.split("\n")) {
+    lines.push([offset, offset + line.length]);
+    offset += line.length + 1;
+  }
+  const text = (i: number) => content.slice(lines[i][0], lines[i][1]);
+  const isBlank = (i: number) => text(i).trim() === "";
+
+  let i = 0;
+  // YAML frontmatter, closed by `---` or `...`
+  if (lines.length > 1 && text(0).trimEnd() === "---") {
+    for (let j = 1; j < lines.length; j++) {
+      if (/^(?:---|\.\.\.)\s*$/.test(text(j))) {
+        ranges.push([0, lines[j][1]]);
+        i = j + 1;
+        break;
+      }
+    }
+  }
+
+  let inList = false;
+  let previousBlank = true;
+  let inParagraph = false;
+  while (i < lines.length) {
+    const line = text(i);
+    const fence = FENCE_RE.exec(line);
+    if (fence) {
+      // Runs to a closing fence at least as long, or the end of the text
+      const marker = fence[1];
+      let j = i + 1;
+      while (j < lines.length) {
+        const close = FENCE_RE.exec(text(j));
+        if (close && close[1][0] === marker[0] && close[1].length >= marker.length && text(j).trim() === close[0].trim()) break;
+        j++;
+      }
+      const end = Math.min(j, lines.length - 1);
+      ranges.push([lines[i][0], lines[end][1]]);
+      i = end + 1;
+      previousBlank = false;
+      inParagraph = false;
+      continue;
+    }
+    if (HTML_BLOCK_RE.test(line) || (!inParagraph && HTML_TAG_LINE_RE.test(line))) {
+      const raw = HTML_RAW_RE.exec(line);
+      let j = i;
+      if (raw) {
+        const close = new RegExp(`</${raw[1]}>`, "i");
+        while (j < lines.length - 1 && !close.test(text(j))) j++;
+      } else if (line.trimStart().startsWith("<!--")) {
+        while (j < lines.length - 1 && !text(j).includes("-->")) j++;
+      } else {
+        while (j < lines.length - 1 && !isBlank(j + 1)) j++;
+      }
+      ranges.push([lines[i][0], lines[j][1]]);
+      i = j + 1;
+      previousBlank = false;
+      inParagraph = false;
+      continue;
+    }
+    if (isBlank(i)) {
+      previousBlank = true;
+      inParagraph = false;
+      i++;
+      continue;
+    }
+    const indented = /^(?: {4}|\t)/.test(line);
+    if (indented && previousBlank && !inList) {
+      // Indented code runs over indented and blank lines
+      let j = i;
+      while (j + 1 < lines.length && (isBlank(j + 1) || /^(?: {4}|\t)/.test(text(j + 1)))) j++;
+      while (j > i && isBlank(j)) j--;
+      ranges.push([lines[i][0], lines[j][1]]);
+      i = j + 1;
+      previousBlank = false;
+      continue;
+    }
+    if (!indented) {
+      if (LIST_ITEM_RE.test(line)) inList = true;
+      else if (previousBlank) inList = false;
+    }
+    if (LINK_DEFINITION_RE.test(line)) {
+      ranges.push([lines[i][0], lines[i][1]]);
+    }
+    previousBlank = false;
+    inParagraph = true;
+    i++;
+  }
+
+  ranges.push(...inlineSkips(content, ranges));
+  return ranges.sort((a, b) => a[0] - b[0]);
+}
+
+/// Code spans, links, images, autolinks, inline HTML and wiki links outside
+/// the block-level `skipped` ranges.
+function inlineSkips(content: string, skipped: [number, number][]): [number, number][] {
+  const ranges: [number, number][] = [];
+  const inBlock = (i: number) => skipped.some(([s, e]) => i >= s && i < e);
+  const covered = (i: number) => inBlock(i) || ranges.some(([s, e]) => i >= s && i < e);
+
+  // Code spans first: nothing inside them is a link or HTML
+  for (let i = 0; i < content.length; i++) {
+    if (content[i] !== "`" || inBlock(i) || (i > 0 && content[i - 1] === "\\")) continue;
+    let n = 0;
+    while (content[i + n] === "`") n++;
+    let j = i + n;
+    let close = -1;
+    while (j < content.length) {
+      const k = content.indexOf("`", j);
+      if (k === -1) break;
+      let m = 0;
+      while (content[k + m] === "`") m++;
+      if (m === n) {
+        close = k;
+        break;
+      }
+      j = k + m;
+    }
+    if (close === -1) {
+      i += n - 1;
+      continue;
+    }
+    ranges.push([i, close + n]);
+    i = close + n - 1;
+  }
+
+  for (const re of [AUTOLINK_RE, INLINE_HTML_RE, WIKI_LINK_RE]) {
+    for (const m of content.matchAll(re)) {
+      const start = m.index!;
+      if (!covered(start)) ranges.push([start, start + m[0].length]);
+    }
+  }
+
+  // Inline links and images: the text and the destination
+  for (let i = 0; i < content.length; i++) {
+    if (content[i] !== "[" || covered(i) || (i > 0 && content[i - 1] === "\\")) continue;
+    const close = matchingBracket(content, i);
+    if (close === -1 || content[close + 1] !== "(") continue;
+    const end = content.indexOf(")", close + 2);
+    if (end === -1 || content.slice(close + 2, end).includes("\n\n")) continue;
+    const start = i > 0 && content[i - 1] === "!" ? i - 1 : i;
+    ranges.push([start, end + 1]);
+    i = end;
+  }
+  return ranges;
+}
+
+/// Index of the `]` closing the `[` at `open`, on the same paragraph.
+function matchingBracket(content: string, open: number): number {
+  let depth = 0;
+  for (let i = open; i < content.length; i++) {
+    const c = content[i];
+    if (c === "\\") {
+      i++;
+    } else if (c === "[") {
+      depth++;
+    } else if (c === "]") {
+      depth--;
+      if (depth === 0) return i;
+    } else if (c === "\n" && content[i + 1] === "\n") {
+      return -1;
+    }
+  }
+  return -1;
+}
+
+/// Ranges of text outside `skipped`, split at line breaks so a tag never
+/// spans lines.
+function textRuns(content: string, skipped: [number, number][]): [number, number][] {
+  const runs: [number, number][] = [];
+  let start = 0;
+  const bounds = [...skipped, [content.length, content.length] as [number, number]];
+  for (const [s, e] of bounds) {
+    if (s > start) {
+      let lineStart = start;
+      for (let i = start; i <= s; i++) {
+        if (i === s || content[i] === "\n") {
+          if (i > lineStart) runs.push([lineStart, i]);
+          lineStart = i + 1;
+        }
+      }
+    }
+    start = Math.max(start, e);
+  }
+  return runs;
+}
+
+function lastChar(text: string): string | undefined {
+  if (text === "") return undefined;
+  return Array.from(text.slice(-2)).pop();
+}
+
+function canStartTag(prev: string | undefined): boolean {
+  if (prev === undefined) return true;
+  return (
+    WHITESPACE_RE.test(prev) ||
+    OPENING_PUNCTUATION.has(prev) ||
+    (!isAscii(prev) && isPunctuation(prev))
+  );
+}
+
+function isTagChar(c: string): boolean {
+  return (
+    ALPHANUMERIC_RE.test(c) ||
+    c === "_" ||
+    c === "-" ||
+    c === "/" ||
+    (!isAscii(c) && !WHITESPACE_RE.test(c) && !CONTROL_RE.test(c) && !isPunctuation(c))
+  );
+}
+
+function isAscii(c: string): boolean {
+  return c.codePointAt(0)! < 0x80;
+}
+
+/// ASCII punctuation plus the Unicode punctuation and symbol blocks that
+/// commonly sit next to tags: Latin-1 symbols, general punctuation, CJK
+/// symbols and fullwidth forms.
+function isPunctuation(c: string): boolean {
+  if (isAscii(c)) return ASCII_PUNCTUATION.includes(c);
+  if (ALPHANUMERIC_RE.test(c)) return false;
+  const code = c.codePointAt(0)!;
+  const ranges: [number, number][] = [
+    [0x00a0, 0x00bf],
+    [0x00d7, 0x00d7],
+    [0x00f7, 0x00f7],
+    [0x2000, 0x206f],
+    [0x2e00, 0x2e7f],
+    [0x3000, 0x303f],
+    [0x30fb, 0x30fb],
+    [0xfe30, 0xfe6f],
+    [0xff01, 0xff0f],
+    [0xff1a, 0xff20],
+    [0xff3b, 0xff40],
+    [0xff5b, 0xff65],
+  ];
+  return ranges.some(([lo, hi]) => code >= lo && code <= hi);
+}
+
+/// Match a single-word tag at the start of `rest` (the text after `#`).
+/// Returns the matched length and the tag name.
+function singleWordTag(rest: string): [number, string] | null {
+  let end = 0;
+  for (const c of rest) {
+    if (!isTagChar(c)) break;
+    end += c.length;
+  }
+  const name = rest.slice(0, end).replace(/[/-]+$/, "");
+  if (name === "" || name.startsWith("/") || /^[0-9]+$/.test(name)) return null;
+  return [name.length, name];
+}
+
+/// Drop trailing underscores that close `_emphasis_` opened earlier on the
+/// line, as in `_#tag_`; they are Markdown, not part of the tag.
+function closeEmphasis(match: [number, string] | null, before: string): [number, string] | null {
+  if (!match || !match[1].endsWith("_") || !/(?:^|[\s\p{P}])_+(?!\s)/u.test(before)) return match;
+  const name = match[1].replace(/_+$/, "").replace(/[/-]+$/, "");
+  return name === "" || /^[0-9]+$/.test(name) ? null : [name.length, name];
+}
+
+/// Match a Bear multi-word tag (`#tag with spaces#`) at the start of `rest`.
+/// The first `#` on the line decides: it must follow a non-space and be
+/// followed by whitespace, punctuation or the end of the text.
+function multiWordTag(rest: string): [number, string] | null {
+  const first = Array.from(rest.slice(0, 2))[0];
+  if (first === undefined || !isTagChar(first) || first === "/") return null;
+  let i = 0;
+  for (const c of rest) {
+    if (c === "#") {
+      const inner = rest.slice(0, i);
+      const after = Array.from(rest.slice(i + 1, i + 3))[0];
+      const closes = after === undefined || (after !== "#" && !isTagChar(after));
+      if (
+        !closes ||
+        !inner.includes(" ") ||
+        /\s$/u.test(inner) ||
+        /^[0-9 ]*$/.test(inner)
+      ) {
+        return null;
+      }
+      return [i + 1, inner.split(/\s+/u).filter(Boolean).join(" ")];
+    }
+    if (!(isTagChar(c) || c === " " || c === "\t")) return null;
+    i += c.length;
+  }
+  return null;
+}
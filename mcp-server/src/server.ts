@@ -1,6 +1,6 @@
 import { McpServer } from "@modelcontextprotocol/sdk/server/mcp.js";
 import { z } from "zod";
-import { createNote, getNote, getNoteByTitle, updateNote, deleteNote, setNoteState, listNotes, searchNotes, listTags, batchCreateNotes, appendToNote, getBacklinks, getDailyNote, advancedQuery, importMarkdownFiles, getActivityFeed, listTemplates, createNoteFromTemplate, registerWebhook, listWebhooks, deleteWebhook, createWorkspace, listWorkspaces, deleteWorkspace, setCurrentWorkspace, getCurrentWorkspace, getForwardLinks, getKnowledgeGraph, semanticSearch, upsertNoteEmbedding, getAllEmbeddings, registerAgent, listAgents, getAgent, getAgentAuditLog, setCurrentAgent, getCurrentAgent, createTask, listTasks, updateTask, completeTask, assignTask, listWorkflowTemplates, getWorkflowTemplate, createWorkflowTemplate, executeWorkflow, updateWebhook, testWebhook, getWebhookLogs, bindAgentWorkspace, getAgentWorkspaces, unbindAgentWorkspace, updateAgent, deactivateAgent, deleteWorkflowTemplate, pinNote, restoreNote, getSetting, setSetting, getAllSettings, exportNoteMarkdown, exportNoteHtml } from "./db/queries.js";
+import { createNote, getNote, getNoteQueryResults, getNoteByTitle, updateNote, patchNote, deleteNote, getNoteOutline, getNoteSection, setNoteState, listNotes, searchNotes, listTags, batchCreateNotes, appendToNote, getBacklinks, getDailyNote, advancedQuery, importMarkdownFiles, getActivityFeed, listTemplates, createNoteFromTemplate, registerWebhook, listWebhooks, deleteWebhook, createWorkspace, listWorkspaces, deleteWorkspace, setCurrentWorkspace, getCurrentWorkspace, getForwardLinks, getKnowledgeGraph, semanticSearch, upsertNoteEmbedding, getAllEmbeddings, registerAgent, listAgents, getAgent, getAgentAuditLog, setCurrentAgent, getCurrentAgent, createTask, listTasks, updateTask, completeTask, assignTask, listWorkflowTemplates, getWorkflowTemplate, createWorkflowTemplate, executeWorkflow, updateWebhook, testWebhook, getWebhookLogs, bindAgentWorkspace, getAgentWorkspaces, unbindAgentWorkspace, updateAgent, deactivateAgent, deleteWorkflowTemplate, pinNote, restoreNote, getSetting, setSetting, getAllSettings, exportNoteMarkdown, exportNoteHtml } from "./db/queries.js";
 
 function text(data: unknown) {
   return { content: [{ type: "text" as const, text: JSON.stringify(data, null, 2) }] };
@@ -113,14 +113,15 @@ export function createServer(): McpServer {
 
   server.tool(
     "read_note",
-    "Read a note by ID",
+    "Read a note by ID. Includes the structured results of any ```bruin-query blocks as query_results",
     {
       id: z.string().describe("The UUID of the note"),
     },
     async (args) => {
       const note = getNote(args.id);
       if (!note) return error(`Note '${args.id}' not found`);
-      return text(note);
+      const queryResults = getNoteQueryResults(note.id);
+      return text(queryResults.length > 0 ? { ...note, query_results: queryResults } : note);
     }
   );
 
@@ -141,6 +142,73 @@ export function createServer(): McpServer {
     }
   );
 
+  server.tool(
+    "get_note_outline",
+    "List a note's headings with their level, anchor, offsets and word count, to read or edit one section of a long note",
+    {
+      id: z.string().describe("The UUID of the note"),
+    },
+    async (args) => {
+      const outline = getNoteOutline(args.id);
+      if (!outline) return error(`Note '${args.id}' not found`);
+      return text(outline);
+    }
+  );
+
+  server.tool(
+    "read_note_section",
+    "Read a single section of a note (heading line included) by anchor from get_note_outline or [[Note#anchor]], or by heading path such as \"Projects > Alpha\"",
+    {
+      id: z.string().describe("The UUID of the note"),
+      anchor: z.string().optional().describe("Section anchor, or the heading text"),
+      heading_path: z.string().optional().describe("Nested heading path separated by '>'"),
+    },
+    async (args) => {
+      try {
+        const section = getNoteSection(args.id, args.anchor, args.heading_path);
+        if (!section) return error(`Note '${args.id}' not found`);
+        return text(section);
+      } catch (e: unknown) {
+        return error((e as Error).message);
+      }
+    }
+  );
+
+  const patchOperation = z.discriminatedUnion("op", [
+    z.object({ op: z.literal("append"), heading: z.string().optional(), text: z.string() }),
+    z.object({ op: z.literal("prepend"), heading: z.string().optional(), text: z.string() }),
+    z.object({ op: z.literal("replace_section"), heading: z.string(), text: z.string() }),
+    z.object({ op: z.literal("insert_after"), after: z.string(), text: z.string() }),
+    z.object({
+      op: z.literal("replace"),
+      find: z.string(),
+      replace: z.string(),
+      regex: z.boolean().optional(),
+      count: z.number().int().positive().optional(),
+    }),
+    z.object({ op: z.literal("toggle_checklist"), item: z.string(), checked: z.boolean().optional() }),
+  ]);
+
+  server.tool(
+    "patch_note",
+    "Edit part of a note without resending its whole content. Operations apply in order and all must succeed: append/prepend (to a heading section such as \"Projects > Alpha\", or the whole note), replace_section, insert_after a line containing text, replace (literal or regex, optionally limited by count) and toggle_checklist.",
+    {
+      id: z.string().describe("The UUID of the note to patch"),
+      operations: z.array(patchOperation).describe("Edits to apply, in order"),
+      expected_version: z.number().int().optional().describe("Fail if the note's version differs (optimistic locking)"),
+    },
+    async (args) => {
+      try {
+        const note = patchNote(args.id, args.operations, args.expected_version);
+        if (!note) return error(`Note '${args.id}' not found`);
+        notifyNoteChanged(args.id);
+        return text(note);
+      } catch (e: unknown) {
+        return error((e as Error).message);
+      }
+    }
+  );
+
   server.tool(
     "delete_note",
     "Delete a note (soft delete to trash, or permanent)",
@@ -0,0 +1,462 @@
+use crate::commands::export::image_mime;
+use crate::commands::files::{images_dir, images_dir_for, stored_asset_name};
+use crate::commands::images::thumbnail_path;
+use crate::commands::settings::read_setting;
+use crate::db::models::{Attachment, AttachmentDetails, AttachmentGcReport, AttachmentNote};
+use crate::document::image::image_extension;
+use crate::document::office::{docx_text, odt_text};
+use crate::document::pdf_text::pdf_page_texts;
+use crate::markdown::attachments::attachment_file_names;
+use crate::sync::icloud;
+use chrono::{Duration, Utc};
+use rusqlite::{Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, State};
+use uuid::Uuid;
+
+/// Days an attachment no note refers to is kept before it is deleted, unless
+/// the `attachment_gc_grace_days` setting says otherwise.
+const DEFAULT_GRACE_DAYS: i64 = 30;
+
+const PDF_MIME: &str = "application/pdf";
+const DOCX_MIME: &str = "application/vnd.openxmlformats-officedocument.wordprocessingml.document";
+const ODT_MIME: &str = "application/vnd.oasis.opendocument.text";
+
+/// Attachments whose text is extracted for search.
+const INDEXED_MIME_TYPES: [&str; 6] = [PDF_MIME, DOCX_MIME, ODT_MIME, "text/plain", "text/markdown", "text/csv"];
+
+/// Larger attachments are not read for their text.
+const MAX_INDEXED_SIZE: i64 = 64 * 1024 * 1024;
+
+const ATTACHMENT_COLUMNS: &str = "a.id, a.hash, a.file_name, a.original_name, a.mime_type, a.size, a.created_at, a.orphaned_at, \
+     (SELECT COUNT(*) FROM note_attachments na WHERE na.attachment_id = a.id), a.thumbnail";
+
+fn row_to_attachment(row: &rusqlite::Row, images_dir: &Path) -> rusqlite::Result<Attachment> {
+    let file_name: String = row.get(2)?;
+    Ok(Attachment {
+        id: row.get(0)?,
+        hash: row.get(1)?,
+        path: images_dir.join(&file_name).to_string_lossy().to_string(),
+        file_name,
+        original_name: row.get(3)?,
+        mime_type: row.get(4)?,
+        size: row.get(5)?,
+        created_at: row.get(6)?,
+        orphaned_at: row.get(7)?,
+        note_count: row.get(8)?,
+        thumbnail: row
+            .get::<_, Option<String>>(9)?
+            .filter(|t| !t.is_empty())
+            .map(|t| thumbnail_path(images_dir, &t).to_string_lossy().to_string()),
+    })
+}
+
+/// Attachments a note refers to, in the order they were stored.
+#[tauri::command]
+pub fn list_note_attachments(
+    app_handle: AppHandle,
+    db: State<'_, Mutex<Connection>>,
+    note_id: String,
+) -> Result<Vec<Attachment>, String> {
+    let images_dir = images_dir(&app_handle)?;
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM attachments a JOIN note_attachments n ON n.attachment_id = a.id \
+             WHERE n.note_id = ?1 ORDER BY a.created_at",
+            ATTACHMENT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let attachments = stmt
+        .query_map([&note_id], |row| row_to_attachment(row, &images_dir))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(attachments)
+}
+
+/// An attachment with the notes that refer to it.
+#[tauri::command]
+pub fn get_attachment(
+    app_handle: AppHandle,
+    db: State<'_, Mutex<Connection>>,
+    id: String,
+) -> Result<AttachmentDetails, String> {
+    let images_dir = images_dir(&app_handle)?;
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    let attachment = conn
+        .query_row(
+            &format!("SELECT {} FROM attachments a WHERE a.id = ?1", ATTACHMENT_COLUMNS),
+            [&id],
+            |row| row_to_attachment(row, &images_dir),
+        )
+        .map_err(|e| format!("Attachment not found: {}", e))?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT n.id, n.title, n.is_trashed FROM notes n JOIN note_attachments na ON na.note_id = n.id \
+             WHERE na.attachment_id = ?1 ORDER BY n.updated_at DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let notes = stmt
+        .query_map([&id], |row| {
+            Ok(AttachmentNote {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                is_trashed: row.get::<_, i32>(2)? != 0,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+    let exists = Path::new(&attachment.path).is_file();
+    Ok(AttachmentDetails { attachment, notes, exists })
+}
+
+/// Delete attachments no note has referred to for `grace_days` (by default
+/// the `attachment_gc_grace_days` setting, or 30).
+#[tauri::command]
+pub fn collect_attachment_garbage(
+    app_handle: AppHandle,
+    db: State<'_, Mutex<Connection>>,
+    grace_days: Option<i64>,
+) -> Result<AttachmentGcReport, String> {
+    let images_dir = images_dir(&app_handle)?;
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    collect_garbage(&conn, &images_dir, grace_days)
+}
+
+pub(crate) fn collect_garbage(
+    conn: &Connection,
+    images_dir: &Path,
+    grace_days: Option<i64>,
+) -> Result<AttachmentGcReport, String> {
+    let grace_days = grace_days
+        .or_else(|| read_setting(conn, "attachment_gc_grace_days")?.trim().parse().ok())
+        .unwrap_or(DEFAULT_GRACE_DAYS)
+        .max(0);
+    let mut report = AttachmentGcReport { registered: register_stored_files(conn, images_dir)?, ..Default::default() };
+
+    // References are rebuilt first, so files registered just now (or notes
+    // changed behind our back) are not taken for orphans
+    rebuild_note_attachments(conn)?;
+
+    let now = Utc::now();
+    let kept = kept_file_names(conn, images_dir)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, file_name, orphaned_at IS NOT NULL FROM attachments \
+             WHERE id NOT IN (SELECT attachment_id FROM note_attachments)",
+        )
+        .map_err(|e| e.to_string())?;
+    let unreferenced: Vec<(String, String, bool)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+    for (id, file_name, orphaned) in unreferenced {
+        let orphaned_at = if kept.contains(&file_name) {
+            None
+        } else if orphaned {
+            continue;
+        } else {
+            report.orphaned += 1;
+            Some(now.to_rfc3339())
+        };
+        conn.execute(
+            "UPDATE attachments SET orphaned_at = ?1 WHERE id = ?2",
+            rusqlite::params![orphaned_at, id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    let cutoff = (now - Duration::days(grace_days)).to_rfc3339();
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, hash, file_name, size, thumbnail FROM attachments \
+             WHERE orphaned_at IS NOT NULL AND orphaned_at <= ?1",
+        )
+        .map_err(|e| e.to_string())?;
+    let expired: Vec<(String, String, String, i64, Option<String>)> = stmt
+        .query_map([&cutoff], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+    for (id, hash, file_name, size, thumbnail) in expired {
+        match fs::remove_file(images_dir.join(&file_name)) {
+            Ok(()) => report.freed_bytes += size,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                log::warn!("Failed to delete attachment {}: {}", file_name, e);
+                continue;
+            }
+        }
+        if let Some(thumbnail) = thumbnail.filter(|t| !t.is_empty()) {
+            let _ = fs::remove_file(thumbnail_path(images_dir, &thumbnail));
+        }
+        // Other devices have had the grace period to pick up the note change
+        let _ = icloud::delete_attachment(&hash, &file_name);
+        conn.execute("DELETE FROM attachments WHERE id = ?1", [&id]).map_err(|e| e.to_string())?;
+        report.removed += 1;
+    }
+    Ok(report)
+}
+
+/// Files referred to outside notes: by templates, and by the previous content
+/// of notes a tag change that can still be undone rewrote.
+fn kept_file_names(conn: &Connection, images_dir: &Path) -> Result<HashSet<String>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT content FROM templates \
+             UNION ALL SELECT cn.previous_content FROM tag_change_notes cn \
+             JOIN tag_changes c ON c.id = cn.change_id WHERE c.undone_at IS NULL",
+        )
+        .map_err(|e| e.to_string())?;
+    let contents = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok());
+    let mut names = HashSet::new();
+    for content in contents {
+        names.extend(attachment_file_names(&content, Some(images_dir)));
+    }
+    Ok(names)
+}
+
+/// Extract and index the text of the next attachment that has not been
+/// indexed yet, returning false when there is none. The database is only
+/// locked around the queries, not while the file is read.
+pub(crate) fn index_next_attachment(db: &Mutex<Connection>, images_dir: &Path) -> Result<bool, String> {
+    let next: Option<(String, String, String, i64)> = {
+        let conn = db.lock().map_err(|e| e.to_string())?;
+        conn.query_row(
+            &format!(
+                "SELECT id, file_name, mime_type, size FROM attachments \
+                 WHERE indexed_at IS NULL AND mime_type IN ({}) ORDER BY created_at LIMIT 1",
+                vec!["?"; INDEXED_MIME_TYPES.len()].join(", ")
+            ),
+            rusqlite::params_from_iter(INDEXED_MIME_TYPES),
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+    };
+    let Some((id, file_name, mime, size)) = next else {
+        return Ok(false);
+    };
+
+    let pages = if size > MAX_INDEXED_SIZE {
+        Err(format!("Too large to index ({} bytes)", size))
+    } else {
+        fs::read(images_dir.join(&file_name))
+            .map_err(|e| format!("Failed to read {}: {}", file_name, e))
+            .and_then(|data| {
+                // A parser bug on one damaged file must not take down the
+                // background thread, which would meet the file again on
+                // every launch
+                std::panic::catch_unwind(|| attachment_text(&mime, &data))
+                    .unwrap_or_else(|_| Err("Text extraction failed".to_string()))
+            })
+    };
+
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM attachment_fts WHERE attachment_id = ?1", [&id])
+        .map_err(|e| e.to_string())?;
+    let error = match pages {
+        Ok(pages) => {
+            for (i, text) in pages.iter().enumerate() {
+                if text.trim().is_empty() {
+                    continue;
+                }
+                let page = (mime == PDF_MIME).then_some(i as i64 + 1);
+                conn.execute(
+                    "INSERT INTO attachment_fts (attachment_id, page, content) VALUES (?1, ?2, ?3)",
+                    rusqlite::params![id, page, text],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+            None
+        }
+        Err(e) => {
+            log::warn!("Failed to index attachment {}: {}", file_name, e);
+            Some(e)
+        }
+    };
+    conn.execute(
+        "UPDATE attachments SET indexed_at = ?2, index_error = ?3 WHERE id = ?1",
+        rusqlite::params![id, Utc::now().to_rfc3339(), error],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+/// The text of a document, by page for PDFs and whole otherwise.
+fn attachment_text(mime: &str, data: &[u8]) -> Result<Vec<String>, String> {
+    match mime {
+        PDF_MIME => pdf_page_texts(data),
+        DOCX_MIME => docx_text(data).map(|text| vec![text]),
+        ODT_MIME => odt_text(data).map(|text| vec![text]),
+        _ => Ok(vec![String::from_utf8_lossy(data).to_string()]),
+    }
+}
+
+/// Register files in the images store that have no record, such as images
+/// saved before attachments were tracked.
+fn register_stored_files(conn: &Connection, images_dir: &Path) -> Result<u32, String> {
+    let Ok(entries) = fs::read_dir(images_dir) else {
+        return Ok(0);
+    };
+    let mut registered = 0;
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if !path.is_file() || file_name.starts_with('.') {
+            continue;
+        }
+        let known: bool = conn
+            .query_row("SELECT COUNT(*) FROM attachments WHERE file_name = ?1", [&file_name], |row| row.get::<_, i64>(0))
+            .map_err(|e| e.to_string())?
+            > 0;
+        if known {
+            continue;
+        }
+        let data = fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        write_attachment(conn, &path, &data)?;
+        registered += 1;
+    }
+    Ok(registered)
+}
+
+/// Store a file in the images store, reusing the stored copy of identical
+/// content, and return its path.
+pub(crate) fn store_attachment(
+    conn: &Connection,
+    images_dir: &Path,
+    name: &str,
+    data: &[u8],
+) -> Result<PathBuf, String> {
+    let hash = format!("{:x}", Sha256::digest(data));
+    let mut stmt = conn
+        .prepare("SELECT file_name FROM attachments WHERE hash = ?1 ORDER BY created_at")
+        .map_err(|e| e.to_string())?;
+    let stored: Vec<String> = stmt
+        .query_map([&hash], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+    if let Some(path) = stored.iter().map(|name| images_dir.join(name)).find(|path| path.is_file()) {
+        return Ok(path);
+    }
+    let path = images_dir.join(stored_asset_name(name, data));
+    write_attachment(conn, &path, data)?;
+    Ok(path)
+}
+
+/// Write a file into the images store at `path`, unless it is there
+/// already, and register it.
+pub(crate) fn write_attachment(conn: &Connection, path: &Path, data: &[u8]) -> Result<(), String> {
+    if !path.exists() {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Failed to create images dir: {}", e))?;
+        }
+        fs::write(path, data).map_err(|e| format!("Failed to save attachment: {}", e))?;
+    }
+    let file_name = path.file_name().ok_or("Invalid attachment path")?.to_string_lossy().to_string();
+    conn.execute(
+        "INSERT INTO attachments (id, hash, file_name, original_name, mime_type, size, created_at) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7) ON CONFLICT(file_name) DO NOTHING",
+        rusqlite::params![
+            Uuid::new_v4().to_string(),
+            format!("{:x}", Sha256::digest(data)),
+            file_name,
+            original_name(&file_name),
+            mime_type(&file_name, data),
+            data.len() as i64,
+            Utc::now().to_rfc3339(),
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Re-record the attachments of every note.
+pub(crate) fn rebuild_note_attachments(conn: &Connection) -> Result<(), String> {
+    let mut stmt = conn.prepare("SELECT id, content FROM notes").map_err(|e| e.to_string())?;
+    let notes: Vec<(String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+    for (id, content) in notes {
+        sync_note_attachments(conn, &id, &content)?;
+    }
+    Ok(())
+}
+
+/// Record which attachments a note's content refers to.
+pub(crate) fn sync_note_attachments(conn: &Connection, note_id: &str, content: &str) -> Result<(), String> {
+    conn.execute("DELETE FROM note_attachments WHERE note_id = ?1", [note_id])
+        .map_err(|e| e.to_string())?;
+    for file_name in attachment_file_names(content, images_dir_for(conn).as_deref()) {
+        let id: Option<String> = conn
+            .query_row("SELECT id FROM attachments WHERE file_name = ?1", [&file_name], |row| row.get(0))
+            .optional()
+            .map_err(|e| e.to_string())?;
+        if let Some(id) = id {
+            conn.execute(
+                "INSERT OR IGNORE INTO note_attachments (note_id, attachment_id) VALUES (?1, ?2)",
+                rusqlite::params![note_id, id],
+            )
+            .map_err(|e| e.to_string())?;
+            conn.execute("UPDATE attachments SET orphaned_at = NULL WHERE id = ?1", [&id])
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// The name a stored file was saved with, without the hash or uuid prefix
+/// the images store adds.
+fn original_name(file_name: &str) -> String {
+    match file_name.split_once('_') {
+        Some((prefix, rest))
+            if !rest.is_empty()
+                && (prefix.len() == 16 || prefix.len() == 36)
+                && prefix.chars().all(|c| c.is_ascii_hexdigit() || c == '-') =>
+        {
+            rest.to_string()
+        }
+        _ => file_name.to_string(),
+    }
+}
+
+fn mime_type(file_name: &str, data: &[u8]) -> &'static str {
+    let sniffed = image_extension(data).and_then(|ext| image_mime(Path::new(&format!("image.{}", ext))));
+    if let Some(mime) = sniffed.or_else(|| image_mime(Path::new(file_name))) {
+        return mime;
+    }
+    let ext = Path::new(file_name)
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "pdf" => PDF_MIME,
+        "docx" => DOCX_MIME,
+        "odt" => ODT_MIME,
+        "txt" => "text/plain",
+        "md" | "markdown" => "text/markdown",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "json" => "application/json",
+        "zip" => "application/zip",
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "mov" => "video/quicktime",
+        _ => "application/octet-stream",
+    }
+}
@@ -0,0 +1,365 @@
+use crate::commands::attachments::{store_attachment, sync_note_attachments};
+use crate::commands::export::{percent_decode, url_encode};
+use crate::commands::files::images_dir;
+use crate::commands::notes::{
+    compute_word_count, fetch_note, log_activity, sync_note_links, sync_tags, sync_to_icloud,
+};
+use crate::commands::properties::index_note_properties;
+use crate::commands::queries::{refresh_query_results, sync_note_queries, QueryChange};
+use crate::commands::tasks::sync_note_checklist;
+use crate::db::models::{ClipParams, Note};
+use crate::document::html::{html_to_markdown, parse_html, Node};
+use crate::document::image::image_extension;
+use crate::document::readability::extract_article;
+use crate::markdown::frontmatter::set_extra_value;
+use crate::markdown::tags::extract_tags;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::Utc;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, State};
+use uuid::Uuid;
+use yaml_rust2::Yaml;
+
+const PAGE_LIMIT: u64 = 10 * 1024 * 1024;
+const IMAGE_LIMIT: u64 = 20 * 1024 * 1024;
+/// Images beyond this many are left as links to the web.
+const MAX_IMAGES: usize = 100;
+const USER_AGENT: &str = "Mozilla/5.0 (compatible; Bruin web clipper)";
+
+/// A clipped article, converted and with its images stored.
+struct ClippedPage {
+    title: String,
+    author: Option<String>,
+    url: Option<String>,
+    content: String,
+}
+
+/// Clip a web page into a new note. The article is picked out of the page
+/// (dropping navigation, ads and comments) and converted to markdown; its
+/// images are downloaded into the images store. The note records
+/// `source_url`, `author` and `clipped_at` as frontmatter properties.
+#[tauri::command]
+pub fn clip_web_page(
+    app_handle: AppHandle,
+    db: State<'_, Mutex<Connection>>,
+    params: ClipParams,
+) -> Result<Note, String> {
+    let images_dir = images_dir(&app_handle)?;
+    // The database is only locked to store each image once it is fetched
+    let page = clip_page(&db, &params, &images_dir)?;
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    save_clip(&conn, page, params.workspace_id.as_deref())
+}
+
+fn clip_page(db: &Mutex<Connection>, params: &ClipParams, images_dir: &Path) -> Result<ClippedPage, String> {
+    let url = params.url.as_deref().map(str::trim).filter(|u| !u.is_empty());
+    if let Some(url) = url {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(format!("Not a web address: {}", url));
+        }
+    }
+    // Where the page came from, for resolving its images
+    let (html, page_url) = match (&params.html, &params.path, url) {
+        (Some(html), _, url) => (html.clone(), url.map(String::from)),
+        (None, Some(path), _) => {
+            let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            (String::from_utf8_lossy(&data).to_string(), Some(file_url(path)))
+        }
+        (None, None, Some(url)) => {
+            let (data, final_url) = fetch(url, PAGE_LIMIT, false)?;
+            (String::from_utf8_lossy(&data).to_string(), Some(final_url))
+        }
+        (None, None, None) => return Err("Nothing to clip: give a URL, HTML or a saved page".to_string()),
+    };
+
+    let mut article = extract_article(&parse_html(&html));
+    let web_url = url
+        .map(String::from)
+        .or_else(|| page_url.clone().filter(|u| u.starts_with("http")))
+        .or(article.url.clone());
+    let resolve_base = |base: Option<String>| match (article.base_url.as_deref(), base) {
+        (Some(href), Some(base)) => Some(resolve_url(&base, href)),
+        (Some(href), None) => Some(href.to_string()),
+        (None, base) => base,
+    };
+    let mut clipper = ImageStore {
+        db,
+        images_dir,
+        // A saved page's images sit next to it, but its links point at the web
+        base: resolve_base(page_url.clone()),
+        allow_files: params.path.is_some(),
+        stored: HashMap::new(),
+    };
+    let link_base = resolve_base(web_url.clone().or(page_url));
+    clipper.rewrite(&mut article.nodes, link_base.as_deref());
+
+    Ok(ClippedPage {
+        title: article.title,
+        author: article.author,
+        url: web_url,
+        content: html_to_markdown(&article.nodes, &HashMap::new()),
+    })
+}
+
+/// Downloads a page's images into the images store.
+struct ImageStore<'a> {
+    db: &'a Mutex<Connection>,
+    images_dir: &'a Path,
+    base: Option<String>,
+    /// Whether images may be read from disk, as for a saved page.
+    allow_files: bool,
+    /// Image URL to the stored copy's asset URL, or `None` if it failed.
+    stored: HashMap<String, Option<String>>,
+}
+
+impl ImageStore<'_> {
+    /// Store images and point them at their copies, and make links absolute.
+    fn rewrite(&mut self, nodes: &mut [Node], link_base: Option<&str>) {
+        for node in nodes.iter_mut() {
+            match node.name() {
+                "img" => {
+                    if let Some(src) = image_source(node) {
+                        let src = match self.base {
+                            Some(ref base) => resolve_url(base, &src),
+                            None => src,
+                        };
+                        let stored = self.store(&src);
+                        node.set_attr("src", stored.unwrap_or(src));
+                    }
+                }
+                "a" => {
+                    let href = node.attr("href").unwrap_or("").trim().to_string();
+                    let href = if href.to_ascii_lowercase().starts_with("javascript:") {
+                        String::new()
+                    } else {
+                        match link_base {
+                            Some(base) if !href.is_empty() && !href.starts_with('#') => resolve_url(base, &href),
+                            _ => href,
+                        }
+                    };
+                    node.set_attr("href", href);
+                }
+                _ => {}
+            }
+            if let Node::Element { children, .. } = node {
+                self.rewrite(children, link_base);
+            }
+        }
+    }
+
+    fn store(&mut self, src: &str) -> Option<String> {
+        if let Some(stored) = self.stored.get(src) {
+            return stored.clone();
+        }
+        if self.stored.len() >= MAX_IMAGES {
+            return None;
+        }
+        let stored = match self.download(src) {
+            Ok(url) => Some(url),
+            Err(e) => {
+                log::warn!("Image not clipped: {}", e);
+                None
+            }
+        };
+        self.stored.insert(src.to_string(), stored.clone());
+        stored
+    }
+
+    fn download(&self, src: &str) -> Result<String, String> {
+        let (data, name) = match src.strip_prefix("data:") {
+            Some(data_url) => {
+                let (_, encoded) = data_url.split_once(";base64,").ok_or("Unsupported data URL")?;
+                let data = BASE64.decode(encoded.trim()).map_err(|e| e.to_string())?;
+                (data, "image".to_string())
+            }
+            None => {
+                let (data, _) = fetch(src, IMAGE_LIMIT, self.allow_files)?;
+                let path = src.split(['?', '#']).next().unwrap_or(src);
+                let name = percent_decode(path.rsplit('/').next().unwrap_or(""));
+                (data, if name.is_empty() { "image".to_string() } else { name })
+            }
+        };
+        let ext = image_extension(&data).ok_or_else(|| format!("{} is not an image", src))?;
+        let has_ext = Path::new(&name).extension().is_some_and(|e| {
+            let e = e.to_string_lossy().to_ascii_lowercase();
+            e == ext || (ext == "jpg" && e == "jpeg")
+        });
+        let name = if has_ext { name } else { format!("{}.{}", name, ext) };
+
+        let conn = self.db.lock().map_err(|e| e.to_string())?;
+        let path = store_attachment(&conn, self.images_dir, &name, &data)?;
+        Ok(format!("asset://localhost/{}", url_encode(&path.to_string_lossy())))
+    }
+}
+
+/// Where an image really is: lazy-loading pages keep it in a data attribute
+/// and put a placeholder in `src`.
+fn image_source(node: &Node) -> Option<String> {
+    let src = node.attr("src").map(str::trim).filter(|s| !s.is_empty());
+    let placeholder = src.is_none_or(|s| s.starts_with("data:") && s.len() < 200);
+    if !placeholder {
+        return src.map(String::from);
+    }
+    ["data-src", "data-lazy-src", "data-original"]
+        .iter()
+        .find_map(|key| node.attr(key).map(str::trim).filter(|s| !s.is_empty()))
+        .map(String::from)
+        .or_else(|| largest_in_srcset(node.attr("srcset").or_else(|| node.attr("data-srcset"))?))
+        .or_else(|| src.map(String::from))
+}
+
+/// The widest candidate of a `srcset`, e.g. `a.jpg 480w, b.jpg 960w`.
+fn largest_in_srcset(srcset: &str) -> Option<String> {
+    srcset
+        .split(',')
+        .filter_map(|candidate| {
+            let mut parts = candidate.split_whitespace();
+            let url = parts.next()?;
+            let width = parts.next().and_then(|d| d.trim_end_matches(['w', 'x']).parse::<f64>().ok()).unwrap_or(1.0);
+            Some((width, url.to_string()))
+        })
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, url)| url)
+}
+
+/// Fetch a URL (or read a `file://` URL, where allowed), returning the data
+/// and the URL it ended up at after redirects.
+fn fetch(url: &str, limit: u64, allow_files: bool) -> Result<(Vec<u8>, String), String> {
+    if let Some(path) = url.strip_prefix("file://") {
+        if !allow_files {
+            return Err(format!("Not fetching local file {}", url));
+        }
+        let data = fs::read(percent_decode(path)).map_err(|e| format!("Failed to read {}: {}", url, e))?;
+        return Ok((data, url.to_string()));
+    }
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(format!("Unsupported URL: {}", url));
+    }
+    let response = ureq::get(url)
+        .timeout(Duration::from_secs(30))
+        .set("User-Agent", USER_AGENT)
+        .call()
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+    let final_url = response.get_url().to_string();
+    let mut data = Vec::new();
+    response
+        .into_reader()
+        .take(limit)
+        .read_to_end(&mut data)
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+    Ok((data, final_url))
+}
+
+/// A `file://` URL for a path, so links in a saved page resolve next to it.
+fn file_url(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let encoded: Vec<String> = path.split('/').map(url_encode).collect();
+    let encoded = encoded.join("/");
+    if encoded.starts_with('/') {
+        format!("file://{}", encoded)
+    } else {
+        format!("file:///{}", encoded)
+    }
+}
+
+/// Resolve `href` against the absolute URL `base`, as a browser does.
+fn resolve_url(base: &str, href: &str) -> String {
+    let href = href.trim();
+    let has_scheme = href
+        .split_once(':')
+        .is_some_and(|(scheme, _)| scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c)));
+    let Some(scheme_end) = base.find("://") else {
+        return href.to_string();
+    };
+    if has_scheme {
+        return href.to_string();
+    }
+    let after_scheme = &base[scheme_end + 3..];
+    let host_end = after_scheme.find(['/', '?', '#']).unwrap_or(after_scheme.len());
+    let origin = &base[..scheme_end + 3 + host_end];
+    let base_path = after_scheme[host_end..].split(['?', '#']).next().unwrap_or("");
+
+    if let Some(rest) = href.strip_prefix("//") {
+        return format!("{}://{}", &base[..scheme_end], rest);
+    }
+    if href.starts_with('#') || href.is_empty() {
+        return format!("{}{}", base.split('#').next().unwrap_or(base), href);
+    }
+    if href.starts_with('?') {
+        return format!("{}{}{}", origin, base_path, href);
+    }
+    let (path, suffix) = match href.find(['?', '#']) {
+        Some(i) => href.split_at(i),
+        None => (href, ""),
+    };
+    let joined = if path.starts_with('/') {
+        path.to_string()
+    } else {
+        let dir = &base_path[..base_path.rfind('/').map(|i| i + 1).unwrap_or(0)];
+        format!("{}{}", if dir.is_empty() { "/" } else { dir }, path)
+    };
+    let mut segments: Vec<&str> = Vec::new();
+    let parts: Vec<&str> = joined.split('/').skip(1).collect();
+    for (i, part) in parts.iter().enumerate() {
+        let last = i + 1 == parts.len();
+        match *part {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            part => segments.push(part),
+        }
+        // `a/..` and `a/.` keep the trailing slash
+        if last && (*part == "." || *part == "..") {
+            segments.push("");
+        }
+    }
+    format!("{}/{}{}", origin, segments.join("/"), suffix)
+}
+
+fn save_clip(conn: &Connection, page: ClippedPage, workspace_id: Option<&str>) -> Result<Note, String> {
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+    let title = match page.title.trim() {
+        "" => page
+            .url
+            .as_deref()
+            .and_then(|u| u.split("://").nth(1))
+            .and_then(|rest| rest.split('/').next())
+            .unwrap_or("Clipped page")
+            .to_string(),
+        title => title.to_string(),
+    };
+    let mut extra = None;
+    for (key, value) in [("source_url", page.url), ("author", page.author), ("clipped_at", Some(now.clone()))] {
+        if let Some(value) = value {
+            extra = set_extra_value(extra.as_deref(), key, Some(Yaml::String(value)));
+        }
+    }
+
+    conn.execute(
+        "INSERT INTO notes (id, title, content, created_at, updated_at, word_count, workspace_id, extra_frontmatter) \
+         VALUES (?1, ?2, ?3, ?4, ?4, ?5, ?6, ?7)",
+        rusqlite::params![id, title, page.content, now, compute_word_count(&page.content), workspace_id, extra],
+    )
+    .map_err(|e| e.to_string())?;
+    sync_tags(conn, &id, &extract_tags(&page.content))?;
+    sync_note_links(conn, &id, &page.content)?;
+    sync_note_queries(conn, &id, &page.content)?;
+    sync_note_checklist(conn, &id, &page.content)?;
+    sync_note_attachments(conn, &id, &page.content)?;
+    index_note_properties(conn, &id)?;
+    let _ = refresh_query_results(conn, QueryChange::Note(&id));
+    let note = fetch_note(conn, &id)?;
+    sync_to_icloud(conn, &note);
+    log_activity(conn, "user", "note_clipped", Some(&id), &format!("Clipped '{}'", note.title), "{}");
+    Ok(note)
+}
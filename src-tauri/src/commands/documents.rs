@@ -0,0 +1,268 @@
+use crate::commands::embeds::{expand_embeds, EmbedFormat};
+use crate::commands::export::{
+    image_mime, local_image_path, markdown_escape, markdown_to_html, HtmlTarget, ImageExport,
+};
+use crate::commands::files::images_dir_for;
+use crate::commands::notes::fetch_note;
+use crate::commands::outline::{find_note_id_by_title, find_note_section};
+use crate::commands::queries::evaluate_query;
+use crate::db::models::{DocumentExportParams, DocumentExportResult, Note, QueryResult};
+use crate::document::epub::{write_epub, EpubChapter, EpubImage};
+use crate::document::pdf::{render_pdf, PdfChapter, NOTE_LINK_SCHEME};
+use crate::markdown::embeds::find_wiki_links;
+use crate::markdown::query::QUERY_LANG;
+use crate::markdown::sections::{find_section_by_anchor, slugify, split_link_target};
+use pulldown_cmark::{CodeBlockKind, Event, Parser, Tag};
+use rusqlite::Connection;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::sync::Mutex;
+use tauri::State;
+use uuid::Uuid;
+
+/// Export a note, a tag or a workspace as a PDF with a table of contents.
+/// Each note starts a new page; wiki links between exported notes jump to
+/// the linked note or heading.
+#[tauri::command]
+pub fn export_pdf(
+    db: State<'_, Mutex<Connection>>,
+    params: DocumentExportParams,
+) -> Result<DocumentExportResult, String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    let (title, notes) = select_notes(&conn, &params)?;
+    let ids: HashSet<&str> = notes.iter().map(|n| n.id.as_str()).collect();
+
+    let chapters: Vec<PdfChapter> = notes
+        .iter()
+        .map(|note| PdfChapter {
+            id: note.id.clone(),
+            title: note.title.clone(),
+            markdown: pdf_markdown(&conn, note, &ids),
+        })
+        .collect();
+    let images_dir = images_dir_for(&conn);
+    let output = render_pdf(&title, &chapters, &|url| {
+        images_dir.as_deref().and_then(|dir| local_image_path(url, dir))
+    })?;
+
+    fs::write(&params.output_path, &output.bytes).map_err(|e| format!("Failed to write PDF: {}", e))?;
+    let mut warnings = Vec::new();
+    if !output.missing_characters.is_empty() {
+        let characters: String = output.missing_characters.iter().collect();
+        warnings.push(format!(
+            "No installed font has the characters \"{}\"; they were replaced with \"?\" in the PDF",
+            characters
+        ));
+    }
+    Ok(DocumentExportResult {
+        output_path: params.output_path,
+        notes: notes.len(),
+        pages: Some(output.pages),
+        warnings,
+    })
+}
+
+/// Export a note, a tag or a workspace as an EPUB book with one chapter per
+/// note. Wiki links between exported notes link to the chapter.
+#[tauri::command]
+pub fn export_epub(
+    db: State<'_, Mutex<Connection>>,
+    params: DocumentExportParams,
+) -> Result<DocumentExportResult, String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    let (title, notes) = select_notes(&conn, &params)?;
+
+    let files: HashMap<String, String> = notes
+        .iter()
+        .enumerate()
+        .map(|(i, note)| (note.id.clone(), format!("chapter-{:03}.xhtml", i + 1)))
+        .collect();
+    let images = ImageExport::Gather {
+        prefix: "images".to_string(),
+        found: RefCell::new(Vec::new()),
+    };
+    let target = HtmlTarget::Book {
+        images: &images,
+        chapters: &files,
+    };
+
+    let chapters: Vec<EpubChapter> = notes
+        .iter()
+        .map(|note| {
+            let content = expand_embeds(&conn, &note.id, &note.content, EmbedFormat::Html);
+            EpubChapter {
+                title: note.title.clone(),
+                file: files[&note.id].clone(),
+                html: markdown_to_html(&conn, &content, &target),
+            }
+        })
+        .collect();
+
+    let mut book_images = Vec::new();
+    if let ImageExport::Gather { found, .. } = &images {
+        for (i, path) in found.borrow().iter().enumerate() {
+            let ext = path
+                .extension()
+                .map(|e| e.to_string_lossy().to_ascii_lowercase())
+                .unwrap_or_default();
+            let (Some(mime), Ok(data)) = (image_mime(path), fs::read(path)) else {
+                log::warn!("Failed to read image {}", path.display());
+                continue;
+            };
+            book_images.push(EpubImage {
+                path: format!("images/image-{}.{}", i, ext),
+                mime,
+                data,
+            });
+        }
+    }
+
+    let modified = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    let bytes = write_epub(&Uuid::new_v4().to_string(), &title, &modified, &chapters, &book_images)?;
+    fs::write(&params.output_path, bytes).map_err(|e| format!("Failed to write EPUB: {}", e))?;
+    Ok(DocumentExportResult {
+        output_path: params.output_path,
+        notes: notes.len(),
+        pages: None,
+        warnings: Vec::new(),
+    })
+}
+
+/// The document title and its notes, ordered by title.
+fn select_notes(conn: &Connection, params: &DocumentExportParams) -> Result<(String, Vec<Note>), String> {
+    let (sql, key, default_title) = match (&params.note_id, &params.tag, &params.workspace_id) {
+        (Some(id), None, None) => {
+            let note = fetch_note(conn, id)?;
+            return Ok((params.title.clone().unwrap_or(note.title.clone()), vec![note]));
+        }
+        (None, Some(tag), None) => (
+            "SELECT DISTINCT n.id FROM notes n JOIN note_tags nt ON nt.note_id = n.id \
+             JOIN tags t ON t.id = nt.tag_id \
+             WHERE n.is_trashed = 0 AND (t.name = ?1 OR substr(t.name, 1, length(?1) + 1) = ?1 || '/') \
+             ORDER BY n.title COLLATE NOCASE",
+            tag.clone(),
+            format!("#{}", tag),
+        ),
+        (None, None, Some(workspace_id)) => (
+            "SELECT id FROM notes WHERE is_trashed = 0 AND workspace_id = ?1 ORDER BY title COLLATE NOCASE",
+            workspace_id.clone(),
+            conn.query_row("SELECT name FROM workspaces WHERE id = ?1", [workspace_id], |row| row.get(0))
+                .map_err(|e| e.to_string())?,
+        ),
+        _ => return Err("Specify exactly one of note_id, tag or workspace_id".to_string()),
+    };
+
+    let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
+    let ids: Vec<String> = stmt
+        .query_map([&key], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+    if ids.is_empty() {
+        return Err("No notes to export".to_string());
+    }
+    let notes = ids.iter().map(|id| fetch_note(conn, id)).collect::<Result<Vec<_>, _>>()?;
+    Ok((params.title.clone().unwrap_or(default_title), notes))
+}
+
+/// Note markdown prepared for the PDF layout: embeds expanded, query blocks
+/// replaced with their results, and wiki links turned into links to notes in
+/// the document (or plain text for notes outside it).
+fn pdf_markdown(conn: &Connection, note: &Note, ids: &HashSet<&str>) -> String {
+    let content = expand_embeds(conn, &note.id, &note.content, EmbedFormat::Markdown);
+
+    let mut replacements: Vec<(usize, usize, String)> = Vec::new();
+    for (event, range) in Parser::new(&content).into_offset_iter() {
+        if let Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang))) = event {
+            if lang.trim() == QUERY_LANG {
+                let source = content[range.clone()]
+                    .lines()
+                    .skip(1)
+                    .take_while(|l| !l.trim_start().starts_with("```") && !l.trim_start().starts_with("~~~"))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let result = evaluate_query(conn, &source);
+                replacements.push((range.start, range.end, query_markdown(&result)));
+            }
+        }
+    }
+
+    for link in find_wiki_links(&content) {
+        let (title, anchor) = split_link_target(&link.target);
+        let text = match link.target.split_once('|') {
+            Some((_, alias)) if !alias.trim().is_empty() => alias.trim(),
+            _ => link.target.as_str(),
+        };
+        let text = markdown_escape(text);
+        let target_id = if title.is_empty() {
+            Some(note.id.clone())
+        } else {
+            find_note_id_by_title(conn, title)
+        };
+        let replacement = match target_id.filter(|id| ids.contains(id.as_str())) {
+            Some(id) => {
+                let anchor = anchor.map(|anchor| {
+                    if id == note.id {
+                        find_section_by_anchor(&content, anchor).map(|(_, a)| a)
+                    } else {
+                        fetch_note(conn, &id)
+                            .ok()
+                            .and_then(|n| find_note_section(&n, Some(anchor), None).ok())
+                            .map(|s| s.anchor)
+                    }
+                    .unwrap_or_else(|| slugify(anchor))
+                });
+                match anchor {
+                    Some(anchor) => format!("[{}](<{}{}#{}>)", text, NOTE_LINK_SCHEME, id, anchor),
+                    None => format!("[{}](<{}{}>)", text, NOTE_LINK_SCHEME, id),
+                }
+            }
+            None => text,
+        };
+        replacements.push((link.start, link.end, replacement));
+    }
+
+    replacements.sort_by_key(|r| r.0);
+    let mut out = String::with_capacity(content.len());
+    let mut last = 0;
+    for (start, end, text) in replacements {
+        if start < last {
+            continue;
+        }
+        out.push_str(&content[last..start]);
+        out.push_str(&text);
+        last = end;
+    }
+    out.push_str(&content[last..]);
+    out
+}
+
+/// Query results as a markdown list of titles, with any column values.
+fn query_markdown(result: &QueryResult) -> String {
+    if let Some(ref error) = result.error {
+        return format!("*Query error: {}*\n", markdown_escape(error));
+    }
+    if result.rows.is_empty() {
+        return "*No results*\n".to_string();
+    }
+    let mut out = String::new();
+    for row in &result.rows {
+        out.push_str("- ");
+        out.push_str(&markdown_escape(&row.title));
+        let values: Vec<String> = row
+            .values
+            .iter()
+            .filter(|v| !v.is_null())
+            .map(|v| match v {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .collect();
+        if !values.is_empty() {
+            out.push_str(&format!(" ({})", markdown_escape(&values.join(", "))));
+        }
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,164 @@
+use crate::commands::export::{html_escape, markdown_to_html, HtmlTarget};
+use crate::commands::notes::fetch_note;
+use crate::commands::outline::{find_note_id_by_title, find_note_section};
+use crate::db::models::RenderedEmbed;
+use crate::markdown::embeds::find_embeds;
+use crate::markdown::sections::split_link_target;
+use rusqlite::Connection;
+use std::sync::Mutex;
+use tauri::State;
+
+/// Embeds nested deeper than this are treated like a cycle.
+const MAX_EMBED_DEPTH: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum EmbedFormat {
+    /// Plain markdown; unresolvable embeds are left as written.
+    Markdown,
+    /// Markdown with each embed wrapped in a `div.bruin-embed`, for HTML
+    /// rendering; unresolvable embeds become an error message.
+    Html,
+}
+
+struct ResolvedEmbedSource {
+    /// Note id, plus `#anchor` for a section; used for cycle detection.
+    key: String,
+    note_id: String,
+    markdown: String,
+}
+
+/// Resolve `![[Note]]`, `![[Note#Section]]` or `![[#Section]]` (a section of
+/// `from_note_id`) to the markdown it stands for.
+fn resolve_embed(conn: &Connection, target: &str, from_note_id: &str) -> Result<ResolvedEmbedSource, String> {
+    let (title, anchor) = split_link_target(target);
+    let note_id = if title.is_empty() {
+        from_note_id.to_string()
+    } else {
+        find_note_id_by_title(conn, title).ok_or_else(|| format!("Note '{}' not found", title))?
+    };
+    let note = fetch_note(conn, &note_id)?;
+
+    match anchor {
+        Some(anchor) => {
+            let section = find_note_section(&note, Some(anchor), None)?;
+            Ok(ResolvedEmbedSource {
+                key: format!("{}#{}", note.id, section.anchor),
+                note_id: note.id,
+                markdown: section.markdown,
+            })
+        }
+        None => Ok(ResolvedEmbedSource {
+            key: note.id.clone(),
+            note_id: note.id,
+            markdown: note.content,
+        }),
+    }
+}
+
+/// Replace every embed in `content` (the content of `note_id`) with what it
+/// embeds, recursively. An embed that would include itself again is
+/// reported instead of expanded.
+pub(crate) fn expand_embeds(conn: &Connection, note_id: &str, content: &str, format: EmbedFormat) -> String {
+    let mut stack = vec![note_id.to_string()];
+    expand(conn, note_id, content, format, &mut stack)
+}
+
+fn expand(conn: &Connection, note_id: &str, content: &str, format: EmbedFormat, stack: &mut Vec<String>) -> String {
+    let embeds = find_embeds(content);
+    if embeds.is_empty() {
+        return content.to_string();
+    }
+
+    let mut out = String::with_capacity(content.len());
+    let mut last = 0;
+    for embed in embeds {
+        out.push_str(&content[last..embed.start]);
+        last = embed.end;
+        let written = &content[embed.start..embed.end];
+
+        match resolve_embed(conn, &embed.target, note_id) {
+            Ok(source) if stack.contains(&source.key) || stack.len() > MAX_EMBED_DEPTH => {
+                out.push_str(&embed_error(written, &format!("Circular embed of '{}'", embed.target), format));
+            }
+            Ok(source) => {
+                stack.push(source.key.clone());
+                let inner = expand(conn, &source.note_id, &source.markdown, format, stack);
+                stack.pop();
+                let inner = inner.trim_end_matches('\n');
+                match format {
+                    // Block content embedded mid-line goes on lines of its own
+                    EmbedFormat::Markdown if inner.contains('\n') => {
+                        if !out.is_empty() && !out.ends_with('\n') {
+                            out.push_str("\n\n");
+                        }
+                        out.push_str(inner);
+                        if !content[last..].is_empty() && !content[last..].starts_with('\n') {
+                            out.push_str("\n\n");
+                        }
+                    }
+                    EmbedFormat::Markdown => out.push_str(inner),
+                    EmbedFormat::Html => out.push_str(&format!(
+                        "\n\n<div class=\"bruin-embed\" data-note-id=\"{}\">\n\n{}\n\n</div>\n\n",
+                        html_escape(&source.note_id),
+                        inner
+                    )),
+                }
+            }
+            Err(e) => out.push_str(&embed_error(written, &e, format)),
+        }
+    }
+    out.push_str(&content[last..]);
+    out
+}
+
+fn embed_error(written: &str, message: &str, format: EmbedFormat) -> String {
+    match format {
+        EmbedFormat::Markdown => written.to_string(),
+        EmbedFormat::Html => format!(
+            "<span class=\"bruin-embed-error\">{}</span>",
+            html_escape(message)
+        ),
+    }
+}
+
+/// Render each top-level embed of a note for display in the editor, in the
+/// order they appear.
+#[tauri::command]
+pub fn render_note_embeds(
+    db: State<'_, Mutex<Connection>>,
+    id: String,
+) -> Result<Vec<RenderedEmbed>, String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    let note = fetch_note(&conn, &id)?;
+
+    Ok(find_embeds(&note.content)
+        .into_iter()
+        .map(|embed| {
+            let mut stack = vec![note.id.clone()];
+            match resolve_embed(&conn, &embed.target, &note.id) {
+                Ok(source) if stack.contains(&source.key) => RenderedEmbed {
+                    target: embed.target.clone(),
+                    note_id: Some(source.note_id),
+                    html: String::new(),
+                    error: Some(format!("Circular embed of '{}'", embed.target)),
+                },
+                Ok(source) => {
+                    stack.push(source.key.clone());
+                    let markdown = expand(&conn, &source.note_id, &source.markdown, EmbedFormat::Html, &mut stack);
+                    RenderedEmbed {
+                        target: embed.target,
+                        note_id: Some(source.note_id),
+                        html: markdown_to_html(&conn, &markdown, &HtmlTarget::App),
+                        error: None,
+                    }
+                }
+                Err(e) => RenderedEmbed {
+                    target: embed.target,
+                    note_id: None,
+                    html: String::new(),
+                    error: Some(e),
+                },
+            }
+        })
+        .collect())
+}
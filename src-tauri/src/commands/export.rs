@@ -1,5 +1,23 @@
-use crate::commands::notes::fetch_note;
+use crate::commands::embeds::{expand_embeds, EmbedFormat};
+use crate::commands::notes::{build_knowledge_graph, fetch_note};
+use crate::commands::outline::{find_note_id_by_title, find_note_section};
+use crate::commands::queries::{evaluate_query, render_query_html};
+use crate::commands::files::images_dir_for;
+use crate::db::models::{ExportGraphParams, GraphEdge, HtmlExportOptions, HtmlExportTheme};
+use crate::markdown::embeds::find_wiki_links;
+use crate::markdown::frontmatter::export_frontmatter;
+use crate::markdown::query::QUERY_LANG;
+use crate::markdown::sanitize::{is_safe_url, sanitize_html};
+use crate::markdown::sections::{find_section_by_anchor, slugify, split_link_target};
+use crate::markdown::tags::find_tag_spans;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, Options, Parser, Tag, TagEnd};
 use rusqlite::Connection;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use tauri::State;
 
@@ -8,28 +26,22 @@ pub fn export_note_markdown(
     db: State<'_, Mutex<Connection>>,
     id: String,
     strip_frontmatter: Option<bool>,
+    flatten: Option<bool>,
 ) -> Result<String, String> {
     let conn = db.lock().map_err(|e| e.to_string())?;
     let note = fetch_note(&conn, &id)?;
 
-    if strip_frontmatter.unwrap_or(false) {
-        Ok(note.content)
+    // Flattening inlines `![[Note]]` embeds so the file stands on its own
+    let content = if flatten.unwrap_or(false) {
+        expand_embeds(&conn, &note.id, &note.content, EmbedFormat::Markdown)
     } else {
-        let tags_yaml = if note.tags.is_empty() {
-            "[]".to_string()
-        } else {
-            format!("[{}]", note.tags.iter().map(|t| format!("\"{}\"", t)).collect::<Vec<_>>().join(", "))
-        };
+        note.content.clone()
+    };
 
-        Ok(format!(
-            "---\ntitle: \"{}\"\ncreated_at: \"{}\"\nupdated_at: \"{}\"\ntags: {}\nstate: \"{}\"\n---\n\n{}",
-            note.title.replace('"', "\\\""),
-            note.created_at,
-            note.updated_at,
-            tags_yaml,
-            note.state,
-            note.content,
-        ))
+    if strip_frontmatter.unwrap_or(false) {
+        Ok(content)
+    } else {
+        Ok(format!("{}\n{}", export_frontmatter(&note), content))
     }
 }
 
@@ -37,54 +49,877 @@ pub fn export_note_markdown(
 pub fn export_note_html(
     db: State<'_, Mutex<Connection>>,
     id: String,
+    options: Option<HtmlExportOptions>,
 ) -> Result<String, String> {
     let conn = db.lock().map_err(|e| e.to_string())?;
     let note = fetch_note(&conn, &id)?;
+    let options = options.unwrap_or_default();
+
+    let images = match options.images.as_deref() {
+        None | Some("inline") => ImageExport::Inline,
+        Some("copy") => {
+            let output = options
+                .output_path
+                .as_deref()
+                .ok_or_else(|| "Copying images needs output_path".to_string())?;
+            ImageExport::beside(Path::new(output))
+        }
+        Some(other) => return Err(format!("Unsupported image mode: '{}'", other)),
+    };
 
-    let parser = pulldown_cmark::Parser::new(&note.content);
-    let mut html_body = String::new();
-    pulldown_cmark::html::push_html(&mut html_body, parser);
+    let content = expand_embeds(&conn, &note.id, &note.content, EmbedFormat::Html);
+    let html_body = markdown_to_html(&conn, &content, &HtmlTarget::File(images));
+    let meta = format!("Last updated: {}", html_escape(&note.updated_at));
+
+    Ok(html_document(&note.title, "", &meta, &html_body, options.theme.as_ref()))
+}
 
-    let html = format!(
+/// Wrap a rendered note in a standalone page styled with `theme`'s colors.
+/// `header` goes above the title, e.g. site navigation.
+pub(crate) fn html_document(
+    title: &str,
+    header: &str,
+    meta: &str,
+    body: &str,
+    theme: Option<&HtmlExportTheme>,
+) -> String {
+    format!(
         r#"<!DOCTYPE html>
-<html lang="en">
+<html lang="en"{theme_attr}>
 <head>
 <meta charset="UTF-8">
 <meta name="viewport" content="width=device-width, initial-scale=1.0">
 <title>{title}</title>
 <style>
-  body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif; max-width: 720px; margin: 2rem auto; padding: 0 1rem; line-height: 1.6; color: #1a1a1a; }}
+{theme_css}
+  body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif; max-width: 720px; margin: 2rem auto; padding: 0 1rem; line-height: 1.6; color: var(--bear-text); background: var(--bear-editor); }}
   h1 {{ font-size: 1.8rem; margin-bottom: 0.5rem; }}
   h2 {{ font-size: 1.4rem; }}
   h3 {{ font-size: 1.2rem; }}
-  pre {{ background: #f5f5f5; padding: 1rem; border-radius: 6px; overflow-x: auto; }}
-  code {{ background: #f0f0f0; padding: 0.15rem 0.4rem; border-radius: 3px; font-size: 0.9em; }}
+  a {{ color: var(--bear-link); }}
+  pre {{ background: var(--bear-inline-code); padding: 1rem; border-radius: 6px; overflow-x: auto; }}
+  code {{ background: var(--bear-inline-code); padding: 0.15rem 0.4rem; border-radius: 3px; font-size: 0.9em; }}
   pre code {{ background: none; padding: 0; }}
-  blockquote {{ border-left: 3px solid #ddd; margin-left: 0; padding-left: 1rem; color: #666; }}
+  blockquote {{ border-left: 3px solid var(--bear-border); margin-left: 0; padding-left: 1rem; color: var(--bear-text-secondary); }}
   img {{ max-width: 100%; }}
   table {{ border-collapse: collapse; width: 100%; }}
-  th, td {{ border: 1px solid #ddd; padding: 0.5rem; text-align: left; }}
-  th {{ background: #f5f5f5; }}
-  .meta {{ color: #888; font-size: 0.85rem; margin-bottom: 1.5rem; }}
+  th, td {{ border: 1px solid var(--bear-border); padding: 0.5rem; text-align: left; }}
+  th {{ background: var(--bear-sidebar); }}
+  li:has(> input[type="checkbox"]) {{ list-style: none; margin-left: -1.4rem; }}
+  .footnote-definition {{ font-size: 0.9em; color: var(--bear-text-secondary); }}
+  .meta {{ color: var(--bear-text-muted); font-size: 0.85rem; margin-bottom: 1.5rem; }}
+  .tag {{ color: var(--bear-tag); background: var(--bear-tag-bg); padding: 0.1rem 0.45rem; border-radius: 999px; font-size: 0.85em; }}
+  .wiki-link {{ color: var(--bear-link); text-decoration: underline dotted; }}
+  .wiki-link.missing {{ color: var(--bear-text-muted); }}
+  .bruin-query-error {{ color: #b00020; font-size: 0.9em; }}
+  .bruin-query-empty {{ color: var(--bear-text-muted); font-style: italic; }}
+  .bruin-embed {{ border-left: 3px solid var(--bear-border); padding-left: 1rem; margin: 1rem 0; }}
+  .bruin-embed-error {{ color: #b00020; font-size: 0.9em; }}
+  .site-nav {{ font-size: 0.9rem; margin-bottom: 1.5rem; }}
+  .site-nav a {{ text-decoration: none; }}
+  .backlinks {{ margin-top: 3rem; padding-top: 1rem; border-top: 1px solid var(--bear-border); font-size: 0.9em; }}
+  .note-list {{ list-style: none; padding: 0; }}
+  .note-list li {{ margin: 0.4rem 0; }}
+  .note-list .date {{ color: var(--bear-text-muted); font-size: 0.85em; margin-left: 0.5rem; }}
+  #search {{ width: 100%; padding: 0.5rem; margin-bottom: 1rem; font-size: 1rem; border: 1px solid var(--bear-border); border-radius: 6px; background: var(--bear-editor); color: var(--bear-text); }}
 </style>
 </head>
 <body>
-<h1>{title}</h1>
-<p class="meta">Last updated: {updated_at}</p>
+{header}<h1>{title}</h1>
+<p class="meta">{meta}</p>
 {body}
 </body>
 </html>"#,
-        title = html_escape(&note.title),
-        updated_at = &note.updated_at,
-        body = html_body,
-    );
+        theme_attr = theme
+            .map(|t| format!(" data-theme=\"{}\"", html_escape(&t.id)))
+            .unwrap_or_default(),
+        theme_css = theme_css(theme),
+        title = html_escape(title),
+        header = header,
+        meta = meta,
+        body = body,
+    )
+}
+
+/// Light colors used when no theme is given, matching the old fixed style.
+const DEFAULT_COLORS: &[(&str, &str)] = &[
+    ("--bear-editor", "#ffffff"),
+    ("--bear-sidebar", "#f5f5f5"),
+    ("--bear-border", "#dddddd"),
+    ("--bear-text", "#1a1a1a"),
+    ("--bear-text-secondary", "#666666"),
+    ("--bear-text-muted", "#888888"),
+    ("--bear-tag", "#c0392b"),
+    ("--bear-tag-bg", "#fde8e8"),
+    ("--bear-link", "#2980b9"),
+    ("--bear-inline-code", "#f0f0f0"),
+];
+
+/// `:root` variables for the page. Theme values that could break out of the
+/// style block are ignored.
+fn theme_css(theme: Option<&HtmlExportTheme>) -> String {
+    let mut colors: BTreeMap<String, String> = DEFAULT_COLORS
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    if let Some(theme) = theme {
+        for (name, value) in &theme.colors {
+            let valid_name = name.starts_with("--bear-")
+                && name[2..].chars().all(|c| c.is_ascii_lowercase() || c == '-');
+            let valid_value = value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "#(),.% -".contains(c));
+            if valid_name && valid_value {
+                colors.insert(name.clone(), value.clone());
+            }
+        }
+    }
+    let vars: Vec<String> = colors.iter().map(|(k, v)| format!("{}: {};", k, v)).collect();
+    format!("  :root {{ {} }}", vars.join(" "))
+}
+
+// --- Knowledge Graph Export ---
+
+/// A graph node with the note attributes needed by external graph tools.
+struct ExportNode {
+    id: String,
+    title: String,
+    tags: Vec<String>,
+    state: String,
+    workspace_id: Option<String>,
+    workspace_name: Option<String>,
+    word_count: i64,
+    link_count: i64,
+}
+
+/// Export the link graph as GraphML, DOT or JSON-LD, with note attributes
+/// for external graph tools. The graph is walked from `center_note_id` up
+/// to `depth` links away (or covers every linked note) and holds at most
+/// `max_nodes` notes. The `tag`, `workspace_id` and `state` filters apply
+/// during the walk, so a note reachable only through a filtered-out note is
+/// left out even when it matches them.
+#[tauri::command]
+pub fn export_knowledge_graph(
+    db: State<'_, Mutex<Connection>>,
+    params: ExportGraphParams,
+) -> Result<String, String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    // Filtered out notes are left out of the traversal, so they don't use up
+    // `max_nodes`
+    let allowed = match (&params.tag, &params.workspace_id, &params.state) {
+        (None, None, None) => None,
+        (tag, workspace_id, state) => {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT n.id FROM notes n \
+                     WHERE (?1 IS NULL OR EXISTS (SELECT 1 FROM note_tags nt JOIN tags t ON t.id = nt.tag_id \
+                        WHERE nt.note_id = n.id AND (t.name = ?1 OR substr(t.name, 1, length(?1) + 1) = ?1 || '/'))) \
+                     AND (?2 IS NULL OR n.workspace_id = ?2) \
+                     AND (?3 IS NULL OR n.state = ?3)",
+                )
+                .map_err(|e| e.to_string())?;
+            let ids = stmt
+                .query_map(rusqlite::params![tag, workspace_id, state], |row| row.get::<_, String>(0))
+                .map_err(|e| e.to_string())?
+                .collect::<Result<HashSet<String>, _>>()
+                .map_err(|e| e.to_string())?;
+            Some(ids)
+        }
+    };
+    let graph = build_knowledge_graph(
+        &conn,
+        params.center_note_id.as_deref(),
+        params.depth.unwrap_or(2),
+        params.max_nodes.unwrap_or(200) as usize,
+        allowed.as_ref(),
+    )?;
+
+    let mut nodes: Vec<ExportNode> = Vec::new();
+    for node in graph.nodes {
+        let row = conn.query_row(
+            "SELECT n.state, n.workspace_id, w.name, n.word_count \
+             FROM notes n LEFT JOIN workspaces w ON w.id = n.workspace_id \
+             WHERE n.id = ?1",
+            [&node.id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            },
+        );
+        // Links can outlive their notes briefly (e.g. mid-sync); skip those nodes.
+        let (state, workspace_id, workspace_name, word_count) = match row {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+
+        nodes.push(ExportNode {
+            id: node.id,
+            title: node.title,
+            tags: node.tags,
+            state,
+            workspace_id,
+            workspace_name,
+            word_count,
+            link_count: node.link_count,
+        });
+    }
+    nodes.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.id.cmp(&b.id)));
+
+    // Keep only edges whose endpoints survived the filters
+    let kept: HashSet<&str> = nodes.iter().map(|n| n.id.as_str()).collect();
+    let edges: Vec<GraphEdge> = graph
+        .edges
+        .into_iter()
+        .filter(|e| kept.contains(e.source.as_str()) && kept.contains(e.target.as_str()))
+        .collect();
+
+    match params.format.as_str() {
+        "graphml" => Ok(graph_to_graphml(&nodes, &edges)),
+        "dot" => Ok(graph_to_dot(&nodes, &edges)),
+        "jsonld" | "json-ld" => graph_to_jsonld(&nodes, &edges),
+        other => Err(format!("Unsupported graph format: '{}'", other)),
+    }
+}
+
+fn graph_to_graphml(nodes: &[ExportNode], edges: &[GraphEdge]) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    for (id, kind) in [
+        ("label", "string"),
+        ("tags", "string"),
+        ("state", "string"),
+        ("workspace_id", "string"),
+        ("workspace", "string"),
+        ("word_count", "long"),
+        ("link_count", "long"),
+    ] {
+        out.push_str(&format!(
+            "  <key id=\"{id}\" for=\"node\" attr.name=\"{id}\" attr.type=\"{kind}\"/>\n"
+        ));
+    }
+    out.push_str("  <key id=\"link_type\" for=\"edge\" attr.name=\"link_type\" attr.type=\"string\"/>\n");
+    out.push_str("  <graph id=\"bruin\" edgedefault=\"directed\">\n");
 
-    Ok(html)
+    for node in nodes {
+        out.push_str(&format!("    <node id=\"{}\">\n", html_escape(&node.id)));
+        out.push_str(&format!("      <data key=\"label\">{}</data>\n", html_escape(&node.title)));
+        out.push_str(&format!("      <data key=\"tags\">{}</data>\n", html_escape(&node.tags.join(";"))));
+        out.push_str(&format!("      <data key=\"state\">{}</data>\n", html_escape(&node.state)));
+        if let Some(ref ws) = node.workspace_id {
+            out.push_str(&format!("      <data key=\"workspace_id\">{}</data>\n", html_escape(ws)));
+        }
+        if let Some(ref ws) = node.workspace_name {
+            out.push_str(&format!("      <data key=\"workspace\">{}</data>\n", html_escape(ws)));
+        }
+        out.push_str(&format!("      <data key=\"word_count\">{}</data>\n", node.word_count));
+        out.push_str(&format!("      <data key=\"link_count\">{}</data>\n", node.link_count));
+        out.push_str("    </node>\n");
+    }
+
+    for (i, edge) in edges.iter().enumerate() {
+        out.push_str(&format!(
+            "    <edge id=\"e{}\" source=\"{}\" target=\"{}\">\n      <data key=\"link_type\">{}</data>\n    </edge>\n",
+            i,
+            html_escape(&edge.source),
+            html_escape(&edge.target),
+            html_escape(&edge.link_type),
+        ));
+    }
+
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
+
+fn graph_to_dot(nodes: &[ExportNode], edges: &[GraphEdge]) -> String {
+    let mut out = String::from("digraph bruin {\n");
+    for node in nodes {
+        let mut attrs = vec![
+            format!("label={}", dot_quote(&node.title)),
+            format!("tags={}", dot_quote(&node.tags.join(";"))),
+            format!("state={}", dot_quote(&node.state)),
+        ];
+        if let Some(ref ws) = node.workspace_id {
+            attrs.push(format!("workspace_id={}", dot_quote(ws)));
+        }
+        if let Some(ref ws) = node.workspace_name {
+            attrs.push(format!("workspace={}", dot_quote(ws)));
+        }
+        attrs.push(format!("word_count={}", node.word_count));
+        attrs.push(format!("link_count={}", node.link_count));
+        out.push_str(&format!("  {} [{}];\n", dot_quote(&node.id), attrs.join(", ")));
+    }
+    for edge in edges {
+        out.push_str(&format!(
+            "  {} -> {} [link_type={}];\n",
+            dot_quote(&edge.source),
+            dot_quote(&edge.target),
+            dot_quote(&edge.link_type),
+        ));
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn graph_to_jsonld(nodes: &[ExportNode], edges: &[GraphEdge]) -> Result<String, String> {
+    let mut items: Vec<serde_json::Value> = Vec::new();
+    for node in nodes {
+        let mut item = serde_json::json!({
+            "@id": format!("urn:bruin:note:{}", node.id),
+            "@type": "CreativeWork",
+            "name": node.title,
+            "keywords": node.tags,
+            "creativeWorkStatus": node.state,
+            "wordCount": node.word_count,
+            "bruin:linkCount": node.link_count,
+        });
+        if let Some(ref ws) = node.workspace_id {
+            item["isPartOf"] = serde_json::json!({
+                "@id": format!("urn:bruin:workspace:{}", ws),
+                "name": node.workspace_name,
+            });
+        }
+        items.push(item);
+    }
+    for edge in edges {
+        items.push(serde_json::json!({
+            "@type": "bruin:Link",
+            "bruin:source": { "@id": format!("urn:bruin:note:{}", edge.source) },
+            "bruin:target": { "@id": format!("urn:bruin:note:{}", edge.target) },
+            "bruin:linkType": edge.link_type,
+        }));
+    }
+
+    let doc = serde_json::json!({
+        "@context": {
+            "@vocab": "https://schema.org/",
+            "bruin": "https://sawzhang.github.io/bruin/ns#",
+        },
+        "@graph": items,
+    });
+    serde_json::to_string_pretty(&doc).map_err(|e| e.to_string())
+}
+
+/// Quote a string as a DOT identifier.
+fn dot_quote(s: &str) -> String {
+    format!(
+        "\"{}\"",
+        s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+    )
+}
+
+/// Where rendered HTML will be shown, which decides how wiki links, tags and
+/// local images are written.
+pub(crate) enum HtmlTarget<'a> {
+    /// Inside the app: wiki links carry `data-wiki-title` for the editor to
+    /// handle, and images keep their app URLs.
+    App,
+    /// A standalone file: wiki links point at sibling `<title>.html` files.
+    File(ImageExport),
+    /// A page of a published site: like `File`, but only notes in `pages`
+    /// (note id to file name) are linked, and tags in `tags` (tag to file
+    /// name) link to their tag pages.
+    Site {
+        images: &'a ImageExport,
+        pages: &'a HashMap<String, String>,
+        tags: &'a HashMap<String, String>,
+    },
+    /// A chapter of an e-book: wiki links to notes in `chapters` (note id to
+    /// file name) become cross-references, and tags are plain text.
+    Book {
+        images: &'a ImageExport,
+        chapters: &'a HashMap<String, String>,
+    },
+}
+
+impl HtmlTarget<'_> {
+    fn images(&self) -> Option<&ImageExport> {
+        match self {
+            HtmlTarget::App => None,
+            HtmlTarget::File(images) => Some(images),
+            HtmlTarget::Site { images, .. } | HtmlTarget::Book { images, .. } => Some(images),
+        }
+    }
+}
+
+/// How images stored on this machine are written into an exported file.
+pub(crate) enum ImageExport {
+    /// As `data:` URIs, so the file stands alone.
+    Inline,
+    /// Copied into `dir` and linked relatively as `prefix/<file name>`.
+    Copy { dir: PathBuf, prefix: String },
+    /// Linked as `prefix/image-<n>.<ext>` and collected in `found`, for the
+    /// caller to package (the n-th path is `image-<n>`).
+    Gather { prefix: String, found: RefCell<Vec<PathBuf>> },
+}
+
+impl ImageExport {
+    /// Copy images into a `<name>_files` folder next to `output`.
+    pub(crate) fn beside(output: &Path) -> Self {
+        let stem = output
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "export".to_string());
+        let folder = format!("{}_files", stem);
+        ImageExport::Copy {
+            dir: output.with_file_name(&folder),
+            prefix: url_encode(&folder),
+        }
+    }
+}
+
+/// Render note markdown to an HTML fragment: GFM extensions, heading ids,
+/// wiki links, tag chips and evaluated query blocks. Raw HTML in the note is
+/// sanitized.
+pub(crate) fn markdown_to_html(conn: &Connection, markdown: &str, target: &HtmlTarget) -> String {
+    let markdown = rewrite_links_and_tags(conn, markdown, target);
+    let images_dir = images_dir_for(conn);
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_GFM;
+
+    let mut events = Vec::new();
+    let mut html_block: Option<String> = None;
+    let mut query: Option<String> = None;
+    for event in Parser::new_ext(&markdown, options) {
+        match event {
+            Event::Start(Tag::HtmlBlock) => html_block = Some(String::new()),
+            Event::Html(ref html) if html_block.is_some() => {
+                if let Some(ref mut block) = html_block {
+                    block.push_str(html);
+                }
+            }
+            Event::End(TagEnd::HtmlBlock) => {
+                let block = html_block.take().unwrap_or_default();
+                events.push(Event::Html(CowStr::from(sanitize_html(&block))));
+            }
+            Event::Html(html) | Event::InlineHtml(html) => {
+                events.push(Event::InlineHtml(CowStr::from(sanitize_html(&html))));
+            }
+            // Query blocks are replaced with their evaluated results
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(ref lang))) if lang.trim() == QUERY_LANG => {
+                query = Some(String::new());
+            }
+            Event::Text(ref text) if query.is_some() => {
+                if let Some(ref mut q) = query {
+                    q.push_str(text);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) if query.is_some() => {
+                let source = query.take().unwrap_or_default();
+                let html = render_query_html(&evaluate_query(conn, &source));
+                events.push(Event::Html(CowStr::from(html)));
+            }
+            Event::Start(Tag::Link { link_type, dest_url, title, id }) => {
+                let dest_url = if is_safe_url(&dest_url, false) { dest_url } else { CowStr::from("") };
+                events.push(Event::Start(Tag::Link { link_type, dest_url, title, id }));
+            }
+            Event::Start(Tag::Image { link_type, dest_url, title, id }) => {
+                let local = images_dir.as_deref().and_then(|dir| local_image_path(&dest_url, dir));
+                let dest_url = match (target.images(), local) {
+                    (Some(images), Some(path)) => export_image(&path, images)
+                        .map(CowStr::from)
+                        .unwrap_or(dest_url),
+                    // The app shows its own images wherever they are
+                    (None, _) if image_url_path(&dest_url).is_some_and(|p| p.is_file()) => dest_url,
+                    _ if is_safe_url(&dest_url, true) => dest_url,
+                    _ => CowStr::from(""),
+                };
+                events.push(Event::Start(Tag::Image { link_type, dest_url, title, id }));
+            }
+            other => events.push(other),
+        }
+    }
+    add_heading_ids(&mut events);
+
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, events.into_iter());
+    html
 }
 
-fn html_escape(s: &str) -> String {
+/// Give every heading an id, using the same anchors as the note outline so
+/// `[[Note#Heading]]` links land on it.
+fn add_heading_ids(events: &mut [Event]) {
+    let mut seen: Vec<String> = Vec::new();
+    for i in 0..events.len() {
+        if !matches!(events[i], Event::Start(Tag::Heading { .. })) {
+            continue;
+        }
+        let mut text = String::new();
+        for event in &events[i + 1..] {
+            match event {
+                Event::Text(t) | Event::Code(t) => text.push_str(t),
+                Event::End(TagEnd::Heading(_)) => break,
+                _ => {}
+            }
+        }
+        let base = slugify(&text);
+        let mut anchor = base.clone();
+        let mut n = 0;
+        while seen.contains(&anchor) {
+            n += 1;
+            anchor = format!("{}-{}", base, n);
+        }
+        seen.push(anchor.clone());
+        if let Event::Start(Tag::Heading { ref mut id, .. }) = events[i] {
+            *id = Some(CowStr::from(anchor));
+        }
+    }
+}
+
+/// Replace `[[wiki links]]` and `#tags` in the markdown with inline HTML.
+fn rewrite_links_and_tags(conn: &Connection, markdown: &str, target: &HtmlTarget) -> String {
+    let mut replacements: Vec<(usize, usize, String)> = Vec::new();
+
+    for link in find_wiki_links(markdown) {
+        let (title, anchor) = split_link_target(&link.target);
+        let text = match link.target.split_once('|') {
+            Some((_, alias)) if !alias.trim().is_empty() => alias.trim(),
+            _ => link.target.as_str(),
+        };
+        let html = match target {
+            HtmlTarget::App => format!(
+                "<a class=\"wiki-link\" data-wiki-title=\"{}\">{}</a>",
+                html_escape(link.target.split('|').next().unwrap_or("").trim()),
+                markdown_escape(text)
+            ),
+            HtmlTarget::File(_) | HtmlTarget::Site { .. } | HtmlTarget::Book { .. } => match link_href(conn, markdown, title, anchor, target) {
+                Some(href) => format!(
+                    "<a class=\"wiki-link\" href=\"{}\">{}</a>",
+                    html_escape(&href),
+                    markdown_escape(text)
+                ),
+                None => format!("<span class=\"wiki-link missing\">{}</span>", markdown_escape(text)),
+            },
+        };
+        replacements.push((link.start, link.end, html));
+    }
+
+    for tag in find_tag_spans(markdown) {
+        let text = markdown_escape(&markdown[tag.start..tag.end]);
+        let html = match target {
+            HtmlTarget::Site { tags, .. } if tags.contains_key(&tag.name) => format!(
+                "<a class=\"tag\" href=\"tags/{}\">{}</a>",
+                url_encode(&tags[&tag.name]),
+                text
+            ),
+            _ => format!("<span class=\"tag\">{}</span>", text),
+        };
+        replacements.push((tag.start, tag.end, html));
+    }
+
+    replacements.sort_by_key(|r| r.0);
+    let mut out = String::with_capacity(markdown.len());
+    let mut last = 0;
+    for (start, end, html) in replacements {
+        if start < last {
+            continue;
+        }
+        out.push_str(&markdown[last..start]);
+        out.push_str(&html);
+        last = end;
+    }
+    out.push_str(&markdown[last..]);
+    out
+}
+
+/// Relative link to the exported file of the note titled `title`, or `None`
+/// if there is no such note (or it is not part of the site or book). An empty
+/// title links to a heading in `markdown`.
+fn link_href(
+    conn: &Connection,
+    markdown: &str,
+    title: &str,
+    anchor: Option<&str>,
+    target: &HtmlTarget,
+) -> Option<String> {
+    if title.is_empty() {
+        let anchor = anchor.unwrap_or("");
+        let anchor = find_section_by_anchor(markdown, anchor)
+            .map(|(_, a)| a)
+            .unwrap_or_else(|| slugify(anchor));
+        return Some(format!("#{}", url_encode(&anchor)));
+    }
+    let note = fetch_note(conn, &find_note_id_by_title(conn, title)?).ok()?;
+    let mut href = match target {
+        HtmlTarget::Site { pages, .. } | HtmlTarget::Book { chapters: pages, .. } => {
+            url_encode(pages.get(&note.id)?)
+        }
+        _ => format!("{}.html", url_encode(&export_file_stem(&note.title))),
+    };
+    if let Some(anchor) = anchor {
+        let anchor = find_note_section(&note, Some(anchor), None)
+            .map(|s| s.anchor)
+            .unwrap_or_else(|_| slugify(anchor));
+        href.push('#');
+        href.push_str(&url_encode(&anchor));
+    }
+    Some(href)
+}
+
+/// File name (without extension) a note is exported under.
+pub(crate) fn export_file_stem(title: &str) -> String {
+    let stem = title
+        .replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_")
+        .trim()
+        .trim_start_matches('.')
+        .to_string();
+    if stem.is_empty() {
+        "Untitled".to_string()
+    } else {
+        stem
+    }
+}
+
+/// File name (without extension) of a tag's page on a published site.
+pub(crate) fn tag_file_stem(tag: &str) -> String {
+    slugify(&tag.replace('/', " "))
+}
+
+/// The image behind `url` if it is a file in the app's `images_dir`, the
+/// only local files exports may read. See `image_url_path` for the URLs.
+pub(crate) fn local_image_path(url: &str, images_dir: &Path) -> Option<PathBuf> {
+    let path = image_url_path(url)?.canonicalize().ok()?;
+    let dir = images_dir.canonicalize().ok()?;
+    (path.starts_with(&dir) && path.is_file()).then_some(path)
+}
+
+/// The path named by an image URL written by the editor (`asset://` or
+/// `http://asset.localhost/` from `convertFileSrc`), a `file://` URL or an
+/// absolute path.
+fn image_url_path(url: &str) -> Option<PathBuf> {
+    let path = if let Some(rest) = url
+        .strip_prefix("asset://localhost/")
+        .or_else(|| url.strip_prefix("http://asset.localhost/"))
+        .or_else(|| url.strip_prefix("https://asset.localhost/"))
+    {
+        percent_decode(rest)
+    } else if let Some(rest) = url.strip_prefix("file://") {
+        percent_decode(rest)
+    } else if Path::new(url).is_absolute() {
+        url.to_string()
+    } else {
+        return None;
+    };
+    Some(PathBuf::from(path))
+}
+
+/// The `src` to use for a local image in an exported file.
+fn export_image(path: &Path, images: &ImageExport) -> Option<String> {
+    match images {
+        ImageExport::Inline => {
+            let mime = image_mime(path)?;
+            let data = fs::read(path).ok()?;
+            Some(format!("data:{};base64,{}", mime, BASE64.encode(data)))
+        }
+        ImageExport::Copy { dir, prefix } => {
+            image_mime(path)?;
+            let name = path.file_name()?;
+            let copied = fs::create_dir_all(dir).and_then(|_| fs::copy(path, dir.join(name)));
+            if let Err(e) = copied {
+                log::warn!("Failed to copy image {}: {}", path.display(), e);
+                return None;
+            }
+            Some(format!("{}/{}", prefix, url_encode(&name.to_string_lossy())))
+        }
+        ImageExport::Gather { prefix, found } => {
+            let ext = path.extension()?.to_string_lossy().to_ascii_lowercase();
+            image_mime(path)?;
+            let mut found = found.borrow_mut();
+            let index = match found.iter().position(|p| p == path) {
+                Some(index) => index,
+                None => {
+                    found.push(path.to_path_buf());
+                    found.len() - 1
+                }
+            };
+            Some(format!("{}/image-{}.{}", prefix, index, ext))
+        }
+    }
+}
+
+pub(crate) fn image_mime(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_string_lossy().to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        "ico" => "image/x-icon",
+        "avif" => "image/avif",
+        "heic" => "image/heic",
+        _ => return None,
+    })
+}
+
+/// Escape ASCII punctuation so text is shown literally by the markdown parser.
+pub(crate) fn markdown_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c.is_ascii_punctuation() {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Percent-encode everything but unreserved URL characters.
+pub(crate) fn url_encode(s: &str) -> String {
+    let mut out = String::new();
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || b"-_.~".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+pub(crate) fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        // The digits are read as bytes: slicing the str could cut through
+        // a multibyte character
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let digits = (hex_digit(bytes[i + 1]), hex_digit(bytes[i + 2]));
+            if let (Some(high), Some(low)) = digits {
+                out.push(high << 4 | low);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+fn hex_digit(b: u8) -> Option<u8> {
+    (b as char).to_digit(16).map(|d| d as u8)
+}
+
+pub(crate) fn html_escape(s: &str) -> String {
     s.replace('&', "&amp;")
         .replace('<', "&lt;")
         .replace('>', "&gt;")
         .replace('"', "&quot;")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_graph() -> (Vec<ExportNode>, Vec<GraphEdge>) {
+        let node = |id: &str, title: &str, workspace: Option<&str>| ExportNode {
+            id: id.to_string(),
+            title: title.to_string(),
+            tags: vec!["a&b".to_string(), "c".to_string()],
+            state: "draft".to_string(),
+            workspace_id: workspace.map(|_| "w1".to_string()),
+            workspace_name: workspace.map(String::from),
+            word_count: 12,
+            link_count: 1,
+        };
+        let nodes = vec![node("n1", "Say \"hi\" <b> & co", Some("R&D")), node("n2", "Plain", None)];
+        let edges = vec![GraphEdge {
+            source: "n1".to_string(),
+            target: "n2".to_string(),
+            link_type: "wiki".to_string(),
+        }];
+        (nodes, edges)
+    }
+
+    #[test]
+    fn test_graph_to_graphml() {
+        let (nodes, edges) = sample_graph();
+        let xml = graph_to_graphml(&nodes, &edges);
+        assert!(xml.contains("<data key=\"label\">Say &quot;hi&quot; &lt;b&gt; &amp; co</data>"));
+        assert!(xml.contains("<data key=\"tags\">a&amp;b;c</data>"));
+        assert!(xml.contains("<data key=\"workspace\">R&amp;D</data>"));
+        assert!(xml.contains("<edge id=\"e0\" source=\"n1\" target=\"n2\">"));
+        assert_eq!(xml.matches("<node ").count(), 2);
+        // Missing workspaces are left out rather than written empty
+        assert_eq!(xml.matches("<data key=\"workspace_id\">").count(), 1);
+    }
+
+    #[test]
+    fn test_graph_to_dot() {
+        let (nodes, edges) = sample_graph();
+        let dot = graph_to_dot(&nodes, &edges);
+        assert!(dot.starts_with("digraph bruin {\n"));
+        assert!(dot.contains("\"n1\" [label=\"Say \\\"hi\\\" <b> & co\", tags=\"a&b;c\", state=\"draft\", workspace_id=\"w1\", workspace=\"R&D\", word_count=12, link_count=1];"));
+        assert!(dot.contains("\"n2\" [label=\"Plain\", tags=\"a&b;c\", state=\"draft\", word_count=12, link_count=1];"));
+        assert!(dot.contains("\"n1\" -> \"n2\" [link_type=\"wiki\"];"));
+        assert_eq!(dot_quote("line\nback\\slash"), "\"line\\nback\\\\slash\"");
+    }
+
+    #[test]
+    fn test_graph_to_jsonld() {
+        let (nodes, edges) = sample_graph();
+        let doc: serde_json::Value = serde_json::from_str(&graph_to_jsonld(&nodes, &edges).unwrap()).unwrap();
+        let graph = doc["@graph"].as_array().unwrap();
+        assert_eq!(graph.len(), 3);
+        assert_eq!(graph[0]["@id"], "urn:bruin:note:n1");
+        assert_eq!(graph[0]["name"], "Say \"hi\" <b> & co");
+        assert_eq!(graph[0]["keywords"], serde_json::json!(["a&b", "c"]));
+        assert_eq!(graph[0]["isPartOf"]["name"], "R&D");
+        assert!(graph[1].get("isPartOf").is_none());
+        assert_eq!(graph[2]["bruin:source"]["@id"], "urn:bruin:note:n1");
+        assert_eq!(graph[2]["bruin:target"]["@id"], "urn:bruin:note:n2");
+        assert_eq!(doc["@context"]["@vocab"], "https://schema.org/");
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("my%20photo%C3%A9.png"), "my photoé.png");
+        // A multibyte character right after `%` is kept as it is
+        assert_eq!(percent_decode("x%aé.png"), "x%aé.png");
+        assert_eq!(percent_decode("x%é.png"), "x%é.png");
+        assert_eq!(percent_decode("50%"), "50%");
+    }
+
+    #[test]
+    fn test_local_images_stay_in_images_dir() {
+        let root = std::env::temp_dir().join(format!("bruin-export-{}", uuid::Uuid::new_v4()));
+        let images = root.join("images");
+        fs::create_dir_all(&images).unwrap();
+        fs::write(images.join("photo one.png"), b"png").unwrap();
+        fs::write(images.join("notes.txt"), b"text").unwrap();
+        fs::write(root.join("secret.png"), b"secret").unwrap();
+
+        let inside = images.join("photo one.png");
+        let url = format!("asset://localhost/{}", url_encode(&inside.to_string_lossy()));
+        assert_eq!(local_image_path(&url, &images), inside.canonicalize().ok());
+        let file_url = format!("file://{}", inside.to_string_lossy());
+        assert!(local_image_path(&file_url, &images).is_some());
+
+        let outside = root.join("secret.png");
+        assert_eq!(local_image_path(&outside.to_string_lossy(), &images), None);
+        assert_eq!(local_image_path(&format!("file://{}", outside.to_string_lossy()), &images), None);
+        let escaping = images.join("..").join("secret.png");
+        assert_eq!(local_image_path(&escaping.to_string_lossy(), &images), None);
+        assert_eq!(local_image_path("/etc/passwd", &images), None);
+
+        // Copying only takes images
+        let copy = ImageExport::Copy {
+            dir: root.join("out"),
+            prefix: "images".to_string(),
+        };
+        let text = local_image_path(&images.join("notes.txt").to_string_lossy(), &images).unwrap();
+        assert_eq!(export_image(&text, &copy), None);
+        assert_eq!(export_image(&inside, &copy).as_deref(), Some("images/photo%20one.png"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
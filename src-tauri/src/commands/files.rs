@@ -1,26 +1,60 @@
-use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use rusqlite::Connection;
+use sha2::{Digest, Sha256};
 use tauri::{AppHandle, Manager, State};
-use uuid::Uuid;
+use crate::commands::attachments::store_attachment;
+use crate::commands::images::{process_image, save_thumbnail, ImageSettings};
 
+/// Store a pasted or dropped image, scaled down, converted and stripped of
+/// its metadata as the `image_*` settings say, with a thumbnail. An image
+/// already in the store is not stored again; its path is returned instead.
 #[tauri::command]
 pub fn save_image(
     app_handle: AppHandle,
-    _db: State<'_, Mutex<Connection>>,
+    db: State<'_, Mutex<Connection>>,
     data: Vec<u8>,
     filename: String,
 ) -> Result<String, String> {
-    let images_dir = app_handle
+    let images_dir = images_dir(&app_handle)?;
+    let settings = {
+        let conn = db.lock().map_err(|e| e.to_string())?;
+        ImageSettings::load(&conn)?
+    };
+    // Decoding and encoding a large photo takes a while, so not under the lock
+    let image = process_image(&data, &filename, &settings)?;
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    let path = store_attachment(&conn, &images_dir, &image.name, &image.data)?;
+    if let (Some((thumbnail, extension)), Some(file_name)) = (image.thumbnail, path.file_name()) {
+        save_thumbnail(&conn, &images_dir, &file_name.to_string_lossy(), &thumbnail, extension)?;
+    }
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Where pasted and imported images are stored.
+pub(crate) fn images_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app_handle
         .path()
         .app_data_dir()
         .map_err(|e| format!("Failed to resolve app data dir: {}", e))?
-        .join("images");
-    fs::create_dir_all(&images_dir)
-        .map_err(|e| format!("Failed to create images dir: {}", e))?;
-    let safe_filename = filename.replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_");
-    let stored_name = format!("{}_{}", Uuid::new_v4(), safe_filename);
-    let path = images_dir.join(&stored_name);
-    fs::write(&path, &data).map_err(|e| format!("Failed to save image: {}", e))?;
-    Ok(path.to_string_lossy().to_string())
+        .join("images"))
+}
+
+/// The images store of the database `conn` is open on, which sits next to
+/// the database file. `None` for an in-memory database.
+pub(crate) fn images_dir_for(conn: &Connection) -> Option<PathBuf> {
+    let db_path = Path::new(conn.path().filter(|p| !p.is_empty())?);
+    Some(db_path.parent()?.join("images"))
+}
+
+pub(crate) fn safe_filename(filename: &str) -> String {
+    filename.replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_")
+}
+
+/// Name for an imported or clipped file in the images store. It is derived
+/// from the data, so storing the same file again reuses the copy and yields
+/// the same note content. `name` may be a path; only its last part is kept.
+pub(crate) fn stored_asset_name(name: &str, data: &[u8]) -> String {
+    let hash = format!("{:x}", Sha256::digest(data));
+    format!("{}_{}", &hash[..16], safe_filename(name.rsplit('/').next().unwrap_or(name)))
 }
@@ -0,0 +1,296 @@
+use crate::commands::settings::read_setting;
+use crate::document::image::{decode_pixels, encode_png, image_dimensions, image_extension, Pixels, MAX_PIXELS};
+use crate::document::jpeg::encode_jpeg;
+use crate::document::metadata::{exif_orientation, strip_metadata};
+use crate::document::webp::encode_webp_lossless;
+use rusqlite::{Connection, OptionalExtension};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Folder of the images store thumbnails are kept in.
+const THUMBNAILS_DIR: &str = "thumbnails";
+
+const THUMBNAIL_QUALITY: u8 = 80;
+
+/// What the `image_format` setting can ask for. There is no AVIF encoder
+/// and no lossy WebP one, so those are refused.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ImageFormat {
+    Original,
+    Jpeg,
+    Png,
+    /// Lossless WebP.
+    Webp,
+}
+
+impl ImageFormat {
+    fn parse(setting: Option<&str>) -> Result<ImageFormat, String> {
+        match setting.map(str::trim) {
+            Some("jpeg") | Some("jpg") => Ok(ImageFormat::Jpeg),
+            Some("png") => Ok(ImageFormat::Png),
+            Some("webp") => Ok(ImageFormat::Webp),
+            Some("original") | Some("") | None => Ok(ImageFormat::Original),
+            Some(other) => Err(format!(
+                "Images cannot be saved as {:?}: image_format must be original, jpeg, png or webp (lossless)",
+                other
+            )),
+        }
+    }
+}
+
+/// How saved images are processed, from the `image_*` settings.
+pub(crate) struct ImageSettings {
+    /// Longest side larger images are scaled down to; 0 keeps their size.
+    pub max_dimension: u32,
+    /// Remove EXIF (with any GPS position), XMP and comments.
+    pub strip_metadata: bool,
+    pub format: ImageFormat,
+    /// JPEG quality, 1–100.
+    pub quality: u8,
+    /// Longest side of thumbnails; 0 turns them off.
+    pub thumbnail_size: u32,
+}
+
+impl ImageSettings {
+    /// The settings, or an error if they ask for a format that cannot be
+    /// written.
+    pub(crate) fn load(conn: &Connection) -> Result<ImageSettings, String> {
+        let number = |key: &str, default: u32| {
+            read_setting(conn, key).and_then(|v| v.trim().parse().ok()).unwrap_or(default)
+        };
+        Ok(ImageSettings {
+            max_dimension: number("image_max_dimension", 2560),
+            strip_metadata: read_setting(conn, "image_strip_metadata").is_none_or(|v| v.trim() != "false"),
+            format: ImageFormat::parse(read_setting(conn, "image_format").as_deref())?,
+            quality: number("image_quality", 85).clamp(1, 100) as u8,
+            thumbnail_size: thumbnail_size(conn),
+        })
+    }
+}
+
+fn thumbnail_size(conn: &Connection) -> u32 {
+    read_setting(conn, "image_thumbnail_size").and_then(|v| v.trim().parse().ok()).unwrap_or(320)
+}
+
+/// An image ready to store.
+pub(crate) struct ProcessedImage {
+    pub data: Vec<u8>,
+    /// The name it was saved with, with the extension changed if the format
+    /// was.
+    pub name: String,
+    /// Thumbnail data and its extension.
+    pub thumbnail: Option<(Vec<u8>, &'static str)>,
+}
+
+/// Check that `data` is an image and apply the settings to it: scale it
+/// down, convert it, strip its metadata and make its thumbnail. JPEG and PNG
+/// images are decoded for this, and turned upright; other formats, and JPEG
+/// or PNG variants the decoder does not handle, only lose their metadata.
+pub(crate) fn process_image(data: &[u8], name: &str, settings: &ImageSettings) -> Result<ProcessedImage, String> {
+    let kind = image_extension(data).ok_or("Not a supported image file")?;
+    let unchanged = |data: Vec<u8>| ProcessedImage { data, name: name.to_string(), thumbnail: None };
+    if kind == "svg" || kind == "avif" {
+        return Ok(unchanged(data.to_vec()));
+    }
+    let (width, height) = image_dimensions(data)
+        .filter(|&(w, h)| w > 0 && h > 0)
+        .ok_or_else(|| format!("Invalid {} image", kind.to_uppercase()))?;
+    if width as u64 * height as u64 > MAX_PIXELS {
+        return Err(format!("Image is too large ({}×{} pixels)", width, height));
+    }
+
+    let target = match settings.format {
+        ImageFormat::Original => kind,
+        ImageFormat::Jpeg => "jpg",
+        ImageFormat::Png => "png",
+        ImageFormat::Webp => "webp",
+    };
+    let oversized = settings.max_dimension > 0 && width.max(height) > settings.max_dimension;
+    // A PNG's orientation is in its EXIF, so it is turned upright for real
+    // before that goes
+    let orientation = exif_orientation(data);
+    let reorient = kind == "png" && settings.strip_metadata && orientation != 1;
+    let stripped = || if settings.strip_metadata { strip_metadata(data) } else { data.to_vec() };
+
+    let wanted = oversized || target != kind || reorient || settings.thumbnail_size > 0;
+    let pixels = match (kind == "jpg" || kind == "png") && wanted {
+        true => match decode_pixels(data) {
+            Ok(pixels) => pixels.oriented(orientation),
+            Err(e) => {
+                log::warn!("Storing {} as it is: {}", name, e);
+                return Ok(unchanged(stripped()));
+            }
+        },
+        false => return Ok(unchanged(stripped())),
+    };
+    let thumbnail = match settings.thumbnail_size {
+        0 => None,
+        size => Some(thumbnail_of(&pixels, size)?),
+    };
+    if !oversized && target == kind && !reorient {
+        return Ok(ProcessedImage { data: stripped(), name: name.to_string(), thumbnail });
+    }
+
+    let resized = pixels.fit_within(settings.max_dimension);
+    let pixels = resized.as_ref().unwrap_or(&pixels);
+    // JPEG has no transparency
+    let target = if target == "jpg" && pixels.has_alpha() { "png" } else { target };
+    let data = match target {
+        "jpg" => encode_jpeg(pixels, settings.quality)?,
+        "webp" => encode_webp_lossless(pixels)?,
+        _ => encode_png(pixels)?,
+    };
+    // Converting a photo to a lossless format can make it several times
+    // larger; at its own size the original is kept instead
+    if resized.is_none() && !reorient && data.len() >= stripped().len() {
+        return Ok(ProcessedImage { data: stripped(), name: name.to_string(), thumbnail });
+    }
+    let name = match target == kind {
+        true => name.to_string(),
+        false => Path::new(name).with_extension(target).to_string_lossy().to_string(),
+    };
+    Ok(ProcessedImage { data, name, thumbnail })
+}
+
+/// A small copy of an image for previews: JPEG, or PNG if it is
+/// transparent.
+fn thumbnail_of(pixels: &Pixels, size: u32) -> Result<(Vec<u8>, &'static str), String> {
+    let resized = pixels.fit_within(size);
+    let thumbnail = resized.as_ref().unwrap_or(pixels);
+    if thumbnail.has_alpha() {
+        Ok((encode_png(thumbnail)?, "png"))
+    } else {
+        Ok((encode_jpeg(thumbnail, THUMBNAIL_QUALITY)?, "jpg"))
+    }
+}
+
+/// Where a thumbnail is stored.
+pub(crate) fn thumbnail_path(images_dir: &Path, thumbnail: &str) -> PathBuf {
+    images_dir.join(THUMBNAILS_DIR).join(thumbnail)
+}
+
+/// Write the thumbnail of the stored file `file_name` and record it.
+pub(crate) fn save_thumbnail(
+    conn: &Connection,
+    images_dir: &Path,
+    file_name: &str,
+    data: &[u8],
+    extension: &str,
+) -> Result<(), String> {
+    let thumbnail = format!("{}.{}", file_name, extension);
+    let path = thumbnail_path(images_dir, &thumbnail);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create thumbnails dir: {}", e))?;
+    }
+    fs::write(&path, data).map_err(|e| format!("Failed to save thumbnail: {}", e))?;
+    conn.execute(
+        "UPDATE attachments SET thumbnail = ?1 WHERE file_name = ?2",
+        rusqlite::params![thumbnail, file_name],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Make the thumbnail of the next image attachment that has none, such as
+/// imported or synced images, returning false when there is none. The
+/// database is only locked around the queries. An image that cannot be
+/// decoded is recorded with an empty thumbnail, so it is not tried again.
+pub(crate) fn thumbnail_next_attachment(db: &Mutex<Connection>, images_dir: &Path) -> Result<bool, String> {
+    let (next, size) = {
+        let conn = db.lock().map_err(|e| e.to_string())?;
+        let size = thumbnail_size(&conn);
+        if size == 0 {
+            return Ok(false);
+        }
+        let next: Option<String> = conn
+            .query_row(
+                "SELECT file_name FROM attachments WHERE thumbnail IS NULL \
+                 AND mime_type IN ('image/jpeg', 'image/png') ORDER BY created_at LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        (next, size)
+    };
+    let Some(file_name) = next else {
+        return Ok(false);
+    };
+
+    let thumbnail = fs::read(images_dir.join(&file_name))
+        .map_err(|e| format!("Failed to read {}: {}", file_name, e))
+        .and_then(|data| {
+            let (width, height) = image_dimensions(&data).ok_or("Invalid image")?;
+            if width as u64 * height as u64 > MAX_PIXELS {
+                return Err("Image is too large".to_string());
+            }
+            std::panic::catch_unwind(|| {
+                let pixels = decode_pixels(&data)?.oriented(exif_orientation(&data));
+                thumbnail_of(&pixels, size)
+            })
+            .unwrap_or_else(|_| Err("Decoding failed".to_string()))
+        });
+
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    match thumbnail {
+        Ok((data, extension)) => save_thumbnail(&conn, images_dir, &file_name, &data, extension)?,
+        Err(e) => {
+            log::warn!("No thumbnail for {}: {}", file_name, e);
+            conn.execute("UPDATE attachments SET thumbnail = '' WHERE file_name = ?1", [&file_name])
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::image::decode_pixels;
+
+    #[test]
+    fn test_process_image() {
+        let settings = ImageSettings {
+            max_dimension: 40,
+            strip_metadata: true,
+            format: ImageFormat::Original,
+            quality: 85,
+            thumbnail_size: 16,
+        };
+        let rgba = (0..100 * 60).flat_map(|i| [(i % 100) as u8 * 2, (i / 100) as u8 * 4, 90, 255]).collect();
+        let png = encode_png(&Pixels { width: 100, height: 60, rgba }).unwrap();
+
+        let image = process_image(&png, "chart.png", &settings).unwrap();
+        assert_eq!(image.name, "chart.png");
+        assert_eq!(image_dimensions(&image.data), Some((40, 24)));
+        let (thumbnail, extension) = image.thumbnail.unwrap();
+        assert_eq!(extension, "jpg");
+        let thumbnail = decode_pixels(&thumbnail).unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (16, 10));
+
+        let webp = ImageSettings { format: ImageFormat::Webp, ..settings };
+        let image = process_image(&png, "chart.png", &webp).unwrap();
+        assert_eq!(image.name, "chart.webp");
+        assert_eq!(image_dimensions(&image.data), Some((40, 24)));
+
+        assert!(process_image(b"<html>not an image</html>", "x.png", &webp).is_err());
+
+        // A noisy photo would grow as PNG, so it stays a JPEG
+        let mut seed = 1u32;
+        let rgba = (0..100 * 60 * 4)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (seed >> 24) as u8 | 3
+            })
+            .collect();
+        let jpeg = encode_jpeg(&Pixels { width: 100, height: 60, rgba }, 50).unwrap();
+        let png = ImageSettings { format: ImageFormat::Png, max_dimension: 0, ..webp };
+        let image = process_image(&jpeg, "photo.jpg", &png).unwrap();
+        assert_eq!(image.name, "photo.jpg");
+        assert!(image.data.starts_with(&[0xFF, 0xD8]));
+
+        assert_eq!(ImageFormat::parse(Some(" webp ")), Ok(ImageFormat::Webp));
+        assert!(ImageFormat::parse(Some("avif")).is_err());
+    }
+}
@@ -0,0 +1,1039 @@
+use crate::commands::attachments::{sync_note_attachments, write_attachment};
+use crate::commands::export::{image_mime, percent_decode, url_encode};
+use crate::commands::files::{images_dir, stored_asset_name};
+use crate::commands::notes::{
+    compute_word_count, fetch_note, log_activity, rebuild_note_links, sync_note_links, sync_tags,
+    sync_to_icloud,
+};
+use crate::commands::properties::index_note_properties;
+use crate::commands::queries::{refresh_query_results, sync_note_queries, QueryChange};
+use crate::commands::tasks::sync_note_checklist;
+use crate::commands::outline::find_note_id_by_title;
+use crate::db::models::{ImportFilePlan, ImportOptions, ImportReport};
+use crate::document::enex::read_enex;
+use crate::document::external::{ExternalNote, ExternalTodo};
+use crate::document::jex::read_jex;
+use crate::document::notion::read_notion;
+use crate::document::textbundle::{
+    is_bundle_archive, is_bundle_dir, read_bundle_archive, read_bundle_dir, TextBundle,
+};
+use crate::markdown::embeds::{find_embeds, find_wiki_links};
+use crate::markdown::frontmatter::{is_reserved_key, parse_frontmatter, set_extra_value, FrontmatterData};
+use crate::markdown::tags::{extract_tags, format_tag};
+use crate::sync::icloud::compute_sync_hash;
+use chrono::{DateTime, Utc};
+use pulldown_cmark::{Event, Parser, Tag};
+use regex::Regex;
+use rusqlite::{Connection, OptionalExtension};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tauri::{AppHandle, State};
+use uuid::Uuid;
+use yaml_rust2::Yaml;
+
+const MARKDOWN_EXTENSIONS: &[&str] = &["md", "markdown"];
+
+/// A note found under one of the selected paths.
+struct SourceFile {
+    /// The file, bundle folder or archive holding the note.
+    path: PathBuf,
+    /// The note's name inside an archive or export.
+    entry: Option<String>,
+    /// Folders between the selected folder and the file.
+    folders: Vec<String>,
+    source: Source,
+}
+
+enum Source {
+    Markdown,
+    Bundle(TextBundle),
+    /// A note from an Evernote, Joplin or Notion export.
+    External(ExternalNote),
+    /// An Evernote export, read a note at a time while importing so its
+    /// attachments are not all held in memory.
+    Enex,
+    /// Why a bundle or export could not be read.
+    Unreadable(String),
+}
+
+struct ImportContext<'a> {
+    conn: &'a Connection,
+    options: &'a ImportOptions,
+    folders: &'a str,
+    images_dir: &'a Path,
+    /// Titles of the bundles being imported, by lowercased Bear id.
+    bundle_titles: HashMap<String, String>,
+    /// Lowercased names of the markdown files being imported.
+    note_names: HashSet<String>,
+    /// Other files in the selected folders, by lowercased file name.
+    attachments: HashMap<String, Vec<PathBuf>>,
+}
+
+/// A file read and matched against existing notes, ready to write.
+struct PlannedNote {
+    plan: ImportFilePlan,
+    title: String,
+    content: String,
+    created_at: String,
+    updated_at: String,
+    is_pinned: bool,
+    state: String,
+    extra_frontmatter: Option<String>,
+    workspace_id: Option<String>,
+    /// Attachments to copy into the images store.
+    assets: Vec<(PathBuf, Vec<u8>)>,
+    todo: Option<ExternalTodo>,
+}
+
+/// Import markdown files and folders (including Obsidian vaults),
+/// TextBundles, TextPacks, Bear backups (`.bear2bk`), Evernote exports
+/// (`.enex`), Joplin exports (`.jex`) and Notion exports (`.zip`).
+/// Folders are read recursively, and their structure becomes tags or
+/// workspaces; Evernote and Joplin notebooks become workspaces. Notes keep
+/// the file's timestamps (or those in its frontmatter or the export). A file
+/// whose frontmatter `id` (or Bear or Joplin id) matches a note updates that
+/// note when the file is newer; a file with
+/// the same title and content as an existing note is skipped, so importing a
+/// folder twice does not duplicate it. With `dry_run`, only the per-file
+/// plan is returned.
+#[tauri::command]
+pub fn import_markdown_files(
+    app_handle: AppHandle,
+    db: State<'_, Mutex<Connection>>,
+    paths: Vec<String>,
+    options: Option<ImportOptions>,
+) -> Result<ImportReport, String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    let images_dir = images_dir(&app_handle)?;
+    import_paths(&conn, &images_dir, &paths, &options.unwrap_or_default())
+}
+
+pub(crate) fn import_paths(
+    conn: &Connection,
+    images_dir: &Path,
+    paths: &[String],
+    options: &ImportOptions,
+) -> Result<ImportReport, String> {
+    let dry_run = options.dry_run.unwrap_or(false);
+    let folders = options.folders.as_deref().unwrap_or("tags");
+    if !["tags", "workspaces", "none"].contains(&folders) {
+        return Err(format!("Unsupported folder mapping: '{}'", folders));
+    }
+
+    let recursive = options.recursive.unwrap_or(true);
+    let mut files = Vec::new();
+    let mut attachments = HashMap::new();
+    for path in paths {
+        collect_files(Path::new(path), Vec::new(), recursive, &mut files);
+        collect_attachments(Path::new(path), recursive, &mut attachments);
+    }
+
+    let bundle_titles = files
+        .iter()
+        .filter_map(|f| match f.source {
+            Source::Bundle(ref bundle) => Some(bundle),
+            _ => None,
+        })
+        .filter_map(|b| Some((b.info.id.as_ref()?.to_lowercase(), bundle_title(b))))
+        .collect();
+    let note_names = files
+        .iter()
+        .filter(|f| matches!(f.source, Source::Markdown))
+        .filter_map(|f| Some(f.path.file_stem()?.to_string_lossy().to_lowercase()))
+        .collect();
+    let ctx = ImportContext {
+        conn,
+        options,
+        folders,
+        images_dir,
+        bundle_titles,
+        note_names,
+        attachments,
+    };
+    let mut report = ImportReport {
+        dry_run,
+        imported: 0,
+        updated: 0,
+        skipped: 0,
+        tasks: 0,
+        files: Vec::new(),
+    };
+    let mut written: Vec<String> = Vec::new();
+    let mut workspaces: HashMap<String, String> = HashMap::new();
+    // Content hash to the path of the first file in this import with it
+    let mut seen: HashMap<String, String> = HashMap::new();
+
+    // Each note is written as soon as it is planned, so only one note's
+    // attachments are in memory at a time
+    let mut import_file = |file: &SourceFile| {
+        let mut note = plan_file(&ctx, file, &mut seen);
+        let action = note.plan.action.clone();
+        if !dry_run && action != "skip" {
+            if let Err(e) = write_note(conn, &mut note, &mut workspaces) {
+                log::warn!("Skipped {}: {}", note.plan.path, e);
+                note.plan.action = "skip".to_string();
+                note.plan.reason = e;
+            } else if let Some(ref id) = note.plan.note_id {
+                written.push(id.clone());
+            }
+        }
+        match note.plan.action.as_str() {
+            "import" => report.imported += 1,
+            "update" => report.updated += 1,
+            _ => report.skipped += 1,
+        }
+        if note.todo.is_some() && note.plan.action != "skip" {
+            report.tasks += 1;
+        }
+        report.files.push(note.plan);
+    };
+    for file in &files {
+        if !matches!(file.source, Source::Enex) {
+            import_file(file);
+            continue;
+        }
+        // ENEX doesn't name the notebook; exports are one file per notebook
+        let notebook = file.path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let read = fs::File::open(&file.path)
+            .map_err(|e| format!("Failed to read file: {}", e))
+            .and_then(|reader| {
+                read_enex(BufReader::new(reader), &notebook, &mut |note| {
+                    import_file(&external_file(&file.path, &file.folders, note))
+                })
+            });
+        if let Err(e) = read {
+            import_file(&SourceFile {
+                path: file.path.clone(),
+                entry: None,
+                folders: file.folders.clone(),
+                source: Source::Unreadable(e),
+            });
+        }
+    }
+
+    if !written.is_empty() {
+        // Links are synced once every note exists, so links between
+        // imported notes resolve
+        for id in &written {
+            let note = fetch_note(conn, id)?;
+            sync_note_links(conn, id, &note.content)?;
+            sync_note_queries(conn, id, &note.content)?;
+            sync_note_checklist(conn, id, &note.content)?;
+            sync_note_attachments(conn, id, &note.content)?;
+            index_note_properties(conn, id)?;
+            sync_to_icloud(conn, &note);
+        }
+        // Existing notes may link to titles that exist only now
+        rebuild_note_links(conn)?;
+        let _ = refresh_query_results(conn, QueryChange::All);
+        log_activity(
+            conn,
+            "user",
+            "notes_imported",
+            None,
+            &format!("Imported {} notes, updated {}", report.imported, report.updated),
+            &serde_json::json!({ "paths": paths, "skipped": report.skipped }).to_string(),
+        );
+    }
+
+    Ok(report)
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| extensions.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Notes at `path`, in name order: markdown files, bundles and the notes of
+/// exports. Hidden files and folders (such as `.obsidian` or `.trash`) are
+/// skipped. A bundle or export that cannot be read is listed without its
+/// contents, so the plan reports it.
+fn collect_files(path: &Path, folders: Vec<String>, recursive: bool, out: &mut Vec<SourceFile>) {
+    let source = |entry, source| SourceFile {
+        path: path.to_path_buf(),
+        entry,
+        folders: folders.clone(),
+        source,
+    };
+    let read = || fs::read(path).map_err(|e| format!("Failed to read file: {}", e));
+    if is_bundle_dir(path) {
+        match read_bundle_dir(path) {
+            Ok(bundle) => out.push(source(None, Source::Bundle(bundle))),
+            Err(e) => out.push(source(None, Source::Unreadable(e))),
+        }
+        return;
+    }
+    if !path.is_file() {
+        // A folder, read below
+    } else if is_bundle_archive(path) {
+        match read().and_then(|data| read_bundle_archive(&data)) {
+            Ok(bundles) => {
+                for bundle in bundles {
+                    out.push(source(Some(format!("{}.textbundle", bundle.name)), Source::Bundle(bundle)));
+                }
+            }
+            Err(e) => out.push(source(None, Source::Unreadable(e))),
+        }
+        return;
+    } else if has_extension(path, &["enex"]) {
+        out.push(source(None, Source::Enex));
+        return;
+    } else if has_extension(path, &["jex", "zip"]) {
+        let notes = if has_extension(path, &["jex"]) {
+            read().and_then(|data| read_jex(&data))
+        } else {
+            read().and_then(|data| read_notion(&data))
+        };
+        match notes {
+            Ok(notes) => out.extend(notes.into_iter().map(|note| external_file(path, &folders, note))),
+            Err(e) => out.push(source(None, Source::Unreadable(e))),
+        }
+        return;
+    } else {
+        if has_extension(path, MARKDOWN_EXTENSIONS) {
+            out.push(source(None, Source::Markdown));
+        }
+        return;
+    }
+    let Ok(entries) = fs::read_dir(path) else {
+        return;
+    };
+    let mut entries: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+    entries.sort();
+    for entry in entries {
+        let name = entry.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        if name.starts_with('.') {
+            continue;
+        }
+        if entry.is_dir() && !is_bundle_dir(&entry) {
+            if recursive {
+                let mut sub = folders.clone();
+                sub.push(name);
+                collect_files(&entry, sub, recursive, out);
+            }
+        } else {
+            collect_files(&entry, folders.clone(), recursive, out);
+        }
+    }
+}
+
+/// A note of the export at `path`, listed under its notebook and folders.
+fn external_file(path: &Path, folders: &[String], note: ExternalNote) -> SourceFile {
+    let mut entry = [note.notebook.as_slice(), note.folders.as_slice()].concat();
+    entry.push(note.title.clone());
+    SourceFile {
+        path: path.to_path_buf(),
+        entry: Some(entry.join("/")),
+        folders: [folders, note.folders.as_slice()].concat(),
+        source: Source::External(note),
+    }
+}
+
+/// Index the files other than notes under `path`, which notes can link to
+/// or embed as attachments.
+fn collect_attachments(path: &Path, recursive: bool, out: &mut HashMap<String, Vec<PathBuf>>) {
+    let Ok(entries) = fs::read_dir(path) else {
+        return;
+    };
+    let mut entries: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+    entries.sort();
+    for entry in entries {
+        let name = entry.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        if name.starts_with('.') || is_bundle_dir(&entry) {
+            continue;
+        }
+        if entry.is_dir() {
+            if recursive {
+                collect_attachments(&entry, recursive, out);
+            }
+        } else if !has_extension(&entry, MARKDOWN_EXTENSIONS) {
+            out.entry(name.to_lowercase()).or_default().push(entry);
+        }
+    }
+}
+
+fn plan_file(ctx: &ImportContext, file: &SourceFile, seen: &mut HashMap<String, String>) -> PlannedNote {
+    let conn = ctx.conn;
+    let path = match file.entry {
+        Some(ref entry) => format!("{}/{}", file.path.to_string_lossy(), entry),
+        None => file.path.to_string_lossy().to_string(),
+    };
+    let mut plan = ImportFilePlan {
+        path: path.clone(),
+        action: "skip".to_string(),
+        reason: String::new(),
+        title: String::new(),
+        note_id: None,
+        tags: Vec::new(),
+        workspace: None,
+        attachments: 0,
+    };
+    let parsed = match file.source {
+        Source::Unreadable(ref e) => Err(e.clone()),
+        Source::Enex => Err("Not a note".to_string()),
+        Source::Bundle(ref bundle) if bundle.info.trashed => Err("In the Bear trash".to_string()),
+        Source::Bundle(ref bundle) => Ok(bundle.text.clone()),
+        Source::External(ref note) => match note.skip {
+            Some(ref reason) => Err(reason.clone()),
+            None => Ok(note.content.clone()),
+        },
+        Source::Markdown => fs::read_to_string(&file.path).map_err(|e| format!("Failed to read file: {}", e)),
+    }
+    .and_then(|raw| match file.source {
+        // Exported notes have no frontmatter; a leading `---` is a rule
+        Source::External(_) => Ok((false, FrontmatterData::default(), raw)),
+        _ => parse_frontmatter(&raw).map(|(fm, body)| (raw.starts_with("---"), fm, body)),
+    });
+    let (has_frontmatter, fm, body) = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            plan.reason = e;
+            return PlannedNote {
+                plan,
+                title: String::new(),
+                content: String::new(),
+                created_at: String::new(),
+                updated_at: String::new(),
+                is_pinned: false,
+                state: String::new(),
+                extra_frontmatter: None,
+                workspace_id: None,
+                assets: Vec::new(),
+                todo: None,
+            };
+        }
+    };
+    // Exports put a blank line between the frontmatter and the content
+    let mut content = if has_frontmatter {
+        body.strip_prefix('\n').unwrap_or(&body).to_string()
+    } else {
+        body
+    };
+
+    let mut title = fm.title.clone().filter(|t| !t.trim().is_empty());
+    let mut assets = Vec::new();
+    let mut wanted = fm.tags.clone();
+    let mut extra = fm.extra.clone();
+    let mut tag_folders: &[String] = &file.folders;
+    let mut map_to_workspace = ctx.folders == "workspaces";
+    match file.source {
+        Source::Bundle(ref bundle) => {
+            // Bear keeps the title as the first heading of the text
+            if title.is_none() {
+                if let Some((heading, rest)) = split_title(&content) {
+                    title = Some(heading);
+                    content = rest;
+                }
+            }
+            title = title.or_else(|| Some(bundle.name.clone()));
+            content = bundle_content(ctx, bundle, &content, &mut assets);
+        }
+        Source::External(ref note) => {
+            title = Some(note.title.clone()).filter(|t| !t.trim().is_empty());
+            content = rewrite_links(ctx, &note.assets, &content, &mut assets);
+            wanted.extend(note.tags.iter().cloned());
+            // The notebook becomes the workspace and sub-notebooks a tag,
+            // wherever the export file is
+            if !note.notebook.is_empty() {
+                tag_folders = &note.notebook;
+                map_to_workspace = ctx.folders != "none";
+            }
+            for (name, value) in &note.properties {
+                if !is_reserved_key(name) {
+                    extra = set_extra_value(extra.as_deref(), name, Some(Yaml::String(value.clone())));
+                }
+            }
+        }
+        Source::Markdown => content = markdown_content(ctx, &file.path, &content, &mut assets),
+        Source::Unreadable(_) | Source::Enex => {}
+    }
+    let title = title
+        .or_else(|| file.path.file_stem().map(|s| s.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "Untitled".to_string());
+    plan.attachments = assets.len() as u32;
+
+    // A workspace from the folder is looked up (or created) when written
+    let mut workspace_id = ctx.options.workspace_id.clone();
+    match tag_folders.split_first() {
+        Some((workspace, rest)) if map_to_workspace => {
+            plan.workspace = Some(workspace.clone());
+            workspace_id = None;
+            tag_folders = rest;
+        }
+        _ => {
+            plan.workspace = workspace_id.as_ref().and_then(|id| {
+                conn.query_row("SELECT name FROM workspaces WHERE id = ?1", [id], |row| row.get(0))
+                    .ok()
+            });
+        }
+    }
+    // Tags are read from the content, so frontmatter and folder tags the
+    // content lacks are added on a last line
+    if ctx.folders != "none" && !tag_folders.is_empty() {
+        wanted.push(tag_folders.join("/"));
+    }
+    let present = extract_tags(&content);
+    let mut added: Vec<String> = Vec::new();
+    for tag in wanted {
+        let written = format_tag(tag.trim().trim_start_matches('#'));
+        // Only names that read back as a tag are kept, e.g. not `#2024`
+        let is_new = matches!(extract_tags(&written).as_slice(), [name] if !present.contains(name));
+        if is_new && !added.contains(&written) {
+            added.push(written);
+        }
+    }
+    if !added.is_empty() {
+        content = match content.trim_end() {
+            "" => format!("{}\n", added.join(" ")),
+            body => format!("{}\n\n{}\n", body, added.join(" ")),
+        };
+    }
+    plan.tags = extract_tags(&content);
+    plan.title = title.clone();
+
+    // Dates, pinned status and id recorded by the app the note came from
+    let (created, updated, pinned, id, origin) = match file.source {
+        Source::Bundle(ref b) => {
+            let id = b.info.id.as_ref().map(|id| id.to_lowercase());
+            (b.info.created_at.clone(), b.info.updated_at.clone(), b.info.pinned, id, "Bear")
+        }
+        Source::External(ref n) => (n.created_at.clone(), n.updated_at.clone(), false, n.id.clone(), n.app),
+        _ => (None, None, false, None, ""),
+    };
+    let created_at = fm
+        .created_at
+        .clone()
+        .or(created)
+        .unwrap_or_else(|| file_time(&file.path, true));
+    let updated_at = fm
+        .updated_at
+        .clone()
+        .or(updated)
+        .unwrap_or_else(|| file_time(&file.path, false));
+    let is_pinned = fm.is_pinned || pinned;
+    let source_id = fm.id.clone().or(id);
+    let hash = compute_sync_hash(&title, &content);
+
+    let existing_by_id = source_id.as_deref().and_then(|id| {
+        conn.query_row(
+            "SELECT id, title, content, updated_at FROM notes WHERE id = ?1",
+            [id],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?)),
+        )
+        .optional()
+        .ok()
+        .flatten()
+    });
+    let duplicate = conn
+        .query_row(
+            "SELECT id, title FROM notes WHERE is_trashed = 0 AND (sync_hash = ?1 OR (title = ?2 AND content = ?3)) LIMIT 1",
+            rusqlite::params![hash, title, content],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+        )
+        .optional()
+        .ok()
+        .flatten();
+
+    if let Some((id, existing_title, existing_content, existing_updated)) = existing_by_id {
+        plan.note_id = Some(id);
+        if existing_title == title && existing_content == content {
+            plan.reason = "Matches the existing note".to_string();
+        } else if is_newer(&updated_at, &existing_updated) {
+            plan.action = "update".to_string();
+            plan.reason = format!("Newer than the existing note '{}'", existing_title);
+        } else {
+            plan.reason = format!("The existing note '{}' is newer", existing_title);
+        }
+    } else if let Some((id, existing_title)) = duplicate {
+        plan.note_id = Some(id);
+        plan.reason = format!("Same content as the note '{}'", existing_title);
+    } else if let Some(other) = seen.get(&hash) {
+        plan.reason = format!("Same content as {}", other);
+    } else {
+        seen.insert(hash, path);
+        plan.action = "import".to_string();
+        plan.reason = match (&fm.id, &source_id) {
+            (Some(_), _) => "New note (id kept from frontmatter)".to_string(),
+            (None, Some(_)) => format!("New note (id kept from {})", origin),
+            _ => "New note".to_string(),
+        };
+        plan.note_id = Some(source_id.unwrap_or_else(|| Uuid::new_v4().to_string()));
+    }
+
+    PlannedNote {
+        plan,
+        title,
+        content,
+        created_at,
+        updated_at,
+        is_pinned,
+        state: fm.state.unwrap_or_else(|| "draft".to_string()),
+        extra_frontmatter: extra,
+        workspace_id,
+        assets,
+        todo: match file.source {
+            Source::External(ref note) => note.todo.clone(),
+            _ => None,
+        },
+    }
+}
+
+/// The title of a bundle's note: its first line when that is a `# heading`,
+/// otherwise the bundle name.
+fn bundle_title(bundle: &TextBundle) -> String {
+    split_title(&bundle.text).map(|(title, _)| title).unwrap_or_else(|| bundle.name.clone())
+}
+
+/// Split a leading `# Title` line off `text`.
+fn split_title(text: &str) -> Option<(String, String)> {
+    let text = text.trim_start_matches(['\n', '\r']);
+    let (first, rest) = text.split_once('\n').unwrap_or((text, ""));
+    let title = first.strip_prefix("# ")?.trim().to_string();
+    (!title.is_empty()).then(|| (title, rest.trim_start_matches(['\n', '\r']).to_string()))
+}
+
+/// The key of the file in `files` a link points at: the same path, or else
+/// a file with the same name.
+fn find_asset<'a>(files: &'a HashMap<String, Vec<u8>>, link: &str) -> Option<&'a String> {
+    let name = percent_decode(link.trim_start_matches("./"));
+    let base = name.rsplit('/').next().unwrap_or(&name).to_string();
+    files.get_key_value(&name).map(|(k, _)| k).or_else(|| {
+        let mut keys: Vec<&String> = files.keys().filter(|k| k.rsplit('/').next() == Some(&base)).collect();
+        keys.sort();
+        keys.first().copied()
+    })
+}
+
+/// Point links and images at `files` to copies in the images store (added
+/// to `assets`), and turn `bear://` note links into wiki links.
+fn rewrite_links(
+    ctx: &ImportContext,
+    files: &HashMap<String, Vec<u8>>,
+    text: &str,
+    assets: &mut Vec<(PathBuf, Vec<u8>)>,
+) -> String {
+    let mut replacements: Vec<(usize, usize, String)> = Vec::new();
+    for (event, range) in Parser::new(text).into_offset_iter() {
+        let (dest_url, is_image) = match event {
+            Event::Start(Tag::Image { dest_url, .. }) => (dest_url, true),
+            Event::Start(Tag::Link { dest_url, .. }) => (dest_url, false),
+            _ => continue,
+        };
+        if replacements.last().is_some_and(|r| range.start < r.1) {
+            continue;
+        }
+        let source = &text[range.clone()];
+        let Some(split) = source.rfind("](") else {
+            continue;
+        };
+        let label = source[if is_image { 2 } else { 1 }..split].to_string();
+
+        if let Some(key) = find_asset(files, &dest_url) {
+            let data = &files[key];
+            let stored = ctx.images_dir.join(stored_asset_name(key, data));
+            let url = format!("asset://localhost/{}", url_encode(&stored.to_string_lossy()));
+            if !assets.iter().any(|(path, _)| *path == stored) {
+                assets.push((stored, data.clone()));
+            }
+            replacements.push((range.start, range.end, format!("{}]({})", &source[..split], url)));
+        } else if let Some(query) = dest_url.strip_prefix("bear://x-callback-url/open-note?") {
+            let param = |key: &str| {
+                query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix(key)?.strip_prefix('='))
+                    .map(percent_decode)
+            };
+            let title = param("id").and_then(|id| {
+                let id = id.to_lowercase();
+                ctx.bundle_titles.get(&id).cloned().or_else(|| {
+                    ctx.conn.query_row("SELECT title FROM notes WHERE id = ?1", [&id], |row| row.get(0)).ok()
+                })
+            });
+            let Some(mut target) = title.or_else(|| param("title")) else {
+                continue;
+            };
+            if let Some(header) = param("header") {
+                target = format!("{}#{}", target, header);
+            }
+            if !label.is_empty() && label != target {
+                target = format!("{}|{}", target, label);
+            }
+            replacements.push((range.start, range.end, format!("[[{}]]", target)));
+        }
+    }
+    apply_replacements(text, replacements)
+}
+
+/// Convert Bear markdown in a bundle to ours. Bear 1 `[image:…]` and
+/// `[file:…]` placeholders become markdown images and links, links are
+/// rewritten as by [`rewrite_links`], and `[[Note/Heading]]` links become
+/// `[[Note#Heading]]`. Bear's `#multi word#` tags are already ours.
+fn bundle_content(
+    ctx: &ImportContext,
+    bundle: &TextBundle,
+    text: &str,
+    assets: &mut Vec<(PathBuf, Vec<u8>)>,
+) -> String {
+    let placeholder_re = Regex::new(r"\[(image|file):([^\]\n]+)\]").unwrap();
+    let text = placeholder_re.replace_all(text, |cap: &regex::Captures| match find_asset(&bundle.assets, &cap[2]) {
+        Some(key) if &cap[1] == "image" => format!("![](<{}>)", key),
+        Some(key) => format!("[{}](<{}>)", key.rsplit('/').next().unwrap_or(key), key),
+        None => cap[0].to_string(),
+    });
+    let text = rewrite_links(ctx, &bundle.assets, &text, assets);
+
+    // Bear links to a heading as [[Note/Heading]]
+    let is_title = |title: &str| {
+        ctx.bundle_titles.values().any(|t| t.eq_ignore_ascii_case(title))
+            || find_note_id_by_title(ctx.conn, title).is_some()
+    };
+    let mut replacements = Vec::new();
+    for link in find_wiki_links(&text) {
+        let (target, alias) = match link.target.split_once('|') {
+            Some((target, alias)) => (target, Some(alias)),
+            None => (link.target.as_str(), None),
+        };
+        let Some((title, heading)) = target.rsplit_once('/') else {
+            continue;
+        };
+        if target.contains('#') || heading.is_empty() || is_title(target) || !is_title(title) {
+            continue;
+        }
+        let alias = alias.map(|a| format!("|{}", a)).unwrap_or_default();
+        replacements.push((link.start, link.end, format!("[[{}#{}{}]]", title, heading, alias)));
+    }
+    apply_replacements(&text, replacements)
+}
+
+/// Convert Obsidian markdown to ours. `![[photo.png]]` embeds of
+/// attachments become markdown images (or links, for other files),
+/// `[[Folder/Note.md]]` links lose the folder and extension, and relative
+/// links to markdown files become wiki links. Attachments next to the file,
+/// or found by name in the selected folders as Obsidian links them, are
+/// copied as by [`rewrite_links`].
+fn markdown_content(ctx: &ImportContext, path: &Path, text: &str, assets: &mut Vec<(PathBuf, Vec<u8>)>) -> String {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let is_note = |name: &str| {
+        ctx.note_names.contains(&name.to_lowercase()) || find_note_id_by_title(ctx.conn, name).is_some()
+    };
+
+    let mut links = find_embeds(text);
+    links.extend(find_wiki_links(text));
+    links.sort_by_key(|link| link.start);
+    let mut replacements = Vec::new();
+    for link in links {
+        let embed = if text[link.start..].starts_with('!') { "!" } else { "" };
+        let (target, alias) = match link.target.split_once('|') {
+            Some((target, alias)) => (target, Some(alias)),
+            None => (link.target.as_str(), None),
+        };
+        let (name, anchor) = match target.split_once('#') {
+            Some((name, anchor)) => (name, Some(anchor)),
+            None => (target, None),
+        };
+        let is_file = Path::new(name)
+            .extension()
+            .is_some_and(|_| !has_extension(Path::new(name), MARKDOWN_EXTENSIONS));
+        if is_file {
+            if embed.is_empty() || resolve_attachment(ctx, dir, name).is_none() {
+                continue;
+            }
+            // An image's alias is its display size, e.g. `![[photo.png|300]]`
+            let replacement = if image_mime(Path::new(name)).is_some() {
+                format!("![](<{}>)", name)
+            } else {
+                format!("[{}](<{}>)", alias.unwrap_or(name.rsplit('/').next().unwrap_or(name)), name)
+            };
+            replacements.push((link.start, link.end, replacement));
+            continue;
+        }
+        let base = name.rsplit('/').next().unwrap_or(name);
+        let base = base.strip_suffix(".md").unwrap_or(base);
+        if base == name || !is_note(base) {
+            continue;
+        }
+        let anchor = anchor.map(|a| format!("#{}", a)).unwrap_or_default();
+        let alias = alias.map(|a| format!("|{}", a)).unwrap_or_default();
+        replacements.push((link.start, link.end, format!("{}[[{}{}{}]]", embed, base, anchor, alias)));
+    }
+    let text = apply_replacements(text, replacements);
+
+    let mut files = HashMap::new();
+    let mut replacements = Vec::new();
+    for (event, range) in Parser::new(&text).into_offset_iter() {
+        let (dest_url, is_image) = match event {
+            Event::Start(Tag::Image { dest_url, .. }) => (dest_url, true),
+            Event::Start(Tag::Link { dest_url, .. }) => (dest_url, false),
+            _ => continue,
+        };
+        if dest_url.contains(':') || dest_url.starts_with('#') {
+            continue;
+        }
+        let link = percent_decode(dest_url.trim_start_matches("./"));
+        let (file, anchor) = match link.split_once('#') {
+            Some((file, anchor)) => (file, Some(anchor)),
+            None => (link.as_str(), None),
+        };
+        let name = file.rsplit('/').next().unwrap_or(file);
+        match name.strip_suffix(".md") {
+            Some(title) if !is_image && is_note(title) => {
+                let source = &text[range.clone()];
+                let label = source.rfind("](").map(|i| &source[1..i]).unwrap_or("");
+                let mut target = title.to_string();
+                if let Some(anchor) = anchor {
+                    target = format!("{}#{}", target, anchor);
+                }
+                if !label.is_empty() && label != target {
+                    target = format!("{}|{}", target, label);
+                }
+                replacements.push((range.start, range.end, format!("[[{}]]", target)));
+            }
+            Some(_) => {}
+            None => {
+                if let Some(data) = resolve_attachment(ctx, dir, &link).and_then(|p| fs::read(p).ok()) {
+                    files.insert(link, data);
+                }
+            }
+        }
+    }
+    let text = apply_replacements(&text, replacements);
+    rewrite_links(ctx, &files, &text, assets)
+}
+
+/// The file a link from a note in `dir` points at: the path relative to
+/// the note, or else a file of that name in the selected folders.
+fn resolve_attachment(ctx: &ImportContext, dir: &Path, link: &str) -> Option<PathBuf> {
+    let direct = dir.join(link);
+    if direct.is_file() && !has_extension(&direct, MARKDOWN_EXTENSIONS) {
+        return Some(direct);
+    }
+    let name = link.rsplit('/').next()?.to_lowercase();
+    let suffix = format!("/{}", link.to_lowercase());
+    ctx.attachments
+        .get(&name)?
+        .iter()
+        .find(|p| p.to_string_lossy().replace('\\', "/").to_lowercase().ends_with(&suffix))
+        .cloned()
+}
+
+fn apply_replacements(text: &str, replacements: Vec<(usize, usize, String)>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for (start, end, replacement) in replacements {
+        out.push_str(&text[last..start]);
+        out.push_str(&replacement);
+        last = end;
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+/// A file's creation (or modification) time as RFC 3339, or now if the
+/// platform does not record it.
+fn file_time(path: &Path, created: bool) -> String {
+    let time = fs::metadata(path)
+        .ok()
+        .and_then(|m| if created { m.created().or_else(|_| m.modified()).ok() } else { m.modified().ok() })
+        .unwrap_or_else(SystemTime::now);
+    DateTime::<Utc>::from(time).to_rfc3339()
+}
+
+fn is_newer(a: &str, b: &str) -> bool {
+    match (DateTime::parse_from_rfc3339(a), DateTime::parse_from_rfc3339(b)) {
+        (Ok(a), Ok(b)) => a > b,
+        _ => a > b,
+    }
+}
+
+fn write_note(
+    conn: &Connection,
+    note: &mut PlannedNote,
+    workspaces: &mut HashMap<String, String>,
+) -> Result<(), String> {
+    let id = note.plan.note_id.clone().ok_or("No note id")?;
+    let word_count = compute_word_count(&note.content);
+
+    for (path, data) in &note.assets {
+        write_attachment(conn, path, data)?;
+    }
+
+    if note.plan.action == "update" {
+        conn.execute(
+            "UPDATE notes SET title = ?1, content = ?2, updated_at = ?3, word_count = ?4, extra_frontmatter = ?5, \
+             version = version + 1 WHERE id = ?6",
+            rusqlite::params![note.title, note.content, note.updated_at, word_count, note.extra_frontmatter, id],
+        )
+        .map_err(|e| e.to_string())?;
+    } else {
+        if let (None, Some(name)) = (&note.workspace_id, &note.plan.workspace) {
+            note.workspace_id = Some(workspace_id_for(conn, name, workspaces)?);
+        }
+        conn.execute(
+            "INSERT INTO notes (id, title, content, created_at, updated_at, word_count, workspace_id, is_pinned, state, extra_frontmatter) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            rusqlite::params![
+                id,
+                note.title,
+                note.content,
+                note.created_at,
+                note.updated_at,
+                word_count,
+                note.workspace_id,
+                note.is_pinned as i32,
+                note.state,
+                note.extra_frontmatter
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    sync_tags(conn, &id, &extract_tags(&note.content))?;
+
+    // A to-do note gets a task; checklist tasks have a position, this doesn't
+    if let Some(ref todo) = note.todo {
+        let status = if todo.done { "done" } else { "todo" };
+        let now = Utc::now().to_rfc3339();
+        let changed = conn
+            .execute(
+                "UPDATE tasks SET title = ?1, status = ?2, due_date = ?3, updated_at = ?4 \
+                 WHERE linked_note_id = ?5 AND checklist_position IS NULL",
+                rusqlite::params![note.title, status, todo.due_date, now, id],
+            )
+            .map_err(|e| e.to_string())?;
+        if changed == 0 {
+            conn.execute(
+                "INSERT INTO tasks (id, title, status, due_date, linked_note_id, workspace_id, created_at, updated_at) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)",
+                rusqlite::params![Uuid::new_v4().to_string(), note.title, status, todo.due_date, id, note.workspace_id, now],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Id of the workspace named `name`, created if there is none.
+fn workspace_id_for(conn: &Connection, name: &str, cache: &mut HashMap<String, String>) -> Result<String, String> {
+    if let Some(id) = cache.get(name) {
+        return Ok(id.clone());
+    }
+    let existing: Option<String> = conn
+        .query_row("SELECT id FROM workspaces WHERE name = ?1", [name], |row| row.get(0))
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let id = match existing {
+        Some(id) => id,
+        None => {
+            let id = Uuid::new_v4().to_string();
+            let now = Utc::now().to_rfc3339();
+            conn.execute(
+                "INSERT INTO workspaces (id, name, description, created_at, updated_at) VALUES (?1, ?2, '', ?3, ?3)",
+                rusqlite::params![id, name, now],
+            )
+            .map_err(|e| e.to_string())?;
+            log_activity(conn, "user", "workspace_created", None, &format!("Created workspace '{}'", name), "{}");
+            id
+        }
+    };
+    cache.insert(name.to_string(), id.clone());
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::migrations::migrate;
+    use crate::db::models::ImportReport;
+
+    fn import(conn: &Connection, root: &Path, folders: &str, dry_run: bool) -> ImportReport {
+        let options = ImportOptions {
+            folders: Some(folders.to_string()),
+            dry_run: Some(dry_run),
+            ..Default::default()
+        };
+        import_paths(conn, &root.join("images"), &[root.join("vault").to_string_lossy().to_string()], &options).unwrap()
+    }
+
+    /// Each file's path in the vault, action and reason.
+    fn plans(root: &Path, report: &ImportReport) -> Vec<(String, String, String)> {
+        let vault = format!("{}/", root.join("vault").to_string_lossy());
+        report
+            .files
+            .iter()
+            .map(|f| (f.path.replace(&vault, ""), f.action.clone(), f.reason.replace(&vault, "")))
+            .collect()
+    }
+
+    #[test]
+    fn test_import_folder() {
+        let root = std::env::temp_dir().join(format!("bruin-import-{}", uuid::Uuid::new_v4()));
+        let vault = root.join("vault");
+        fs::create_dir_all(vault.join("Work").join("Projects")).unwrap();
+        fs::create_dir_all(vault.join(".obsidian")).unwrap();
+        fs::write(vault.join("Inbox.md"), "Inbox body\n").unwrap();
+        let plan = "---\nid: plan-id\ntitle: Plan\ntags: [planning]\nupdated_at: 2020-01-01T00:00:00Z\n---\n\nThe plan\n";
+        fs::write(vault.join("Work").join("Plan.md"), plan).unwrap();
+        fs::write(vault.join("Work").join("Projects").join("Alpha.md"), "Alpha body\n").unwrap();
+        fs::write(vault.join("Work").join("Projects").join("Alpha 2.md"), "---\ntitle: Alpha\n---\nAlpha body\n").unwrap();
+        fs::write(vault.join(".obsidian").join("hidden.md"), "Hidden\n").unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+
+        // A dry run plans every file, with folders as tags, and writes nothing
+        let dry = import(&conn, &root, "tags", true);
+        let expected = vec![
+            ("Inbox.md", "import", "New note"),
+            ("Work/Plan.md", "import", "New note (id kept from frontmatter)"),
+            ("Work/Projects/Alpha 2.md", "import", "New note"),
+            ("Work/Projects/Alpha.md", "skip", "Same content as Work/Projects/Alpha 2.md"),
+        ];
+        let owned = |plans: &[(&str, &str, &str)]| -> Vec<(String, String, String)> {
+            plans.iter().map(|(a, b, c)| (a.to_string(), b.to_string(), c.to_string())).collect()
+        };
+        assert_eq!(plans(&root, &dry), owned(&expected));
+        assert_eq!(dry.files[1].tags, vec!["Work", "planning"]);
+        assert_eq!(dry.files[1].note_id.as_deref(), Some("plan-id"));
+        assert_eq!(dry.files[2].tags, vec!["Work/Projects"]);
+        assert_eq!((dry.imported, dry.skipped), (3, 1));
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 0);
+
+        // Top-level folders become workspaces and deeper ones tags
+        let report = import(&conn, &root, "workspaces", false);
+        let actions: Vec<&str> = report.files.iter().map(|f| f.action.as_str()).collect();
+        assert_eq!(actions, vec!["import", "import", "import", "skip"]);
+        // Notes are written as they are planned, so the copy matches a note
+        assert_eq!(report.files[3].reason, "Same content as the note 'Alpha'");
+        assert_eq!(report.files[0].workspace, None);
+        assert_eq!(report.files[1].workspace.as_deref(), Some("Work"));
+        assert_eq!(report.files[1].tags, vec!["planning"]);
+        assert_eq!(report.files[2].tags, vec!["Projects"]);
+        let workspace: String = conn
+            .query_row("SELECT w.name FROM notes n JOIN workspaces w ON w.id = n.workspace_id WHERE n.id = 'plan-id'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(workspace, "Work");
+
+        // Importing again skips everything
+        let again = import(&conn, &root, "workspaces", false);
+        assert_eq!(
+            plans(&root, &again),
+            owned(&[
+                ("Inbox.md", "skip", "Same content as the note 'Inbox'"),
+                ("Work/Plan.md", "skip", "Matches the existing note"),
+                ("Work/Projects/Alpha 2.md", "skip", "Same content as the note 'Alpha'"),
+                ("Work/Projects/Alpha.md", "skip", "Same content as the note 'Alpha'"),
+            ])
+        );
+
+        // A newer file with a known id updates its note
+        fs::write(vault.join("Work").join("Plan.md"), plan.replace("2020", "2030").replace("The plan", "The new plan")).unwrap();
+        let changed = import(&conn, &root, "workspaces", true);
+        assert_eq!(plans(&root, &changed)[1], owned(&[("Work/Plan.md", "update", "Newer than the existing note 'Plan'")])[0]);
+        assert_eq!((changed.updated, changed.skipped), (1, 3));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,14 +1,25 @@
 pub mod activity;
 pub mod agents;
+pub mod attachments;
+pub mod clipper;
+pub mod documents;
+pub mod embeds;
 pub mod export;
 pub mod files;
+pub mod images;
+pub mod import;
 pub mod notes;
+pub mod outline;
+pub mod properties;
+pub mod publish;
+pub mod queries;
 pub mod search;
 pub mod settings;
 pub mod sync;
 pub mod tags;
 pub mod tasks;
 pub mod templates;
+pub mod vault;
 pub mod webhooks;
 pub mod workflows;
 pub mod workspaces;
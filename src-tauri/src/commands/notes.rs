@@ -1,18 +1,27 @@
+use crate::commands::attachments::sync_note_attachments;
+use crate::commands::files::images_dir_for;
+use crate::commands::images::thumbnail_path;
+use crate::commands::outline::find_note_id_by_title;
+use crate::commands::properties::property_query_parts;
+use crate::commands::queries::{refresh_query_results, sync_note_queries, QueryChange};
+use crate::commands::tasks::sync_note_checklist;
+use crate::commands::tags::resolve_tag_aliases;
 use crate::db::models::*;
+use crate::markdown::patch::apply_patch;
+use crate::markdown::sections::split_link_target;
+use crate::markdown::stats::text_stats;
 use crate::markdown::tags::extract_tags;
 use crate::markdown::tags::get_parent_tag;
+use crate::sync::attachments::export_note_attachments;
 use crate::sync::icloud;
 use chrono::Utc;
 use rusqlite::Connection;
-use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use tauri::State;
 use uuid::Uuid;
 
-fn compute_word_count(content: &str) -> i64 {
-    content.split_whitespace().count() as i64
+pub(crate) fn compute_word_count(content: &str) -> i64 {
+    text_stats(content).words
 }
 
 pub(crate) fn log_activity(
@@ -141,15 +150,10 @@ pub(crate) fn sync_tags(conn: &Connection, note_id: &str, tags: &[String]) -> Re
     conn.execute("DELETE FROM note_tags WHERE note_id = ?1", [note_id])
         .map_err(|e| e.to_string())?;
 
+    let tags = resolve_tag_aliases(conn, tags)?;
     let mut new_tag_ids: Vec<i64> = Vec::new();
-    for tag_name in tags {
-        let parent = get_parent_tag(tag_name);
-
-        conn.execute(
-            "INSERT INTO tags (name, parent_name) VALUES (?1, ?2) ON CONFLICT(name) DO NOTHING",
-            rusqlite::params![tag_name, parent],
-        )
-        .map_err(|e| e.to_string())?;
+    for tag_name in &tags {
+        ensure_tag_exists(conn, tag_name)?;
 
         let tag_id: i64 = conn
             .query_row("SELECT id FROM tags WHERE name = ?1", [tag_name], |row| {
@@ -186,6 +190,24 @@ pub(crate) fn sync_tags(conn: &Connection, note_id: &str, tags: &[String]) -> Re
     Ok(())
 }
 
+/// Insert `tag_name` and any missing ancestor tags, so parents like "work"
+/// exist as rows for "work/projects".
+pub(crate) fn ensure_tag_exists(conn: &Connection, tag_name: &str) -> Result<(), String> {
+    let mut name = tag_name.to_string();
+    loop {
+        let parent = get_parent_tag(&name);
+        conn.execute(
+            "INSERT INTO tags (name, parent_name) VALUES (?1, ?2) ON CONFLICT(name) DO NOTHING",
+            rusqlite::params![name, parent],
+        )
+        .map_err(|e| e.to_string())?;
+        match parent {
+            Some(p) => name = p,
+            None => return Ok(()),
+        }
+    }
+}
+
 pub(crate) fn fetch_note_tags(conn: &Connection, note_id: &str) -> Result<Vec<String>, String> {
     let mut stmt = conn
         .prepare("SELECT t.name FROM tags t JOIN note_tags nt ON t.id = nt.tag_id WHERE nt.note_id = ?1 ORDER BY t.name")
@@ -229,10 +251,44 @@ pub(crate) fn batch_fetch_tags(conn: &Connection, note_ids: &[String]) -> Result
     Ok(map)
 }
 
+/// Batch-fetch the thumbnail path of the earliest stored image of each note.
+pub(crate) fn batch_fetch_thumbnails(conn: &Connection, note_ids: &[String]) -> Result<std::collections::HashMap<String, String>, String> {
+    use std::collections::HashMap;
+
+    let mut map: HashMap<String, String> = HashMap::new();
+    let Some(images_dir) = images_dir_for(conn) else {
+        return Ok(map);
+    };
+    if note_ids.is_empty() {
+        return Ok(map);
+    }
+
+    let placeholders: Vec<String> = (1..=note_ids.len()).map(|i| format!("?{}", i)).collect();
+    let sql = format!(
+        "SELECT na.note_id, a.thumbnail FROM attachments a JOIN note_attachments na ON na.attachment_id = a.id \
+         WHERE na.note_id IN ({}) AND a.thumbnail IS NOT NULL AND a.thumbnail != '' ORDER BY a.created_at DESC",
+        placeholders.join(", ")
+    );
+
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let params: Vec<&dyn rusqlite::types::ToSql> = note_ids.iter().map(|id| id as &dyn rusqlite::types::ToSql).collect();
+    let rows = stmt.query_map(params.as_slice(), |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    }).map_err(|e| e.to_string())?;
+
+    // Later rows are older, so the earliest image wins
+    for row in rows {
+        let (note_id, thumbnail) = row.map_err(|e| e.to_string())?;
+        map.insert(note_id, thumbnail_path(&images_dir, &thumbnail).to_string_lossy().to_string());
+    }
+
+    Ok(map)
+}
+
 pub(crate) fn fetch_note(conn: &Connection, id: &str) -> Result<Note, String> {
     let note = conn
         .query_row(
-            "SELECT id, title, content, created_at, updated_at, is_trashed, is_pinned, word_count, file_path, sync_hash, state, workspace_id, version FROM notes WHERE id = ?1",
+            "SELECT id, title, content, created_at, updated_at, is_trashed, is_pinned, word_count, file_path, sync_hash, state, workspace_id, version, extra_frontmatter FROM notes WHERE id = ?1",
             [id],
             |row| {
                 Ok(Note {
@@ -250,22 +306,30 @@ pub(crate) fn fetch_note(conn: &Connection, id: &str) -> Result<Note, String> {
                     state: row.get::<_, String>(10).unwrap_or_else(|_| "draft".to_string()),
                     workspace_id: row.get(11)?,
                     version: row.get::<_, i32>(12).unwrap_or(1),
+                    extra_frontmatter: row.get(13)?,
+                    char_count: 0,
+                    reading_minutes: 0,
                 })
             },
         )
         .map_err(|e| format!("Note not found: {}", e))?;
 
     let tags = fetch_note_tags(conn, &note.id)?;
-    Ok(Note { tags, ..note })
+    let stats = text_stats(&note.content);
+    Ok(Note { tags, char_count: stats.characters, reading_minutes: stats.reading_minutes, ..note })
 }
 
-/// Compute sync hash and update in DB, then export to iCloud.
-fn sync_to_icloud(conn: &Connection, note: &Note) {
+/// Compute sync hash and update in DB, then export to iCloud after the
+/// note's attachments.
+pub(crate) fn sync_to_icloud(conn: &Connection, note: &Note) {
     let hash = icloud::compute_sync_hash(&note.title, &note.content);
     let _ = conn.execute(
         "UPDATE notes SET sync_hash = ?1 WHERE id = ?2",
         rusqlite::params![hash, note.id],
     );
+    if let Err(e) = export_note_attachments(conn, &note.id) {
+        log::warn!("Failed to sync attachments of note {}: {}", note.id, e);
+    }
     let _ = icloud::export_note(note);
 }
 
@@ -290,6 +354,10 @@ pub fn create_note(
 
     sync_tags(&conn, &id, &tags)?;
     sync_note_links(&conn, &id, &content)?;
+    sync_note_queries(&conn, &id, &content)?;
+    sync_note_checklist(&conn, &id, &content)?;
+    sync_note_attachments(&conn, &id, &content)?;
+    let _ = refresh_query_results(&conn, QueryChange::Note(&id));
     let note = fetch_note(&conn, &id)?;
     sync_to_icloud(&conn, &note);
     log_activity(&conn, "user", "note_created", Some(&id), &format!("Created note '{}'", note.title), "{}");
@@ -324,21 +392,69 @@ pub fn update_note(
 
     let title = params.title.unwrap_or(existing.title);
     let content = params.content.unwrap_or(existing.content);
-    let word_count = compute_word_count(&content);
-    let tags = extract_tags(&content);
-    let new_version = existing.version + 1;
+    let note = save_note_content(&conn, &params.id, &title, &content, existing.version + 1, &now)?;
+    log_activity(&conn, "user", "note_updated", Some(&params.id), &format!("Updated note '{}'", note.title), "{}");
+    Ok(note)
+}
+
+/// Write a note's title and content, re-derive everything built from the
+/// content (tags, links, query blocks, checklist tasks, attachments) and
+/// export it.
+pub(crate) fn save_note_content(
+    conn: &Connection,
+    id: &str,
+    title: &str,
+    content: &str,
+    version: i32,
+    now: &str,
+) -> Result<Note, String> {
+    let word_count = compute_word_count(content);
+    let tags = extract_tags(content);
 
     conn.execute(
         "UPDATE notes SET title = ?1, content = ?2, updated_at = ?3, word_count = ?4, version = ?5 WHERE id = ?6",
-        rusqlite::params![title, content, now, word_count, new_version, params.id],
+        rusqlite::params![title, content, now, word_count, version, id],
     )
     .map_err(|e| e.to_string())?;
 
-    sync_tags(&conn, &params.id, &tags)?;
-    sync_note_links(&conn, &params.id, &content)?;
-    let note = fetch_note(&conn, &params.id)?;
-    sync_to_icloud(&conn, &note);
-    log_activity(&conn, "user", "note_updated", Some(&params.id), &format!("Updated note '{}'", note.title), "{}");
+    sync_tags(conn, id, &tags)?;
+    sync_note_links(conn, id, content)?;
+    sync_note_queries(conn, id, content)?;
+    sync_note_checklist(conn, id, content)?;
+    sync_note_attachments(conn, id, content)?;
+    let _ = refresh_query_results(conn, QueryChange::Note(id));
+    let note = fetch_note(conn, id)?;
+    sync_to_icloud(conn, &note);
+    Ok(note)
+}
+
+/// Apply structured edits to a note's content instead of replacing all of
+/// it. The operations apply in order and all must succeed.
+#[tauri::command]
+pub fn patch_note(
+    db: State<'_, Mutex<Connection>>,
+    params: PatchNoteParams,
+) -> Result<Note, String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    let now = Utc::now().to_rfc3339();
+
+    let existing = fetch_note(&conn, &params.id)?;
+    if let Some(expected) = params.expected_version {
+        if existing.version != expected {
+            return Err(format!(
+                "Version conflict: expected {} but found {}. Another agent may have updated this note.",
+                expected, existing.version
+            ));
+        }
+    }
+    if params.operations.is_empty() {
+        return Ok(existing);
+    }
+
+    let content = apply_patch(&existing.content, &params.operations)?;
+    let note = save_note_content(&conn, &params.id, &existing.title, &content, existing.version + 1, &now)?;
+    let details = serde_json::json!({ "operations": params.operations.len() }).to_string();
+    log_activity(&conn, "user", "note_patched", Some(&params.id), &format!("Patched note '{}'", note.title), &details);
     Ok(note)
 }
 
@@ -370,6 +486,7 @@ pub fn delete_note(
         log_activity(&conn, "user", "note_trashed", Some(&id), &format!("Moved note '{}' to trash", id), "{}");
     }
 
+    let _ = refresh_query_results(&conn, QueryChange::Note(&id));
     Ok(())
 }
 
@@ -394,6 +511,8 @@ pub fn list_notes(
         params.tag.as_ref().map(|t| vec![t.clone()])
     };
 
+    let filters = params.property_filters.clone().unwrap_or_default();
+
     if let Some(ref tags) = tag_list {
         let placeholders: Vec<String> = tags.iter().enumerate().map(|(i, _)| format!("?{}", i + 1)).collect();
         let in_clause = placeholders.join(", ");
@@ -403,20 +522,28 @@ pub fn list_notes(
         } else {
             String::new()
         };
+        let (prop_filter, prop_order, prop_params) = property_query_parts(
+            &filters,
+            params.sort_property.as_deref(),
+            params.sort_order.as_deref(),
+            tag_count + 5 + if params.workspace_id.is_some() { 1 } else { 0 },
+        )?;
         let sql = format!(
             "SELECT n.id, n.title, n.content, n.updated_at, n.is_pinned, n.is_trashed, n.word_count, n.state, n.workspace_id \
              FROM notes n \
              JOIN note_tags nt ON n.id = nt.note_id \
              JOIN tags t ON nt.tag_id = t.id \
-             WHERE t.name IN ({}) AND n.is_trashed = ?{} {} \
+             WHERE t.name IN ({}) AND n.is_trashed = ?{} {}{} \
              GROUP BY n.id \
              HAVING COUNT(DISTINCT t.name) = ?{} \
-             ORDER BY n.is_pinned DESC, n.updated_at DESC \
+             ORDER BY n.is_pinned DESC, {}n.updated_at DESC \
              LIMIT ?{} OFFSET ?{}",
             in_clause,
             tag_count + 1,
             ws_filter,
+            prop_filter,
             tag_count + 2,
+            prop_order,
             tag_count + 3,
             tag_count + 4 + if params.workspace_id.is_some() { 1 } else { 0 },
         );
@@ -434,6 +561,7 @@ pub fn list_notes(
             sql_params.push(Box::new(ws_param.clone()));
         }
         sql_params.push(Box::new(offset));
+        sql_params.extend(prop_params);
 
         let param_refs: Vec<&dyn rusqlite::types::ToSql> = sql_params.iter().map(|p| p.as_ref()).collect();
         let rows = stmt
@@ -459,6 +587,8 @@ pub fn list_notes(
                     tags: vec![],
                     state: row.get::<_, String>(7).unwrap_or_else(|_| "draft".to_string()),
                     workspace_id: row.get(8)?,
+                    attachment_match: None,
+                    thumbnail: None,
                 })
             })
             .map_err(|e| e.to_string())?;
@@ -468,19 +598,36 @@ pub fn list_notes(
             items.push(item);
         }
     } else {
+        let (prop_filter, prop_order, prop_params) = property_query_parts(
+            &filters,
+            params.sort_property.as_deref(),
+            params.sort_order.as_deref(),
+            5,
+        )?;
         // Use 'n' alias for consistency with workspace clause
         let sql = format!(
             "SELECT n.id, n.title, n.content, n.updated_at, n.is_pinned, n.is_trashed, n.word_count, n.state, n.workspace_id \
              FROM notes n \
-             WHERE n.is_trashed = ?1 {} \
-             ORDER BY n.is_pinned DESC, n.updated_at DESC \
+             WHERE n.is_trashed = ?1 {}{} \
+             ORDER BY n.is_pinned DESC, {}n.updated_at DESC \
              LIMIT ?2 OFFSET ?3",
-            if params.workspace_id.is_some() { "AND n.workspace_id = ?4" } else { "AND (?4 IS NULL OR 1=1)" }
+            if params.workspace_id.is_some() { "AND n.workspace_id = ?4" } else { "AND (?4 IS NULL OR 1=1)" },
+            prop_filter,
+            prop_order,
         );
         let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
 
+        let mut sql_params: Vec<Box<dyn rusqlite::types::ToSql>> = vec![
+            Box::new(trashed),
+            Box::new(limit),
+            Box::new(offset),
+            Box::new(ws_param),
+        ];
+        sql_params.extend(prop_params);
+        let param_refs: Vec<&dyn rusqlite::types::ToSql> = sql_params.iter().map(|p| p.as_ref()).collect();
+
         let rows = stmt
-            .query_map(rusqlite::params![trashed, limit, offset, ws_param], |row| {
+            .query_map(param_refs.as_slice(), |row| {
                 let content: String = row.get(2)?;
                 let preview = if content.len() > 200 {
                     let mut end = 200;
@@ -502,6 +649,8 @@ pub fn list_notes(
                     tags: vec![],
                     state: row.get::<_, String>(7).unwrap_or_else(|_| "draft".to_string()),
                     workspace_id: row.get(8)?,
+                    attachment_match: None,
+                    thumbnail: None,
                 })
             })
             .map_err(|e| e.to_string())?;
@@ -515,10 +664,12 @@ pub fn list_notes(
     // Batch-fetch tags for all notes (fixes N+1)
     let note_ids: Vec<String> = items.iter().map(|n| n.id.clone()).collect();
     let tags_map = batch_fetch_tags(&conn, &note_ids)?;
+    let thumbnails = batch_fetch_thumbnails(&conn, &note_ids)?;
     for item in &mut items {
         if let Some(tags) = tags_map.get(&item.id) {
             item.tags = tags.clone();
         }
+        item.thumbnail = thumbnails.get(&item.id).cloned();
     }
 
     Ok(items)
@@ -557,6 +708,7 @@ pub fn trash_note(db: State<'_, Mutex<Connection>>, id: String) -> Result<(), St
 
     let _ = icloud::delete_note_file(&id);
     log_activity(&conn, "user", "note_trashed", Some(&id), &format!("Moved note '{}' to trash", id), "{}");
+    let _ = refresh_query_results(&conn, QueryChange::Note(&id));
     Ok(())
 }
 
@@ -573,6 +725,7 @@ pub fn restore_note(db: State<'_, Mutex<Connection>>, id: String) -> Result<(),
         sync_to_icloud(&conn, &note);
     }
     log_activity(&conn, "user", "note_restored", Some(&id), &format!("Restored note '{}' from trash", id), "{}");
+    let _ = refresh_query_results(&conn, QueryChange::Note(&id));
     Ok(())
 }
 
@@ -612,55 +765,10 @@ pub fn set_note_state(
     .map_err(|e| e.to_string())?;
 
     log_activity(&conn, "user", "state_changed", Some(&id), &format!("Changed state '{}' → '{}'", existing.state, state), "{}");
-    fetch_note(&conn, &id)
-}
-
-// --- Bear Markdown Import ---
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ImportResult {
-    pub imported: u32,
-    pub skipped: u32,
-}
-
-#[tauri::command]
-pub fn import_markdown_files(
-    db: State<'_, Mutex<Connection>>,
-    paths: Vec<String>,
-) -> Result<ImportResult, String> {
-    let conn = db.lock().map_err(|e| e.to_string())?;
-    let mut imported = 0u32;
-    let mut skipped = 0u32;
-
-    // Collect all .md file paths (support both files and directories)
-    let mut md_files: Vec<PathBuf> = Vec::new();
-    for path_str in &paths {
-        let path = PathBuf::from(path_str);
-        if path.is_dir() {
-            if let Ok(entries) = fs::read_dir(&path) {
-                for entry in entries.flatten() {
-                    let p = entry.path();
-                    if p.extension().and_then(|e| e.to_str()) == Some("md") {
-                        md_files.push(p);
-                    }
-                }
-            }
-        } else if path.extension().and_then(|e| e.to_str()) == Some("md") {
-            md_files.push(path);
-        }
-    }
-
-    for md_path in &md_files {
-        match import_single_markdown(&conn, md_path) {
-            Ok(_) => imported += 1,
-            Err(e) => {
-                log::warn!("Skipped {}: {}", md_path.display(), e);
-                skipped += 1;
-            }
-        }
-    }
-
-    Ok(ImportResult { imported, skipped })
+    let _ = refresh_query_results(&conn, QueryChange::Note(&id));
+    let note = fetch_note(&conn, &id)?;
+    sync_to_icloud(&conn, &note);
+    Ok(note)
 }
 
 // --- Knowledge Graph: wiki-link sync ---
@@ -674,21 +782,20 @@ pub(crate) fn sync_note_links(conn: &Connection, note_id: &str, content: &str) -
         .map_err(|e| e.to_string())?;
 
     for cap in re.captures_iter(content) {
-        let linked_title = &cap[1];
-        // Resolve title to note id
-        let target_id: Option<String> = conn
-            .query_row(
-                "SELECT id FROM notes WHERE title = ?1 AND is_trashed = 0",
-                [linked_title],
-                |row| row.get(0),
-            )
-            .ok();
+        // `[[Note#Heading]]` links to Note; `[[#Heading]]` stays within this note
+        let (linked_title, _) = split_link_target(&cap[1]);
+        if linked_title.is_empty() {
+            continue;
+        }
+        let target_id = find_note_id_by_title(conn, linked_title);
+        let start = cap.get(0).map(|m| m.start()).unwrap_or(0);
+        let link_type = if content[..start].ends_with('!') { "embed" } else { "wiki_link" };
 
         if let Some(target_id) = target_id {
             if target_id != note_id {
                 let _ = conn.execute(
-                    "INSERT OR IGNORE INTO note_links (source_note_id, target_note_id, link_type, created_at) VALUES (?1, ?2, 'wiki_link', ?3)",
-                    rusqlite::params![note_id, target_id, now],
+                    "INSERT OR IGNORE INTO note_links (source_note_id, target_note_id, link_type, created_at) VALUES (?1, ?2, ?3, ?4)",
+                    rusqlite::params![note_id, target_id, link_type, now],
                 );
             }
         }
@@ -696,6 +803,23 @@ pub(crate) fn sync_note_links(conn: &Connection, note_id: &str, content: &str) -
     Ok(())
 }
 
+/// Re-sync the links of every note with a wiki link, so links written
+/// before their target note existed (as in a bulk import) resolve.
+pub(crate) fn rebuild_note_links(conn: &Connection) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare("SELECT id, content FROM notes WHERE is_trashed = 0 AND content LIKE '%[[%'")
+        .map_err(|e| e.to_string())?;
+    let notes: Vec<(String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+    for (id, content) in notes {
+        sync_note_links(conn, &id, &content)?;
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub fn get_knowledge_graph(
     db: State<'_, Mutex<Connection>>,
@@ -704,18 +828,38 @@ pub fn get_knowledge_graph(
     max_nodes: Option<i32>,
 ) -> Result<KnowledgeGraph, String> {
     let conn = db.lock().map_err(|e| e.to_string())?;
-    let max = max_nodes.unwrap_or(200) as usize;
-    let depth_limit = depth.unwrap_or(2);
+    build_knowledge_graph(
+        &conn,
+        center_note_id.as_deref(),
+        depth.unwrap_or(2),
+        max_nodes.unwrap_or(200) as usize,
+        None,
+    )
+}
 
+/// BFS over `note_links` from `center_note_id` (or every linked note when
+/// `None`), shared by the graph view and graph export. When `allowed` is
+/// given, other notes are neither visited nor counted toward `max`.
+pub(crate) fn build_knowledge_graph(
+    conn: &Connection,
+    center_note_id: Option<&str>,
+    depth_limit: i32,
+    max: usize,
+    allowed: Option<&std::collections::HashSet<String>>,
+) -> Result<KnowledgeGraph, String> {
     use std::collections::{HashMap, HashSet, VecDeque};
 
+    let is_allowed = |id: &String| allowed.is_none_or(|a| a.contains(id));
+
     let mut visited: HashSet<String> = HashSet::new();
     let mut queue: VecDeque<(String, i32)> = VecDeque::new();
     let mut edges: Vec<GraphEdge> = Vec::new();
 
-    if let Some(ref center_id) = center_note_id {
-        queue.push_back((center_id.clone(), 0));
-        visited.insert(center_id.clone());
+    if let Some(center_id) = center_note_id {
+        if is_allowed(&center_id.to_string()) {
+            queue.push_back((center_id.to_string(), 0));
+            visited.insert(center_id.to_string());
+        }
     } else {
         // No center: grab all linked notes
         let mut stmt = conn
@@ -725,7 +869,7 @@ pub fn get_knowledge_graph(
             .query_map([], |row| row.get::<_, String>(0))
             .map_err(|e| e.to_string())?;
         for id in rows.flatten() {
-            if visited.len() < max {
+            if visited.len() < max && is_allowed(&id) {
                 visited.insert(id.clone());
                 queue.push_back((id, 0));
             }
@@ -754,7 +898,7 @@ pub fn get_knowledge_graph(
                 target: target_id.clone(),
                 link_type: link_type.clone(),
             });
-            if !visited.contains(target_id) && visited.len() < max {
+            if !visited.contains(target_id) && visited.len() < max && is_allowed(target_id) {
                 visited.insert(target_id.clone());
                 queue.push_back((target_id.clone(), current_depth + 1));
             }
@@ -776,7 +920,7 @@ pub fn get_knowledge_graph(
                 target: current_id.clone(),
                 link_type: link_type.clone(),
             });
-            if !visited.contains(source_id) && visited.len() < max {
+            if !visited.contains(source_id) && visited.len() < max && is_allowed(source_id) {
                 visited.insert(source_id.clone());
                 queue.push_back((source_id.clone(), current_depth + 1));
             }
@@ -799,7 +943,7 @@ pub fn get_knowledge_graph(
         let title: String = conn
             .query_row("SELECT title FROM notes WHERE id = ?1", [node_id], |row| row.get(0))
             .unwrap_or_else(|_| "Unknown".to_string());
-        let tags = fetch_note_tags(&conn, node_id).unwrap_or_default();
+        let tags = fetch_note_tags(conn, node_id).unwrap_or_default();
         nodes.push(GraphNode {
             id: node_id.clone(),
             title,
@@ -810,37 +954,3 @@ pub fn get_knowledge_graph(
 
     Ok(KnowledgeGraph { nodes, edges })
 }
-
-fn import_single_markdown(conn: &Connection, path: &Path) -> Result<Note, String> {
-    let raw = fs::read_to_string(path)
-        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
-
-    // Determine if file has Bruin frontmatter or is a plain Bear export
-    let (title, body, file_tags) = if raw.starts_with("---") {
-        let file_note = icloud::import_file(path)?;
-        (file_note.title, file_note.content, file_note.tags)
-    } else {
-        let title = path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("Untitled")
-            .to_string();
-        let tags = extract_tags(&raw);
-        (title, raw, tags)
-    };
-
-    let id = Uuid::new_v4().to_string();
-    let now = Utc::now().to_rfc3339();
-    let word_count = compute_word_count(&body);
-
-    conn.execute(
-        "INSERT INTO notes (id, title, content, created_at, updated_at, word_count) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-        rusqlite::params![id, title, body, now, now, word_count],
-    )
-    .map_err(|e| e.to_string())?;
-
-    sync_tags(conn, &id, &file_tags)?;
-    let note = fetch_note(conn, &id)?;
-    sync_to_icloud(conn, &note);
-    Ok(note)
-}
@@ -0,0 +1,128 @@
+use crate::commands::notes::{compute_word_count, fetch_note};
+use crate::db::models::{Note, NoteSection, OutlineEntry, ResolvedLink};
+use crate::markdown::sections::{
+    find_section, find_section_by_anchor, find_sections, parse_heading_path, section_anchors,
+    split_link_target, Section,
+};
+use rusqlite::Connection;
+use std::sync::Mutex;
+use tauri::State;
+
+fn note_section(note: &Note, section: &Section, anchor: String) -> NoteSection {
+    NoteSection {
+        note_id: note.id.clone(),
+        note_title: note.title.clone(),
+        level: section.heading.level,
+        heading: section.heading.text.clone(),
+        anchor,
+        markdown: note.content[section.heading.start..section.end].to_string(),
+        start: section.heading.start,
+        end: section.end,
+        word_count: compute_word_count(&note.content[section.heading.body_start..section.end]),
+    }
+}
+
+/// Find a section by anchor (as in `[[Note#anchor]]`) or by heading path
+/// (`Projects > Alpha`).
+pub(crate) fn find_note_section(
+    note: &Note,
+    anchor: Option<&str>,
+    heading_path: Option<&str>,
+) -> Result<NoteSection, String> {
+    if let Some(anchor) = anchor {
+        let (section, anchor) = find_section_by_anchor(&note.content, anchor)
+            .ok_or_else(|| format!("Section '{}' not found in '{}'", anchor, note.title))?;
+        return Ok(note_section(note, &section, anchor));
+    }
+    let path = heading_path.ok_or_else(|| "Either anchor or heading_path is required".to_string())?;
+    let section = find_section(&note.content, &parse_heading_path(path))?;
+    // Report the same anchor the outline uses for this heading
+    let sections = find_sections(&note.content);
+    let anchors = section_anchors(&sections);
+    let anchor = sections
+        .iter()
+        .position(|s| s.heading.start == section.heading.start)
+        .map(|i| anchors[i].clone())
+        .unwrap_or_default();
+    Ok(note_section(note, &section, anchor))
+}
+
+/// Find a note by title for a wiki link: exact match first, then
+/// case-insensitive.
+pub(crate) fn find_note_id_by_title(conn: &Connection, title: &str) -> Option<String> {
+    conn.query_row(
+        "SELECT id FROM notes WHERE title = ?1 AND is_trashed = 0",
+        [title],
+        |row| row.get(0),
+    )
+    .or_else(|_| {
+        conn.query_row(
+            "SELECT id FROM notes WHERE title = ?1 COLLATE NOCASE AND is_trashed = 0 ORDER BY updated_at DESC LIMIT 1",
+            [title],
+            |row| row.get(0),
+        )
+    })
+    .ok()
+}
+
+#[tauri::command]
+pub fn get_note_outline(
+    db: State<'_, Mutex<Connection>>,
+    id: String,
+) -> Result<Vec<OutlineEntry>, String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    let note = fetch_note(&conn, &id)?;
+    let sections = find_sections(&note.content);
+    let anchors = section_anchors(&sections);
+
+    Ok(sections
+        .iter()
+        .zip(anchors)
+        .map(|(section, anchor)| OutlineEntry {
+            level: section.heading.level,
+            heading: section.heading.text.clone(),
+            anchor,
+            start: section.heading.start,
+            end: section.end,
+            word_count: compute_word_count(&note.content[section.heading.body_start..section.end]),
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub fn get_note_section(
+    db: State<'_, Mutex<Connection>>,
+    id: String,
+    anchor: Option<String>,
+    heading_path: Option<String>,
+) -> Result<NoteSection, String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    let note = fetch_note(&conn, &id)?;
+    find_note_section(&note, anchor.as_deref(), heading_path.as_deref())
+}
+
+/// Resolve a wiki link target such as `Note`, `Note#Heading` or `#Heading`
+/// (relative to `from_note_id`).
+#[tauri::command]
+pub fn resolve_wiki_link(
+    db: State<'_, Mutex<Connection>>,
+    target: String,
+    from_note_id: Option<String>,
+) -> Result<ResolvedLink, String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    let (title, anchor) = split_link_target(&target);
+
+    let note_id = if title.is_empty() {
+        from_note_id.ok_or_else(|| format!("'{}' needs a note to resolve against", target))?
+    } else {
+        find_note_id_by_title(&conn, title).ok_or_else(|| format!("Note '{}' not found", title))?
+    };
+    let note = fetch_note(&conn, &note_id)?;
+    let section = anchor.and_then(|a| find_note_section(&note, Some(a), None).ok());
+
+    Ok(ResolvedLink {
+        note_id: note.id,
+        note_title: note.title,
+        section,
+    })
+}
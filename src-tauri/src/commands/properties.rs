@@ -0,0 +1,649 @@
+use crate::commands::notes::{fetch_note, log_activity, sync_to_icloud};
+use crate::commands::queries::{refresh_query_results, QueryChange};
+use crate::commands::search::escape_like;
+use crate::db::models::{DefinePropertyParams, Note, NoteProperty, PropertyDefinition, PropertyFilter};
+use crate::markdown::frontmatter::{is_reserved_key, parse_extra, scalar_to_string, set_extra_value};
+use crate::markdown::sections::split_link_target;
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use rusqlite::types::ToSql;
+use rusqlite::Connection;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tauri::State;
+use uuid::Uuid;
+use yaml_rust2::Yaml;
+
+/// Extra WHERE conditions, ORDER BY terms and their params for `list_notes`.
+type PropertyQueryParts = (String, String, Vec<Box<dyn ToSql>>);
+
+const PROPERTY_TYPES: &[&str] = &["text", "number", "date", "select", "checkbox", "note"];
+
+/// An indexed property value: the text form (dates as `YYYY-MM-DD`, note
+/// references as the target note id) and the numeric form where one exists.
+struct IndexedValue {
+    text: Option<String>,
+    number: Option<f64>,
+}
+
+fn row_to_definition(row: &rusqlite::Row) -> rusqlite::Result<PropertyDefinition> {
+    let options: String = row.get(4)?;
+    Ok(PropertyDefinition {
+        id: row.get(0)?,
+        workspace_id: row.get(1)?,
+        name: row.get(2)?,
+        property_type: row.get(3)?,
+        options: serde_json::from_str(&options).unwrap_or_default(),
+        created_at: row.get(5)?,
+        updated_at: row.get(6)?,
+    })
+}
+
+/// Definitions that apply to a note in `workspace_id`: the workspace's own
+/// plus global ones, with a workspace definition winning over a global one
+/// of the same name.
+fn applicable_definitions(
+    conn: &Connection,
+    workspace_id: Option<&str>,
+) -> Result<Vec<PropertyDefinition>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, workspace_id, name, property_type, options, created_at, updated_at \
+             FROM property_definitions \
+             WHERE workspace_id IS NULL OR workspace_id = ?1 \
+             ORDER BY workspace_id IS NULL, name",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([workspace_id], row_to_definition)
+        .map_err(|e| e.to_string())?;
+
+    let mut seen = HashSet::new();
+    let mut defs = Vec::new();
+    for row in rows {
+        let def = row.map_err(|e| e.to_string())?;
+        if seen.insert(def.name.clone()) {
+            defs.push(def);
+        }
+    }
+    Ok(defs)
+}
+
+fn parse_date(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(raw, "%Y-%m-%d") {
+        return Some(date.format("%Y-%m-%d").to_string());
+    }
+    DateTime::parse_from_rfc3339(raw)
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc).to_rfc3339())
+}
+
+fn parse_checkbox(value: &Yaml) -> Option<bool> {
+    match value {
+        Yaml::Boolean(b) => Some(*b),
+        Yaml::Integer(i) => Some(*i != 0),
+        Yaml::String(s) => match s.to_lowercase().as_str() {
+            "true" | "yes" | "y" | "x" | "1" => Some(true),
+            "false" | "no" | "n" | "" | "0" => Some(false),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Resolve a note reference written as an id, a title or `[[Title]]`. A
+/// `#Heading` suffix is ignored.
+fn resolve_note_ref(conn: &Connection, raw: &str) -> String {
+    let target = raw
+        .trim()
+        .trim_start_matches("[[")
+        .trim_end_matches("]]");
+    let (target, _) = split_link_target(target);
+    conn.query_row(
+        "SELECT id FROM notes WHERE (id = ?1 OR title = ?1 COLLATE NOCASE) AND is_trashed = 0 \
+         ORDER BY id = ?1 DESC LIMIT 1",
+        [target],
+        |row| row.get(0),
+    )
+    .unwrap_or_else(|_| target.to_string())
+}
+
+/// Coerce a frontmatter value to a property's type. Returns `None` when the
+/// value cannot be read as that type.
+fn coerce_value(conn: &Connection, def: &PropertyDefinition, value: &Yaml) -> Option<IndexedValue> {
+    match def.property_type.as_str() {
+        "number" => {
+            let number = match value {
+                Yaml::Integer(i) => *i as f64,
+                Yaml::Real(s) | Yaml::String(s) => s.trim().parse().ok()?,
+                _ => return None,
+            };
+            Some(IndexedValue { text: Some(number.to_string()), number: Some(number) })
+        }
+        "date" => {
+            let date = parse_date(&scalar_to_string(value)?)?;
+            Some(IndexedValue { text: Some(date), number: None })
+        }
+        "checkbox" => {
+            let checked = parse_checkbox(value)?;
+            Some(IndexedValue {
+                text: Some(checked.to_string()),
+                number: Some(if checked { 1.0 } else { 0.0 }),
+            })
+        }
+        "note" => {
+            let raw = scalar_to_string(value)?;
+            Some(IndexedValue { text: Some(resolve_note_ref(conn, &raw)), number: None })
+        }
+        // text and select
+        _ => Some(IndexedValue { text: scalar_to_string(value), number: None }),
+    }
+}
+
+/// Rebuild the `note_properties` rows for one note from its preserved
+/// frontmatter and the definitions that apply to its workspace.
+pub(crate) fn index_note_properties(conn: &Connection, note_id: &str) -> Result<(), String> {
+    let (extra, workspace_id): (Option<String>, Option<String>) = conn
+        .query_row(
+            "SELECT extra_frontmatter, workspace_id FROM notes WHERE id = ?1",
+            [note_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("Note not found: {}", e))?;
+
+    conn.execute("DELETE FROM note_properties WHERE note_id = ?1", [note_id])
+        .map_err(|e| e.to_string())?;
+
+    let values = parse_extra(extra.as_deref());
+    if values.is_empty() {
+        return Ok(());
+    }
+
+    for def in applicable_definitions(conn, workspace_id.as_deref())? {
+        let Some(raw) = values.get(&Yaml::String(def.name.clone())) else {
+            continue;
+        };
+        if let Some(value) = coerce_value(conn, &def, raw) {
+            conn.execute(
+                "INSERT INTO note_properties (note_id, name, property_type, value_text, value_number) \
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![note_id, def.name, def.property_type, value.text, value.number],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Re-index every note a definition change can affect: the notes in
+/// `workspace_id`, or all notes for a global definition.
+fn reindex_scope(conn: &Connection, workspace_id: Option<&str>) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare("SELECT id FROM notes WHERE ?1 IS NULL OR workspace_id = ?1")
+        .map_err(|e| e.to_string())?;
+    let note_ids: Vec<String> = stmt
+        .query_map([workspace_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+    for note_id in note_ids {
+        index_note_properties(conn, &note_id)?;
+    }
+    Ok(())
+}
+
+/// Turn a JSON value from the frontend or an agent into the YAML written to
+/// frontmatter, validated against the property's type and options.
+fn json_to_property_yaml(
+    conn: &Connection,
+    def: &PropertyDefinition,
+    value: &serde_json::Value,
+) -> Result<Yaml, String> {
+    let invalid = || format!("Invalid value for {} property '{}': {}", def.property_type, def.name, value);
+    let yaml = match value {
+        serde_json::Value::Bool(b) => Yaml::Boolean(*b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Yaml::Integer(i),
+            None => Yaml::Real(n.to_string()),
+        },
+        serde_json::Value::String(s) => Yaml::String(s.clone()),
+        _ => return Err(invalid()),
+    };
+    let coerced = coerce_value(conn, def, &yaml).ok_or_else(invalid)?;
+
+    if def.property_type == "select" && !def.options.is_empty() {
+        let text = coerced.text.clone().unwrap_or_default();
+        if !def.options.contains(&text) {
+            return Err(format!(
+                "'{}' is not an option of '{}' (expected one of: {})",
+                text,
+                def.name,
+                def.options.join(", ")
+            ));
+        }
+    }
+
+    // Store normalised dates and checkboxes so the file reads back the same
+    Ok(match def.property_type.as_str() {
+        "date" => Yaml::String(coerced.text.unwrap_or_default()),
+        "checkbox" => Yaml::Boolean(coerced.number == Some(1.0)),
+        _ => yaml,
+    })
+}
+
+fn indexed_to_json(property_type: &str, text: Option<String>, number: Option<f64>) -> serde_json::Value {
+    match property_type {
+        "number" => number.map(serde_json::Value::from).unwrap_or(serde_json::Value::Null),
+        "checkbox" => serde_json::Value::Bool(number == Some(1.0)),
+        _ => text.map(serde_json::Value::String).unwrap_or(serde_json::Value::Null),
+    }
+}
+
+/// Resolve date keywords in filter values so agents can ask for `due < today`.
+pub(crate) fn resolve_relative_date(value: &str) -> String {
+    let today = Local::now().date_naive();
+    let date = match value {
+        "today" => today,
+        "tomorrow" => today + Duration::days(1),
+        "yesterday" => today - Duration::days(1),
+        _ => return value.to_string(),
+    };
+    date.format("%Y-%m-%d").to_string()
+}
+
+/// Build the extra WHERE conditions and ORDER BY terms `list_notes` needs for
+/// property filters and sorting. Placeholders are numbered from
+/// `first_param`, and the returned params must be appended in order.
+pub(crate) fn property_query_parts(
+    filters: &[PropertyFilter],
+    sort_property: Option<&str>,
+    sort_order: Option<&str>,
+    first_param: usize,
+) -> Result<PropertyQueryParts, String> {
+    let mut where_sql = String::new();
+    let mut params: Vec<Box<dyn ToSql>> = Vec::new();
+    let mut next = first_param;
+
+    for filter in filters {
+        let name_idx = next;
+        params.push(Box::new(filter.name.clone()));
+        next += 1;
+
+        let op = match filter.op.as_str() {
+            "eq" | "=" => "=",
+            "ne" | "!=" => "!=",
+            "lt" | "<" => "<",
+            "lte" | "<=" => "<=",
+            "gt" | ">" => ">",
+            "gte" | ">=" => ">=",
+            "contains" => "LIKE",
+            "is_empty" => {
+                where_sql.push_str(&format!(
+                    " AND NOT EXISTS (SELECT 1 FROM note_properties np WHERE np.note_id = n.id AND np.name = ?{})",
+                    name_idx
+                ));
+                continue;
+            }
+            "is_not_empty" => {
+                where_sql.push_str(&format!(
+                    " AND EXISTS (SELECT 1 FROM note_properties np WHERE np.note_id = n.id AND np.name = ?{})",
+                    name_idx
+                ));
+                continue;
+            }
+            other => return Err(format!("Unknown property filter operator '{}'", other)),
+        };
+
+        let value_idx = next;
+        next += 1;
+        match filter.value.as_ref() {
+            Some(serde_json::Value::Bool(b)) => params.push(Box::new(*b as i64)),
+            Some(serde_json::Value::Number(n)) => params.push(Box::new(n.as_f64().unwrap_or(0.0))),
+            Some(serde_json::Value::String(s)) if op == "LIKE" => params.push(Box::new(escape_like(s))),
+            Some(serde_json::Value::String(s)) => params.push(Box::new(resolve_relative_date(s))),
+            _ => return Err(format!("Property filter '{}' {} needs a value", filter.name, filter.op)),
+        }
+
+        // `ne` also matches notes that do not have the property at all
+        let (exists, cmp) = match op {
+            "!=" => ("NOT EXISTS", "="),
+            "LIKE" => ("EXISTS", "LIKE"),
+            _ => ("EXISTS", op),
+        };
+        let condition = if cmp == "LIKE" {
+            format!("np.value_text LIKE '%' || ?{} || '%' ESCAPE '\\'", value_idx)
+        } else {
+            format!(
+                "CASE WHEN np.property_type IN ('number', 'checkbox') \
+                 THEN np.value_number {cmp} CAST(?{v} AS REAL) \
+                 ELSE np.value_text {cmp} ?{v} END",
+                cmp = cmp,
+                v = value_idx
+            )
+        };
+        where_sql.push_str(&format!(
+            " AND {} (SELECT 1 FROM note_properties np WHERE np.note_id = n.id AND np.name = ?{} AND {})",
+            exists, name_idx, condition
+        ));
+    }
+
+    let mut order_sql = String::new();
+    if let Some(name) = sort_property {
+        let direction = if sort_order == Some("desc") { "DESC" } else { "ASC" };
+        let expr = format!(
+            "(SELECT COALESCE(np.value_number, np.value_text) FROM note_properties np WHERE np.note_id = n.id AND np.name = ?{})",
+            next
+        );
+        params.push(Box::new(name.to_string()));
+        order_sql = format!("{} IS NULL, {} {}, ", expr, expr, direction);
+    }
+
+    Ok((where_sql, order_sql, params))
+}
+
+#[tauri::command]
+pub fn define_property(
+    db: State<'_, Mutex<Connection>>,
+    params: DefinePropertyParams,
+) -> Result<PropertyDefinition, String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    let name = params.name.trim().to_string();
+    if name.is_empty() {
+        return Err("Property name cannot be empty".to_string());
+    }
+    if is_reserved_key(&name) {
+        return Err(format!("'{}' is a reserved frontmatter key", name));
+    }
+    if !PROPERTY_TYPES.contains(&params.property_type.as_str()) {
+        return Err(format!(
+            "Unknown property type '{}' (expected one of: {})",
+            params.property_type,
+            PROPERTY_TYPES.join(", ")
+        ));
+    }
+
+    let options = serde_json::to_string(&params.options.unwrap_or_default()).map_err(|e| e.to_string())?;
+    let now = Utc::now().to_rfc3339();
+    let existing: Option<String> = conn
+        .query_row(
+            "SELECT id FROM property_definitions WHERE COALESCE(workspace_id, '') = COALESCE(?1, '') AND name = ?2",
+            rusqlite::params![params.workspace_id, name],
+            |row| row.get(0),
+        )
+        .ok();
+
+    let id = match existing {
+        Some(id) => {
+            conn.execute(
+                "UPDATE property_definitions SET property_type = ?1, options = ?2, updated_at = ?3 WHERE id = ?4",
+                rusqlite::params![params.property_type, options, now, id],
+            )
+            .map_err(|e| e.to_string())?;
+            id
+        }
+        None => {
+            let id = Uuid::new_v4().to_string();
+            conn.execute(
+                "INSERT INTO property_definitions (id, workspace_id, name, property_type, options, created_at, updated_at) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                rusqlite::params![id, params.workspace_id, name, params.property_type, options, now, now],
+            )
+            .map_err(|e| e.to_string())?;
+            id
+        }
+    };
+
+    reindex_scope(&conn, params.workspace_id.as_deref())?;
+    log_activity(&conn, "user", "property_defined", None, &format!("Defined {} property '{}'", params.property_type, name), "{}");
+
+    conn.query_row(
+        "SELECT id, workspace_id, name, property_type, options, created_at, updated_at FROM property_definitions WHERE id = ?1",
+        [&id],
+        row_to_definition,
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_property_definitions(
+    db: State<'_, Mutex<Connection>>,
+    workspace_id: Option<String>,
+) -> Result<Vec<PropertyDefinition>, String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    if workspace_id.is_some() {
+        return applicable_definitions(&conn, workspace_id.as_deref());
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, workspace_id, name, property_type, options, created_at, updated_at \
+             FROM property_definitions ORDER BY workspace_id IS NOT NULL, workspace_id, name",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], row_to_definition).map_err(|e| e.to_string())?;
+
+    let mut defs = Vec::new();
+    for row in rows {
+        defs.push(row.map_err(|e| e.to_string())?);
+    }
+    Ok(defs)
+}
+
+#[tauri::command]
+pub fn delete_property_definition(
+    db: State<'_, Mutex<Connection>>,
+    id: String,
+) -> Result<(), String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    let (workspace_id, name): (Option<String>, String) = conn
+        .query_row(
+            "SELECT workspace_id, name FROM property_definitions WHERE id = ?1",
+            [&id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|_| format!("Property definition '{}' not found", id))?;
+
+    conn.execute("DELETE FROM property_definitions WHERE id = ?1", [&id])
+        .map_err(|e| e.to_string())?;
+
+    // Values stay in frontmatter; they just stop being indexed
+    reindex_scope(&conn, workspace_id.as_deref())?;
+    log_activity(&conn, "user", "property_deleted", None, &format!("Deleted property '{}'", name), "{}");
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_note_properties(
+    db: State<'_, Mutex<Connection>>,
+    id: String,
+) -> Result<Vec<NoteProperty>, String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT name, property_type, value_text, value_number FROM note_properties WHERE note_id = ?1 ORDER BY name",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([&id], |row| {
+            let property_type: String = row.get(1)?;
+            let value = indexed_to_json(&property_type, row.get(2)?, row.get(3)?);
+            Ok(NoteProperty {
+                name: row.get(0)?,
+                property_type,
+                value,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut props = Vec::new();
+    for row in rows {
+        props.push(row.map_err(|e| e.to_string())?);
+    }
+    Ok(props)
+}
+
+/// Set (or clear, with a null value) a declared property on a note. The value
+/// is written to the note's frontmatter and re-indexed.
+#[tauri::command]
+pub fn set_note_property(
+    db: State<'_, Mutex<Connection>>,
+    id: String,
+    name: String,
+    value: Option<serde_json::Value>,
+    expected_version: Option<i32>,
+) -> Result<Note, String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    let existing = fetch_note(&conn, &id)?;
+
+    if let Some(expected) = expected_version {
+        if existing.version != expected {
+            return Err(format!(
+                "Version conflict: expected {} but found {}. Another agent may have updated this note.",
+                expected, existing.version
+            ));
+        }
+    }
+
+    let def = applicable_definitions(&conn, existing.workspace_id.as_deref())?
+        .into_iter()
+        .find(|d| d.name == name)
+        .ok_or_else(|| format!("Property '{}' is not defined for this note's workspace", name))?;
+
+    let yaml = match value.as_ref() {
+        None | Some(serde_json::Value::Null) => None,
+        Some(v) => Some(json_to_property_yaml(&conn, &def, v)?),
+    };
+    let extra = set_extra_value(existing.extra_frontmatter.as_deref(), &name, yaml);
+
+    conn.execute(
+        "UPDATE notes SET extra_frontmatter = ?1, updated_at = ?2, version = ?3 WHERE id = ?4",
+        rusqlite::params![extra, Utc::now().to_rfc3339(), existing.version + 1, id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    index_note_properties(&conn, &id)?;
+    let _ = refresh_query_results(&conn, QueryChange::Note(&id));
+    let note = fetch_note(&conn, &id)?;
+    sync_to_icloud(&conn, &note);
+    log_activity(&conn, "user", "note_property_set", Some(&id), &format!("Set property '{}' on '{}'", name, note.title), "{}");
+    Ok(note)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::migrations::migrate;
+    use rusqlite::types::{ToSqlOutput, Value};
+
+    fn values(params: &[Box<dyn ToSql>]) -> Vec<Value> {
+        params
+            .iter()
+            .map(|p| match p.to_sql().unwrap() {
+                ToSqlOutput::Borrowed(value) => value.into(),
+                ToSqlOutput::Owned(value) => value,
+                other => panic!("unexpected param {:?}", other),
+            })
+            .collect()
+    }
+
+    fn filter(name: &str, op: &str, value: Option<serde_json::Value>) -> PropertyFilter {
+        PropertyFilter { name: name.to_string(), op: op.to_string(), value }
+    }
+
+    fn definition(property_type: &str) -> PropertyDefinition {
+        PropertyDefinition {
+            id: "d1".to_string(),
+            workspace_id: None,
+            name: "field".to_string(),
+            property_type: property_type.to_string(),
+            options: Vec::new(),
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_property_query_parts() {
+        let filters = vec![
+            filter("progress", "contains", Some(serde_json::json!("50%_done"))),
+            filter("due", "lt", Some(serde_json::json!("2026-01-01"))),
+            filter("done", "eq", Some(serde_json::json!(true))),
+            filter("owner", "is_empty", None),
+            filter("status", "ne", Some(serde_json::json!("open"))),
+        ];
+        let (where_sql, order_sql, params) = property_query_parts(&filters, Some("due"), Some("desc"), 3).unwrap();
+        assert!(where_sql.contains("np.name = ?3 AND np.value_text LIKE '%' || ?4 || '%' ESCAPE '\\'"));
+        assert!(where_sql.contains("np.value_number < CAST(?6 AS REAL) ELSE np.value_text < ?6 END"));
+        assert!(where_sql.contains(" AND NOT EXISTS (SELECT 1 FROM note_properties np WHERE np.note_id = n.id AND np.name = ?9)"));
+        // `ne` matches notes without the property too
+        assert!(where_sql.contains(" AND NOT EXISTS (SELECT 1 FROM note_properties np WHERE np.note_id = n.id AND np.name = ?10 AND"));
+        assert!(order_sql.contains("np.name = ?12) DESC, "));
+        assert_eq!(
+            values(&params),
+            vec![
+                Value::Text("progress".to_string()),
+                Value::Text("50\\%\\_done".to_string()),
+                Value::Text("due".to_string()),
+                Value::Text("2026-01-01".to_string()),
+                Value::Text("done".to_string()),
+                Value::Integer(1),
+                Value::Text("owner".to_string()),
+                Value::Text("status".to_string()),
+                Value::Text("open".to_string()),
+                Value::Text("due".to_string()),
+            ]
+        );
+
+        assert!(property_query_parts(&[filter("x", "like", Some(serde_json::json!("a")))], None, None, 1).is_err());
+        assert!(property_query_parts(&[filter("x", "eq", None)], None, None, 1).is_err());
+    }
+
+    #[test]
+    fn test_coerce_value() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO notes (id, title, content, created_at, updated_at) VALUES ('n1', 'Plan', '', '', '')",
+            [],
+        )
+        .unwrap();
+        let coerce = |property_type: &str, value: Yaml| {
+            coerce_value(&conn, &definition(property_type), &value).map(|v| (v.text, v.number))
+        };
+
+        assert_eq!(coerce("number", Yaml::Integer(3)), Some((Some("3".to_string()), Some(3.0))));
+        assert_eq!(coerce("number", Yaml::String(" 2.5 ".to_string())), Some((Some("2.5".to_string()), Some(2.5))));
+        assert_eq!(coerce("number", Yaml::String("many".to_string())), None);
+        assert_eq!(
+            coerce("date", Yaml::String("2026-03-04".to_string())),
+            Some((Some("2026-03-04".to_string()), None))
+        );
+        assert_eq!(coerce("date", Yaml::String("soon".to_string())), None);
+        assert_eq!(coerce("checkbox", Yaml::String("yes".to_string())), Some((Some("true".to_string()), Some(1.0))));
+        assert_eq!(coerce("note", Yaml::String("[[plan#Goals]]".to_string())), Some((Some("n1".to_string()), None)));
+        // Unknown notes keep the written reference
+        assert_eq!(coerce("note", Yaml::String("Missing".to_string())), Some((Some("Missing".to_string()), None)));
+        assert_eq!(coerce("select", Yaml::String("open".to_string())), Some((Some("open".to_string()), None)));
+    }
+
+    #[test]
+    fn test_resolve_relative_date() {
+        let today = Local::now().date_naive();
+        assert_eq!(resolve_relative_date("today"), today.format("%Y-%m-%d").to_string());
+        assert_eq!(resolve_relative_date("tomorrow"), (today + Duration::days(1)).format("%Y-%m-%d").to_string());
+        assert_eq!(resolve_relative_date("yesterday"), (today - Duration::days(1)).format("%Y-%m-%d").to_string());
+        assert_eq!(resolve_relative_date("Today"), "Today");
+        assert_eq!(resolve_relative_date("2026-01-01"), "2026-01-01");
+    }
+
+    #[test]
+    fn test_parse_checkbox() {
+        assert_eq!(parse_checkbox(&Yaml::Boolean(true)), Some(true));
+        assert_eq!(parse_checkbox(&Yaml::Integer(0)), Some(false));
+        assert_eq!(parse_checkbox(&Yaml::Integer(2)), Some(true));
+        for (text, checked) in [("Yes", Some(true)), ("x", Some(true)), ("N", Some(false)), ("", Some(false)), ("maybe", None)] {
+            assert_eq!(parse_checkbox(&Yaml::String(text.to_string())), checked, "{:?}", text);
+        }
+        assert_eq!(parse_checkbox(&Yaml::Null), None);
+    }
+}
@@ -0,0 +1,631 @@
+use crate::commands::embeds::{expand_embeds, EmbedFormat};
+use crate::commands::export::{
+    export_file_stem, html_document, html_escape, markdown_to_html, tag_file_stem, url_encode,
+    HtmlTarget, ImageExport,
+};
+use crate::commands::notes::{fetch_note, log_activity};
+use crate::commands::queries::evaluate_query;
+use crate::db::models::{HtmlExportTheme, Note, PublishSiteParams, PublishSiteResult};
+use crate::markdown::query::extract_query_blocks;
+use crate::markdown::tags::find_tag_spans;
+use pulldown_cmark::{Event, Parser, TagEnd};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Component, Path};
+use std::sync::Mutex;
+use tauri::State;
+
+/// Kept in the output folder to tell which pages are already up to date.
+const MANIFEST_FILE: &str = ".bruin-site.json";
+const FEED_ENTRIES: usize = 20;
+/// Characters of each note's text kept in the search index.
+const SEARCH_TEXT_LIMIT: usize = 20_000;
+/// How `expand_embeds` marks the note an embed came from in HTML.
+const EMBED_ID_ATTRIBUTE: &str = "class=\"bruin-embed\" data-note-id=\"";
+
+#[derive(Default, Serialize, Deserialize)]
+struct SiteManifest {
+    /// Page per note id.
+    pages: BTreeMap<String, ManifestPage>,
+    tag_files: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManifestPage {
+    file: String,
+    fingerprint: String,
+}
+
+#[derive(Serialize)]
+struct SearchEntry<'a> {
+    title: &'a str,
+    url: &'a str,
+    tags: &'a [String],
+    text: String,
+}
+
+struct Page {
+    note: Note,
+    file: String,
+}
+
+/// Render every published note (of a workspace, if given) into a static site
+/// in `output_dir`: a page per note with backlinks, an index with search, a
+/// page per tag and an Atom feed. Pages whose note, links and backlinks are
+/// unchanged since the last run are not rendered again.
+#[tauri::command]
+pub fn publish_site(
+    db: State<'_, Mutex<Connection>>,
+    params: PublishSiteParams,
+) -> Result<PublishSiteResult, String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    let out = Path::new(&params.output_dir);
+    fs::create_dir_all(out).map_err(|e| format!("Failed to create output dir: {}", e))?;
+
+    let site_title = match params.site_title.as_deref().map(str::trim) {
+        Some(title) if !title.is_empty() => title.to_string(),
+        _ => params
+            .workspace_id
+            .as_deref()
+            .and_then(|id| {
+                conn.query_row("SELECT name FROM workspaces WHERE id = ?1", [id], |row| row.get(0))
+                    .ok()
+            })
+            .unwrap_or_else(|| "Bruin".to_string()),
+    };
+    let base_url = params
+        .base_url
+        .as_deref()
+        .map(|u| u.trim().trim_end_matches('/').to_string())
+        .filter(|u| !u.is_empty());
+
+    let pages = load_pages(&conn, params.workspace_id.as_deref())?;
+    let files: HashMap<String, String> = pages
+        .iter()
+        .map(|p| (p.note.id.clone(), p.file.clone()))
+        .collect();
+    let tags = tag_files(&pages);
+
+    let manifest: SiteManifest = fs::read_to_string(out.join(MANIFEST_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    let config = serde_json::to_string(&(&site_title, &base_url, &params.theme)).map_err(|e| e.to_string())?;
+
+    let images = ImageExport::Copy {
+        dir: out.join("images"),
+        prefix: "images".to_string(),
+    };
+    let target = HtmlTarget::Site {
+        images: &images,
+        pages: &files,
+        tags: &tags,
+    };
+    let nav = format!(
+        "<nav class=\"site-nav\"><a href=\"index.html\">{}</a></nav>\n",
+        html_escape(&site_title)
+    );
+
+    let mut result = PublishSiteResult {
+        output_dir: params.output_dir.clone(),
+        pages_written: 0,
+        pages_unchanged: 0,
+        pages_removed: 0,
+        tag_pages: 0,
+    };
+    let mut new_manifest = SiteManifest::default();
+
+    for page in &pages {
+        let backlinks = backlinks(&conn, &page.note.id, &files)?;
+        let content = expand_embeds(&conn, &page.note.id, &page.note.content, EmbedFormat::Html);
+        let fingerprint = page_fingerprint(&conn, &config, page, &content, &backlinks, &files, &tags)?;
+
+        let unchanged = !params.full_rebuild.unwrap_or(false)
+            && manifest
+                .pages
+                .get(&page.note.id)
+                .is_some_and(|p| p.fingerprint == fingerprint && p.file == page.file)
+            && out.join(&page.file).is_file();
+
+        if unchanged {
+            result.pages_unchanged += 1;
+        } else {
+            let mut body = markdown_to_html(&conn, &content, &target);
+            if !backlinks.is_empty() {
+                body.push_str("<section class=\"backlinks\">\n<h2>Linked from</h2>\n<ul class=\"note-list\">\n");
+                for (title, file) in &backlinks {
+                    body.push_str(&format!(
+                        "<li><a href=\"{}\">{}</a></li>\n",
+                        url_encode(file),
+                        html_escape(title)
+                    ));
+                }
+                body.push_str("</ul>\n</section>\n");
+            }
+            let html = html_document(&page.note.title, &nav, &page_meta(&page.note, "", &tags), &body, params.theme.as_ref());
+            fs::write(out.join(&page.file), html).map_err(|e| format!("Failed to write page: {}", e))?;
+            result.pages_written += 1;
+        }
+
+        new_manifest.pages.insert(
+            page.note.id.clone(),
+            ManifestPage {
+                file: page.file.clone(),
+                fingerprint,
+            },
+        );
+    }
+
+    // Pages of notes that are no longer published, or were renamed
+    for (id, old) in &manifest.pages {
+        if new_manifest.pages.get(id).map(|p| &p.file) != Some(&old.file)
+            && !files.values().any(|f| *f == old.file)
+            && remove_site_file(out, &old.file).map_err(|e| format!("Failed to remove page: {}", e))?
+        {
+            result.pages_removed += 1;
+        }
+    }
+
+    new_manifest.tag_files = write_tag_pages(out, &pages, &tags, &site_title, params.theme.as_ref())?;
+    for old in &manifest.tag_files {
+        if !new_manifest.tag_files.contains(old) {
+            let _ = remove_site_file(&out.join("tags"), old);
+        }
+    }
+    result.tag_pages = new_manifest.tag_files.len();
+
+    write_index(out, &pages, &site_title, params.theme.as_ref())?;
+    write_search_index(out, &pages)?;
+    write_feed(out, &pages, &site_title, base_url.as_deref())?;
+
+    let manifest_json = serde_json::to_string_pretty(&new_manifest).map_err(|e| e.to_string())?;
+    fs::write(out.join(MANIFEST_FILE), manifest_json).map_err(|e| format!("Failed to write manifest: {}", e))?;
+
+    log_activity(
+        &conn,
+        "user",
+        "site_published",
+        None,
+        &format!(
+            "Published '{}' ({} pages, {} updated)",
+            site_title,
+            pages.len(),
+            result.pages_written
+        ),
+        &serde_json::json!({ "output_dir": params.output_dir, "workspace_id": params.workspace_id }).to_string(),
+    );
+
+    Ok(result)
+}
+
+/// Published notes with the file each is written to. File names come from
+/// titles; the older note keeps the plain name when two titles clash.
+fn load_pages(conn: &Connection, workspace_id: Option<&str>) -> Result<Vec<Page>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id FROM notes WHERE state = 'published' AND is_trashed = 0 \
+             AND (?1 IS NULL OR workspace_id = ?1) ORDER BY created_at, id",
+        )
+        .map_err(|e| e.to_string())?;
+    let ids: Vec<String> = stmt
+        .query_map([workspace_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut taken: Vec<String> = vec!["index".to_string()];
+    let mut pages = Vec::new();
+    for id in ids {
+        let note = fetch_note(conn, &id)?;
+        let mut stem = export_file_stem(&note.title);
+        if taken.contains(&stem.to_lowercase()) {
+            stem = format!("{}-{}", stem, &note.id[..8.min(note.id.len())]);
+        }
+        taken.push(stem.to_lowercase());
+        pages.push(Page {
+            file: format!("{}.html", stem),
+            note,
+        });
+    }
+    Ok(pages)
+}
+
+/// Remove the page `name` from `dir`, if it is there. Names come from the
+/// manifest in the output folder, which could have been edited, so anything
+/// but a plain `.html` file name is left alone rather than letting it reach
+/// outside `dir`.
+fn remove_site_file(dir: &Path, name: &str) -> std::io::Result<bool> {
+    let mut components = Path::new(name).components();
+    let plain = matches!((components.next(), components.next()), (Some(Component::Normal(_)), None));
+    let path = dir.join(name);
+    if !plain || !name.ends_with(".html") || !path.is_file() {
+        return Ok(false);
+    }
+    fs::remove_file(path)?;
+    Ok(true)
+}
+
+/// Tag page file names, by tag. Tags whose names slug the same (`a/b` and
+/// `a-b`, `C++` and `C#`) get the later one's name hash appended, as notes
+/// with the same title get their id appended.
+fn tag_files(pages: &[Page]) -> HashMap<String, String> {
+    let tags: BTreeSet<&str> = pages
+        .iter()
+        .flat_map(|p| p.note.tags.iter().map(String::as_str))
+        .collect();
+    let mut taken: Vec<String> = Vec::new();
+    let mut files = HashMap::new();
+    for tag in tags {
+        let mut stem = tag_file_stem(tag);
+        if taken.contains(&stem.to_lowercase()) {
+            let hash = format!("{:x}", Sha256::digest(tag.as_bytes()));
+            stem = format!("{}-{}", stem, &hash[..8]);
+        }
+        taken.push(stem.to_lowercase());
+        files.insert(tag.to_string(), format!("{}.html", stem));
+    }
+    files
+}
+
+/// Published notes linking to `note_id`, as (title, file), by title.
+fn backlinks(
+    conn: &Connection,
+    note_id: &str,
+    files: &HashMap<String, String>,
+) -> Result<Vec<(String, String)>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT DISTINCT n.id, n.title FROM note_links nl \
+             JOIN notes n ON n.id = nl.source_note_id \
+             WHERE nl.target_note_id = ?1 ORDER BY n.title",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([note_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| e.to_string())?;
+    Ok(rows
+        .filter_map(|r| r.ok())
+        .filter_map(|(id, title)| files.get(&id).map(|file| (title, file.clone())))
+        .collect())
+}
+
+/// Everything a page's HTML depends on: the note with every embed expanded
+/// (nested ones included), the results of its queries, the titles and
+/// published state of the notes linked from it or its embeds, the pages of
+/// its tags, its backlinks and the site settings.
+fn page_fingerprint(
+    conn: &Connection,
+    config: &str,
+    page: &Page,
+    content: &str,
+    backlinks: &[(String, String)],
+    files: &HashMap<String, String>,
+    tags: &HashMap<String, String>,
+) -> Result<String, String> {
+    let mut hasher = Sha256::new();
+    for part in [config, &page.note.id, &page.note.updated_at, &page.file, content] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+
+    for source in extract_query_blocks(content) {
+        let result = serde_json::to_string(&evaluate_query(conn, &source)).map_err(|e| e.to_string())?;
+        hasher.update(result.as_bytes());
+        hasher.update([0]);
+    }
+
+    // Embedded notes are marked with their id in the expanded content
+    let mut note_ids = vec![page.note.id.as_str()];
+    for (at, _) in content.match_indices(EMBED_ID_ATTRIBUTE) {
+        let rest = &content[at + EMBED_ID_ATTRIBUTE.len()..];
+        if let Some(id) = rest.split('"').next().filter(|id| !note_ids.contains(id)) {
+            note_ids.push(id);
+        }
+    }
+    let mut stmt = conn
+        .prepare(
+            "SELECT n.id, n.title FROM note_links nl \
+             JOIN notes n ON n.id = nl.target_note_id \
+             WHERE nl.source_note_id = ?1 ORDER BY n.id",
+        )
+        .map_err(|e| e.to_string())?;
+    for note_id in note_ids {
+        let links = stmt
+            .query_map([note_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+            .map_err(|e| e.to_string())?;
+        for (id, title) in links.filter_map(|r| r.ok()) {
+            let file = files.get(&id).map(String::as_str).unwrap_or("");
+            for part in [id.as_str(), title.as_str(), file] {
+                hasher.update(part.as_bytes());
+                hasher.update([0]);
+            }
+        }
+    }
+    let mut linked_tags: Vec<String> = find_tag_spans(content).into_iter().map(|t| t.name).collect();
+    linked_tags.extend(page.note.tags.iter().cloned());
+    linked_tags.sort();
+    linked_tags.dedup();
+    for tag in &linked_tags {
+        let file = tags.get(tag).map(String::as_str).unwrap_or("");
+        for part in [tag.as_str(), file] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+    }
+    for (title, file) in backlinks {
+        hasher.update(title.as_bytes());
+        hasher.update(file.as_bytes());
+        hasher.update([0]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Date and tag links shown under a page title. `root` is the path back to
+/// the site root from the page.
+fn page_meta(note: &Note, root: &str, tags: &HashMap<String, String>) -> String {
+    let mut meta = format!("Updated {}", html_escape(date(&note.updated_at)));
+    for tag in &note.tags {
+        let Some(file) = tags.get(tag) else { continue };
+        meta.push_str(&format!(
+            " <a class=\"tag\" href=\"{}tags/{}\">#{}</a>",
+            root,
+            url_encode(file),
+            html_escape(tag)
+        ));
+    }
+    meta
+}
+
+fn date(timestamp: &str) -> &str {
+    timestamp.get(..10).unwrap_or(timestamp)
+}
+
+fn note_list(pages: &[&Page], root: &str) -> String {
+    let mut html = String::from("<ul class=\"note-list\">\n");
+    for page in pages {
+        html.push_str(&format!(
+            "<li><a href=\"{}{}\">{}</a><span class=\"date\">{}</span></li>\n",
+            root,
+            url_encode(&page.file),
+            html_escape(&page.note.title),
+            html_escape(date(&page.note.updated_at))
+        ));
+    }
+    html.push_str("</ul>\n");
+    html
+}
+
+fn sorted_by_title(pages: &[Page]) -> Vec<&Page> {
+    let mut sorted: Vec<&Page> = pages.iter().collect();
+    sorted.sort_by_key(|p| p.note.title.to_lowercase());
+    sorted
+}
+
+/// Write the page named in `tags` for every tag on a published note.
+/// Returns the file names written.
+fn write_tag_pages(
+    out: &Path,
+    pages: &[Page],
+    tags: &HashMap<String, String>,
+    site_title: &str,
+    theme: Option<&HtmlExportTheme>,
+) -> Result<Vec<String>, String> {
+    let mut by_tag: BTreeMap<&str, Vec<&Page>> = BTreeMap::new();
+    for page in sorted_by_title(pages) {
+        for tag in &page.note.tags {
+            by_tag.entry(tag.as_str()).or_default().push(page);
+        }
+    }
+
+    let dir = out.join("tags");
+    if !by_tag.is_empty() {
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create tags dir: {}", e))?;
+    }
+    let nav = format!(
+        "<nav class=\"site-nav\"><a href=\"../index.html\">{}</a></nav>\n",
+        html_escape(site_title)
+    );
+    let mut written = Vec::new();
+    for (tag, tagged) in by_tag {
+        let file = tags[tag].clone();
+        let meta = format!("{} notes", tagged.len());
+        let html = html_document(&format!("#{}", tag), &nav, &meta, &note_list(&tagged, "../"), theme);
+        fs::write(dir.join(&file), html).map_err(|e| format!("Failed to write tag page: {}", e))?;
+        written.push(file);
+    }
+    Ok(written)
+}
+
+const SEARCH_SCRIPT: &str = r#"<script src="search-index.js"></script>
+<script>
+(function () {
+  var input = document.getElementById("search");
+  var results = document.getElementById("search-results");
+  var all = document.getElementById("all-notes");
+  input.addEventListener("input", function () {
+    var terms = input.value.toLowerCase().split(/\s+/).filter(Boolean);
+    results.innerHTML = "";
+    results.hidden = terms.length === 0;
+    all.hidden = terms.length > 0;
+    (window.BRUIN_SEARCH_INDEX || []).filter(function (page) {
+      var text = (page.title + " " + page.tags.join(" ") + " " + page.text).toLowerCase();
+      return terms.every(function (term) { return text.indexOf(term) !== -1; });
+    }).forEach(function (page) {
+      var li = document.createElement("li");
+      var a = document.createElement("a");
+      a.href = page.url;
+      a.textContent = page.title;
+      li.appendChild(a);
+      results.appendChild(li);
+    });
+  });
+})();
+</script>
+"#;
+
+fn write_index(
+    out: &Path,
+    pages: &[Page],
+    site_title: &str,
+    theme: Option<&HtmlExportTheme>,
+) -> Result<(), String> {
+    let mut body = String::from(
+        "<input id=\"search\" type=\"search\" placeholder=\"Search\" autocomplete=\"off\">\n\
+         <ul id=\"search-results\" class=\"note-list\" hidden></ul>\n<div id=\"all-notes\">\n",
+    );
+    body.push_str(&note_list(&sorted_by_title(pages), ""));
+    body.push_str("</div>\n");
+    body.push_str(SEARCH_SCRIPT);
+
+    let meta = format!("{} notes &middot; <a href=\"feed.xml\">Feed</a>", pages.len());
+    let html = html_document(site_title, "", &meta, &body, theme);
+    fs::write(out.join("index.html"), html).map_err(|e| format!("Failed to write index: {}", e))
+}
+
+/// `search-index.js` rather than JSON, so search also works when the site is
+/// opened from disk, where pages cannot fetch files.
+fn write_search_index(out: &Path, pages: &[Page]) -> Result<(), String> {
+    let entries: Vec<SearchEntry> = pages
+        .iter()
+        .map(|p| SearchEntry {
+            title: &p.note.title,
+            url: &p.file,
+            tags: &p.note.tags,
+            text: plain_text(&p.note.content).chars().take(SEARCH_TEXT_LIMIT).collect(),
+        })
+        .collect();
+    let json = serde_json::to_string(&entries).map_err(|e| e.to_string())?;
+    fs::write(out.join("search-index.js"), format!("window.BRUIN_SEARCH_INDEX = {};\n", json))
+        .map_err(|e| format!("Failed to write search index: {}", e))
+}
+
+/// The text of a note without markdown syntax.
+fn plain_text(markdown: &str) -> String {
+    let mut text = String::new();
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Text(t) | Event::Code(t) => text.push_str(&t),
+            Event::SoftBreak | Event::HardBreak | Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::Item) => {
+                text.push(' ')
+            }
+            _ => {}
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Atom feed of the most recently updated pages.
+fn write_feed(out: &Path, pages: &[Page], site_title: &str, base_url: Option<&str>) -> Result<(), String> {
+    let mut recent: Vec<&Page> = pages.iter().collect();
+    recent.sort_by(|a, b| b.note.updated_at.cmp(&a.note.updated_at));
+    recent.truncate(FEED_ENTRIES);
+
+    let link = |file: &str| match base_url {
+        Some(base) => format!("{}/{}", base, url_encode(file)),
+        None => url_encode(file),
+    };
+    let updated = recent
+        .first()
+        .map(|p| p.note.updated_at.clone())
+        .unwrap_or_else(|| chrono::Utc::now().to_rfc3339());
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!("  <title>{}</title>\n", html_escape(site_title)));
+    xml.push_str(&format!(
+        "  <id>{}</id>\n",
+        html_escape(base_url.unwrap_or("urn:bruin:site"))
+    ));
+    if let Some(base) = base_url {
+        xml.push_str(&format!("  <link href=\"{}/\"/>\n", html_escape(base)));
+        xml.push_str(&format!("  <link rel=\"self\" href=\"{}/feed.xml\"/>\n", html_escape(base)));
+    }
+    xml.push_str(&format!("  <updated>{}</updated>\n", html_escape(&updated)));
+    for page in recent {
+        let summary: String = plain_text(&page.note.content).chars().take(300).collect();
+        xml.push_str("  <entry>\n");
+        xml.push_str(&format!("    <title>{}</title>\n", html_escape(&page.note.title)));
+        xml.push_str(&format!("    <id>urn:bruin:note:{}</id>\n", html_escape(&page.note.id)));
+        xml.push_str(&format!("    <link href=\"{}\"/>\n", html_escape(&link(&page.file))));
+        xml.push_str(&format!("    <published>{}</published>\n", html_escape(&page.note.created_at)));
+        xml.push_str(&format!("    <updated>{}</updated>\n", html_escape(&page.note.updated_at)));
+        for tag in &page.note.tags {
+            xml.push_str(&format!("    <category term=\"{}\"/>\n", html_escape(tag)));
+        }
+        xml.push_str(&format!("    <summary>{}</summary>\n", html_escape(&summary)));
+        xml.push_str("  </entry>\n");
+    }
+    xml.push_str("</feed>\n");
+    fs::write(out.join("feed.xml"), xml).map_err(|e| format!("Failed to write feed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(tags: &[&str]) -> Page {
+        Page {
+            note: Note {
+                id: "note".to_string(),
+                title: "Note".to_string(),
+                content: String::new(),
+                created_at: String::new(),
+                updated_at: String::new(),
+                is_trashed: false,
+                is_pinned: false,
+                word_count: 0,
+                char_count: 0,
+                reading_minutes: 0,
+                file_path: None,
+                sync_hash: None,
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+                state: "published".to_string(),
+                workspace_id: None,
+                version: 1,
+                extra_frontmatter: Default::default(),
+            },
+            file: "Note.html".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_tag_files_are_distinct() {
+        let pages = [page(&["a/b", "a-b", "C++"]), page(&["C#", "rust"])];
+        let files = tag_files(&pages);
+        assert_eq!(files.len(), 5);
+        assert_eq!(files["rust"], "rust.html");
+        assert_eq!(files["C#"], "c.html");
+        assert!(files["C++"].starts_with("c-"));
+        assert_eq!(files["a-b"], "a-b.html");
+        assert!(files["a/b"].starts_with("a-b-"));
+
+        let mut names: Vec<String> = files.values().map(|f| f.to_lowercase()).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), 5);
+        assert_eq!(tag_files(&pages), files);
+    }
+
+    #[test]
+    fn test_remove_site_file_stays_in_dir() {
+        let root = std::env::temp_dir().join(format!("bruin-publish-{}", uuid::Uuid::new_v4()));
+        let site = root.join("site");
+        fs::create_dir_all(&site).unwrap();
+        fs::write(site.join("old.html"), "").unwrap();
+        fs::write(root.join("outside.html"), "").unwrap();
+        fs::write(site.join("keep.txt"), "").unwrap();
+
+        let outside = root.join("outside.html");
+        for name in ["../outside.html", outside.to_str().unwrap(), "keep.txt", "missing.html", ""] {
+            assert!(!remove_site_file(&site, name).unwrap(), "{}", name);
+        }
+        assert!(outside.is_file());
+        assert!(site.join("keep.txt").is_file());
+
+        assert!(remove_site_file(&site, "old.html").unwrap());
+        assert!(!site.join("old.html").exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
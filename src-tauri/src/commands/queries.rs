@@ -0,0 +1,494 @@
+use crate::commands::export::html_escape;
+use crate::commands::properties::resolve_relative_date;
+use crate::commands::search::escape_like;
+use crate::db::models::{NoteQueryResult, QueryResult, QueryRow};
+use crate::markdown::query::{
+    extract_query_blocks, parse_query, Query, QueryKind, QuerySource, QueryValue,
+};
+use crate::markdown::sections::split_link_target;
+use chrono::Utc;
+use rusqlite::types::{ToSql, Value};
+use rusqlite::{Connection, OptionalExtension};
+use std::sync::Mutex;
+use tauri::State;
+
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+
+fn note_field_expr(field: &str) -> Option<&'static str> {
+    Some(match field.to_lowercase().as_str() {
+        "title" => "n.title",
+        "state" => "n.state",
+        "created" | "created_at" => "n.created_at",
+        "updated" | "updated_at" => "n.updated_at",
+        "words" | "word_count" => "n.word_count",
+        "pinned" => "n.is_pinned",
+        "workspace" => "(SELECT w.name FROM workspaces w WHERE w.id = n.workspace_id)",
+        "tags" => "(SELECT group_concat(t.name, ', ') FROM note_tags nt JOIN tags t ON t.id = nt.tag_id WHERE nt.note_id = n.id)",
+        _ => return None,
+    })
+}
+
+fn task_field_expr(field: &str) -> Option<&'static str> {
+    Some(match field.to_lowercase().as_str() {
+        "title" => "k.title",
+        "description" => "k.description",
+        "status" => "k.status",
+        "priority" => "k.priority",
+        "due" | "due_date" => "k.due_date",
+        "created" | "created_at" => "k.created_at",
+        "updated" | "updated_at" => "k.updated_at",
+        "agent" | "assigned" => "(SELECT a.name FROM agents a WHERE a.id = k.assigned_agent_id)",
+        "note" => "(SELECT n.title FROM notes n WHERE n.id = k.linked_note_id)",
+        _ => return None,
+    })
+}
+
+/// Holds the SQL params of a query being built, handing out placeholders.
+struct SqlParams(Vec<Box<dyn ToSql>>);
+
+impl SqlParams {
+    fn push(&mut self, value: Box<dyn ToSql>) -> String {
+        self.0.push(value);
+        format!("?{}", self.0.len())
+    }
+
+    fn push_value(&mut self, value: &QueryValue) -> String {
+        match value {
+            QueryValue::Text(s) => self.push(Box::new(resolve_relative_date(s))),
+            QueryValue::Number(n) => self.push(Box::new(*n)),
+            QueryValue::Bool(b) => self.push(Box::new(*b as i64)),
+        }
+    }
+
+    /// A LIKE pattern matching text that contains `value`, for use with
+    /// `ESCAPE '\'`.
+    fn push_contains(&mut self, value: &QueryValue) -> String {
+        let text = match value {
+            QueryValue::Text(s) => s.clone(),
+            QueryValue::Number(n) => n.to_string(),
+            QueryValue::Bool(b) => (*b as i64).to_string(),
+        };
+        self.push(Box::new(format!("%{}%", escape_like(&text))))
+    }
+}
+
+/// SQL expression for a query field. Unknown note fields are read from the
+/// note's typed properties; tasks have no properties.
+fn field_expr(kind: &QueryKind, field: &str, params: &mut SqlParams) -> Result<String, String> {
+    if *kind == QueryKind::Tasks {
+        return task_field_expr(field)
+            .map(String::from)
+            .ok_or_else(|| format!("Unknown task field '{}'", field));
+    }
+    if let Some(expr) = note_field_expr(field) {
+        return Ok(expr.to_string());
+    }
+    let name = params.push(Box::new(field.to_string()));
+    Ok(format!(
+        "(SELECT COALESCE(np.value_number, np.value_text) FROM note_properties np WHERE np.note_id = n.id AND np.name = {})",
+        name
+    ))
+}
+
+fn tag_tree_match(params: &mut SqlParams, tag: &str) -> String {
+    let p = params.push(Box::new(tag.to_string()));
+    format!("(t.name = {p} OR substr(t.name, 1, length({p}) + 1) = {p} || '/')", p = p)
+}
+
+fn resolve_note_id(conn: &Connection, target: &str) -> Option<String> {
+    let (target, _) = split_link_target(target);
+    conn.query_row(
+        "SELECT id FROM notes WHERE (id = ?1 OR title = ?1 COLLATE NOCASE) AND is_trashed = 0 ORDER BY id = ?1 DESC LIMIT 1",
+        [target],
+        |row| row.get(0),
+    )
+    .ok()
+}
+
+/// SQL for `query`, limited to rows whose `only.0` column is `only.1` when
+/// given.
+fn build_sql(
+    conn: &Connection,
+    query: &Query,
+    only: Option<(&str, &str)>,
+    params: &mut SqlParams,
+) -> Result<(String, Vec<String>), String> {
+    let is_tasks = query.kind == QueryKind::Tasks;
+    let mut conditions: Vec<String> = Vec::new();
+    if let Some((column, id)) = only {
+        conditions.push(format!("{} = {}", column, params.push(Box::new(id.to_string()))));
+    }
+
+    // Sources are alternatives
+    let mut sources = Vec::new();
+    for source in &query.sources {
+        sources.push(match (source, is_tasks) {
+            (QuerySource::Tag(tag), false) => format!(
+                "EXISTS (SELECT 1 FROM note_tags nt JOIN tags t ON t.id = nt.tag_id WHERE nt.note_id = n.id AND {})",
+                tag_tree_match(params, tag)
+            ),
+            (QuerySource::Tag(tag), true) => format!(
+                "k.linked_note_id IN (SELECT nt.note_id FROM note_tags nt JOIN tags t ON t.id = nt.tag_id WHERE {})",
+                tag_tree_match(params, tag)
+            ),
+            (QuerySource::Note(target), _) => match resolve_note_id(conn, target) {
+                Some(id) => {
+                    let p = params.push(Box::new(id));
+                    if is_tasks {
+                        format!("k.linked_note_id = {}", p)
+                    } else {
+                        format!(
+                            "EXISTS (SELECT 1 FROM note_links l WHERE l.source_note_id = n.id AND l.target_note_id = {})",
+                            p
+                        )
+                    }
+                }
+                None => "0".to_string(),
+            },
+        });
+    }
+    if !sources.is_empty() {
+        conditions.push(format!("({})", sources.join(" OR ")));
+    }
+
+    for cond in &query.conditions {
+        // `tag = x` matches x and its children rather than comparing a column
+        if !is_tasks && cond.field.eq_ignore_ascii_case("tag") {
+            let QueryValue::Text(ref tag) = cond.value else {
+                return Err("tag conditions need a tag name".to_string());
+            };
+            let negate = match cond.op.as_str() {
+                "=" => "",
+                "!=" => "NOT ",
+                other => return Err(format!("Operator '{}' is not supported for tag", other)),
+            };
+            conditions.push(format!(
+                "{}EXISTS (SELECT 1 FROM note_tags nt JOIN tags t ON t.id = nt.tag_id WHERE nt.note_id = n.id AND {})",
+                negate,
+                tag_tree_match(params, tag)
+            ));
+            continue;
+        }
+
+        let expr = field_expr(&query.kind, &cond.field, params)?;
+        if cond.op == "~" {
+            let pattern = params.push_contains(&cond.value);
+            conditions.push(format!("{} LIKE {} ESCAPE '\\'", expr, pattern));
+            continue;
+        }
+        let value = params.push_value(&cond.value);
+        let collate = if matches!(cond.value, QueryValue::Text(_)) { " COLLATE NOCASE" } else { "" };
+        conditions.push(match cond.op.as_str() {
+            "!=" => format!("({} IS NULL OR {} != {}{})", expr, expr, value, collate),
+            "=" => format!("{} = {}{}", expr, value, collate),
+            op @ ("<" | "<=" | ">" | ">=") => format!("{} {} {}", expr, op, value),
+            other => return Err(format!("Unknown operator '{}'", other)),
+        });
+    }
+
+    let (table, trashed_filter) = if is_tasks {
+        ("tasks k", None)
+    } else {
+        ("notes n", Some("n.is_trashed = 0"))
+    };
+    let mut where_parts: Vec<String> = trashed_filter.into_iter().map(String::from).collect();
+    where_parts.extend(conditions);
+    let where_clause = if where_parts.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", where_parts.join(" AND "))
+    };
+
+    let columns: Vec<String> = match query.kind {
+        QueryKind::List => vec![],
+        QueryKind::Table if query.fields.is_empty() => vec!["state".to_string(), "updated".to_string()],
+        QueryKind::Tasks if query.fields.is_empty() => {
+            vec!["status".to_string(), "priority".to_string(), "due".to_string()]
+        }
+        _ => query.fields.clone(),
+    };
+    let mut select = if is_tasks {
+        vec!["k.id".to_string(), "k.linked_note_id".to_string(), "k.title".to_string()]
+    } else {
+        vec!["n.id".to_string(), "n.id".to_string(), "n.title".to_string()]
+    };
+    for column in &columns {
+        select.push(field_expr(&query.kind, column, params)?);
+    }
+
+    let order = match query.sort {
+        Some((ref field, descending)) => {
+            let expr = field_expr(&query.kind, field, params)?;
+            format!("{} IS NULL, {} {}", expr, expr, if descending { "DESC" } else { "ASC" })
+        }
+        None if is_tasks => "CASE k.priority WHEN 'urgent' THEN 0 WHEN 'high' THEN 1 WHEN 'medium' THEN 2 WHEN 'low' THEN 3 END, k.updated_at DESC".to_string(),
+        None => "n.updated_at DESC".to_string(),
+    };
+    let limit = params.push(Box::new(query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as i64));
+
+    let sql = format!(
+        "SELECT {} FROM {} {} ORDER BY {} LIMIT {}",
+        select.join(", "),
+        table,
+        where_clause,
+        order,
+        limit
+    );
+    Ok((sql, columns))
+}
+
+fn sql_to_json(value: Value) -> serde_json::Value {
+    match value {
+        Value::Integer(i) => serde_json::Value::from(i),
+        Value::Real(f) => serde_json::Value::from(f),
+        Value::Text(s) => serde_json::Value::String(s),
+        Value::Null | Value::Blob(_) => serde_json::Value::Null,
+    }
+}
+
+fn run_parsed_query(
+    conn: &Connection,
+    query: &Query,
+    only: Option<(&str, &str)>,
+) -> Result<(Vec<String>, Vec<QueryRow>), String> {
+    let mut params = SqlParams(Vec::new());
+    let (sql, columns) = build_sql(conn, query, only, &mut params)?;
+
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let param_refs: Vec<&dyn ToSql> = params.0.iter().map(|p| p.as_ref()).collect();
+    let rows = stmt
+        .query_map(param_refs.as_slice(), |row| {
+            let mut values = Vec::with_capacity(columns.len());
+            for i in 0..columns.len() {
+                values.push(sql_to_json(row.get(i + 3)?));
+            }
+            Ok(QueryRow {
+                id: row.get(0)?,
+                note_id: row.get(1)?,
+                title: row.get(2)?,
+                values,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    let rows = rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+    Ok((columns, rows))
+}
+
+/// Evaluate a query's source text. Parse and evaluation errors are reported
+/// in `QueryResult::error` so they can be shown in place of the results.
+pub(crate) fn evaluate_query(conn: &Connection, source: &str) -> QueryResult {
+    let parsed = parse_query(source);
+    let kind = match parsed {
+        Ok(ref q) => match q.kind {
+            QueryKind::List => "list",
+            QueryKind::Table => "table",
+            QueryKind::Tasks => "tasks",
+        },
+        Err(_) => "list",
+    };
+    match parsed.and_then(|q| run_parsed_query(conn, &q, None)) {
+        Ok((columns, rows)) => QueryResult {
+            kind: kind.to_string(),
+            columns,
+            rows,
+            error: None,
+        },
+        Err(e) => QueryResult {
+            kind: kind.to_string(),
+            columns: vec![],
+            rows: vec![],
+            error: Some(e),
+        },
+    }
+}
+
+/// Re-evaluate the query blocks in a note's content and store the results,
+/// so agents reading the database see the same data the editor shows.
+pub(crate) fn sync_note_queries(conn: &Connection, note_id: &str, content: &str) -> Result<(), String> {
+    conn.execute("DELETE FROM note_query_results WHERE note_id = ?1", [note_id])
+        .map_err(|e| e.to_string())?;
+
+    let now = Utc::now().to_rfc3339();
+    for (index, source) in extract_query_blocks(content).iter().enumerate() {
+        let result = evaluate_query(conn, source);
+        let json = serde_json::to_string(&result).map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO note_query_results (note_id, block_index, query, result, evaluated_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![note_id, index as i64, source, json, now],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// What a write changed, which decides the stored query results to refresh.
+pub(crate) enum QueryChange<'a> {
+    /// A note, with its tags, properties, links and checklist tasks.
+    Note(&'a str),
+    /// A task, and the note it is linked to.
+    Task(&'a str),
+    /// Anything, e.g. after files were synced or notes imported.
+    All,
+}
+
+/// Re-evaluate the stored query blocks whose results `change` can affect:
+/// those that listed the changed note or task, or would list it now.
+/// Called after writes that can change what a query returns.
+pub(crate) fn refresh_query_results(conn: &Connection, change: QueryChange) -> Result<(), String> {
+    let linked_note: Option<String> = match change {
+        QueryChange::Task(id) => conn
+            .query_row("SELECT linked_note_id FROM tasks WHERE id = ?1", [id], |row| row.get(0))
+            .optional()
+            .map_err(|e| e.to_string())?
+            .flatten(),
+        _ => None,
+    };
+
+    let mut stmt = conn
+        .prepare("SELECT note_id, block_index, query, result FROM note_query_results")
+        .map_err(|e| e.to_string())?;
+    let blocks: Vec<(String, i64, String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let now = Utc::now().to_rfc3339();
+    for (note_id, index, source, stored) in blocks {
+        if !is_affected(conn, &source, &stored, &change, linked_note.as_deref()) {
+            continue;
+        }
+        let json = serde_json::to_string(&evaluate_query(conn, &source)).map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE note_query_results SET result = ?1, evaluated_at = ?2 WHERE note_id = ?3 AND block_index = ?4",
+            rusqlite::params![json, now, note_id, index],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Whether the query `source`, whose stored result is `stored`, can return
+/// something else after `change`.
+fn is_affected(conn: &Connection, source: &str, stored: &str, change: &QueryChange, linked_note: Option<&str>) -> bool {
+    let Ok(query) = parse_query(source) else {
+        return false;
+    };
+    // Notes named by title can be renamed, created or trashed
+    if query.sources.iter().any(|s| matches!(s, QuerySource::Note(_))) {
+        return true;
+    }
+    let Ok(stored) = serde_json::from_str::<QueryResult>(stored) else {
+        return true;
+    };
+    let is_tasks = query.kind == QueryKind::Tasks;
+    let (column, id, listed) = match (change, is_tasks) {
+        (QueryChange::All, _) => return true,
+        (QueryChange::Note(id), false) => ("n.id", *id, stored.rows.iter().any(|r| r.note_id.as_deref() == Some(id))),
+        (QueryChange::Note(id), true) => {
+            ("k.linked_note_id", *id, stored.rows.iter().any(|r| r.note_id.as_deref() == Some(id)))
+        }
+        (QueryChange::Task(id), true) => ("k.id", *id, stored.rows.iter().any(|r| r.id == *id)),
+        (QueryChange::Task(_), false) => match linked_note {
+            Some(id) => ("n.id", id, stored.rows.iter().any(|r| r.note_id.as_deref() == Some(id))),
+            None => return false,
+        },
+    };
+    listed || run_parsed_query(conn, &query, Some((column, id))).map_or(true, |(_, rows)| !rows.is_empty())
+}
+
+fn json_cell(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(s) => html_escape(s),
+        other => html_escape(&other.to_string()),
+    }
+}
+
+/// Render query results as an HTML list or table for export.
+pub(crate) fn render_query_html(result: &QueryResult) -> String {
+    if let Some(ref error) = result.error {
+        return format!("<div class=\"bruin-query bruin-query-error\">Query error: {}</div>\n", html_escape(error));
+    }
+    if result.rows.is_empty() {
+        return "<p class=\"bruin-query bruin-query-empty\">No results</p>\n".to_string();
+    }
+
+    let mut html = String::new();
+    if result.kind == "list" {
+        html.push_str("<ul class=\"bruin-query\">\n");
+        for row in &result.rows {
+            html.push_str(&format!(
+                "<li data-note-id=\"{}\">{}</li>\n",
+                html_escape(row.note_id.as_deref().unwrap_or("")),
+                html_escape(&row.title)
+            ));
+        }
+        html.push_str("</ul>\n");
+        return html;
+    }
+
+    html.push_str("<table class=\"bruin-query\">\n<thead><tr><th>title</th>");
+    for column in &result.columns {
+        html.push_str(&format!("<th>{}</th>", html_escape(column)));
+    }
+    html.push_str("</tr></thead>\n<tbody>\n");
+    for row in &result.rows {
+        html.push_str(&format!(
+            "<tr data-note-id=\"{}\"><td>{}</td>",
+            html_escape(row.note_id.as_deref().unwrap_or("")),
+            html_escape(&row.title)
+        ));
+        for value in &row.values {
+            html.push_str(&format!("<td>{}</td>", json_cell(value)));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</tbody>\n</table>\n");
+    html
+}
+
+#[tauri::command]
+pub fn run_query(db: State<'_, Mutex<Connection>>, query: String) -> Result<QueryResult, String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    Ok(evaluate_query(&conn, &query))
+}
+
+/// Evaluate the query blocks in a note and return their results in block order.
+#[tauri::command]
+pub fn get_note_query_results(
+    db: State<'_, Mutex<Connection>>,
+    id: String,
+) -> Result<Vec<NoteQueryResult>, String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    let content: String = conn
+        .query_row("SELECT content FROM notes WHERE id = ?1", [&id], |row| row.get(0))
+        .map_err(|e| format!("Note not found: {}", e))?;
+    sync_note_queries(&conn, &id, &content)?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT block_index, query, result, evaluated_at FROM note_query_results WHERE note_id = ?1 ORDER BY block_index",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([&id], |row| {
+            let result: String = row.get(2)?;
+            Ok(NoteQueryResult {
+                block_index: row.get(0)?,
+                query: row.get(1)?,
+                result: serde_json::from_str(&result).unwrap_or(QueryResult {
+                    kind: "list".to_string(),
+                    columns: vec![],
+                    rows: vec![],
+                    error: Some("Stored result is unreadable".to_string()),
+                }),
+                evaluated_at: row.get(3)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
@@ -1,5 +1,7 @@
-use crate::commands::notes::{batch_fetch_tags, fetch_note_tags};
+use crate::commands::notes::{batch_fetch_tags, batch_fetch_thumbnails, fetch_note_tags};
+use crate::commands::settings::read_setting;
 use crate::db::models::*;
+use chrono::Utc;
 use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
 use std::sync::Mutex;
@@ -13,6 +15,31 @@ pub fn search_notes(
     let conn = db.lock().map_err(|e| e.to_string())?;
     let limit = params.limit.unwrap_or(20);
 
+    // The trigram tokenizer cannot match anything shorter than three
+    // characters, such as most two-character Chinese words
+    let terms = plain_terms(&params.query);
+    let mut items = if search_tokenizer(&conn) == "trigram" && terms.iter().any(|t| t.chars().count() < 3) {
+        substring_search(&conn, &terms, limit)?
+    } else {
+        match_search(&conn, &params.query, limit)?
+    };
+
+    // Batch-fetch tags (fixes N+1)
+    let note_ids: Vec<String> = items.iter().map(|n| n.id.clone()).collect();
+    let tags_map = batch_fetch_tags(&conn, &note_ids)?;
+    let thumbnails = batch_fetch_thumbnails(&conn, &note_ids)?;
+    for item in &mut items {
+        if let Some(tags) = tags_map.get(&item.id) {
+            item.tags = tags.clone();
+        }
+        item.thumbnail = thumbnails.get(&item.id).cloned();
+    }
+
+    Ok(items)
+}
+
+/// Notes matching a full-text query, in notes or their attachments.
+fn match_search(conn: &Connection, query: &str, limit: i64) -> Result<Vec<NoteListItem>, String> {
     let mut stmt = conn
         .prepare(
             "SELECT n.id, n.title, snippet(notes_fts, 1, '<mark>', '</mark>', '...', 32) as preview, \
@@ -26,7 +53,7 @@ pub fn search_notes(
         .map_err(|e| e.to_string())?;
 
     let rows = stmt
-        .query_map(rusqlite::params![params.query, limit], |row| {
+        .query_map(rusqlite::params![query, limit], |row| {
             Ok(NoteListItem {
                 id: row.get(0)?,
                 title: row.get(1)?,
@@ -38,6 +65,8 @@ pub fn search_notes(
                 tags: vec![],
                 state: "draft".to_string(),
                 workspace_id: row.get(7)?,
+                attachment_match: None,
+                thumbnail: None,
             })
         })
         .map_err(|e| e.to_string())?;
@@ -48,18 +77,218 @@ pub fn search_notes(
         items.push(item);
     }
 
-    // Batch-fetch tags (fixes N+1)
-    let note_ids: Vec<String> = items.iter().map(|n| n.id.clone()).collect();
-    let tags_map = batch_fetch_tags(&conn, &note_ids)?;
-    for item in &mut items {
-        if let Some(tags) = tags_map.get(&item.id) {
-            item.tags = tags.clone();
+    // Text extracted from attachments: the best match per note is shown on
+    // the note, and notes matched only there follow the others
+    let mut stmt = conn
+        .prepare(
+            "SELECT n.id, n.title, n.updated_at, n.is_pinned, n.is_trashed, n.word_count, n.workspace_id, \
+             a.id, a.original_name, fts.page, snippet(attachment_fts, 2, '<mark>', '</mark>', '...', 32) \
+             FROM attachment_fts fts \
+             JOIN attachments a ON a.id = fts.attachment_id \
+             JOIN note_attachments na ON na.attachment_id = a.id \
+             JOIN notes n ON n.id = na.note_id \
+             WHERE attachment_fts MATCH ?1 AND n.is_trashed = 0 \
+             ORDER BY rank \
+             LIMIT ?2",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params![query, limit], |row| {
+            let attachment_match = AttachmentMatch {
+                attachment_id: row.get(7)?,
+                name: row.get(8)?,
+                page: row.get(9)?,
+                snippet: row.get(10)?,
+            };
+            Ok(NoteListItem {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                preview: attachment_match.snippet.clone(),
+                updated_at: row.get(2)?,
+                is_pinned: row.get::<_, i32>(3)? != 0,
+                is_trashed: row.get::<_, i32>(4)? != 0,
+                word_count: row.get(5)?,
+                tags: vec![],
+                state: "draft".to_string(),
+                workspace_id: row.get(6)?,
+                attachment_match: Some(attachment_match),
+                thumbnail: None,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    for row in rows {
+        let item = row.map_err(|e| e.to_string())?;
+        match items.iter_mut().find(|existing| existing.id == item.id) {
+            Some(existing) => {
+                if existing.attachment_match.is_none() {
+                    existing.attachment_match = item.attachment_match;
+                }
+            }
+            None => items.push(item),
         }
     }
-
+    items.truncate(limit as usize);
     Ok(items)
 }
 
+/// The words of a search query without its full-text syntax: quotes,
+/// prefix stars and AND/OR/NOT.
+fn plain_terms(query: &str) -> Vec<String> {
+    query
+        .split_whitespace()
+        .filter(|t| !matches!(*t, "AND" | "OR" | "NOT"))
+        .map(|t| t.trim_matches(|c| c == '"' || c == '*' || c == '(' || c == ')').to_string())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+/// `text` with the LIKE wildcards escaped, for use with `ESCAPE '\'`.
+pub(crate) fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// Notes containing every term, found by scanning rather than through the
+/// index. Attachments are not searched this way.
+fn substring_search(conn: &Connection, terms: &[String], limit: i64) -> Result<Vec<NoteListItem>, String> {
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+    let clauses: Vec<String> = (1..=terms.len())
+        .map(|i| format!("AND (title LIKE ?{0} ESCAPE '\\' OR content LIKE ?{0} ESCAPE '\\')", i))
+        .collect();
+    let sql = format!(
+        "SELECT id, title, content, updated_at, is_pinned, is_trashed, word_count, workspace_id \
+         FROM notes WHERE is_trashed = 0 {} ORDER BY is_pinned DESC, updated_at DESC LIMIT {}",
+        clauses.join(" "),
+        limit
+    );
+    let patterns: Vec<String> = terms
+        .iter()
+        .map(|t| format!("%{}%", escape_like(t)))
+        .collect();
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(&patterns), |row| {
+            let content: String = row.get(2)?;
+            Ok(NoteListItem {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                preview: substring_snippet(&content, &terms[0]),
+                updated_at: row.get(3)?,
+                is_pinned: row.get::<_, i32>(4)? != 0,
+                is_trashed: row.get::<_, i32>(5)? != 0,
+                word_count: row.get(6)?,
+                tags: vec![],
+                state: "draft".to_string(),
+                workspace_id: row.get(7)?,
+                attachment_match: None,
+                thumbnail: None,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+/// Text around the first occurrence of `term`, marked the way FTS5
+/// `snippet()` marks matches.
+fn substring_snippet(content: &str, term: &str) -> String {
+    const BEFORE: usize = 20;
+    const AFTER: usize = 60;
+    let lower = content.to_lowercase();
+    // Lowercasing can change lengths; then only exact case is found
+    let found = match lower.len() == content.len() {
+        true => lower.find(&term.to_lowercase()),
+        false => content.find(term),
+    };
+    let Some(at) = found else {
+        return content.chars().take(BEFORE + AFTER).collect();
+    };
+    let end = at + term.len();
+    let start = content[..at].char_indices().rev().nth(BEFORE - 1).map_or(0, |(i, _)| i);
+    let stop = content[end..].char_indices().nth(AFTER).map_or(content.len(), |(i, _)| end + i);
+    format!(
+        "{}{}<mark>{}</mark>{}{}",
+        if start > 0 { "..." } else { "" },
+        &content[start..at],
+        &content[at..end],
+        &content[end..stop],
+        if stop < content.len() { "..." } else { "" },
+    )
+}
+
+/// FTS5 tokenizers the search indexes can be built with.
+const TOKENIZERS: [&str; 2] = ["unicode61", "trigram"];
+
+/// The tokenizer the `search_tokenizer` setting asks for.
+pub(crate) fn search_tokenizer(conn: &Connection) -> &'static str {
+    let setting = read_setting(conn, "search_tokenizer");
+    TOKENIZERS.into_iter().find(|t| setting.as_deref().map(str::trim) == Some(*t)).unwrap_or(TOKENIZERS[0])
+}
+
+/// Rebuild the search indexes if they were built with another tokenizer
+/// than the setting asks for, returning whether they were. Note text is
+/// reindexed right away; attachment text is extracted again in the
+/// background.
+pub(crate) fn rebuild_search_index(conn: &Connection) -> Result<bool, String> {
+    let tokenizer = search_tokenizer(conn);
+    let sql: String = conn
+        .query_row("SELECT sql FROM sqlite_master WHERE name = 'notes_fts'", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    let current = TOKENIZERS.into_iter().rev().find(|t| sql.contains(t)).unwrap_or(TOKENIZERS[0]);
+    if current == tokenizer {
+        return Ok(false);
+    }
+    conn.execute_batch(&format!(
+        "
+        BEGIN;
+        DROP TABLE notes_fts;
+        CREATE VIRTUAL TABLE notes_fts USING fts5(
+            title,
+            content,
+            content=notes,
+            content_rowid=rowid,
+            tokenize='{0}'
+        );
+        INSERT INTO notes_fts(notes_fts) VALUES('rebuild');
+        DROP TABLE attachment_fts;
+        CREATE VIRTUAL TABLE attachment_fts USING fts5(
+            attachment_id UNINDEXED,
+            page UNINDEXED,
+            content,
+            tokenize='{0}'
+        );
+        UPDATE attachments SET indexed_at = NULL, index_error = NULL;
+        COMMIT;
+        ",
+        tokenizer
+    ))
+    .map_err(|e| {
+        let _ = conn.execute_batch("ROLLBACK");
+        e.to_string()
+    })?;
+    log::info!("Rebuilt the search index with the {} tokenizer", tokenizer);
+    Ok(true)
+}
+
+/// Switch the search index tokenizer and rebuild the index: `trigram`
+/// matches any run of three or more characters, for Chinese, Japanese and
+/// other text without spaces between words; `unicode61` matches words.
+#[tauri::command]
+pub fn set_search_tokenizer(db: State<'_, Mutex<Connection>>, tokenizer: String) -> Result<(), String> {
+    if !TOKENIZERS.contains(&tokenizer.as_str()) {
+        return Err(format!("Unknown tokenizer: {}", tokenizer));
+    }
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO settings (key, value, updated_at) VALUES ('search_tokenizer', ?1, ?2) \
+         ON CONFLICT(key) DO UPDATE SET value = ?1, updated_at = ?2",
+        rusqlite::params![tokenizer, Utc::now().to_rfc3339()],
+    )
+    .map_err(|e| e.to_string())?;
+    rebuild_search_index(&conn)?;
+    Ok(())
+}
+
 // --- Semantic Search ---
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
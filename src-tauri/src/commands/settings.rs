@@ -49,3 +49,9 @@ pub fn get_all_settings(db: State<'_, Mutex<Connection>>) -> Result<HashMap<Stri
     }
     Ok(map)
 }
+
+/// A setting's value, for settings read by the backend itself.
+pub(crate) fn read_setting(conn: &Connection, key: &str) -> Option<String> {
+    conn.query_row("SELECT value FROM settings WHERE key = ?1", [key], |row| row.get(0))
+        .ok()
+}
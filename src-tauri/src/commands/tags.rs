@@ -1,55 +1,177 @@
-use crate::commands::notes::{batch_fetch_tags, fetch_note_tags, log_activity};
+use crate::commands::attachments::sync_note_attachments;
+use crate::commands::notes::{
+    batch_fetch_tags, batch_fetch_thumbnails, compute_word_count, ensure_tag_exists, fetch_note, fetch_note_tags,
+    log_activity, sync_note_links, sync_tags, sync_to_icloud,
+};
+use crate::commands::queries::{refresh_query_results, sync_note_queries, QueryChange};
+use crate::commands::tasks::sync_note_checklist;
 use crate::db::models::*;
-use crate::markdown::frontmatter::serialize_frontmatter;
+use crate::markdown::tags::{map_tag, rewrite_tag};
+use chrono::Utc;
 use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
 use std::sync::Mutex;
 use tauri::State;
+use uuid::Uuid;
 
-/// Re-write iCloud markdown files for notes affected by a tag change.
-fn resync_notes_icloud(conn: &Connection, note_ids: &[String]) {
+/// Re-derive what `update_note` builds from a note's content after a tag
+/// change rewrote it. Tags are left to the caller, which moves the rows.
+fn sync_rewritten_content(conn: &Connection, note_id: &str, content: &str) -> Result<(), String> {
+    sync_note_links(conn, note_id, content)?;
+    sync_note_queries(conn, note_id, content)?;
+    sync_note_checklist(conn, note_id, content)?;
+    sync_note_attachments(conn, note_id, content)
+}
+
+/// Refresh stored query results and re-write iCloud markdown files for
+/// notes affected by a committed tag change.
+fn resync_notes(conn: &Connection, note_ids: &[String]) {
     for nid in note_ids {
-        let note = match conn.query_row(
-            "SELECT id, title, content, created_at, updated_at, is_trashed, is_pinned, word_count, file_path, sync_hash, state, workspace_id, version \
-             FROM notes WHERE id = ?1",
-            [nid],
-            |row| {
-                Ok(Note {
-                    id: row.get(0)?,
-                    title: row.get(1)?,
-                    content: row.get(2)?,
-                    created_at: row.get(3)?,
-                    updated_at: row.get(4)?,
-                    is_trashed: row.get::<_, i32>(5)? != 0,
-                    is_pinned: row.get::<_, i32>(6)? != 0,
-                    word_count: row.get(7)?,
-                    file_path: row.get(8)?,
-                    sync_hash: row.get(9)?,
-                    tags: vec![],
-                    state: row.get::<_, String>(10).unwrap_or_else(|_| "draft".to_string()),
-                    workspace_id: row.get(11)?,
-                    version: row.get::<_, i32>(12).unwrap_or(1),
-                })
-            },
-        ) {
-            Ok(mut n) => {
-                n.tags = fetch_note_tags(conn, nid).unwrap_or_default();
-                n
-            }
-            Err(_) => continue,
+        let _ = refresh_query_results(conn, QueryChange::Note(nid));
+        if let Ok(note) = fetch_note(conn, nid) {
+            sync_to_icloud(conn, &note);
+        }
+    }
+}
+
+/// IDs of notes tagged with `name` or any of its child tags.
+fn notes_with_tag_tree(conn: &Connection, name: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT DISTINCT nt.note_id FROM note_tags nt JOIN tags t ON t.id = nt.tag_id \
+             WHERE t.name = ?1 OR substr(t.name, 1, length(?1) + 1) = ?1 || '/'",
+        )
+        .map_err(|e| e.to_string())?;
+    let note_ids = stmt
+        .query_map([name], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(note_ids)
+}
+
+fn tag_exists(conn: &Connection, name: &str) -> Result<bool, String> {
+    conn.query_row("SELECT COUNT(*) FROM tags WHERE name = ?1", [name], |row| row.get::<_, i64>(0))
+        .map(|count| count > 0)
+        .map_err(|e| e.to_string())
+}
+
+/// Record a tag change and rewrite the hashtags in each affected note's
+/// content, snapshotting the previous content and tags so it can be undone.
+fn record_tag_change(
+    conn: &Connection,
+    operation: &str,
+    old_name: &str,
+    new_name: Option<&str>,
+    note_ids: &[String],
+) -> Result<String, String> {
+    let change_id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+
+    conn.execute(
+        "INSERT INTO tag_changes (id, operation, old_name, new_name, note_count, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        rusqlite::params![change_id, operation, old_name, new_name, note_ids.len() as i64, now],
+    )
+    .map_err(|e| e.to_string())?;
+
+    for nid in note_ids {
+        let note = fetch_note(conn, nid)?;
+        let content = rewrite_tag(&note.content, old_name, new_name);
+        let version_after = if content != note.content {
+            conn.execute(
+                "UPDATE notes SET content = ?1, word_count = ?2, updated_at = ?3, version = version + 1 WHERE id = ?4",
+                rusqlite::params![content, compute_word_count(&content), now, nid],
+            )
+            .map_err(|e| e.to_string())?;
+            sync_rewritten_content(conn, nid, &content)?;
+            note.version + 1
+        } else {
+            note.version
         };
 
-        if let Some(ref path) = note.file_path {
-            let md = serialize_frontmatter(&note);
-            let _ = std::fs::write(path, md);
-        }
+        let previous_tags = serde_json::to_string(&note.tags).unwrap_or_else(|_| "[]".to_string());
+        conn.execute(
+            "INSERT INTO tag_change_notes (change_id, note_id, previous_content, previous_tags, version_after) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![change_id, nid, note.content, previous_tags, version_after],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(change_id)
+}
+
+/// Rename `old_name` and all of its child tags in the tags table.
+fn rename_tag_rows(conn: &Connection, old_name: &str, new_name: &str) -> Result<(), String> {
+    // Compute new parent, making sure it exists as a row
+    let new_parent = new_name.rfind('/').map(|pos| new_name[..pos].to_string());
+    if let Some(ref parent) = new_parent {
+        ensure_tag_exists(conn, parent)?;
+    }
+
+    // Rename the tag
+    conn.execute(
+        "UPDATE tags SET name = ?1, parent_name = ?2 WHERE name = ?3",
+        rusqlite::params![new_name, new_parent, old_name],
+    )
+    .map_err(|e| e.to_string())?;
+
+    // Also rename child tags that had this as parent prefix
+    let old_prefix = format!("{}/", old_name);
+    let new_prefix = format!("{}/", new_name);
+    let mut stmt = conn
+        .prepare("SELECT id, name FROM tags WHERE substr(name, 1, length(?1)) = ?1")
+        .map_err(|e| e.to_string())?;
+    let children: Vec<(i64, String)> = stmt
+        .query_map([&old_prefix], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    for (child_id, child_name) in children {
+        let updated_name = format!("{}{}", new_prefix, &child_name[old_prefix.len()..]);
+        let updated_parent = updated_name.rfind('/').map(|pos| updated_name[..pos].to_string());
+        conn.execute(
+            "UPDATE tags SET name = ?1, parent_name = ?2 WHERE id = ?3",
+            rusqlite::params![updated_name, updated_parent, child_id],
+        )
+        .map_err(|e| e.to_string())?;
     }
+
+    Ok(())
+}
+
+fn fetch_tag_change(conn: &Connection, id: &str) -> Result<TagChange, String> {
+    conn.query_row(
+        "SELECT id, operation, old_name, new_name, note_count, created_at, undone_at FROM tag_changes WHERE id = ?1",
+        [id],
+        |row| {
+            Ok(TagChange {
+                id: row.get(0)?,
+                operation: row.get(1)?,
+                old_name: row.get(2)?,
+                new_name: row.get(3)?,
+                note_count: row.get(4)?,
+                created_at: row.get(5)?,
+                undone_at: row.get(6)?,
+            })
+        },
+    )
+    .map_err(|e| format!("Tag change not found: {}", e))
 }
 
 #[tauri::command]
 pub fn list_tags(db: State<'_, Mutex<Connection>>) -> Result<Vec<Tag>, String> {
     let conn = db.lock().map_err(|e| e.to_string())?;
+    // total_count rolls child tags up into their parents ("work" counts "work/projects")
     let mut stmt = conn
-        .prepare("SELECT id, name, parent_name, note_count, is_pinned FROM tags ORDER BY is_pinned DESC, name")
+        .prepare(
+            "SELECT t.id, t.name, t.parent_name, t.note_count, t.is_pinned, t.description, t.color, t.icon, \
+             (SELECT COUNT(DISTINCT nt.note_id) FROM note_tags nt JOIN tags c ON c.id = nt.tag_id \
+              WHERE c.name = t.name OR substr(c.name, 1, length(t.name) + 1) = t.name || '/') \
+             FROM tags t ORDER BY t.is_pinned DESC, t.name",
+        )
         .map_err(|e| e.to_string())?;
 
     let tags = stmt
@@ -60,6 +182,10 @@ pub fn list_tags(db: State<'_, Mutex<Connection>>) -> Result<Vec<Tag>, String> {
                 parent_name: row.get(2)?,
                 note_count: row.get(3)?,
                 is_pinned: row.get::<_, i32>(4)? != 0,
+                description: row.get(5)?,
+                color: row.get(6)?,
+                icon: row.get(7)?,
+                total_count: row.get(8)?,
             })
         })
         .map_err(|e| e.to_string())?
@@ -93,113 +219,405 @@ pub fn rename_tag(
     let conn = db.lock().map_err(|e| e.to_string())?;
 
     // Check if new name already exists
-    let exists: bool = conn
-        .query_row(
-            "SELECT COUNT(*) FROM tags WHERE name = ?1",
-            [&new_name],
-            |row| row.get::<_, i64>(0),
-        )
-        .map_err(|e| e.to_string())?
-        > 0;
-    if exists {
-        return Err(format!("Tag '{}' already exists", new_name));
+    if tag_exists(&conn, &new_name)? {
+        return Err(format!("Tag '{}' already exists; merge the tags instead", new_name));
     }
 
-    // Compute new parent
-    let new_parent = new_name.rfind('/').map(|pos| new_name[..pos].to_string());
+    // Content and tag rows change together or not at all
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let note_ids = notes_with_tag_tree(&tx, &old_name)?;
+    let change_id = record_tag_change(&tx, "rename", &old_name, Some(&new_name), &note_ids)?;
+    rename_tag_rows(&tx, &old_name, &new_name)?;
+    tx.commit().map_err(|e| e.to_string())?;
 
-    // Rename the tag
-    conn.execute(
-        "UPDATE tags SET name = ?1, parent_name = ?2 WHERE name = ?3",
-        rusqlite::params![new_name, new_parent, old_name],
+    resync_notes(&conn, &note_ids);
+    log_activity(
+        &conn,
+        "user",
+        "tag_renamed",
+        None,
+        &format!("Renamed tag '{}' to '{}'", old_name, new_name),
+        &serde_json::json!({ "change_id": change_id, "note_count": note_ids.len() }).to_string(),
+    );
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_tag(
+    db: State<'_, Mutex<Connection>>,
+    name: String,
+) -> Result<(), String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+
+    let tag_id: i64 = conn
+        .query_row("SELECT id FROM tags WHERE name = ?1", [&name], |row| row.get(0))
+        .map_err(|_| format!("Tag '{}' not found", name))?;
+
+    // Content and tag rows change together or not at all
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+
+    // Rewrite content of affected notes (including child tags) before removing associations
+    let note_ids = notes_with_tag_tree(&tx, &name)?;
+    let change_id = record_tag_change(&tx, "delete", &name, None, &note_ids)?;
+
+    // Remove all note_tags associations
+    tx.execute("DELETE FROM note_tags WHERE tag_id = ?1", [tag_id])
+        .map_err(|e| e.to_string())?;
+
+    // Delete the tag itself
+    tx.execute("DELETE FROM tags WHERE id = ?1", [tag_id])
+        .map_err(|e| e.to_string())?;
+
+    // Also delete child tags
+    let prefix = format!("{}/", name);
+    tx.execute(
+        "DELETE FROM note_tags WHERE tag_id IN (SELECT id FROM tags WHERE substr(name, 1, length(?1)) = ?1)",
+        [&prefix],
     )
     .map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM tags WHERE substr(name, 1, length(?1)) = ?1", [&prefix])
+        .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
 
-    // Also rename child tags that had this as parent prefix
-    let old_prefix = format!("{}/", old_name);
-    let new_prefix = format!("{}/", new_name);
+    // Re-sync affected notes' query results and iCloud files
+    resync_notes(&conn, &note_ids);
+    log_activity(
+        &conn,
+        "user",
+        "tag_deleted",
+        None,
+        &format!("Deleted tag '{}'", name),
+        &serde_json::json!({ "change_id": change_id, "note_count": note_ids.len() }).to_string(),
+    );
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_tag_changes(
+    db: State<'_, Mutex<Connection>>,
+    limit: Option<i64>,
+) -> Result<Vec<TagChange>, String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
     let mut stmt = conn
-        .prepare("SELECT id, name FROM tags WHERE name LIKE ?1")
+        .prepare(
+            "SELECT id, operation, old_name, new_name, note_count, created_at, undone_at \
+             FROM tag_changes ORDER BY created_at DESC LIMIT ?1",
+        )
         .map_err(|e| e.to_string())?;
-    let children: Vec<(i64, String)> = stmt
-        .query_map([format!("{}%", old_prefix)], |row| {
-            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+
+    let rows = stmt
+        .query_map([limit.unwrap_or(50)], |row| {
+            Ok(TagChange {
+                id: row.get(0)?,
+                operation: row.get(1)?,
+                old_name: row.get(2)?,
+                new_name: row.get(3)?,
+                note_count: row.get(4)?,
+                created_at: row.get(5)?,
+                undone_at: row.get(6)?,
+            })
         })
+        .map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UndoTagChangeResult {
+    pub restored: u32,
+    /// Notes edited since the change; their content was left alone.
+    pub skipped_note_ids: Vec<String>,
+}
+
+#[tauri::command]
+pub fn undo_tag_change(
+    db: State<'_, Mutex<Connection>>,
+    id: String,
+) -> Result<UndoTagChangeResult, String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    let change = fetch_tag_change(&conn, &id)?;
+    if change.undone_at.is_some() {
+        return Err(format!("Tag change '{}' was already undone", id));
+    }
+
+    // Content and tag rows change together or not at all
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let mut stmt = tx
+        .prepare("SELECT note_id, previous_content, previous_tags, version_after FROM tag_change_notes WHERE change_id = ?1")
+        .map_err(|e| e.to_string())?;
+    let snapshots: Vec<(String, String, String, i32)> = stmt
+        .query_map([&id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
         .map_err(|e| e.to_string())?
         .filter_map(|r| r.ok())
         .collect();
+    drop(stmt);
 
-    for (child_id, child_name) in children {
-        let updated_name = format!("{}{}", new_prefix, &child_name[old_prefix.len()..]);
-        let updated_parent = updated_name.rfind('/').map(|pos| updated_name[..pos].to_string());
-        conn.execute(
-            "UPDATE tags SET name = ?1, parent_name = ?2 WHERE id = ?3",
-            rusqlite::params![updated_name, updated_parent, child_id],
+    // Notes edited since the change keep its tags. Theirs are read before
+    // the rows are renamed, as the rename would carry them along.
+    let mut restorable = Vec::new();
+    let mut skipped = Vec::new();
+    for (note_id, previous_content, previous_tags, version_after) in snapshots {
+        match fetch_note(&tx, &note_id) {
+            Ok(current) if current.version != version_after => skipped.push(current),
+            Ok(_) => restorable.push((note_id, previous_content, previous_tags)),
+            Err(_) => {}
+        }
+    }
+
+    // Rename the tag rows back so ids, pins and metadata survive the round
+    // trip. Merged and deleted tags are recreated by sync_tags below.
+    if let (true, Some(new_name)) = (change.operation == "rename", &change.new_name) {
+        if !tag_exists(&tx, &change.old_name)? {
+            rename_tag_rows(&tx, new_name, &change.old_name)?;
+        }
+    }
+
+    let now = Utc::now().to_rfc3339();
+    let mut restored_ids = Vec::new();
+    for (note_id, previous_content, previous_tags) in restorable {
+        tx.execute(
+            "UPDATE notes SET content = ?1, word_count = ?2, updated_at = ?3, version = version + 1 WHERE id = ?4",
+            rusqlite::params![previous_content, compute_word_count(&previous_content), now, note_id],
         )
         .map_err(|e| e.to_string())?;
+        sync_rewritten_content(&tx, &note_id, &previous_content)?;
+        let tags: Vec<String> = serde_json::from_str(&previous_tags).unwrap_or_default();
+        sync_tags(&tx, &note_id, &tags)?;
+        restored_ids.push(note_id);
+    }
+    let mut skipped_note_ids = Vec::new();
+    for note in skipped {
+        sync_tags(&tx, &note.id, &note.tags)?;
+        skipped_note_ids.push(note.id);
     }
 
-    // Collect affected note IDs and re-sync iCloud files
-    let tag_id: i64 = conn
-        .query_row("SELECT id FROM tags WHERE name = ?1", [&new_name], |row| row.get(0))
-        .map_err(|e| e.to_string())?;
-    let mut note_stmt = conn
-        .prepare("SELECT note_id FROM note_tags WHERE tag_id = ?1")
-        .map_err(|e| e.to_string())?;
-    let note_ids: Vec<String> = note_stmt
-        .query_map([tag_id], |row| row.get::<_, String>(0))
-        .map_err(|e| e.to_string())?
-        .filter_map(|r| r.ok())
-        .collect();
+    tx.execute(
+        "UPDATE tag_changes SET undone_at = ?1 WHERE id = ?2",
+        rusqlite::params![now, id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    let restored = restored_ids.len() as u32;
+    resync_notes(&conn, &restored_ids);
+    log_activity(
+        &conn,
+        "user",
+        "tag_change_undone",
+        None,
+        &format!("Undid {} of tag '{}'", change.operation, change.old_name),
+        &serde_json::json!({ "change_id": id, "restored": restored }).to_string(),
+    );
 
-    resync_notes_icloud(&conn, &note_ids);
-    log_activity(&conn, "user", "tag_renamed", None, &format!("Renamed tag '{}' to '{}'", old_name, new_name), "{}");
+    Ok(UndoTagChangeResult { restored, skipped_note_ids })
+}
+
+/// A note's tags after merging `source` into `target`: the source tree moves
+/// under the target, and tags that end up the same appear once.
+fn merge_tag_names(tags: Vec<String>, source: &str, target: &str) -> Vec<String> {
+    let mut merged: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = map_tag(&tag, source, Some(target)).flatten().unwrap_or(tag);
+        if !merged.contains(&tag) {
+            merged.push(tag);
+        }
+    }
+    merged
+}
+
+#[tauri::command]
+pub fn merge_tags(
+    db: State<'_, Mutex<Connection>>,
+    source: String,
+    target: String,
+) -> Result<(), String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    if map_tag(&target, &source, None).is_some() {
+        return Err(format!("Cannot merge '{}' into itself or its child '{}'", source, target));
+    }
+
+    let note_ids = notes_with_tag_tree(&conn, &source)?;
+    if note_ids.is_empty() && !tag_exists(&conn, &source)? {
+        return Err(format!("Tag '{}' not found", source));
+    }
+
+    // Content and tag rows change together or not at all
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let change_id = record_tag_change(&tx, "merge", &source, Some(&target), &note_ids)?;
+    for nid in &note_ids {
+        let merged = merge_tag_names(fetch_note_tags(&tx, nid)?, &source, &target);
+        sync_tags(&tx, nid, &merged)?;
+    }
+    ensure_tag_exists(&tx, &target)?;
+
+    // The source tree is now empty; its metadata gives way to the target's
+    tx.execute(
+        "DELETE FROM tags WHERE (name = ?1 OR substr(name, 1, length(?1) + 1) = ?1 || '/') \
+         AND NOT EXISTS (SELECT 1 FROM note_tags WHERE note_tags.tag_id = tags.id)",
+        [&source],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    resync_notes(&conn, &note_ids);
+    log_activity(
+        &conn,
+        "user",
+        "tag_merged",
+        None,
+        &format!("Merged tag '{}' into '{}'", source, target),
+        &serde_json::json!({ "change_id": change_id, "note_count": note_ids.len() }).to_string(),
+    );
     Ok(())
 }
 
+/// Set a tag's description, color and icon. `None` keeps the current value
+/// and an empty string clears it.
 #[tauri::command]
-pub fn delete_tag(
+pub fn update_tag_metadata(
     db: State<'_, Mutex<Connection>>,
     name: String,
+    description: Option<String>,
+    color: Option<String>,
+    icon: Option<String>,
 ) -> Result<(), String> {
     let conn = db.lock().map_err(|e| e.to_string())?;
+    let updated = conn
+        .execute(
+            "UPDATE tags SET description = COALESCE(?1, description), \
+             color = CASE WHEN ?2 IS NULL THEN color ELSE NULLIF(?2, '') END, \
+             icon = CASE WHEN ?3 IS NULL THEN icon ELSE NULLIF(?3, '') END WHERE name = ?4",
+            rusqlite::params![description, color, icon, name],
+        )
+        .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err(format!("Tag '{}' not found", name));
+    }
+    Ok(())
+}
 
-    // Get tag id
-    let tag_id: i64 = conn
-        .query_row("SELECT id FROM tags WHERE name = ?1", [&name], |row| row.get(0))
-        .map_err(|_| format!("Tag '{}' not found", name))?;
+// --- Tag Aliases ---
 
-    // Get affected note IDs before removing associations
-    let mut note_stmt = conn
-        .prepare("SELECT note_id FROM note_tags WHERE tag_id = ?1")
+/// Normalise tags through `tag_aliases`, so `ml` and `ml/deep` become
+/// `machine-learning` and `machine-learning/deep`. Order is kept, duplicates
+/// created by the mapping are dropped.
+pub(crate) fn resolve_tag_aliases(conn: &Connection, tags: &[String]) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT alias, tag_name FROM tag_aliases")
         .map_err(|e| e.to_string())?;
-    let note_ids: Vec<String> = note_stmt
-        .query_map([tag_id], |row| row.get::<_, String>(0))
+    let aliases: Vec<(String, String)> = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
         .map_err(|e| e.to_string())?
         .filter_map(|r| r.ok())
         .collect();
 
-    // Remove all note_tags associations
-    conn.execute("DELETE FROM note_tags WHERE tag_id = ?1", [tag_id])
-        .map_err(|e| e.to_string())?;
+    Ok(apply_tag_aliases(&aliases, tags))
+}
 
-    // Delete the tag itself
-    conn.execute("DELETE FROM tags WHERE id = ?1", [tag_id])
-        .map_err(|e| e.to_string())?;
+/// Map `tags` through `(alias, canonical)` pairs; see `resolve_tag_aliases`.
+fn apply_tag_aliases(aliases: &[(String, String)], tags: &[String]) -> Vec<String> {
+    let mut resolved: Vec<String> = Vec::new();
+    for tag in tags {
+        // Prefer the most specific alias when both "ml" and "ml/deep" are declared
+        let tag = aliases
+            .iter()
+            .filter_map(|(alias, canonical)| {
+                map_tag(tag, alias, Some(canonical)).flatten().map(|t| (alias.len(), t))
+            })
+            .max_by_key(|(len, _)| *len)
+            .map(|(_, t)| t)
+            .unwrap_or_else(|| tag.clone());
+        if !resolved.contains(&tag) {
+            resolved.push(tag);
+        }
+    }
+    resolved
+}
 
-    // Also delete child tags
-    let prefix = format!("{}/%", name);
+#[tauri::command]
+pub fn set_tag_alias(
+    db: State<'_, Mutex<Connection>>,
+    alias: String,
+    tag_name: String,
+) -> Result<TagAlias, String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    let canonical = resolve_tag_aliases(&conn, std::slice::from_ref(&tag_name))?
+        .pop()
+        .unwrap_or(tag_name);
+    if map_tag(&canonical, &alias, None).is_some() {
+        return Err(format!("Alias '{}' would point at itself via '{}'", alias, canonical));
+    }
+
+    let now = Utc::now().to_rfc3339();
     conn.execute(
-        "DELETE FROM note_tags WHERE tag_id IN (SELECT id FROM tags WHERE name LIKE ?1)",
-        [&prefix],
+        "INSERT INTO tag_aliases (alias, tag_name, created_at) VALUES (?1, ?2, ?3) \
+         ON CONFLICT(alias) DO UPDATE SET tag_name = excluded.tag_name",
+        rusqlite::params![alias, canonical, now],
+    )
+    .map_err(|e| e.to_string())?;
+
+    // Re-point existing aliases at the new canonical tag to avoid chains
+    conn.execute(
+        "UPDATE tag_aliases SET tag_name = ?1 WHERE tag_name = ?2",
+        rusqlite::params![canonical, alias],
+    )
+    .map_err(|e| e.to_string())?;
+
+    // Normalise notes already using the alias; their content is left as written
+    let note_ids = notes_with_tag_tree(&conn, &alias)?;
+    for nid in &note_ids {
+        let tags = fetch_note_tags(&conn, nid)?;
+        sync_tags(&conn, nid, &tags)?;
+    }
+    ensure_tag_exists(&conn, &canonical)?;
+    conn.execute(
+        "DELETE FROM tags WHERE (name = ?1 OR substr(name, 1, length(?1) + 1) = ?1 || '/') \
+         AND NOT EXISTS (SELECT 1 FROM note_tags WHERE note_tags.tag_id = tags.id)",
+        [&alias],
     )
     .map_err(|e| e.to_string())?;
-    conn.execute("DELETE FROM tags WHERE name LIKE ?1", [&prefix])
+    resync_notes(&conn, &note_ids);
+
+    log_activity(
+        &conn,
+        "user",
+        "tag_alias_set",
+        None,
+        &format!("Aliased tag '{}' to '{}'", alias, canonical),
+        "{}",
+    );
+    Ok(TagAlias {
+        alias,
+        tag_name: canonical,
+        created_at: now,
+    })
+}
+
+#[tauri::command]
+pub fn list_tag_aliases(db: State<'_, Mutex<Connection>>) -> Result<Vec<TagAlias>, String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare("SELECT alias, tag_name, created_at FROM tag_aliases ORDER BY alias")
         .map_err(|e| e.to_string())?;
 
-    // Re-sync affected notes' iCloud files
-    resync_notes_icloud(&conn, &note_ids);
-    log_activity(&conn, "user", "tag_deleted", None, &format!("Deleted tag '{}'", name), "{}");
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(TagAlias {
+                alias: row.get(0)?,
+                tag_name: row.get(1)?,
+                created_at: row.get(2)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn remove_tag_alias(db: State<'_, Mutex<Connection>>, alias: String) -> Result<(), String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM tag_aliases WHERE alias = ?1", [&alias])
+        .map_err(|e| e.to_string())?;
     Ok(())
 }
 
@@ -243,6 +661,8 @@ pub fn get_notes_by_tag(
                 tags: vec![],
                 state: row.get::<_, String>(7).unwrap_or_else(|_| "draft".to_string()),
                 workspace_id: row.get(8)?,
+                attachment_match: None,
+                thumbnail: None,
             })
         })
         .map_err(|e| e.to_string())?;
@@ -256,11 +676,47 @@ pub fn get_notes_by_tag(
     // Batch-fetch tags (fixes N+1)
     let note_ids: Vec<String> = items.iter().map(|n| n.id.clone()).collect();
     let tags_map = batch_fetch_tags(&conn, &note_ids)?;
+    let thumbnails = batch_fetch_thumbnails(&conn, &note_ids)?;
     for item in &mut items {
         if let Some(tags) = tags_map.get(&item.id) {
             item.tags = tags.clone();
         }
+        item.thumbnail = thumbnails.get(&item.id).cloned();
     }
 
     Ok(items)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_apply_tag_aliases() {
+        let aliases = vec![
+            ("ml".to_string(), "machine-learning".to_string()),
+            ("ml/dl".to_string(), "deep-learning".to_string()),
+        ];
+        assert_eq!(
+            apply_tag_aliases(&aliases, &names(&["ml", "ml/nlp", "ml/dl/cnn", "mlx", "machine-learning"])),
+            names(&["machine-learning", "machine-learning/nlp", "deep-learning/cnn", "mlx"])
+        );
+        assert!(apply_tag_aliases(&aliases, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_merge_tag_names() {
+        assert_eq!(
+            merge_tag_names(names(&["js", "javascript", "js/react", "jsx"]), "js", "javascript"),
+            names(&["javascript", "javascript/react", "jsx"])
+        );
+        assert_eq!(
+            merge_tag_names(names(&["work/old", "work/new"]), "work/old", "work/new"),
+            names(&["work/new"])
+        );
+    }
+}
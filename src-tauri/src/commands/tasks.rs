@@ -1,5 +1,7 @@
 use crate::db::models::Task;
-use crate::commands::notes::log_activity;
+use crate::commands::notes::{fetch_note, log_activity, save_note_content};
+use crate::commands::queries::{refresh_query_results, QueryChange};
+use crate::markdown::checklist::{extract_checklist, set_checked, set_title};
 use chrono::Utc;
 use rusqlite::Connection;
 use std::sync::Mutex;
@@ -29,6 +31,197 @@ fn fetch_task(conn: &Connection, id: &str) -> Result<Task, String> {
     .map_err(|e| format!("Task not found: {}", e))
 }
 
+/// Whether two item texts share at least half of their words.
+fn similar_titles(a: &str, b: &str) -> bool {
+    let a: Vec<String> = a.split_whitespace().map(str::to_lowercase).collect();
+    let b: Vec<String> = b.split_whitespace().map(str::to_lowercase).collect();
+    let shared = a.iter().filter(|w| b.contains(w)).count();
+    shared > 0 && shared * 2 >= a.len().max(b.len())
+}
+
+/// Mirror the `- [ ]` items of a note into tasks linked to it. Items keep
+/// their task across edits: first by matching text, then by similar text,
+/// then by position, so reordering or rewording an item doesn't create a new
+/// task. Ticking an item
+/// completes its task and unticking a done task reopens it; `@due(...)` and
+/// `!priority` markers override the task's fields when present. Tasks whose
+/// item was removed from the note are kept but unlinked from it.
+pub(crate) fn sync_note_checklist(conn: &Connection, note_id: &str, content: &str) -> Result<(), String> {
+    let items = extract_checklist(content);
+
+    let mut stmt = conn
+        .prepare("SELECT id, title, status, checklist_position FROM tasks WHERE linked_note_id = ?1 AND checklist_position IS NOT NULL ORDER BY checklist_position")
+        .map_err(|e| e.to_string())?;
+    let existing: Vec<(String, String, String, i64)> = stmt
+        .query_map([note_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut claimed = vec![false; existing.len()];
+    let mut matched: Vec<Option<usize>> = vec![None; items.len()];
+    for (i, item) in items.iter().enumerate() {
+        if let Some(j) = (0..existing.len()).find(|&j| !claimed[j] && existing[j].1 == item.title) {
+            claimed[j] = true;
+            matched[i] = Some(j);
+        }
+    }
+    for (i, item) in items.iter().enumerate() {
+        if matched[i].is_some() {
+            continue;
+        }
+        if let Some(j) = (0..existing.len()).find(|&j| !claimed[j] && similar_titles(&existing[j].1, &item.title)) {
+            claimed[j] = true;
+            matched[i] = Some(j);
+        }
+    }
+    for (i, slot) in matched.iter_mut().enumerate() {
+        if slot.is_some() {
+            continue;
+        }
+        if let Some(j) = (0..existing.len()).find(|&j| !claimed[j] && existing[j].3 == i as i64) {
+            claimed[j] = true;
+            *slot = Some(j);
+        }
+    }
+
+    let workspace_id: Option<String> = conn
+        .query_row("SELECT workspace_id FROM notes WHERE id = ?1", [note_id], |row| row.get(0))
+        .unwrap_or(None);
+    let now = Utc::now().to_rfc3339();
+
+    for (i, item) in items.iter().enumerate() {
+        match matched[i] {
+            Some(j) => {
+                let (ref id, _, ref status, _) = existing[j];
+                let status = match (item.checked, status.as_str()) {
+                    (true, _) => "done",
+                    (false, "done") => "todo",
+                    (false, other) => other,
+                };
+                conn.execute(
+                    "UPDATE tasks SET title = ?1, status = ?2, priority = COALESCE(?3, priority), due_date = COALESCE(?4, due_date), checklist_position = ?5, updated_at = ?6
+                     WHERE id = ?7 AND (title != ?1 OR status != ?2 OR priority != COALESCE(?3, priority) OR due_date IS NOT COALESCE(?4, due_date) OR checklist_position != ?5)",
+                    rusqlite::params![item.title, status, item.priority, item.due_date, i as i64, now, id],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+            None => {
+                conn.execute(
+                    "INSERT INTO tasks (id, title, status, priority, due_date, linked_note_id, workspace_id, checklist_position, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                    rusqlite::params![
+                        Uuid::new_v4().to_string(),
+                        item.title,
+                        if item.checked { "done" } else { "todo" },
+                        item.priority.as_deref().unwrap_or("medium"),
+                        item.due_date,
+                        note_id,
+                        workspace_id,
+                        i as i64,
+                        now,
+                        now,
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+        }
+    }
+
+    for (j, (id, ..)) in existing.iter().enumerate() {
+        if !claimed[j] {
+            conn.execute(
+                "UPDATE tasks SET linked_note_id = NULL, checklist_position = NULL, updated_at = ?1 WHERE id = ?2",
+                rusqlite::params![now, id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Update the checklist item behind a task so its text and tick match the
+/// task, then re-save the note. An item is found by the task's title, else
+/// by its position when the title was edited. Tasks not created from a
+/// checklist item are left alone.
+pub(crate) fn sync_task_to_note(conn: &Connection, task_id: &str) -> Result<(), String> {
+    let (note_id, position, title, status): (Option<String>, Option<i64>, String, String) = conn
+        .query_row(
+            "SELECT linked_note_id, checklist_position, title, status FROM tasks WHERE id = ?1",
+            [task_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .map_err(|e| format!("Task not found: {}", e))?;
+    let (Some(note_id), Some(position)) = (note_id, position) else {
+        return Ok(());
+    };
+
+    let note = fetch_note(conn, &note_id)?;
+    let items = extract_checklist(&note.content);
+    let item = items
+        .get(position as usize)
+        .filter(|item| item.title == title)
+        .or_else(|| items.iter().find(|item| item.title == title))
+        .or_else(|| items.get(position as usize));
+    let Some(item) = item else {
+        return Ok(());
+    };
+
+    // The title goes on one line; the marker keeps its offsets as only the
+    // text after it changes
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut content = note.content.clone();
+    if !title.is_empty() && item.title != title {
+        content = set_title(&content, item, &title);
+    }
+    let checked = status == "done";
+    if item.checked != checked {
+        content = set_checked(&content, item, checked);
+    }
+    if content == note.content {
+        return Ok(());
+    }
+    save_note_content(conn, &note_id, &note.title, &content, note.version + 1, &Utc::now().to_rfc3339())?;
+    Ok(())
+}
+
+/// Reconcile checklists with tasks after the database was changed outside
+/// the app (e.g. by agents through the MCP server). Whichever side changed
+/// last wins: tasks updated after their note tick or untick it, then each
+/// note's items are mirrored back into tasks.
+pub(crate) fn reconcile_checklists(conn: &Connection) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT t.id FROM tasks t JOIN notes n ON n.id = t.linked_note_id
+             WHERE t.checklist_position IS NOT NULL AND t.updated_at > n.updated_at",
+        )
+        .map_err(|e| e.to_string())?;
+    let task_ids: Vec<String> = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    for id in task_ids {
+        let _ = sync_task_to_note(conn, &id);
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, content FROM notes
+             WHERE is_trashed = 0 AND (content LIKE '%[ ]%' OR content LIKE '%[x]%'
+                OR id IN (SELECT linked_note_id FROM tasks WHERE checklist_position IS NOT NULL))",
+        )
+        .map_err(|e| e.to_string())?;
+    let notes: Vec<(String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    for (id, content) in notes {
+        sync_note_checklist(conn, &id, &content)?;
+    }
+    Ok(())
+}
+
 #[tauri::command]
 #[allow(clippy::too_many_arguments)]
 pub fn create_task(
@@ -63,6 +256,7 @@ pub fn create_task(
     .map_err(|e| e.to_string())?;
 
     log_activity(&conn, "user", "task_created", None, &format!("Created task '{}'", title), "{}");
+    let _ = refresh_query_results(&conn, QueryChange::Task(&id));
     fetch_task(&conn, &id)
 }
 
@@ -167,7 +361,9 @@ pub fn update_task(
     )
     .map_err(|e| e.to_string())?;
 
+    sync_task_to_note(&conn, &id)?;
     log_activity(&conn, "user", "task_updated", None, &format!("Updated task '{}'", new_title), "{}");
+    let _ = refresh_query_results(&conn, QueryChange::Task(&id));
     fetch_task(&conn, &id)
 }
 
@@ -182,8 +378,10 @@ pub fn complete_task(db: State<'_, Mutex<Connection>>, id: String) -> Result<Tas
     )
     .map_err(|e| e.to_string())?;
 
+    sync_task_to_note(&conn, &id)?;
     let task = fetch_task(&conn, &id)?;
     log_activity(&conn, "user", "task_completed", None, &format!("Completed task '{}'", task.title), "{}");
+    let _ = refresh_query_results(&conn, QueryChange::Task(&id));
     Ok(task)
 }
 
@@ -196,5 +394,6 @@ pub fn delete_task(db: State<'_, Mutex<Connection>>, id: String) -> Result<(), S
         .map_err(|e| e.to_string())?;
 
     log_activity(&conn, "user", "task_deleted", None, &format!("Deleted task '{}'", task.title), "{}");
+    let _ = refresh_query_results(&conn, QueryChange::Task(&id));
     Ok(())
 }
@@ -1,4 +1,4 @@
-use crate::commands::notes::{fetch_note, log_activity, sync_tags};
+use crate::commands::notes::{compute_word_count, fetch_note, log_activity, sync_tags};
 use crate::db::models::Template;
 use crate::markdown::tags::extract_tags;
 use crate::sync::icloud;
@@ -86,7 +86,7 @@ pub fn create_note_from_template(
 
     let id = Uuid::new_v4().to_string();
     let timestamp = now.to_rfc3339();
-    let word_count = content.split_whitespace().count() as i64;
+    let word_count = compute_word_count(&content);
 
     // Merge template tags with any inline tags from expanded content
     let mut all_tags = template.tags.clone();
@@ -0,0 +1,402 @@
+use crate::commands::export::{export_file_stem, local_image_path, url_encode};
+use crate::commands::files::images_dir_for;
+use crate::commands::notes::{fetch_note, log_activity};
+use crate::commands::outline::{find_note_id_by_title, find_note_section};
+use crate::db::models::{Note, VaultExportParams, VaultExportResult};
+use crate::document::zip::ZipWriter;
+use crate::markdown::embeds::{find_embeds, find_wiki_links};
+use crate::markdown::frontmatter::export_frontmatter;
+use crate::markdown::sections::{find_section_by_anchor, split_link_target};
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::State;
+
+/// Folder for bundled images, also set as Obsidian's attachment folder.
+const ATTACHMENTS_DIR: &str = "attachments";
+const MANIFEST_FILE: &str = "bruin-export.json";
+
+#[derive(Serialize)]
+struct VaultManifest<'a> {
+    exported_at: String,
+    workspace_id: Option<&'a str>,
+    notes: Vec<ManifestNote<'a>>,
+    attachments: Vec<ManifestAttachment>,
+}
+
+#[derive(Serialize)]
+struct ManifestNote<'a> {
+    id: &'a str,
+    title: &'a str,
+    path: &'a str,
+    workspace: Option<&'a str>,
+    tags: &'a [String],
+    created_at: &'a str,
+    updated_at: &'a str,
+}
+
+#[derive(Serialize)]
+struct ManifestAttachment {
+    source: String,
+    path: String,
+}
+
+struct VaultNote {
+    note: Note,
+    workspace: Option<String>,
+    /// Path inside the vault, e.g. `Work/Plan.md`.
+    path: String,
+}
+
+/// Where the vault is written.
+enum VaultOutput {
+    Zip(ZipWriter),
+    Directory(PathBuf),
+}
+
+impl VaultOutput {
+    fn add(&mut self, path: &str, data: &[u8]) -> Result<(), String> {
+        match self {
+            VaultOutput::Zip(zip) => zip.add(path, data),
+            VaultOutput::Directory(dir) => {
+                let file = dir.join(path);
+                if let Some(parent) = file.parent() {
+                    fs::create_dir_all(parent).map_err(|e| format!("Failed to create folder: {}", e))?;
+                }
+                fs::write(&file, data).map_err(|e| format!("Failed to write {}: {}", path, e))
+            }
+        }
+    }
+}
+
+/// Export every note (or one workspace's notes) as an Obsidian-compatible
+/// vault: a markdown file per note with frontmatter, in a folder per
+/// workspace, wiki links pointing at the exported file names, local images
+/// copied into `attachments/`, and a `bruin-export.json` manifest. Trashed
+/// notes are left out.
+#[tauri::command]
+pub fn export_vault(
+    db: State<'_, Mutex<Connection>>,
+    params: VaultExportParams,
+) -> Result<VaultExportResult, String> {
+    let conn = db.lock().map_err(|e| e.to_string())?;
+    let output_path = Path::new(&params.output_path);
+    let mut output = match params.format.as_deref() {
+        None | Some("zip") => VaultOutput::Zip(ZipWriter::new()),
+        Some("directory") => {
+            let not_empty = fs::read_dir(output_path).is_ok_and(|mut entries| entries.next().is_some());
+            if not_empty {
+                return Err("The export folder must be empty".to_string());
+            }
+            fs::create_dir_all(output_path).map_err(|e| format!("Failed to create export folder: {}", e))?;
+            VaultOutput::Directory(output_path.to_path_buf())
+        }
+        Some(other) => return Err(format!("Unsupported vault format: '{}'", other)),
+    };
+
+    let notes = load_vault_notes(&conn, params.workspace_id.as_deref())?;
+    let paths: HashMap<&str, &str> = notes.iter().map(|n| (n.note.id.as_str(), n.path.as_str())).collect();
+
+    let images_dir = images_dir_for(&conn);
+    let mut attachments: HashMap<PathBuf, String> = HashMap::new();
+    let mut missing_attachments = 0;
+    for vault_note in &notes {
+        let depth = vault_note.path.matches('/').count();
+        let content = rewrite_wiki_links(&conn, &vault_note.note, &paths);
+        let content = rewrite_images(&content, depth, images_dir.as_deref(), &mut attachments, &mut missing_attachments);
+        let file = format!("{}\n{}", export_frontmatter(&vault_note.note), content);
+        output.add(&vault_note.path, file.as_bytes())?;
+    }
+
+    let mut bundled: Vec<ManifestAttachment> = Vec::new();
+    let mut sources: Vec<(&PathBuf, &String)> = attachments.iter().collect();
+    sources.sort_by(|a, b| a.1.cmp(b.1));
+    for (source, name) in sources {
+        let path = format!("{}/{}", ATTACHMENTS_DIR, name);
+        match fs::read(source) {
+            Ok(data) => output.add(&path, &data)?,
+            Err(e) => {
+                log::warn!("Failed to read attachment {}: {}", source.display(), e);
+                missing_attachments += 1;
+                continue;
+            }
+        }
+        bundled.push(ManifestAttachment {
+            source: source.to_string_lossy().to_string(),
+            path,
+        });
+    }
+
+    let attachment_count = bundled.len();
+    let manifest = VaultManifest {
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        workspace_id: params.workspace_id.as_deref(),
+        notes: notes
+            .iter()
+            .map(|n| ManifestNote {
+                id: &n.note.id,
+                title: &n.note.title,
+                path: &n.path,
+                workspace: n.workspace.as_deref(),
+                tags: &n.note.tags,
+                created_at: &n.note.created_at,
+                updated_at: &n.note.updated_at,
+            })
+            .collect(),
+        attachments: bundled,
+    };
+    let manifest_json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    output.add(MANIFEST_FILE, manifest_json.as_bytes())?;
+    let app_config = serde_json::json!({ "attachmentFolderPath": ATTACHMENTS_DIR });
+    output.add(".obsidian/app.json", app_config.to_string().as_bytes())?;
+
+    if let VaultOutput::Zip(zip) = output {
+        fs::write(output_path, zip.finish()).map_err(|e| format!("Failed to write zip: {}", e))?;
+    }
+
+    log_activity(
+        &conn,
+        "user",
+        "vault_exported",
+        None,
+        &format!("Exported {} notes to {}", notes.len(), params.output_path),
+        &serde_json::json!({ "output_path": params.output_path, "workspace_id": params.workspace_id }).to_string(),
+    );
+
+    Ok(VaultExportResult {
+        output_path: params.output_path,
+        notes: notes.len(),
+        attachments: attachment_count,
+        missing_attachments,
+    })
+}
+
+/// Notes to export with their vault paths. In a whole-vault export, notes in
+/// a workspace go in a folder named after it. File names come from titles
+/// and are unique across the vault (clashes get an id suffix), so
+/// `[[file name]]` links resolve in Obsidian wherever the note lives.
+fn load_vault_notes(conn: &Connection, workspace_id: Option<&str>) -> Result<Vec<VaultNote>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT n.id, w.name FROM notes n LEFT JOIN workspaces w ON w.id = n.workspace_id \
+             WHERE n.is_trashed = 0 AND (?1 IS NULL OR n.workspace_id = ?1) \
+             ORDER BY w.name, n.title COLLATE NOCASE, n.created_at",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows: Vec<(String, Option<String>)> = stmt
+        .query_map([workspace_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut taken: Vec<String> = Vec::new();
+    let mut notes = Vec::new();
+    for (id, workspace) in rows {
+        let note = fetch_note(conn, &id)?;
+        let mut stem = export_file_stem(&note.title);
+        if taken.contains(&stem.to_lowercase()) {
+            stem = format!("{}-{}", stem, &note.id[..8.min(note.id.len())]);
+        }
+        taken.push(stem.to_lowercase());
+        let path = match (&workspace, workspace_id) {
+            (Some(name), None) => format!("{}/{}.md", export_file_stem(name), stem),
+            _ => format!("{}.md", stem),
+        };
+        notes.push(VaultNote { note, workspace, path });
+    }
+    Ok(notes)
+}
+
+/// Point `[[links]]` and `![[embeds]]` at the exported file names. Section
+/// anchors become heading text, as Obsidian expects, and a link whose file
+/// name differs from the note title keeps the title as its alias.
+fn rewrite_wiki_links(conn: &Connection, note: &Note, paths: &HashMap<&str, &str>) -> String {
+    relink(&note.content, &|title, anchor| {
+        let id = find_note_id_by_title(conn, title)?;
+        let path = paths.get(id.as_str())?;
+        let file = path.rsplit('/').next().unwrap_or(path).trim_end_matches(".md").to_string();
+        let heading = anchor.and_then(|a| {
+            let linked = fetch_note(conn, &id).ok()?;
+            find_note_section(&linked, Some(a), None).ok().map(|s| s.heading)
+        });
+        Some((file, heading))
+    })
+}
+
+/// File name of a linked note and the heading text of the link's anchor.
+type LinkTarget = (String, Option<String>);
+
+/// `content` with its wiki links and embeds rewritten. `resolve` gives the
+/// target of a linked title and anchor; links it can't resolve are left as
+/// they are.
+fn relink(content: &str, resolve: &dyn Fn(&str, Option<&str>) -> Option<LinkTarget>) -> String {
+    let mut spans = find_wiki_links(content);
+    spans.extend(find_embeds(content));
+    spans.sort_by_key(|s| s.start);
+
+    let mut out = String::with_capacity(content.len());
+    let mut last = 0;
+    for span in spans {
+        let (title, anchor) = split_link_target(&span.target);
+        let alias = span.target.split_once('|').map(|(_, a)| a.trim()).filter(|a| !a.is_empty());
+
+        let (file, heading) = if title.is_empty() {
+            let heading = anchor.and_then(|a| find_section_by_anchor(content, a)).map(|(s, _)| s.heading.text);
+            (String::new(), heading)
+        } else {
+            let Some(resolved) = resolve(title, anchor) else {
+                continue;
+            };
+            resolved
+        };
+
+        let mut target = file.clone();
+        if let Some(anchor) = anchor {
+            target.push('#');
+            target.push_str(heading.as_deref().unwrap_or(anchor));
+        }
+        match alias {
+            Some(alias) => {
+                target.push('|');
+                target.push_str(alias);
+            }
+            None if !title.is_empty() && file != title => {
+                target.push('|');
+                target.push_str(&span.target);
+            }
+            None => {}
+        }
+
+        let bang = if content[span.start..].starts_with('!') { "!" } else { "" };
+        out.push_str(&content[last..span.start]);
+        out.push_str(&format!("{}[[{}]]", bang, target));
+        last = span.end;
+    }
+    out.push_str(&content[last..]);
+    out
+}
+
+/// Point images in `images_dir` at copies in the attachments folder,
+/// relative to a note `depth` folders below the vault root. `attachments`
+/// maps each source file to its (unique) name there. Other local files are
+/// never bundled.
+fn rewrite_images(
+    content: &str,
+    depth: usize,
+    images_dir: Option<&Path>,
+    attachments: &mut HashMap<PathBuf, String>,
+    missing: &mut usize,
+) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_FOOTNOTES | Options::ENABLE_STRIKETHROUGH;
+    let mut replacements: Vec<(usize, usize, String)> = Vec::new();
+    for (event, range) in Parser::new_ext(content, options).into_offset_iter() {
+        let Event::Start(Tag::Image { dest_url, .. }) = event else {
+            continue;
+        };
+        let source = &content[range.clone()];
+        // Only inline images `![alt](url)` are rewritten
+        let (Some(alt), Some(split)) = (source.strip_prefix("!["), source.rfind("](")) else {
+            continue;
+        };
+        let Some(path) = images_dir.and_then(|dir| local_image_path(&dest_url, dir)) else {
+            if dest_url.starts_with("asset:") || dest_url.contains("asset.localhost") || dest_url.starts_with("file:") {
+                *missing += 1;
+            }
+            continue;
+        };
+
+        let name = match attachments.get(&path) {
+            Some(name) => name.clone(),
+            None => {
+                let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                let name = if attachments.values().any(|n| *n == file_name) {
+                    format!("{}-{}", attachments.len(), file_name)
+                } else {
+                    file_name
+                };
+                attachments.insert(path, name.clone());
+                name
+            }
+        };
+        let href = format!("{}{}/{}", "../".repeat(depth), ATTACHMENTS_DIR, url_encode(&name));
+        replacements.push((range.start, range.end, format!("![{}]({})", &alt[..split - 2], href)));
+    }
+
+    let mut out = String::with_capacity(content.len());
+    let mut last = 0;
+    for (start, end, text) in replacements {
+        out.push_str(&content[last..start]);
+        out.push_str(&text);
+        last = end;
+    }
+    out.push_str(&content[last..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relink() {
+        let content = "[[Plan]] [[plan|the plan]] [[Plan#goals]] [[Weird: Title]] [[Weird: Title#goals]]\n\
+                       ![[Plan]] [[#local-part]] [[Missing]]\n\n# Local part\n";
+        let resolve = |title: &str, anchor: Option<&str>| -> Option<LinkTarget> {
+            let file = match title.to_lowercase().as_str() {
+                "plan" => "Plan",
+                "weird: title" => "Weird- Title",
+                _ => return None,
+            };
+            Some((file.to_string(), anchor.map(|_| "Goals".to_string())))
+        };
+        assert_eq!(
+            relink(content, &resolve),
+            "[[Plan]] [[Plan|the plan]] [[Plan#Goals]] [[Weird- Title|Weird: Title]] \
+             [[Weird- Title#Goals|Weird: Title#goals]]\n\
+             ![[Plan]] [[#Local part]] [[Missing]]\n\n# Local part\n"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_images() {
+        let root = std::env::temp_dir().join(format!("bruin-vault-{}", uuid::Uuid::new_v4()));
+        let images = root.join("images");
+        fs::create_dir_all(images.join("old")).unwrap();
+        fs::write(images.join("a.png"), b"png").unwrap();
+        fs::write(images.join("old").join("a.png"), b"png").unwrap();
+        fs::write(root.join("secret.png"), b"secret").unwrap();
+
+        let url = |path: PathBuf| format!("file://{}", path.to_string_lossy());
+        let content = format!(
+            "![one]({}) ![two]({}) ![secret]({}) ![web](https://example.com/a.png) ![ref][r]\n\n[r]: {}\n",
+            url(images.join("a.png")),
+            url(images.join("old").join("a.png")),
+            url(root.join("secret.png")),
+            url(images.join("a.png")),
+        );
+        let mut attachments = HashMap::new();
+        let mut missing = 0;
+        let nested = rewrite_images(&content, 2, Some(&images), &mut attachments, &mut missing);
+        assert_eq!(
+            nested.lines().next().unwrap(),
+            format!(
+                "![one](../../attachments/a.png) ![two](../../attachments/1-a.png) ![secret]({}) \
+                 ![web](https://example.com/a.png) ![ref][r]",
+                url(root.join("secret.png"))
+            )
+        );
+        // Files outside the images folder are never bundled
+        assert_eq!(missing, 1);
+        assert_eq!(attachments.len(), 2);
+
+        // The same file keeps its attachment name in other notes
+        let top = rewrite_images(&format!("![]({})", url(images.join("a.png"))), 0, Some(&images), &mut attachments, &mut missing);
+        assert_eq!(top, "![](attachments/a.png)");
+        assert_eq!(rewrite_images(&content, 0, None, &mut attachments, &mut missing), content);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -43,6 +43,14 @@ pub fn run_migrations(app_handle: &AppHandle) -> Result<(), Box<dyn std::error::
 
     let conn = Connection::open(&db_path)?;
 
+    migrate(&conn)?;
+    app_handle.manage(Mutex::new(conn));
+
+    Ok(())
+}
+
+/// Create the schema and apply every migration to `conn`.
+pub(crate) fn migrate(conn: &Connection) -> Result<(), Box<dyn std::error::Error>> {
     conn.execute_batch("PRAGMA journal_mode=WAL;")?;
     conn.execute_batch("PRAGMA foreign_keys=ON;")?;
 
@@ -452,7 +460,229 @@ pub fn run_migrations(app_handle: &AppHandle) -> Result<(), Box<dyn std::error::
         conn.execute_batch("ALTER TABLE tags ADD COLUMN is_pinned INTEGER NOT NULL DEFAULT 0;")?;
     }
 
-    app_handle.manage(Mutex::new(conn));
+    // Phase 16: Undoable tag changes (content snapshots per affected note)
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS tag_changes (
+            id TEXT PRIMARY KEY,
+            operation TEXT NOT NULL,
+            old_name TEXT NOT NULL,
+            new_name TEXT,
+            note_count INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            undone_at TEXT
+        );
+        CREATE TABLE IF NOT EXISTS tag_change_notes (
+            change_id TEXT NOT NULL REFERENCES tag_changes(id) ON DELETE CASCADE,
+            note_id TEXT NOT NULL REFERENCES notes(id) ON DELETE CASCADE,
+            previous_content TEXT NOT NULL,
+            previous_tags TEXT NOT NULL DEFAULT '[]',
+            version_after INTEGER NOT NULL,
+            PRIMARY KEY (change_id, note_id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_tag_changes_created ON tag_changes(created_at DESC);
+        ",
+    )?;
+
+    // Phase 17: Tag metadata, aliases and explicit parent tags
+    for (col, ddl) in [
+        ("description", "ALTER TABLE tags ADD COLUMN description TEXT NOT NULL DEFAULT '';"),
+        ("color", "ALTER TABLE tags ADD COLUMN color TEXT;"),
+        ("icon", "ALTER TABLE tags ADD COLUMN icon TEXT;"),
+    ] {
+        let has_col: bool = conn
+            .prepare("SELECT COUNT(*) FROM pragma_table_info('tags') WHERE name=?1")?
+            .query_row([col], |row| row.get::<_, i64>(0))
+            .unwrap_or(0)
+            > 0;
+        if !has_col {
+            conn.execute_batch(ddl)?;
+        }
+    }
+
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS tag_aliases (
+            alias TEXT PRIMARY KEY,
+            tag_name TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        );
+        ",
+    )?;
+
+    // Backfill rows for parent tags that only existed implicitly (e.g. "work" for "work/projects")
+    let tag_names: Vec<String> = conn
+        .prepare("SELECT name FROM tags WHERE parent_name IS NOT NULL")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .filter_map(|r| r.ok())
+        .collect();
+    for name in tag_names {
+        let mut parent = crate::markdown::tags::get_parent_tag(&name);
+        while let Some(p) = parent {
+            let grandparent = crate::markdown::tags::get_parent_tag(&p);
+            conn.execute(
+                "INSERT INTO tags (name, parent_name) VALUES (?1, ?2) ON CONFLICT(name) DO NOTHING",
+                rusqlite::params![p, grandparent],
+            )?;
+            parent = grandparent;
+        }
+    }
+
+    // Phase 18: Preserve unknown frontmatter keys across sync
+    let has_extra_fm_col: bool = conn
+        .prepare("SELECT COUNT(*) FROM pragma_table_info('notes') WHERE name='extra_frontmatter'")?
+        .query_row([], |row| row.get::<_, i64>(0))
+        .unwrap_or(0)
+        > 0;
+    if !has_extra_fm_col {
+        conn.execute_batch("ALTER TABLE notes ADD COLUMN extra_frontmatter TEXT;")?;
+    }
+
+    // Phase 19: Typed note properties (definitions per workspace, indexed values)
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS property_definitions (
+            id TEXT PRIMARY KEY,
+            workspace_id TEXT REFERENCES workspaces(id) ON DELETE CASCADE,
+            name TEXT NOT NULL,
+            property_type TEXT NOT NULL CHECK(property_type IN ('text', 'number', 'date', 'select', 'checkbox', 'note')),
+            options TEXT NOT NULL DEFAULT '[]',
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_property_definitions_scope
+            ON property_definitions(COALESCE(workspace_id, ''), name);
+
+        CREATE TABLE IF NOT EXISTS note_properties (
+            note_id TEXT NOT NULL REFERENCES notes(id) ON DELETE CASCADE,
+            name TEXT NOT NULL,
+            property_type TEXT NOT NULL,
+            value_text TEXT,
+            value_number REAL,
+            PRIMARY KEY (note_id, name)
+        );
+        CREATE INDEX IF NOT EXISTS idx_note_properties_text ON note_properties(name, value_text);
+        CREATE INDEX IF NOT EXISTS idx_note_properties_number ON note_properties(name, value_number);
+        ",
+    )?;
+
+    // Phase 20: Stored results of ```bruin-query blocks, readable by agents
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS note_query_results (
+            note_id TEXT NOT NULL REFERENCES notes(id) ON DELETE CASCADE,
+            block_index INTEGER NOT NULL,
+            query TEXT NOT NULL,
+            result TEXT NOT NULL,
+            evaluated_at TEXT NOT NULL,
+            PRIMARY KEY (note_id, block_index)
+        );
+        ",
+    )?;
+
+    // Phase 21: Tasks mirrored from checklist items in their linked note
+    let has_checklist_col: bool = conn
+        .prepare("SELECT COUNT(*) FROM pragma_table_info('tasks') WHERE name='checklist_position'")?
+        .query_row([], |row| row.get::<_, i64>(0))
+        .unwrap_or(0)
+        > 0;
+    if !has_checklist_col {
+        conn.execute_batch(
+            "
+            ALTER TABLE tasks ADD COLUMN checklist_position INTEGER;
+            CREATE INDEX IF NOT EXISTS idx_tasks_checklist ON tasks(linked_note_id, checklist_position);
+            ",
+        )?;
+    }
+
+    // Phase 22: Attachment registry, with the notes that refer to each file
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS attachments (
+            id TEXT PRIMARY KEY,
+            hash TEXT NOT NULL,
+            file_name TEXT NOT NULL UNIQUE,
+            original_name TEXT NOT NULL,
+            mime_type TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            created_at TEXT NOT NULL,
+            orphaned_at TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_attachments_hash ON attachments(hash);
+
+        CREATE TABLE IF NOT EXISTS note_attachments (
+            note_id TEXT NOT NULL REFERENCES notes(id) ON DELETE CASCADE,
+            attachment_id TEXT NOT NULL REFERENCES attachments(id) ON DELETE CASCADE,
+            PRIMARY KEY (note_id, attachment_id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_note_attachments_attachment ON note_attachments(attachment_id);
+        ",
+    )?;
+
+    // Phase 23: Full-text index of text extracted from attachments, one row
+    // per PDF page (or per file for other documents)
+    let has_indexed_col: bool = conn
+        .prepare("SELECT COUNT(*) FROM pragma_table_info('attachments') WHERE name='indexed_at'")?
+        .query_row([], |row| row.get::<_, i64>(0))
+        .unwrap_or(0)
+        > 0;
+    if !has_indexed_col {
+        conn.execute_batch(
+            "
+            ALTER TABLE attachments ADD COLUMN indexed_at TEXT;
+            ALTER TABLE attachments ADD COLUMN index_error TEXT;
+            ",
+        )?;
+    }
+    conn.execute_batch(
+        "
+        CREATE VIRTUAL TABLE IF NOT EXISTS attachment_fts USING fts5(
+            attachment_id UNINDEXED,
+            page UNINDEXED,
+            content
+        );
+
+        CREATE TRIGGER IF NOT EXISTS attachments_fts_delete AFTER DELETE ON attachments BEGIN
+            DELETE FROM attachment_fts WHERE attachment_id = old.id;
+        END;
+        ",
+    )?;
+
+    // Phase 24: Thumbnails of image attachments, for note list previews
+    let has_thumbnail_col: bool = conn
+        .prepare("SELECT COUNT(*) FROM pragma_table_info('attachments') WHERE name='thumbnail'")?
+        .query_row([], |row| row.get::<_, i64>(0))
+        .unwrap_or(0)
+        > 0;
+    if !has_thumbnail_col {
+        conn.execute("ALTER TABLE attachments ADD COLUMN thumbnail TEXT", [])?;
+    }
+
+    // Phase 25: Search index tokenizer from the `search_tokenizer` setting,
+    // and word counts that count Chinese and Japanese characters (redone
+    // once, marked by the `word_count_version` setting)
+    crate::commands::search::rebuild_search_index(conn)?;
+    let has_word_counts: bool = conn
+        .prepare("SELECT COUNT(*) FROM settings WHERE key = 'word_count_version'")?
+        .query_row([], |row| row.get::<_, i64>(0))
+        .unwrap_or(0)
+        > 0;
+    if !has_word_counts {
+        let notes: Vec<(String, String)> = conn
+            .prepare("SELECT id, content FROM notes")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        let tx = conn.unchecked_transaction()?;
+        for (id, content) in notes {
+            let words = crate::markdown::stats::text_stats(&content).words;
+            tx.execute("UPDATE notes SET word_count = ?1 WHERE id = ?2", rusqlite::params![words, id])?;
+        }
+        tx.execute(
+            "INSERT OR IGNORE INTO settings (key, value, updated_at) VALUES ('word_count_version', '1', ?1)",
+            [chrono::Utc::now().to_rfc3339()],
+        )?;
+        tx.commit()?;
+    }
 
     Ok(())
 }
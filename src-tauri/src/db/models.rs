@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Note {
@@ -16,6 +17,16 @@ pub struct Note {
     pub state: String,
     pub workspace_id: Option<String>,
     pub version: i32,
+    /// Frontmatter keys Bruin does not own, kept as a YAML mapping so they
+    /// survive a round-trip through the database.
+    #[serde(default)]
+    pub extra_frontmatter: Option<String>,
+    /// Characters other than whitespace.
+    #[serde(default)]
+    pub char_count: i64,
+    /// Estimated minutes to read the note.
+    #[serde(default)]
+    pub reading_minutes: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,6 +41,12 @@ pub struct NoteListItem {
     pub tags: Vec<String>,
     pub state: String,
     pub workspace_id: Option<String>,
+    /// Set when a search matched text in one of the note's attachments.
+    #[serde(default)]
+    pub attachment_match: Option<AttachmentMatch>,
+    /// Path of a preview of the first image in the note.
+    #[serde(default)]
+    pub thumbnail: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,7 +55,30 @@ pub struct Tag {
     pub name: String,
     pub parent_name: Option<String>,
     pub note_count: i64,
+    /// Distinct notes tagged with this tag or any of its descendants.
+    pub total_count: i64,
     pub is_pinned: bool,
+    pub description: String,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagAlias {
+    pub alias: String,
+    pub tag_name: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagChange {
+    pub id: String,
+    pub operation: String,
+    pub old_name: String,
+    pub new_name: Option<String>,
+    pub note_count: i64,
+    pub created_at: String,
+    pub undone_at: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -167,6 +207,317 @@ pub struct Workspace {
     pub updated_at: String,
 }
 
+// --- Note Properties ---
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PropertyDefinition {
+    pub id: String,
+    pub workspace_id: Option<String>,
+    pub name: String,
+    pub property_type: String,
+    pub options: Vec<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DefinePropertyParams {
+    pub workspace_id: Option<String>,
+    pub name: String,
+    pub property_type: String,
+    pub options: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteProperty {
+    pub name: String,
+    pub property_type: String,
+    pub value: serde_json::Value,
+}
+
+// --- Live Queries ---
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryRow {
+    /// Note id, or task id for `tasks` queries
+    pub id: String,
+    pub note_id: Option<String>,
+    pub title: String,
+    pub values: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryResult {
+    pub kind: String,
+    pub columns: Vec<String>,
+    pub rows: Vec<QueryRow>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteQueryResult {
+    pub block_index: i64,
+    pub query: String,
+    pub result: QueryResult,
+    pub evaluated_at: String,
+}
+
+// --- Document Outline ---
+
+/// One heading of a note. Offsets are byte offsets into the note content;
+/// a section runs from its heading to the next heading of the same or a
+/// higher level, and its word count includes any subsections.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutlineEntry {
+    pub level: u32,
+    pub heading: String,
+    pub anchor: String,
+    pub start: usize,
+    pub end: usize,
+    pub word_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteSection {
+    pub note_id: String,
+    pub note_title: String,
+    pub level: u32,
+    pub heading: String,
+    pub anchor: String,
+    /// The section's markdown, heading line included.
+    pub markdown: String,
+    pub start: usize,
+    pub end: usize,
+    pub word_count: i64,
+}
+
+/// A `[[Note#Heading]]` target resolved to a note and, when the heading
+/// exists, its section.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedLink {
+    pub note_id: String,
+    pub note_title: String,
+    pub section: Option<NoteSection>,
+}
+
+// --- Embeds ---
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenderedEmbed {
+    /// What is between the brackets of `![[...]]`
+    pub target: String,
+    pub note_id: Option<String>,
+    pub html: String,
+    pub error: Option<String>,
+}
+
+// --- HTML Export ---
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HtmlExportOptions {
+    /// One of the app's themes; the export uses its colors.
+    pub theme: Option<HtmlExportTheme>,
+    /// "inline" (default) embeds images as data URIs; "copy" copies them
+    /// into a folder next to `output_path`.
+    pub images: Option<String>,
+    /// Where the HTML file will be written. Required to copy images.
+    pub output_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HtmlExportTheme {
+    pub id: String,
+    /// Theme CSS variables, e.g. `--bear-text`.
+    pub colors: BTreeMap<String, String>,
+}
+
+// --- Site Publishing ---
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishSiteParams {
+    pub output_dir: String,
+    /// Only publish notes in this workspace; all workspaces if unset.
+    pub workspace_id: Option<String>,
+    /// Defaults to the workspace name.
+    pub site_title: Option<String>,
+    /// Absolute URL the site is served from, used for feed links.
+    pub base_url: Option<String>,
+    pub theme: Option<HtmlExportTheme>,
+    /// Render every page again, even those that have not changed.
+    pub full_rebuild: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishSiteResult {
+    pub output_dir: String,
+    pub pages_written: usize,
+    pub pages_unchanged: usize,
+    pub pages_removed: usize,
+    pub tag_pages: usize,
+}
+
+// --- Document Export ---
+
+/// Notes for a PDF or EPUB export: exactly one of `note_id`, `tag` (with its
+/// subtags) or `workspace_id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentExportParams {
+    pub note_id: Option<String>,
+    pub tag: Option<String>,
+    pub workspace_id: Option<String>,
+    pub output_path: String,
+    /// Defaults to the note title, tag or workspace name.
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentExportResult {
+    pub output_path: String,
+    pub notes: usize,
+    /// Page count, for PDFs.
+    pub pages: Option<usize>,
+    /// Problems that did not stop the export, such as characters no font
+    /// could show.
+    pub warnings: Vec<String>,
+}
+
+// --- Vault Export ---
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultExportParams {
+    /// Zip file to write, or an empty folder for "directory".
+    pub output_path: String,
+    /// "zip" (default) or "directory".
+    pub format: Option<String>,
+    /// Only export this workspace's notes; all notes if unset.
+    pub workspace_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultExportResult {
+    pub output_path: String,
+    pub notes: usize,
+    pub attachments: usize,
+    /// Local images referenced by notes that could not be found.
+    pub missing_attachments: usize,
+}
+
+// --- Import ---
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportOptions {
+    /// Also import from subfolders of selected folders. Defaults to true.
+    pub recursive: Option<bool>,
+    /// How subfolders are kept: "tags" (default) tags each note with its
+    /// folder path, "workspaces" puts each top-level folder in a workspace
+    /// of that name (deeper folders become tags), "none" ignores them.
+    pub folders: Option<String>,
+    /// Workspace for notes that folder mapping does not place.
+    pub workspace_id: Option<String>,
+    /// Only report what would happen; nothing is written.
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub imported: u32,
+    pub updated: u32,
+    pub skipped: u32,
+    /// Tasks made (or to be made) for to-do notes.
+    pub tasks: u32,
+    pub files: Vec<ImportFilePlan>,
+}
+
+/// What an import does (or would do) with one file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportFilePlan {
+    pub path: String,
+    /// "import", "update" or "skip".
+    pub action: String,
+    pub reason: String,
+    pub title: String,
+    /// The new or updated note, or the existing note a duplicate matches.
+    pub note_id: Option<String>,
+    pub tags: Vec<String>,
+    pub workspace: Option<String>,
+    /// Attachments copied into the images store.
+    pub attachments: u32,
+}
+
+// --- Clipper ---
+
+/// A web page to clip: pasted `html` (e.g. from the clipboard), a saved
+/// page at `path`, or else the page at `url`, fetched. With `html` or
+/// `path`, `url` is recorded as the source and resolves relative links.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClipParams {
+    pub url: Option<String>,
+    pub html: Option<String>,
+    pub path: Option<String>,
+    pub workspace_id: Option<String>,
+}
+
+// --- Attachments ---
+
+/// A file in the images store. Files are stored once per content.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: String,
+    /// SHA-256 of the file, hex.
+    pub hash: String,
+    /// Name in the images store.
+    pub file_name: String,
+    /// Name the file was saved or imported with.
+    pub original_name: String,
+    pub mime_type: String,
+    pub size: i64,
+    pub created_at: String,
+    /// Since when no note refers to it; it is deleted after a grace period.
+    pub orphaned_at: Option<String>,
+    pub path: String,
+    /// Notes referring to it, trashed ones included.
+    pub note_count: i64,
+    /// Path of its preview, for images.
+    pub thumbnail: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentNote {
+    pub id: String,
+    pub title: String,
+    pub is_trashed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentDetails {
+    pub attachment: Attachment,
+    pub notes: Vec<AttachmentNote>,
+    /// Whether the file is still in the images store.
+    pub exists: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AttachmentGcReport {
+    /// Files found in the images store that were not registered yet.
+    pub registered: u32,
+    /// Attachments newly found unused.
+    pub orphaned: u32,
+    /// Attachments deleted after being unused for the grace period.
+    pub removed: u32,
+    pub freed_bytes: i64,
+}
+
+/// Where a search matched the extracted text of an attachment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentMatch {
+    pub attachment_id: String,
+    pub name: String,
+    /// 1-based page, for PDFs.
+    pub page: Option<u32>,
+    pub snippet: String,
+}
+
 // --- Knowledge Graph ---
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -190,6 +541,18 @@ pub struct KnowledgeGraph {
     pub edges: Vec<GraphEdge>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportGraphParams {
+    /// One of "graphml", "dot" or "jsonld".
+    pub format: String,
+    pub center_note_id: Option<String>,
+    pub depth: Option<i32>,
+    pub max_nodes: Option<i32>,
+    pub tag: Option<String>,
+    pub workspace_id: Option<String>,
+    pub state: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateNoteParams {
     pub title: Option<String>,
@@ -205,6 +568,53 @@ pub struct UpdateNoteParams {
     pub expected_version: Option<i32>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatchNoteParams {
+    pub id: String,
+    pub operations: Vec<PatchOperation>,
+    pub expected_version: Option<i32>,
+}
+
+/// One edit in a `patch_note` call. Heading paths name nested headings
+/// separated by `>`, e.g. `"Projects > Alpha"`; without a heading, append and
+/// prepend apply to the whole note.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum PatchOperation {
+    Append {
+        heading: Option<String>,
+        text: String,
+    },
+    Prepend {
+        heading: Option<String>,
+        text: String,
+    },
+    ReplaceSection {
+        heading: String,
+        text: String,
+    },
+    /// Insert after the first line containing `after`.
+    InsertAfter {
+        after: String,
+        text: String,
+    },
+    /// Replace `find` (a regex when `regex` is set) with `replace`. All
+    /// matches are replaced unless `count` (at least 1) limits them.
+    Replace {
+        find: String,
+        replace: String,
+        #[serde(default)]
+        regex: bool,
+        count: Option<usize>,
+    },
+    /// Tick or untick the checklist item whose text matches `item`, or
+    /// flip it when `checked` is omitted.
+    ToggleChecklist {
+        item: String,
+        checked: Option<bool>,
+    },
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListNotesParams {
     pub tag: Option<String>,
@@ -215,6 +625,17 @@ pub struct ListNotesParams {
     pub offset: Option<i64>,
     pub trashed: Option<bool>,
     pub workspace_id: Option<String>,
+    pub property_filters: Option<Vec<PropertyFilter>>,
+    pub sort_property: Option<String>,
+}
+
+/// A condition on a typed note property, e.g. `status eq "blocked"` or
+/// `due lt "today"`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PropertyFilter {
+    pub name: String,
+    pub op: String,
+    pub value: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
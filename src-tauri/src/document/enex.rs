@@ -0,0 +1,249 @@
+use crate::document::external::{ExternalNote, ExternalTodo};
+use crate::document::html::{html_to_markdown, parse_html};
+use crate::document::xml::{XmlEvent, XmlReader};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use std::collections::HashMap;
+use std::io::BufRead;
+
+/// An attachment of an Evernote note.
+struct Resource {
+    data: Vec<u8>,
+    mime: String,
+    file_name: Option<String>,
+}
+
+#[derive(Default)]
+struct NoteBuilder {
+    title: String,
+    enml: String,
+    created: Option<String>,
+    updated: Option<String>,
+    tags: Vec<String>,
+    resources: Vec<Resource>,
+    reminder: Option<String>,
+    reminder_done: bool,
+}
+
+/// Read the notes of an Evernote export (`.enex`) one at a time, calling
+/// `on_note` for each. `notebook` is the notebook the file was exported
+/// from, which ENEX does not record.
+pub fn read_enex<R: BufRead>(
+    reader: R,
+    notebook: &str,
+    on_note: &mut dyn FnMut(ExternalNote),
+) -> Result<(), String> {
+    let mut xml = XmlReader::new(reader);
+    let mut note: Option<NoteBuilder> = None;
+    // Elements open inside the current note
+    let mut path: Vec<String> = Vec::new();
+
+    while let Some(event) = xml.next_event()? {
+        match event {
+            XmlEvent::Start { name, .. } if name == "note" && note.is_none() => {
+                note = Some(NoteBuilder::default());
+                path.clear();
+            }
+            XmlEvent::Start { name, .. } => {
+                let Some(ref mut builder) = note else {
+                    continue;
+                };
+                let parent = path.last().map(String::as_str);
+                match (parent, name.as_str()) {
+                    (None, "title") => builder.title = xml.read_text()?.trim().to_string(),
+                    (None, "content") => builder.enml = xml.read_text()?,
+                    (None, "created") => builder.created = enex_time(&xml.read_text()?),
+                    (None, "updated") => builder.updated = enex_time(&xml.read_text()?),
+                    (None, "tag") => builder.tags.push(xml.read_text()?.trim().to_string()),
+                    (None, "resource") => builder.resources.push(read_resource(&mut xml)?),
+                    (Some("note-attributes"), "reminder-time") => builder.reminder = enex_time(&xml.read_text()?),
+                    (Some("note-attributes"), "reminder-done-time") => {
+                        xml.read_text()?;
+                        builder.reminder_done = true;
+                    }
+                    _ => path.push(name),
+                }
+            }
+            XmlEvent::End(name) if name == "note" && path.is_empty() => {
+                if let Some(builder) = note.take() {
+                    on_note(build_note(builder, notebook));
+                }
+            }
+            XmlEvent::End(_) => {
+                path.pop();
+            }
+            XmlEvent::Text(_) => {}
+        }
+    }
+    Ok(())
+}
+
+fn read_resource<R: BufRead>(xml: &mut XmlReader<R>) -> Result<Resource, String> {
+    let mut resource = Resource {
+        data: Vec::new(),
+        mime: String::new(),
+        file_name: None,
+    };
+    let mut depth = 0;
+    while let Some(event) = xml.next_event()? {
+        match event {
+            XmlEvent::Start { name, .. } => match name.as_str() {
+                "data" => {
+                    let encoded: String = xml.read_text()?.chars().filter(|c| !c.is_whitespace()).collect();
+                    resource.data = BASE64.decode(encoded).map_err(|e| format!("Invalid attachment data: {}", e))?;
+                }
+                "mime" => resource.mime = xml.read_text()?.trim().to_string(),
+                "file-name" => resource.file_name = Some(xml.read_text()?.trim().to_string()),
+                _ => depth += 1,
+            },
+            XmlEvent::End(_) if depth == 0 => break,
+            XmlEvent::End(_) => depth -= 1,
+            XmlEvent::Text(_) => {}
+        }
+    }
+    Ok(resource)
+}
+
+/// ENEX times look like `20240131T093000Z`.
+fn enex_time(s: &str) -> Option<String> {
+    let time = NaiveDateTime::parse_from_str(s.trim(), "%Y%m%dT%H%M%SZ").ok()?;
+    Some(Utc.from_utc_datetime(&time).to_rfc3339())
+}
+
+fn build_note(builder: NoteBuilder, notebook: &str) -> ExternalNote {
+    // en-media elements point at resources by the MD5 of their data
+    let mut media = HashMap::new();
+    let mut assets = HashMap::new();
+    for resource in builder.resources {
+        let hash = md5_hex(&resource.data);
+        let file_name = resource
+            .file_name
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| format!("{}.{}", &hash[..8], mime_extension(&resource.mime)));
+        let key = format!("resources/{}/{}", hash, file_name);
+        media.insert(hash, (key.clone(), resource.mime.starts_with("image/"), file_name));
+        assets.insert(key, resource.data);
+    }
+
+    let content = html_to_markdown(&parse_html(&builder.enml), &media);
+    ExternalNote {
+        app: "Evernote",
+        id: None,
+        title: builder.title,
+        content,
+        tags: builder.tags,
+        notebook: vec![notebook.to_string()],
+        created_at: builder.created,
+        updated_at: builder.updated,
+        assets,
+        todo: builder.reminder.map(|due| ExternalTodo {
+            done: builder.reminder_done,
+            due_date: due.get(..10).map(String::from),
+        }),
+        skip: None,
+        ..Default::default()
+    }
+}
+
+fn mime_extension(mime: &str) -> &str {
+    match mime {
+        "image/jpeg" => "jpg",
+        "image/svg+xml" => "svg",
+        "application/pdf" => "pdf",
+        "text/plain" => "txt",
+        other => other.rsplit('/').next().filter(|e| e.chars().all(|c| c.is_ascii_alphanumeric())).unwrap_or("bin"),
+    }
+}
+
+/// MD5 digest as lowercase hex; Evernote identifies resources by it.
+fn md5_hex(data: &[u8]) -> String {
+    const S: [u32; 64] = [
+        7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9,
+        14, 20, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15,
+        21, 6, 10, 15, 21,
+    ];
+    let k: Vec<u32> = (0..64).map(|i| ((i as f64 + 1.0).sin().abs() * 4_294_967_296.0) as u32).collect();
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+    let mut state: [u32; 4] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476];
+    for chunk in message.chunks(64) {
+        let m: Vec<u32> = chunk.chunks(4).map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]])).collect();
+        let [mut a, mut b, mut c, mut d] = state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let rotated = a.wrapping_add(f).wrapping_add(k[i]).wrapping_add(m[g]).rotate_left(S[i]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(rotated);
+        }
+        state[0] = state[0].wrapping_add(a);
+        state[1] = state[1].wrapping_add(b);
+        state[2] = state[2].wrapping_add(c);
+        state[3] = state[3].wrapping_add(d);
+    }
+    state.iter().flat_map(|s| s.to_le_bytes()).map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_enex() {
+        let enex = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE en-export SYSTEM "http://xml.evernote.com/pub/evernote-export4.dtd">
+<en-export>
+<note><title>Trip &amp; plans</title>
+<content><![CDATA[<?xml version="1.0" encoding="UTF-8"?><!DOCTYPE en-note SYSTEM "http://xml.evernote.com/pub/enml2.dtd">
+<en-note><h2>Packing</h2><div><en-todo checked="true"/>Passport</div><div><en-todo/>Charger *2</div>
+<div>See <b>this</b> <a href="https://example.com">site</a> #not-a-tag<br/></div>
+<ul><li><div>One</div><ul><li>Nested</li></ul></li><li>Two</li></ul>
+<div><en-media hash="{}" type="image/png"/></div>
+<table><tr><td>a</td><td>b|c</td></tr><tr><td>1</td></tr></table></en-note>]]></content>
+<created>20240131T093000Z</created><updated>20240201T100000Z</updated>
+<tag>travel</tag><tag>to do</tag>
+<note-attributes><reminder-time>20240301T080000Z</reminder-time></note-attributes>
+<resource><data encoding="base64">aGVs
+bG8=</data><mime>image/png</mime><resource-attributes><file-name>pic.png</file-name></resource-attributes></resource>
+</note>
+</en-export>"#,
+            md5_hex(b"hello")
+        );
+        let mut notes = Vec::new();
+        read_enex(enex.as_bytes(), "Travel", &mut |note| notes.push(note)).unwrap();
+
+        assert_eq!(md5_hex(b"hello"), "5d41402abc4b2a76b9719d911017c592");
+        assert_eq!(notes.len(), 1);
+        let note = &notes[0];
+        assert_eq!(note.title, "Trip & plans");
+        assert_eq!(note.tags, vec!["travel", "to do"]);
+        assert_eq!(note.notebook, vec!["Travel"]);
+        assert_eq!(note.created_at.as_deref(), Some("2024-01-31T09:30:00+00:00"));
+        assert_eq!(note.todo, Some(ExternalTodo { done: false, due_date: Some("2024-03-01".to_string()) }));
+        let key = format!("resources/{}/pic.png", md5_hex(b"hello"));
+        assert_eq!(note.assets[&key], b"hello");
+        assert_eq!(
+            note.content,
+            format!(
+                "## Packing\n\n- [x] Passport\n- [ ] Charger \\*2\n\n\
+                 See **this** [site](https://example.com) \\#not-a-tag\n\n\
+                 - One\n  - Nested\n- Two\n\n![]({})\n\n| a | b\\|c |\n| --- | --- |\n| 1 |  |\n",
+                key
+            )
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use crate::document::zip::ZipWriter;
+use regex::Regex;
+
+const STYLESHEET: &str = "body { font-family: serif; line-height: 1.5; margin: 0 5%; }
+h1, h2, h3, h4 { font-family: sans-serif; line-height: 1.25; }
+pre, code { font-family: monospace; font-size: 0.9em; }
+pre { background: #f4f4f4; padding: 0.6em; white-space: pre-wrap; }
+blockquote { border-left: 3px solid #ccc; margin-left: 0; padding-left: 1em; color: #555; }
+img { max-width: 100%; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.25em 0.5em; }
+.tag { color: #666; }
+.wiki-link.missing { color: #999; }
+";
+
+/// One note of the book, already rendered to an HTML fragment.
+pub struct EpubChapter {
+    pub title: String,
+    /// File name inside the book, e.g. `chapter-001.xhtml`.
+    pub file: String,
+    pub html: String,
+}
+
+pub struct EpubImage {
+    /// Path inside the book relative to the chapters, e.g. `images/image-0.png`.
+    pub path: String,
+    pub mime: &'static str,
+    pub data: Vec<u8>,
+}
+
+/// Package chapters and images as an EPUB 3 book with a navigation document.
+pub fn write_epub(
+    id: &str,
+    title: &str,
+    modified: &str,
+    chapters: &[EpubChapter],
+    images: &[EpubImage],
+) -> Result<Vec<u8>, String> {
+    let mut zip = ZipWriter::new();
+    zip.add_stored("mimetype", b"application/epub+zip");
+    zip.add(
+        "META-INF/container.xml",
+        br#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#,
+    )?;
+
+    let mut manifest = String::from(
+        "    <item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n    \
+         <item id=\"style\" href=\"style.css\" media-type=\"text/css\"/>\n",
+    );
+    let mut spine = String::new();
+    let mut nav = String::new();
+    for (i, chapter) in chapters.iter().enumerate() {
+        manifest.push_str(&format!(
+            "    <item id=\"chapter-{}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>\n",
+            i,
+            xml_escape(&chapter.file)
+        ));
+        spine.push_str(&format!("    <itemref idref=\"chapter-{}\"/>\n", i));
+        nav.push_str(&format!(
+            "      <li><a href=\"{}\">{}</a></li>\n",
+            xml_escape(&chapter.file),
+            xml_escape(&chapter.title)
+        ));
+        zip.add(
+            &format!("OEBPS/{}", chapter.file),
+            xhtml_page(&chapter.title, &format!("<h1>{}</h1>\n{}", xml_escape(&chapter.title), chapter.html)).as_bytes(),
+        )?;
+    }
+    for (i, image) in images.iter().enumerate() {
+        manifest.push_str(&format!(
+            "    <item id=\"image-{}\" href=\"{}\" media-type=\"{}\"/>\n",
+            i,
+            xml_escape(&image.path),
+            image.mime
+        ));
+        zip.add(&format!("OEBPS/{}", image.path), &image.data)?;
+    }
+
+    let package = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="book-id">urn:uuid:{}</dc:identifier>
+    <dc:title>{}</dc:title>
+    <dc:language>en</dc:language>
+    <meta property="dcterms:modified">{}</meta>
+  </metadata>
+  <manifest>
+{}  </manifest>
+  <spine>
+{}  </spine>
+</package>
+"#,
+        xml_escape(id),
+        xml_escape(title),
+        xml_escape(modified),
+        manifest,
+        spine
+    );
+    zip.add("OEBPS/content.opf", package.as_bytes())?;
+
+    let nav_body = format!(
+        "<nav epub:type=\"toc\" id=\"toc\">\n  <h1>Contents</h1>\n  <ol>\n{}  </ol>\n</nav>",
+        nav
+    );
+    zip.add("OEBPS/nav.xhtml", xhtml_page(title, &nav_body).as_bytes())?;
+    zip.add("OEBPS/style.css", STYLESHEET.as_bytes())?;
+    Ok(zip.finish())
+}
+
+fn xhtml_page(title: &str, body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\">\n\
+         <head>\n<meta charset=\"UTF-8\"/>\n<title>{}</title>\n\
+         <link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\"/>\n</head>\n<body>\n{}\n</body>\n</html>\n",
+        xml_escape(title),
+        to_xhtml(body)
+    )
+}
+
+/// Make rendered HTML well-formed XML: void elements are self-closed,
+/// `&nbsp;` becomes a numeric reference and other HTML-only entities are
+/// shown literally.
+fn to_xhtml(html: &str) -> String {
+    let void_re = Regex::new(r"<(br|hr|img|input|col|wbr)\b((?:[^>/]|/[^>])*)/?>").unwrap();
+    let html = void_re.replace_all(html, "<$1$2/>");
+    let entity_re = Regex::new(r"&([a-zA-Z][a-zA-Z0-9]*);").unwrap();
+    entity_re
+        .replace_all(&html, |cap: &regex::Captures| match &cap[1] {
+            "amp" | "lt" | "gt" | "quot" | "apos" => cap[0].to_string(),
+            "nbsp" => "&#160;".to_string(),
+            _ => format!("&amp;{};", &cap[1]),
+        })
+        .into_owned()
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_xhtml() {
+        assert_eq!(
+            to_xhtml("<p>a<br>b&nbsp;<img src=\"x.png\" alt=\"\"></p><hr />"),
+            "<p>a<br/>b&#160;<img src=\"x.png\" alt=\"\"/></p><hr />"
+        );
+        assert_eq!(to_xhtml("&copy; &amp; &lt;"), "&amp;copy; &amp; &lt;");
+    }
+}
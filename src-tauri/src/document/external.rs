@@ -0,0 +1,35 @@
+use std::collections::HashMap;
+
+/// A note read from another app's export. Attachments are linked from the
+/// markdown content by their key in `assets`, e.g. `![](<resources/a1/photo.png>)`.
+#[derive(Debug, Default)]
+pub struct ExternalNote {
+    /// The app the note was exported from, e.g. `Joplin`.
+    pub app: &'static str,
+    /// The note's id in the other app, kept so a re-import updates it.
+    pub id: Option<String>,
+    pub title: String,
+    pub content: String,
+    pub tags: Vec<String>,
+    /// Notebook path, outermost first.
+    pub notebook: Vec<String>,
+    /// Folders the note sat in within the export, outermost first. Unlike
+    /// notebooks, these map to tags or workspaces as folders on disk do.
+    pub folders: Vec<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    pub assets: HashMap<String, Vec<u8>>,
+    pub todo: Option<ExternalTodo>,
+    /// Properties as (name, value), in the export's order.
+    pub properties: Vec<(String, String)>,
+    /// Why the note is not imported, e.g. it is encrypted.
+    pub skip: Option<String>,
+}
+
+/// A note the other app treats as a to-do, imported as a task.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalTodo {
+    pub done: bool,
+    /// `YYYY-MM-DD`
+    pub due_date: Option<String>,
+}
@@ -0,0 +1,511 @@
+use crate::document::xml::{XmlEvent, XmlReader};
+use std::collections::HashMap;
+
+/// An element or text of a parsed HTML (or ENML) document.
+#[derive(Debug, Clone)]
+pub enum Node {
+    Element {
+        name: String,
+        attrs: Vec<(String, String)>,
+        children: Vec<Node>,
+    },
+    Text(String),
+}
+
+impl Node {
+    /// The element name, lowercased; empty for text.
+    pub fn name(&self) -> &str {
+        match self {
+            Node::Element { name, .. } => name,
+            Node::Text(_) => "",
+        }
+    }
+
+    pub fn attr(&self, key: &str) -> Option<&str> {
+        match self {
+            Node::Element { attrs, .. } => attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str()),
+            Node::Text(_) => None,
+        }
+    }
+
+    pub fn set_attr(&mut self, key: &str, value: String) {
+        if let Node::Element { attrs, .. } = self {
+            match attrs.iter_mut().find(|(k, _)| k == key) {
+                Some(attr) => attr.1 = value,
+                None => attrs.push((key.to_string(), value)),
+            }
+        }
+    }
+
+    pub fn children(&self) -> &[Node] {
+        match self {
+            Node::Element { children, .. } => children,
+            Node::Text(_) => &[],
+        }
+    }
+
+    fn style_has(&self, property: &str) -> bool {
+        self.attr("style").is_some_and(|s| s.replace(' ', "").contains(property))
+    }
+}
+
+/// Elements without content or an end tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source", "track", "wbr",
+];
+
+/// Elements whose content is code or form state rather than text, dropped
+/// before parsing so a `<` in a script doesn't read as markup.
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "noscript", "template", "textarea", "svg", "math", "iframe"];
+
+/// Elements closed by the start of another of the same group, e.g. an open
+/// `<li>` by the next `<li>`, unless one of the scope elements is nearer.
+const IMPLIED_ENDS: &[(&[&str], &[&str])] = &[
+    (&["li"], &["ul", "ol"]),
+    (&["dt", "dd"], &["dl"]),
+    (&["tr"], &["table", "thead", "tbody", "tfoot"]),
+    (&["td", "th"], &["tr", "table"]),
+    (&["thead", "tbody", "tfoot"], &["table"]),
+    (&["option"], &["select"]),
+];
+
+/// Parse HTML (or ENML, which is XHTML) into a tree, as forgiving as a
+/// browser about what real pages get wrong: void and unclosed elements,
+/// stray end tags, unquoted attributes and uppercase names. Names are
+/// lowercased.
+pub fn parse_html(html: &str) -> Vec<Node> {
+    let html = strip_raw_text(html);
+    let mut xml = XmlReader::new(html.as_bytes());
+    // Open elements, innermost last; the first holds the top-level nodes
+    let mut stack: Vec<Node> = vec![element(String::new(), Vec::new())];
+    let close = |stack: &mut Vec<Node>| {
+        if let Some(node) = stack.pop() {
+            if let Some(Node::Element { children, .. }) = stack.last_mut() {
+                children.push(node);
+            }
+        }
+    };
+    // Markup cut off at the end just ends the document
+    while let Ok(Some(event)) = xml.next_event() {
+        match event {
+            XmlEvent::Start { name, attrs } => {
+                let name = name.to_ascii_lowercase();
+                let attrs = attrs.into_iter().map(|(k, v)| (k.to_ascii_lowercase(), v)).collect();
+                if let Some(depth) = implied_end(&stack, &name) {
+                    while stack.len() > depth {
+                        close(&mut stack);
+                    }
+                }
+                stack.push(element(name.clone(), attrs));
+                if VOID_ELEMENTS.contains(&name.as_str()) {
+                    close(&mut stack);
+                }
+            }
+            XmlEvent::End(name) => {
+                let name = name.to_ascii_lowercase();
+                // A stray end tag (or one for an element closed already) is ignored
+                if let Some(depth) = stack.iter().skip(1).rposition(|n| n.name() == name) {
+                    while stack.len() > depth + 1 {
+                        close(&mut stack);
+                    }
+                }
+            }
+            XmlEvent::Text(text) => {
+                if let Some(Node::Element { children, .. }) = stack.last_mut() {
+                    children.push(Node::Text(text));
+                }
+            }
+        }
+    }
+    while stack.len() > 1 {
+        close(&mut stack);
+    }
+    match stack.pop() {
+        Some(Node::Element { children, .. }) => children,
+        _ => Vec::new(),
+    }
+}
+
+fn element(name: String, attrs: Vec<(String, String)>) -> Node {
+    Node::Element {
+        name,
+        attrs,
+        children: Vec::new(),
+    }
+}
+
+/// The stack depth to close back to before `name` starts: an open element
+/// it implicitly ends, or a `<p>` a block element ends.
+fn implied_end(stack: &[Node], name: &str) -> Option<usize> {
+    if let Some((group, scope)) = IMPLIED_ENDS.iter().find(|(group, _)| group.contains(&name)) {
+        for (i, node) in stack.iter().enumerate().skip(1).rev() {
+            if scope.contains(&node.name()) {
+                return None;
+            }
+            if group.contains(&node.name()) {
+                return Some(i);
+            }
+        }
+        return None;
+    }
+    let ends_paragraph = name == "p" || (BLOCK_ELEMENTS.contains(&name) && name != "div") || name == "li";
+    (ends_paragraph && stack.last().is_some_and(|n| n.name() == "p")).then(|| stack.len() - 1)
+}
+
+/// Remove raw text elements with their content.
+fn strip_raw_text(html: &str) -> String {
+    let lower = html.to_ascii_lowercase();
+    let mut out = String::with_capacity(html.len());
+    let mut pos = 0;
+    while let Some((start, name)) = RAW_TEXT_ELEMENTS
+        .iter()
+        .filter_map(|name| {
+            let open = format!("<{}", name);
+            let mut from = pos;
+            // `<style` must not match `<styles>`
+            while let Some(i) = lower[from..].find(&open) {
+                let at = from + i;
+                let next = lower.as_bytes().get(at + open.len()).copied().unwrap_or(b'>');
+                if !next.is_ascii_alphanumeric() && next != b'-' {
+                    return Some((at, *name));
+                }
+                from = at + open.len();
+            }
+            None
+        })
+        .min_by_key(|(at, _)| *at)
+    {
+        out.push_str(&html[pos..start]);
+        let close = format!("</{}", name);
+        pos = match lower[start..].find(&close) {
+            Some(i) => lower[start + i..].find('>').map(|j| start + i + j + 1).unwrap_or(html.len()),
+            None => html.len(),
+        };
+    }
+    out.push_str(&html[pos..]);
+    out
+}
+
+/// Convert parsed HTML to markdown. `media` maps Evernote resource hashes
+/// to the asset key, whether it is an image, and its file name, for ENML's
+/// `<en-media>`; other documents pass an empty map.
+pub fn html_to_markdown(nodes: &[Node], media: &HashMap<String, (String, bool, String)>) -> String {
+    let converter = MarkdownConverter { media };
+    join_blocks(converter.blocks(nodes))
+}
+
+/// A converted block, and whether it is a list line that should not be
+/// separated from a neighbouring one by a blank line.
+type Block = (String, bool);
+
+fn join_blocks(blocks: Vec<Block>) -> String {
+    let mut out = String::new();
+    let mut prev_tight = false;
+    for (i, (text, tight)) in blocks.iter().enumerate() {
+        if i > 0 {
+            out.push_str(if prev_tight && *tight { "\n" } else { "\n\n" });
+        }
+        out.push_str(text);
+        prev_tight = *tight;
+    }
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+struct MarkdownConverter<'a> {
+    media: &'a HashMap<String, (String, bool, String)>,
+}
+
+const BLOCK_ELEMENTS: &[&str] = &[
+    "en-note", "html", "body", "div", "p", "h1", "h2", "h3", "h4", "h5", "h6", "ul", "ol", "blockquote", "pre",
+    "table", "hr", "center", "section", "article", "main", "header", "footer", "aside", "nav", "figure",
+    "figcaption", "details", "summary", "address", "dl", "dt", "dd",
+];
+
+impl MarkdownConverter<'_> {
+    fn blocks(&self, nodes: &[Node]) -> Vec<Block> {
+        let mut blocks = Vec::new();
+        let mut inline = String::new();
+        for node in nodes {
+            let Node::Element { name, children, .. } = node else {
+                inline.push_str(&self.inline(node));
+                continue;
+            };
+            if !BLOCK_ELEMENTS.contains(&name.as_str()) {
+                inline.push_str(&self.inline(node));
+                continue;
+            }
+            flush_inline(&mut inline, &mut blocks);
+            match name.as_str() {
+                "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                    let level = name[1..].parse().unwrap_or(1);
+                    let text = collapse(&self.inlines(children));
+                    if !text.is_empty() {
+                        blocks.push((format!("{} {}", "#".repeat(level), text), false));
+                    }
+                }
+                "ul" | "ol" => {
+                    let list = self.list(node);
+                    if !list.is_empty() {
+                        blocks.push((list, true));
+                    }
+                }
+                "blockquote" => {
+                    let inner = join_blocks(self.blocks(children));
+                    let quoted: Vec<String> = inner.trim_end().lines().map(|l| format!("> {}", l).trim_end().to_string()).collect();
+                    if !quoted.is_empty() {
+                        blocks.push((quoted.join("\n"), false));
+                    }
+                }
+                "pre" => blocks.push((fence(&code_text(children)), false)),
+                "div" if node.style_has("en-codeblock:true") => blocks.push((fence(&code_text(children)), false)),
+                "table" => {
+                    let table = self.table(node);
+                    if !table.is_empty() {
+                        blocks.push((table, false));
+                    }
+                }
+                "hr" => blocks.push(("---".to_string(), false)),
+                _ => blocks.extend(self.blocks(children)),
+            }
+        }
+        flush_inline(&mut inline, &mut blocks);
+        blocks
+    }
+
+    fn inlines(&self, nodes: &[Node]) -> String {
+        nodes.iter().map(|n| self.inline(n)).collect()
+    }
+
+    fn inline(&self, node: &Node) -> String {
+        let (name, children) = match node {
+            // Line breaks in the source are spaces; only `<br>` breaks lines
+            Node::Text(text) => return escape_text(&text.replace(['\u{a0}', '\n'], " ")),
+            Node::Element { name, children, .. } => (name.as_str(), children),
+        };
+        match name {
+            "br" => "\n".to_string(),
+            "b" | "strong" => wrap(&self.inlines(children), "**"),
+            "i" | "em" => wrap(&self.inlines(children), "*"),
+            "s" | "strike" | "del" => wrap(&self.inlines(children), "~~"),
+            "code" | "tt" => {
+                let code = text_content(children);
+                if code.is_empty() {
+                    code
+                } else if code.contains('`') {
+                    format!("`` {} ``", code)
+                } else {
+                    format!("`{}`", code)
+                }
+            }
+            "a" => {
+                let text = collapse(&self.inlines(children));
+                match node.attr("href").unwrap_or("") {
+                    "" => text,
+                    // Links between Evernote notes become wiki links by title
+                    href if href.starts_with("evernote:") => format!("[[{}]]", text_content(children).trim()),
+                    href if text.is_empty() => format!("<{}>", href),
+                    href => format!("[{}]({})", text, link_dest(href)),
+                }
+            }
+            "en-media" => match node.attr("hash").and_then(|h| self.media.get(h)) {
+                Some((key, true, _)) => format!("![]({})", link_dest(key)),
+                Some((key, false, file_name)) => format!("[{}]({})", escape_text(file_name), link_dest(key)),
+                None => String::new(),
+            },
+            "img" => match node.attr("src") {
+                Some(src) => format!("![{}]({})", escape_text(node.attr("alt").unwrap_or("")), link_dest(src)),
+                None => String::new(),
+            },
+            "en-todo" => {
+                if node.attr("checked") == Some("true") { "[x] " } else { "[ ] " }.to_string()
+            }
+            "en-crypt" => "*[encrypted content]*".to_string(),
+            _ if BLOCK_ELEMENTS.contains(&name) => format!(" {} ", self.inlines(children)),
+            _ => self.inlines(children),
+        }
+    }
+
+    /// A list as markdown lines; nested lists are indented under their item.
+    fn list(&self, node: &Node) -> String {
+        let Node::Element { name, children, .. } = node else {
+            return String::new();
+        };
+        let is_todo = node.style_has("--en-todo:true");
+        let mut lines = Vec::new();
+        let items = children.iter().filter(|c| matches!(c, Node::Element { name, .. } if name == "li"));
+        for (i, item) in items.enumerate() {
+            let Node::Element { children, .. } = item else {
+                continue;
+            };
+            let marker = if is_todo {
+                if item.style_has("--en-checked:true") { "- [x] " } else { "- [ ] " }.to_string()
+            } else if name == "ol" {
+                format!("{}. ", i + 1)
+            } else {
+                "- ".to_string()
+            };
+            let blocks = self.blocks(children);
+            let mut text = blocks.first().map(|(t, _)| t.clone()).unwrap_or_default();
+            // A todo inside an item is already a list line
+            if let Some(rest) = text.strip_prefix("- [") {
+                text = format!("[{}", rest);
+            }
+            lines.push(format!("{}{}", marker, text.replace('\n', &format!("\n{}", " ".repeat(marker.len())))));
+            for (block, _) in blocks.iter().skip(1) {
+                for line in block.lines() {
+                    lines.push(format!("{}{}", " ".repeat(marker.len().min(4)), line));
+                }
+            }
+        }
+        lines.join("\n")
+    }
+
+    fn table(&self, node: &Node) -> String {
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        collect_rows(node, &mut |cells: &[Node]| {
+            let row = cells
+                .iter()
+                .filter_map(|cell| match cell {
+                    Node::Element { name, children, .. } if name == "td" || name == "th" => {
+                        Some(collapse(&self.inlines(children)).replace('\n', " ").replace('|', "\\|"))
+                    }
+                    _ => None,
+                })
+                .collect();
+            rows.push(row);
+        });
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return String::new();
+        }
+        let line = |row: &[String]| {
+            let mut cells: Vec<&str> = row.iter().map(String::as_str).collect();
+            cells.resize(columns, "");
+            format!("| {} |", cells.join(" | "))
+        };
+        let mut out = vec![line(&rows[0]), format!("|{}", " --- |".repeat(columns))];
+        out.extend(rows[1..].iter().map(|r| line(r)));
+        out.join("\n")
+    }
+}
+
+fn collect_rows(node: &Node, on_row: &mut dyn FnMut(&[Node])) {
+    if let Node::Element { name, children, .. } = node {
+        if name == "tr" {
+            on_row(children);
+        } else {
+            for child in children {
+                collect_rows(child, on_row);
+            }
+        }
+    }
+}
+
+fn flush_inline(inline: &mut String, blocks: &mut Vec<Block>) {
+    let text = collapse(inline);
+    inline.clear();
+    if text.is_empty() {
+        return;
+    }
+    match text.strip_prefix("[ ] ").or_else(|| text.strip_prefix("[x] ")) {
+        Some(rest) => blocks.push((format!("- {}{}", &text[..4], rest), true)),
+        None => blocks.push((text, false)),
+    }
+}
+
+/// Collapse runs of spaces within lines and trim the lines.
+fn collapse(s: &str) -> String {
+    let lines: Vec<String> = s
+        .split('\n')
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect();
+    lines.join("\n").trim_matches('\n').to_string()
+}
+
+/// Wrap inline text in emphasis markers, keeping surrounding spaces outside.
+fn wrap(text: &str, marker: &str) -> String {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return text.to_string();
+    }
+    let lead = if text.starts_with(char::is_whitespace) { " " } else { "" };
+    let trail = if text.ends_with(char::is_whitespace) { " " } else { "" };
+    format!("{}{}{}{}{}", lead, marker, trimmed, marker, trail)
+}
+
+/// Escape characters that would be read as markdown. A `#` starting a word
+/// is escaped too, so text doesn't turn into tags.
+fn escape_text(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut prev = ' ';
+    for c in s.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>') || (c == '#' && prev.is_whitespace()) {
+            out.push('\\');
+        }
+        out.push(c);
+        prev = c;
+    }
+    out
+}
+
+/// The text of `nodes`, with `<br>` as a line break.
+pub fn text_content(nodes: &[Node]) -> String {
+    nodes
+        .iter()
+        .map(|node| match node {
+            Node::Text(text) => text.replace('\u{a0}', " "),
+            Node::Element { name, .. } if name == "br" => "\n".to_string(),
+            Node::Element { children, .. } => text_content(children),
+        })
+        .collect()
+}
+
+/// Code block text: each child `div` is a line.
+fn code_text(nodes: &[Node]) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        match node {
+            Node::Element { name, children, .. } if name == "div" || name == "p" => {
+                out.push_str(&text_content(children));
+                out.push('\n');
+            }
+            other => out.push_str(&text_content(std::slice::from_ref(other))),
+        }
+    }
+    out
+}
+
+fn fence(code: &str) -> String {
+    let fence = if code.contains("```") { "~~~~" } else { "```" };
+    format!("{}\n{}\n{}", fence, code.trim_end_matches('\n'), fence)
+}
+
+/// A link destination, in angle brackets when it has characters that would
+/// end it.
+fn link_dest(url: &str) -> String {
+    if url.contains([' ', '(', ')', '<', '>']) {
+        format!("<{}>", url.replace('<', "%3C").replace('>', "%3E"))
+    } else {
+        url.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_to_markdown() {
+        let html = "<!DOCTYPE html><HTML><head><style>p { color: red }</style><script>if (a < b) {}</script></head>\
+                    <body><P>One<br>two <IMG SRC=pic.png alt=\"A pic\"><p>Next <a href='/x y'>link</a>\
+                    <ul><li>a<li>b</ul></div><table><tr><th>h<td>1</table></body>";
+        let nodes = parse_html(html);
+        assert_eq!(
+            html_to_markdown(&nodes, &HashMap::new()),
+            "One\ntwo ![A pic](pic.png)\n\nNext [link](</x y>)\n\n- a\n- b\n\n| h | 1 |\n| --- | --- |\n"
+        );
+    }
+}
@@ -0,0 +1,550 @@
+use crate::document::jpeg::decode_jpeg;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+/// Images with more pixels than this are refused rather than decoded.
+pub const MAX_PIXELS: u64 = 100_000_000;
+
+/// An image ready to embed in a PDF.
+pub struct RasterImage {
+    pub width: u32,
+    pub height: u32,
+    /// 1 (gray), 3 (RGB) or 4 (CMYK, JPEG only).
+    pub components: u8,
+    pub data: ImageData,
+}
+
+pub enum ImageData {
+    /// JPEG file bytes, embedded as they are.
+    Jpeg(Vec<u8>),
+    /// zlib-compressed 8-bit samples, plus zlib-compressed 8-bit alpha.
+    Flate { samples: Vec<u8>, alpha: Option<Vec<u8>> },
+}
+
+/// Read a JPEG or PNG image. Interlaced PNGs and 16-bit samples are not
+/// supported.
+pub fn load_image(bytes: &[u8]) -> Result<RasterImage, String> {
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        load_jpeg(bytes)
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        load_png(bytes)
+    } else {
+        Err("Unsupported image format".to_string())
+    }
+}
+
+/// The usual file extension for image data, by its signature.
+pub fn image_extension(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        Some("jpg")
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("png")
+    } else if bytes.starts_with(b"GIF8") {
+        Some("gif")
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        Some("webp")
+    } else if bytes.get(4..12).is_some_and(|b| b == b"ftypavif") {
+        Some("avif")
+    } else if bytes.starts_with(b"<svg") || (bytes.starts_with(b"<?xml") && bytes.windows(4).any(|w| w == b"<svg")) {
+        Some("svg")
+    } else {
+        None
+    }
+}
+
+/// Width and height of a JPEG, PNG, GIF or WebP image, read from its header.
+pub fn image_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let le16 = |at: usize| Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32);
+    let le24 = |at: usize| Some(bytes.get(at..at + 3)?.iter().rev().fold(0u32, |n, &b| n << 8 | b as u32));
+    match image_extension(bytes)? {
+        "jpg" => jpeg_header(bytes).ok().map(|(width, height, _)| (width, height)),
+        "png" if bytes.get(12..16) == Some(b"IHDR") => Some((
+            u32::from_be_bytes(bytes.get(16..20)?.try_into().ok()?),
+            u32::from_be_bytes(bytes.get(20..24)?.try_into().ok()?),
+        )),
+        "gif" => Some((le16(6)?, le16(8)?)),
+        "webp" => match bytes.get(12..16)? {
+            b"VP8 " if bytes.get(23..26) == Some(&[0x9D, 0x01, 0x2A]) => Some((le16(26)? & 0x3FFF, le16(28)? & 0x3FFF)),
+            b"VP8L" if bytes.get(20) == Some(&0x2F) => {
+                let bits = u32::from_le_bytes(bytes.get(21..25)?.try_into().ok()?);
+                Some(((bits & 0x3FFF) + 1, (bits >> 14 & 0x3FFF) + 1))
+            }
+            b"VP8X" => Some((le24(24)? + 1, le24(27)? + 1)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Decoded 8-bit RGBA pixels, row by row.
+pub struct Pixels {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+/// Decode a JPEG or PNG image to pixels.
+pub fn decode_pixels(bytes: &[u8]) -> Result<Pixels, String> {
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        return decode_jpeg(bytes);
+    }
+    if !bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Err("Unsupported image format".to_string());
+    }
+    let png = read_png(bytes)?;
+    let mut rgba = Vec::with_capacity(png.alpha.len() * 4);
+    for (i, &alpha) in png.alpha.iter().enumerate() {
+        if png.components == 1 {
+            let gray = png.samples[i];
+            rgba.extend_from_slice(&[gray, gray, gray, alpha]);
+        } else {
+            rgba.extend_from_slice(&png.samples[i * 3..i * 3 + 3]);
+            rgba.push(alpha);
+        }
+    }
+    Ok(Pixels { width: png.width, height: png.height, rgba })
+}
+
+impl Pixels {
+    pub fn has_alpha(&self) -> bool {
+        self.rgba.chunks(4).any(|pixel| pixel[3] != 255)
+    }
+
+    /// Turned and flipped upright as the EXIF orientation (1–8) says.
+    pub fn oriented(self, orientation: u16) -> Pixels {
+        if !(2..=8).contains(&orientation) {
+            return self;
+        }
+        let (w, h) = (self.width as usize, self.height as usize);
+        let transposed = orientation >= 5;
+        let (out_w, out_h) = if transposed { (h, w) } else { (w, h) };
+        let mut rgba = vec![0u8; self.rgba.len()];
+        for y in 0..out_h {
+            for x in 0..out_w {
+                let (sx, sy) = match orientation {
+                    2 => (w - 1 - x, y),
+                    3 => (w - 1 - x, h - 1 - y),
+                    4 => (x, h - 1 - y),
+                    5 => (y, x),
+                    6 => (y, h - 1 - x),
+                    7 => (w - 1 - y, h - 1 - x),
+                    _ => (w - 1 - y, x),
+                };
+                let (from, to) = ((sy * w + sx) * 4, (y * out_w + x) * 4);
+                rgba[to..to + 4].copy_from_slice(&self.rgba[from..from + 4]);
+            }
+        }
+        Pixels { width: out_w as u32, height: out_h as u32, rgba }
+    }
+
+    /// Scaled down, keeping the aspect ratio, so neither side is longer than
+    /// `max_side`; `None` if the image already fits.
+    pub fn fit_within(&self, max_side: u32) -> Option<Pixels> {
+        let longest = self.width.max(self.height);
+        if max_side == 0 || longest <= max_side {
+            return None;
+        }
+        let scale = max_side as f64 / longest as f64;
+        let width = ((self.width as f64 * scale).round() as u32).max(1);
+        let height = ((self.height as f64 * scale).round() as u32).max(1);
+        Some(self.resize(width, height))
+    }
+
+    /// Resampled to `width` by `height` by averaging the source pixels each
+    /// target pixel covers, with alpha premultiplied so transparent pixels do
+    /// not bleed their color.
+    pub fn resize(&self, width: u32, height: u32) -> Pixels {
+        let (src_w, src_h) = (self.width as usize, self.height as usize);
+        let (dst_w, dst_h) = (width as usize, height as usize);
+        let premultiplied: Vec<f32> = self
+            .rgba
+            .chunks(4)
+            .flat_map(|p| {
+                let alpha = p[3] as f32 / 255.0;
+                [p[0] as f32 * alpha, p[1] as f32 * alpha, p[2] as f32 * alpha, p[3] as f32]
+            })
+            .collect();
+
+        let columns = box_weights(src_w, dst_w);
+        let mut rows_scaled = vec![0f32; dst_w * src_h * 4];
+        for y in 0..src_h {
+            for (x, (start, weights)) in columns.iter().enumerate() {
+                let out = &mut rows_scaled[(y * dst_w + x) * 4..(y * dst_w + x) * 4 + 4];
+                for (i, weight) in weights.iter().enumerate() {
+                    let from = (y * src_w + start + i) * 4;
+                    for c in 0..4 {
+                        out[c] += premultiplied[from + c] * weight;
+                    }
+                }
+            }
+        }
+
+        let rows = box_weights(src_h, dst_h);
+        let mut rgba = Vec::with_capacity(dst_w * dst_h * 4);
+        for (start, weights) in &rows {
+            for x in 0..dst_w {
+                let mut sum = [0f32; 4];
+                for (i, weight) in weights.iter().enumerate() {
+                    let from = ((start + i) * dst_w + x) * 4;
+                    for c in 0..4 {
+                        sum[c] += rows_scaled[from + c] * weight;
+                    }
+                }
+                let alpha = sum[3] / 255.0;
+                for value in &sum[..3] {
+                    let color = if alpha > 0.0 { value / alpha } else { 0.0 };
+                    rgba.push(color.round().clamp(0.0, 255.0) as u8);
+                }
+                rgba.push(sum[3].round().clamp(0.0, 255.0) as u8);
+            }
+        }
+        Pixels { width, height, rgba }
+    }
+}
+
+/// For each target position along an axis, the first source position it
+/// covers and the normalized weight of each covered source position.
+fn box_weights(src: usize, dst: usize) -> Vec<(usize, Vec<f32>)> {
+    let scale = src as f64 / dst as f64;
+    (0..dst)
+        .map(|i| {
+            let (start, end) = (i as f64 * scale, ((i + 1) as f64 * scale).min(src as f64));
+            let first = start.floor() as usize;
+            let last = (end.ceil() as usize).clamp(first + 1, src);
+            let weights = (first..last)
+                .map(|j| ((end.min(j as f64 + 1.0) - start.max(j as f64)) / (end - start)) as f32)
+                .collect();
+            (first, weights)
+        })
+        .collect()
+}
+
+/// Encode pixels as a PNG, RGB unless some pixels are transparent. Each row
+/// gets the filter that leaves it the smallest residuals.
+pub fn encode_png(pixels: &Pixels) -> Result<Vec<u8>, String> {
+    let has_alpha = pixels.has_alpha();
+    let channels = if has_alpha { 4 } else { 3 };
+    let stride = pixels.width as usize * channels;
+    let samples: Vec<u8> = if has_alpha {
+        pixels.rgba.clone()
+    } else {
+        pixels.rgba.chunks(4).flat_map(|p| [p[0], p[1], p[2]]).collect()
+    };
+
+    let mut raw = Vec::with_capacity((stride + 1) * pixels.height as usize);
+    let mut candidate = vec![0u8; stride];
+    let mut best = vec![0u8; stride];
+    for y in 0..pixels.height as usize {
+        let row = &samples[y * stride..(y + 1) * stride];
+        let previous = if y > 0 { Some(&samples[(y - 1) * stride..y * stride]) } else { None };
+        let mut best_filter = 0;
+        let mut best_cost = u64::MAX;
+        for filter in 0..5u8 {
+            for x in 0..stride {
+                let a = if x >= channels { row[x - channels] } else { 0 };
+                let b = previous.map_or(0, |p| p[x]);
+                let c = if x >= channels { previous.map_or(0, |p| p[x - channels]) } else { 0 };
+                let predicted = match filter {
+                    0 => 0,
+                    1 => a,
+                    2 => b,
+                    3 => ((a as u16 + b as u16) / 2) as u8,
+                    _ => paeth(a, b, c),
+                };
+                candidate[x] = row[x].wrapping_sub(predicted);
+            }
+            let cost = candidate.iter().map(|&v| (v as i8).unsigned_abs() as u64).sum();
+            if cost < best_cost {
+                best_cost = cost;
+                best_filter = filter;
+                best.copy_from_slice(&candidate);
+            }
+        }
+        raw.push(best_filter);
+        raw.extend_from_slice(&best);
+    }
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    let mut chunk = |kind: &[u8], data: &[u8]| {
+        png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let start = png.len();
+        png.extend_from_slice(kind);
+        png.extend_from_slice(data);
+        let crc = crc32fast::hash(&png[start..]);
+        png.extend_from_slice(&crc.to_be_bytes());
+    };
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&pixels.width.to_be_bytes());
+    header.extend_from_slice(&pixels.height.to_be_bytes());
+    header.extend_from_slice(&[8, if has_alpha { 6 } else { 2 }, 0, 0, 0]);
+    chunk(b"IHDR", &header);
+    chunk(b"IDAT", &deflate(&raw)?);
+    chunk(b"IEND", &[]);
+    Ok(png)
+}
+
+fn load_jpeg(bytes: &[u8]) -> Result<RasterImage, String> {
+    let (width, height, components) = jpeg_header(bytes)?;
+    Ok(RasterImage { width, height, components, data: ImageData::Jpeg(bytes.to_vec()) })
+}
+
+/// Width, height and component count from a JPEG's frame header.
+fn jpeg_header(bytes: &[u8]) -> Result<(u32, u32, u8), String> {
+    let mut i = 2;
+    while i + 4 <= bytes.len() {
+        if bytes[i] != 0xFF {
+            i += 1;
+            continue;
+        }
+        let marker = bytes[i + 1];
+        if marker == 0xFF || (0xD0..=0xD9).contains(&marker) || marker == 0x01 {
+            i += if marker == 0xFF { 1 } else { 2 };
+            continue;
+        }
+        let length = u16::from_be_bytes([bytes[i + 2], bytes[i + 3]]) as usize;
+        // Start of frame: SOF0..SOF15, except DHT, JPG and DAC
+        if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+            let frame = bytes.get(i + 4..i + 10).ok_or("Truncated JPEG")?;
+            return Ok((
+                u16::from_be_bytes([frame[3], frame[4]]) as u32,
+                u16::from_be_bytes([frame[1], frame[2]]) as u32,
+                frame[5],
+            ));
+        }
+        i += 2 + length;
+    }
+    Err("JPEG has no frame header".to_string())
+}
+
+/// 8-bit samples of a PNG: gray or RGB, with alpha (opaque if no alpha
+/// channel or transparency).
+struct PngSamples {
+    width: u32,
+    height: u32,
+    components: u8,
+    samples: Vec<u8>,
+    alpha: Vec<u8>,
+}
+
+fn load_png(bytes: &[u8]) -> Result<RasterImage, String> {
+    let png = read_png(bytes)?;
+    let has_alpha = png.alpha.iter().any(|&a| a != 255);
+    Ok(RasterImage {
+        width: png.width,
+        height: png.height,
+        components: png.components,
+        data: ImageData::Flate {
+            samples: deflate(&png.samples)?,
+            alpha: if has_alpha { Some(deflate(&png.alpha)?) } else { None },
+        },
+    })
+}
+
+fn read_png(bytes: &[u8]) -> Result<PngSamples, String> {
+    let mut pos = 8;
+    let mut header: Option<[u8; 13]> = None;
+    let mut palette: Vec<u8> = Vec::new();
+    let mut transparency: Vec<u8> = Vec::new();
+    let mut compressed: Vec<u8> = Vec::new();
+
+    while pos + 8 <= bytes.len() {
+        let length = u32::from_be_bytes(bytes[pos..pos + 4].try_into().unwrap()) as usize;
+        let kind = &bytes[pos + 4..pos + 8];
+        let data = bytes.get(pos + 8..pos + 8 + length).ok_or("Truncated PNG")?;
+        match kind {
+            // A second header could describe another size than the one
+            // callers checked
+            b"IHDR" if header.is_some() => return Err("PNG has more than one header".to_string()),
+            b"IHDR" if length == 13 => header = Some(data.try_into().unwrap()),
+            b"PLTE" => palette = data.to_vec(),
+            b"tRNS" => transparency = data.to_vec(),
+            b"IDAT" => compressed.extend_from_slice(data),
+            b"IEND" => break,
+            _ => {}
+        }
+        pos += 12 + length;
+    }
+
+    let header = header.ok_or("PNG has no header")?;
+    let width = u32::from_be_bytes(header[0..4].try_into().unwrap());
+    let height = u32::from_be_bytes(header[4..8].try_into().unwrap());
+    let (depth, color_type, interlace) = (header[8], header[9], header[12]);
+    if interlace != 0 {
+        return Err("Interlaced PNGs are not supported".to_string());
+    }
+    let channels: usize = match color_type {
+        0 | 3 => 1,
+        2 => 3,
+        4 => 2,
+        6 => 4,
+        _ => return Err("Unsupported PNG color type".to_string()),
+    };
+    if depth != 8 && !(color_type == 3 && [1, 2, 4].contains(&depth)) {
+        return Err(format!("{}-bit PNGs are not supported", depth));
+    }
+
+    if width == 0 || height == 0 || width as u64 * height as u64 > MAX_PIXELS {
+        return Err(format!("Unsupported PNG size {}×{}", width, height));
+    }
+
+    // Sizes come from the header, so nothing is allocated for them until
+    // the data is known to be there
+    let stride = (width as usize * channels * depth as usize).div_ceil(8);
+    let expected = (stride + 1) * height as usize;
+    let mut raw = Vec::new();
+    ZlibDecoder::new(&compressed[..])
+        .take(expected as u64)
+        .read_to_end(&mut raw)
+        .map_err(|e| format!("Invalid PNG data: {}", e))?;
+    if raw.len() < expected {
+        return Err("Truncated PNG data".to_string());
+    }
+    let rows = unfilter(&raw, stride, channels.max(depth as usize / 8), height as usize)?;
+
+    let pixels = width as usize * height as usize;
+    let mut samples = Vec::with_capacity(pixels * 3);
+    let mut alpha = Vec::with_capacity(pixels);
+    for row in rows.chunks(stride) {
+        for x in 0..width as usize {
+            match color_type {
+                0 => samples.push(row[x]),
+                2 => samples.extend_from_slice(&row[x * 3..x * 3 + 3]),
+                4 => {
+                    samples.push(row[x * 2]);
+                    alpha.push(row[x * 2 + 1]);
+                }
+                6 => {
+                    samples.extend_from_slice(&row[x * 4..x * 4 + 3]);
+                    alpha.push(row[x * 4 + 3]);
+                }
+                _ => {
+                    let per_byte = 8 / depth as usize;
+                    let byte = row[x / per_byte];
+                    let shift = 8 - depth as usize * (x % per_byte + 1);
+                    let index = ((byte >> shift) & ((1u16 << depth) - 1) as u8) as usize;
+                    let rgb = palette.get(index * 3..index * 3 + 3).unwrap_or(&[0, 0, 0]);
+                    samples.extend_from_slice(rgb);
+                    alpha.push(transparency.get(index).copied().unwrap_or(255));
+                }
+            }
+        }
+    }
+
+    if alpha.is_empty() {
+        alpha = vec![255; pixels];
+    }
+    Ok(PngSamples {
+        width,
+        height,
+        components: if color_type == 0 || color_type == 4 { 1 } else { 3 },
+        samples,
+        alpha,
+    })
+}
+
+/// Undo PNG row filters. `bpp` is bytes per complete pixel (at least 1).
+fn unfilter(raw: &[u8], stride: usize, bpp: usize, height: usize) -> Result<Vec<u8>, String> {
+    let mut out = vec![0u8; stride * height];
+    for y in 0..height {
+        let start = y * (stride + 1);
+        let line = raw.get(start..start + stride + 1).ok_or("Truncated PNG data")?;
+        let (filter, line) = (line[0], &line[1..]);
+        for x in 0..stride {
+            let a = if x >= bpp { out[y * stride + x - bpp] } else { 0 };
+            let b = if y > 0 { out[(y - 1) * stride + x] } else { 0 };
+            let c = if x >= bpp && y > 0 { out[(y - 1) * stride + x - bpp] } else { 0 };
+            let predicted = match filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((a as u16 + b as u16) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => return Err("Invalid PNG filter".to_string()),
+            };
+            out[y * stride + x] = line[x].wrapping_add(predicted);
+        }
+    }
+    Ok(out)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+fn deflate(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).map_err(|e| e.to_string())?;
+    encoder.finish().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_png_with_alpha() {
+        // 2x1 RGBA: opaque red, half-transparent blue
+        let mut raw = vec![0u8];
+        raw.extend_from_slice(&[255, 0, 0, 255, 0, 0, 255, 128]);
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        let mut chunk = |kind: &[u8], data: &[u8]| {
+            png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            png.extend_from_slice(kind);
+            png.extend_from_slice(data);
+            png.extend_from_slice(&[0; 4]); // CRC is not checked
+        };
+        chunk(b"IHDR", &[0, 0, 0, 2, 0, 0, 0, 1, 8, 6, 0, 0, 0]);
+        chunk(b"IDAT", &deflate(&raw).unwrap());
+        chunk(b"IEND", &[]);
+
+        let image = load_image(&png).unwrap();
+        assert_eq!((image.width, image.height, image.components), (2, 1, 3));
+        let ImageData::Flate { samples, alpha } = image.data else {
+            panic!("expected flate data");
+        };
+        let mut decoded = Vec::new();
+        ZlibDecoder::new(&samples[..]).read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, vec![255, 0, 0, 0, 0, 255]);
+        assert!(alpha.is_some());
+    }
+
+    #[test]
+    fn test_png_header_is_not_trusted() {
+        let png = |headers: &[[u8; 13]]| {
+            let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+            let mut chunk = |kind: &[u8], data: &[u8]| {
+                png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+                png.extend_from_slice(kind);
+                png.extend_from_slice(data);
+                png.extend_from_slice(&[0; 4]);
+            };
+            for header in headers {
+                chunk(b"IHDR", header);
+            }
+            chunk(b"IDAT", &deflate(&[0, 1, 2, 3]).unwrap());
+            chunk(b"IEND", &[]);
+            png
+        };
+        let small = [0, 0, 0, 1, 0, 0, 0, 1, 8, 2, 0, 0, 0];
+        let huge = [0, 0, 0xFF, 0xFF, 0, 0, 0xFF, 0xFF, 8, 6, 0, 0, 0];
+        let wide = [0, 0, 0x27, 0x10, 0, 0, 0x27, 0x10, 8, 6, 0, 0, 0];
+
+        assert!(decode_pixels(&png(&[small])).is_ok());
+        assert!(decode_pixels(&png(&[huge])).is_err());
+        // Within the pixel limit, but the data is not there
+        assert!(decode_pixels(&png(&[wide])).is_err());
+        // The first header is what image_dimensions reports
+        assert_eq!(image_dimensions(&png(&[small, huge])), Some((1, 1)));
+        assert!(decode_pixels(&png(&[small, huge])).is_err());
+    }
+}
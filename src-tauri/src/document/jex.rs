@@ -0,0 +1,245 @@
+use crate::document::external::{ExternalNote, ExternalTodo};
+use crate::document::tar::read_tar;
+use chrono::{DateTime, TimeZone, Utc};
+use regex::Regex;
+use std::collections::HashMap;
+
+const TYPE_NOTE: &str = "1";
+const TYPE_FOLDER: &str = "2";
+const TYPE_RESOURCE: &str = "4";
+const TYPE_TAG: &str = "5";
+const TYPE_NOTE_TAG: &str = "6";
+
+/// One item of a Joplin export: a note, notebook, resource, tag or note tag.
+struct Item {
+    title: String,
+    body: String,
+    fields: HashMap<String, String>,
+}
+
+impl Item {
+    fn field(&self, key: &str) -> &str {
+        self.fields.get(key).map(String::as_str).unwrap_or("")
+    }
+
+    /// A flag or millisecond timestamp field that is set.
+    fn is_set(&self, key: &str) -> bool {
+        !matches!(self.field(key), "" | "0")
+    }
+}
+
+/// Read the notes of a Joplin export archive (`.jex`). Notebooks give the
+/// notebook path, `:/id` links to resources become asset links and links to
+/// notes become wiki links, and to-dos keep their completion and due date.
+pub fn read_jex(data: &[u8]) -> Result<Vec<ExternalNote>, String> {
+    let entries = read_tar(data)?;
+    let mut files: HashMap<String, Vec<u8>> = HashMap::new();
+    let mut items: Vec<Item> = Vec::new();
+    for entry in entries {
+        if entry.name.starts_with("resources/") {
+            files.insert(entry.name, entry.data);
+        } else if entry.name.ends_with(".md") {
+            items.push(parse_item(&String::from_utf8_lossy(&entry.data)));
+        }
+    }
+
+    let of_type = |kind: &'static str| items.iter().filter(move |item| item.field("type_") == kind);
+    let folders: HashMap<&str, &Item> = of_type(TYPE_FOLDER).map(|f| (f.field("id"), f)).collect();
+    let tags: HashMap<&str, &str> = of_type(TYPE_TAG).map(|t| (t.field("id"), t.title.as_str())).collect();
+    let titles: HashMap<&str, &str> = of_type(TYPE_NOTE).map(|n| (n.field("id"), n.title.as_str())).collect();
+    let mut note_tags: HashMap<&str, Vec<String>> = HashMap::new();
+    for link in of_type(TYPE_NOTE_TAG) {
+        if let Some(tag) = tags.get(link.field("tag_id")) {
+            note_tags.entry(link.field("note_id")).or_default().push(tag.to_string());
+        }
+    }
+    // Resource id to (asset key, file name)
+    let mut resources: HashMap<&str, (String, String)> = HashMap::new();
+    for resource in of_type(TYPE_RESOURCE) {
+        let id = resource.field("id");
+        let ext = resource.field("file_extension");
+        let stored = if ext.is_empty() { format!("resources/{}", id) } else { format!("resources/{}.{}", id, ext) };
+        let file_name = match resource.title.as_str() {
+            "" if ext.is_empty() => id.to_string(),
+            "" => format!("{}.{}", id, ext),
+            title => title.to_string(),
+        };
+        resources.insert(id, (stored, file_name));
+    }
+
+    let link_re = Regex::new(r"(!?)\[([^\]]*)\]\(:/([0-9a-fA-F]{32})(?:#([^)\s]*))?\)").unwrap();
+    let img_re = Regex::new(r#"<img[^>]*?src=["']:/([0-9a-fA-F]{32})["'][^>]*>"#).unwrap();
+
+    let mut notes = Vec::new();
+    for item in of_type(TYPE_NOTE) {
+        let id = item.field("id");
+        let mut assets = HashMap::new();
+        let mut asset = |resource_id: &str| -> Option<String> {
+            let (stored, file_name) = resources.get(resource_id)?;
+            let key = format!("resources/{}/{}", resource_id, file_name);
+            assets.insert(key.clone(), files.get(stored)?.clone());
+            Some(key)
+        };
+
+        let body = img_re.replace_all(&item.body, |cap: &regex::Captures| match asset(&cap[1]) {
+            Some(key) => format!("![](<{}>)", key),
+            None => cap[0].to_string(),
+        });
+        let body = link_re.replace_all(&body, |cap: &regex::Captures| {
+            let (bang, text, target) = (&cap[1], &cap[2], &cap[3]);
+            if let Some(key) = asset(target) {
+                return format!("{}[{}](<{}>)", bang, text, key);
+            }
+            let Some(title) = titles.get(target) else {
+                return cap[0].to_string();
+            };
+            let mut link = title.to_string();
+            if let Some(anchor) = cap.get(4) {
+                link = format!("{}#{}", link, anchor.as_str());
+            }
+            if !text.is_empty() && text != *title {
+                link = format!("{}|{}", link, text);
+            }
+            format!("[[{}]]", link)
+        });
+
+        let mut notebook = Vec::new();
+        let mut parent = item.field("parent_id");
+        while let Some(folder) = folders.get(parent) {
+            // Guard against a cycle in a damaged export
+            if notebook.len() > 32 {
+                break;
+            }
+            notebook.insert(0, folder.title.clone());
+            parent = folder.field("parent_id");
+        }
+
+        let skip = if item.is_set("encryption_applied") {
+            Some("Encrypted in Joplin".to_string())
+        } else if item.is_set("deleted_time") {
+            Some("In the Joplin trash".to_string())
+        } else if item.is_set("is_conflict") {
+            Some("A Joplin conflict copy".to_string())
+        } else {
+            None
+        };
+        let time = |keys: &[&str]| keys.iter().find_map(|k| iso_time(item.field(k)));
+        notes.push(ExternalNote {
+            app: "Joplin",
+            id: Some(id.to_string()),
+            title: item.title.clone(),
+            content: body.into_owned(),
+            tags: note_tags.remove(id).unwrap_or_default(),
+            notebook,
+            created_at: time(&["user_created_time", "created_time"]),
+            updated_at: time(&["user_updated_time", "updated_time"]),
+            assets,
+            todo: item.is_set("is_todo").then(|| ExternalTodo {
+                done: item.is_set("todo_completed"),
+                due_date: item
+                    .field("todo_due")
+                    .parse::<i64>()
+                    .ok()
+                    .filter(|&ms| ms > 0)
+                    .and_then(|ms| Utc.timestamp_millis_opt(ms).single())
+                    .map(|due| due.format("%Y-%m-%d").to_string()),
+            }),
+            skip,
+            ..Default::default()
+        });
+    }
+    Ok(notes)
+}
+
+/// Split an item file into its title, body and the `key: value` lines that
+/// end it.
+fn parse_item(text: &str) -> Item {
+    let lines: Vec<&str> = text.trim_end().lines().collect();
+    let field_re = Regex::new(r"^([a-z_]+): ?(.*)$").unwrap();
+    let mut fields = HashMap::new();
+    let mut end = lines.len();
+    while end > 0 {
+        let Some(cap) = field_re.captures(lines[end - 1]) else {
+            break;
+        };
+        fields.insert(cap[1].to_string(), cap[2].to_string());
+        end -= 1;
+    }
+
+    let content = &lines[..end];
+    let title = content.first().map(|t| t.trim().to_string()).unwrap_or_default();
+    let body = content.iter().skip(1).copied().collect::<Vec<_>>().join("\n");
+    let body = body.trim_matches('\n');
+    Item {
+        title,
+        body: if body.is_empty() { String::new() } else { format!("{}\n", body) },
+        fields,
+    }
+}
+
+fn iso_time(s: &str) -> Option<String> {
+    DateTime::parse_from_rfc3339(s).ok().map(|t| t.with_timezone(&Utc).to_rfc3339())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tar_file(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut header = vec![0u8; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        let size = format!("{:011o}\0", data.len());
+        header[124..136].copy_from_slice(size.as_bytes());
+        header[156] = b'0';
+        let mut out = header;
+        out.extend_from_slice(data);
+        out.resize(out.len().div_ceil(512) * 512, 0);
+        out
+    }
+
+    #[test]
+    fn test_read_jex() {
+        let note_id = "a".repeat(32);
+        let other_id = "b".repeat(32);
+        let folder_id = "c".repeat(32);
+        let child_id = "d".repeat(32);
+        let resource_id = "e".repeat(32);
+        let tag_id = "f".repeat(32);
+        let mut tar = Vec::new();
+        tar.extend(tar_file(
+            &format!("{}.md", note_id),
+            format!(
+                "Shopping\n\nSee [other](:/{}) and ![pic](:/{})\n\nid: {}\nparent_id: {}\n\
+                 user_created_time: 2023-05-01T10:00:00.000Z\nis_todo: 1\ntodo_due: 1700000000000\n\
+                 todo_completed: 0\ntype_: 1",
+                other_id, resource_id, note_id, child_id
+            )
+            .as_bytes(),
+        ));
+        tar.extend(tar_file(&format!("{}.md", other_id), format!("Other\n\nid: {}\nparent_id: \ntype_: 1", other_id).as_bytes()));
+        tar.extend(tar_file(&format!("{}.md", folder_id), format!("Home\n\nid: {}\nparent_id: \ntype_: 2", folder_id).as_bytes()));
+        tar.extend(tar_file(
+            &format!("{}.md", child_id),
+            format!("Errands\n\nid: {}\nparent_id: {}\ntype_: 2", child_id, folder_id).as_bytes(),
+        ));
+        tar.extend(tar_file(
+            &format!("{}.md", resource_id),
+            format!("photo.png\n\nid: {}\nmime: image/png\nfile_extension: png\ntype_: 4", resource_id).as_bytes(),
+        ));
+        tar.extend(tar_file(&format!("resources/{}.png", resource_id), b"png"));
+        tar.extend(tar_file(&format!("{}.md", tag_id), format!("groceries\n\nid: {}\ntype_: 5", tag_id).as_bytes()));
+        tar.extend(tar_file("link.md", format!("id: 1\nnote_id: {}\ntag_id: {}\ntype_: 6", note_id, tag_id).as_bytes()));
+        tar.extend(vec![0; 1024]);
+
+        let notes = read_jex(&tar).unwrap();
+        assert_eq!(notes.len(), 2);
+        let note = notes.iter().find(|n| n.title == "Shopping").unwrap();
+        let key = format!("resources/{}/photo.png", resource_id);
+        assert_eq!(note.content, format!("See [[Other|other]] and ![pic](<{}>)\n", key));
+        assert_eq!(note.assets[&key], b"png");
+        assert_eq!(note.notebook, vec!["Home", "Errands"]);
+        assert_eq!(note.tags, vec!["groceries"]);
+        assert_eq!(note.created_at.as_deref(), Some("2023-05-01T10:00:00+00:00"));
+        assert_eq!(note.todo, Some(ExternalTodo { done: false, due_date: Some("2023-11-14".to_string()) }));
+    }
+}
@@ -0,0 +1,918 @@
+use crate::document::image::{Pixels, MAX_PIXELS};
+use std::f32::consts::PI;
+
+/// Position in a block, in natural (row-major) order, of each coefficient in
+/// the zig-zag order JPEG stores them in.
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20, 13, 6, 7, 14, 21,
+    28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59, 52, 45, 38, 31, 39, 46, 53, 60, 61,
+    54, 47, 55, 62, 63,
+];
+
+/// `COSINES[x * 8 + u]` is C(u)/2 · cos((2x + 1)uπ/16), the DCT basis.
+fn cosines() -> [f32; 64] {
+    let mut table = [0.0; 64];
+    for x in 0..8 {
+        for u in 0..8 {
+            let scale = if u == 0 { 0.5 / 2f32.sqrt() } else { 0.5 };
+            table[x * 8 + u] = scale * ((2 * x + 1) as f32 * u as f32 * PI / 16.0).cos();
+        }
+    }
+    table
+}
+
+struct Huffman {
+    /// Symbol and code length for each 9-bit prefix of short codes.
+    fast: Vec<(u8, u8)>,
+    /// Per length, the largest code (or -1), and the offset of its symbols.
+    max_code: [i32; 17],
+    offsets: [i32; 17],
+    symbols: Vec<u8>,
+}
+
+const FAST_BITS: u32 = 9;
+
+impl Huffman {
+    fn new(counts: &[u8; 16], symbols: Vec<u8>) -> Huffman {
+        let mut fast = vec![(0, 0); 1 << FAST_BITS];
+        let mut max_code = [-1; 17];
+        let mut offsets = [0; 17];
+        let (mut code, mut k) = (0i32, 0usize);
+        for len in 1..=16 {
+            let count = counts[len - 1] as usize;
+            offsets[len] = k as i32 - code;
+            for _ in 0..count {
+                if len as u32 <= FAST_BITS {
+                    let shift = FAST_BITS - len as u32;
+                    let start = (code as usize) << shift;
+                    for entry in fast.iter_mut().skip(start).take(1 << shift) {
+                        *entry = (symbols.get(k).copied().unwrap_or(0), len as u8);
+                    }
+                }
+                code += 1;
+                k += 1;
+            }
+            if count > 0 {
+                max_code[len] = code - 1;
+            }
+            code <<= 1;
+        }
+        Huffman { fast, max_code, offsets, symbols }
+    }
+}
+
+/// Reads entropy-coded data, undoing 0xFF00 byte stuffing. At a marker it
+/// stops and yields zero bits.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bits: u64,
+    count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn fill(&mut self) {
+        while self.count <= 56 {
+            let mut byte = 0;
+            if let Some(&b) = self.data.get(self.pos) {
+                if b != 0xFF {
+                    byte = b;
+                    self.pos += 1;
+                } else if self.data.get(self.pos + 1) == Some(&0) {
+                    byte = 0xFF;
+                    self.pos += 2;
+                }
+            }
+            self.bits |= (byte as u64) << (56 - self.count);
+            self.count += 8;
+        }
+    }
+
+    fn peek(&mut self, n: u32) -> u32 {
+        if self.count < n {
+            self.fill();
+        }
+        (self.bits >> (64 - n)) as u32
+    }
+
+    fn consume(&mut self, n: u32) {
+        self.bits <<= n;
+        self.count -= n;
+    }
+
+    fn bits(&mut self, n: u32) -> u32 {
+        if n == 0 {
+            return 0;
+        }
+        let value = self.peek(n);
+        self.consume(n);
+        value
+    }
+
+    /// `n` bits as a signed value (JPEG's EXTEND).
+    fn signed(&mut self, n: u32) -> i32 {
+        let value = self.bits(n) as i32;
+        if n > 0 && value < 1 << (n - 1) {
+            value - (1 << n) + 1
+        } else {
+            value
+        }
+    }
+
+    fn decode(&mut self, table: &Huffman) -> Result<u8, String> {
+        let peeked = self.peek(16);
+        let (symbol, len) = table.fast[(peeked >> (16 - FAST_BITS)) as usize];
+        if len > 0 {
+            self.consume(len as u32);
+            return Ok(symbol);
+        }
+        for len in FAST_BITS as usize + 1..=16 {
+            let code = (peeked >> (16 - len)) as i32;
+            if code <= table.max_code[len] {
+                self.consume(len as u32);
+                return table
+                    .symbols
+                    .get((table.offsets[len] + code) as usize)
+                    .copied()
+                    .ok_or_else(|| "Invalid JPEG Huffman code".to_string());
+            }
+        }
+        Err("Invalid JPEG Huffman code".to_string())
+    }
+
+    /// Skip past the next restart marker.
+    fn restart(&mut self) {
+        self.bits = 0;
+        self.count = 0;
+        while self.pos + 1 < self.data.len() {
+            if self.data[self.pos] == 0xFF && (0xD0..=0xD7).contains(&self.data[self.pos + 1]) {
+                self.pos += 2;
+                return;
+            }
+            self.pos += 1;
+        }
+    }
+
+    /// Where the entropy-coded data ends: the first marker that is not a
+    /// restart marker.
+    fn end(&self) -> usize {
+        let mut pos = self.pos;
+        while pos + 1 < self.data.len() {
+            let next = self.data[pos + 1];
+            if self.data[pos] == 0xFF && next != 0 && next != 0xFF && !(0xD0..=0xD7).contains(&next) {
+                return pos;
+            }
+            pos += 1;
+        }
+        self.data.len()
+    }
+}
+
+struct Component {
+    id: u8,
+    h: usize,
+    v: usize,
+    quant: usize,
+    /// Blocks per line and per column, padded to whole MCUs.
+    blocks_w: usize,
+    blocks_h: usize,
+    coefficients: Vec<[i16; 64]>,
+    dc_table: usize,
+    ac_table: usize,
+    prediction: i32,
+}
+
+struct Frame {
+    width: usize,
+    height: usize,
+    progressive: bool,
+    h_max: usize,
+    v_max: usize,
+    mcus_x: usize,
+    mcus_y: usize,
+    components: Vec<Component>,
+}
+
+struct Scan {
+    components: Vec<usize>,
+    start: usize,
+    end: usize,
+    high: u32,
+    low: u32,
+}
+
+/// Decode a baseline or progressive JPEG to pixels. Arithmetic-coded,
+/// 12-bit and CMYK files are not supported.
+pub fn decode_jpeg(data: &[u8]) -> Result<Pixels, String> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return Err("Not a JPEG file".to_string());
+    }
+    let mut quant = [[0u16; 64]; 4];
+    let mut dc_tables: [Option<Huffman>; 4] = [None, None, None, None];
+    let mut ac_tables: [Option<Huffman>; 4] = [None, None, None, None];
+    let mut frame: Option<Frame> = None;
+    let mut restart_interval = 0;
+    let mut adobe_transform: Option<u8> = None;
+
+    let mut pos = 2;
+    loop {
+        while data.get(pos) == Some(&0xFF) && data.get(pos + 1) == Some(&0xFF) {
+            pos += 1;
+        }
+        let (Some(&0xFF), Some(&marker)) = (data.get(pos), data.get(pos + 1)) else {
+            break;
+        };
+        pos += 2;
+        if marker == 0xD9 {
+            break;
+        }
+        if (0xD0..=0xD7).contains(&marker) || marker == 0x01 {
+            continue;
+        }
+        let length = match data.get(pos..pos + 2) {
+            Some(bytes) => u16::from_be_bytes([bytes[0], bytes[1]]) as usize,
+            None => break,
+        };
+        let segment = data.get(pos + 2..pos + length).ok_or("Truncated JPEG")?;
+        pos += length;
+        match marker {
+            0xDB => {
+                let mut rest = segment;
+                while !rest.is_empty() {
+                    let (precision, id) = (rest[0] >> 4, (rest[0] & 3) as usize);
+                    let size = if precision == 0 { 64 } else { 128 };
+                    let values = rest.get(1..1 + size).ok_or("Truncated JPEG quantization table")?;
+                    for (i, &position) in ZIGZAG.iter().enumerate() {
+                        quant[id][position] = if precision == 0 {
+                            values[i] as u16
+                        } else {
+                            u16::from_be_bytes([values[i * 2], values[i * 2 + 1]])
+                        };
+                    }
+                    rest = &rest[1 + size..];
+                }
+            }
+            0xC4 => {
+                let mut rest = segment;
+                while rest.len() >= 17 {
+                    let (class, id) = (rest[0] >> 4, (rest[0] & 3) as usize);
+                    let counts: [u8; 16] = rest[1..17].try_into().unwrap();
+                    let total: usize = counts.iter().map(|&c| c as usize).sum();
+                    let symbols = rest.get(17..17 + total).ok_or("Truncated JPEG Huffman table")?.to_vec();
+                    let table = Some(Huffman::new(&counts, symbols));
+                    if class == 0 {
+                        dc_tables[id] = table;
+                    } else {
+                        ac_tables[id] = table;
+                    }
+                    rest = &rest[17 + total..];
+                }
+            }
+            0xDD => restart_interval = u16::from_be_bytes([segment[0], segment[1]]) as usize,
+            0xEE if segment.starts_with(b"Adobe") && segment.len() >= 12 => adobe_transform = Some(segment[11]),
+            0xC0..=0xC2 => frame = Some(read_frame(segment, marker == 0xC2)?),
+            0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF => {
+                return Err("Unsupported JPEG encoding".to_string());
+            }
+            0xDA => {
+                let frame = frame.as_mut().ok_or("JPEG scan before frame header")?;
+                let scan = read_scan(segment, frame)?;
+                let mut reader = BitReader { data, pos, bits: 0, count: 0 };
+                decode_scan(&mut reader, frame, &scan, &dc_tables, &ac_tables, restart_interval)?;
+                pos = reader.end();
+            }
+            _ => {}
+        }
+    }
+
+    let frame = frame.ok_or("JPEG has no frame header")?;
+    render(&frame, &quant, adobe_transform)
+}
+
+fn read_frame(segment: &[u8], progressive: bool) -> Result<Frame, String> {
+    if segment.len() < 6 || segment[0] != 8 {
+        return Err("Only 8-bit JPEGs are supported".to_string());
+    }
+    let height = u16::from_be_bytes([segment[1], segment[2]]) as usize;
+    let width = u16::from_be_bytes([segment[3], segment[4]]) as usize;
+    let count = segment[5] as usize;
+    if width == 0 || height == 0 {
+        return Err("JPEG has no size".to_string());
+    }
+    if width as u64 * height as u64 > MAX_PIXELS {
+        return Err(format!("JPEG is too large ({}×{} pixels)", width, height));
+    }
+    if count != 1 && count != 3 {
+        return Err("CMYK JPEGs are not supported".to_string());
+    }
+    let mut components = Vec::new();
+    for i in 0..count {
+        let spec = segment.get(6 + i * 3..9 + i * 3).ok_or("Truncated JPEG frame header")?;
+        let (h, v) = ((spec[1] >> 4) as usize, (spec[1] & 15) as usize);
+        if !(1..=4).contains(&h) || !(1..=4).contains(&v) {
+            return Err("Invalid JPEG sampling factors".to_string());
+        }
+        components.push(Component {
+            id: spec[0],
+            h,
+            v,
+            quant: (spec[2] & 3) as usize,
+            blocks_w: 0,
+            blocks_h: 0,
+            coefficients: Vec::new(),
+            dc_table: 0,
+            ac_table: 0,
+            prediction: 0,
+        });
+    }
+    let h_max = components.iter().map(|c| c.h).max().unwrap_or(1);
+    let v_max = components.iter().map(|c| c.v).max().unwrap_or(1);
+    let mcus_x = width.div_ceil(8 * h_max);
+    let mcus_y = height.div_ceil(8 * v_max);
+    for component in &mut components {
+        component.blocks_w = mcus_x * component.h;
+        component.blocks_h = mcus_y * component.v;
+        component.coefficients = vec![[0; 64]; component.blocks_w * component.blocks_h];
+    }
+    Ok(Frame { width, height, progressive, h_max, v_max, mcus_x, mcus_y, components })
+}
+
+fn read_scan(segment: &[u8], frame: &mut Frame) -> Result<Scan, String> {
+    let count = *segment.first().ok_or("Truncated JPEG scan header")? as usize;
+    let mut components = Vec::new();
+    for i in 0..count {
+        let spec = segment.get(1 + i * 2..3 + i * 2).ok_or("Truncated JPEG scan header")?;
+        let index = frame
+            .components
+            .iter()
+            .position(|c| c.id == spec[0])
+            .ok_or("JPEG scan refers to an unknown component")?;
+        frame.components[index].dc_table = (spec[1] >> 4) as usize & 3;
+        frame.components[index].ac_table = (spec[1] & 15) as usize & 3;
+        components.push(index);
+    }
+    let tail = segment.get(1 + count * 2..4 + count * 2).ok_or("Truncated JPEG scan header")?;
+    let (start, end) = (tail[0] as usize, tail[1] as usize);
+    if start > end || end > 63 {
+        return Err("Invalid JPEG spectral selection".to_string());
+    }
+    Ok(Scan { components, start, end, high: (tail[2] >> 4) as u32, low: (tail[2] & 15) as u32 })
+}
+
+fn decode_scan(
+    reader: &mut BitReader,
+    frame: &mut Frame,
+    scan: &Scan,
+    dc_tables: &[Option<Huffman>; 4],
+    ac_tables: &[Option<Huffman>; 4],
+    restart_interval: usize,
+) -> Result<(), String> {
+    for &index in &scan.components {
+        frame.components[index].prediction = 0;
+    }
+    let mut eob_run = 0u32;
+
+    // A single-component scan covers only the blocks inside the image; an
+    // interleaved one goes MCU by MCU
+    let units: Vec<Vec<(usize, usize)>> = if scan.components.len() == 1 {
+        let component = &frame.components[scan.components[0]];
+        let width = (frame.width * component.h).div_ceil(frame.h_max).div_ceil(8);
+        let height = (frame.height * component.v).div_ceil(frame.v_max).div_ceil(8);
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| vec![(scan.components[0], y * component.blocks_w + x)]))
+            .collect()
+    } else {
+        let mut units = Vec::with_capacity(frame.mcus_x * frame.mcus_y);
+        for mcu_y in 0..frame.mcus_y {
+            for mcu_x in 0..frame.mcus_x {
+                let mut blocks = Vec::new();
+                for &index in &scan.components {
+                    let component = &frame.components[index];
+                    for v in 0..component.v {
+                        for h in 0..component.h {
+                            let row = mcu_y * component.v + v;
+                            let column = mcu_x * component.h + h;
+                            blocks.push((index, row * component.blocks_w + column));
+                        }
+                    }
+                }
+                units.push(blocks);
+            }
+        }
+        units
+    };
+
+    for (n, blocks) in units.iter().enumerate() {
+        if restart_interval > 0 && n > 0 && n % restart_interval == 0 {
+            reader.restart();
+            eob_run = 0;
+            for &index in &scan.components {
+                frame.components[index].prediction = 0;
+            }
+        }
+        for &(index, block) in blocks {
+            let component = &mut frame.components[index];
+            let dc = dc_tables[component.dc_table].as_ref();
+            let ac = ac_tables[component.ac_table].as_ref();
+            let missing = || "JPEG refers to a missing Huffman table".to_string();
+            let coefficients = &mut component.coefficients[block];
+            if !frame.progressive {
+                let dc = dc.ok_or_else(missing)?;
+                let ac = ac.ok_or_else(missing)?;
+                let size = reader.decode(dc)? as u32;
+                component.prediction += reader.signed(size.min(16));
+                coefficients[0] = component.prediction as i16;
+                let mut k = 1;
+                while k < 64 {
+                    let rs = reader.decode(ac)?;
+                    let (run, size) = ((rs >> 4) as usize, (rs & 15) as u32);
+                    if size == 0 {
+                        if run != 15 {
+                            break;
+                        }
+                        k += 16;
+                        continue;
+                    }
+                    k += run;
+                    if k > 63 {
+                        break;
+                    }
+                    coefficients[ZIGZAG[k]] = reader.signed(size) as i16;
+                    k += 1;
+                }
+            } else if scan.start == 0 {
+                if scan.high == 0 {
+                    let size = reader.decode(dc.ok_or_else(missing)?)? as u32;
+                    component.prediction += reader.signed(size.min(16));
+                    coefficients[0] = (component.prediction << scan.low) as i16;
+                } else if reader.bits(1) == 1 {
+                    coefficients[0] |= 1 << scan.low;
+                }
+            } else if scan.high == 0 {
+                decode_ac_first(reader, coefficients, ac.ok_or_else(missing)?, scan, &mut eob_run)?;
+            } else {
+                decode_ac_refine(reader, coefficients, ac.ok_or_else(missing)?, scan, &mut eob_run)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn decode_ac_first(
+    reader: &mut BitReader,
+    coefficients: &mut [i16; 64],
+    table: &Huffman,
+    scan: &Scan,
+    eob_run: &mut u32,
+) -> Result<(), String> {
+    if *eob_run > 0 {
+        *eob_run -= 1;
+        return Ok(());
+    }
+    let mut k = scan.start;
+    while k <= scan.end {
+        let rs = reader.decode(table)?;
+        let (run, size) = ((rs >> 4) as u32, (rs & 15) as u32);
+        if size == 0 {
+            if run < 15 {
+                *eob_run = (1 << run) - 1 + reader.bits(run);
+                break;
+            }
+            k += 16;
+            continue;
+        }
+        k += run as usize;
+        if k > 63 {
+            break;
+        }
+        coefficients[ZIGZAG[k]] = (reader.signed(size) * (1 << scan.low)) as i16;
+        k += 1;
+    }
+    Ok(())
+}
+
+/// A successive-approximation pass adding one bit of precision to the AC
+/// coefficients, as in the JPEG spec's G.1.2.3.
+fn decode_ac_refine(
+    reader: &mut BitReader,
+    coefficients: &mut [i16; 64],
+    table: &Huffman,
+    scan: &Scan,
+    eob_run: &mut u32,
+) -> Result<(), String> {
+    let plus = 1i16 << scan.low;
+    let minus = -1i16 << scan.low;
+    let refine = |reader: &mut BitReader, coefficient: &mut i16| {
+        if reader.bits(1) == 1 && *coefficient & plus == 0 {
+            *coefficient += if *coefficient >= 0 { plus } else { minus };
+        }
+    };
+
+    let mut k = scan.start;
+    if *eob_run == 0 {
+        while k <= scan.end {
+            let rs = reader.decode(table)?;
+            let mut run = (rs >> 4) as i32;
+            let mut value = 0;
+            if rs & 15 != 0 {
+                value = if reader.bits(1) == 1 { plus } else { minus };
+            } else if run != 15 {
+                *eob_run = (1 << run) + reader.bits(run as u32);
+                break;
+            }
+            // Skip `run` zero coefficients, refining the nonzero ones passed
+            while k <= scan.end {
+                let coefficient = &mut coefficients[ZIGZAG[k]];
+                if *coefficient != 0 {
+                    refine(reader, coefficient);
+                } else {
+                    run -= 1;
+                    if run < 0 {
+                        break;
+                    }
+                }
+                k += 1;
+            }
+            if value != 0 && k <= 63 {
+                coefficients[ZIGZAG[k]] = value;
+            }
+            k += 1;
+        }
+    }
+    if *eob_run > 0 {
+        while k <= scan.end {
+            let coefficient = &mut coefficients[ZIGZAG[k]];
+            if *coefficient != 0 {
+                refine(reader, coefficient);
+            }
+            k += 1;
+        }
+        *eob_run -= 1;
+    }
+    Ok(())
+}
+
+/// Dequantize, inverse-transform and color-convert the decoded blocks.
+fn render(frame: &Frame, quant: &[[u16; 64]; 4], adobe_transform: Option<u8>) -> Result<Pixels, String> {
+    let cosines = cosines();
+    let planes: Vec<Vec<u8>> = frame
+        .components
+        .iter()
+        .map(|component| {
+            let stride = component.blocks_w * 8;
+            let mut plane = vec![0u8; stride * component.blocks_h * 8];
+            let table = &quant[component.quant];
+            for (n, coefficients) in component.coefficients.iter().enumerate() {
+                let (bx, by) = (n % component.blocks_w, n / component.blocks_w);
+                let mut block = [0f32; 64];
+                for i in 0..64 {
+                    block[i] = coefficients[i] as f32 * table[i] as f32;
+                }
+                let samples = inverse_dct(&block, &cosines);
+                for y in 0..8 {
+                    let row = (by * 8 + y) * stride + bx * 8;
+                    plane[row..row + 8].copy_from_slice(&samples[y * 8..y * 8 + 8]);
+                }
+            }
+            plane
+        })
+        .collect();
+
+    let mut rgba = Vec::with_capacity(frame.width * frame.height * 4);
+    let sample = |index: usize, x: usize, y: usize| -> f32 {
+        let component = &frame.components[index];
+        let sx = x * component.h / frame.h_max;
+        let sy = y * component.v / frame.v_max;
+        planes[index][sy * component.blocks_w * 8 + sx] as f32
+    };
+    // Three components are YCbCr unless an Adobe marker or R, G, B
+    // component ids say otherwise
+    let is_rgb = adobe_transform == Some(0)
+        || frame.components.iter().map(|c| c.id).eq([b'R', b'G', b'B'].iter().copied());
+    for y in 0..frame.height {
+        for x in 0..frame.width {
+            if frame.components.len() == 1 {
+                let gray = sample(0, x, y) as u8;
+                rgba.extend_from_slice(&[gray, gray, gray, 255]);
+                continue;
+            }
+            let (a, b, c) = (sample(0, x, y), sample(1, x, y), sample(2, x, y));
+            let (r, g, b) = if is_rgb {
+                (a, b, c)
+            } else {
+                (
+                    a + 1.402 * (c - 128.0),
+                    a - 0.344_136 * (b - 128.0) - 0.714_136 * (c - 128.0),
+                    a + 1.772 * (b - 128.0),
+                )
+            };
+            rgba.extend_from_slice(&[clamp(r), clamp(g), clamp(b), 255]);
+        }
+    }
+    Ok(Pixels { width: frame.width as u32, height: frame.height as u32, rgba })
+}
+
+fn clamp(value: f32) -> u8 {
+    value.round().clamp(0.0, 255.0) as u8
+}
+
+fn inverse_dct(block: &[f32; 64], cosines: &[f32; 64]) -> [u8; 64] {
+    let mut out = [0u8; 64];
+    if block[1..].iter().all(|&c| c == 0.0) {
+        out.fill(clamp(block[0] / 8.0 + 128.0));
+        return out;
+    }
+    // Rows first (u → x), then columns (v → y)
+    let mut rows = [0f32; 64];
+    for v in 0..8 {
+        for x in 0..8 {
+            rows[v * 8 + x] = (0..8).map(|u| block[v * 8 + u] * cosines[x * 8 + u]).sum();
+        }
+    }
+    for y in 0..8 {
+        for x in 0..8 {
+            let value: f32 = (0..8).map(|v| rows[v * 8 + x] * cosines[y * 8 + v]).sum();
+            out[y * 8 + x] = clamp(value + 128.0);
+        }
+    }
+    out
+}
+
+fn forward_dct(samples: &[f32; 64], cosines: &[f32; 64]) -> [f32; 64] {
+    let mut rows = [0f32; 64];
+    for y in 0..8 {
+        for u in 0..8 {
+            rows[y * 8 + u] = (0..8).map(|x| samples[y * 8 + x] * cosines[x * 8 + u]).sum();
+        }
+    }
+    let mut out = [0f32; 64];
+    for v in 0..8 {
+        for u in 0..8 {
+            out[v * 8 + u] = (0..8).map(|y| rows[y * 8 + u] * cosines[y * 8 + v]).sum();
+        }
+    }
+    out
+}
+
+/// The example tables of the JPEG spec (Annex K), in natural order.
+const LUMA_QUANT: [u16; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61, 12, 12, 14, 19, 26, 58, 60, 55, 14, 13, 16, 24, 40, 57, 69, 56, 14, 17, 22, 29, 51,
+    87, 80, 62, 18, 22, 37, 56, 68, 109, 103, 77, 24, 35, 55, 64, 81, 104, 113, 92, 49, 64, 78, 87, 103, 121, 120, 101,
+    72, 92, 95, 98, 112, 100, 103, 99,
+];
+const CHROMA_QUANT: [u16; 64] = [
+    17, 18, 24, 47, 99, 99, 99, 99, 18, 21, 26, 66, 99, 99, 99, 99, 24, 26, 56, 99, 99, 99, 99, 99, 47, 66, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99,
+];
+
+const LUMA_DC_COUNTS: [u8; 16] = [0, 1, 5, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0];
+const CHROMA_DC_COUNTS: [u8; 16] = [0, 3, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0];
+const DC_SYMBOLS: [u8; 12] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+
+const LUMA_AC_COUNTS: [u8; 16] = [0, 2, 1, 3, 3, 2, 4, 3, 5, 5, 4, 4, 0, 0, 1, 0x7d];
+const LUMA_AC_SYMBOLS: [u8; 162] = [
+    0x01, 0x02, 0x03, 0x00, 0x04, 0x11, 0x05, 0x12, 0x21, 0x31, 0x41, 0x06, 0x13, 0x51, 0x61, 0x07, 0x22, 0x71, 0x14,
+    0x32, 0x81, 0x91, 0xa1, 0x08, 0x23, 0x42, 0xb1, 0xc1, 0x15, 0x52, 0xd1, 0xf0, 0x24, 0x33, 0x62, 0x72, 0x82, 0x09,
+    0x0a, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x25, 0x26, 0x27, 0x28, 0x29, 0x2a, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a,
+    0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x63, 0x64, 0x65,
+    0x66, 0x67, 0x68, 0x69, 0x6a, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88,
+    0x89, 0x8a, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7, 0xa8, 0xa9,
+    0xaa, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9, 0xca,
+    0xd2, 0xd3, 0xd4, 0xd5, 0xd6, 0xd7, 0xd8, 0xd9, 0xda, 0xe1, 0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8, 0xe9, 0xea,
+    0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa,
+];
+
+const CHROMA_AC_COUNTS: [u8; 16] = [0, 2, 1, 2, 4, 4, 3, 4, 7, 5, 4, 4, 0, 1, 2, 0x77];
+const CHROMA_AC_SYMBOLS: [u8; 162] = [
+    0x00, 0x01, 0x02, 0x03, 0x11, 0x04, 0x05, 0x21, 0x31, 0x06, 0x12, 0x41, 0x51, 0x07, 0x61, 0x71, 0x13, 0x22, 0x32,
+    0x81, 0x08, 0x14, 0x42, 0x91, 0xa1, 0xb1, 0xc1, 0x09, 0x23, 0x33, 0x52, 0xf0, 0x15, 0x62, 0x72, 0xd1, 0x0a, 0x16,
+    0x24, 0x34, 0xe1, 0x25, 0xf1, 0x17, 0x18, 0x19, 0x1a, 0x26, 0x27, 0x28, 0x29, 0x2a, 0x35, 0x36, 0x37, 0x38, 0x39,
+    0x3a, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x63, 0x64,
+    0x65, 0x66, 0x67, 0x68, 0x69, 0x6a, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x82, 0x83, 0x84, 0x85, 0x86,
+    0x87, 0x88, 0x89, 0x8a, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7,
+    0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7, 0xc8,
+    0xc9, 0xca, 0xd2, 0xd3, 0xd4, 0xd5, 0xd6, 0xd7, 0xd8, 0xd9, 0xda, 0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8, 0xe9,
+    0xea, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa,
+];
+
+/// Code and length of each symbol of a Huffman table.
+fn huffman_codes(counts: &[u8; 16], symbols: &[u8]) -> [(u16, u8); 256] {
+    let mut codes = [(0, 0); 256];
+    let (mut code, mut k) = (0u16, 0);
+    for len in 1..=16 {
+        for _ in 0..counts[len - 1] {
+            codes[symbols[k] as usize] = (code, len as u8);
+            code += 1;
+            k += 1;
+        }
+        code <<= 1;
+    }
+    codes
+}
+
+struct BitWriter {
+    out: Vec<u8>,
+    bits: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, len: u32) {
+        if len == 0 {
+            return;
+        }
+        self.bits = self.bits << len | (value & ((1 << len) - 1));
+        self.count += len;
+        while self.count >= 8 {
+            let byte = (self.bits >> (self.count - 8)) as u8;
+            self.out.push(byte);
+            if byte == 0xFF {
+                self.out.push(0);
+            }
+            self.count -= 8;
+        }
+        self.bits &= (1 << self.count) - 1;
+    }
+
+    /// Pad the last byte with one bits.
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.write(0x7F, 8 - self.count);
+        }
+        self.out
+    }
+}
+
+/// Encode pixels as a baseline JPEG with 4:2:0 chroma subsampling.
+/// `quality` is 1–100, scaling the spec's example tables as libjpeg does.
+/// Alpha is dropped.
+pub fn encode_jpeg(pixels: &Pixels, quality: u8) -> Result<Vec<u8>, String> {
+    let (width, height) = (pixels.width as usize, pixels.height as usize);
+    if width == 0 || height == 0 || width > 65535 || height > 65535 {
+        return Err("Image size is out of range for JPEG".to_string());
+    }
+    let quality = quality.clamp(1, 100) as u32;
+    let scale = if quality < 50 { 5000 / quality } else { 200 - quality * 2 };
+    let scaled = |table: &[u16; 64]| table.map(|q| ((q as u32 * scale + 50) / 100).clamp(1, 255) as u16);
+    let quant = [scaled(&LUMA_QUANT), scaled(&CHROMA_QUANT)];
+
+    let mut out = vec![0xFF, 0xD8];
+    let mut segment = |marker: u8, body: &[u8]| {
+        out.extend_from_slice(&[0xFF, marker]);
+        out.extend_from_slice(&((body.len() + 2) as u16).to_be_bytes());
+        out.extend_from_slice(body);
+    };
+    segment(0xE0, b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+    for (id, table) in quant.iter().enumerate() {
+        let mut body = vec![id as u8];
+        body.extend(ZIGZAG.iter().map(|&position| table[position] as u8));
+        segment(0xDB, &body);
+    }
+    let mut frame = vec![8];
+    frame.extend_from_slice(&(height as u16).to_be_bytes());
+    frame.extend_from_slice(&(width as u16).to_be_bytes());
+    frame.extend_from_slice(&[3, 1, 0x22, 0, 2, 0x11, 1, 3, 0x11, 1]);
+    segment(0xC0, &frame);
+    let tables: [(u8, &[u8; 16], &[u8]); 4] = [
+        (0x00, &LUMA_DC_COUNTS, &DC_SYMBOLS),
+        (0x10, &LUMA_AC_COUNTS, &LUMA_AC_SYMBOLS),
+        (0x01, &CHROMA_DC_COUNTS, &DC_SYMBOLS),
+        (0x11, &CHROMA_AC_COUNTS, &CHROMA_AC_SYMBOLS),
+    ];
+    for (class_id, counts, symbols) in tables {
+        let mut body = vec![class_id];
+        body.extend_from_slice(counts);
+        body.extend_from_slice(symbols);
+        segment(0xC4, &body);
+    }
+    segment(0xDA, &[3, 1, 0x00, 2, 0x11, 3, 0x11, 0, 63, 0]);
+
+    let codes = [
+        (huffman_codes(&LUMA_DC_COUNTS, &DC_SYMBOLS), huffman_codes(&LUMA_AC_COUNTS, &LUMA_AC_SYMBOLS)),
+        (huffman_codes(&CHROMA_DC_COUNTS, &DC_SYMBOLS), huffman_codes(&CHROMA_AC_COUNTS, &CHROMA_AC_SYMBOLS)),
+    ];
+    let cosines = cosines();
+    let mut writer = BitWriter { out: Vec::new(), bits: 0, count: 0 };
+    let mut predictions = [0i32; 3];
+
+    // Edge pixels are repeated to fill the last MCUs
+    let ycc = |x: usize, y: usize| -> [f32; 3] {
+        let i = (y.min(height - 1) * width + x.min(width - 1)) * 4;
+        let (r, g, b) = (pixels.rgba[i] as f32, pixels.rgba[i + 1] as f32, pixels.rgba[i + 2] as f32);
+        [
+            0.299 * r + 0.587 * g + 0.114 * b,
+            -0.168_736 * r - 0.331_264 * g + 0.5 * b + 128.0,
+            0.5 * r - 0.418_688 * g - 0.081_312 * b + 128.0,
+        ]
+    };
+    for mcu_y in (0..height).step_by(16) {
+        for mcu_x in (0..width).step_by(16) {
+            let mut luma = [[0f32; 64]; 4];
+            let mut chroma = [[0f32; 64]; 2];
+            for y in 0..16 {
+                for x in 0..16 {
+                    let [l, cb, cr] = ycc(mcu_x + x, mcu_y + y);
+                    luma[(y / 8) * 2 + x / 8][(y % 8) * 8 + x % 8] = l - 128.0;
+                    let i = (y / 2) * 8 + x / 2;
+                    chroma[0][i] += (cb - 128.0) / 4.0;
+                    chroma[1][i] += (cr - 128.0) / 4.0;
+                }
+            }
+            let blocks = luma.iter().map(|b| (0, b)).chain(chroma.iter().enumerate().map(|(i, b)| (i + 1, b)));
+            for (component, samples) in blocks {
+                let table = usize::from(component > 0);
+                let coefficients = forward_dct(samples, &cosines);
+                let mut quantized = [0i32; 64];
+                for (i, &position) in ZIGZAG.iter().enumerate() {
+                    quantized[i] = (coefficients[position] / quant[table][position] as f32).round() as i32;
+                }
+                let (dc_codes, ac_codes) = &codes[table];
+                let diff = quantized[0] - predictions[component];
+                predictions[component] = quantized[0];
+                let size = magnitude_bits(diff);
+                let (code, len) = dc_codes[size as usize];
+                writer.write(code as u32, len as u32);
+                writer.write(magnitude_value(diff, size), size);
+
+                let mut run = 0;
+                for &value in &quantized[1..] {
+                    if value == 0 {
+                        run += 1;
+                        continue;
+                    }
+                    while run > 15 {
+                        let (code, len) = ac_codes[0xF0];
+                        writer.write(code as u32, len as u32);
+                        run -= 16;
+                    }
+                    let size = magnitude_bits(value);
+                    let (code, len) = ac_codes[(run << 4 | size) as usize];
+                    writer.write(code as u32, len as u32);
+                    writer.write(magnitude_value(value, size), size);
+                    run = 0;
+                }
+                if run > 0 {
+                    let (code, len) = ac_codes[0x00];
+                    writer.write(code as u32, len as u32);
+                }
+            }
+        }
+    }
+    out.extend(writer.finish());
+    out.extend_from_slice(&[0xFF, 0xD9]);
+    Ok(out)
+}
+
+fn magnitude_bits(value: i32) -> u32 {
+    32 - value.unsigned_abs().leading_zeros()
+}
+
+fn magnitude_value(value: i32, size: u32) -> u32 {
+    if value < 0 {
+        (value - 1) as u32 & ((1 << size) - 1)
+    } else {
+        value as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jpeg_round_trip() {
+        // Both AC tables cover every run/size symbol exactly once
+        for (counts, symbols) in [(LUMA_AC_COUNTS, LUMA_AC_SYMBOLS), (CHROMA_AC_COUNTS, CHROMA_AC_SYMBOLS)] {
+            assert_eq!(counts.iter().map(|&c| c as usize).sum::<usize>(), symbols.len());
+            let mut sorted = symbols.to_vec();
+            sorted.sort();
+            let mut expected = vec![0x00, 0xF0];
+            expected.extend((0..16).flat_map(|run| (1..=10).map(move |size| run << 4 | size)));
+            expected.sort();
+            assert_eq!(sorted, expected);
+        }
+
+        // A gradient with a sharp edge, at a size that is not whole MCUs
+        let (width, height) = (37, 21);
+        let mut rgba = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let edge = if x >= 20 { 200 } else { 20 };
+                rgba.extend_from_slice(&[(x * 6) as u8, (y * 10) as u8, edge, 255]);
+            }
+        }
+        let pixels = Pixels { width, height, rgba };
+        let encoded = encode_jpeg(&pixels, 90).unwrap();
+        let decoded = decode_jpeg(&encoded).unwrap();
+        assert_eq!((decoded.width, decoded.height), (width, height));
+        let error: f64 = pixels
+            .rgba
+            .iter()
+            .zip(&decoded.rgba)
+            .map(|(&a, &b)| (a as f64 - b as f64).powi(2))
+            .sum::<f64>()
+            / pixels.rgba.len() as f64;
+        assert!(error < 20.0, "mean squared error {}", error);
+    }
+}
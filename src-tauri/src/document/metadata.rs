@@ -0,0 +1,235 @@
+//! Camera metadata in image files: EXIF (which may hold GPS coordinates),
+//! XMP, IPTC and text comments.
+
+/// PNG chunks holding metadata rather than image data.
+const PNG_METADATA_CHUNKS: [&[u8; 4]; 5] = [b"eXIf", b"tEXt", b"zTXt", b"iTXt", b"tIME"];
+
+/// The EXIF orientation of a JPEG, PNG or WebP image: 1 when upright or
+/// unknown, up to 8 for turned and mirrored images.
+pub fn exif_orientation(bytes: &[u8]) -> u16 {
+    let exif = if bytes.starts_with(&[0xFF, 0xD8]) {
+        jpeg_segments(bytes)
+            .into_iter()
+            .find(|&(marker, body)| marker == 0xE1 && body.starts_with(b"Exif\0\0"))
+            .map(|(_, body)| &body[6..])
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png_chunks(bytes).into_iter().find(|(kind, _)| kind == b"eXIf").map(|(_, data)| data)
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        riff_chunks(bytes).into_iter().find(|(kind, _)| kind == b"EXIF").map(|(_, data)| {
+            // Some writers keep the JPEG-style prefix
+            data.strip_prefix(b"Exif\0\0").unwrap_or(data)
+        })
+    } else {
+        None
+    };
+    exif.and_then(tiff_orientation).filter(|o| (1..=8).contains(o)).unwrap_or(1)
+}
+
+/// The Orientation tag (0x0112) of IFD0 in TIFF-structured EXIF data.
+fn tiff_orientation(tiff: &[u8]) -> Option<u16> {
+    let big_endian = match tiff.get(0..2)? {
+        b"MM" => true,
+        b"II" => false,
+        _ => return None,
+    };
+    let u16_at = |at: usize| -> Option<u16> {
+        let bytes: [u8; 2] = tiff.get(at..at + 2)?.try_into().ok()?;
+        Some(if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+    };
+    let u32_at = |at: usize| -> Option<u32> {
+        let bytes: [u8; 4] = tiff.get(at..at + 4)?.try_into().ok()?;
+        Some(if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    };
+    let ifd = u32_at(4)? as usize;
+    let count = u16_at(ifd)? as usize;
+    (0..count.min(512))
+        .map(|i| ifd + 2 + i * 12)
+        .find(|&entry| u16_at(entry) == Some(0x0112))
+        .and_then(|entry| u16_at(entry + 8))
+}
+
+/// The image with its metadata removed. The pixel data is copied as it is,
+/// and color profiles are kept. A JPEG keeps a minimal EXIF block with its
+/// orientation, so it still displays upright. Other formats are returned
+/// unchanged.
+pub fn strip_metadata(bytes: &[u8]) -> Vec<u8> {
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        strip_jpeg(bytes)
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        let mut out = bytes[..8].to_vec();
+        let mut pos = 8;
+        while pos + 12 <= bytes.len() {
+            let length = u32::from_be_bytes(bytes[pos..pos + 4].try_into().unwrap()) as usize;
+            let end = (pos + 12 + length).min(bytes.len());
+            let kind = &bytes[pos + 4..pos + 8];
+            if !PNG_METADATA_CHUNKS.iter().any(|k| k.as_slice() == kind) {
+                out.extend_from_slice(&bytes[pos..end]);
+            }
+            pos = end;
+        }
+        out
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        let mut out = b"RIFF\0\0\0\0WEBP".to_vec();
+        for (kind, data) in riff_chunks(bytes) {
+            if kind == b"EXIF" || kind == b"XMP " {
+                continue;
+            }
+            out.extend_from_slice(kind);
+            out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            let start = out.len();
+            out.extend_from_slice(data);
+            if kind == b"VP8X" && data.len() >= 4 {
+                // Clear the EXIF and XMP flags
+                out[start] &= !0x0C;
+            }
+            if data.len() % 2 == 1 {
+                out.push(0);
+            }
+        }
+        let size = (out.len() - 8) as u32;
+        out[4..8].copy_from_slice(&size.to_le_bytes());
+        out
+    } else {
+        bytes.to_vec()
+    }
+}
+
+fn strip_jpeg(bytes: &[u8]) -> Vec<u8> {
+    let orientation = exif_orientation(bytes);
+    let mut out = vec![0xFF, 0xD8];
+    let mut pos = 2;
+    let mut wrote_orientation = orientation == 1;
+    while pos + 4 <= bytes.len() && bytes[pos] == 0xFF {
+        let marker = bytes[pos + 1];
+        if marker == 0xDA || marker == 0xD9 {
+            break;
+        }
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        let length = u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+        let end = (pos + 2 + length).min(bytes.len());
+        let body = &bytes[(pos + 4).min(end)..end];
+        let keep = match marker {
+            // JFIF, and the Adobe marker that says how colors are stored
+            0xE0 => true,
+            0xEE => true,
+            0xE2 => body.starts_with(b"ICC_PROFILE\0"),
+            0xE1..=0xEF | 0xFE => false,
+            _ => true,
+        };
+        // The orientation goes after JFIF (which must come first), ahead of
+        // the tables and frame
+        if !wrote_orientation && marker != 0xE0 {
+            out.extend_from_slice(&orientation_segment(orientation));
+            wrote_orientation = true;
+        }
+        if keep {
+            out.extend_from_slice(&bytes[pos..end]);
+        }
+        pos = end;
+    }
+    if !wrote_orientation {
+        out.extend_from_slice(&orientation_segment(orientation));
+    }
+    out.extend_from_slice(&bytes[pos.min(bytes.len())..]);
+    out
+}
+
+/// An APP1 segment with EXIF data holding only an orientation.
+fn orientation_segment(orientation: u16) -> Vec<u8> {
+    let mut segment = vec![0xFF, 0xE1, 0, 34];
+    segment.extend_from_slice(b"Exif\0\0MM\0\x2A\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01");
+    segment.extend_from_slice(&orientation.to_be_bytes());
+    segment.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+    segment
+}
+
+/// Marker and body of each JPEG segment before the image data.
+fn jpeg_segments(bytes: &[u8]) -> Vec<(u8, &[u8])> {
+    let mut segments = Vec::new();
+    let mut pos = 2;
+    while pos + 4 <= bytes.len() && bytes[pos] == 0xFF {
+        let marker = bytes[pos + 1];
+        if marker == 0xDA || marker == 0xD9 {
+            break;
+        }
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        let length = u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+        let end = (pos + 2 + length).min(bytes.len());
+        segments.push((marker, &bytes[(pos + 4).min(end)..end]));
+        pos = end;
+    }
+    segments
+}
+
+fn png_chunks(bytes: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut chunks = Vec::new();
+    let mut pos = 8;
+    while pos + 12 <= bytes.len() {
+        let length = u32::from_be_bytes(bytes[pos..pos + 4].try_into().unwrap()) as usize;
+        let Some(data) = bytes.get(pos + 8..pos + 8 + length) else {
+            break;
+        };
+        chunks.push((&bytes[pos + 4..pos + 8], data));
+        pos += 12 + length;
+    }
+    chunks
+}
+
+fn riff_chunks(bytes: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut chunks = Vec::new();
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let length = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        let Some(data) = bytes.get(pos + 8..pos + 8 + length) else {
+            break;
+        };
+        chunks.push((&bytes[pos..pos + 4], data));
+        pos += 8 + length + length % 2;
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_metadata() {
+        // EXIF with an orientation and a GPS IFD pointer, then a comment
+        let mut tiff = b"II\x2A\0\x08\0\0\0\x02\0".to_vec();
+        tiff.extend_from_slice(&[0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0]);
+        tiff.extend_from_slice(&[0x25, 0x88, 4, 0, 1, 0, 0, 0, 0x26, 0, 0, 0]);
+        tiff.extend_from_slice(&[0; 4]);
+        let mut exif = b"Exif\0\0".to_vec();
+        exif.extend_from_slice(&tiff);
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0, 16];
+        jpeg.extend_from_slice(b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+        jpeg.extend_from_slice(&[0xFF, 0xE1]);
+        jpeg.extend_from_slice(&((exif.len() + 2) as u16).to_be_bytes());
+        jpeg.extend_from_slice(&exif);
+        jpeg.extend_from_slice(&[0xFF, 0xFE, 0, 7]);
+        jpeg.extend_from_slice(b"hello");
+        jpeg.extend_from_slice(&[0xFF, 0xDA, 0, 2, 1, 2, 3, 0xFF, 0xD9]);
+        assert_eq!(exif_orientation(&jpeg), 6);
+
+        let stripped = strip_metadata(&jpeg);
+        assert_eq!(exif_orientation(&stripped), 6);
+        assert!(!stripped.windows(2).any(|w| w == [0x25, 0x88]));
+        assert!(!stripped.windows(5).any(|w| w == b"hello"));
+        assert!(stripped.starts_with(&jpeg[..20]));
+        assert!(stripped.ends_with(&[0xFF, 0xDA, 0, 2, 1, 2, 3, 0xFF, 0xD9]));
+
+        // WebP: EXIF and XMP chunks go, and so do their flags
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8X\x0A\0\0\0\x0C\0\0\0\0\0\0\0\0\0".to_vec();
+        webp.extend_from_slice(b"VP8L\x01\0\0\0\x2F\0EXIF\x03\0\0\0abc\0XMP \x02\0\0\0xy");
+        let stripped = strip_metadata(&webp);
+        assert_eq!(&stripped[8..], b"WEBPVP8X\x0A\0\0\0\0\0\0\0\0\0\0\0\0\0VP8L\x01\0\0\0\x2F\0");
+        assert_eq!(u32::from_le_bytes(stripped[4..8].try_into().unwrap()) as usize, stripped.len() - 8);
+    }
+}
@@ -0,0 +1,19 @@
+pub mod enex;
+pub mod epub;
+pub mod external;
+pub mod html;
+pub mod image;
+pub mod jex;
+pub mod jpeg;
+pub mod metadata;
+pub mod notion;
+pub mod office;
+pub mod pdf;
+pub mod pdf_text;
+pub mod readability;
+pub mod tar;
+pub mod textbundle;
+pub mod truetype;
+pub mod webp;
+pub mod xml;
+pub mod zip;
@@ -0,0 +1,350 @@
+use crate::commands::export::percent_decode;
+use crate::document::external::ExternalNote;
+use crate::document::zip::read_zip;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+
+/// A page of the export, or a database row that has no page.
+struct Page {
+    /// Path in the archive; rows without a page get `<database>/<title>`.
+    path: String,
+    title: String,
+    id: Option<String>,
+    text: String,
+    /// Set once the page is matched to a database row.
+    row: bool,
+    properties: Vec<(String, String)>,
+}
+
+/// Read a Notion "Markdown & CSV" export. The id Notion appends to every
+/// file and folder name is dropped (and kept as the note id), links between
+/// pages become wiki links, and each database CSV row becomes a note with
+/// the row's cells as properties, merged into the row's page when it has
+/// one. Subpages sit in folders named after their parent page, which are
+/// returned as the notes' folders.
+pub fn read_notion(data: &[u8]) -> Result<Vec<ExternalNote>, String> {
+    let mut files = HashMap::new();
+    read_entries(data, &mut files, true)?;
+
+    // A folder belongs to a page (or database) when it has a file of the
+    // same name next to it; other folders only wrap the export
+    let owners: HashSet<&str> = files
+        .keys()
+        .filter_map(|p| p.strip_suffix(".md").or_else(|| p.strip_suffix(".csv")))
+        .map(|p| p.strip_suffix("_all").unwrap_or(p))
+        .collect();
+    let folders_of = |path: &str| -> Vec<String> {
+        let (dir, _) = split_path(path);
+        let mut folders = Vec::new();
+        for (i, _) in dir.match_indices('/').chain([(dir.len(), "")]) {
+            let folder = &dir[..i];
+            if owners.contains(folder) {
+                folders.push(split_id(split_path(folder).1).0);
+            }
+        }
+        folders
+    };
+
+    let mut paths: Vec<&String> = files.keys().filter(|p| p.ends_with(".md")).collect();
+    paths.sort();
+    let mut pages: Vec<Page> = Vec::new();
+    for path in paths {
+        let text = String::from_utf8_lossy(&files[path]);
+        let (name, id) = split_id(split_path(path).1.trim_end_matches(".md"));
+        // Pages start with their title as a heading
+        let (first, rest) = text.split_once('\n').unwrap_or((&text, ""));
+        let (title, text) = match first.strip_prefix("# ") {
+            Some(title) if !title.trim().is_empty() => (title.trim().to_string(), rest.to_string()),
+            _ => (name, text.to_string()),
+        };
+        pages.push(Page {
+            path: path.clone(),
+            title,
+            id: id.map(|id| format_uuid(&id)),
+            text,
+            row: false,
+            properties: Vec::new(),
+        });
+    }
+    let titles_by_path: HashMap<String, String> = pages.iter().map(|p| (p.path.clone(), p.title.clone())).collect();
+    let titles_by_id: HashMap<String, String> = pages
+        .iter()
+        .filter_map(|p| Some((p.id.as_ref()?.replace('-', ""), p.title.clone())))
+        .collect();
+
+    // Newer exports have `Database.csv` with the rows of the exported view
+    // and `Database_all.csv` with every row
+    let mut tables: HashMap<&str, &String> = HashMap::new();
+    for path in files.keys().filter(|p| p.ends_with(".csv")) {
+        let stem = &path[..path.len() - 4];
+        match stem.strip_suffix("_all") {
+            Some(base) => {
+                tables.insert(base, path);
+            }
+            None => {
+                tables.entry(stem).or_insert(path);
+            }
+        }
+    }
+    let mut tables: Vec<(&str, &String)> = tables.into_iter().collect();
+    tables.sort();
+    for (base, csv) in tables {
+        let mut rows = parse_csv(String::from_utf8_lossy(&files[csv]).trim_start_matches('\u{feff}')).into_iter();
+        let Some(header) = rows.next() else {
+            continue;
+        };
+        let header: Vec<String> = header.iter().map(|h| h.trim().to_string()).collect();
+        let row_dir = format!("{}/", base);
+        for row in rows {
+            let Some(title) = row.first().map(|t| t.trim().to_string()).filter(|t| !t.is_empty()) else {
+                continue;
+            };
+            let properties: Vec<(String, String)> = header
+                .iter()
+                .zip(&row)
+                .skip(1)
+                .filter(|(name, value)| !name.is_empty() && !value.trim().is_empty())
+                .map(|(name, value)| (name.clone(), value.trim().to_string()))
+                .collect();
+            let page = pages.iter_mut().find(|p| {
+                !p.row
+                    && p.title == title
+                    && p.path.strip_prefix(&row_dir).is_some_and(|rest| !rest.contains('/'))
+            });
+            match page {
+                Some(page) => {
+                    // The page repeats the row's cells as `Name: value` lines
+                    page.text = strip_property_lines(&page.text, &header);
+                    page.row = true;
+                    page.properties = properties;
+                }
+                None => pages.push(Page {
+                    path: format!("{}{}", row_dir, title),
+                    title,
+                    id: None,
+                    text: String::new(),
+                    row: true,
+                    properties,
+                }),
+            }
+        }
+    }
+
+    let link_re = Regex::new(r"(!?)\[([^\]\n]*)\]\(([^)\s]+)\)").unwrap();
+    let notion_url_re = Regex::new(r"^https?://(?:www\.)?notion\.so/\S*?([0-9a-f]{32})(?:[?#]\S*)?$").unwrap();
+    let mut notes = Vec::new();
+    for page in pages {
+        let (dir, _) = split_path(&page.path);
+        let mut assets = HashMap::new();
+        let content = link_re.replace_all(&page.text, |cap: &regex::Captures| {
+            let (bang, label, dest) = (&cap[1], &cap[2], &cap[3]);
+            if let Some(title) = notion_url_re.captures(dest).and_then(|c| titles_by_id.get(&c[1])) {
+                return wiki_link(title, label);
+            }
+            if dest.contains("://") || dest.starts_with('#') || dest.starts_with("mailto:") {
+                return cap[0].to_string();
+            }
+            let target = join_path(dir, &percent_decode(dest));
+            if let Some(title) = titles_by_path.get(&target) {
+                return wiki_link(title, label);
+            }
+            if target.ends_with(".csv") {
+                // A link to a database: its rows are notes of their own
+                return label.to_string();
+            }
+            match files.get(&target) {
+                Some(data) if !target.ends_with(".md") => {
+                    assets.insert(target.clone(), data.clone());
+                    format!("{}[{}](<{}>)", bang, label, target)
+                }
+                _ => cap[0].to_string(),
+            }
+        });
+
+        let mut tags = Vec::new();
+        let mut properties = Vec::new();
+        for (name, value) in page.properties {
+            // A multi-select "Tags" column holds the page's tags
+            if name.eq_ignore_ascii_case("tags") {
+                tags.extend(value.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()));
+            } else {
+                properties.push((name, value));
+            }
+        }
+        notes.push(ExternalNote {
+            app: "Notion",
+            id: page.id,
+            title: page.title,
+            content: content.trim_start_matches('\n').to_string(),
+            tags,
+            folders: folders_of(&page.path),
+            assets,
+            properties,
+            ..Default::default()
+        });
+    }
+    Ok(notes)
+}
+
+/// Collect the files of a zip. Large exports are a zip of zips.
+fn read_entries(data: &[u8], out: &mut HashMap<String, Vec<u8>>, nested: bool) -> Result<(), String> {
+    for entry in read_zip(data)? {
+        if entry.name.starts_with("__MACOSX/") {
+            continue;
+        }
+        if nested && entry.name.to_ascii_lowercase().ends_with(".zip") {
+            read_entries(&entry.data, out, false)?;
+        } else {
+            out.insert(entry.name, entry.data);
+        }
+    }
+    Ok(())
+}
+
+fn split_path(path: &str) -> (&str, &str) {
+    path.rsplit_once('/').unwrap_or(("", path))
+}
+
+/// Split the id Notion appends to a name, e.g. `Plans 0123…cdef`.
+fn split_id(name: &str) -> (String, Option<String>) {
+    let re = Regex::new(r"^(.*?)\s+([0-9a-f]{32})$").unwrap();
+    match re.captures(name) {
+        Some(cap) if !cap[1].is_empty() => (cap[1].to_string(), Some(cap[2].to_string())),
+        _ => (name.to_string(), None),
+    }
+}
+
+fn format_uuid(hex: &str) -> String {
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+/// Resolve a relative link from a file in `dir` to a path in the archive.
+fn join_path(dir: &str, link: &str) -> String {
+    let mut parts: Vec<&str> = dir.split('/').filter(|p| !p.is_empty()).collect();
+    for part in link.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+fn wiki_link(title: &str, label: &str) -> String {
+    if label.is_empty() || label == title {
+        format!("[[{}]]", title)
+    } else {
+        format!("[[{}|{}]]", title, label)
+    }
+}
+
+/// Drop the `Name: value` lines for the database's columns that follow a
+/// row page's title.
+fn strip_property_lines(text: &str, columns: &[String]) -> String {
+    let mut lines = text.trim_start_matches('\n').lines().peekable();
+    while let Some(line) = lines.peek() {
+        let name = line.split_once(':').map(|(name, _)| name.trim());
+        if !name.is_some_and(|name| columns.iter().any(|c| c == name)) {
+            break;
+        }
+        lines.next();
+    }
+    let rest: Vec<&str> = lines.collect();
+    let rest = rest.join("\n");
+    let rest = rest.trim_start_matches('\n');
+    if rest.is_empty() {
+        String::new()
+    } else {
+        format!("{}\n", rest.trim_end_matches('\n'))
+    }
+}
+
+/// Parse CSV with quoted fields, which may hold commas, quotes and newlines.
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => row.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::zip::ZipWriter;
+
+    #[test]
+    fn test_read_notion() {
+        let id = |c: char| c.to_string().repeat(32);
+        let mut zip = ZipWriter::new();
+        zip.add(
+            &format!("Home {}.md", id('a')),
+            format!(
+                "# Home\n\nSee [Trip](Home%20{a}/Trip%20{b}.md), [tasks](Tasks%20{c}.csv) and \
+                 [again](https://www.notion.so/Trip-{b}).\n\n![map.png](Home%20{a}/map.png)\n",
+                a = id('a'),
+                b = id('b'),
+                c = id('c')
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+        zip.add(&format!("Home {}/Trip {}.md", id('a'), id('b')), b"# Trip\n\nPack.\n").unwrap();
+        zip.add_stored(&format!("Home {}/map.png", id('a')), b"png");
+        zip.add(
+            &format!("Tasks {}_all.csv", id('c')),
+            "\u{feff}Name,Status,Tags\nBook hotel,Done,\"travel, urgent\"\n\"Call \"\"Bob\"\"\",,\n".as_bytes(),
+        )
+        .unwrap();
+        zip.add(&format!("Tasks {}.csv", id('c')), b"Name,Status\nBook hotel,Done\n").unwrap();
+        zip.add(
+            &format!("Tasks {}/Book hotel {}.md", id('c'), id('d')),
+            b"# Book hotel\n\nStatus: Done\nTags: travel, urgent\n\nNear the station.\n",
+        )
+        .unwrap();
+        let notes = read_notion(&zip.finish()).unwrap();
+
+        let note = |title: &str| notes.iter().find(|n| n.title == title).unwrap();
+        assert_eq!(notes.len(), 4);
+        let home = note("Home");
+        assert_eq!(home.id.as_deref(), Some("aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa"));
+        assert_eq!(
+            home.content,
+            format!("See [[Trip]], tasks and [[Trip|again]].\n\n![map.png](<Home {}/map.png>)\n", id('a'))
+        );
+        assert_eq!(home.assets.len(), 1);
+        assert_eq!(note("Trip").folders, vec!["Home"]);
+        let row = note("Book hotel");
+        assert_eq!(row.content, "Near the station.\n");
+        assert_eq!(row.folders, vec!["Tasks"]);
+        assert_eq!(row.tags, vec!["travel", "urgent"]);
+        assert_eq!(row.properties, vec![("Status".to_string(), "Done".to_string())]);
+        let bare = note("Call \"Bob\"");
+        assert_eq!((bare.content.as_str(), bare.properties.len()), ("", 0));
+    }
+}
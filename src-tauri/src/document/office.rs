@@ -0,0 +1,99 @@
+use crate::document::xml::{XmlEvent, XmlReader};
+use crate::document::zip::read_zip;
+
+/// Plain text of a Word document: one line per paragraph.
+pub fn docx_text(data: &[u8]) -> Result<String, String> {
+    let xml = zip_file(data, "word/document.xml")?;
+    let mut reader = XmlReader::new(xml.as_slice());
+    let mut text = String::new();
+    let mut in_text = false;
+    while let Some(event) = reader.next_event()? {
+        match event {
+            XmlEvent::Start { name, .. } => match name.as_str() {
+                "w:t" => in_text = true,
+                "w:tab" => text.push('\t'),
+                "w:br" | "w:cr" => text.push('\n'),
+                _ => {}
+            },
+            XmlEvent::End(name) => match name.as_str() {
+                "w:t" => in_text = false,
+                "w:p" => text.push('\n'),
+                _ => {}
+            },
+            XmlEvent::Text(t) if in_text => text.push_str(&t),
+            XmlEvent::Text(_) => {}
+        }
+    }
+    Ok(text)
+}
+
+/// Plain text of an OpenDocument text file: one line per paragraph or
+/// heading.
+pub fn odt_text(data: &[u8]) -> Result<String, String> {
+    let xml = zip_file(data, "content.xml")?;
+    let mut reader = XmlReader::new(xml.as_slice());
+    let mut text = String::new();
+    // Text only counts inside paragraphs and headings, not in styles or
+    // between elements
+    let mut depth = 0;
+    while let Some(event) = reader.next_event()? {
+        match event {
+            XmlEvent::Start { name, attrs } => match name.as_str() {
+                "text:p" | "text:h" => depth += 1,
+                "text:s" => {
+                    let count = attrs
+                        .iter()
+                        .find(|(key, _)| key == "text:c")
+                        .and_then(|(_, value)| value.parse().ok())
+                        .unwrap_or(1);
+                    text.push_str(&" ".repeat(count));
+                }
+                "text:tab" => text.push('\t'),
+                "text:line-break" => text.push('\n'),
+                _ => {}
+            },
+            XmlEvent::End(name) => {
+                if name == "text:p" || name == "text:h" {
+                    depth -= 1;
+                    text.push('\n');
+                }
+            }
+            XmlEvent::Text(t) if depth > 0 => text.push_str(&t),
+            XmlEvent::Text(_) => {}
+        }
+    }
+    Ok(text)
+}
+
+fn zip_file(data: &[u8], name: &str) -> Result<Vec<u8>, String> {
+    read_zip(data)?
+        .into_iter()
+        .find(|entry| entry.name == name)
+        .map(|entry| entry.data)
+        .ok_or_else(|| format!("{} is missing", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::zip::ZipWriter;
+
+    #[test]
+    fn test_office_text() {
+        let mut docx = ZipWriter::new();
+        docx.add(
+            "word/document.xml",
+            br#"<w:document><w:body><w:p><w:r><w:t>Bears </w:t></w:r><w:r><w:t xml:space="preserve">sleep &amp; dream</w:t></w:r></w:p><w:p><w:r><w:t>In</w:t><w:tab/><w:t>dens</w:t></w:r></w:p></w:body></w:document>"#,
+        )
+        .unwrap();
+        assert_eq!(docx_text(&docx.finish()).unwrap(), "Bears sleep & dream\nIn\tdens\n");
+
+        let mut odt = ZipWriter::new();
+        odt.add(
+            "content.xml",
+            br#"<office:document-content><office:automatic-styles><style:style style:name="P1"/></office:automatic-styles><office:body><office:text><text:h text:outline-level="1">Winter</text:h><text:p>Bears<text:s text:c="2"/>sleep<text:line-break/>deeply</text:p></office:text></office:body></office:document-content>"#,
+        )
+        .unwrap();
+        assert_eq!(odt_text(&odt.finish()).unwrap(), "Winter\nBears  sleep\ndeeply\n");
+    }
+}
@@ -0,0 +1,1335 @@
+use crate::document::image::{load_image, ImageData, RasterImage};
+use crate::document::truetype::{FontMetrics, TrueTypeFont};
+use crate::markdown::sections::slugify;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Write;
+use std::path::PathBuf;
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN_X: f32 = 56.0;
+const CONTENT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN_X;
+const CONTENT_TOP: f32 = 770.0;
+const CONTENT_BOTTOM: f32 = 64.0;
+const BODY_SIZE: f32 = 11.0;
+const CODE_SIZE: f32 = 9.0;
+const LIST_INDENT: f32 = 18.0;
+const QUOTE_INDENT: f32 = 14.0;
+const TOC_LEADING: f32 = 16.0;
+
+const TEXT_COLOR: (f32, f32, f32) = (0.1, 0.1, 0.1);
+const MUTED_COLOR: (f32, f32, f32) = (0.45, 0.45, 0.45);
+const LINK_COLOR: (f32, f32, f32) = (0.0, 0.35, 0.7);
+
+/// Link URLs with this prefix point at a note in the same document, as
+/// `bruin-note:<note id>` or `bruin-note:<note id>#<anchor>`.
+pub const NOTE_LINK_SCHEME: &str = "bruin-note:";
+
+/// One note of the document. Each chapter starts on a new page.
+pub struct PdfChapter {
+    pub id: String,
+    pub title: String,
+    pub markdown: String,
+}
+
+pub struct PdfOutput {
+    pub bytes: Vec<u8>,
+    pub pages: usize,
+    /// Characters no available font has; they are drawn as `?`.
+    pub missing_characters: Vec<char>,
+}
+
+/// Lay out `chapters` on A4 pages with a running header, page numbers and a
+/// linked table of contents built from titles and headings. `image_path`
+/// maps an image URL in the markdown to a local file; other images are shown
+/// as their alt text. Text uses the standard PDF fonts, with a subset of an
+/// installed Unicode font embedded for characters outside Windows-1252.
+pub fn render_pdf(
+    title: &str,
+    chapters: &[PdfChapter],
+    image_path: &dyn Fn(&str) -> Option<PathBuf>,
+) -> Result<PdfOutput, String> {
+    let mut text = title.to_string();
+    for chapter in chapters {
+        text.push_str(&chapter.title);
+        text.push_str(&chapter.markdown);
+    }
+    render(title, chapters, image_path, Fonts::for_text(&text))
+}
+
+fn render(
+    title: &str,
+    chapters: &[PdfChapter],
+    image_path: &dyn Fn(&str) -> Option<PathBuf>,
+    fonts: Fonts,
+) -> Result<PdfOutput, String> {
+    let mut layout = Layout::new(image_path, fonts);
+    for (index, chapter) in chapters.iter().enumerate() {
+        layout.chapter(index, chapter, chapters.len() > 1);
+    }
+    layout.finish(title, chapters)
+}
+
+// --- Fonts ---
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Font {
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+    Mono,
+}
+
+const FONTS: [(Font, &str, &str); 5] = [
+    (Font::Regular, "F1", "Helvetica"),
+    (Font::Bold, "F2", "Helvetica-Bold"),
+    (Font::Italic, "F3", "Helvetica-Oblique"),
+    (Font::BoldItalic, "F4", "Helvetica-BoldOblique"),
+    (Font::Mono, "F5", "Courier"),
+];
+
+/// Advance widths of ASCII 32..=126 in thousandths of the font size.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+const HELVETICA_BOLD_WIDTHS: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722, 722, 667,
+    611, 778, 722, 278, 556, 722, 611, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 333, 278, 333, 584, 556, 333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556,
+    278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+impl Font {
+    fn resource(self) -> &'static str {
+        FONTS.iter().find(|f| f.0 == self).map(|f| f.1).unwrap_or("F1")
+    }
+
+    fn styled(bold: bool, italic: bool) -> Font {
+        match (bold, italic) {
+            (true, true) => Font::BoldItalic,
+            (true, false) => Font::Bold,
+            (false, true) => Font::Italic,
+            (false, false) => Font::Regular,
+        }
+    }
+
+    fn bold(self) -> Font {
+        match self {
+            Font::Regular => Font::Bold,
+            Font::Italic => Font::BoldItalic,
+            other => other,
+        }
+    }
+
+    /// Width of a character in the standard font, in thousandths of the
+    /// font size.
+    fn char_width(self, c: char) -> f32 {
+        let table = match self {
+            Font::Mono => return 600.0,
+            Font::Bold | Font::BoldItalic => &HELVETICA_BOLD_WIDTHS,
+            Font::Regular | Font::Italic => &HELVETICA_WIDTHS,
+        };
+        match c as u32 {
+            32..=126 => table[c as usize - 32] as f32,
+            _ => 556.0,
+        }
+    }
+}
+
+/// A character's byte in WinAnsiEncoding, the standard fonts' encoding.
+fn win_ansi(c: char) -> Option<u8> {
+    Some(match c {
+        ' '..='~' => c as u8,
+        '\u{a0}'..='\u{ff}' => c as u32 as u8,
+        '€' => 0x80,
+        '‚' => 0x82,
+        '„' => 0x84,
+        '…' => 0x85,
+        '‰' => 0x89,
+        '‹' => 0x8B,
+        'Œ' => 0x8C,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        '™' => 0x99,
+        '›' => 0x9B,
+        'œ' => 0x9C,
+        '\t' => b' ',
+        _ => return None,
+    })
+}
+
+/// A PDF string literal of WinAnsi bytes.
+fn pdf_literal(bytes: &[u8]) -> Vec<u8> {
+    let mut out = vec![b'('];
+    for &b in bytes {
+        if matches!(b, b'(' | b')' | b'\\') {
+            out.push(b'\\');
+        }
+        out.push(b);
+    }
+    out.push(b')');
+    out
+}
+
+/// TrueType fonts tried, in order, for characters outside Windows-1252. The
+/// first that has all of them is embedded, else the one that has the most.
+const UNICODE_FONTS: [&str; 9] = [
+    "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+    "/Library/Fonts/Arial Unicode.ttf",
+    "C:\\Windows\\Fonts\\arialuni.ttf",
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+    "/usr/share/fonts/truetype/noto/NotoSans-Regular.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+];
+
+/// Resource name of the embedded font.
+const UNICODE_FONT: &str = "F6";
+
+/// Measures and encodes text. Characters in Windows-1252 use the standard
+/// fonts; the rest use a subset of a TrueType font, embedded with
+/// Identity-H encoding, or are drawn as `?` when no font has them.
+struct Fonts {
+    unicode: Option<EmbeddedFont>,
+    missing: RefCell<BTreeSet<char>>,
+}
+
+struct EmbeddedFont {
+    name: String,
+    metrics: FontMetrics,
+    data: Vec<u8>,
+    /// Glyph id in the subset and advance width, by character.
+    glyphs: HashMap<char, (u16, f32)>,
+}
+
+impl Fonts {
+    /// Fonts for `text`, embedding an installed font only if it needs one.
+    fn for_text(text: &str) -> Fonts {
+        let needed: BTreeSet<char> = text.chars().filter(|&c| win_ansi(c).is_none() && !c.is_control()).collect();
+        let mut best: Option<(usize, TrueTypeFont)> = None;
+        if !needed.is_empty() {
+            for path in UNICODE_FONTS {
+                let Some(font) = std::fs::read(path)
+                    .ok()
+                    .and_then(|data| TrueTypeFont::parse(data).ok())
+                    .filter(|font| font.embeddable())
+                else {
+                    continue;
+                };
+                let covered = needed.iter().filter(|&&c| font.glyph_index(c).is_some()).count();
+                if best.as_ref().is_none_or(|(most, _)| covered > *most) {
+                    best = Some((covered, font));
+                }
+                if covered == needed.len() {
+                    break;
+                }
+            }
+        }
+        Fonts::with_font(best.map(|(_, font)| font), &needed)
+    }
+
+    /// Fonts embedding the glyphs `font` has for `needed`.
+    fn with_font(font: Option<TrueTypeFont>, needed: &BTreeSet<char>) -> Fonts {
+        let unicode = font.and_then(|font| {
+            let found: Vec<(char, u16)> = needed.iter().filter_map(|&c| Some((c, font.glyph_index(c)?))).collect();
+            if found.is_empty() {
+                return None;
+            }
+            let glyph_ids: Vec<u16> = found.iter().map(|(_, g)| *g).collect();
+            let subset = font
+                .subset(&glyph_ids)
+                .map_err(|e| log::warn!("Failed to subset font: {}", e))
+                .ok()?;
+            let glyphs = found
+                .into_iter()
+                .map(|(c, g)| (c, (subset.glyph_ids[&g], font.advance(g))))
+                .collect();
+            Some(EmbeddedFont {
+                name: font.postscript_name(),
+                metrics: font.metrics(),
+                data: subset.data,
+                glyphs,
+            })
+        });
+        Fonts {
+            unicode,
+            missing: RefCell::new(BTreeSet::new()),
+        }
+    }
+
+    fn embedded(&self, c: char) -> Option<(u16, f32)> {
+        self.unicode.as_ref()?.glyphs.get(&c).copied()
+    }
+
+    fn char_width(&self, font: Font, c: char) -> f32 {
+        if win_ansi(c).is_some() {
+            return font.char_width(c);
+        }
+        match self.embedded(c) {
+            Some((_, width)) => width,
+            None => font.char_width('?'),
+        }
+    }
+
+    fn width(&self, font: Font, size: f32, text: &str) -> f32 {
+        text.chars().map(|c| self.char_width(font, c)).sum::<f32>() * size / 1000.0
+    }
+
+    /// Operators showing `text`, switching to the embedded font and back
+    /// as needed.
+    fn show(&self, font: Font, size: f32, text: &str) -> Vec<u8> {
+        let mut ops = Vec::new();
+        let mut standard: Vec<u8> = Vec::new();
+        let mut glyphs = String::new();
+        let flush = |ops: &mut Vec<u8>, standard: &mut Vec<u8>, glyphs: &mut String| {
+            if !standard.is_empty() {
+                ops.extend(format!("/{} {} Tf ", font.resource(), size).into_bytes());
+                ops.extend(pdf_literal(standard));
+                ops.extend_from_slice(b" Tj ");
+                standard.clear();
+            }
+            if !glyphs.is_empty() {
+                ops.extend(format!("/{} {} Tf <{}> Tj ", UNICODE_FONT, size, glyphs).into_bytes());
+                glyphs.clear();
+            }
+        };
+        for c in text.chars() {
+            let byte = win_ansi(c);
+            let glyph = if byte.is_none() { self.embedded(c) } else { None };
+            if let Some((id, _)) = glyph {
+                if !standard.is_empty() {
+                    flush(&mut ops, &mut standard, &mut glyphs);
+                }
+                glyphs.push_str(&format!("{:04X}", id));
+            } else {
+                if !glyphs.is_empty() {
+                    flush(&mut ops, &mut standard, &mut glyphs);
+                }
+                standard.push(byte.unwrap_or_else(|| {
+                    if !c.is_control() {
+                        self.missing.borrow_mut().insert(c);
+                    }
+                    b'?'
+                }));
+            }
+        }
+        flush(&mut ops, &mut standard, &mut glyphs);
+        ops
+    }
+}
+
+/// A PDF text string for metadata and URIs; UTF-16 when not plain ASCII.
+fn pdf_text_string(text: &str) -> String {
+    if text.chars().all(|c| (' '..='~').contains(&c)) {
+        format!("({})", text.replace('\\', "\\\\").replace('(', "\\(").replace(')', "\\)"))
+    } else {
+        let hex: String = text.encode_utf16().map(|u| format!("{:04X}", u)).collect();
+        format!("<FEFF{}>", hex)
+    }
+}
+
+// --- Markdown to blocks ---
+
+#[derive(Debug, Clone)]
+struct Run {
+    text: String,
+    font: Font,
+    link: Option<String>,
+}
+
+#[derive(Debug)]
+enum Block {
+    Heading { level: u32, runs: Vec<Run>, anchor: String },
+    Text { runs: Vec<Run>, indent: f32, prefix: Option<String>, quote: bool },
+    Code { text: String, indent: f32 },
+    Image { url: String, alt: String, indent: f32 },
+    Rule,
+    Row { cells: Vec<Vec<Run>>, header: bool },
+}
+
+#[derive(Default)]
+struct BlockBuilder {
+    blocks: Vec<Block>,
+    runs: Vec<Run>,
+    bold: usize,
+    italic: usize,
+    links: Vec<String>,
+    /// Next number of each open list; `None` for bullet lists.
+    lists: Vec<Option<u64>>,
+    quote: usize,
+    prefix: Option<String>,
+    code: Option<String>,
+    image: Option<(String, String)>,
+    cells: Vec<Vec<Run>>,
+    anchors: Vec<String>,
+}
+
+impl BlockBuilder {
+    fn indent(&self) -> f32 {
+        self.lists.len() as f32 * LIST_INDENT + self.quote as f32 * QUOTE_INDENT
+    }
+
+    fn push(&mut self, text: &str, font: Font) {
+        if let Some((_, ref mut alt)) = self.image {
+            alt.push_str(text);
+        } else if let Some(ref mut code) = self.code {
+            code.push_str(text);
+        } else {
+            self.runs.push(Run {
+                text: text.to_string(),
+                font,
+                link: self.links.last().cloned(),
+            });
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.runs.iter().all(|r| r.text.trim().is_empty()) {
+            self.runs.clear();
+            return;
+        }
+        let block = Block::Text {
+            runs: std::mem::take(&mut self.runs),
+            indent: self.indent(),
+            prefix: self.prefix.take(),
+            quote: self.quote > 0,
+        };
+        self.blocks.push(block);
+    }
+
+    fn event(&mut self, event: Event) {
+        let font = Font::styled(self.bold > 0, self.italic > 0);
+        match event {
+            Event::Start(Tag::Heading { .. })
+            | Event::Start(Tag::Table(_))
+            | Event::End(TagEnd::Paragraph | TagEnd::Item | TagEnd::FootnoteDefinition) => self.flush(),
+            Event::End(TagEnd::Heading(level)) => {
+                let text: String = self.runs.iter().map(|r| r.text.as_str()).collect();
+                let base = slugify(&text);
+                let mut anchor = base.clone();
+                let mut n = 0;
+                while self.anchors.contains(&anchor) {
+                    n += 1;
+                    anchor = format!("{}-{}", base, n);
+                }
+                self.anchors.push(anchor.clone());
+                let runs = std::mem::take(&mut self.runs);
+                self.blocks.push(Block::Heading {
+                    level: heading_level(level),
+                    runs,
+                    anchor,
+                });
+            }
+            Event::Start(Tag::BlockQuote(_)) => {
+                self.flush();
+                self.quote += 1;
+            }
+            Event::End(TagEnd::BlockQuote) => {
+                self.flush();
+                self.quote = self.quote.saturating_sub(1);
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                let text = self.code.take().unwrap_or_default();
+                let indent = self.indent();
+                self.blocks.push(Block::Code {
+                    text: text.trim_end_matches('\n').to_string(),
+                    indent,
+                });
+            }
+            Event::Start(Tag::List(start)) => {
+                self.flush();
+                self.lists.push(start);
+            }
+            Event::End(TagEnd::List(_)) => {
+                self.flush();
+                self.lists.pop();
+            }
+            Event::Start(Tag::Item) => {
+                self.flush();
+                self.prefix = Some(match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}.", *n - 1)
+                    }
+                    _ => "•".to_string(),
+                });
+            }
+            Event::TaskListMarker(checked) => {
+                self.prefix = Some(if checked { "[x]" } else { "[ ]" }.to_string());
+            }
+            Event::Start(Tag::FootnoteDefinition(label)) => {
+                self.flush();
+                self.prefix = Some(format!("[{}]", label));
+            }
+            Event::FootnoteReference(label) => self.push(&format!("[{}]", label), font),
+            Event::Start(Tag::Strong) => self.bold += 1,
+            Event::End(TagEnd::Strong) => self.bold = self.bold.saturating_sub(1),
+            Event::Start(Tag::Emphasis) => self.italic += 1,
+            Event::End(TagEnd::Emphasis) => self.italic = self.italic.saturating_sub(1),
+            Event::Start(Tag::Link { dest_url, .. }) => self.links.push(dest_url.to_string()),
+            Event::End(TagEnd::Link) => {
+                self.links.pop();
+            }
+            Event::Start(Tag::Image { dest_url, .. }) => {
+                self.flush();
+                self.image = Some((dest_url.to_string(), String::new()));
+            }
+            Event::End(TagEnd::Image) => {
+                if let Some((url, alt)) = self.image.take() {
+                    let indent = self.indent();
+                    self.blocks.push(Block::Image { url, alt, indent });
+                }
+            }
+            Event::Start(Tag::TableHead | Tag::TableRow) => self.cells.clear(),
+            Event::End(TagEnd::TableCell) => {
+                let cell = std::mem::take(&mut self.runs);
+                self.cells.push(cell);
+            }
+            Event::End(TagEnd::TableHead) => {
+                let cells = std::mem::take(&mut self.cells);
+                self.blocks.push(Block::Row { cells, header: true });
+            }
+            Event::End(TagEnd::TableRow) => {
+                let cells = std::mem::take(&mut self.cells);
+                self.blocks.push(Block::Row { cells, header: false });
+            }
+            Event::Start(Tag::CodeBlock(_)) => {
+                self.flush();
+                self.code = Some(String::new());
+            }
+            Event::Text(text) => self.push(&text, font),
+            Event::Code(text) => self.push(&text, Font::Mono),
+            Event::SoftBreak => self.push(" ", font),
+            Event::HardBreak => self.push("\n", font),
+            Event::Rule => {
+                self.flush();
+                self.blocks.push(Block::Rule);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn heading_level(level: HeadingLevel) -> u32 {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
+
+fn markdown_blocks(markdown: &str) -> Vec<Block> {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS;
+    let mut builder = BlockBuilder::default();
+    for event in Parser::new_ext(markdown, options) {
+        builder.event(event);
+    }
+    builder.flush();
+    builder.blocks
+}
+
+// --- Layout ---
+
+enum LinkTarget {
+    Uri(String),
+    /// Page index (before the contents pages are added) and y position.
+    Position(usize, f32),
+    /// `<note id>` or `<note id>#<anchor>`, resolved once all notes are laid out.
+    Note(String),
+}
+
+struct Annotation {
+    rect: [f32; 4],
+    target: LinkTarget,
+}
+
+struct Page {
+    content: Vec<u8>,
+    annotations: Vec<Annotation>,
+    chapter: usize,
+}
+
+struct TocEntry {
+    level: u32,
+    title: String,
+    page: usize,
+    y: f32,
+}
+
+struct Segment {
+    text: String,
+    font: Font,
+    link: Option<String>,
+    width: f32,
+}
+
+struct Layout<'a> {
+    pages: Vec<Page>,
+    y: f32,
+    images: Vec<RasterImage>,
+    image_index: HashMap<String, Option<usize>>,
+    toc: Vec<TocEntry>,
+    /// Note id, or `<note id>#<anchor>`, to page and y position.
+    anchors: HashMap<String, (usize, f32)>,
+    image_path: &'a dyn Fn(&str) -> Option<PathBuf>,
+    fonts: Fonts,
+}
+
+impl<'a> Layout<'a> {
+    fn new(image_path: &'a dyn Fn(&str) -> Option<PathBuf>, fonts: Fonts) -> Self {
+        Layout {
+            pages: Vec::new(),
+            y: CONTENT_TOP,
+            images: Vec::new(),
+            image_index: HashMap::new(),
+            toc: Vec::new(),
+            anchors: HashMap::new(),
+            image_path,
+            fonts,
+        }
+    }
+
+    fn new_page(&mut self, chapter: usize) {
+        self.pages.push(Page {
+            content: Vec::new(),
+            annotations: Vec::new(),
+            chapter,
+        });
+        self.y = CONTENT_TOP;
+    }
+
+    fn page(&mut self) -> &mut Page {
+        self.pages.last_mut().expect("a page is open")
+    }
+
+    /// Start a new page unless `height` still fits on this one.
+    fn ensure(&mut self, height: f32) {
+        if self.y - height < CONTENT_BOTTOM {
+            let chapter = self.page().chapter;
+            self.new_page(chapter);
+        }
+    }
+
+    fn chapter(&mut self, index: usize, chapter: &PdfChapter, in_toc: bool) {
+        self.new_page(index);
+        self.anchors.insert(chapter.id.clone(), (self.pages.len() - 1, PAGE_HEIGHT));
+        if in_toc {
+            self.toc.push(TocEntry {
+                level: 0,
+                title: chapter.title.clone(),
+                page: self.pages.len() - 1,
+                y: PAGE_HEIGHT,
+            });
+        }
+        let title = vec![Run {
+            text: chapter.title.clone(),
+            font: Font::Bold,
+            link: None,
+        }];
+        self.write_runs(&title, MARGIN_X, CONTENT_WIDTH, 22.0, 28.0, TEXT_COLOR);
+        self.y -= 12.0;
+
+        for block in markdown_blocks(&chapter.markdown) {
+            self.block(&chapter.id, block);
+        }
+    }
+
+    fn block(&mut self, chapter_id: &str, block: Block) {
+        match block {
+            Block::Heading { level, runs, anchor } => {
+                let size = match level {
+                    1 => 18.0,
+                    2 => 15.0,
+                    3 => 13.0,
+                    _ => 11.5,
+                };
+                // Keep a heading together with the first lines after it
+                self.ensure(size * 1.4 + 3.0 * BODY_SIZE * 1.45);
+                self.y -= size * 0.5;
+                let position = (self.pages.len() - 1, self.y);
+                self.anchors.insert(format!("{}#{}", chapter_id, anchor), position);
+                if level <= 3 {
+                    self.toc.push(TocEntry {
+                        level,
+                        title: runs.iter().map(|r| r.text.as_str()).collect(),
+                        page: position.0,
+                        y: position.1,
+                    });
+                }
+                let runs: Vec<Run> = runs
+                    .into_iter()
+                    .map(|r| Run { font: r.font.bold(), ..r })
+                    .collect();
+                self.write_runs(&runs, MARGIN_X, CONTENT_WIDTH, size, size * 1.35, TEXT_COLOR);
+                self.y -= 4.0;
+            }
+            Block::Text { runs, indent, prefix, quote } => {
+                let x = MARGIN_X + indent;
+                let color = if quote { MUTED_COLOR } else { TEXT_COLOR };
+                let leading = BODY_SIZE * 1.45;
+                self.ensure(leading);
+                if let Some(prefix) = prefix {
+                    let width = self.fonts.width(Font::Regular, BODY_SIZE, &prefix);
+                    let y = self.y - leading + (leading - BODY_SIZE) / 2.0 + BODY_SIZE * 0.2;
+                    self.text(x - width - 5.0, y, Font::Regular, BODY_SIZE, color, &prefix);
+                }
+                let first_page = self.pages.len();
+                let start_y = self.y;
+                self.write_runs(&runs, x, CONTENT_WIDTH - indent, BODY_SIZE, leading, color);
+                if quote && self.pages.len() == first_page {
+                    let bar_x = x - 8.0;
+                    let bottom = self.y - BODY_SIZE * 0.3;
+                    self.line(bar_x, start_y, bar_x, bottom, 1.5, MUTED_COLOR);
+                }
+                self.y -= if indent > 0.0 && !quote { 2.0 } else { 6.0 };
+            }
+            Block::Code { text, indent } => {
+                let x = MARGIN_X + indent;
+                let width = CONTENT_WIDTH - indent;
+                let per_line = ((width - 12.0) / (CODE_SIZE * 0.6)).floor().max(1.0) as usize;
+                let leading = CODE_SIZE * 1.45;
+                self.y -= 2.0;
+                for line in text.split('\n') {
+                    let chars: Vec<char> = line.chars().collect();
+                    let pieces: Vec<String> = if chars.is_empty() {
+                        vec![String::new()]
+                    } else {
+                        chars.chunks(per_line).map(|c| c.iter().collect()).collect()
+                    };
+                    for piece in pieces {
+                        self.ensure(leading);
+                        self.rect(x, self.y - leading, width, leading, (0.95, 0.95, 0.95));
+                        self.y -= leading;
+                        let y = self.y + CODE_SIZE * 0.35;
+                        self.text(x + 6.0, y, Font::Mono, CODE_SIZE, TEXT_COLOR, &piece);
+                    }
+                }
+                self.y -= 8.0;
+            }
+            Block::Image { url, alt, indent } => self.image(&url, &alt, indent),
+            Block::Rule => {
+                self.ensure(14.0);
+                self.y -= 7.0;
+                self.line(MARGIN_X, self.y, PAGE_WIDTH - MARGIN_X, self.y, 0.5, MUTED_COLOR);
+                self.y -= 7.0;
+            }
+            Block::Row { cells, header } => {
+                let mut runs = Vec::new();
+                for (i, cell) in cells.into_iter().enumerate() {
+                    if i > 0 {
+                        runs.push(Run {
+                            text: "  |  ".to_string(),
+                            font: Font::Regular,
+                            link: None,
+                        });
+                    }
+                    runs.extend(cell.into_iter().map(|r| Run {
+                        font: if header { r.font.bold() } else { r.font },
+                        ..r
+                    }));
+                }
+                let leading = BODY_SIZE * 1.45;
+                self.write_runs(&runs, MARGIN_X, CONTENT_WIDTH, BODY_SIZE, leading, TEXT_COLOR);
+                if header {
+                    let y = self.y - 3.0;
+                    self.line(MARGIN_X, y, PAGE_WIDTH - MARGIN_X, y, 0.5, MUTED_COLOR);
+                }
+                self.y -= 3.0;
+            }
+        }
+    }
+
+    fn image(&mut self, url: &str, alt: &str, indent: f32) {
+        let index = match self.image_index.get(url) {
+            Some(index) => *index,
+            None => {
+                let loaded = (self.image_path)(url)
+                    .and_then(|path| std::fs::read(path).ok())
+                    .and_then(|bytes| load_image(&bytes).ok());
+                let index = loaded.map(|image| {
+                    self.images.push(image);
+                    self.images.len() - 1
+                });
+                self.image_index.insert(url.to_string(), index);
+                index
+            }
+        };
+
+        let Some(index) = index else {
+            let text = if alt.is_empty() { "[image]".to_string() } else { format!("[image: {}]", alt) };
+            let runs = vec![Run { text, font: Font::Italic, link: None }];
+            let leading = BODY_SIZE * 1.45;
+            self.write_runs(&runs, MARGIN_X + indent, CONTENT_WIDTH - indent, BODY_SIZE, leading, MUTED_COLOR);
+            self.y -= 6.0;
+            return;
+        };
+
+        let image = &self.images[index];
+        // Images are placed at 96 dpi, shrunk to fit the page
+        let mut width = (image.width as f32 * 0.75).min(CONTENT_WIDTH - indent);
+        let mut height = width * image.height as f32 / image.width.max(1) as f32;
+        let max_height = CONTENT_TOP - CONTENT_BOTTOM;
+        if height > max_height {
+            width *= max_height / height;
+            height = max_height;
+        }
+        self.ensure(height + 6.0);
+        self.y -= height + 3.0;
+        let ops = format!(
+            "q {:.2} 0 0 {:.2} {:.2} {:.2} cm /Im{} Do Q\n",
+            width,
+            height,
+            MARGIN_X + indent,
+            self.y,
+            index
+        );
+        self.page().content.extend_from_slice(ops.as_bytes());
+        self.y -= 9.0;
+    }
+
+    /// Write runs as wrapped lines, starting new pages as needed.
+    fn write_runs(&mut self, runs: &[Run], x: f32, width: f32, size: f32, leading: f32, color: (f32, f32, f32)) {
+        for line in wrap(&self.fonts, runs, size, width) {
+            self.ensure(leading);
+            self.y -= leading;
+            let baseline = self.y + (leading - size) / 2.0 + size * 0.2;
+            let mut cursor = x;
+            for segment in line {
+                let link_color = segment.link.as_ref().map(|_| LINK_COLOR).unwrap_or(color);
+                self.text(cursor, baseline, segment.font, size, link_color, &segment.text);
+                if let Some(link) = segment.link {
+                    let target = match link.strip_prefix(NOTE_LINK_SCHEME) {
+                        Some(note) => LinkTarget::Note(note.to_string()),
+                        None => LinkTarget::Uri(link),
+                    };
+                    self.page().annotations.push(Annotation {
+                        rect: [cursor, baseline - size * 0.25, cursor + segment.width, baseline + size * 0.85],
+                        target,
+                    });
+                }
+                cursor += segment.width;
+            }
+        }
+    }
+
+    fn text(&mut self, x: f32, y: f32, font: Font, size: f32, color: (f32, f32, f32), text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let ops = text_ops(&self.fonts, x, y, font, size, color, text);
+        self.page().content.extend(ops);
+    }
+
+    fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, width: f32, color: (f32, f32, f32)) {
+        let ops = format!(
+            "{:.3} {:.3} {:.3} RG {} w {:.2} {:.2} m {:.2} {:.2} l S\n",
+            color.0, color.1, color.2, width, x1, y1, x2, y2
+        );
+        self.page().content.extend_from_slice(ops.as_bytes());
+    }
+
+    fn rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: (f32, f32, f32)) {
+        let ops = format!(
+            "{:.3} {:.3} {:.3} rg {:.2} {:.2} {:.2} {:.2} re f\n",
+            color.0, color.1, color.2, x, y, width, height
+        );
+        self.page().content.extend_from_slice(ops.as_bytes());
+    }
+
+    /// Add the contents pages, headers and footers, and write the file.
+    fn finish(mut self, title: &str, chapters: &[PdfChapter]) -> Result<PdfOutput, String> {
+        if self.pages.is_empty() {
+            self.new_page(0);
+        }
+        let body_pages = std::mem::take(&mut self.pages);
+
+        // Contents pages come first, so every page index shifts by their count
+        let per_page = ((CONTENT_TOP - 40.0 - CONTENT_BOTTOM) / TOC_LEADING).floor() as usize;
+        let toc = std::mem::take(&mut self.toc);
+        let toc_pages = if toc.len() > 1 { toc.len().div_ceil(per_page) } else { 0 };
+        for (i, entries) in toc.chunks(per_page).take(toc_pages).enumerate() {
+            self.new_page(usize::MAX);
+            if i == 0 {
+                let heading = vec![Run { text: "Contents".to_string(), font: Font::Bold, link: None }];
+                self.write_runs(&heading, MARGIN_X, CONTENT_WIDTH, 18.0, 24.0, TEXT_COLOR);
+                self.y -= 12.0;
+            }
+            for entry in entries {
+                self.y -= TOC_LEADING;
+                let indent = if entry.level == 0 {
+                    0.0
+                } else {
+                    (entry.level - 1) as f32 * 14.0 + if chapters.len() > 1 { 14.0 } else { 0.0 }
+                };
+                let font = if entry.level <= 1 { Font::Bold } else { Font::Regular };
+                let number = (entry.page + toc_pages + 1).to_string();
+                let number_width = self.fonts.width(Font::Regular, BODY_SIZE, &number);
+                let max_width = CONTENT_WIDTH - indent - number_width - 12.0;
+                let text = truncate(&self.fonts, &entry.title, font, BODY_SIZE, max_width);
+                let y = self.y;
+                self.text(MARGIN_X + indent, y, font, BODY_SIZE, TEXT_COLOR, &text);
+                self.text(PAGE_WIDTH - MARGIN_X - number_width, y, Font::Regular, BODY_SIZE, TEXT_COLOR, &number);
+                self.page().annotations.push(Annotation {
+                    rect: [MARGIN_X, y - 4.0, PAGE_WIDTH - MARGIN_X, y + BODY_SIZE],
+                    target: LinkTarget::Position(entry.page, entry.y),
+                });
+            }
+        }
+        let mut pages = std::mem::take(&mut self.pages);
+        pages.extend(body_pages);
+
+        for (i, page) in pages.iter_mut().enumerate() {
+            let mut ops = Vec::new();
+            let header_y = PAGE_HEIGHT - 40.0;
+            let chapter_title = chapters.get(page.chapter).map(|c| c.title.as_str()).unwrap_or("");
+            let fonts = &self.fonts;
+            let header = truncate(fonts, title, Font::Regular, 8.5, CONTENT_WIDTH / 2.0 - 6.0);
+            ops.extend(text_ops(fonts, MARGIN_X, header_y, Font::Regular, 8.5, MUTED_COLOR, &header));
+            if !chapter_title.is_empty() && chapter_title != title {
+                let right = truncate(fonts, chapter_title, Font::Regular, 8.5, CONTENT_WIDTH / 2.0 - 6.0);
+                let width = fonts.width(Font::Regular, 8.5, &right);
+                ops.extend(text_ops(fonts, PAGE_WIDTH - MARGIN_X - width, header_y, Font::Regular, 8.5, MUTED_COLOR, &right));
+            }
+            ops.extend_from_slice(
+                format!(
+                    "0.8 0.8 0.8 RG 0.5 w {:.2} {:.2} m {:.2} {:.2} l S\n",
+                    MARGIN_X,
+                    header_y - 6.0,
+                    PAGE_WIDTH - MARGIN_X,
+                    header_y - 6.0
+                )
+                .as_bytes(),
+            );
+            let number = (i + 1).to_string();
+            let width = fonts.width(Font::Regular, 9.0, &number);
+            ops.extend(text_ops(fonts, (PAGE_WIDTH - width) / 2.0, 32.0, Font::Regular, 9.0, MUTED_COLOR, &number));
+            page.content.extend(ops);
+        }
+
+        let page_count = pages.len();
+        let bytes = write_pdf(title, &pages, &self.images, &self.anchors, toc_pages, &self.fonts)?;
+        Ok(PdfOutput {
+            bytes,
+            pages: page_count,
+            missing_characters: self.fonts.missing.take().into_iter().collect(),
+        })
+    }
+}
+
+fn text_ops(fonts: &Fonts, x: f32, y: f32, font: Font, size: f32, color: (f32, f32, f32), text: &str) -> Vec<u8> {
+    let mut ops = format!(
+        "BT {:.3} {:.3} {:.3} rg {:.2} {:.2} Td ",
+        color.0, color.1, color.2, x, y
+    )
+    .into_bytes();
+    ops.extend(fonts.show(font, size, text));
+    ops.extend_from_slice(b"ET\n");
+    ops
+}
+
+/// Shorten `text` with an ellipsis to fit `width`.
+fn truncate(fonts: &Fonts, text: &str, font: Font, size: f32, width: f32) -> String {
+    if fonts.width(font, size, text) <= width {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    for c in text.chars() {
+        if fonts.width(font, size, &out) + fonts.char_width(font, c) * size / 1000.0 + fonts.width(font, size, "…")
+            > width
+        {
+            break;
+        }
+        out.push(c);
+    }
+    out.push('…');
+    out
+}
+
+/// Break runs into lines no wider than `width`, at spaces where possible.
+fn wrap(fonts: &Fonts, runs: &[Run], size: f32, width: f32) -> Vec<Vec<Segment>> {
+    let mut lines: Vec<Vec<Segment>> = vec![Vec::new()];
+    let mut line_width = 0.0;
+
+    for run in runs {
+        let mut rest = run.text.as_str();
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('\n') {
+                lines.push(Vec::new());
+                line_width = 0.0;
+                rest = after;
+                continue;
+            }
+            // Next word with its trailing spaces
+            let word_end = rest.find([' ', '\n']).unwrap_or(rest.len());
+            let space_end = rest[word_end..]
+                .find(|c: char| c != ' ')
+                .map(|i| word_end + i)
+                .unwrap_or(rest.len());
+            let mut piece = &rest[..space_end];
+            rest = &rest[space_end..];
+
+            let word_width = fonts.width(run.font, size, piece.trim_end());
+            if line_width > 0.0 && line_width + word_width > width {
+                lines.push(Vec::new());
+                line_width = 0.0;
+            }
+            if line_width == 0.0 {
+                piece = piece.trim_start();
+                if piece.is_empty() {
+                    continue;
+                }
+            }
+
+            // A word longer than the line is split between characters
+            let mut piece = piece.to_string();
+            while fonts.width(run.font, size, piece.trim_end()) > width - line_width && piece.chars().count() > 1 {
+                let mut head = String::new();
+                for c in piece.chars() {
+                    if fonts.width(run.font, size, &head) + fonts.char_width(run.font, c) * size / 1000.0
+                        > width - line_width
+                        && !head.is_empty()
+                    {
+                        break;
+                    }
+                    head.push(c);
+                }
+                let tail = piece[head.len()..].to_string();
+                push_segment(fonts, lines.last_mut().unwrap(), run, head, size);
+                lines.push(Vec::new());
+                line_width = 0.0;
+                piece = tail;
+            }
+            line_width += fonts.width(run.font, size, &piece);
+            push_segment(fonts, lines.last_mut().unwrap(), run, piece, size);
+        }
+    }
+    lines.retain(|l| !l.is_empty());
+    lines
+}
+
+fn push_segment(fonts: &Fonts, line: &mut Vec<Segment>, run: &Run, text: String, size: f32) {
+    if let Some(last) = line.last_mut() {
+        if last.font == run.font && last.link == run.link {
+            last.text.push_str(&text);
+            last.width = fonts.width(run.font, size, &last.text);
+            return;
+        }
+    }
+    line.push(Segment {
+        width: fonts.width(run.font, size, &text),
+        text,
+        font: run.font,
+        link: run.link.clone(),
+    });
+}
+
+// --- File structure ---
+
+fn compress(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).map_err(|e| e.to_string())?;
+    encoder.finish().map_err(|e| e.to_string())
+}
+
+fn stream(dict: &str, data: &[u8]) -> Vec<u8> {
+    let mut out = format!("<< {} /Length {} >>\nstream\n", dict, data.len()).into_bytes();
+    out.extend_from_slice(data);
+    out.extend_from_slice(b"\nendstream");
+    out
+}
+
+fn write_pdf(
+    title: &str,
+    pages: &[Page],
+    images: &[RasterImage],
+    anchors: &HashMap<String, (usize, f32)>,
+    toc_pages: usize,
+    fonts: &Fonts,
+) -> Result<Vec<u8>, String> {
+    // Object numbers: 1 catalog, 2 page tree, 3 info, 4 resources, then
+    // fonts, images (with soft masks), and a page and content stream per page
+    let mut objects: Vec<Vec<u8>> = vec![Vec::new(); 4];
+    let mut font_refs = Vec::new();
+    for (_, name, base) in FONTS {
+        objects.push(format!("<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>", base).into_bytes());
+        font_refs.push(format!("/{} {} 0 R", name, objects.len()));
+    }
+    if let Some(font) = &fonts.unicode {
+        font_refs.push(format!("/{} {} 0 R", UNICODE_FONT, write_embedded_font(&mut objects, font)?));
+    }
+
+    let mut image_ids = Vec::new();
+    for image in images {
+        let color_space = match image.components {
+            1 => "/DeviceGray",
+            4 => "/DeviceCMYK",
+            _ => "/DeviceRGB",
+        };
+        let base = format!(
+            "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace {} /BitsPerComponent 8",
+            image.width, image.height, color_space
+        );
+        match &image.data {
+            ImageData::Jpeg(data) => {
+                objects.push(stream(&format!("{} /Filter /DCTDecode", base), data));
+            }
+            ImageData::Flate { samples, alpha } => {
+                let mut dict = format!("{} /Filter /FlateDecode", base);
+                if let Some(alpha) = alpha {
+                    objects.push(stream(
+                        &format!(
+                            "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceGray /BitsPerComponent 8 /Filter /FlateDecode",
+                            image.width, image.height
+                        ),
+                        alpha,
+                    ));
+                    dict.push_str(&format!(" /SMask {} 0 R", objects.len()));
+                }
+                objects.push(stream(&dict, samples));
+            }
+        }
+        image_ids.push(objects.len());
+    }
+
+    let page_start = objects.len() + 1;
+    let page_id = |index: usize| page_start + index * 2;
+    let destination = |page: usize, y: f32| format!("[{} 0 R /XYZ 0 {:.2} null]", page_id(page + toc_pages), y);
+
+    for (i, page) in pages.iter().enumerate() {
+        let mut annotations = Vec::new();
+        for annotation in &page.annotations {
+            let action = match &annotation.target {
+                LinkTarget::Uri(uri) => format!("/A << /S /URI /URI {} >>", pdf_text_string(uri)),
+                LinkTarget::Position(page, y) => format!("/Dest {}", destination(*page, *y)),
+                LinkTarget::Note(key) => match anchors.get(key).or_else(|| anchors.get(key.split('#').next().unwrap_or(""))) {
+                    Some((page, y)) => format!("/Dest {}", destination(*page, *y)),
+                    None => continue,
+                },
+            };
+            let [x1, y1, x2, y2] = annotation.rect;
+            annotations.push(format!(
+                "<< /Type /Annot /Subtype /Link /Rect [{:.2} {:.2} {:.2} {:.2}] /Border [0 0 0] {} >>",
+                x1, y1, x2, y2, action
+            ));
+        }
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources 4 0 R /Contents {} 0 R /Annots [{}] >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                page_id(i) + 1,
+                annotations.join(" ")
+            )
+            .into_bytes(),
+        );
+        objects.push(stream("/Filter /FlateDecode", &compress(&page.content)?));
+    }
+
+    objects[0] = b"<< /Type /Catalog /Pages 2 0 R >>".to_vec();
+    let kids: Vec<String> = (0..pages.len()).map(|i| format!("{} 0 R", page_id(i))).collect();
+    objects[1] = format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len()).into_bytes();
+    objects[2] = format!(
+        "<< /Title {} /Producer (Bruin) /CreationDate (D:{}) >>",
+        pdf_text_string(title),
+        chrono::Utc::now().format("%Y%m%d%H%M%SZ")
+    )
+    .into_bytes();
+    let xobjects: Vec<String> = image_ids.iter().enumerate().map(|(i, id)| format!("/Im{} {} 0 R", i, id)).collect();
+    objects[3] = format!(
+        "<< /Font << {} >> /XObject << {} >> >>",
+        font_refs.join(" "),
+        xobjects.join(" ")
+    )
+    .into_bytes();
+
+    let mut out = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+        out.extend_from_slice(object);
+        out.extend_from_slice(b"\nendobj\n");
+    }
+    let xref = out.len();
+    out.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        out.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    out.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R /Info 3 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .as_bytes(),
+    );
+    Ok(out)
+}
+
+/// Add the objects of an embedded TrueType subset: the font file, its
+/// descriptor, the CID font with glyph widths, the ToUnicode map that keeps
+/// the text searchable, and the Type 0 font. Returns the Type 0 font's
+/// object number.
+fn write_embedded_font(objects: &mut Vec<Vec<u8>>, font: &EmbeddedFont) -> Result<usize, String> {
+    // A subset's name starts with six capital letters and a plus sign
+    let base = format!("BRUINA+{}", font.name);
+    let glyphs: BTreeMap<u16, (char, f32)> = font.glyphs.iter().map(|(&c, &(id, width))| (id, (c, width))).collect();
+
+    objects.push(stream(
+        &format!("/Length1 {} /Filter /FlateDecode", font.data.len()),
+        &compress(&font.data)?,
+    ));
+    let file = objects.len();
+    let m = &font.metrics;
+    objects.push(
+        format!(
+            "<< /Type /FontDescriptor /FontName /{} /Flags 4 /FontBBox [{:.0} {:.0} {:.0} {:.0}] /ItalicAngle 0 \
+             /Ascent {:.0} /Descent {:.0} /CapHeight {:.0} /StemV 80 /FontFile2 {} 0 R >>",
+            base, m.bbox[0], m.bbox[1], m.bbox[2], m.bbox[3], m.ascent, m.descent, m.cap_height, file
+        )
+        .into_bytes(),
+    );
+    let descriptor = objects.len();
+    let widths: Vec<String> = glyphs.iter().map(|(id, (_, width))| format!("{} [{:.0}]", id, width)).collect();
+    objects.push(
+        format!(
+            "<< /Type /Font /Subtype /CIDFontType2 /BaseFont /{} \
+             /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> \
+             /FontDescriptor {} 0 R /W [{}] /CIDToGIDMap /Identity >>",
+            base,
+            descriptor,
+            widths.join(" ")
+        )
+        .into_bytes(),
+    );
+    let cid_font = objects.len();
+
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+         /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+         1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+    let entries: Vec<(u16, char)> = glyphs.iter().map(|(&id, &(c, _))| (id, c)).collect();
+    for chunk in entries.chunks(100) {
+        cmap.push_str(&format!("{} beginbfchar\n", chunk.len()));
+        for (id, c) in chunk {
+            let unicode: String = c.encode_utf16(&mut [0; 2]).iter().map(|u| format!("{:04X}", u)).collect();
+            cmap.push_str(&format!("<{:04X}> <{}>\n", id, unicode));
+        }
+        cmap.push_str("endbfchar\n");
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+    objects.push(stream("/Filter /FlateDecode", &compress(cmap.as_bytes())?));
+    let to_unicode = objects.len();
+
+    objects.push(
+        format!(
+            "<< /Type /Font /Subtype /Type0 /BaseFont /{} /Encoding /Identity-H /DescendantFonts [{} 0 R] /ToUnicode {} 0 R >>",
+            base, cid_font, to_unicode
+        )
+        .into_bytes(),
+    );
+    Ok(objects.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap() {
+        let runs = vec![
+            Run { text: "Hello brave ".to_string(), font: Font::Regular, link: None },
+            Run { text: "new world".to_string(), font: Font::Bold, link: None },
+        ];
+        let fonts = Fonts::with_font(None, &BTreeSet::new());
+        let width = fonts.width(Font::Regular, 10.0, "Hello brave new");
+        let lines = wrap(&fonts, &runs, 10.0, width);
+        let texts: Vec<Vec<&str>> = lines
+            .iter()
+            .map(|l| l.iter().map(|s| s.text.as_str()).collect())
+            .collect();
+        assert_eq!(texts, vec![vec!["Hello brave "], vec!["new world"]]);
+    }
+
+    #[test]
+    fn test_render_pdf() {
+        let chapters = vec![
+            PdfChapter {
+                id: "a".to_string(),
+                title: "First".to_string(),
+                markdown: "# Intro\nSee [the other](bruin-note:b#details).\n\n- one\n- two\n\n```\ncode\n```\n".to_string(),
+            },
+            PdfChapter {
+                id: "b".to_string(),
+                title: "Second".to_string(),
+                markdown: "## Details\nText ![missing](nowhere.png)".to_string(),
+            },
+        ];
+        let output = render_pdf("Book", &chapters, &|_| None).unwrap();
+        let text = String::from_utf8_lossy(&output.bytes);
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.ends_with("%%EOF\n"));
+        // Contents page plus one page per chapter
+        assert_eq!(output.pages, 3);
+        assert_eq!(text.matches("/Subtype /Link").count(), 4 + 1);
+        assert!(!text.contains("/Type0"));
+        assert!(output.missing_characters.is_empty());
+    }
+
+    #[test]
+    fn test_render_pdf_embeds_unicode_font() {
+        let chapters = vec![PdfChapter {
+            id: "a".to_string(),
+            title: "Ω".to_string(),
+            markdown: "Café Ω あ 日本".to_string(),
+        }];
+        let needed: BTreeSet<char> = "Ωあ日本".chars().collect();
+        let font = TrueTypeFont::parse(crate::document::truetype::test_font()).unwrap();
+        let output = render("Book", &chapters, &|_| None, Fonts::with_font(Some(font), &needed)).unwrap();
+        let text = String::from_utf8_lossy(&output.bytes);
+        assert!(text.contains("/Subtype /Type0 /BaseFont /BRUINA+Font /Encoding /Identity-H"));
+        assert!(text.contains("/CIDToGIDMap /Identity"));
+        assert!(text.contains("/W [1 [600] 2 [1000]]"));
+        // Only the characters the font lacks are reported
+        assert_eq!(output.missing_characters, vec!['日', '本']);
+
+        let output = render("Book", &chapters, &|_| None, Fonts::with_font(None, &needed)).unwrap();
+        assert_eq!(output.missing_characters, vec!['Ω', 'あ', '日', '本']);
+    }
+
+    #[test]
+    fn test_show_switches_fonts() {
+        let needed: BTreeSet<char> = ['Ω'].into();
+        let font = TrueTypeFont::parse(crate::document::truetype::test_font()).unwrap();
+        let fonts = Fonts::with_font(Some(font), &needed);
+        let ops = String::from_utf8(fonts.show(Font::Bold, 11.0, "a(Ω)")).unwrap();
+        assert_eq!(ops, "/F2 11 Tf (a\\() Tj /F6 11 Tf <0001> Tj /F2 11 Tf (\\)) Tj ");
+        assert_eq!(fonts.width(Font::Bold, 10.0, "Ω"), 6.0);
+    }
+}
@@ -0,0 +1,800 @@
+use flate2::read::ZlibDecoder;
+use regex::bytes::Regex;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+
+/// A PDF value. Content streams and CMaps also yield operators, as `Op`.
+#[derive(Debug, Clone, PartialEq)]
+enum Obj {
+    Null,
+    Bool(bool),
+    Num(f64),
+    Str(Vec<u8>),
+    Name(String),
+    Array(Vec<Obj>),
+    Dict(HashMap<String, Obj>),
+    Ref(u32),
+    Op(String),
+}
+
+impl Obj {
+    fn get(&self, key: &str) -> Option<&Obj> {
+        match self {
+            Obj::Dict(dict) => dict.get(key),
+            _ => None,
+        }
+    }
+
+    fn num(&self) -> Option<f64> {
+        match self {
+            Obj::Num(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> Option<&str> {
+        match self {
+            Obj::Name(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\r' | b'\x0c' | b'\0')
+}
+
+fn is_delimiter(b: u8) -> bool {
+    matches!(b, b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%')
+}
+
+/// Reads PDF values, as in objects, content streams and CMaps.
+struct Lexer<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(&b) = self.data.get(self.pos) {
+            if is_whitespace(b) {
+                self.pos += 1;
+            } else if b == b'%' {
+                while self.data.get(self.pos).is_some_and(|&b| b != b'\n' && b != b'\r') {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// The next value, with `n g R` read as a reference.
+    fn value(&mut self) -> Option<Obj> {
+        let token = self.token()?;
+        if let Obj::Num(n) = token {
+            if n >= 0.0 && n.fract() == 0.0 {
+                let start = self.pos;
+                if let (Some(Obj::Num(g)), Some(Obj::Op(op))) = (self.token(), self.token()) {
+                    if op == "R" && g.fract() == 0.0 {
+                        return Some(Obj::Ref(n as u32));
+                    }
+                }
+                self.pos = start;
+            }
+        }
+        Some(token)
+    }
+
+    fn token(&mut self) -> Option<Obj> {
+        self.skip_whitespace();
+        let b = *self.data.get(self.pos)?;
+        match b {
+            b'(' => Some(Obj::Str(self.literal_string())),
+            b'<' if self.data.get(self.pos + 1) == Some(&b'<') => {
+                self.pos += 2;
+                let mut dict = HashMap::new();
+                loop {
+                    match self.token()? {
+                        Obj::Name(key) => {
+                            let value = self.value()?;
+                            dict.insert(key, value);
+                        }
+                        Obj::Op(op) if op == ">>" => break,
+                        _ => {}
+                    }
+                }
+                Some(Obj::Dict(dict))
+            }
+            b'<' => {
+                self.pos += 1;
+                let end = self.data[self.pos..].iter().position(|&b| b == b'>').map_or(self.data.len(), |i| self.pos + i);
+                let digits: Vec<u8> = self.data[self.pos..end].iter().copied().filter(u8::is_ascii_hexdigit).collect();
+                self.pos = end + 1;
+                Some(Obj::Str(hex_bytes(&digits)))
+            }
+            b'>' if self.data.get(self.pos + 1) == Some(&b'>') => {
+                self.pos += 2;
+                Some(Obj::Op(">>".to_string()))
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                loop {
+                    match self.value()? {
+                        Obj::Op(op) if op == "]" => break,
+                        item => items.push(item),
+                    }
+                }
+                Some(Obj::Array(items))
+            }
+            b'/' => {
+                self.pos += 1;
+                let start = self.pos;
+                while self.data.get(self.pos).is_some_and(|&b| !is_whitespace(b) && !is_delimiter(b)) {
+                    self.pos += 1;
+                }
+                Some(Obj::Name(name_string(&self.data[start..self.pos])))
+            }
+            b']' | b'>' | b')' | b'{' | b'}' => {
+                self.pos += 1;
+                Some(Obj::Op((b as char).to_string()))
+            }
+            _ => {
+                let start = self.pos;
+                while self.data.get(self.pos).is_some_and(|&b| !is_whitespace(b) && !is_delimiter(b)) {
+                    self.pos += 1;
+                }
+                let word = String::from_utf8_lossy(&self.data[start..self.pos]).to_string();
+                Some(match word.as_str() {
+                    "true" => Obj::Bool(true),
+                    "false" => Obj::Bool(false),
+                    "null" => Obj::Null,
+                    _ => match word.parse::<f64>() {
+                        Ok(n) if b.is_ascii_digit() || matches!(b, b'-' | b'+' | b'.') => Obj::Num(n),
+                        _ => Obj::Op(word),
+                    },
+                })
+            }
+        }
+    }
+
+    /// A `(...)` string, which may nest balanced parentheses.
+    fn literal_string(&mut self) -> Vec<u8> {
+        self.pos += 1;
+        let mut out = Vec::new();
+        let mut depth = 0;
+        while let Some(&b) = self.data.get(self.pos) {
+            self.pos += 1;
+            match b {
+                b'(' => {
+                    depth += 1;
+                    out.push(b);
+                }
+                b')' if depth == 0 => break,
+                b')' => {
+                    depth -= 1;
+                    out.push(b);
+                }
+                b'\\' => {
+                    let Some(&escaped) = self.data.get(self.pos) else {
+                        break;
+                    };
+                    self.pos += 1;
+                    match escaped {
+                        b'n' => out.push(b'\n'),
+                        b'r' => out.push(b'\r'),
+                        b't' => out.push(b'\t'),
+                        b'b' => out.push(8),
+                        b'f' => out.push(12),
+                        b'0'..=b'7' => {
+                            let mut code = (escaped - b'0') as u32;
+                            for _ in 0..2 {
+                                match self.data.get(self.pos) {
+                                    Some(&d @ b'0'..=b'7') => {
+                                        code = code * 8 + (d - b'0') as u32;
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            out.push(code as u8);
+                        }
+                        // A backslash at the end of a line continues it
+                        b'\r' => {
+                            if self.data.get(self.pos) == Some(&b'\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        b'\n' => {}
+                        other => out.push(other),
+                    }
+                }
+                _ => out.push(b),
+            }
+        }
+        out
+    }
+}
+
+fn hex_bytes(digits: &[u8]) -> Vec<u8> {
+    let value = |d: u8| (d as char).to_digit(16).unwrap_or(0) as u8;
+    digits
+        .chunks(2)
+        .map(|pair| value(pair[0]) << 4 | pair.get(1).map_or(0, |&d| value(d)))
+        .collect()
+}
+
+/// A name with its `#xx` escapes resolved.
+fn name_string(raw: &[u8]) -> String {
+    let mut out = Vec::with_capacity(raw.len());
+    let mut i = 0;
+    while i < raw.len() {
+        if raw[i] == b'#' && i + 2 < raw.len() + 1 && raw.get(i + 1..i + 3).is_some_and(|h| h.iter().all(u8::is_ascii_hexdigit)) {
+            out.push(hex_bytes(&raw[i + 1..i + 3])[0]);
+            i += 3;
+        } else {
+            out.push(raw[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+struct PdfObject {
+    value: Obj,
+    /// Raw stream data, still encoded.
+    stream: Option<Vec<u8>>,
+}
+
+/// The objects of a PDF file, found by scanning for `n g obj` rather than
+/// through the cross-reference table, so damaged files still mostly read.
+struct Document {
+    objects: HashMap<u32, PdfObject>,
+    trailers: Vec<Obj>,
+}
+
+impl Document {
+    fn parse(data: &[u8]) -> Document {
+        let mut objects = HashMap::new();
+        let mut trailers = Vec::new();
+        let header = Regex::new(r"(?-u)(\d+)\s+\d+\s+obj\b").unwrap();
+        let mut resume = 0;
+        for cap in header.captures_iter(data) {
+            let whole = cap.get(0).unwrap();
+            // Skip matches inside the previous object's stream
+            if whole.start() < resume {
+                continue;
+            }
+            let Some(number) = std::str::from_utf8(&cap[1]).ok().and_then(|n| n.parse::<u32>().ok()) else {
+                continue;
+            };
+            let mut lexer = Lexer::new(data, whole.end());
+            let Some(value) = lexer.value() else {
+                continue;
+            };
+            lexer.skip_whitespace();
+            let mut stream = None;
+            if data.get(lexer.pos..).is_some_and(|rest| rest.starts_with(b"stream")) {
+                let mut start = lexer.pos + 6;
+                if data.get(start) == Some(&b'\r') {
+                    start += 1;
+                }
+                if data.get(start) == Some(&b'\n') {
+                    start += 1;
+                }
+                let declared = value
+                    .get("Length")
+                    .and_then(Obj::num)
+                    .filter(|&n| n >= 0.0)
+                    .and_then(|n| start.checked_add(n as usize));
+                let end = declared
+                    .filter(|&end| {
+                        let mut after = Lexer::new(data, end);
+                        after.skip_whitespace();
+                        data.get(after.pos..).is_some_and(|rest| rest.starts_with(b"endstream"))
+                    })
+                    .or_else(|| find(data, b"endstream", start).map(|end| trim_eol(data, start, end)))
+                    .unwrap_or(data.len());
+                stream = Some(data.get(start..end).unwrap_or_default().to_vec());
+                lexer.pos = end;
+            }
+            resume = lexer.pos;
+            if value.get("Type").and_then(Obj::name) == Some("XRef") {
+                trailers.push(value.clone());
+            }
+            objects.insert(number, PdfObject { value, stream });
+        }
+
+        let mut from = 0;
+        while let Some(at) = find(data, b"trailer", from) {
+            let mut lexer = Lexer::new(data, at + 7);
+            if let Some(trailer @ Obj::Dict(_)) = lexer.value() {
+                trailers.push(trailer);
+            }
+            from = at + 7;
+        }
+
+        let mut document = Document { objects, trailers };
+        document.unpack_object_streams();
+        document
+    }
+
+    /// Add the objects packed into object streams.
+    fn unpack_object_streams(&mut self) {
+        let packed: Vec<u32> = self
+            .objects
+            .iter()
+            .filter(|(_, object)| object.value.get("Type").and_then(Obj::name) == Some("ObjStm"))
+            .map(|(number, _)| *number)
+            .collect();
+        for number in packed {
+            let object = &self.objects[&number];
+            let (Some(count), Some(first)) = (
+                object.value.get("N").and_then(Obj::num),
+                object.value.get("First").and_then(Obj::num),
+            ) else {
+                continue;
+            };
+            let Some(data) = self.decode(object) else {
+                continue;
+            };
+            let first = first as usize;
+            let mut index = Lexer::new(&data[..first.min(data.len())], 0);
+            let mut entries = Vec::new();
+            for _ in 0..count as usize {
+                match (index.token(), index.token()) {
+                    (Some(Obj::Num(n)), Some(Obj::Num(offset))) => entries.push((n as u32, first + offset as usize)),
+                    _ => break,
+                }
+            }
+            for (n, offset) in entries {
+                if self.objects.contains_key(&n) || offset >= data.len() {
+                    continue;
+                }
+                if let Some(value) = Lexer::new(&data, offset).value() {
+                    self.objects.insert(n, PdfObject { value, stream: None });
+                }
+            }
+        }
+    }
+
+    fn resolve<'a>(&'a self, mut obj: &'a Obj) -> &'a Obj {
+        for _ in 0..32 {
+            match obj {
+                Obj::Ref(n) => match self.objects.get(n) {
+                    Some(object) => obj = &object.value,
+                    None => return &Obj::Null,
+                },
+                _ => return obj,
+            }
+        }
+        &Obj::Null
+    }
+
+    fn get<'a>(&'a self, obj: &'a Obj, key: &str) -> Option<&'a Obj> {
+        self.resolve(obj).get(key).map(|value| self.resolve(value))
+    }
+
+    /// A stream's data with its filters undone; `None` for filters this
+    /// reader does not know, such as image codecs.
+    fn decode(&self, object: &PdfObject) -> Option<Vec<u8>> {
+        let mut data = object.stream.clone()?;
+        let filters = match object.value.get("Filter").map(|f| self.resolve(f)) {
+            Some(Obj::Name(name)) => vec![name.clone()],
+            Some(Obj::Array(names)) => names.iter().filter_map(|n| self.resolve(n).name().map(String::from)).collect(),
+            _ => vec![],
+        };
+        for filter in filters {
+            data = match filter.as_str() {
+                "FlateDecode" | "Fl" => inflate(&data)?,
+                "ASCIIHexDecode" | "AHx" => {
+                    let digits: Vec<u8> = data.iter().copied().take_while(|&b| b != b'>').filter(u8::is_ascii_hexdigit).collect();
+                    hex_bytes(&digits)
+                }
+                _ => return None,
+            };
+        }
+        Some(data)
+    }
+
+    fn stream_data(&self, obj: &Obj) -> Option<Vec<u8>> {
+        match obj {
+            Obj::Ref(n) => self.decode(self.objects.get(n)?),
+            _ => None,
+        }
+    }
+
+    /// Pages in order, each with the resources it has or inherits.
+    fn pages(&self) -> Vec<(&Obj, Option<&Obj>)> {
+        let root = self.trailers.iter().find_map(|t| t.get("Root"));
+        let mut pages = Vec::new();
+        if let Some(tree) = root.and_then(|root| self.get(root, "Pages")) {
+            let mut seen = HashSet::new();
+            self.collect_pages(tree, None, &mut pages, &mut seen, 0);
+        }
+        if pages.is_empty() {
+            // No usable page tree: take the page objects in file order
+            let mut numbers: Vec<&u32> = self
+                .objects
+                .iter()
+                .filter(|(_, o)| o.value.get("Type").and_then(Obj::name) == Some("Page"))
+                .map(|(n, _)| n)
+                .collect();
+            numbers.sort();
+            for n in numbers {
+                let page = &self.objects[n].value;
+                pages.push((page, page.get("Resources").map(|r| self.resolve(r))));
+            }
+        }
+        pages
+    }
+
+    fn collect_pages<'a>(
+        &'a self,
+        node: &'a Obj,
+        inherited: Option<&'a Obj>,
+        pages: &mut Vec<(&'a Obj, Option<&'a Obj>)>,
+        seen: &mut HashSet<*const Obj>,
+        depth: usize,
+    ) {
+        let node = self.resolve(node);
+        if depth > 64 || !seen.insert(node as *const Obj) {
+            return;
+        }
+        let resources = node.get("Resources").map(|r| self.resolve(r)).or(inherited);
+        match node.get("Kids").map(|k| self.resolve(k)) {
+            Some(Obj::Array(kids)) => {
+                for kid in kids {
+                    self.collect_pages(kid, resources, pages, seen, depth + 1);
+                }
+            }
+            _ => pages.push((node, resources)),
+        }
+    }
+
+    fn is_encrypted(&self) -> bool {
+        self.trailers.iter().any(|t| t.get("Encrypt").is_some())
+    }
+}
+
+fn find(data: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    data.get(from..)?.windows(needle.len()).position(|w| w == needle).map(|i| from + i)
+}
+
+/// Back off the line break before `endstream`.
+fn trim_eol(data: &[u8], start: usize, mut end: usize) -> usize {
+    if end > start && data[end - 1] == b'\n' {
+        end -= 1;
+    }
+    if end > start && data[end - 1] == b'\r' {
+        end -= 1;
+    }
+    end
+}
+
+/// Inflate zlib data, keeping what was decoded of a truncated stream.
+fn inflate(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    match ZlibDecoder::new(data).read_to_end(&mut out) {
+        Ok(_) => Some(out),
+        Err(_) if !out.is_empty() => Some(out),
+        Err(_) => None,
+    }
+}
+
+/// How a font's string bytes map to text.
+struct Font {
+    /// Codes are two bytes, as in composite (Type0) fonts.
+    two_byte: bool,
+    to_unicode: HashMap<u32, String>,
+}
+
+impl Font {
+    fn load(document: &Document, font: &Obj) -> Font {
+        let mut two_byte = document.get(font, "Subtype").and_then(Obj::name) == Some("Type0");
+        let mut to_unicode = HashMap::new();
+        if let Some(cmap) = document.resolve(font).get("ToUnicode").and_then(|c| document.stream_data(c)) {
+            if let Some(width) = parse_cmap(&cmap, &mut to_unicode) {
+                two_byte = width >= 2;
+            }
+        }
+        Font { two_byte, to_unicode }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> String {
+        let mut text = String::new();
+        if self.two_byte {
+            for pair in bytes.chunks(2) {
+                let code = pair.iter().fold(0u32, |code, &b| code << 8 | b as u32);
+                if let Some(mapped) = self.to_unicode.get(&code) {
+                    text.push_str(mapped);
+                }
+            }
+        } else {
+            for &b in bytes {
+                match self.to_unicode.get(&(b as u32)) {
+                    Some(mapped) => text.push_str(mapped),
+                    None => text.push(win_ansi_char(b)),
+                }
+            }
+        }
+        text
+    }
+}
+
+/// Read a ToUnicode CMap into `map`, returning the code width in bytes from
+/// its code space, if given.
+fn parse_cmap(data: &[u8], map: &mut HashMap<u32, String>) -> Option<usize> {
+    let code = |bytes: &[u8]| bytes.iter().fold(0u32, |code, &b| code << 8 | b as u32);
+    let mut lexer = Lexer::new(data, 0);
+    let mut operands: Vec<Obj> = Vec::new();
+    let mut width = None;
+    while let Some(token) = lexer.value() {
+        let Obj::Op(op) = token else {
+            operands.push(token);
+            continue;
+        };
+        match op.as_str() {
+            "endcodespacerange" => {
+                if let Some(Obj::Str(low)) = operands.first() {
+                    width = Some(low.len());
+                }
+            }
+            "endbfchar" => {
+                for pair in operands.chunks(2) {
+                    if let [Obj::Str(src), Obj::Str(dst)] = pair {
+                        map.insert(code(src), utf16_string(dst));
+                    }
+                }
+            }
+            "endbfrange" => {
+                for range in operands.chunks(3) {
+                    let [Obj::Str(low), Obj::Str(high), dst] = range else {
+                        continue;
+                    };
+                    let (low, high) = (code(low), code(high));
+                    if high < low || high - low > 0xFFFF {
+                        continue;
+                    }
+                    for (i, c) in (low..=high).enumerate() {
+                        let text = match dst {
+                            Obj::Str(start) if !start.is_empty() => {
+                                // The last byte counts up through the range
+                                let mut units = start.clone();
+                                let last = units.len() - 1;
+                                units[last] = units[last].wrapping_add(i as u8);
+                                utf16_string(&units)
+                            }
+                            Obj::Array(items) => match items.get(i) {
+                                Some(Obj::Str(s)) => utf16_string(s),
+                                _ => continue,
+                            },
+                            _ => continue,
+                        };
+                        map.insert(c, text);
+                    }
+                }
+            }
+            _ => {}
+        }
+        operands.clear();
+    }
+    width
+}
+
+fn utf16_string(bytes: &[u8]) -> String {
+    if bytes.len() == 1 {
+        return win_ansi_char(bytes[0]).to_string();
+    }
+    let units: Vec<u16> = bytes.chunks(2).map(|p| u16::from_be_bytes([p[0], *p.get(1).unwrap_or(&0)])).collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// A byte of WinAnsiEncoding (Windows-1252), the usual simple font encoding.
+fn win_ansi_char(b: u8) -> char {
+    const HIGH: [char; 32] = [
+        '€', ' ', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', ' ', 'Ž', ' ', ' ', '‘', '’', '“', '”', '•', '–',
+        '—', '˜', '™', 'š', '›', 'œ', ' ', 'ž', 'Ÿ',
+    ];
+    match b {
+        0x80..=0x9F => HIGH[(b - 0x80) as usize],
+        b if b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r') => ' ',
+        b => b as char,
+    }
+}
+
+/// Text shown by a page's content stream. Lines are broken where the text
+/// moves down, and words spaced where it skips ahead, judging by an average
+/// glyph width since font metrics are not read.
+fn page_text(document: &Document, page: &Obj, resources: Option<&Obj>) -> String {
+    let content = match page.get("Contents").map(|c| (c, document.resolve(c))) {
+        Some((_, Obj::Array(parts))) => {
+            let parts: Vec<Vec<u8>> = parts.iter().filter_map(|p| document.stream_data(p)).collect();
+            parts.join(&b'\n')
+        }
+        Some((reference, _)) => document.stream_data(reference).unwrap_or_default(),
+        None => return String::new(),
+    };
+    let font_resources = resources.and_then(|r| document.get(r, "Font"));
+    let mut fonts: HashMap<String, Font> = HashMap::new();
+
+    let mut text = String::new();
+    let mut operands: Vec<Obj> = Vec::new();
+    let mut font_name = String::new();
+    let mut size = 10.0;
+    let mut leading = 0.0;
+    // Text matrix scale and translation; rotation and skew are ignored
+    let (mut scale, mut line_x, mut line_y) = (1.0, 0.0, 0.0);
+    let mut x = 0.0;
+    let mut last: Option<(f64, f64)> = None;
+
+    let mut lexer = Lexer::new(&content, 0);
+    while let Some(token) = lexer.value() {
+        let Obj::Op(op) = token else {
+            operands.push(token);
+            continue;
+        };
+        let num = |i: usize| operands.get(i).and_then(Obj::num).unwrap_or(0.0);
+        let mut shown: Vec<Obj> = Vec::new();
+        match op.as_str() {
+            "BT" => {
+                (scale, line_x, line_y) = (1.0, 0.0, 0.0);
+                x = 0.0;
+            }
+            "Tf" => {
+                font_name = operands.first().and_then(Obj::name).unwrap_or("").to_string();
+                size = num(1).abs().max(1.0);
+            }
+            "TL" => leading = num(0),
+            "Td" | "TD" => {
+                if op == "TD" {
+                    leading = -num(1);
+                }
+                line_x += num(0) * scale;
+                line_y += num(1) * scale;
+                x = line_x;
+            }
+            "Tm" => {
+                scale = num(0).abs().max(num(3).abs()).max(0.01);
+                (line_x, line_y) = (num(4), num(5));
+                x = line_x;
+            }
+            "T*" => {
+                line_y -= leading * scale;
+                x = line_x;
+            }
+            "Tj" => shown = operands.last().cloned().into_iter().collect(),
+            "TJ" => {
+                if let Some(Obj::Array(items)) = operands.last() {
+                    shown = items.clone();
+                }
+            }
+            "'" | "\"" => {
+                line_y -= leading * scale;
+                x = line_x;
+                shown = operands.last().cloned().into_iter().collect();
+            }
+            "BI" => {
+                // Inline image data is binary; skip to its end
+                let end = content[lexer.pos..]
+                    .windows(4)
+                    .position(|w| is_whitespace(w[0]) && &w[1..3] == b"EI" && is_whitespace(w[3]))
+                    .map_or(content.len(), |i| lexer.pos + i + 3);
+                lexer.pos = end;
+            }
+            _ => {}
+        }
+        operands.clear();
+        if shown.is_empty() {
+            continue;
+        }
+
+        let font = fonts.entry(font_name.clone()).or_insert_with(|| match font_resources.and_then(|f| f.get(&font_name)) {
+            Some(font) => Font::load(document, font),
+            None => Font { two_byte: false, to_unicode: HashMap::new() },
+        });
+        let em = size * scale;
+        for item in shown {
+            match item {
+                Obj::Str(bytes) => {
+                    let decoded = font.decode(&bytes);
+                    if decoded.is_empty() {
+                        continue;
+                    }
+                    if let Some((last_x, last_y)) = last {
+                        if (line_y - last_y).abs() > em * 0.5 {
+                            text.push('\n');
+                        } else if x > last_x + em * 0.15 && !text.ends_with(char::is_whitespace) {
+                            text.push(' ');
+                        }
+                    }
+                    text.push_str(&decoded);
+                    x += decoded.chars().count() as f64 * em * 0.5;
+                    last = Some((x, line_y));
+                }
+                // Adjustments are in thousandths of an em; a large gap is a space
+                Obj::Num(adjust) => {
+                    if adjust < -250.0 && !text.ends_with(char::is_whitespace) && last.is_some() {
+                        text.push(' ');
+                    }
+                    x -= adjust / 1000.0 * em;
+                    last = last.map(|(_, y)| (x, y));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    text.lines()
+        .map(|line| line.split(' ').filter(|w| !w.is_empty()).collect::<Vec<_>>().join(" "))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// The text of each page of a PDF. Text in images (scans) is not found.
+pub fn pdf_page_texts(data: &[u8]) -> Result<Vec<String>, String> {
+    if !data.starts_with(b"%PDF") {
+        return Err("Not a PDF file".to_string());
+    }
+    let document = Document::parse(data);
+    if document.is_encrypted() {
+        return Err("Encrypted PDFs are not supported".to_string());
+    }
+    Ok(document
+        .pages()
+        .into_iter()
+        .map(|(page, resources)| page_text(&document, page, resources))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::pdf::{render_pdf, PdfChapter};
+
+    #[test]
+    fn test_pdf_page_texts() {
+        let chapters = vec![
+            PdfChapter { id: "a".to_string(), title: "Bears".to_string(), markdown: "Bears **sleep** all winter.".to_string() },
+            PdfChapter { id: "b".to_string(), title: "Owls".to_string(), markdown: "Owls “hunt” at night.".to_string() },
+        ];
+        let output = render_pdf("Animals", &chapters, &|_| None).unwrap();
+        let pages = pdf_page_texts(&output.bytes).unwrap();
+        assert_eq!(pages.len(), 3);
+        assert!(pages[1].contains("Bears sleep all winter."), "{}", pages[1]);
+        assert!(pages[2].contains("Owls “hunt” at night."), "{}", pages[2]);
+
+        // A composite font mapped to Unicode through a ToUnicode CMap
+        let cmap = b"/CIDInit /ProcSet findresource begin 1 begincodespacerange <0000> <FFFF> endcodespacerange \
+            3 beginbfchar <0001> <0048> <0002> <0069> <0005> <597D> endbfchar 1 beginbfrange <0003> <0004> <0041> endbfrange end";
+        let content = b"BT /F1 12 Tf 72 700 Td <00010002> Tj 0 -14 Td [<0003> -400 <0004>] TJ <0005> Tj ET";
+        let mut pdf = b"%PDF-1.7\n1 0 obj << /Type /Catalog /Pages 2 0 R >> endobj\n\
+            2 0 obj << /Type /Pages /Kids [3 0 R] /Resources << /Font << /F1 4 0 R >> >> >> endobj\n\
+            3 0 obj << /Type /Page /Parent 2 0 R /Contents 5 0 R >> endobj\n\
+            4 0 obj << /Type /Font /Subtype /Type0 /ToUnicode 6 0 R >> endobj\n"
+            .to_vec();
+        for (n, data) in [(5, &content[..]), (6, &cmap[..])] {
+            pdf.extend(format!("{} 0 obj << /Length {} >>\nstream\n", n, data.len()).bytes());
+            pdf.extend_from_slice(data);
+            pdf.extend_from_slice(b"\nendstream endobj\n");
+        }
+        pdf.extend_from_slice(b"trailer << /Root 1 0 R >>\n%%EOF");
+        assert_eq!(pdf_page_texts(&pdf).unwrap(), vec!["Hi\nA B好"]);
+
+        // Damaged files give errors or less text, never a panic
+        for end in 0..pdf.len() {
+            let _ = pdf_page_texts(&pdf[..end]);
+        }
+        let text = String::from_utf8_lossy(&pdf);
+        let huge = text.replacen(&format!("/Length {}", content.len()), "/Length 99999999999999999999999", 1);
+        assert_ne!(huge, text);
+        assert!(pdf_page_texts(huge.as_bytes()).is_ok());
+    }
+}
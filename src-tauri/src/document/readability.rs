@@ -0,0 +1,388 @@
+use crate::document::html::{text_content, Node};
+use regex::Regex;
+use std::collections::HashMap;
+
+/// The main content of a web page and what the page says about it.
+#[derive(Debug, Default)]
+pub struct Article {
+    pub title: String,
+    pub author: Option<String>,
+    /// The page's own (canonical) URL, if it gives one.
+    pub url: Option<String>,
+    /// The `<base href>` of the page, which relative links resolve against.
+    pub base_url: Option<String>,
+    /// The content without the page's navigation, sidebars, comments and
+    /// other chrome, and without its title heading.
+    pub nodes: Vec<Node>,
+}
+
+/// Elements that are never part of an article.
+const DROPPED_ELEMENTS: &[&str] = &[
+    "head", "nav", "aside", "footer", "form", "button", "input", "select", "dialog", "menu", "link", "meta",
+    "title", "object", "embed", "canvas",
+];
+
+/// Blocks whose text counts as a paragraph when scoring.
+const PARAGRAPH_ELEMENTS: &[&str] = &["p", "pre", "td", "blockquote"];
+
+/// Elements that make a `div` a container rather than a paragraph.
+const BLOCK_CHILDREN: &[&str] = &[
+    "p", "div", "pre", "table", "ul", "ol", "blockquote", "section", "article", "h1", "h2", "h3", "h4", "h5", "h6",
+    "figure",
+];
+
+/// Find the article in a parsed web page with the heuristics of Mozilla's
+/// Readability: page chrome is dropped by element and by class or id, each
+/// paragraph scores its parent and grandparent by its length and commas,
+/// scores are discounted by link density, and the best container is kept
+/// together with siblings that look like part of it.
+pub fn extract_article(document: &[Node]) -> Article {
+    let mut meta: HashMap<String, String> = HashMap::new();
+    let mut page_title = None;
+    let mut base_url = None;
+    collect_head(document, &mut meta, &mut page_title, &mut base_url);
+    let meta = |keys: &[&str]| keys.iter().find_map(|k| meta.get(*k)).map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+
+    let patterns = Patterns::new();
+    let body = find_element(document, "body").map(|b| b.children().to_vec()).unwrap_or_else(|| document.to_vec());
+    let body = clean(body, &patterns);
+
+    let h1 = first_text(&body, "h1");
+    let title = meta(&["og:title", "twitter:title"])
+        .or_else(|| match (&page_title, &h1) {
+            // `<title>` often adds the site name; the heading doesn't
+            (Some(page), Some(h1)) if page.contains(h1.as_str()) => Some(h1.clone()),
+            (Some(page), _) => Some(page.clone()),
+            (None, h1) => h1.clone(),
+        })
+        .unwrap_or_default();
+    let author = meta(&["author", "article:author", "dc.creator"])
+        .filter(|a| !a.starts_with("http"))
+        .or_else(|| find_byline(&body, &patterns))
+        .map(|a| {
+            let a = a.trim();
+            a.strip_prefix("By ").or_else(|| a.strip_prefix("by ")).unwrap_or(a).trim().to_string()
+        });
+
+    let url = meta(&["og:url", "canonical"]).filter(|u| u.starts_with("http"));
+    let mut nodes = main_content(&body, &patterns);
+    remove_bylines(&mut nodes, &patterns);
+    remove_title(&mut nodes, &title);
+    Article {
+        title,
+        author,
+        url,
+        base_url,
+        nodes,
+    }
+}
+
+struct Patterns {
+    unlikely: Regex,
+    likely: Regex,
+    negative: Regex,
+    positive: Regex,
+    byline: Regex,
+}
+
+impl Patterns {
+    fn new() -> Self {
+        Patterns {
+            unlikely: Regex::new(
+                r"(?i)-ad-|\bads?\b|banner|breadcrumb|combx|comment|community|cookie|cover-wrap|disqus|extra|footer|gdpr|header|legends|menu|related|remark|replies|rss|shoutbox|sidebar|skyscraper|social|sponsor|supplemental|popup|promo|share|newsletter|subscribe|signup|tweet|twitter",
+            )
+            .unwrap(),
+            likely: Regex::new(r"(?i)and|article|body|column|content|main|shadow").unwrap(),
+            negative: Regex::new(
+                r"(?i)-ad-|hidden|\bhid\b|banner|combx|comment|com-|contact|foot|footer|footnote|gdpr|masthead|media|meta|outbrain|promo|related|scroll|share|shoutbox|sidebar|skyscraper|sponsor|shopping|tags|tool|widget",
+            )
+            .unwrap(),
+            positive: Regex::new(r"(?i)article|body|content|entry|hentry|h-entry|main|page|pagination|post|text|blog|story")
+                .unwrap(),
+            byline: Regex::new(r"(?i)byline|author|dateline|writtenby|p-author").unwrap(),
+        }
+    }
+}
+
+fn class_and_id(node: &Node) -> String {
+    format!("{} {}", node.attr("class").unwrap_or(""), node.attr("id").unwrap_or(""))
+}
+
+/// `<meta>` values by name or property (and the canonical link as
+/// `canonical`), the `<title>` and `<base href>`.
+fn collect_head(nodes: &[Node], meta: &mut HashMap<String, String>, title: &mut Option<String>, base: &mut Option<String>) {
+    for node in nodes {
+        match node.name() {
+            "meta" => {
+                let key = node.attr("property").or_else(|| node.attr("name")).map(str::to_ascii_lowercase);
+                if let (Some(key), Some(content)) = (key, node.attr("content")) {
+                    meta.entry(key).or_insert_with(|| content.to_string());
+                }
+            }
+            "link" if node.attr("rel").is_some_and(|r| r.eq_ignore_ascii_case("canonical")) => {
+                if let Some(href) = node.attr("href") {
+                    meta.entry("canonical".to_string()).or_insert_with(|| href.to_string());
+                }
+            }
+            "title" if title.is_none() => {
+                *title = Some(normalize_space(&text_content(node.children()))).filter(|t| !t.is_empty());
+            }
+            "base" if base.is_none() => *base = node.attr("href").map(String::from),
+            "" => {}
+            _ => collect_head(node.children(), meta, title, base),
+        }
+    }
+}
+
+fn find_element<'a>(nodes: &'a [Node], name: &str) -> Option<&'a Node> {
+    nodes.iter().find_map(|node| {
+        if node.name() == name {
+            Some(node)
+        } else {
+            find_element(node.children(), name)
+        }
+    })
+}
+
+fn first_text(nodes: &[Node], name: &str) -> Option<String> {
+    find_element(nodes, name).map(|n| normalize_space(&text_content(n.children()))).filter(|t| !t.is_empty())
+}
+
+fn normalize_space(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Drop chrome: elements that are never content, hidden ones, and ones
+/// whose class or id says they are navigation, ads, comments and the like.
+fn clean(nodes: Vec<Node>, patterns: &Patterns) -> Vec<Node> {
+    nodes
+        .into_iter()
+        .filter(|node| !is_chrome(node, patterns))
+        .map(|node| match node {
+            Node::Element { name, attrs, children } => Node::Element {
+                name,
+                attrs,
+                children: clean(children, patterns),
+            },
+            text => text,
+        })
+        .collect()
+}
+
+fn is_chrome(node: &Node, patterns: &Patterns) -> bool {
+    let name = node.name();
+    if name.is_empty() {
+        return false;
+    }
+    let hidden = node.attr("hidden").is_some()
+        || node.attr("aria-hidden") == Some("true")
+        || node.attr("style").is_some_and(|s| s.replace(' ', "").contains("display:none"));
+    let matches = class_and_id(node);
+    let unlikely = !["body", "article", "main", "a"].contains(&name)
+        && patterns.unlikely.is_match(&matches)
+        && !patterns.likely.is_match(&matches);
+    DROPPED_ELEMENTS.contains(&name) || hidden || unlikely
+}
+
+/// Whether a node is a short "By …" line naming the author.
+fn is_byline(node: &Node, patterns: &Patterns) -> bool {
+    let marked = node.attr("rel") == Some("author")
+        || node.attr("itemprop").is_some_and(|p| p.contains("author"))
+        || patterns.byline.is_match(&class_and_id(node));
+    marked && (1..100).contains(&inner_text(node).len())
+}
+
+fn find_byline(nodes: &[Node], patterns: &Patterns) -> Option<String> {
+    nodes.iter().find_map(|node| {
+        if is_byline(node, patterns) {
+            Some(inner_text(node))
+        } else {
+            find_byline(node.children(), patterns)
+        }
+    })
+}
+
+fn remove_bylines(nodes: &mut Vec<Node>, patterns: &Patterns) {
+    nodes.retain(|node| !is_byline(node, patterns));
+    for node in nodes {
+        if let Node::Element { children, .. } = node {
+            remove_bylines(children, patterns);
+        }
+    }
+}
+
+fn inner_text(node: &Node) -> String {
+    normalize_space(&text_content(node.children()))
+}
+
+/// The share of a node's text that is link text.
+fn link_density(node: &Node) -> f64 {
+    fn link_length(nodes: &[Node]) -> usize {
+        nodes
+            .iter()
+            .map(|n| if n.name() == "a" { inner_text(n).len() } else { link_length(n.children()) })
+            .sum()
+    }
+    let length = inner_text(node).len();
+    if length == 0 {
+        return 0.0;
+    }
+    link_length(node.children()) as f64 / length as f64
+}
+
+/// The starting score of a container: by its element and its class and id.
+fn base_score(node: &Node, patterns: &Patterns) -> f64 {
+    let by_element = match node.name() {
+        "article" | "main" => 10.0,
+        "div" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    let matches = class_and_id(node);
+    let mut by_class = 0.0;
+    if patterns.negative.is_match(&matches) {
+        by_class -= 25.0;
+    }
+    if patterns.positive.is_match(&matches) {
+        by_class += 25.0;
+    }
+    by_element + by_class
+}
+
+fn is_paragraph(node: &Node) -> bool {
+    match node.name() {
+        name if PARAGRAPH_ELEMENTS.contains(&name) => true,
+        // Many pages use divs of text as paragraphs
+        "div" | "section" => !node.children().iter().any(|c| BLOCK_CHILDREN.contains(&c.name())),
+        _ => false,
+    }
+}
+
+/// Score containers by the paragraphs in them: a paragraph adds its score
+/// to its parent, half to its grandparent and a third to the level above.
+fn score_paragraphs<'a>(
+    node: &'a Node,
+    ancestors: &mut Vec<&'a Node>,
+    scores: &mut HashMap<*const Node, (f64, &'a Node)>,
+    patterns: &Patterns,
+) {
+    if is_paragraph(node) {
+        let text = inner_text(node);
+        if text.len() >= 25 {
+            let score = 1.0 + text.matches([',', '，']).count() as f64 + (text.len() as f64 / 100.0).min(3.0);
+            for (level, ancestor) in ancestors.iter().rev().take(3).enumerate() {
+                let entry = scores
+                    .entry(*ancestor as *const Node)
+                    .or_insert_with(|| (base_score(ancestor, patterns), ancestor));
+                entry.0 += score / if level == 0 { 1.0 } else { level as f64 + 1.0 };
+            }
+        }
+    }
+    ancestors.push(node);
+    for child in node.children() {
+        if matches!(child, Node::Element { .. }) {
+            score_paragraphs(child, ancestors, scores, patterns);
+        }
+    }
+    ancestors.pop();
+}
+
+/// The best-scoring container, with siblings that belong to the article.
+fn main_content(body: &[Node], patterns: &Patterns) -> Vec<Node> {
+    let root = Node::Element {
+        name: "body".to_string(),
+        attrs: Vec::new(),
+        children: body.to_vec(),
+    };
+    let mut scores = HashMap::new();
+    for child in root.children() {
+        score_paragraphs(child, &mut vec![&root], &mut scores, patterns);
+    }
+    let best = scores
+        .values()
+        .map(|(score, node)| (score * (1.0 - link_density(node)), *node))
+        .filter(|(_, node)| !std::ptr::eq(*node, &root))
+        .max_by(|a, b| a.0.total_cmp(&b.0));
+    let Some((best_score, best)) = best else {
+        return body.to_vec();
+    };
+
+    // Content split over sibling containers is joined back together
+    let Some(parent) = find_parent(&root, best) else {
+        return vec![best.clone()];
+    };
+    let threshold = (best_score * 0.2).max(10.0);
+    parent
+        .children()
+        .iter()
+        .filter(|sibling| {
+            if std::ptr::eq(*sibling, best) {
+                return true;
+            }
+            if let Some((score, _)) = scores.get(&(*sibling as *const Node)) {
+                return score * (1.0 - link_density(sibling)) >= threshold;
+            }
+            if sibling.name() != "p" {
+                return false;
+            }
+            let text = inner_text(sibling);
+            let density = link_density(sibling);
+            (text.len() > 80 && density < 0.25) || (density == 0.0 && text.contains(". "))
+        })
+        .cloned()
+        .collect()
+}
+
+fn find_parent<'a>(node: &'a Node, target: &Node) -> Option<&'a Node> {
+    if node.children().iter().any(|c| std::ptr::eq(c, target)) {
+        return Some(node);
+    }
+    node.children().iter().find_map(|c| find_parent(c, target))
+}
+
+/// Remove the first heading when it repeats the title.
+fn remove_title(nodes: &mut Vec<Node>, title: &str) -> bool {
+    for i in 0..nodes.len() {
+        if matches!(nodes[i].name(), "h1" | "h2") {
+            if inner_text(&nodes[i]) == title {
+                nodes.remove(i);
+                return true;
+            }
+            return false;
+        }
+        if let Node::Element { children, .. } = &mut nodes[i] {
+            if remove_title(children, title) {
+                return true;
+            }
+        }
+        if !inner_text(&nodes[i]).is_empty() && nodes[i].name() != "header" {
+            return false;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::html::{html_to_markdown, parse_html};
+
+    #[test]
+    fn test_extract_article() {
+        let article = extract_article(&parse_html(include_str!("fixtures/article.html")));
+        assert_eq!(article.title, "How Bears Prepare for Winter");
+        assert_eq!(article.author.as_deref(), Some("Jane Doe"));
+        assert_eq!(article.url.as_deref(), Some("https://wild.example/bears-winter"));
+        assert_eq!(article.base_url, None);
+        let markdown = html_to_markdown(&article.nodes, &HashMap::new());
+        assert!(markdown.starts_with("Every autumn, bears eat"), "{}", markdown);
+        assert!(markdown.contains("![A bear fishing](/images/bear.jpg)"));
+        assert!(markdown.contains("## Finding a den"));
+        assert!(markdown.contains("They wake up in spring"));
+        for chrome in ["Subscribe", "Home", "Related stories", "Comments", "Copyright", "trackPageView"] {
+            assert!(!markdown.contains(chrome), "{} in {}", chrome, markdown);
+        }
+    }
+}
@@ -0,0 +1,61 @@
+/// A file read from a tar archive.
+pub struct TarEntry {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// Read the regular files of a ustar archive. Long names stored with GNU
+/// `././@LongLink` records or pax `path` headers are supported.
+pub fn read_tar(data: &[u8]) -> Result<Vec<TarEntry>, String> {
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    let mut long_name: Option<String> = None;
+    while pos + 512 <= data.len() {
+        let header = &data[pos..pos + 512];
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        let field = |start: usize, len: usize| {
+            let bytes = &header[start..start + len];
+            let end = bytes.iter().position(|&b| b == 0).unwrap_or(len);
+            String::from_utf8_lossy(&bytes[..end]).to_string()
+        };
+        let size = usize::from_str_radix(field(124, 12).trim(), 8)
+            .map_err(|_| "Not a valid tar archive".to_string())?;
+        let start = pos + 512;
+        let body = data.get(start..start + size).ok_or("Truncated tar archive")?;
+        pos = start + size.div_ceil(512) * 512;
+
+        let kind = header[156];
+        let mut name = match field(345, 155) {
+            prefix if !prefix.is_empty() && &header[257..262] == b"ustar" => format!("{}/{}", prefix, field(0, 100)),
+            _ => field(0, 100),
+        };
+        match kind {
+            b'L' => {
+                long_name = Some(String::from_utf8_lossy(body).trim_end_matches('\0').to_string());
+                continue;
+            }
+            b'x' => {
+                // Pax records are "<len> key=value\n"
+                long_name = String::from_utf8_lossy(body)
+                    .lines()
+                    .find_map(|line| line.split_once(' ')?.1.strip_prefix("path=").map(String::from));
+                continue;
+            }
+            b'0' | 0 => {}
+            _ => {
+                long_name = None;
+                continue;
+            }
+        }
+        if let Some(long) = long_name.take() {
+            name = long;
+        }
+        entries.push(TarEntry {
+            name: name.trim_start_matches("./").to_string(),
+            data: body.to_vec(),
+        });
+    }
+    Ok(entries)
+}
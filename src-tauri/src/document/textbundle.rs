@@ -0,0 +1,180 @@
+use crate::document::zip::read_zip;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+const BEAR_KEY: &str = "net.shinyfrog.bear";
+
+/// Files of a bundle as (path in the bundle, data).
+type BundleFiles = Vec<(String, Vec<u8>)>;
+
+/// A TextBundle (or one note of a TextPack or Bear backup): the markdown
+/// text and the files it references.
+pub struct TextBundle {
+    /// Bundle name without its extension, e.g. `Meeting notes`.
+    pub name: String,
+    pub text: String,
+    /// Files by their path in the bundle, e.g. `assets/photo.png`.
+    pub assets: HashMap<String, Vec<u8>>,
+    pub info: BundleInfo,
+}
+
+/// Note metadata Bear keeps in a bundle's `info.json`.
+#[derive(Debug, Default, PartialEq)]
+pub struct BundleInfo {
+    pub id: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    pub pinned: bool,
+    pub trashed: bool,
+}
+
+pub fn is_bundle_dir(path: &Path) -> bool {
+    path.is_dir() && has_extension(path, &["textbundle"])
+}
+
+pub fn is_bundle_archive(path: &Path) -> bool {
+    path.is_file() && has_extension(path, &["textpack", "bear2bk"])
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| extensions.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Read a `.textbundle` folder.
+pub fn read_bundle_dir(path: &Path) -> Result<TextBundle, String> {
+    let mut files = Vec::new();
+    read_dir_files(path, "", &mut files)?;
+    let name = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    bundle_from_files(name, files)
+}
+
+fn read_dir_files(dir: &Path, prefix: &str, out: &mut BundleFiles) -> Result<(), String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+        if path.is_dir() {
+            read_dir_files(&path, &format!("{}/", name), out)?;
+        } else {
+            let data = fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            out.push((name, data));
+        }
+    }
+    Ok(())
+}
+
+/// Read the bundles in a TextPack or `.bear2bk` archive. Both are zips of
+/// `.textbundle` folders; a TextPack may also hold a single bundle's files
+/// at its root.
+pub fn read_bundle_archive(data: &[u8]) -> Result<Vec<TextBundle>, String> {
+    let mut groups: Vec<(String, BundleFiles)> = Vec::new();
+    for entry in read_zip(data)? {
+        if entry.name.starts_with("__MACOSX/") {
+            continue;
+        }
+        let (bundle, file) = match entry.name.find(".textbundle/") {
+            Some(i) => (&entry.name[..i], &entry.name[i + ".textbundle/".len()..]),
+            None => ("", entry.name.as_str()),
+        };
+        let bundle = bundle.rsplit('/').next().unwrap_or(bundle).to_string();
+        let item = (file.to_string(), entry.data);
+        match groups.iter_mut().find(|(name, _)| *name == bundle) {
+            Some((_, files)) => files.push(item),
+            None => groups.push((bundle, vec![item])),
+        }
+    }
+    groups
+        .into_iter()
+        .filter(|(_, files)| files.iter().any(|(name, _)| is_text_file(name)))
+        .map(|(name, files)| bundle_from_files(name, files))
+        .collect()
+}
+
+fn is_text_file(name: &str) -> bool {
+    ["text.md", "text.markdown", "text.txt"].contains(&name)
+}
+
+fn bundle_from_files(name: String, files: BundleFiles) -> Result<TextBundle, String> {
+    let mut text = None;
+    let mut info = BundleInfo::default();
+    let mut assets = HashMap::new();
+    for (file, data) in files {
+        if is_text_file(&file) {
+            text = Some(String::from_utf8(data).map_err(|_| format!("{}: text is not UTF-8", name))?);
+        } else if file == "info.json" {
+            info = parse_info(&data);
+        } else if !file.split('/').any(|part| part.starts_with('.')) {
+            assets.insert(file, data);
+        }
+    }
+    Ok(TextBundle {
+        text: text.ok_or_else(|| format!("{}: no text file in the bundle", name))?,
+        name,
+        assets,
+        info,
+    })
+}
+
+/// Dates, pinned and trashed status from Bear's section of `info.json`.
+fn parse_info(data: &[u8]) -> BundleInfo {
+    let Ok(json) = serde_json::from_slice::<serde_json::Value>(data) else {
+        return BundleInfo::default();
+    };
+    let bear = &json[BEAR_KEY];
+    let text = |key: &str| bear[key].as_str().filter(|s| !s.is_empty()).map(String::from);
+    // Flags are 0/1 in backups, but booleans and "yes" also turn up
+    let flag = |key: &str| match &bear[key] {
+        serde_json::Value::Bool(b) => *b,
+        serde_json::Value::Number(n) => n.as_i64() == Some(1),
+        serde_json::Value::String(s) => s == "1" || s.eq_ignore_ascii_case("yes"),
+        _ => false,
+    };
+    BundleInfo {
+        id: text("uniqueIdentifier"),
+        created_at: text("creationDate"),
+        updated_at: text("modificationDate"),
+        pinned: flag("pinned"),
+        trashed: flag("trashed"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::zip::ZipWriter;
+
+    #[test]
+    fn test_read_bundle_archive() {
+        let mut zip = ZipWriter::new();
+        zip.add("Notes/One.textbundle/text.md", b"# One\nBody").unwrap();
+        zip.add(
+            "Notes/One.textbundle/info.json",
+            br#"{"net.shinyfrog.bear": {"uniqueIdentifier": "ABC-1", "creationDate": "2020-01-02T03:04:05Z", "pinned": 1, "trashed": 0}}"#,
+        )
+        .unwrap();
+        zip.add_stored("Notes/One.textbundle/assets/pic.png", b"png");
+        zip.add("Two.textbundle/text.markdown", b"Two").unwrap();
+        zip.add("__MACOSX/Two.textbundle/._text.markdown", b"junk").unwrap();
+        let bundles = read_bundle_archive(&zip.finish()).unwrap();
+
+        assert_eq!(bundles.len(), 2);
+        assert_eq!(bundles[0].name, "One");
+        assert_eq!(bundles[0].text, "# One\nBody");
+        assert_eq!(bundles[0].assets["assets/pic.png"], b"png");
+        assert_eq!(
+            bundles[0].info,
+            BundleInfo {
+                id: Some("ABC-1".to_string()),
+                created_at: Some("2020-01-02T03:04:05Z".to_string()),
+                updated_at: None,
+                pinned: true,
+                trashed: false,
+            }
+        );
+        assert_eq!(bundles[1].name, "Two");
+        assert!(bundles[1].assets.is_empty());
+    }
+}
@@ -0,0 +1,541 @@
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+
+/// Tables copied unchanged into a subset; the glyph tables are rebuilt.
+const HINTING_TABLES: [&[u8; 4]; 3] = [b"cvt ", b"fpgm", b"prep"];
+
+/// Composite glyph component flags.
+const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+const WE_HAVE_A_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+/// A TrueType font (`.ttf`, or the first font of a `.ttc`), read far enough
+/// to map characters to glyphs, measure them and write a subset for
+/// embedding in a PDF. Fonts with PostScript (CFF) outlines are rejected.
+pub struct TrueTypeFont {
+    data: Vec<u8>,
+    tables: HashMap<[u8; 4], Range<usize>>,
+    units_per_em: u16,
+    num_glyphs: u16,
+    num_h_metrics: u16,
+    long_loca: bool,
+    /// Format and byte range of the Unicode cmap subtable.
+    cmap: (u16, Range<usize>),
+}
+
+/// Vertical metrics and bounding box, in thousandths of the font size.
+pub struct FontMetrics {
+    pub ascent: f32,
+    pub descent: f32,
+    pub cap_height: f32,
+    pub bbox: [f32; 4],
+}
+
+/// A font with only some glyphs, renumbered from 0 in the order of
+/// `glyph_ids`.
+pub struct FontSubset {
+    pub data: Vec<u8>,
+    /// Original glyph id to its id in the subset.
+    pub glyph_ids: HashMap<u16, u16>,
+}
+
+impl TrueTypeFont {
+    pub fn parse(data: Vec<u8>) -> Result<TrueTypeFont, String> {
+        let start = if data.starts_with(b"ttcf") {
+            u32_at(&data, 12).ok_or("Truncated font collection")? as usize
+        } else {
+            0
+        };
+        match data.get(start..start + 4) {
+            Some([0, 1, 0, 0]) | Some(b"true") => {}
+            Some(b"OTTO") => return Err("Fonts with PostScript outlines are not supported".to_string()),
+            _ => return Err("Not a TrueType font".to_string()),
+        }
+
+        let count = u16_at(&data, start + 4).ok_or("Truncated font")? as usize;
+        let mut tables = HashMap::new();
+        for i in 0..count {
+            let record = start + 12 + i * 16;
+            let tag: [u8; 4] = data
+                .get(record..record + 4)
+                .and_then(|t| t.try_into().ok())
+                .ok_or("Truncated font table directory")?;
+            let offset = u32_at(&data, record + 8).ok_or("Truncated font table directory")? as usize;
+            let length = u32_at(&data, record + 12).ok_or("Truncated font table directory")? as usize;
+            if offset.checked_add(length).is_none_or(|end| end > data.len()) {
+                return Err(format!("Font table '{}' is out of bounds", String::from_utf8_lossy(&tag)));
+            }
+            tables.insert(tag, offset..offset + length);
+        }
+        for tag in [b"head", b"hhea", b"maxp", b"hmtx", b"loca", b"glyf", b"cmap"] {
+            if !tables.contains_key(tag) {
+                return Err(format!("Font has no '{}' table", String::from_utf8_lossy(tag)));
+            }
+        }
+
+        let head = tables[b"head"].start;
+        let units_per_em = u16_at(&data, head + 18).filter(|&u| u > 0).ok_or("Invalid font header")?;
+        let long_loca = i16_at(&data, head + 50).ok_or("Invalid font header")? == 1;
+        let num_glyphs = u16_at(&data, tables[b"maxp"].start + 4).ok_or("Invalid 'maxp' table")?;
+        let num_h_metrics = u16_at(&data, tables[b"hhea"].start + 34)
+            .filter(|&n| n > 0)
+            .ok_or("Invalid 'hhea' table")?;
+        let cmap = unicode_cmap(&data, tables[b"cmap"].clone()).ok_or("Font has no Unicode character map")?;
+
+        Ok(TrueTypeFont {
+            data,
+            tables,
+            units_per_em,
+            num_glyphs,
+            num_h_metrics,
+            long_loca,
+            cmap,
+        })
+    }
+
+    /// Whether the font's license allows embedding it in a document.
+    pub fn embeddable(&self) -> bool {
+        let Some(os2) = self.tables.get(b"OS/2") else {
+            return true;
+        };
+        let fs_type = u16_at(&self.data, os2.start + 8).unwrap_or(0);
+        // Restricted license, or bitmaps only
+        fs_type & 0x000F != 0x0002 && fs_type & 0x0200 == 0
+    }
+
+    /// The glyph for `c`, or `None` if the font does not have one.
+    pub fn glyph_index(&self, c: char) -> Option<u16> {
+        let (format, range) = &self.cmap;
+        let code = c as u32;
+        let glyph = match format {
+            4 => {
+                if code > 0xFFFF {
+                    return None;
+                }
+                let table = range.start;
+                let seg_x2 = u16_at(&self.data, table + 6)? as usize;
+                let ends = table + 14;
+                let starts = ends + seg_x2 + 2;
+                let deltas = starts + seg_x2;
+                let range_offsets = deltas + seg_x2;
+                let seg = (0..seg_x2 / 2).find(|&i| u16_at(&self.data, ends + i * 2).is_some_and(|end| end as u32 >= code))?;
+                let start = u16_at(&self.data, starts + seg * 2)? as u32;
+                if start > code {
+                    return None;
+                }
+                let delta = u16_at(&self.data, deltas + seg * 2)?;
+                let range_offset = u16_at(&self.data, range_offsets + seg * 2)? as usize;
+                if range_offset == 0 {
+                    (code as u16).wrapping_add(delta)
+                } else {
+                    let at = range_offsets + seg * 2 + range_offset + (code - start) as usize * 2;
+                    match u16_at(&self.data, at)? {
+                        0 => 0,
+                        g => g.wrapping_add(delta),
+                    }
+                }
+            }
+            12 => {
+                let table = range.start;
+                let groups = u32_at(&self.data, table + 12)? as usize;
+                let (mut lo, mut hi) = (0, groups);
+                let mut found = None;
+                while lo < hi {
+                    let mid = (lo + hi) / 2;
+                    let group = table + 16 + mid * 12;
+                    let (first, last) = (u32_at(&self.data, group)?, u32_at(&self.data, group + 4)?);
+                    if code < first {
+                        hi = mid;
+                    } else if code > last {
+                        lo = mid + 1;
+                    } else {
+                        found = Some(u32_at(&self.data, group + 8)? + (code - first));
+                        break;
+                    }
+                }
+                u16::try_from(found?).ok()?
+            }
+            _ => return None,
+        };
+        (glyph != 0 && glyph < self.num_glyphs).then_some(glyph)
+    }
+
+    /// Advance width of `glyph`, in thousandths of the font size.
+    pub fn advance(&self, glyph: u16) -> f32 {
+        let index = glyph.min(self.num_h_metrics - 1) as usize;
+        let units = u16_at(&self.data, self.tables[b"hmtx"].start + index * 4).unwrap_or(0);
+        self.scale(units as f32)
+    }
+
+    pub fn metrics(&self) -> FontMetrics {
+        let head = self.tables[b"head"].start;
+        let hhea = self.tables[b"hhea"].start;
+        let value = |pos: usize| self.scale(i16_at(&self.data, pos).unwrap_or(0) as f32);
+        let ascent = value(hhea + 4);
+        // sCapHeight is in OS/2 version 2 and later
+        let cap_height = self
+            .tables
+            .get(b"OS/2")
+            .filter(|os2| u16_at(&self.data, os2.start).is_some_and(|v| v >= 2))
+            .map(|os2| value(os2.start + 88))
+            .unwrap_or(ascent);
+        FontMetrics {
+            ascent,
+            descent: value(hhea + 6),
+            cap_height,
+            bbox: [value(head + 36), value(head + 38), value(head + 40), value(head + 42)],
+        }
+    }
+
+    /// The PostScript name from the `name` table, reduced to characters
+    /// allowed in a PDF name.
+    pub fn postscript_name(&self) -> String {
+        let name = self.tables.get(b"name").and_then(|range| {
+            let table = range.start;
+            let count = u16_at(&self.data, table + 2)? as usize;
+            let strings = table + u16_at(&self.data, table + 4)? as usize;
+            (0..count).find_map(|i| {
+                let record = table + 6 + i * 12;
+                if u16_at(&self.data, record + 6)? != 6 {
+                    return None;
+                }
+                let platform = u16_at(&self.data, record)?;
+                let length = u16_at(&self.data, record + 8)? as usize;
+                let offset = strings + u16_at(&self.data, record + 10)? as usize;
+                let bytes = self.data.get(offset..offset + length)?;
+                Some(match platform {
+                    0 | 3 => {
+                        let units: Vec<u16> = bytes.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+                        String::from_utf16_lossy(&units)
+                    }
+                    _ => bytes.iter().map(|&b| b as char).collect(),
+                })
+            })
+        });
+        let name: String = name
+            .unwrap_or_default()
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+            .collect();
+        if name.is_empty() {
+            "Font".to_string()
+        } else {
+            name
+        }
+    }
+
+    /// A font with glyph 0, `glyphs` and the glyphs their composites use,
+    /// renumbered in ascending order of their original ids. The character
+    /// map is left out: PDF text addresses glyphs by id.
+    pub fn subset(&self, glyphs: &[u16]) -> Result<FontSubset, String> {
+        let mut keep: BTreeSet<u16> = glyphs.iter().copied().filter(|&g| g < self.num_glyphs).collect();
+        keep.insert(0);
+        let mut pending: Vec<u16> = keep.iter().copied().collect();
+        while let Some(glyph) = pending.pop() {
+            for (_, component) in self.components(glyph) {
+                if component < self.num_glyphs && keep.insert(component) {
+                    pending.push(component);
+                }
+            }
+        }
+        let glyph_ids: HashMap<u16, u16> = keep.iter().enumerate().map(|(i, &g)| (g, i as u16)).collect();
+
+        let mut glyf = Vec::new();
+        let mut loca = Vec::new();
+        let mut hmtx = Vec::new();
+        let hmtx_start = self.tables[b"hmtx"].start;
+        for &glyph in &keep {
+            loca.extend_from_slice(&(glyf.len() as u32).to_be_bytes());
+            let mut data = self.glyph_range(glyph).map(|r| self.data[r].to_vec()).unwrap_or_default();
+            for (at, component) in self.components(glyph) {
+                let id = glyph_ids.get(&component).copied().unwrap_or(0);
+                data[at..at + 2].copy_from_slice(&id.to_be_bytes());
+            }
+            glyf.extend_from_slice(&data);
+            glyf.resize(glyf.len().next_multiple_of(4), 0);
+
+            let metric = glyph.min(self.num_h_metrics - 1) as usize;
+            let advance = u16_at(&self.data, hmtx_start + metric * 4).unwrap_or(0);
+            let lsb = if (glyph as usize) < self.num_h_metrics as usize {
+                u16_at(&self.data, hmtx_start + glyph as usize * 4 + 2)
+            } else {
+                u16_at(&self.data, hmtx_start + self.num_h_metrics as usize * 4 + (glyph - self.num_h_metrics) as usize * 2)
+            };
+            hmtx.extend_from_slice(&advance.to_be_bytes());
+            hmtx.extend_from_slice(&lsb.unwrap_or(0).to_be_bytes());
+        }
+        loca.extend_from_slice(&(glyf.len() as u32).to_be_bytes());
+
+        let count = (keep.len() as u16).to_be_bytes();
+        let mut head = self.table(b"head").to_vec();
+        head[8..12].fill(0);
+        head[50..52].copy_from_slice(&1u16.to_be_bytes());
+        let mut hhea = self.table(b"hhea").to_vec();
+        hhea[34..36].copy_from_slice(&count);
+        let mut maxp = self.table(b"maxp").to_vec();
+        maxp[4..6].copy_from_slice(&count);
+
+        let mut tables: Vec<([u8; 4], Vec<u8>)> = vec![
+            (*b"glyf", glyf),
+            (*b"head", head),
+            (*b"hhea", hhea),
+            (*b"hmtx", hmtx),
+            (*b"loca", loca),
+            (*b"maxp", maxp),
+        ];
+        for tag in HINTING_TABLES {
+            if self.tables.contains_key(tag) {
+                tables.push((*tag, self.table(tag).to_vec()));
+            }
+        }
+        Ok(FontSubset {
+            data: write_font(tables),
+            glyph_ids,
+        })
+    }
+
+    fn table(&self, tag: &[u8; 4]) -> &[u8] {
+        &self.data[self.tables[tag].clone()]
+    }
+
+    fn scale(&self, units: f32) -> f32 {
+        units * 1000.0 / self.units_per_em as f32
+    }
+
+    /// Byte range of `glyph`'s outline, or `None` for an empty glyph.
+    fn glyph_range(&self, glyph: u16) -> Option<Range<usize>> {
+        let loca = self.tables[b"loca"].start;
+        let glyf = &self.tables[b"glyf"];
+        let (start, end) = if self.long_loca {
+            let at = loca + glyph as usize * 4;
+            (u32_at(&self.data, at)? as usize, u32_at(&self.data, at + 4)? as usize)
+        } else {
+            let at = loca + glyph as usize * 2;
+            (u16_at(&self.data, at)? as usize * 2, u16_at(&self.data, at + 2)? as usize * 2)
+        };
+        (start < end && glyf.start + end <= glyf.end).then(|| glyf.start + start..glyf.start + end)
+    }
+
+    /// Components of a composite glyph: the offset of each glyph id within
+    /// the glyph's data, and the id.
+    fn components(&self, glyph: u16) -> Vec<(usize, u16)> {
+        let Some(range) = self.glyph_range(glyph) else {
+            return Vec::new();
+        };
+        let data = &self.data[range];
+        if i16_at(data, 0).is_none_or(|contours| contours >= 0) {
+            return Vec::new();
+        }
+        let mut components = Vec::new();
+        let mut pos = 10;
+        while let (Some(flags), Some(id)) = (u16_at(data, pos), u16_at(data, pos + 2)) {
+            components.push((pos + 2, id));
+            pos += 4 + if flags & ARG_1_AND_2_ARE_WORDS != 0 { 4 } else { 2 };
+            pos += if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+                8
+            } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+                4
+            } else if flags & WE_HAVE_A_SCALE != 0 {
+                2
+            } else {
+                0
+            };
+            if flags & MORE_COMPONENTS == 0 {
+                break;
+            }
+        }
+        components
+    }
+}
+
+/// The best Unicode subtable of a `cmap` table: full-repertoire format 12,
+/// else BMP format 4.
+fn unicode_cmap(data: &[u8], cmap: Range<usize>) -> Option<(u16, Range<usize>)> {
+    let count = u16_at(data, cmap.start + 2)? as usize;
+    let mut best: Option<(u8, u16, Range<usize>)> = None;
+    for i in 0..count {
+        let record = cmap.start + 4 + i * 8;
+        let platform = u16_at(data, record)?;
+        let encoding = u16_at(data, record + 2)?;
+        let offset = cmap.start + u32_at(data, record + 4)? as usize;
+        let format = u16_at(data, offset)?;
+        let length = match format {
+            4 => u16_at(data, offset + 2)? as usize,
+            12 => u32_at(data, offset + 4)? as usize,
+            _ => continue,
+        };
+        let rank = match (platform, encoding, format) {
+            (3, 10, 12) | (0, _, 12) => 2,
+            (3, 1, 4) | (0, _, 4) => 1,
+            _ => continue,
+        };
+        if offset + length <= cmap.end && best.as_ref().is_none_or(|b| rank > b.0) {
+            best = Some((rank, format, offset..offset + length));
+        }
+    }
+    best.map(|(_, format, range)| (format, range))
+}
+
+/// Assemble an sfnt file from its tables, with checksums and the head
+/// table's checksum adjustment filled in.
+fn write_font(mut tables: Vec<([u8; 4], Vec<u8>)>) -> Vec<u8> {
+    tables.sort_by_key(|t| t.0);
+    let count = tables.len() as u16;
+    let entry_selector = 15 - count.leading_zeros() as u16;
+    let search_range = (1u16 << entry_selector) * 16;
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+    for value in [count, search_range, entry_selector, count * 16 - search_range] {
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+    let mut offset = 12 + tables.len() * 16;
+    for (tag, data) in &tables {
+        out.extend_from_slice(tag);
+        out.extend_from_slice(&checksum(data).to_be_bytes());
+        out.extend_from_slice(&(offset as u32).to_be_bytes());
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += data.len().next_multiple_of(4);
+    }
+    let mut head_at = None;
+    for (tag, data) in &tables {
+        if tag == b"head" {
+            head_at = Some(out.len());
+        }
+        out.extend_from_slice(data);
+        out.resize(out.len().next_multiple_of(4), 0);
+    }
+    if let Some(at) = head_at.filter(|at| at + 12 <= out.len()) {
+        let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&out));
+        out[at + 8..at + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    out
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+fn u16_at(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn i16_at(data: &[u8], pos: usize) -> Option<i16> {
+    u16_at(data, pos).map(|v| v as i16)
+}
+
+fn u32_at(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+/// A small font for tests: glyph 1 is a simple glyph for 'Ω' (advance 600
+/// of 1000 units), glyph 2 a composite of glyph 1 for 'あ' (advance 1000).
+#[cfg(test)]
+pub(crate) fn test_font() -> Vec<u8> {
+    let mut head = vec![0u8; 54];
+    head[0..4].copy_from_slice(&0x0001_0000u32.to_be_bytes());
+    head[12..16].copy_from_slice(&0x5F0F_3CF5u32.to_be_bytes());
+    head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+    head[36..44].copy_from_slice(&[0xFF, 0x38, 0xFF, 0x06, 0x03, 0xE8, 0x03, 0x84]);
+    head[50..52].copy_from_slice(&0u16.to_be_bytes());
+
+    let mut hhea = vec![0u8; 36];
+    hhea[0..4].copy_from_slice(&0x0001_0000u32.to_be_bytes());
+    hhea[4..6].copy_from_slice(&800i16.to_be_bytes());
+    hhea[6..8].copy_from_slice(&(-200i16).to_be_bytes());
+    hhea[34..36].copy_from_slice(&3u16.to_be_bytes());
+
+    let mut maxp = vec![0u8; 6];
+    maxp[0..4].copy_from_slice(&0x0000_5000u32.to_be_bytes());
+    maxp[4..6].copy_from_slice(&3u16.to_be_bytes());
+
+    let hmtx: Vec<u8> = [(500u16, 0u16), (600, 50), (1000, 0)]
+        .iter()
+        .flat_map(|(advance, lsb)| [advance.to_be_bytes(), lsb.to_be_bytes()].concat())
+        .collect();
+
+    // A one-point simple glyph, and a composite placing it at (10, 20)
+    let simple: Vec<u8> = vec![0, 1, 0, 0, 0, 0, 0, 10, 0, 10, 0, 0, 0, 0, 0x37, 10, 10, 0];
+    let composite: Vec<u8> = vec![0xFF, 0xFF, 0, 0, 0, 0, 0, 10, 0, 10, 0x00, 0x02, 0, 1, 10, 20];
+    let glyf = [simple.clone(), composite.clone()].concat();
+    let loca: Vec<u8> = [0u16, 0, (simple.len() / 2) as u16, (glyf.len() / 2) as u16]
+        .iter()
+        .flat_map(|v| v.to_be_bytes())
+        .collect();
+
+    // Format 4: 'Ω' (U+03A9) and 'あ' (U+3042), then the final 0xFFFF segment
+    let segments: [(u16, u16, u16); 3] = [
+        (0x03A9, 0x03A9, 1u16.wrapping_sub(0x03A9)),
+        (0x3042, 0x3042, 2u16.wrapping_sub(0x3042)),
+        (0xFFFF, 0xFFFF, 1),
+    ];
+    let mut subtable = vec![0, 4, 0, 0, 0, 0, 0, 6, 0, 4, 0, 1, 0, 2];
+    subtable.extend(segments.iter().flat_map(|s| s.1.to_be_bytes()));
+    subtable.extend_from_slice(&[0, 0]);
+    subtable.extend(segments.iter().flat_map(|s| s.0.to_be_bytes()));
+    subtable.extend(segments.iter().flat_map(|s| s.2.to_be_bytes()));
+    subtable.extend(segments.iter().flat_map(|_| 0u16.to_be_bytes()));
+    let length = subtable.len() as u16;
+    subtable[2..4].copy_from_slice(&length.to_be_bytes());
+    let mut cmap = vec![0, 0, 0, 1, 0, 3, 0, 1, 0, 0, 0, 12];
+    cmap.extend(subtable);
+
+    write_font(vec![
+        (*b"cmap", cmap),
+        (*b"glyf", glyf),
+        (*b"head", head),
+        (*b"hhea", hhea),
+        (*b"hmtx", hmtx),
+        (*b"loca", loca),
+        (*b"maxp", maxp),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_font() {
+        let font = TrueTypeFont::parse(test_font()).unwrap();
+        assert_eq!(font.glyph_index('Ω'), Some(1));
+        assert_eq!(font.glyph_index('あ'), Some(2));
+        assert_eq!(font.glyph_index('a'), None);
+        assert_eq!(font.glyph_index('😀'), None);
+        assert_eq!(font.advance(1), 600.0);
+        assert_eq!(font.advance(2), 1000.0);
+        assert!(font.embeddable());
+        let metrics = font.metrics();
+        assert_eq!((metrics.ascent, metrics.descent), (800.0, -200.0));
+        assert_eq!(metrics.bbox, [-200.0, -250.0, 1000.0, 900.0]);
+
+        assert!(TrueTypeFont::parse(b"OTTO\0\0\0\0".to_vec()).is_err());
+        assert!(TrueTypeFont::parse(b"not a font".to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_subset_renumbers_glyphs() {
+        let font = TrueTypeFont::parse(test_font()).unwrap();
+        // The composite pulls in the glyph it is built from
+        let subset = font.subset(&[2]).unwrap();
+        assert_eq!(subset.glyph_ids, HashMap::from([(0, 0), (1, 1), (2, 2)]));
+
+        let only_simple = font.subset(&[1]).unwrap();
+        assert_eq!(only_simple.glyph_ids, HashMap::from([(0, 0), (1, 1)]));
+        assert_eq!(checksum(&only_simple.data), 0xB1B0_AFBA);
+
+        // The subset is itself a font, without a character map
+        let data = subset.data;
+        let count = u16_at(&data, 4).unwrap() as usize;
+        let tags: Vec<String> = (0..count)
+            .map(|i| String::from_utf8_lossy(&data[12 + i * 16..16 + i * 16]).to_string())
+            .collect();
+        assert_eq!(tags, vec!["glyf", "head", "hhea", "hmtx", "loca", "maxp"]);
+    }
+}
@@ -0,0 +1,345 @@
+use crate::document::image::Pixels;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Order the code length code's own lengths are stored in.
+const CODE_LENGTH_ORDER: [usize; 19] = [17, 18, 0, 1, 2, 3, 4, 5, 16, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+
+/// Predictor blocks are 1 << PREDICTOR_BITS pixels square.
+const PREDICTOR_BITS: u32 = 4;
+
+/// Green (with its 24 length prefixes), red, blue, alpha and distance.
+const ALPHABET_SIZES: [usize; 5] = [256 + 24, 256, 256, 256, 40];
+
+/// Writes bits least significant first, as VP8L is read.
+struct BitWriter {
+    out: Vec<u8>,
+    bits: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, len: u32) {
+        self.bits |= (value as u64) << self.count;
+        self.count += len;
+        while self.count >= 8 {
+            self.out.push(self.bits as u8);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.out.push(self.bits as u8);
+        }
+        self.out
+    }
+}
+
+/// A canonical prefix code. A code with a single symbol takes no bits.
+struct PrefixCode {
+    lengths: Vec<u8>,
+    /// Codes, bit-reversed for writing.
+    codes: Vec<u32>,
+    single: bool,
+}
+
+impl PrefixCode {
+    fn new(counts: &[u32], limit: u8) -> PrefixCode {
+        let lengths = code_lengths(counts, limit);
+        let single = lengths.iter().filter(|&&l| l > 0).count() <= 1;
+        let mut codes = vec![0; lengths.len()];
+        let mut code = 0u32;
+        for len in 1..=limit {
+            for (symbol, _) in lengths.iter().enumerate().filter(|(_, &l)| l == len) {
+                codes[symbol] = code.reverse_bits() >> (32 - len as u32);
+                code += 1;
+            }
+            code <<= 1;
+        }
+        PrefixCode { lengths, codes, single }
+    }
+
+    fn write_symbol(&self, writer: &mut BitWriter, symbol: usize) {
+        if !self.single {
+            writer.write(self.codes[symbol], self.lengths[symbol] as u32);
+        }
+    }
+
+    /// Write the code's description: as a "simple" code when it has at most
+    /// two symbols below 256, otherwise as run-length coded code lengths.
+    fn write_header(&self, writer: &mut BitWriter) {
+        let used: Vec<usize> = (0..self.lengths.len()).filter(|&s| self.lengths[s] > 0).collect();
+        if used.len() <= 2 && used.iter().all(|&s| s < 256) {
+            writer.write(1, 1);
+            let first = used.first().copied().unwrap_or(0);
+            writer.write(used.len().saturating_sub(1) as u32, 1);
+            if first < 2 {
+                writer.write(0, 1);
+                writer.write(first as u32, 1);
+            } else {
+                writer.write(1, 1);
+                writer.write(first as u32, 8);
+            }
+            if let Some(&second) = used.get(1) {
+                writer.write(second as u32, 8);
+            }
+            return;
+        }
+
+        let tokens = length_tokens(&self.lengths);
+        let mut counts = [0u32; 19];
+        for &(token, _) in &tokens {
+            counts[token as usize] += 1;
+        }
+        let length_code = PrefixCode::new(&counts, 7);
+        let stored = CODE_LENGTH_ORDER
+            .iter()
+            .rposition(|&i| length_code.lengths[i] > 0)
+            .map_or(4, |last| (last + 1).max(4));
+        writer.write(0, 1);
+        writer.write(stored as u32 - 4, 4);
+        for &i in &CODE_LENGTH_ORDER[..stored] {
+            writer.write(length_code.lengths[i] as u32, 3);
+        }
+        // No limit on the number of lengths read
+        writer.write(0, 1);
+        for (token, extra) in tokens {
+            length_code.write_symbol(writer, token as usize);
+            match token {
+                16 => writer.write(extra, 2),
+                17 => writer.write(extra, 3),
+                18 => writer.write(extra, 7),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Code lengths for symbol counts, no longer than `limit`. Counts are
+/// flattened until the Huffman tree fits.
+fn code_lengths(counts: &[u32], limit: u8) -> Vec<u8> {
+    let mut lengths = vec![0u8; counts.len()];
+    let used: Vec<usize> = (0..counts.len()).filter(|&s| counts[s] > 0).collect();
+    if used.len() == 1 {
+        lengths[used[0]] = 1;
+    }
+    if used.len() < 2 {
+        return lengths;
+    }
+    let mut weights: Vec<u64> = used.iter().map(|&s| counts[s] as u64).collect();
+    loop {
+        // Nodes after the leaves are internal; parents[i] links upwards
+        let mut parents = vec![usize::MAX; used.len() * 2 - 1];
+        let mut heap: BinaryHeap<Reverse<(u64, usize)>> =
+            weights.iter().enumerate().map(|(i, &w)| Reverse((w, i))).collect();
+        let mut next = used.len();
+        while heap.len() > 1 {
+            let Reverse((w1, a)) = heap.pop().unwrap();
+            let Reverse((w2, b)) = heap.pop().unwrap();
+            parents[a] = next;
+            parents[b] = next;
+            heap.push(Reverse((w1 + w2, next)));
+            next += 1;
+        }
+        let depth = |mut node: usize| {
+            let mut depth = 0;
+            while parents[node] != usize::MAX {
+                node = parents[node];
+                depth += 1;
+            }
+            depth
+        };
+        let depths: Vec<u8> = (0..used.len()).map(depth).collect();
+        if depths.iter().all(|&d| d <= limit) {
+            for (i, &symbol) in used.iter().enumerate() {
+                lengths[symbol] = depths[i];
+            }
+            return lengths;
+        }
+        for weight in &mut weights {
+            *weight = (*weight / 2).max(1);
+        }
+    }
+}
+
+/// Code lengths as code length code symbols with their extra bits: 0–15
+/// literally, 16 to repeat the previous length 3–6 times, 17 and 18 for
+/// 3–10 and 11–138 zeros.
+fn length_tokens(lengths: &[u8]) -> Vec<(u8, u32)> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < lengths.len() {
+        let length = lengths[i];
+        let run = lengths[i..].iter().take_while(|&&l| l == length).count();
+        if length == 0 && run >= 3 {
+            let run = run.min(138);
+            if run >= 11 {
+                tokens.push((18, (run - 11) as u32));
+            } else {
+                tokens.push((17, (run - 3) as u32));
+            }
+            i += run;
+            continue;
+        }
+        tokens.push((length, 0));
+        i += 1;
+        let mut repeats = run - 1;
+        if length == 0 {
+            continue;
+        }
+        while repeats >= 3 {
+            let n = repeats.min(6);
+            tokens.push((16, (n - 3) as u32));
+            repeats -= n;
+        }
+        for _ in 0..repeats {
+            tokens.push((length, 0));
+        }
+        i += run - 1;
+    }
+    tokens
+}
+
+/// Write an image of ARGB pixels with no color cache or backward references,
+/// one prefix code per channel.
+fn write_image(writer: &mut BitWriter, argb: &[[u8; 4]]) {
+    let mut counts: Vec<Vec<u32>> = ALPHABET_SIZES.iter().map(|&size| vec![0; size]).collect();
+    for &[a, r, g, b] in argb {
+        counts[0][g as usize] += 1;
+        counts[1][r as usize] += 1;
+        counts[2][b as usize] += 1;
+        counts[3][a as usize] += 1;
+    }
+    let codes: Vec<PrefixCode> = counts.iter().map(|c| PrefixCode::new(c, 15)).collect();
+    for code in &codes {
+        code.write_header(writer);
+    }
+    for &[a, r, g, b] in argb {
+        codes[0].write_symbol(writer, g as usize);
+        codes[1].write_symbol(writer, r as usize);
+        codes[2].write_symbol(writer, b as usize);
+        codes[3].write_symbol(writer, a as usize);
+    }
+}
+
+fn average([a0, r0, g0, b0]: [u8; 4], [a1, r1, g1, b1]: [u8; 4]) -> [u8; 4] {
+    let avg = |x: u8, y: u8| ((x as u16 + y as u16) / 2) as u8;
+    [avg(a0, a1), avg(r0, r1), avg(g0, g1), avg(b0, b1)]
+}
+
+/// The prediction of predictor `mode` (1–10) from the left, top, top-right
+/// and top-left neighbours.
+fn predict(mode: u8, left: [u8; 4], top: [u8; 4], top_right: [u8; 4], top_left: [u8; 4]) -> [u8; 4] {
+    match mode {
+        1 => left,
+        2 => top,
+        3 => top_right,
+        4 => top_left,
+        5 => average(average(left, top_right), top),
+        6 => average(left, top_left),
+        7 => average(left, top),
+        8 => average(top_left, top),
+        9 => average(top, top_right),
+        _ => average(average(left, top_left), average(top, top_right)),
+    }
+}
+
+fn subtract([a0, r0, g0, b0]: [u8; 4], [a1, r1, g1, b1]: [u8; 4]) -> [u8; 4] {
+    [a0.wrapping_sub(a1), r0.wrapping_sub(r1), g0.wrapping_sub(g1), b0.wrapping_sub(b1)]
+}
+
+/// Encode pixels as a lossless WebP, using the subtract-green and predictor
+/// transforms.
+pub fn encode_webp_lossless(pixels: &Pixels) -> Result<Vec<u8>, String> {
+    let (width, height) = (pixels.width as usize, pixels.height as usize);
+    if width == 0 || height == 0 || width > 16384 || height > 16384 {
+        return Err("Image size is out of range for WebP".to_string());
+    }
+    // ARGB with green subtracted from red and blue
+    let image: Vec<[u8; 4]> = pixels
+        .rgba
+        .chunks(4)
+        .map(|p| [p[3], p[0].wrapping_sub(p[1]), p[1], p[2].wrapping_sub(p[1])])
+        .collect();
+
+    let neighbours = |x: usize, y: usize| {
+        let i = y * width + x;
+        (image[i - 1], image[i - width], image[i - width + 1], image[i - width - 1])
+    };
+    let block = 1 << PREDICTOR_BITS;
+    let (blocks_w, blocks_h) = (width.div_ceil(block), height.div_ceil(block));
+    let mut modes = vec![0u8; blocks_w * blocks_h];
+    for by in 0..blocks_h {
+        for bx in 0..blocks_w {
+            let mut best = (u64::MAX, 1);
+            for mode in 1..=10 {
+                let mut cost = 0u64;
+                for y in (by * block).max(1)..((by + 1) * block).min(height) {
+                    for x in (bx * block).max(1)..((bx + 1) * block).min(width) {
+                        let (left, top, top_right, top_left) = neighbours(x, y);
+                        let residual = subtract(image[y * width + x], predict(mode, left, top, top_right, top_left));
+                        cost += residual.iter().map(|&v| (v as i8).unsigned_abs() as u64).sum::<u64>();
+                    }
+                }
+                if cost < best.0 {
+                    best = (cost, mode);
+                }
+            }
+            modes[by * blocks_w + bx] = best.1;
+        }
+    }
+
+    let mut residuals = Vec::with_capacity(image.len());
+    for y in 0..height {
+        for x in 0..width {
+            let prediction = if x == 0 && y == 0 {
+                [255, 0, 0, 0]
+            } else if y == 0 {
+                image[x - 1]
+            } else if x == 0 {
+                image[(y - 1) * width]
+            } else {
+                let (left, top, top_right, top_left) = neighbours(x, y);
+                let mode = modes[(y >> PREDICTOR_BITS) * blocks_w + (x >> PREDICTOR_BITS)];
+                predict(mode, left, top, top_right, top_left)
+            };
+            residuals.push(subtract(image[y * width + x], prediction));
+        }
+    }
+
+    let mut writer = BitWriter { out: Vec::new(), bits: 0, count: 0 };
+    writer.write(0x2F, 8);
+    writer.write(width as u32 - 1, 14);
+    writer.write(height as u32 - 1, 14);
+    writer.write(u32::from(pixels.has_alpha()), 1);
+    writer.write(0, 3);
+    // Transforms are listed in the order they were applied
+    writer.write(1, 1);
+    writer.write(2, 2);
+    writer.write(1, 1);
+    writer.write(0, 2);
+    writer.write(PREDICTOR_BITS - 2, 3);
+    writer.write(0, 1);
+    let mode_image: Vec<[u8; 4]> = modes.iter().map(|&mode| [255, 0, mode, 0]).collect();
+    write_image(&mut writer, &mode_image);
+    writer.write(0, 1);
+    // No color cache, one set of prefix codes for the whole image
+    writer.write(0, 1);
+    writer.write(0, 1);
+    write_image(&mut writer, &residuals);
+    let data = writer.finish();
+
+    let mut out = Vec::with_capacity(data.len() + 21);
+    let padded = data.len() + data.len() % 2;
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&((12 + padded) as u32).to_le_bytes());
+    out.extend_from_slice(b"WEBPVP8L");
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(&data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+    Ok(out)
+}
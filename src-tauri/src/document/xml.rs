@@ -0,0 +1,249 @@
+use std::collections::VecDeque;
+use std::io::BufRead;
+
+#[derive(Debug, PartialEq)]
+pub enum XmlEvent {
+    Start { name: String, attrs: Vec<(String, String)> },
+    End(String),
+    /// Character data with entities resolved, including CDATA sections.
+    Text(String),
+}
+
+/// Pull parser for the XML that note exports use. It reads from any
+/// `BufRead`, so large exports are never held as a tree. Empty elements
+/// produce a start and an end event; comments, processing instructions and
+/// the doctype are skipped. Well-formedness is not checked.
+pub struct XmlReader<R: BufRead> {
+    reader: R,
+    pending: VecDeque<XmlEvent>,
+}
+
+impl<R: BufRead> XmlReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn next_event(&mut self) -> Result<Option<XmlEvent>, String> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(Some(event));
+            }
+            let mut text = Vec::new();
+            self.reader.read_until(b'<', &mut text).map_err(|e| e.to_string())?;
+            let at_tag = text.last() == Some(&b'<');
+            if at_tag {
+                text.pop();
+                self.read_tag()?;
+            }
+            if !text.is_empty() {
+                return Ok(Some(XmlEvent::Text(unescape(&String::from_utf8_lossy(&text)))));
+            }
+            if !at_tag {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Read everything up to the end of the element just started, as text.
+    /// Nested elements are dropped.
+    pub fn read_text(&mut self) -> Result<String, String> {
+        let mut text = String::new();
+        let mut depth = 0;
+        while let Some(event) = self.next_event()? {
+            match event {
+                XmlEvent::Start { .. } => depth += 1,
+                XmlEvent::End(_) if depth == 0 => break,
+                XmlEvent::End(_) => depth -= 1,
+                XmlEvent::Text(t) => text.push_str(&t),
+            }
+        }
+        Ok(text)
+    }
+
+    /// Read the markup after a `<` and queue its events.
+    fn read_tag(&mut self) -> Result<(), String> {
+        let mut tag = Vec::new();
+        loop {
+            let before = tag.len();
+            self.reader.read_until(b'>', &mut tag).map_err(|e| e.to_string())?;
+            if tag.len() == before {
+                return Err("Unexpected end of XML".to_string());
+            }
+            let complete = if tag.starts_with(b"!--") {
+                tag.ends_with(b"-->")
+            } else if tag.starts_with(b"![CDATA[") {
+                tag.ends_with(b"]]>")
+            } else if tag.starts_with(b"!") {
+                // A doctype may have an internal subset in brackets
+                !tag.contains(&b'[') || tag.ends_with(b"]>")
+            } else {
+                // `>` can appear in a quoted attribute value
+                let quotes = |q: u8| tag.iter().filter(|&&b| b == q).count();
+                quotes(b'"') % 2 == 0 && quotes(b'\'') % 2 == 0
+            };
+            if complete {
+                break;
+            }
+        }
+        tag.pop();
+
+        if let Some(data) = tag.strip_prefix(b"![CDATA[") {
+            let data = &data[..data.len() - 2];
+            self.pending.push_back(XmlEvent::Text(String::from_utf8_lossy(data).to_string()));
+            return Ok(());
+        }
+        if tag.starts_with(b"!") || tag.starts_with(b"?") {
+            return Ok(());
+        }
+        let tag = String::from_utf8_lossy(&tag);
+        if let Some(name) = tag.strip_prefix('/') {
+            self.pending.push_back(XmlEvent::End(name.trim().to_string()));
+            return Ok(());
+        }
+
+        let (tag, empty) = match tag.strip_suffix('/') {
+            Some(tag) => (tag, true),
+            None => (tag.as_ref(), false),
+        };
+        let name_end = tag.find(|c: char| c.is_whitespace()).unwrap_or(tag.len());
+        let name = tag[..name_end].to_string();
+        let attrs = parse_attrs(&tag[name_end..]);
+        self.pending.push_back(XmlEvent::Start { name: name.clone(), attrs });
+        if empty {
+            self.pending.push_back(XmlEvent::End(name));
+        }
+        Ok(())
+    }
+}
+
+/// Attributes of a start tag. Values may also be unquoted, or missing as
+/// in HTML's `<input disabled>`.
+fn parse_attrs(s: &str) -> Vec<(String, String)> {
+    let mut attrs = Vec::new();
+    let mut rest = s.trim_start();
+    while !rest.is_empty() {
+        let name_end = rest.find(|c: char| c.is_whitespace() || c == '=').unwrap_or(rest.len());
+        let name = rest[..name_end].to_string();
+        rest = rest[name_end..].trim_start();
+        let Some(value_start) = rest.strip_prefix('=').map(str::trim_start) else {
+            if !name.is_empty() {
+                attrs.push((name, String::new()));
+            }
+            if name_end == 0 {
+                // A stray `"` or similar; skip it
+                rest = rest.get(1..).unwrap_or("").trim_start();
+            }
+            continue;
+        };
+        let (value, after) = match value_start.chars().next() {
+            Some(quote @ ('"' | '\'')) => match value_start[1..].find(quote) {
+                Some(end) => (&value_start[1..1 + end], &value_start[end + 2..]),
+                None => (&value_start[1..], ""),
+            },
+            _ => {
+                let end = value_start.find(char::is_whitespace).unwrap_or(value_start.len());
+                (&value_start[..end], &value_start[end..])
+            }
+        };
+        attrs.push((name, unescape(value)));
+        rest = after.trim_start();
+    }
+    attrs
+}
+
+/// Resolve character references and the entities HTML-like exports use.
+pub fn unescape(s: &str) -> String {
+    if !s.contains('&') {
+        return s.to_string();
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semi) = rest.find(';').filter(|&i| i <= 10) else {
+            out.push('&');
+            rest = &rest[1..];
+            continue;
+        };
+        let entity = &rest[1..semi];
+        let c = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            // Named references common in web pages
+            "ndash" => Some('–'),
+            "mdash" => Some('—'),
+            "hellip" => Some('…'),
+            "lsquo" => Some('‘'),
+            "rsquo" => Some('’'),
+            "ldquo" => Some('“'),
+            "rdquo" => Some('”'),
+            "laquo" => Some('«'),
+            "raquo" => Some('»'),
+            "middot" => Some('·'),
+            "bull" => Some('•'),
+            "copy" => Some('©'),
+            "reg" => Some('®'),
+            "trade" => Some('™'),
+            "times" => Some('×'),
+            "deg" => Some('°'),
+            "euro" => Some('€'),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xml_reader() {
+        let xml = "<?xml version=\"1.0\"?>\n<!DOCTYPE en-export SYSTEM \"x.dtd\">\
+                   <a x=\"1 &amp; 2\" y='>'><!-- note --><b/>t&lt;&#233;<![CDATA[<p>raw</p>]]></a>";
+        let mut reader = XmlReader::new(xml.as_bytes());
+        let mut events = Vec::new();
+        while let Some(event) = reader.next_event().unwrap() {
+            events.push(event);
+        }
+        assert_eq!(
+            events,
+            vec![
+                XmlEvent::Text("\n".to_string()),
+                XmlEvent::Start {
+                    name: "a".to_string(),
+                    attrs: vec![("x".to_string(), "1 & 2".to_string()), ("y".to_string(), ">".to_string())],
+                },
+                XmlEvent::Start { name: "b".to_string(), attrs: vec![] },
+                XmlEvent::End("b".to_string()),
+                XmlEvent::Text("t<é".to_string()),
+                XmlEvent::Text("<p>raw</p>".to_string()),
+                XmlEvent::End("a".to_string()),
+            ]
+        );
+    }
+}
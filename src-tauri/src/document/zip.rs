@@ -0,0 +1,165 @@
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+struct Entry {
+    name: String,
+    offset: u32,
+    crc: u32,
+    compressed_size: u32,
+    size: u32,
+    method: u16,
+}
+
+/// Minimal zip archive writer, enough for EPUB containers: files are
+/// deflated unless added with [`ZipWriter::add_stored`].
+#[derive(Default)]
+pub struct ZipWriter {
+    out: Vec<u8>,
+    entries: Vec<Entry>,
+}
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file without compression (EPUB requires this for `mimetype`).
+    pub fn add_stored(&mut self, name: &str, data: &[u8]) {
+        self.add_entry(name, data, data.to_vec(), 0);
+    }
+
+    pub fn add(&mut self, name: &str, data: &[u8]) -> Result<(), String> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).map_err(|e| e.to_string())?;
+        let compressed = encoder.finish().map_err(|e| e.to_string())?;
+        self.add_entry(name, data, compressed, 8);
+        Ok(())
+    }
+
+    fn add_entry(&mut self, name: &str, data: &[u8], stored: Vec<u8>, method: u16) {
+        let entry = Entry {
+            name: name.to_string(),
+            offset: self.out.len() as u32,
+            crc: crc32fast::hash(data),
+            compressed_size: stored.len() as u32,
+            size: data.len() as u32,
+            method,
+        };
+
+        self.out.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        self.out.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        self.out.extend_from_slice(&0x0800u16.to_le_bytes()); // UTF-8 names
+        self.out.extend_from_slice(&entry.method.to_le_bytes());
+        self.write_time_and_sizes(&entry);
+        self.out.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+        self.out.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+        self.out.extend_from_slice(entry.name.as_bytes());
+        self.out.extend_from_slice(&stored);
+        self.entries.push(entry);
+    }
+
+    fn write_time_and_sizes(&mut self, entry: &Entry) {
+        self.out.extend_from_slice(&0u16.to_le_bytes()); // time
+        self.out.extend_from_slice(&0x0021u16.to_le_bytes()); // date: 1980-01-01
+        self.out.extend_from_slice(&entry.crc.to_le_bytes());
+        self.out.extend_from_slice(&entry.compressed_size.to_le_bytes());
+        self.out.extend_from_slice(&entry.size.to_le_bytes());
+    }
+
+    /// Write the central directory and return the archive.
+    pub fn finish(mut self) -> Vec<u8> {
+        let directory_start = self.out.len() as u32;
+        let entries = std::mem::take(&mut self.entries);
+        for entry in &entries {
+            self.out.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            self.out.extend_from_slice(&20u16.to_le_bytes()); // version made by
+            self.out.extend_from_slice(&20u16.to_le_bytes()); // version needed
+            self.out.extend_from_slice(&0x0800u16.to_le_bytes());
+            self.out.extend_from_slice(&entry.method.to_le_bytes());
+            self.write_time_and_sizes(entry);
+            self.out.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+            self.out.extend_from_slice(&[0; 8]); // extra, comment, disk, internal attrs
+            self.out.extend_from_slice(&0u32.to_le_bytes()); // external attrs
+            self.out.extend_from_slice(&entry.offset.to_le_bytes());
+            self.out.extend_from_slice(entry.name.as_bytes());
+        }
+        let directory_size = self.out.len() as u32 - directory_start;
+
+        self.out.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        self.out.extend_from_slice(&[0; 4]); // disk numbers
+        self.out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        self.out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        self.out.extend_from_slice(&directory_size.to_le_bytes());
+        self.out.extend_from_slice(&directory_start.to_le_bytes());
+        self.out.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        self.out
+    }
+}
+
+/// A file read from a zip archive.
+pub struct ZipEntry {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+fn u16_at(data: &[u8], pos: usize) -> Option<usize> {
+    data.get(pos..pos + 2).map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+}
+
+fn u32_at(data: &[u8], pos: usize) -> Option<usize> {
+    data.get(pos..pos + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+}
+
+/// Read the files of a zip archive, found through its central directory.
+/// Stored and deflated entries are supported; folders are left out, as are
+/// ZIP64 archives.
+pub fn read_zip(data: &[u8]) -> Result<Vec<ZipEntry>, String> {
+    let invalid = || "Not a valid zip archive".to_string();
+    let end = (0..data.len().saturating_sub(21))
+        .rev()
+        .find(|&i| data[i..].starts_with(&0x0605_4b50u32.to_le_bytes()))
+        .ok_or_else(invalid)?;
+    let count = u16_at(data, end + 10).ok_or_else(invalid)?;
+    let mut pos = u32_at(data, end + 16).ok_or_else(invalid)?;
+
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0..count {
+        if !data.get(pos..).is_some_and(|d| d.starts_with(&0x0201_4b50u32.to_le_bytes())) {
+            return Err(invalid());
+        }
+        let method = u16_at(data, pos + 10).ok_or_else(invalid)?;
+        let compressed_size = u32_at(data, pos + 20).ok_or_else(invalid)?;
+        let size = u32_at(data, pos + 24).ok_or_else(invalid)?;
+        let name_len = u16_at(data, pos + 28).ok_or_else(invalid)?;
+        let extra_len = u16_at(data, pos + 30).ok_or_else(invalid)?;
+        let comment_len = u16_at(data, pos + 32).ok_or_else(invalid)?;
+        let offset = u32_at(data, pos + 42).ok_or_else(invalid)?;
+        let name_bytes = data.get(pos + 46..pos + 46 + name_len).ok_or_else(invalid)?;
+        let name = String::from_utf8_lossy(name_bytes).replace('\\', "/");
+        pos += 46 + name_len + extra_len + comment_len;
+        if name.ends_with('/') {
+            continue;
+        }
+
+        // The local header repeats the name and may have its own extra field
+        let local_name_len = u16_at(data, offset + 26).ok_or_else(invalid)?;
+        let local_extra_len = u16_at(data, offset + 28).ok_or_else(invalid)?;
+        let start = offset + 30 + local_name_len + local_extra_len;
+        let stored = data.get(start..start + compressed_size).ok_or_else(invalid)?;
+        let data = match method {
+            0 => stored.to_vec(),
+            8 => {
+                let mut out = Vec::with_capacity(size);
+                DeflateDecoder::new(stored)
+                    .read_to_end(&mut out)
+                    .map_err(|e| format!("Failed to inflate {}: {}", name, e))?;
+                out
+            }
+            other => return Err(format!("Unsupported compression method {} for {}", other, name)),
+        };
+        entries.push(ZipEntry { name, data });
+    }
+    Ok(entries)
+}
@@ -1,5 +1,6 @@
 mod commands;
 mod db;
+mod document;
 mod markdown;
 mod sync;
 
@@ -57,18 +58,67 @@ pub fn run() {
                 log::info!("iCloud not available, skipping initial sync and file watcher");
             }
 
+            // Register untracked images and delete attachments unused past
+            // their grace period, then keep indexing the text of new
+            // attachments and making thumbnails of new images, off the main
+            // thread
+            let gc_handle = app_handle.clone();
+            std::thread::spawn(move || {
+                let Ok(images_dir) = commands::files::images_dir(&gc_handle) else {
+                    return;
+                };
+                let db = gc_handle.state::<Mutex<rusqlite::Connection>>();
+                {
+                    let Ok(conn) = db.lock() else {
+                        return;
+                    };
+                    match commands::attachments::collect_garbage(&conn, &images_dir, None) {
+                        Ok(report) => log::info!(
+                            "Attachments: {} registered, {} removed ({} bytes)",
+                            report.registered, report.removed, report.freed_bytes
+                        ),
+                        Err(e) => log::warn!("Attachment cleanup failed: {}", e),
+                    }
+                }
+                loop {
+                    let indexed = match commands::attachments::index_next_attachment(&db, &images_dir) {
+                        Ok(indexed) => indexed,
+                        Err(e) => {
+                            log::warn!("Attachment indexing failed: {}", e);
+                            false
+                        }
+                    };
+                    let thumbnailed = match commands::images::thumbnail_next_attachment(&db, &images_dir) {
+                        Ok(thumbnailed) => thumbnailed,
+                        Err(e) => {
+                            log::warn!("Thumbnail generation failed: {}", e);
+                            false
+                        }
+                    };
+                    if indexed || thumbnailed {
+                        continue;
+                    }
+                    std::thread::sleep(std::time::Duration::from_secs(30));
+                }
+            });
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             commands::notes::create_note,
             commands::notes::get_note,
             commands::notes::update_note,
+            commands::notes::patch_note,
             commands::notes::delete_note,
             commands::notes::list_notes,
             commands::notes::pin_note,
             commands::notes::trash_note,
             commands::notes::restore_note,
-            commands::notes::import_markdown_files,
+            commands::import::import_markdown_files,
+            commands::clipper::clip_web_page,
+            commands::attachments::list_note_attachments,
+            commands::attachments::get_attachment,
+            commands::attachments::collect_attachment_garbage,
             commands::notes::set_note_state,
             commands::activity::get_activity_feed,
             commands::templates::list_templates,
@@ -84,6 +134,13 @@ pub fn run() {
             commands::tags::pin_tag,
             commands::tags::rename_tag,
             commands::tags::delete_tag,
+            commands::tags::list_tag_changes,
+            commands::tags::undo_tag_change,
+            commands::tags::merge_tags,
+            commands::tags::update_tag_metadata,
+            commands::tags::set_tag_alias,
+            commands::tags::list_tag_aliases,
+            commands::tags::remove_tag_alias,
             commands::search::search_notes,
             commands::sync::trigger_sync,
             commands::sync::get_sync_status,
@@ -94,11 +151,17 @@ pub fn run() {
             commands::workspaces::delete_workspace,
             commands::notes::get_knowledge_graph,
             commands::search::semantic_search,
+            commands::search::set_search_tokenizer,
             commands::settings::get_setting,
             commands::settings::set_setting,
             commands::settings::get_all_settings,
             commands::export::export_note_markdown,
             commands::export::export_note_html,
+            commands::export::export_knowledge_graph,
+            commands::publish::publish_site,
+            commands::documents::export_pdf,
+            commands::documents::export_epub,
+            commands::vault::export_vault,
             commands::files::save_image,
             // Agent commands
             commands::agents::register_agent,
@@ -123,6 +186,21 @@ pub fn run() {
             commands::workflows::get_workflow_template,
             commands::workflows::create_workflow_template,
             commands::workflows::delete_workflow_template,
+            // Property commands
+            commands::properties::define_property,
+            commands::properties::list_property_definitions,
+            commands::properties::delete_property_definition,
+            commands::properties::get_note_properties,
+            commands::properties::set_note_property,
+            // Live query commands
+            commands::queries::run_query,
+            commands::queries::get_note_query_results,
+            // Outline commands
+            commands::outline::get_note_outline,
+            commands::outline::get_note_section,
+            commands::outline::resolve_wiki_link,
+            // Embed commands
+            commands::embeds::render_note_embeds,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,109 @@
+use crate::commands::export::{percent_decode, url_encode};
+use regex::Regex;
+use std::path::Path;
+
+const LOCAL_URL_PATTERN: &str = r#"(asset://localhost/|https?://asset\.localhost/|file://)([^\s()<>"'\]]+)"#;
+
+/// File names of the local files a note refers to: images and links written
+/// by the editor (`asset://` or `http://asset.localhost/` URLs from
+/// `convertFileSrc`), `file://` URLs and plain paths into `images_dir`. Only
+/// the name is kept, so a note synced from another machine still finds its
+/// files in the local store.
+pub fn attachment_file_names(content: &str, images_dir: Option<&Path>) -> Vec<String> {
+    let re = Regex::new(LOCAL_URL_PATTERN).unwrap();
+    let mut paths: Vec<String> = re.captures_iter(content).map(|cap| percent_decode(&cap[2])).collect();
+    if let Some(dir) = images_dir {
+        let dir = dir.to_string_lossy();
+        let pattern = format!(r#"{}[/\\]([^\s()<>"'\]/\\]+)"#, regex::escape(dir.trim_end_matches(['/', '\\'])));
+        if let Ok(plain) = Regex::new(&pattern) {
+            paths.extend(plain.captures_iter(content).map(|cap| cap[1].to_string()));
+        }
+    }
+    let mut names: Vec<String> = Vec::new();
+    for path in paths {
+        let name = path.rsplit(['/', '\\']).next().unwrap_or("").to_string();
+        if !name.is_empty() && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// Point URLs into another machine's images store at the same file in
+/// `images_dir`. Notes embed the absolute path of the device they were
+/// written on, so a note synced from elsewhere would otherwise show none of
+/// its images. A URL is taken to be into a store when its folder is named
+/// `images` or `images_dir` holds a file of that name.
+pub fn localize_attachment_urls(content: &str, images_dir: &Path) -> String {
+    let re = Regex::new(LOCAL_URL_PATTERN).unwrap();
+    re.replace_all(content, |cap: &regex::Captures| {
+        let path = percent_decode(&cap[2]);
+        let mut parts = path.rsplit(['/', '\\']);
+        let name = parts.next().unwrap_or("");
+        let folder = parts.next().unwrap_or("");
+        let local = images_dir.join(name);
+        let is_store = folder == "images" || local.is_file();
+        if name.is_empty() || name == ".." || !is_store || Path::new(&path) == local {
+            return cap[0].to_string();
+        }
+        let local = local.to_string_lossy();
+        if &cap[1] == "file://" {
+            let encoded: Vec<String> = local.split('/').map(url_encode).collect();
+            format!("file://{}", encoded.join("/"))
+        } else {
+            format!("asset://localhost/{}", url_encode(&local))
+        }
+    })
+    .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attachment_file_names() {
+        let content = "![cat](asset://localhost/%2FUsers%2Fme%2Fimages%2Fab12_cat.png)\n\
+            <img src=\"http://asset.localhost/C%3A%5Cdata%5Cimages%5Cdog.jpg\">\n\
+            [paper](file:///tmp/paper.pdf) and again ![](asset://localhost/%2Fother%2Fab12_cat.png)\n\
+            [site](https://example.com/x.png)";
+        assert_eq!(attachment_file_names(content, None), vec!["ab12_cat.png", "dog.jpg", "paper.pdf"]);
+
+        // Plain paths count only inside the images store
+        let content = "![](/data/app/images/ef56_owl.png) ![](/data/app/other/x.png) ![](/data/app/images-old/y.png)";
+        assert_eq!(attachment_file_names(content, Some(Path::new("/data/app/images"))), vec!["ef56_owl.png"]);
+        assert!(attachment_file_names(content, None).is_empty());
+    }
+
+    #[test]
+    fn test_localize_attachment_urls() {
+        let images = std::env::temp_dir().join(format!("bruin-images-{}", uuid::Uuid::new_v4())).join("images");
+        std::fs::create_dir_all(&images).unwrap();
+        std::fs::write(images.join("ab12_cat.png"), b"png").unwrap();
+        let local = |name: &str| format!("asset://localhost/{}", url_encode(&images.join(name).to_string_lossy()));
+
+        // Another Mac's store, by path or by a file we have
+        let content = "![cat](asset://localhost/%2FUsers%2Falice%2FLibrary%2Fbruin%2Fimages%2Fab12_cat.png)\n\
+            ![dog](http://asset.localhost/%2FUsers%2Falice%2Fimages%2Fcd34_dog%20one.jpg)\n\
+            ![cat again](asset://localhost/%2Fvar%2Fmobile%2Fdata%2Fab12_cat.png)";
+        let expected = format!(
+            "![cat]({})\n![dog]({})\n![cat again]({})",
+            local("ab12_cat.png"),
+            local("cd34_dog one.jpg"),
+            local("ab12_cat.png")
+        );
+        assert_eq!(localize_attachment_urls(content, &images), expected);
+        // Already local: unchanged, so syncing twice changes nothing
+        assert_eq!(localize_attachment_urls(&expected, &images), expected);
+
+        let file_url = localize_attachment_urls("[cat](file:///Users/alice/images/ab12_cat.png)", &images);
+        assert_eq!(attachment_file_names(&file_url, None), vec!["ab12_cat.png"]);
+        assert!(file_url.starts_with("[cat](file:///"));
+        assert!(!file_url.contains("alice"));
+
+        // Files outside a store are left alone
+        let other = "![x](asset://localhost/%2FUsers%2Falice%2FDesktop%2Fx.png) [web](https://example.com/images/a.png)";
+        assert_eq!(localize_attachment_urls(other, &images), other);
+        std::fs::remove_dir_all(images.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,137 @@
+use chrono::NaiveDate;
+use pulldown_cmark::{Event, Options, Parser};
+
+const PRIORITIES: &[&str] = &["low", "medium", "high", "urgent"];
+
+/// A `- [ ]` / `- [x]` item in a note, with its inline markers parsed out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChecklistItem {
+    /// Byte range of the `[ ]` / `[x]` marker in the content.
+    pub marker_start: usize,
+    pub marker_end: usize,
+    pub checked: bool,
+    /// Item text with `@due(...)` and `!priority` markers removed.
+    pub title: String,
+    pub due_date: Option<String>,
+    pub priority: Option<String>,
+}
+
+/// Find every task list item in `content`, in document order. Items inside
+/// code blocks are ignored, as are items with no text.
+pub fn extract_checklist(content: &str) -> Vec<ChecklistItem> {
+    let mut items = Vec::new();
+    let parser = Parser::new_ext(content, Options::ENABLE_TASKLISTS).into_offset_iter();
+    for (event, range) in parser {
+        if let Event::TaskListMarker(checked) = event {
+            let rest = &content[range.end..];
+            let line = rest.split('\n').next().unwrap_or("").trim_end_matches('\r');
+            let (title, due_date, priority) = parse_markers(line);
+            if title.is_empty() {
+                continue;
+            }
+            items.push(ChecklistItem {
+                marker_start: range.start,
+                marker_end: range.end,
+                checked,
+                title,
+                due_date,
+                priority,
+            });
+        }
+    }
+    items
+}
+
+/// Split `@due(YYYY-MM-DD)` and `!low` / `!medium` / `!high` / `!urgent`
+/// markers out of an item's text. Markers that don't parse stay in the title.
+fn parse_markers(text: &str) -> (String, Option<String>, Option<String>) {
+    let mut due_date = None;
+    let mut priority = None;
+    let mut words = Vec::new();
+
+    for word in text.split_whitespace() {
+        if let Some(date) = word.strip_prefix("@due(").and_then(|w| w.strip_suffix(')')) {
+            if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok() {
+                due_date = Some(date.to_string());
+                continue;
+            }
+        }
+        if let Some(p) = word.strip_prefix('!') {
+            let p = p.to_lowercase();
+            if PRIORITIES.contains(&p.as_str()) {
+                priority = Some(p);
+                continue;
+            }
+        }
+        words.push(word);
+    }
+
+    (words.join(" "), due_date, priority)
+}
+
+/// Replace the text of `item` in `content` with `title`, keeping its
+/// `@due(...)` and `!priority` markers.
+pub fn set_title(content: &str, item: &ChecklistItem, title: &str) -> String {
+    let rest = &content[item.marker_end..];
+    let line = rest.split('\n').next().unwrap_or("").trim_end_matches('\r');
+    let markers = line.split_whitespace().filter(|word| parse_markers(word).0.is_empty());
+    let text: Vec<&str> = std::iter::once(title).chain(markers).collect();
+    format!(
+        "{} {}{}",
+        &content[..item.marker_end],
+        text.join(" "),
+        &rest[line.len()..]
+    )
+}
+
+/// Tick or untick `item` in `content`.
+pub fn set_checked(content: &str, item: &ChecklistItem, checked: bool) -> String {
+    let marker = if checked { "[x]" } else { "[ ]" };
+    format!(
+        "{}{}{}",
+        &content[..item.marker_start],
+        marker,
+        &content[item.marker_end..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_checklist() {
+        let content = "# Plan\n- [ ] Write spec @due(2026-11-01) !high\n- [x] Kick-off\n  - [ ] nested !LOW\n* [ ] \n\n```\n- [ ] not a task\n```\n1. [X] numbered @due(soon)\n";
+        let items = extract_checklist(content);
+        let titles: Vec<&str> = items.iter().map(|i| i.title.as_str()).collect();
+        assert_eq!(titles, vec!["Write spec", "Kick-off", "nested", "numbered @due(soon)"]);
+        assert_eq!(items[0].due_date.as_deref(), Some("2026-11-01"));
+        assert_eq!(items[0].priority.as_deref(), Some("high"));
+        assert!(!items[0].checked);
+        assert!(items[1].checked);
+        assert_eq!(items[2].priority.as_deref(), Some("low"));
+        assert!(items[3].checked);
+        assert!(items[3].due_date.is_none());
+    }
+
+    #[test]
+    fn test_set_checked() {
+        let content = "- [ ] one\n- [x] two\n";
+        let items = extract_checklist(content);
+        let ticked = set_checked(content, &items[0], true);
+        assert_eq!(ticked, "- [x] one\n- [x] two\n");
+        let items = extract_checklist(&ticked);
+        assert_eq!(set_checked(&ticked, &items[1], false), "- [x] one\n- [ ] two\n");
+    }
+
+    #[test]
+    fn test_set_title() {
+        let content = "- [ ] Write  spec @due(2026-11-01) !high\r\n- [x] two\n";
+        let items = extract_checklist(content);
+        assert_eq!(
+            set_title(content, &items[0], "Write the spec"),
+            "- [ ] Write the spec @due(2026-11-01) !high\r\n- [x] two\n"
+        );
+        assert_eq!(set_title(content, &items[1], "three"), "- [ ] Write  spec @due(2026-11-01) !high\r\n- [x] three\n");
+    }
+}
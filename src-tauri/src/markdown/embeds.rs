@@ -0,0 +1,88 @@
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use regex::Regex;
+use std::ops::Range;
+
+/// A `[[Note]]` link or `![[Note]]` embed in a note.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkSpan {
+    /// Byte range of the whole `[[...]]` or `![[...]]` in the content.
+    pub start: usize,
+    pub end: usize,
+    /// What is between the brackets, e.g. `Note#Section`.
+    pub target: String,
+}
+
+/// Source ranges of text outside code blocks and HTML. Adjacent text events
+/// are joined: the parser splits `![[x]]` on brackets.
+fn text_runs(content: &str) -> Vec<Range<usize>> {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS;
+
+    let mut runs: Vec<Range<usize>> = Vec::new();
+    let mut skip_depth = 0usize;
+    for (event, range) in Parser::new_ext(content, options).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(_) | Tag::HtmlBlock) => skip_depth += 1,
+            Event::End(TagEnd::CodeBlock | TagEnd::HtmlBlock) => {
+                skip_depth = skip_depth.saturating_sub(1)
+            }
+            Event::Text(_) if skip_depth == 0 => match runs.last_mut() {
+                Some(last) if last.end == range.start => last.end = range.end,
+                _ => runs.push(range),
+            },
+            _ => {}
+        }
+    }
+    runs
+}
+
+fn find_spans(content: &str, re: &Regex, embeds: bool) -> Vec<LinkSpan> {
+    let mut spans = Vec::new();
+    for run in text_runs(content) {
+        for cap in re.captures_iter(&content[run.clone()]) {
+            let m = cap.get(0).unwrap();
+            let start = run.start + m.start();
+            let is_embed = start > 0 && content.as_bytes()[start - 1] == b'!';
+            if embeds || !is_embed {
+                spans.push(LinkSpan {
+                    start,
+                    end: run.start + m.end(),
+                    target: cap[1].trim().to_string(),
+                });
+            }
+        }
+    }
+    spans
+}
+
+/// Find every embed in `content`, in document order. Embeds in code blocks,
+/// inline code and HTML are skipped.
+pub fn find_embeds(content: &str) -> Vec<LinkSpan> {
+    find_spans(content, &Regex::new(r"!\[\[([^\[\]\n]+)\]\]").unwrap(), true)
+}
+
+/// Find every `[[wiki link]]` that is not an embed, in document order,
+/// skipping code and HTML like [`find_embeds`].
+pub fn find_wiki_links(content: &str) -> Vec<LinkSpan> {
+    find_spans(content, &Regex::new(r"\[\[([^\[\]\n]+)\]\]").unwrap(), false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_embeds() {
+        let content = "Intro\n\n![[Roster]]\n\nSee ![[Glossary#Terms]] inline, not [[Plain]].\n\n```\n![[In code]]\n```\n`![[inline code]]`\n";
+        let embeds = find_embeds(content);
+        let targets: Vec<&str> = embeds.iter().map(|e| e.target.as_str()).collect();
+        assert_eq!(targets, vec!["Roster", "Glossary#Terms"]);
+        assert_eq!(&content[embeds[0].start..embeds[0].end], "![[Roster]]");
+
+        let links = find_wiki_links(content);
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].target, "Plain");
+    }
+}
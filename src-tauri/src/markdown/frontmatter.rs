@@ -1,7 +1,21 @@
 use crate::db::models::Note;
-use yaml_rust2::{Yaml, YamlLoader};
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::{Yaml, YamlEmitter, YamlLoader};
 
-#[derive(Debug, Clone)]
+/// Frontmatter keys owned by Bruin. Anything else is preserved verbatim in
+/// `FrontmatterData::extra` and written back on export.
+const KNOWN_KEYS: &[&str] = &[
+    "id",
+    "title",
+    "tags",
+    "created_at",
+    "updated_at",
+    "is_pinned",
+    "state",
+    "workspace_id",
+];
+
+#[derive(Debug, Clone, Default)]
 pub struct FrontmatterData {
     pub id: Option<String>,
     pub title: Option<String>,
@@ -9,44 +23,151 @@ pub struct FrontmatterData {
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
     pub is_pinned: bool,
+    pub state: Option<String>,
+    pub workspace_id: Option<String>,
+    /// Unknown keys, as a YAML mapping in their original order.
+    pub extra: Option<String>,
 }
 
 /// Serialize a Note into a markdown string with YAML frontmatter.
 pub fn serialize_frontmatter(note: &Note) -> String {
-    let mut fm = String::new();
-    fm.push_str("---\n");
-    fm.push_str(&format!("id: \"{}\"\n", note.id));
-    fm.push_str(&format!("title: \"{}\"\n", note.title.replace('"', "\\\"")));
-    if !note.tags.is_empty() {
-        fm.push_str("tags:\n");
-        for tag in &note.tags {
-            fm.push_str(&format!("  - \"{}\"\n", tag));
-        }
+    let mut map = Hash::new();
+    insert_str(&mut map, "id", &note.id);
+    insert_str(&mut map, "title", &note.title);
+    insert_tags(&mut map, &note.tags);
+    insert_str(&mut map, "created_at", &note.created_at);
+    insert_str(&mut map, "updated_at", &note.updated_at);
+    map.insert(
+        Yaml::String("is_pinned".to_string()),
+        Yaml::Boolean(note.is_pinned),
+    );
+    insert_str(&mut map, "state", &note.state);
+    if let Some(ref workspace_id) = note.workspace_id {
+        insert_str(&mut map, "workspace_id", workspace_id);
+    }
+    merge_extra(&mut map, note.extra_frontmatter.as_deref());
+
+    let mut out = emit_frontmatter(map);
+    out.push_str(&note.content);
+    out
+}
+
+/// Build the frontmatter for a standalone markdown export: the user-facing
+/// fields plus any preserved custom keys, without Bruin's internal ids.
+pub fn export_frontmatter(note: &Note) -> String {
+    let mut map = Hash::new();
+    insert_str(&mut map, "title", &note.title);
+    insert_str(&mut map, "created_at", &note.created_at);
+    insert_str(&mut map, "updated_at", &note.updated_at);
+    insert_tags(&mut map, &note.tags);
+    insert_str(&mut map, "state", &note.state);
+    merge_extra(&mut map, note.extra_frontmatter.as_deref());
+    emit_frontmatter(map)
+}
+
+/// Emit a YAML mapping between `---` delimiters, ending with a newline.
+/// Strings are quoted and escaped by the emitter wherever YAML needs it.
+pub fn emit_frontmatter(map: Hash) -> String {
+    let mut yaml = String::new();
+    if !map.is_empty() {
+        let mut emitter = YamlEmitter::new(&mut yaml);
+        // Dumping a mapping into a String cannot fail
+        let _ = emitter.dump(&Yaml::Hash(map));
+    }
+    let body = yaml
+        .strip_prefix("---")
+        .unwrap_or(&yaml)
+        .trim_start_matches('\n');
+    if body.is_empty() {
+        "---\n---\n".to_string()
     } else {
-        fm.push_str("tags: []\n");
+        format!("---\n{}\n---\n", body)
+    }
+}
+
+fn insert_str(map: &mut Hash, key: &str, value: &str) {
+    map.insert(
+        Yaml::String(key.to_string()),
+        Yaml::String(value.to_string()),
+    );
+}
+
+fn insert_tags(map: &mut Hash, tags: &[String]) {
+    map.insert(
+        Yaml::String("tags".to_string()),
+        Yaml::Array(tags.iter().map(|t| Yaml::String(t.clone())).collect()),
+    );
+}
+
+/// Append preserved custom keys to `map`, skipping any that would shadow a
+/// key Bruin writes itself.
+fn merge_extra(map: &mut Hash, extra: Option<&str>) {
+    for (key, value) in parse_extra(extra) {
+        if !map.contains_key(&key) && !is_known_key(&key) {
+            map.insert(key, value);
+        }
+    }
+}
+
+fn is_known_key(key: &Yaml) -> bool {
+    key.as_str().is_some_and(is_reserved_key)
+}
+
+/// Whether `key` is a frontmatter key Bruin writes itself and so cannot be
+/// used for a custom property.
+pub fn is_reserved_key(key: &str) -> bool {
+    KNOWN_KEYS.contains(&key)
+}
+
+/// Parse preserved custom keys back into a YAML mapping. Invalid YAML yields
+/// an empty mapping.
+pub fn parse_extra(extra: Option<&str>) -> Hash {
+    extra
+        .and_then(|e| YamlLoader::load_from_str(e).ok())
+        .and_then(|docs| docs.into_iter().next())
+        .and_then(|doc| match doc {
+            Yaml::Hash(map) => Some(map),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+/// Set or remove one custom key, keeping the order of the others. Returns
+/// the new `extra_frontmatter` value, `None` once no custom keys remain.
+pub fn set_extra_value(extra: Option<&str>, key: &str, value: Option<Yaml>) -> Option<String> {
+    let mut map = parse_extra(extra);
+    let key = Yaml::String(key.to_string());
+    match value {
+        Some(value) => {
+            map.insert(key, value);
+        }
+        None => {
+            map.remove(&key);
+        }
+    }
+    if map.is_empty() {
+        return None;
+    }
+    let mut yaml = String::new();
+    let _ = YamlEmitter::new(&mut yaml).dump(&Yaml::Hash(map));
+    Some(yaml.strip_prefix("---\n").unwrap_or(&yaml).to_string())
+}
+
+/// Render a scalar YAML value as a string, so `tags: [2024]` or
+/// `id: 42` are not silently dropped.
+pub fn scalar_to_string(value: &Yaml) -> Option<String> {
+    match value {
+        Yaml::String(s) | Yaml::Real(s) => Some(s.clone()),
+        Yaml::Integer(i) => Some(i.to_string()),
+        Yaml::Boolean(b) => Some(b.to_string()),
+        _ => None,
     }
-    fm.push_str(&format!("created_at: \"{}\"\n", note.created_at));
-    fm.push_str(&format!("updated_at: \"{}\"\n", note.updated_at));
-    fm.push_str(&format!("is_pinned: {}\n", note.is_pinned));
-    fm.push_str("---\n");
-    fm.push_str(&note.content);
-    fm
 }
 
 /// Parse a markdown string with YAML frontmatter into FrontmatterData and body content.
 pub fn parse_frontmatter(content: &str) -> Result<(FrontmatterData, String), String> {
     if !content.starts_with("---") {
-        return Ok((
-            FrontmatterData {
-                id: None,
-                title: None,
-                tags: vec![],
-                created_at: None,
-                updated_at: None,
-                is_pinned: false,
-            },
-            content.to_string(),
-        ));
+        return Ok((FrontmatterData::default(), content.to_string()));
     }
 
     let rest = &content[3..];
@@ -76,45 +197,125 @@ pub fn parse_frontmatter(content: &str) -> Result<(FrontmatterData, String), Str
     let docs = YamlLoader::load_from_str(yaml_str.trim())
         .map_err(|e| format!("YAML parse error: {}", e))?;
 
-    if docs.is_empty() {
-        return Ok((
-            FrontmatterData {
-                id: None,
-                title: None,
-                tags: vec![],
-                created_at: None,
-                updated_at: None,
-                is_pinned: false,
-            },
-            body,
-        ));
-    }
+    let map = match docs.into_iter().next() {
+        Some(Yaml::Hash(map)) => map,
+        _ => return Ok((FrontmatterData::default(), body)),
+    };
 
-    let doc = &docs[0];
+    let get = |key: &str| map.get(&Yaml::String(key.to_string()));
+    let get_str = |key: &str| get(key).and_then(scalar_to_string);
 
-    let id = doc["id"].as_str().map(String::from);
-    let title = doc["title"].as_str().map(String::from);
-    let created_at = doc["created_at"].as_str().map(String::from);
-    let updated_at = doc["updated_at"].as_str().map(String::from);
-    let is_pinned = doc["is_pinned"].as_bool().unwrap_or(false);
+    let tags = match get("tags") {
+        Some(Yaml::Array(arr)) => arr.iter().filter_map(scalar_to_string).collect(),
+        Some(value) => scalar_to_string(value).into_iter().collect(),
+        None => vec![],
+    };
 
-    let tags = match &doc["tags"] {
-        Yaml::Array(arr) => arr
-            .iter()
-            .filter_map(|t| t.as_str().map(String::from))
-            .collect(),
-        _ => vec![],
+    let mut extra_map = Hash::new();
+    for (key, value) in &map {
+        if !is_known_key(key) {
+            extra_map.insert(key.clone(), value.clone());
+        }
+    }
+    let extra = if extra_map.is_empty() {
+        None
+    } else {
+        let mut yaml = String::new();
+        let _ = YamlEmitter::new(&mut yaml).dump(&Yaml::Hash(extra_map));
+        Some(yaml.strip_prefix("---\n").unwrap_or(&yaml).to_string())
     };
 
     Ok((
         FrontmatterData {
-            id,
-            title,
+            id: get_str("id"),
+            title: get_str("title"),
             tags,
-            created_at,
-            updated_at,
-            is_pinned,
+            created_at: get_str("created_at"),
+            updated_at: get_str("updated_at"),
+            is_pinned: get("is_pinned").and_then(Yaml::as_bool).unwrap_or(false),
+            state: get_str("state"),
+            workspace_id: get_str("workspace_id"),
+            extra,
         },
         body,
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note() -> Note {
+        Note {
+            id: "n1".to_string(),
+            title: "Say \"hi\": a #title".to_string(),
+            content: "Body\n".to_string(),
+            created_at: "2024-01-01T00:00:00+00:00".to_string(),
+            updated_at: "2024-01-02T00:00:00+00:00".to_string(),
+            is_trashed: false,
+            is_pinned: true,
+            word_count: 1,
+            file_path: None,
+            sync_hash: None,
+            tags: vec![
+                "it's \"quoted\"".to_string(),
+                "multi\nline".to_string(),
+                "true".to_string(),
+            ],
+            state: "review".to_string(),
+            workspace_id: Some("ws1".to_string()),
+            version: 1,
+            extra_frontmatter: Some(
+                "source: \"https://example.com\"\nauthor: Ann\ndue: 2024-05-01\n".to_string(),
+            ),
+            char_count: 4,
+            reading_minutes: 1,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let original = note();
+        let serialized = serialize_frontmatter(&original);
+        let (fm, body) = parse_frontmatter(&serialized).unwrap();
+
+        assert_eq!(body, original.content);
+        assert_eq!(fm.id.as_deref(), Some("n1"));
+        assert_eq!(fm.title.as_deref(), Some(original.title.as_str()));
+        assert_eq!(fm.tags, original.tags);
+        assert_eq!(fm.created_at.as_deref(), Some(original.created_at.as_str()));
+        assert!(fm.is_pinned);
+        assert_eq!(fm.state.as_deref(), Some("review"));
+        assert_eq!(fm.workspace_id.as_deref(), Some("ws1"));
+        assert_eq!(
+            fm.extra.as_deref(),
+            Some("source: \"https://example.com\"\nauthor: Ann\ndue: 2024-05-01")
+        );
+
+        // A second pass is stable
+        let again = Note {
+            extra_frontmatter: fm.extra,
+            ..original
+        };
+        assert_eq!(serialize_frontmatter(&again), serialized);
+    }
+
+    #[test]
+    fn test_extra_keys_cannot_shadow_known_keys() {
+        let mut n = note();
+        n.extra_frontmatter = Some("title: spoofed\nrating: 5\n".to_string());
+        let (fm, _) = parse_frontmatter(&serialize_frontmatter(&n)).unwrap();
+        assert_eq!(fm.title.as_deref(), Some(n.title.as_str()));
+        assert_eq!(fm.extra.as_deref(), Some("rating: 5"));
+    }
+
+    #[test]
+    fn test_parse_lenient_values() {
+        let (fm, body) = parse_frontmatter("---\nid: 42\ntags: solo\n---\ntext").unwrap();
+        assert_eq!(fm.id.as_deref(), Some("42"));
+        assert_eq!(fm.tags, vec!["solo"]);
+        assert!(fm.state.is_none());
+        assert!(fm.extra.is_none());
+        assert_eq!(body, "text");
+    }
+}
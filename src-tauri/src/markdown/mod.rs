@@ -1,2 +1,10 @@
+pub mod attachments;
+pub mod checklist;
+pub mod embeds;
 pub mod frontmatter;
+pub mod patch;
+pub mod query;
+pub mod sanitize;
+pub mod sections;
+pub mod stats;
 pub mod tags;
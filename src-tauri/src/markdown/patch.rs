@@ -0,0 +1,206 @@
+use crate::db::models::PatchOperation;
+use crate::markdown::checklist::{extract_checklist, set_checked};
+use crate::markdown::sections::{find_section, parse_heading_path, Section};
+use regex::Regex;
+
+/// Apply `operations` to `content` in order. If any operation does not apply
+/// (missing heading, no match, ...) the whole patch fails.
+pub fn apply_patch(content: &str, operations: &[PatchOperation]) -> Result<String, String> {
+    let mut content = content.to_string();
+    for (i, op) in operations.iter().enumerate() {
+        content = apply_operation(&content, op)
+            .map_err(|e| format!("Operation {} ({}) failed: {}", i + 1, op_name(op), e))?;
+    }
+    Ok(content)
+}
+
+fn op_name(op: &PatchOperation) -> &'static str {
+    match op {
+        PatchOperation::Append { .. } => "append",
+        PatchOperation::Prepend { .. } => "prepend",
+        PatchOperation::ReplaceSection { .. } => "replace_section",
+        PatchOperation::InsertAfter { .. } => "insert_after",
+        PatchOperation::Replace { .. } => "replace",
+        PatchOperation::ToggleChecklist { .. } => "toggle_checklist",
+    }
+}
+
+fn apply_operation(content: &str, op: &PatchOperation) -> Result<String, String> {
+    match op {
+        PatchOperation::Append { heading: None, text } => {
+            let body = content.trim_end_matches('\n');
+            if body.is_empty() {
+                Ok(block(text))
+            } else {
+                Ok(format!("{}\n{}", body, block(text)))
+            }
+        }
+        PatchOperation::Append { heading: Some(heading), text } => {
+            let section = section(content, heading)?;
+            let body = &content[section.heading.body_start..section.end];
+            let last = section.heading.body_start + body.trim_end().len();
+            let pos = if last > section.heading.body_start && content[last..].starts_with('\n') {
+                last + 1
+            } else {
+                last
+            };
+            Ok(insert_at(content, pos, text))
+        }
+        PatchOperation::Prepend { heading: None, text } => Ok(insert_at(content, 0, text)),
+        PatchOperation::Prepend { heading: Some(heading), text } => {
+            let section = section(content, heading)?;
+            Ok(insert_at(content, section.heading.body_start, text))
+        }
+        PatchOperation::ReplaceSection { heading, text } => {
+            let section = section(content, heading)?;
+            let mut out = content[..section.heading.body_start].to_string();
+            if !out.ends_with('\n') {
+                out.push('\n');
+            }
+            out.push_str(&block(text));
+            if section.end < content.len() {
+                out.push('\n');
+            }
+            out.push_str(&content[section.end..]);
+            Ok(out)
+        }
+        PatchOperation::InsertAfter { after, text } => {
+            let mut offset = 0;
+            for line in content.split_inclusive('\n') {
+                offset += line.len();
+                if line.contains(after.as_str()) {
+                    return Ok(insert_at(content, offset, text));
+                }
+            }
+            Err(format!("No line contains '{}'", after))
+        }
+        PatchOperation::Replace { find, replace, regex, count } => {
+            if find.is_empty() {
+                return Err("'find' is empty".to_string());
+            }
+            if *count == Some(0) {
+                return Err("'count' must be at least 1".to_string());
+            }
+            // 0 is "all" to `Regex::replacen`
+            let limit = count.unwrap_or(0);
+            if *regex {
+                let re = Regex::new(find).map_err(|e| format!("Invalid regex: {}", e))?;
+                if !re.is_match(content) {
+                    return Err(format!("No match for /{}/", find));
+                }
+                Ok(re.replacen(content, limit, replace.as_str()).into_owned())
+            } else {
+                if !content.contains(find.as_str()) {
+                    return Err(format!("No match for '{}'", find));
+                }
+                match count {
+                    Some(n) => Ok(content.replacen(find.as_str(), replace, *n)),
+                    None => Ok(content.replace(find.as_str(), replace)),
+                }
+            }
+        }
+        PatchOperation::ToggleChecklist { item, checked } => {
+            let items = extract_checklist(content);
+            let wanted = item.trim().to_lowercase();
+            let found = items
+                .iter()
+                .find(|i| i.title.to_lowercase() == wanted)
+                .or_else(|| items.iter().find(|i| i.title.to_lowercase().contains(&wanted)))
+                .ok_or_else(|| format!("No checklist item matches '{}'", item))?;
+            Ok(set_checked(content, found, checked.unwrap_or(!found.checked)))
+        }
+    }
+}
+
+fn section(content: &str, heading: &str) -> Result<Section, String> {
+    find_section(content, &parse_heading_path(heading))
+}
+
+/// `text` as whole lines, ending with exactly one newline.
+fn block(text: &str) -> String {
+    format!("{}\n", text.trim_end_matches('\n'))
+}
+
+/// Insert `text` as whole lines at `pos`, which must be at a line start or at
+/// the end of the content.
+fn insert_at(content: &str, pos: usize, text: &str) -> String {
+    let mut out = String::with_capacity(content.len() + text.len() + 2);
+    out.push_str(&content[..pos]);
+    if pos > 0 && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    out.push_str(&block(text));
+    out.push_str(&content[pos..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = "# Projects\n## Alpha\n- one\n\n## Beta\ntext\n# Log\n- [ ] Ship it\n- [x] Plan";
+
+    fn patch(ops: Vec<PatchOperation>) -> Result<String, String> {
+        apply_patch(DOC, &ops)
+    }
+
+    #[test]
+    fn test_section_operations() {
+        let out = patch(vec![
+            PatchOperation::Append { heading: Some("Projects > Alpha".into()), text: "- two".into() },
+            PatchOperation::Prepend { heading: Some("Beta".into()), text: "first".into() },
+        ])
+        .unwrap();
+        assert_eq!(out, "# Projects\n## Alpha\n- one\n- two\n\n## Beta\nfirst\ntext\n# Log\n- [ ] Ship it\n- [x] Plan");
+
+        let out = patch(vec![PatchOperation::ReplaceSection { heading: "Alpha".into(), text: "replaced\n\n".into() }]).unwrap();
+        assert!(out.starts_with("# Projects\n## Alpha\nreplaced\n\n## Beta\n"));
+
+        let out = patch(vec![PatchOperation::Append { heading: Some("Log".into()), text: "- [ ] Review".into() }]).unwrap();
+        assert!(out.ends_with("- [x] Plan\n- [ ] Review\n"));
+    }
+
+    #[test]
+    fn test_whole_note_and_line_operations() {
+        let out = patch(vec![
+            PatchOperation::Prepend { heading: None, text: "Top".into() },
+            PatchOperation::Append { heading: None, text: "Bottom".into() },
+            PatchOperation::InsertAfter { after: "## Beta".into(), text: "inserted".into() },
+        ])
+        .unwrap();
+        assert!(out.starts_with("Top\n# Projects\n"));
+        assert!(out.contains("## Beta\ninserted\ntext\n"));
+        assert!(out.ends_with("- [x] Plan\nBottom\n"));
+    }
+
+    #[test]
+    fn test_replace_and_toggle() {
+        let out = patch(vec![
+            PatchOperation::Replace { find: r"## (\w+)".into(), replace: "## Project $1".into(), regex: true, count: Some(1) },
+            PatchOperation::Replace { find: "text".into(), replace: "body".into(), regex: false, count: None },
+            PatchOperation::ToggleChecklist { item: "ship".into(), checked: None },
+            PatchOperation::ToggleChecklist { item: "Plan".into(), checked: Some(false) },
+        ])
+        .unwrap();
+        assert!(out.contains("## Project Alpha\n"));
+        assert!(out.contains("## Beta\nbody\n"));
+        assert!(out.ends_with("- [x] Ship it\n- [ ] Plan"));
+    }
+
+    #[test]
+    fn test_failures_are_reported() {
+        let err = patch(vec![
+            PatchOperation::Append { heading: None, text: "x".into() },
+            PatchOperation::ReplaceSection { heading: "Missing".into(), text: "x".into() },
+        ])
+        .unwrap_err();
+        assert!(err.starts_with("Operation 2 (replace_section) failed"));
+        assert!(patch(vec![PatchOperation::InsertAfter { after: "nope".into(), text: "x".into() }]).is_err());
+        assert!(patch(vec![PatchOperation::Replace { find: "(".into(), replace: "".into(), regex: true, count: None }]).is_err());
+        assert!(patch(vec![PatchOperation::ToggleChecklist { item: "nothing".into(), checked: None }]).is_err());
+        for regex in [false, true] {
+            let zero = PatchOperation::Replace { find: "text".into(), replace: "x".into(), regex, count: Some(0) };
+            assert!(patch(vec![zero]).unwrap_err().contains("'count' must be at least 1"));
+        }
+    }
+}
@@ -0,0 +1,359 @@
+use pulldown_cmark::{CodeBlockKind, Event, Parser, Tag, TagEnd};
+
+/// Info string of a fenced block that holds a live query.
+pub const QUERY_LANG: &str = "bruin-query";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryKind {
+    List,
+    Table,
+    Tasks,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuerySource {
+    /// `#tag`, including its child tags
+    Tag(String),
+    /// `[[Note]]`: notes that link to Note, or tasks linked to it
+    Note(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryValue {
+    Text(String),
+    Number(f64),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryCondition {
+    pub field: String,
+    pub op: String,
+    pub value: QueryValue,
+}
+
+/// A parsed query such as
+/// `table status, due from #project/alpha where state = review sort updated desc limit 20`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub kind: QueryKind,
+    pub fields: Vec<String>,
+    pub sources: Vec<QuerySource>,
+    pub conditions: Vec<QueryCondition>,
+    /// Sort field and whether it is descending.
+    pub sort: Option<(String, bool)>,
+    pub limit: Option<usize>,
+}
+
+/// Return the source of every ```` ```bruin-query ```` block in `content`, in
+/// document order.
+pub fn extract_query_blocks(content: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current: Option<String> = None;
+    for event in Parser::new(content) {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang))) if lang.trim() == QUERY_LANG => {
+                current = Some(String::new());
+            }
+            Event::Text(text) => {
+                if let Some(ref mut buf) = current {
+                    buf.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some(buf) = current.take() {
+                    blocks.push(buf);
+                }
+            }
+            _ => {}
+        }
+    }
+    blocks
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Tag(String),
+    Link(String),
+    Op(String),
+    Comma,
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = src.char_indices().peekable();
+
+    while let Some(&(i, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == ',' {
+            chars.next();
+            tokens.push(Token::Comma);
+        } else if c == '"' || c == '\'' {
+            chars.next();
+            let mut s = String::new();
+            let mut closed = false;
+            while let Some((_, ch)) = chars.next() {
+                if ch == '\\' {
+                    if let Some((_, escaped)) = chars.next() {
+                        s.push(escaped);
+                    }
+                } else if ch == c {
+                    closed = true;
+                    break;
+                } else {
+                    s.push(ch);
+                }
+            }
+            if !closed {
+                return Err("Unterminated string".to_string());
+            }
+            tokens.push(Token::Str(s));
+        } else if src[i..].starts_with("[[") {
+            let end = src[i..]
+                .find("]]")
+                .ok_or_else(|| "Unterminated [[link]]".to_string())?;
+            tokens.push(Token::Link(src[i + 2..i + end].trim().to_string()));
+            while chars.peek().is_some_and(|&(j, _)| j < i + end + 2) {
+                chars.next();
+            }
+        } else if matches!(c, '=' | '!' | '<' | '>' | '~') {
+            chars.next();
+            let mut op = c.to_string();
+            if let Some(&(_, '=')) = chars.peek() {
+                chars.next();
+                op.push('=');
+            }
+            if op == "!" {
+                return Err("Expected '!='".to_string());
+            }
+            tokens.push(Token::Op(op));
+        } else {
+            let mut word = String::new();
+            while let Some(&(_, ch)) = chars.peek() {
+                if ch.is_whitespace() || matches!(ch, ',' | '=' | '!' | '<' | '>' | '~' | '"') {
+                    break;
+                }
+                word.push(ch);
+                chars.next();
+            }
+            match word.strip_prefix('#') {
+                Some(tag) if !tag.is_empty() => tokens.push(Token::Tag(tag.to_string())),
+                _ => tokens.push(Token::Word(word)),
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn is_keyword(token: Option<&Token>, keyword: &str) -> bool {
+    matches!(token, Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+}
+
+fn describe(token: Option<&Token>) -> String {
+    match token {
+        None => "end of query".to_string(),
+        Some(Token::Word(w)) => format!("'{}'", w),
+        Some(Token::Str(s)) => format!("\"{}\"", s),
+        Some(Token::Tag(t)) => format!("#{}", t),
+        Some(Token::Link(l)) => format!("[[{}]]", l),
+        Some(Token::Op(o)) => format!("'{}'", o),
+        Some(Token::Comma) => "','".to_string(),
+    }
+}
+
+/// Parse a query:
+///
+/// ```text
+/// (list | table [field, ...] | tasks)
+///   [from #tag | [[Note]] (or ...)]
+///   [where field op value (and ...)]
+///   [sort field [asc | desc]]
+///   [limit n]
+/// ```
+///
+/// `op` is one of `=`, `!=`, `<`, `<=`, `>`, `>=`, `~` or `contains`.
+pub fn parse_query(src: &str) -> Result<Query, String> {
+    let tokens = tokenize(src)?;
+    let mut pos = 0;
+    let peek = |pos: usize| tokens.get(pos);
+
+    let kind = match peek(pos) {
+        Some(Token::Word(w)) if w.eq_ignore_ascii_case("list") => QueryKind::List,
+        Some(Token::Word(w)) if w.eq_ignore_ascii_case("table") => QueryKind::Table,
+        Some(Token::Word(w)) if w.eq_ignore_ascii_case("tasks") => QueryKind::Tasks,
+        other => {
+            return Err(format!(
+                "Query must start with list, table or tasks, found {}",
+                describe(other)
+            ))
+        }
+    };
+    pos += 1;
+
+    let mut query = Query {
+        kind,
+        fields: vec![],
+        sources: vec![],
+        conditions: vec![],
+        sort: None,
+        limit: None,
+    };
+
+    if query.kind == QueryKind::Table {
+        while let Some(Token::Word(w)) = peek(pos) {
+            if ["from", "where", "sort", "limit"].iter().any(|k| w.eq_ignore_ascii_case(k)) {
+                break;
+            }
+            query.fields.push(w.clone());
+            pos += 1;
+            if peek(pos) == Some(&Token::Comma) {
+                pos += 1;
+            }
+        }
+    }
+
+    if is_keyword(peek(pos), "from") {
+        pos += 1;
+        loop {
+            match peek(pos) {
+                Some(Token::Tag(t)) => query.sources.push(QuerySource::Tag(t.clone())),
+                Some(Token::Link(l)) => query.sources.push(QuerySource::Note(l.clone())),
+                other => return Err(format!("Expected #tag or [[Note]] after 'from', found {}", describe(other))),
+            }
+            pos += 1;
+            if !is_keyword(peek(pos), "or") {
+                break;
+            }
+            pos += 1;
+        }
+    }
+
+    if is_keyword(peek(pos), "where") {
+        pos += 1;
+        loop {
+            let field = match peek(pos) {
+                Some(Token::Word(w)) => w.clone(),
+                other => return Err(format!("Expected a field name, found {}", describe(other))),
+            };
+            pos += 1;
+            let op = match peek(pos) {
+                Some(Token::Op(o)) => o.clone(),
+                Some(Token::Word(w)) if w.eq_ignore_ascii_case("contains") => "~".to_string(),
+                other => return Err(format!("Expected an operator after '{}', found {}", field, describe(other))),
+            };
+            pos += 1;
+            let value = match peek(pos) {
+                Some(Token::Str(s)) => QueryValue::Text(s.clone()),
+                Some(Token::Tag(t)) => QueryValue::Text(t.clone()),
+                Some(Token::Link(l)) => QueryValue::Text(l.clone()),
+                Some(Token::Word(w)) if w.eq_ignore_ascii_case("true") => QueryValue::Bool(true),
+                Some(Token::Word(w)) if w.eq_ignore_ascii_case("false") => QueryValue::Bool(false),
+                Some(Token::Word(w)) => match w.parse::<f64>() {
+                    Ok(n) => QueryValue::Number(n),
+                    Err(_) => QueryValue::Text(w.clone()),
+                },
+                other => return Err(format!("Expected a value for '{}', found {}", field, describe(other))),
+            };
+            pos += 1;
+            query.conditions.push(QueryCondition { field, op, value });
+            if !is_keyword(peek(pos), "and") {
+                break;
+            }
+            pos += 1;
+        }
+    }
+
+    if is_keyword(peek(pos), "sort") {
+        pos += 1;
+        let field = match peek(pos) {
+            Some(Token::Word(w)) => w.clone(),
+            other => return Err(format!("Expected a field after 'sort', found {}", describe(other))),
+        };
+        pos += 1;
+        let descending = if is_keyword(peek(pos), "desc") {
+            pos += 1;
+            true
+        } else {
+            if is_keyword(peek(pos), "asc") {
+                pos += 1;
+            }
+            false
+        };
+        query.sort = Some((field, descending));
+    }
+
+    if is_keyword(peek(pos), "limit") {
+        pos += 1;
+        query.limit = match peek(pos) {
+            Some(Token::Word(w)) => Some(w.parse().map_err(|_| format!("Invalid limit '{}'", w))?),
+            other => return Err(format!("Expected a number after 'limit', found {}", describe(other))),
+        };
+        pos += 1;
+    }
+
+    if pos < tokens.len() {
+        return Err(format!("Unexpected {}", describe(peek(pos))));
+    }
+    Ok(query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_list_query() {
+        let q = parse_query("list from #project/alpha where state = review sort updated desc").unwrap();
+        assert_eq!(q.kind, QueryKind::List);
+        assert_eq!(q.sources, vec![QuerySource::Tag("project/alpha".to_string())]);
+        assert_eq!(
+            q.conditions,
+            vec![QueryCondition {
+                field: "state".to_string(),
+                op: "=".to_string(),
+                value: QueryValue::Text("review".to_string()),
+            }]
+        );
+        assert_eq!(q.sort, Some(("updated".to_string(), true)));
+    }
+
+    #[test]
+    fn test_parse_table_query() {
+        let q = parse_query(
+            "TABLE status, due\nFROM #a OR [[Roadmap]]\nWHERE due < today AND points >= 3 AND title contains \"q 1\"\nLIMIT 5",
+        )
+        .unwrap();
+        assert_eq!(q.kind, QueryKind::Table);
+        assert_eq!(q.fields, vec!["status", "due"]);
+        assert_eq!(
+            q.sources,
+            vec![QuerySource::Tag("a".to_string()), QuerySource::Note("Roadmap".to_string())]
+        );
+        assert_eq!(q.conditions.len(), 3);
+        assert_eq!(q.conditions[1].value, QueryValue::Number(3.0));
+        assert_eq!(q.conditions[1].op, ">=");
+        assert_eq!(q.conditions[2].op, "~");
+        assert_eq!(q.conditions[2].value, QueryValue::Text("q 1".to_string()));
+        assert_eq!(q.limit, Some(5));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse_query("").is_err());
+        assert!(parse_query("select *").is_err());
+        assert!(parse_query("list from work").is_err());
+        assert!(parse_query("list where state").is_err());
+        assert!(parse_query("list where title = \"open").is_err());
+        assert!(parse_query("list limit ten").is_err());
+        assert!(parse_query("list sort title extra").is_err());
+    }
+
+    #[test]
+    fn test_extract_query_blocks() {
+        let content = "# Notes\n```bruin-query\nlist from #a\n```\n```rust\nfn x() {}\n```\n```bruin-query\ntasks\n```\n";
+        assert_eq!(extract_query_blocks(content), vec!["list from #a\n", "tasks\n"]);
+    }
+}
@@ -0,0 +1,190 @@
+use regex::Regex;
+
+/// Tags kept as-is (minus disallowed attributes). Anything else is dropped,
+/// keeping its text.
+const ALLOWED_TAGS: &[&str] = &[
+    "a", "abbr", "b", "blockquote", "br", "caption", "cite", "code", "dd", "del", "details",
+    "div", "dl", "dt", "em", "figcaption", "figure", "h1", "h2", "h3", "h4", "h5", "h6", "hr",
+    "i", "img", "ins", "kbd", "li", "mark", "ol", "p", "pre", "q", "s", "small", "span",
+    "strong", "sub", "summary", "sup", "table", "tbody", "td", "tfoot", "th", "thead", "tr",
+    "u", "ul",
+];
+
+/// Tags dropped together with everything inside them.
+const DROPPED_TAGS: &[&str] = &[
+    "script", "style", "iframe", "frame", "frameset", "object", "embed", "applet", "template",
+    "noscript", "textarea", "select", "title", "svg", "math",
+];
+
+const ALLOWED_ATTRS: &[&str] = &[
+    "href", "src", "alt", "title", "class", "id", "width", "height", "colspan", "rowspan",
+    "align", "open", "start", "data-note-id", "data-wiki-title",
+];
+
+/// Clean raw HTML from a note for export: only a fixed set of tags and
+/// attributes survive, and links may only use http(s), mailto or relative
+/// URLs (plus `data:image/` for images). Comments are removed.
+pub fn sanitize_html(html: &str) -> String {
+    let tag_re = Regex::new(
+        r#"(?s)<!--.*?(?:-->|$)|<(/?)([a-zA-Z][a-zA-Z0-9-]*)((?:[^>"']|"[^"]*"|'[^']*')*)>"#,
+    )
+    .unwrap();
+
+    let mut out = String::with_capacity(html.len());
+    let mut last = 0;
+    // Inside a dropped tag, nothing is written until it closes
+    let mut dropping: Option<String> = None;
+
+    for cap in tag_re.captures_iter(html) {
+        let m = cap.get(0).unwrap();
+        if dropping.is_none() {
+            out.push_str(&escape_text(&html[last..m.start()]));
+        }
+        last = m.end();
+
+        let Some(name) = cap.get(2) else {
+            continue; // comment
+        };
+        let name = name.as_str().to_ascii_lowercase();
+        let closing = !cap[1].is_empty();
+        let attrs = cap.get(3).map(|a| a.as_str()).unwrap_or("");
+
+        if let Some(ref open) = dropping {
+            if closing && *open == name {
+                dropping = None;
+            }
+            continue;
+        }
+        if DROPPED_TAGS.contains(&name.as_str()) {
+            if !closing && !attrs.trim_end().ends_with('/') {
+                dropping = Some(name);
+            }
+            continue;
+        }
+        if !ALLOWED_TAGS.contains(&name.as_str()) {
+            continue;
+        }
+
+        if closing {
+            out.push_str(&format!("</{}>", name));
+        } else {
+            out.push('<');
+            out.push_str(&name);
+            out.push_str(&clean_attributes(&name, attrs));
+            out.push('>');
+        }
+    }
+    if dropping.is_none() {
+        out.push_str(&escape_text(&html[last..]));
+    }
+    out
+}
+
+fn clean_attributes(tag: &str, attrs: &str) -> String {
+    let attr_re =
+        Regex::new(r#"([a-zA-Z_:][-a-zA-Z0-9_:.]*)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+)))?"#)
+            .unwrap();
+
+    let mut out = String::new();
+    for cap in attr_re.captures_iter(attrs) {
+        let name = cap[1].to_ascii_lowercase();
+        if !ALLOWED_ATTRS.contains(&name.as_str()) {
+            continue;
+        }
+        let value = cap
+            .get(2)
+            .or_else(|| cap.get(3))
+            .or_else(|| cap.get(4))
+            .map(|v| v.as_str())
+            .unwrap_or("");
+        let safe = match name.as_str() {
+            "href" => is_safe_url(value, false),
+            "src" => tag == "img" && is_safe_url(value, true),
+            _ => true,
+        };
+        if safe {
+            out.push_str(&format!(
+                " {}=\"{}\"",
+                name,
+                value.replace('"', "&quot;").replace('<', "&lt;").replace('>', "&gt;")
+            ));
+        }
+    }
+    out
+}
+
+/// Whether a URL is relative or uses an allowed scheme. Character references
+/// and whitespace are resolved first, as a browser would, so
+/// `jav&#x61;script:` is caught.
+pub fn is_safe_url(url: &str, image: bool) -> bool {
+    let decoded: String = decode_char_refs(url)
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+
+    let scheme_end = decoded.find(':');
+    let path_start = decoded.find(['/', '?', '#']);
+    match (scheme_end, path_start) {
+        (None, _) => true,
+        (Some(colon), Some(path)) if path < colon => true,
+        (Some(colon), _) => {
+            let scheme = &decoded[..colon];
+            matches!(scheme, "http" | "https")
+                || (!image && scheme == "mailto")
+                || (image && decoded.starts_with("data:image/") && !decoded.starts_with("data:image/svg"))
+        }
+    }
+}
+
+fn decode_char_refs(s: &str) -> String {
+    let re = Regex::new(r"&(#[xX][0-9a-fA-F]+|#[0-9]+|colon|tab|newline|amp);?").unwrap();
+    re.replace_all(s, |cap: &regex::Captures| {
+        let body = &cap[1];
+        let c = if let Some(hex) = body.strip_prefix("#x").or_else(|| body.strip_prefix("#X")) {
+            u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+        } else if let Some(dec) = body.strip_prefix('#') {
+            dec.parse().ok().and_then(char::from_u32)
+        } else {
+            match body.to_ascii_lowercase().as_str() {
+                "colon" => Some(':'),
+                "tab" => Some('\t'),
+                "newline" => Some('\n'),
+                _ => Some('&'),
+            }
+        };
+        c.map(String::from).unwrap_or_default()
+    })
+    .into_owned()
+}
+
+fn escape_text(s: &str) -> String {
+    s.replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_html() {
+        assert_eq!(
+            sanitize_html("<p class=\"x\" onclick=\"evil()\" style=\"color:red\">Hi <b>there</b></p>"),
+            "<p class=\"x\">Hi <b>there</b></p>"
+        );
+        assert_eq!(sanitize_html("a<script>alert('<p>')</script>b"), "ab");
+        assert_eq!(sanitize_html("<!-- hidden -->x<blink>y</blink>"), "xy");
+        assert_eq!(
+            sanitize_html("<a href=\"jav&#x61;script:alert(1)\">x</a><a href='notes/a.html#b'>y</a>"),
+            "<a>x</a><a href=\"notes/a.html#b\">y</a>"
+        );
+        assert_eq!(
+            sanitize_html("<img src=\"data:image/png;base64,AA\"><img src=\"data:text/html,x\">"),
+            "<img src=\"data:image/png;base64,AA\"><img>"
+        );
+        assert_eq!(
+            sanitize_html("<div class=\"bruin-embed\" data-note-id=\"n1\">"),
+            "<div class=\"bruin-embed\" data-note-id=\"n1\">"
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+/// A heading in a note, with byte offsets into the content.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heading {
+    pub level: u32,
+    pub text: String,
+    /// Start of the heading line.
+    pub start: usize,
+    /// Start of the line after the heading.
+    pub body_start: usize,
+}
+
+/// A heading plus everything under it, up to the next heading of the same or
+/// a higher level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    pub heading: Heading,
+    pub end: usize,
+}
+
+/// Find every heading in `content`, in document order. Headings inside code
+/// blocks are not headings and are skipped by the parser.
+pub fn find_headings(content: &str) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut current: Option<(u32, usize, usize, String)> = None;
+
+    let parser = Parser::new_ext(content, Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS)
+        .into_offset_iter();
+    for (event, range) in parser {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                current = Some((level as u32, range.start, range.end, String::new()));
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, _, _, ref mut buf)) = current {
+                    buf.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some((level, start, end, text)) = current.take() {
+                    headings.push(Heading {
+                        level,
+                        text: text.trim().to_string(),
+                        start,
+                        body_start: line_end(content, end.max(start)),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    headings
+}
+
+/// Offset just past the newline ending the line that `pos` is on (or that
+/// ends right before `pos`).
+fn line_end(content: &str, pos: usize) -> usize {
+    if pos > 0 && content.as_bytes()[pos - 1] == b'\n' {
+        return pos;
+    }
+    content[pos..]
+        .find('\n')
+        .map(|i| pos + i + 1)
+        .unwrap_or(content.len())
+}
+
+/// All sections of `content`, in document order.
+pub fn find_sections(content: &str) -> Vec<Section> {
+    let headings = find_headings(content);
+    headings
+        .iter()
+        .enumerate()
+        .map(|(i, heading)| {
+            let end = headings[i + 1..]
+                .iter()
+                .find(|h| h.level <= heading.level)
+                .map(|h| h.start)
+                .unwrap_or(content.len());
+            Section {
+                heading: heading.clone(),
+                end,
+            }
+        })
+        .collect()
+}
+
+/// Split a heading path such as `Projects > Alpha` into its segments.
+pub fn parse_heading_path(path: &str) -> Vec<String> {
+    path.split('>')
+        .map(|s| s.trim().trim_start_matches('#').trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Find the section at `path`: each segment names a heading nested under the
+/// previous one, e.g. `["Projects", "Alpha"]`. Headings match
+/// case-insensitively and the first match wins.
+pub fn find_section(content: &str, path: &[String]) -> Result<Section, String> {
+    if path.is_empty() {
+        return Err("Heading path is empty".to_string());
+    }
+    let sections = find_sections(content);
+    let mut scope = 0..content.len();
+    let mut parent_level = 0;
+    let mut found: Option<&Section> = None;
+
+    for segment in path {
+        found = sections.iter().find(|s| {
+            s.heading.level > parent_level
+                && s.heading.start >= scope.start
+                && s.end <= scope.end
+                && s.heading.text.eq_ignore_ascii_case(segment)
+        });
+        match found {
+            Some(section) => {
+                scope = section.heading.body_start..section.end;
+                parent_level = section.heading.level;
+            }
+            None => return Err(format!("Heading '{}' not found", path.join(" > "))),
+        }
+    }
+    found.cloned().ok_or_else(|| format!("Heading '{}' not found", path.join(" > ")))
+}
+
+/// Turn heading text into an anchor: lowercase, words joined by `-`,
+/// punctuation dropped. Letters and digits in any script are kept.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.trim().chars() {
+        if c.is_alphanumeric() || c == '_' || c == '-' {
+            slug.extend(c.to_lowercase());
+        } else if c.is_whitespace() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_matches('-').to_string();
+    if slug.is_empty() {
+        "section".to_string()
+    } else {
+        slug
+    }
+}
+
+/// Anchors for `sections`, in the same order. Repeated headings get `-1`,
+/// `-2`, ... suffixes so every anchor in a note is unique.
+pub fn section_anchors(sections: &[Section]) -> Vec<String> {
+    let mut seen: Vec<String> = Vec::new();
+    sections
+        .iter()
+        .map(|section| {
+            let base = slugify(&section.heading.text);
+            let mut anchor = base.clone();
+            let mut n = 0;
+            while seen.contains(&anchor) {
+                n += 1;
+                anchor = format!("{}-{}", base, n);
+            }
+            seen.push(anchor.clone());
+            anchor
+        })
+        .collect()
+}
+
+/// Find the section for an anchor from `[[Note#Heading]]` or an outline.
+/// Accepts the anchor itself or the heading text as written.
+pub fn find_section_by_anchor(content: &str, anchor: &str) -> Option<(Section, String)> {
+    let sections = find_sections(content);
+    let anchors = section_anchors(&sections);
+    let wanted = anchor.trim().trim_start_matches('#');
+    let index = anchors
+        .iter()
+        .position(|a| a == wanted)
+        .or_else(|| sections.iter().position(|s| s.heading.text.eq_ignore_ascii_case(wanted)))
+        .or_else(|| {
+            let slug = slugify(wanted);
+            anchors.iter().position(|a| *a == slug)
+        })?;
+    Some((sections[index].clone(), anchors[index].clone()))
+}
+
+/// Split a wiki link target such as `Note#Heading|alias` into the note title
+/// and the optional heading anchor. The title is empty for `[[#Heading]]`.
+pub fn split_link_target(target: &str) -> (&str, Option<&str>) {
+    let target = target.split('|').next().unwrap_or(target);
+    match target.split_once('#') {
+        Some((title, anchor)) if !anchor.trim().is_empty() => (title.trim(), Some(anchor.trim())),
+        Some((title, _)) => (title.trim(), None),
+        None => (target.trim(), None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = "Intro\n# Projects\nAll\n## Alpha\nA body\n\n## Beta `b`\nB body\n# Notes\n```\n# not a heading\n```\n## Alpha\nOther alpha";
+
+    #[test]
+    fn test_find_headings() {
+        let headings = find_headings(DOC);
+        let texts: Vec<(u32, &str)> = headings.iter().map(|h| (h.level, h.text.as_str())).collect();
+        assert_eq!(
+            texts,
+            vec![(1, "Projects"), (2, "Alpha"), (2, "Beta b"), (1, "Notes"), (2, "Alpha")]
+        );
+        assert_eq!(&DOC[headings[1].start..headings[1].body_start], "## Alpha\n");
+    }
+
+    #[test]
+    fn test_find_section_by_path() {
+        let path = parse_heading_path("Projects > alpha");
+        let section = find_section(DOC, &path).unwrap();
+        assert_eq!(&DOC[section.heading.body_start..section.end], "A body\n\n");
+
+        let section = find_section(DOC, &parse_heading_path("Notes > Alpha")).unwrap();
+        assert_eq!(&DOC[section.heading.body_start..section.end], "Other alpha");
+
+        let section = find_section(DOC, &parse_heading_path("## Projects")).unwrap();
+        assert_eq!(section.end, DOC.find("# Notes").unwrap());
+
+        assert!(find_section(DOC, &parse_heading_path("Beta b > Alpha")).is_err());
+        assert!(find_section(DOC, &parse_heading_path("Missing")).is_err());
+    }
+
+    #[test]
+    fn test_anchors() {
+        assert_eq!(slugify("Beta `b`: the Plan!"), "beta-b-the-plan");
+        assert_eq!(slugify("Überblick 概要"), "überblick-概要");
+        assert_eq!(slugify("???"), "section");
+
+        let anchors = section_anchors(&find_sections(DOC));
+        assert_eq!(anchors, vec!["projects", "alpha", "beta-b", "notes", "alpha-1"]);
+
+        let (section, anchor) = find_section_by_anchor(DOC, "alpha-1").unwrap();
+        assert_eq!(anchor, "alpha-1");
+        assert_eq!(&DOC[section.heading.body_start..section.end], "Other alpha");
+        assert_eq!(find_section_by_anchor(DOC, "Beta b").unwrap().1, "beta-b");
+        assert!(find_section_by_anchor(DOC, "gamma").is_none());
+    }
+
+    #[test]
+    fn test_split_link_target() {
+        assert_eq!(split_link_target("Note"), ("Note", None));
+        assert_eq!(split_link_target("Note#Heading"), ("Note", Some("Heading")));
+        assert_eq!(split_link_target("Note # Heading|alias"), ("Note", Some("Heading")));
+        assert_eq!(split_link_target("#local"), ("", Some("local")));
+        assert_eq!(split_link_target("Note#"), ("Note", None));
+    }
+}
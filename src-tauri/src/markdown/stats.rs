@@ -0,0 +1,115 @@
+//! Word, character and reading-time counts that hold up for languages
+//! written without spaces between words.
+
+/// Reading speed for space-separated text, in words per minute.
+const WORDS_PER_MINUTE: f64 = 230.0;
+/// Reading speed for Chinese and Japanese, in characters per minute.
+const CJK_CHARACTERS_PER_MINUTE: f64 = 500.0;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TextStats {
+    /// Words of space-separated text, plus one for each Chinese or Japanese
+    /// character, the way word processors count them. Markdown punctuation
+    /// is not a word.
+    pub words: i64,
+    /// Characters other than whitespace. Combining marks, variation
+    /// selectors and emoji joiners are part of the character before them.
+    pub characters: i64,
+    /// Minutes to read it, rounded up; 0 only for empty text.
+    pub reading_minutes: i64,
+}
+
+pub fn text_stats(text: &str) -> TextStats {
+    let mut words = 0;
+    let mut cjk = 0;
+    let mut characters = 0;
+    let mut in_word = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            in_word = false;
+            continue;
+        }
+        if is_mark(c) {
+            continue;
+        }
+        characters += 1;
+        if is_cjk(c) {
+            cjk += 1;
+            in_word = false;
+        } else if c.is_alphanumeric() {
+            if !in_word {
+                words += 1;
+                in_word = true;
+            }
+        } else {
+            // "don't", "well-known" and "3.14" are one word each
+            let joins = matches!(c, '\'' | '’' | '-' | '.' | '_')
+                && chars.peek().is_some_and(|&next| next.is_alphanumeric() && !is_cjk(next));
+            in_word = in_word && joins;
+        }
+    }
+    let minutes = words as f64 / WORDS_PER_MINUTE + cjk as f64 / CJK_CHARACTERS_PER_MINUTE;
+    TextStats {
+        words: words + cjk,
+        characters,
+        reading_minutes: if words + cjk == 0 { 0 } else { minutes.ceil().max(1.0) as i64 },
+    }
+}
+
+/// Han ideographs, kana and bopomofo: scripts written without spaces, where
+/// each character counts as a word.
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF // Hiragana, Katakana
+        | 0x3100..=0x312F // Bopomofo
+        | 0x31F0..=0x31FF // Katakana extensions
+        | 0x3400..=0x4DBF // CJK extension A
+        | 0x4E00..=0x9FFF // CJK unified ideographs
+        | 0xF900..=0xFAFF // Compatibility ideographs
+        | 0xFF66..=0xFF9D // Halfwidth katakana
+        | 0x20000..=0x3134F // Extensions B to G
+    ) && !is_mark(c) && c != '・'
+}
+
+/// Characters that modify the one before them rather than standing alone.
+fn is_mark(c: char) -> bool {
+    matches!(c as u32,
+        0x0300..=0x036F // Combining diacritics
+        | 0x1AB0..=0x1AFF
+        | 0x1DC0..=0x1DFF
+        | 0x200C..=0x200D // Zero-width (non-)joiners
+        | 0x20D0..=0x20FF
+        | 0x3099..=0x309A // Combining kana voicing marks
+        | 0xFE00..=0xFE0F // Variation selectors
+        | 0xFE20..=0xFE2F
+        | 0x1F3FB..=0x1F3FF // Emoji skin tones
+        | 0xE0100..=0xE01EF
+    ) || (('\u{0900}'..='\u{0DFF}').contains(&c) && !c.is_alphanumeric() && !matches!(c, '\u{0964}' | '\u{0965}'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_stats() {
+        let stats = text_stats("# Hello, world!\n\n- don't stop well-known 3.14");
+        assert_eq!(stats.words, 6);
+        assert_eq!(stats.characters, 37);
+        assert_eq!(stats.reading_minutes, 1);
+
+        // Each ideograph and kana is a word; punctuation is not
+        let stats = text_stats("今日は良い天気です。Rust で書く");
+        assert_eq!(stats.words, 9 + 1 + 3);
+        assert_eq!(stats.characters, 17);
+
+        // Accents and emoji modifiers belong to their character
+        assert_eq!(text_stats("cafe\u{301} 👍🏽").characters, 5);
+        assert_eq!(text_stats("नमस्ते दुनिया").words, 2);
+
+        let long = "中".repeat(2000);
+        assert_eq!(text_stats(&long), TextStats { words: 2000, characters: 2000, reading_minutes: 4 });
+        assert_eq!(text_stats(" \n").reading_minutes, 0);
+    }
+}
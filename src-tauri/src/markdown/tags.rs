@@ -1,16 +1,23 @@
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
 use regex::Regex;
+use std::ops::Range;
 
-/// Extract tags from markdown content, ignoring tags inside code blocks.
-pub fn extract_tags(content: &str) -> Vec<String> {
-    let tag_re = Regex::new(r"#([a-zA-Z0-9_]+(?:/[a-zA-Z0-9_]+)*)").unwrap();
-
-    // Remove fenced code blocks before extracting tags
-    let code_block_re = Regex::new(r"(?s)```.*?```").unwrap();
-    let cleaned = code_block_re.replace_all(content, "");
+/// A hashtag found in note content. `start..end` covers the whole tag in the
+/// source, including the leading `#` and, for Bear multi-word tags, the
+/// closing `#`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagSpan {
+    pub start: usize,
+    pub end: usize,
+    pub name: String,
+}
 
-    let mut tags: Vec<String> = tag_re
-        .captures_iter(&cleaned)
-        .map(|cap| cap[1].to_string())
+/// Extract tags from markdown content, ignoring code, links, HTML and
+/// frontmatter.
+pub fn extract_tags(content: &str) -> Vec<String> {
+    let mut tags: Vec<String> = find_tag_spans(content)
+        .into_iter()
+        .map(|span| span.name)
         .collect();
 
     tags.sort();
@@ -18,6 +25,309 @@ pub fn extract_tags(content: &str) -> Vec<String> {
     tags
 }
 
+/// Find every hashtag in `content`. Only plain text in the Markdown AST is
+/// scanned, so tags inside fenced or indented code, inline code, link and
+/// image text, HTML and YAML frontmatter are skipped, as is anything inside
+/// a `[[wiki link]]`.
+///
+/// A tag starts with `#` at the start of a line or after whitespace or
+/// opening punctuation, and runs over Unicode letters, digits, marks, `_`,
+/// `-` and `/`. Bear-style `#multi word tags#` are recognised when the
+/// closing `#` is on the same line and directly follows a non-space.
+/// Escaped `\#`, `##` and purely numeric tags such as `#1` are not tags.
+pub fn find_tag_spans(content: &str) -> Vec<TagSpan> {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS;
+
+    // Collect source ranges of text outside skipped contexts, joining
+    // adjacent text events so a tag split by the parser stays whole.
+    let mut runs: Vec<Range<usize>> = Vec::new();
+    let mut skip_depth = 0usize;
+    for (event, range) in Parser::new_ext(content, options).into_offset_iter() {
+        match event {
+            Event::Start(
+                Tag::CodeBlock(_)
+                | Tag::Link { .. }
+                | Tag::Image { .. }
+                | Tag::HtmlBlock
+                | Tag::MetadataBlock(_),
+            ) => skip_depth += 1,
+            Event::End(
+                TagEnd::CodeBlock
+                | TagEnd::Link
+                | TagEnd::Image
+                | TagEnd::HtmlBlock
+                | TagEnd::MetadataBlock(_),
+            ) => skip_depth = skip_depth.saturating_sub(1),
+            Event::Text(_) if skip_depth == 0 => match runs.last_mut() {
+                Some(last) if last.end == range.start => last.end = range.end,
+                _ => runs.push(range),
+            },
+            _ => {}
+        }
+    }
+
+    let wiki_re = Regex::new(r"\[\[[^\]\n]*\]\]").unwrap();
+    let mut spans = Vec::new();
+    for run in runs {
+        let text = &content[run.clone()];
+        let wiki: Vec<Range<usize>> = wiki_re.find_iter(text).map(|m| m.range()).collect();
+
+        let mut pos = 0;
+        while let Some(offset) = text[pos..].find('#') {
+            let at = pos + offset;
+            pos = at + 1;
+            if wiki.iter().any(|r| r.contains(&at)) {
+                continue;
+            }
+            if !can_start_tag(content[..run.start + at].chars().next_back()) {
+                continue;
+            }
+            let rest = &text[at + 1..];
+            if let Some((len, name)) = multi_word_tag(rest).or_else(|| single_word_tag(rest)) {
+                spans.push(TagSpan {
+                    start: run.start + at,
+                    end: run.start + at + 1 + len,
+                    name,
+                });
+                pos = at + 1 + len;
+            }
+        }
+    }
+    spans
+}
+
+/// Format a tag name as it is written in note content: `#name`, or
+/// `#name#` for Bear multi-word tags.
+pub fn format_tag(name: &str) -> String {
+    if name.contains(char::is_whitespace) {
+        format!("#{}#", name)
+    } else {
+        format!("#{}", name)
+    }
+}
+
+fn can_start_tag(prev: Option<char>) -> bool {
+    match prev {
+        None => true,
+        Some(c) => {
+            c.is_whitespace()
+                || matches!(
+                    c,
+                    '(' | '[' | '{' | '"' | '\'' | '*' | '_' | '~' | ',' | ';' | '|'
+                )
+                || (!c.is_ascii() && is_punctuation(c))
+        }
+    }
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric()
+        || matches!(c, '_' | '-' | '/')
+        || (!c.is_ascii() && !c.is_whitespace() && !c.is_control() && !is_punctuation(c))
+}
+
+/// ASCII punctuation plus the Unicode punctuation and symbol blocks that
+/// commonly sit next to tags: Latin-1 symbols, general punctuation, CJK
+/// symbols and fullwidth forms.
+fn is_punctuation(c: char) -> bool {
+    if c.is_ascii() {
+        return c.is_ascii_punctuation();
+    }
+    if c.is_alphanumeric() {
+        return false;
+    }
+    matches!(c as u32,
+        0x00A0..=0x00BF
+        | 0x00D7
+        | 0x00F7
+        | 0x2000..=0x206F
+        | 0x2E00..=0x2E7F
+        | 0x3000..=0x303F
+        | 0x30FB
+        | 0xFE30..=0xFE6F
+        | 0xFF01..=0xFF0F
+        | 0xFF1A..=0xFF20
+        | 0xFF3B..=0xFF40
+        | 0xFF5B..=0xFF65
+    )
+}
+
+/// Match a single-word tag at the start of `rest` (the text after `#`).
+/// Returns the matched length and the tag name.
+fn single_word_tag(rest: &str) -> Option<(usize, String)> {
+    let end = rest
+        .char_indices()
+        .find(|(_, c)| !is_tag_char(*c))
+        .map_or(rest.len(), |(i, _)| i);
+    let name = rest[..end].trim_end_matches(['/', '-']);
+    if name.is_empty() || name.starts_with('/') || name.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some((name.len(), name.to_string()))
+}
+
+/// Match a Bear multi-word tag (`#tag with spaces#`) at the start of `rest`.
+/// The first `#` on the line decides: it must follow a non-space and be
+/// followed by whitespace, punctuation or the end of the text.
+fn multi_word_tag(rest: &str) -> Option<(usize, String)> {
+    let first = rest.chars().next()?;
+    if !is_tag_char(first) || first == '/' {
+        return None;
+    }
+    for (i, c) in rest.char_indices() {
+        if c == '#' {
+            let inner = &rest[..i];
+            let after = rest[i + 1..].chars().next();
+            let closes = after.is_none_or(|a| a != '#' && !is_tag_char(a));
+            if !closes
+                || !inner.contains(' ')
+                || inner.ends_with(char::is_whitespace)
+                || inner.chars().all(|c| c.is_ascii_digit() || c == ' ')
+            {
+                return None;
+            }
+            let name = inner.split_whitespace().collect::<Vec<_>>().join(" ");
+            return Some((i + 1, name));
+        }
+        if !(is_tag_char(c) || c == ' ' || c == '\t') {
+            return None;
+        }
+    }
+    None
+}
+
+/// Rewrite every hashtag that is `old` or one of its children, swapping the
+/// `old` prefix for `new`, or removing the tag when `new` is `None`. Only
+/// tags that `find_tag_spans` reports are touched, and a leading YAML
+/// frontmatter `tags:` list is rewritten too.
+pub fn rewrite_tag(content: &str, old: &str, new: Option<&str>) -> String {
+    let (frontmatter, body) = split_leading_frontmatter(content);
+    let mut out = String::with_capacity(content.len());
+    out.push_str(&rewrite_frontmatter_tags(frontmatter, old, new));
+
+    let mut last = 0;
+    for span in find_tag_spans(body) {
+        let mapped = match map_tag(&span.name, old, new) {
+            Some(m) => m,
+            None => continue,
+        };
+        let before = &body[last..span.start];
+        match mapped {
+            Some(renamed) => {
+                out.push_str(before);
+                out.push_str(&format_tag(&renamed));
+            }
+            None => {
+                // Drop the tag along with one separating space, so
+                // "a #old b" becomes "a b" rather than "a  b".
+                let next = body[span.end..].chars().next();
+                if before.ends_with(' ') && next.is_none_or(|c| c == ' ' || c == '\n') {
+                    out.push_str(&before[..before.len() - 1]);
+                } else {
+                    out.push_str(before);
+                }
+            }
+        }
+        last = span.end;
+    }
+    out.push_str(&body[last..]);
+    out
+}
+
+/// Map `tag` through a rename of `old` to `new`. Returns `None` if `tag` is
+/// unaffected, `Some(None)` if it should be removed.
+pub fn map_tag(tag: &str, old: &str, new: Option<&str>) -> Option<Option<String>> {
+    let rest = if tag == old {
+        ""
+    } else if tag.len() > old.len() && tag.starts_with(old) && tag.as_bytes()[old.len()] == b'/' {
+        &tag[old.len()..]
+    } else {
+        return None;
+    };
+    Some(new.map(|n| format!("{}{}", n, rest)))
+}
+
+/// Split `content` into a leading `---` frontmatter block (delimiters
+/// included) and the rest. The frontmatter part is empty if there is none.
+fn split_leading_frontmatter(content: &str) -> (&str, &str) {
+    if !content.starts_with("---\n") {
+        return ("", content);
+    }
+    let mut offset = 4;
+    for line in content[4..].split_inclusive('\n') {
+        offset += line.len();
+        if line.trim_end() == "---" {
+            return content.split_at(offset);
+        }
+    }
+    ("", content)
+}
+
+fn rewrite_frontmatter_tags(frontmatter: &str, old: &str, new: Option<&str>) -> String {
+    let mut out = String::with_capacity(frontmatter.len());
+    let mut in_tags_list = false;
+
+    for line in frontmatter.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if let Some(value) = line.strip_prefix("tags:") {
+            in_tags_list = value.trim().is_empty();
+            let value = value.trim();
+            if value.starts_with('[') && value.ends_with(']') {
+                let items: Vec<String> = value[1..value.len() - 1]
+                    .split(',')
+                    .map(|item| item.trim())
+                    .filter(|item| !item.is_empty())
+                    .filter_map(|item| rewrite_yaml_scalar(item, old, new))
+                    .collect();
+                out.push_str(&format!("tags: [{}]", items.join(", ")));
+                if line.ends_with('\n') {
+                    out.push('\n');
+                }
+                continue;
+            }
+        } else if in_tags_list && trimmed.starts_with("- ") {
+            let indent = &line[..line.len() - trimmed.len()];
+            let item = trimmed[2..].trim_end();
+            if let Some(rewritten) = rewrite_yaml_scalar(item, old, new) {
+                out.push_str(&format!("{}- {}", indent, rewritten));
+                if line.ends_with('\n') {
+                    out.push('\n');
+                }
+            }
+            continue;
+        } else if !line.starts_with(' ') && !line.starts_with('-') {
+            in_tags_list = false;
+        }
+        out.push_str(line);
+    }
+    out
+}
+
+/// Rewrite a single (possibly quoted) YAML tag value, keeping its quoting.
+/// Returns `None` when the tag is removed.
+fn rewrite_yaml_scalar(item: &str, old: &str, new: Option<&str>) -> Option<String> {
+    let quote = item
+        .chars()
+        .next()
+        .filter(|c| (*c == '"' || *c == '\'') && item.len() > 1 && item.ends_with(*c));
+    let bare = match quote {
+        Some(_) => &item[1..item.len() - 1],
+        None => item,
+    };
+    match map_tag(bare, old, new) {
+        None => Some(item.to_string()),
+        Some(None) => None,
+        Some(Some(renamed)) => Some(match quote {
+            Some(q) => format!("{}{}{}", q, renamed, q),
+            None => renamed,
+        }),
+    }
+}
+
 /// For a tag like "work/projects", return the parent "work".
 pub fn get_parent_tag(tag: &str) -> Option<String> {
     tag.rfind('/').map(|pos| tag[..pos].to_string())
@@ -34,6 +344,141 @@ mod tests {
         assert_eq!(tags, vec!["another", "rust", "work/projects"]);
     }
 
+    #[test]
+    fn test_extract_tags_unicode() {
+        assert_eq!(
+            extract_tags("学习 #编程 和 #日本語/タグ"),
+            vec!["日本語/タグ", "编程"]
+        );
+        assert_eq!(extract_tags("Voir #café et #naïve."), vec!["café", "naïve"]);
+        assert_eq!(extract_tags("「#タグ」、#标签，完"), vec!["タグ", "标签"]);
+        assert_eq!(extract_tags("#emoji🎉 ok"), vec!["emoji🎉"]);
+        assert_eq!(extract_tags("Done — #dash-tag."), vec!["dash-tag"]);
+    }
+
+    #[test]
+    fn test_extract_tags_bear_multi_word() {
+        assert_eq!(
+            extract_tags("#tag with spaces# and #single"),
+            vec!["single", "tag with spaces"]
+        );
+        assert_eq!(extract_tags("#reading  list#."), vec!["reading list"]);
+        assert_eq!(extract_tags("#work/big project#"), vec!["work/big project"]);
+        // The closing # must directly follow a word and end the tag
+        assert_eq!(extract_tags("#one and #two"), vec!["one", "two"]);
+        assert_eq!(extract_tags("#a b#c"), vec!["a"]);
+        // Multi-word tags never span lines
+        assert_eq!(extract_tags("#first line\nsecond#"), vec!["first"]);
+    }
+
+    #[test]
+    fn test_extract_tags_skips_code() {
+        assert_eq!(extract_tags("Use `#not_tag` here #yes"), vec!["yes"]);
+        assert_eq!(extract_tags("~~~\n#fenced\n~~~\n#after"), vec!["after"]);
+        assert_eq!(
+            extract_tags("Para\n\n    #indented code\n\n#real"),
+            vec!["real"]
+        );
+        assert!(extract_tags("```rust\nlet x = \"#nope\";\n```").is_empty());
+    }
+
+    #[test]
+    fn test_extract_tags_skips_links_and_html() {
+        assert_eq!(
+            extract_tags("See [#label](https://x.io/#frag) #ok"),
+            vec!["ok"]
+        );
+        assert!(extract_tags("Visit https://example.com/page#anchor now").is_empty());
+        assert!(extract_tags("<https://example.com/#anchor>").is_empty());
+        assert!(extract_tags("![#alt](img.png)").is_empty());
+        assert!(extract_tags("<a href=\"#top\">up</a>").is_empty());
+        assert!(extract_tags("<div>\n#inside html\n</div>").is_empty());
+        assert!(extract_tags("Link to [[Note#Heading]] and [[#local]]").is_empty());
+    }
+
+    #[test]
+    fn test_extract_tags_edge_cases() {
+        // Headings are not tags, but tags inside headings are
+        assert_eq!(extract_tags("# Title\n## Sub #topic ##"), vec!["topic"]);
+        assert!(extract_tags("Issue #42 and #1").is_empty());
+        assert_eq!(extract_tags("#2024 #2024-plan"), vec!["2024-plan"]);
+        assert!(extract_tags("Escaped \\#hash and ##double").is_empty());
+        assert!(extract_tags("C# and a#b and &#35;x").is_empty());
+        assert_eq!(
+            extract_tags("(#paren) *#em* **#strong** _#under_"),
+            vec!["em", "paren", "strong", "under"]
+        );
+        assert_eq!(extract_tags("#trail/ #dash-"), vec!["dash", "trail"]);
+        assert_eq!(
+            extract_tags("| #cell | x |\n|---|---|\n| #row | y |"),
+            vec!["cell", "row"]
+        );
+        assert_eq!(extract_tags("- [ ] #todo item"), vec!["todo"]);
+        assert_eq!(extract_tags("> quoted #quote"), vec!["quote"]);
+    }
+
+    #[test]
+    fn test_extract_tags_skips_frontmatter() {
+        let content = "---\ntitle: \"#nope\"\ncolor: #fff\n---\nBody #yes";
+        assert_eq!(extract_tags(content), vec!["yes"]);
+    }
+
+    #[test]
+    fn test_find_tag_spans() {
+        let content = "a #x and #y z#";
+        let spans = find_tag_spans(content);
+        assert_eq!(spans.len(), 2);
+        assert_eq!(&content[spans[0].start..spans[0].end], "#x");
+        assert_eq!(&content[spans[1].start..spans[1].end], "#y z#");
+        assert_eq!(spans[1].name, "y z");
+    }
+
+    #[test]
+    fn test_rewrite_tag_multi_word_and_unicode() {
+        assert_eq!(
+            rewrite_tag(
+                "Read #reading list# and #阅读",
+                "reading list",
+                Some("books")
+            ),
+            "Read #books and #阅读"
+        );
+        assert_eq!(
+            rewrite_tag("#阅读/小说 x", "阅读", Some("to read")),
+            "#to read/小说# x"
+        );
+        assert_eq!(
+            rewrite_tag("`#work` [#work](u) #work", "work", Some("job")),
+            "`#work` [#work](u) #job"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_tag_rename() {
+        let content = "See #work and #work/projects, not #workshop\n```\n#work\n```\n";
+        assert_eq!(
+            rewrite_tag(content, "work", Some("job")),
+            "See #job and #job/projects, not #workshop\n```\n#work\n```\n"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_tag_delete() {
+        let content = "Hello #rust world\n#rust\nend #rust/async";
+        assert_eq!(rewrite_tag(content, "rust", None), "Hello world\n\nend");
+    }
+
+    #[test]
+    fn test_rewrite_tag_frontmatter() {
+        let content = "---\ntags:\n  - \"ml\"\n  - other\nsource: x\n---\nBody #ml\n";
+        assert_eq!(
+            rewrite_tag(content, "ml", Some("machine-learning")),
+            "---\ntags:\n  - \"machine-learning\"\n  - other\nsource: x\n---\nBody #machine-learning\n"
+        );
+        let inline = "---\ntags: [ml, 'ml/deep', x]\n---\n";
+        assert_eq!(rewrite_tag(inline, "ml", None), "---\ntags: [x]\n---\n");
+    }
+
     #[test]
     fn test_get_parent_tag() {
         assert_eq!(get_parent_tag("work/projects"), Some("work".to_string()));
@@ -0,0 +1,99 @@
+use crate::commands::attachments::{rebuild_note_attachments, write_attachment};
+use crate::commands::files::images_dir_for;
+use crate::sync::icloud;
+use rusqlite::Connection;
+use sha2::{Digest, Sha256};
+use std::fs;
+
+/// Result of bringing attachments from the iCloud directory into the images
+/// store.
+#[derive(Debug, Clone, Default)]
+pub struct AttachmentImport {
+    pub imported: u32,
+    /// Placeholders still downloading, or copies not fully synced yet.
+    pub pending: u32,
+}
+
+/// Copy the attachments a note refers to into the iCloud directory. Called
+/// before the note itself is exported, so other devices find its images.
+pub fn export_note_attachments(conn: &Connection, note_id: &str) -> Result<u32, String> {
+    export_attachments(
+        conn,
+        "SELECT a.hash, a.file_name FROM attachments a JOIN note_attachments na ON na.attachment_id = a.id \
+         WHERE na.note_id = ?1",
+        [note_id],
+    )
+}
+
+/// Copy every attachment of a note that is not in the trash into the iCloud
+/// directory, for attachments stored before they were synced.
+pub fn export_all_attachments(conn: &Connection) -> Result<u32, String> {
+    export_attachments(
+        conn,
+        "SELECT DISTINCT a.hash, a.file_name FROM attachments a \
+         JOIN note_attachments na ON na.attachment_id = a.id JOIN notes n ON n.id = na.note_id \
+         WHERE n.is_trashed = 0",
+        [],
+    )
+}
+
+fn export_attachments<P: rusqlite::Params>(conn: &Connection, sql: &str, params: P) -> Result<u32, String> {
+    let Some(images_dir) = images_dir_for(conn) else {
+        return Ok(0);
+    };
+    let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
+    let attachments: Vec<(String, String)> = stmt
+        .query_map(params, |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut exported = 0;
+    for (hash, file_name) in attachments {
+        let source = images_dir.join(&file_name);
+        if !source.is_file() {
+            log::warn!("Attachment {} is missing from the images store", file_name);
+            continue;
+        }
+        if icloud::export_attachment(&hash, &file_name, &source)? {
+            exported += 1;
+        }
+    }
+    Ok(exported)
+}
+
+/// Copy attachments synced from other devices into the images store and
+/// link them to the notes that refer to them. Placeholders are asked to
+/// download and left for a later run, as are copies whose data does not
+/// match their hash yet.
+pub fn import_attachments(conn: &Connection) -> Result<AttachmentImport, String> {
+    let mut result = AttachmentImport::default();
+    let Some(images_dir) = images_dir_for(conn) else {
+        return Ok(result);
+    };
+    for attachment in icloud::list_icloud_attachments()? {
+        let target = images_dir.join(&attachment.file_name);
+        if target.exists() {
+            continue;
+        }
+        if !attachment.downloaded {
+            icloud::request_download(&attachment.path);
+            result.pending += 1;
+            continue;
+        }
+        let data = fs::read(&attachment.path)
+            .map_err(|e| format!("Failed to read {}: {}", attachment.path.display(), e))?;
+        if format!("{:x}", Sha256::digest(&data)) != attachment.hash {
+            log::debug!("Attachment {} has not fully synced yet", attachment.file_name);
+            result.pending += 1;
+            continue;
+        }
+        write_attachment(conn, &target, &data)?;
+        result.imported += 1;
+    }
+    // Notes synced before their attachments can now be linked to them
+    if result.imported > 0 {
+        rebuild_note_attachments(conn)?;
+    }
+    Ok(result)
+}
@@ -220,6 +220,111 @@ pub fn list_icloud_files() -> Result<Vec<PathBuf>, String> {
     Ok(files)
 }
 
+/// A file in the `attachments` folder of the iCloud directory, named
+/// `<sha256>_<file name>` after the images store file it is a copy of.
+#[derive(Debug, Clone)]
+pub struct SyncedAttachment {
+    pub hash: String,
+    pub file_name: String,
+    pub path: PathBuf,
+    /// False for an iCloud placeholder whose data has not downloaded yet.
+    pub downloaded: bool,
+}
+
+/// Where attachments are synced, next to the notes.
+pub fn get_attachments_dir() -> Result<PathBuf, String> {
+    Ok(get_icloud_dir()?.join("attachments"))
+}
+
+fn attachment_sync_name(hash: &str, file_name: &str) -> String {
+    format!("{}_{}", hash, file_name)
+}
+
+/// Split a synced attachment's name into its hash and file name.
+fn parse_attachment_sync_name(name: &str) -> Option<(&str, &str)> {
+    let (hash, file_name) = name.split_once('_')?;
+    (hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()) && !file_name.is_empty())
+        .then_some((hash, file_name))
+}
+
+/// Copy an attachment into the iCloud directory unless it is there already.
+/// Its name carries its hash, so an existing copy never needs replacing.
+/// Returns whether it was copied.
+pub fn export_attachment(hash: &str, file_name: &str, source: &Path) -> Result<bool, String> {
+    let dir = get_attachments_dir()?;
+    let name = attachment_sync_name(hash, file_name);
+    let target = dir.join(&name);
+    // An iCloud placeholder means another device uploaded it
+    if target.exists() || dir.join(format!(".{}.icloud", name)).exists() {
+        return Ok(false);
+    }
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create iCloud attachments directory: {}", e))?;
+    // Written under a hidden name first, so a half-written copy is never
+    // taken for the attachment
+    let partial = dir.join(format!(".{}.partial", name));
+    fs::copy(source, &partial)
+        .and_then(|_| fs::rename(&partial, &target))
+        .map_err(|e| {
+            let _ = fs::remove_file(&partial);
+            format!("Failed to copy attachment {} to iCloud: {}", file_name, e)
+        })?;
+    Ok(true)
+}
+
+/// Delete an attachment's copy from the iCloud directory.
+pub fn delete_attachment(hash: &str, file_name: &str) -> Result<(), String> {
+    let file_path = get_attachments_dir()?.join(attachment_sync_name(hash, file_name));
+    if file_path.exists() {
+        fs::remove_file(&file_path).map_err(|e| format!("Failed to delete attachment file: {}", e))?;
+    }
+    Ok(())
+}
+
+/// List the attachments in the iCloud directory, including placeholders for
+/// ones still to download.
+pub fn list_icloud_attachments() -> Result<Vec<SyncedAttachment>, String> {
+    let dir = get_attachments_dir()?;
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+    let entries =
+        fs::read_dir(&dir).map_err(|e| format!("Failed to read iCloud attachments directory: {}", e))?;
+
+    let mut attachments = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        // `.name.icloud` is the placeholder for `name`
+        let (name, downloaded) = match name.strip_prefix('.').and_then(|n| n.strip_suffix(".icloud")) {
+            Some(name) => (name.to_string(), false),
+            None if name.starts_with('.') => continue,
+            None => (name, true),
+        };
+        if let Some((hash, file_name)) = parse_attachment_sync_name(&name) {
+            attachments.push(SyncedAttachment {
+                hash: hash.to_string(),
+                file_name: file_name.to_string(),
+                path: if downloaded { path } else { dir.join(&name) },
+                downloaded,
+            });
+        }
+    }
+    Ok(attachments)
+}
+
+/// Ask iCloud to download a file it only has a placeholder for.
+pub fn request_download(path: &Path) {
+    #[cfg(target_os = "macos")]
+    {
+        if let Err(e) = std::process::Command::new("brctl").arg("download").arg(path).output() {
+            log::debug!("Failed to request download of {}: {}", path.display(), e);
+        }
+    }
+    #[cfg(not(target_os = "macos"))]
+    let _ = path;
+}
+
 /// Read .md file, parse frontmatter, return Note data.
 pub fn import_file(path: &Path) -> Result<Note, String> {
     let raw = fs::read_to_string(path)
@@ -228,10 +333,7 @@ pub fn import_file(path: &Path) -> Result<Note, String> {
     let (fm, body) = frontmatter::parse_frontmatter(&raw)?;
 
     let now = chrono::Utc::now().to_rfc3339();
-    let word_count = body
-        .split_whitespace()
-        .filter(|s| !s.is_empty())
-        .count() as i64;
+    let stats = crate::markdown::stats::text_stats(&body);
 
     let tags = if fm.tags.is_empty() {
         crate::markdown::tags::extract_tags(&body)
@@ -247,12 +349,15 @@ pub fn import_file(path: &Path) -> Result<Note, String> {
         updated_at: fm.updated_at.unwrap_or(now),
         is_trashed: false,
         is_pinned: fm.is_pinned,
-        word_count,
+        word_count: stats.words,
         file_path: Some(path.to_string_lossy().to_string()),
         sync_hash: None,
         tags,
-        state: "draft".to_string(),
-        workspace_id: None,
+        state: fm.state.unwrap_or_else(|| "draft".to_string()),
+        workspace_id: fm.workspace_id,
         version: 1,
+        extra_frontmatter: fm.extra,
+        char_count: stats.characters,
+        reading_minutes: stats.reading_minutes,
     })
 }
@@ -1,3 +1,4 @@
+pub mod attachments;
 pub mod icloud;
 pub mod reconciler;
 pub mod watcher;
@@ -1,5 +1,7 @@
+use crate::commands::files::images_dir_for;
 use crate::db::models::Note;
-use crate::sync::icloud;
+use crate::markdown::attachments::localize_attachment_urls;
+use crate::sync::{attachments, icloud};
 use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
@@ -55,36 +57,56 @@ pub fn reconcile(db_note: Option<&Note>, file_note: &Note) -> SyncAction {
 }
 
 /// Insert or update a note from file into the database using merge strategy.
-/// On conflict, preserves DB-only fields: state, workspace_id, is_trashed, created_at.
+/// On conflict, preserves DB-only fields: is_trashed, created_at. State and
+/// workspace come from the file's frontmatter; an unknown state or a workspace
+/// that does not exist locally falls back to the current value.
 pub(crate) fn import_note_to_db(conn: &Connection, note: &Note) -> Result<(), String> {
+    // The hash is of the file as synced, so the local image paths written
+    // below don't make the note look changed and send it back
     let hash = icloud::compute_sync_hash(&note.title, &note.content);
+    let content = match images_dir_for(conn) {
+        Some(images_dir) => localize_attachment_urls(&note.content, &images_dir),
+        None => note.content.clone(),
+    };
 
     conn.execute(
-        "INSERT INTO notes (id, title, content, created_at, updated_at, is_trashed, is_pinned, word_count, sync_hash)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+        "INSERT INTO notes (id, title, content, created_at, updated_at, is_trashed, is_pinned, word_count, sync_hash, state, workspace_id, extra_frontmatter)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9,
+                 CASE WHEN ?10 IN ('draft', 'review', 'published') THEN ?10 ELSE 'draft' END,
+                 (SELECT id FROM workspaces WHERE id = ?11), ?12)
          ON CONFLICT(id) DO UPDATE SET
            title = excluded.title,
            content = excluded.content,
            updated_at = excluded.updated_at,
            is_pinned = excluded.is_pinned,
            word_count = excluded.word_count,
-           sync_hash = excluded.sync_hash",
+           sync_hash = excluded.sync_hash,
+           state = CASE WHEN ?10 IN ('draft', 'review', 'published') THEN ?10 ELSE notes.state END,
+           workspace_id = CASE WHEN ?11 IS NULL THEN NULL ELSE COALESCE(excluded.workspace_id, notes.workspace_id) END,
+           extra_frontmatter = excluded.extra_frontmatter",
         rusqlite::params![
             note.id,
             note.title,
-            note.content,
+            content,
             note.created_at,
             note.updated_at,
             note.is_trashed as i32,
             note.is_pinned as i32,
             note.word_count,
-            hash
+            hash,
+            note.state,
+            note.workspace_id,
+            note.extra_frontmatter
         ],
     )
     .map_err(|e| e.to_string())?;
 
     crate::commands::notes::sync_tags(conn, &note.id, &note.tags)?;
-    crate::commands::notes::sync_note_links(conn, &note.id, &note.content)?;
+    crate::commands::notes::sync_note_links(conn, &note.id, &content)?;
+    crate::commands::properties::index_note_properties(conn, &note.id)?;
+    crate::commands::queries::sync_note_queries(conn, &note.id, &content)?;
+    crate::commands::tasks::sync_note_checklist(conn, &note.id, &content)?;
+    crate::commands::attachments::sync_note_attachments(conn, &note.id, &content)?;
 
     Ok(())
 }
@@ -98,7 +120,7 @@ pub(crate) fn fetch_note_by_id(conn: &Connection, id: &str) -> Option<Note> {
 fn fetch_all_notes(conn: &Connection) -> Result<Vec<Note>, String> {
     let mut stmt = conn
         .prepare(
-            "SELECT id, title, content, created_at, updated_at, is_trashed, is_pinned, word_count, file_path, sync_hash, state, workspace_id FROM notes WHERE is_trashed = 0",
+            "SELECT id, title, content, created_at, updated_at, is_trashed, is_pinned, word_count, file_path, sync_hash, state, workspace_id, version, extra_frontmatter FROM notes WHERE is_trashed = 0",
         )
         .map_err(|e| e.to_string())?;
 
@@ -119,6 +141,9 @@ fn fetch_all_notes(conn: &Connection) -> Result<Vec<Note>, String> {
                 state: row.get::<_, String>(10).unwrap_or_else(|_| "draft".to_string()),
                 workspace_id: row.get(11)?,
                 version: row.get::<_, i32>(12).unwrap_or(1),
+                extra_frontmatter: row.get(13)?,
+                char_count: 0,
+                reading_minutes: 0,
             })
         })
         .map_err(|e| e.to_string())?;
@@ -154,6 +179,19 @@ pub fn full_reconcile(
     let mut imported_note_ids: Vec<String> = Vec::new();
     let mut failures: Vec<FailedSyncOp> = Vec::new();
 
+    // Attachments go first: synced-in ones are in place for the notes that
+    // refer to them, and ours are uploaded before the notes exported below
+    match attachments::import_attachments(conn) {
+        Ok(result) if result.pending > 0 => {
+            log::info!("{} attachments are still downloading from iCloud", result.pending);
+        }
+        Ok(_) => {}
+        Err(e) => log::warn!("Failed to import attachments from iCloud: {}", e),
+    }
+    if let Err(e) = attachments::export_all_attachments(conn) {
+        log::warn!("Failed to export attachments to iCloud: {}", e);
+    }
+
     // Retry previously failed operations first
     if let Some(retries) = retry_queue {
         for mut op in retries {
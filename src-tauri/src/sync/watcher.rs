@@ -1,6 +1,6 @@
 use crate::commands::sync::SyncState;
 use crate::sync::reconciler::SyncAction;
-use crate::sync::{icloud, reconciler};
+use crate::sync::{attachments, icloud, reconciler};
 use notify::{Event, EventKind, RecursiveMode, RecommendedWatcher, Watcher};
 use rusqlite::Connection;
 use std::collections::HashMap;
@@ -16,6 +16,8 @@ use tauri::{AppHandle, Emitter, Manager};
 enum FileEvent {
     Changed(PathBuf),
     Removed(PathBuf),
+    /// Something arrived in the attachments folder.
+    AttachmentChanged,
 }
 
 /// Holds watcher handles so they can be dropped for graceful shutdown.
@@ -51,6 +53,10 @@ pub fn start_watcher(app_handle: &AppHandle) -> Result<WatcherState, Box<dyn std
     let icloud_dir = icloud::get_icloud_dir()
         .map_err(|e| format!("Cannot start iCloud watcher: {}", e))?;
     fs::create_dir_all(&icloud_dir)?;
+    let attachments_dir = icloud::get_attachments_dir()
+        .map_err(|e| format!("Cannot start iCloud watcher: {}", e))?;
+    fs::create_dir_all(&attachments_dir)?;
+    let watched_attachments_dir = attachments_dir.clone();
 
     let (tx, rx) = mpsc::channel::<FileEvent>();
 
@@ -64,7 +70,13 @@ pub fn start_watcher(app_handle: &AppHandle) -> Result<WatcherState, Box<dyn std
                         if is_icloud_placeholder(&path) {
                             continue;
                         }
-                        if path.extension().and_then(|e| e.to_str()) == Some("md") {
+                        if path.parent() == Some(watched_attachments_dir.as_path()) {
+                            // Also fires when a placeholder's data has downloaded
+                            let hidden = path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with('.'));
+                            if !hidden {
+                                let _ = tx_clone.send(FileEvent::AttachmentChanged);
+                            }
+                        } else if path.extension().and_then(|e| e.to_str()) == Some("md") {
                             let _ = tx_clone.send(FileEvent::Changed(path));
                         }
                     }
@@ -90,6 +102,7 @@ pub fn start_watcher(app_handle: &AppHandle) -> Result<WatcherState, Box<dyn std
     // Start watching iCloud directory
     let mut icloud_w = icloud_watcher;
     icloud_w.watch(&icloud_dir, RecursiveMode::NonRecursive)?;
+    icloud_w.watch(&attachments_dir, RecursiveMode::NonRecursive)?;
 
     // --- MCP trigger file watcher ---
     let app_support_dir = app_handle.path().app_data_dir()
@@ -125,6 +138,7 @@ pub fn start_watcher(app_handle: &AppHandle) -> Result<WatcherState, Box<dyn std
         let mut pending_changes: HashMap<PathBuf, Instant> = HashMap::new();
         let mut pending_removes: HashMap<PathBuf, Instant> = HashMap::new();
         let mut pending_full_reconcile = false;
+        let mut pending_attachments: Option<Instant> = None;
         let mut last_full_reconcile_trigger: Option<Instant> = None;
 
         loop {
@@ -141,6 +155,9 @@ pub fn start_watcher(app_handle: &AppHandle) -> Result<WatcherState, Box<dyn std
                     FileEvent::Removed(path) => {
                         pending_removes.insert(path, Instant::now());
                     }
+                    FileEvent::AttachmentChanged => {
+                        pending_attachments = Some(Instant::now());
+                    }
                 },
                 Err(mpsc::RecvTimeoutError::Timeout) => {
                     let now = Instant::now();
@@ -158,6 +175,9 @@ pub fn start_watcher(app_handle: &AppHandle) -> Result<WatcherState, Box<dyn std
                                         match reconciler::full_reconcile(&conn, None, None) {
                                             Ok(result) => {
                                                 log::info!("MCP trigger: synced {} files", result.files_synced);
+                                                // Agents may have changed notes or tasks directly in the DB
+                                                let _ = crate::commands::tasks::reconcile_checklists(&conn);
+                                                let _ = crate::commands::queries::refresh_query_results(&conn, crate::commands::queries::QueryChange::All);
                                                 if !result.imported_note_ids.is_empty() {
                                                     let _ = app.emit("notes-imported", &result.imported_note_ids);
                                                 }
@@ -178,6 +198,23 @@ pub fn start_watcher(app_handle: &AppHandle) -> Result<WatcherState, Box<dyn std
                         }
                     }
 
+                    // Bring in attachments that finished syncing
+                    if pending_attachments.is_some_and(|t| now.duration_since(t) >= Duration::from_millis(500)) {
+                        pending_attachments = None;
+                        let db = app.state::<Mutex<Connection>>();
+                        match db.lock() {
+                            Ok(conn) => match attachments::import_attachments(&conn) {
+                                Ok(result) if result.imported > 0 => {
+                                    log::info!("Synced {} attachments from iCloud", result.imported);
+                                    let _ = app.emit("sync-status-changed", ());
+                                }
+                                Ok(_) => {}
+                                Err(e) => log::warn!("Failed to import attachments from iCloud: {}", e),
+                            },
+                            Err(e) => log::error!("Failed to acquire DB lock for attachment sync: {}", e),
+                        };
+                    }
+
                     // Process file changes
                     let ready_changes: Vec<PathBuf> = pending_changes
                         .iter()
@@ -211,6 +248,7 @@ pub fn start_watcher(app_handle: &AppHandle) -> Result<WatcherState, Box<dyn std
                             for path in &ready_removes {
                                 process_file_removal(&conn, path);
                             }
+                            let _ = crate::commands::queries::refresh_query_results(&conn, crate::commands::queries::QueryChange::All);
                             // Update SyncState so UI shows "Synced"
                             let sync_state = app.state::<Mutex<SyncState>>();
                             if let Ok(mut state) = sync_state.lock() {
@@ -15,12 +15,15 @@ import TableCell from "@tiptap/extension-table-cell";
 import TableHeader from "@tiptap/extension-table-header";
 import { Plugin, PluginKey } from "@tiptap/pm/state";
 import { convertFileSrc } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { Markdown } from "tiptap-markdown";
 import clsx from "clsx";
 import tippy, { type Instance as TippyInstance } from "tippy.js";
 import { InlineTag } from "./extensions/InlineTag";
 import { WikiLink } from "./extensions/WikiLink";
 import { SlashCommand, slashCommands } from "./extensions/SlashCommand";
+import { QueryBlock } from "./extensions/QueryBlock";
+import { NoteEmbed } from "./extensions/NoteEmbed";
 import { SlashCommandMenu } from "./SlashCommandMenu";
 import * as tauri from "../../lib/tauri";
 import "../../styles/code-highlight.css";
@@ -32,6 +35,8 @@ interface MarkdownEditorProps {
   onTagClick?: (tag: string) => void;
   onWikiLinkClick?: (title: string) => void;
   wordCount?: number;
+  noteId?: string;
+  noteVersion?: number;
 }
 
 export function MarkdownEditor({
@@ -40,6 +45,8 @@ export function MarkdownEditor({
   onTagClick,
   onWikiLinkClick,
   wordCount,
+  noteId,
+  noteVersion,
 }: MarkdownEditorProps) {
   const editor = useEditor({
     extensions: [
@@ -131,6 +138,8 @@ export function MarkdownEditor({
       }),
       InlineTag.configure({ onTagClick }),
       WikiLink.configure({ onLinkClick: onWikiLinkClick }),
+      QueryBlock.configure({ onResultClick: onWikiLinkClick }),
+      NoteEmbed.configure({ onEmbedClick: onWikiLinkClick }),
       SlashCommand.configure({
         suggestion: {
           items: ({ query }: { query: string }) => {
@@ -209,6 +218,41 @@ export function MarkdownEditor({
     }
   }, [content, editor]);
 
+  // Load live query results and embedded notes for the note, and reload them
+  // whenever the note is saved or a sync may have changed what they show
+  useEffect(() => {
+    if (!editor || !noteId) return;
+    let cancelled = false;
+    const load = () => {
+      tauri
+        .getNoteQueryResults(noteId)
+        .then((results) => {
+          if (!cancelled && !editor.isDestroyed) {
+            editor.commands.setQueryResults(results);
+          }
+        })
+        .catch(() => {});
+      tauri
+        .renderNoteEmbeds(noteId)
+        .then((embeds) => {
+          if (!cancelled && !editor.isDestroyed) {
+            editor.commands.setNoteEmbeds(embeds);
+          }
+        })
+        .catch(() => {});
+    };
+    load();
+
+    const unlisteners = [
+      listen("sync-status-changed", load),
+      listen("notes-imported", load),
+    ];
+    return () => {
+      cancelled = true;
+      unlisteners.forEach((p) => p.then((fn) => fn()));
+    };
+  }, [editor, noteId, noteVersion]);
+
   if (!editor) return null;
 
   return (
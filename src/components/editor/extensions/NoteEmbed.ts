@@ -0,0 +1,169 @@
+import { Extension } from "@tiptap/core";
+import { Plugin, PluginKey } from "@tiptap/pm/state";
+import { Decoration, DecorationSet } from "@tiptap/pm/view";
+import type { Node as PMNode } from "@tiptap/pm/model";
+import type { RenderedEmbed } from "../../../types/embed";
+
+export interface NoteEmbedOptions {
+  onEmbedClick?: (target: string) => void;
+}
+
+declare module "@tiptap/core" {
+  interface Commands<ReturnType> {
+    noteEmbed: {
+      setNoteEmbeds: (embeds: RenderedEmbed[]) => ReturnType;
+    };
+  }
+}
+
+const noteEmbedKey = new PluginKey<DecorationSet>("noteEmbed");
+
+const EMBED_RE = /!\[\[([^[\]\n]+)\]\]/g;
+
+// The `[[...]]` part of an embed is parsed into a wikiLink node, so the
+// markdown is rebuilt from the paragraph's children
+function blockMarkdown(node: PMNode): string {
+  let text = "";
+  node.forEach((child) => {
+    if (child.type.name === "wikiLink") text += `[[${child.attrs.title}]]`;
+    else if (child.isText) text += child.text ?? "";
+  });
+  return text;
+}
+
+function renderEmbed(
+  target: string,
+  embed: RenderedEmbed | undefined,
+  onEmbedClick?: (target: string) => void,
+): HTMLElement {
+  const container = document.createElement("div");
+  container.className = "bruin-embed";
+  container.contentEditable = "false";
+
+  const header = document.createElement("div");
+  header.className = "bruin-embed-title";
+  header.textContent = target;
+  if (onEmbedClick) {
+    header.addEventListener("mousedown", (event) => {
+      event.preventDefault();
+      onEmbedClick(target);
+    });
+  }
+  container.appendChild(header);
+
+  const body = document.createElement("div");
+  if (!embed) {
+    body.className = "bruin-embed-pending";
+    body.textContent = "Loading…";
+  } else if (embed.error) {
+    body.className = "bruin-embed-error";
+    body.textContent = embed.error;
+  } else {
+    body.className = "bruin-embed-body";
+    body.innerHTML = embed.html;
+    // Wiki links inside the embed open like the editor's own links
+    if (onEmbedClick) {
+      body.addEventListener("mousedown", (event) => {
+        const link = (event.target as HTMLElement).closest("[data-wiki-title]");
+        if (!link) return;
+        event.preventDefault();
+        onEmbedClick(link.getAttribute("data-wiki-title") ?? "");
+      });
+    }
+  }
+  container.appendChild(body);
+  return container;
+}
+
+function buildDecorations(
+  doc: PMNode,
+  embeds: RenderedEmbed[],
+  generation: number,
+  onEmbedClick?: (target: string) => void,
+): DecorationSet {
+  const decorations: Decoration[] = [];
+  const used = new Set<RenderedEmbed>();
+  doc.descendants((node, pos) => {
+    if (node.type.name === "codeBlock") return false;
+    if (!node.isTextblock) return true;
+
+    const targets = [...blockMarkdown(node).matchAll(EMBED_RE)].map((m) => m[1].trim());
+    targets.forEach((target, i) => {
+      // Embeds are rendered in document order; match by target so an edit
+      // above one does not show another's content
+      const embed = embeds.find((e) => e.target === target && !used.has(e));
+      if (embed) used.add(embed);
+      decorations.push(
+        Decoration.widget(
+          pos + node.nodeSize,
+          () => renderEmbed(target, embed, onEmbedClick),
+          { side: -1, key: `embed-${generation}-${pos}-${i}-${target}` },
+        ),
+      );
+    });
+    return false;
+  });
+  return DecorationSet.create(doc, decorations);
+}
+
+/**
+ * Renders the content of `![[Note]]` and `![[Note#Section]]` embeds below
+ * the paragraph containing them. Content is rendered by the backend and
+ * pushed in with `setNoteEmbeds`.
+ */
+export const NoteEmbed = Extension.create<NoteEmbedOptions>({
+  name: "noteEmbed",
+
+  addOptions() {
+    return {
+      onEmbedClick: undefined,
+    };
+  },
+
+  addStorage() {
+    return {
+      embeds: [] as RenderedEmbed[],
+      // Bumped on every update so widgets are redrawn with the new content
+      generation: 0,
+    };
+  },
+
+  addCommands() {
+    return {
+      setNoteEmbeds:
+        (embeds: RenderedEmbed[]) =>
+        ({ tr, dispatch }) => {
+          this.storage.embeds = embeds;
+          this.storage.generation += 1;
+          if (dispatch) {
+            tr.setMeta(noteEmbedKey, true);
+            tr.setMeta("addToHistory", false);
+          }
+          return true;
+        },
+    };
+  },
+
+  addProseMirrorPlugins() {
+    const storage = this.storage;
+    const onEmbedClick = this.options.onEmbedClick;
+    return [
+      new Plugin({
+        key: noteEmbedKey,
+        state: {
+          init: (_, state) =>
+            buildDecorations(state.doc, storage.embeds, storage.generation, onEmbedClick),
+          apply: (tr, old) => {
+            if (!tr.docChanged && !tr.getMeta(noteEmbedKey)) return old;
+            return buildDecorations(tr.doc, storage.embeds, storage.generation, onEmbedClick);
+          },
+        },
+        props: {
+          decorations(state) {
+            return noteEmbedKey.getState(state);
+          },
+        },
+      }),
+    ];
+  },
+});
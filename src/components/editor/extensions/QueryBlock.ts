@@ -0,0 +1,182 @@
+import { Extension } from "@tiptap/core";
+import { Plugin, PluginKey } from "@tiptap/pm/state";
+import { Decoration, DecorationSet } from "@tiptap/pm/view";
+import type { Node as PMNode } from "@tiptap/pm/model";
+import type { NoteQueryResult, QueryCell, QueryResult } from "../../../types/query";
+
+export const QUERY_LANG = "bruin-query";
+
+export interface QueryBlockOptions {
+  onResultClick?: (title: string) => void;
+}
+
+declare module "@tiptap/core" {
+  interface Commands<ReturnType> {
+    queryBlock: {
+      setQueryResults: (results: NoteQueryResult[]) => ReturnType;
+    };
+  }
+}
+
+const queryBlockKey = new PluginKey<DecorationSet>("queryBlock");
+
+function formatCell(value: QueryCell): string {
+  if (value === null) return "";
+  if (typeof value === "boolean") return value ? "✓" : "";
+  return String(value);
+}
+
+function renderResult(
+  result: QueryResult | undefined,
+  onResultClick?: (title: string) => void,
+): HTMLElement {
+  const container = document.createElement("div");
+  container.className = "bruin-query-result";
+  container.contentEditable = "false";
+
+  if (!result) {
+    container.classList.add("bruin-query-pending");
+    container.textContent = "Evaluating query…";
+    return container;
+  }
+  if (result.error) {
+    container.classList.add("bruin-query-error");
+    container.textContent = result.error;
+    return container;
+  }
+  if (result.rows.length === 0) {
+    container.classList.add("bruin-query-empty");
+    container.textContent = "No results";
+    return container;
+  }
+
+  const titleCell = (title: string) => {
+    const span = document.createElement("span");
+    span.className = "bruin-query-title";
+    span.textContent = title;
+    if (onResultClick && result.kind !== "tasks") {
+      span.addEventListener("mousedown", (event) => {
+        event.preventDefault();
+        onResultClick(title);
+      });
+    }
+    return span;
+  };
+
+  if (result.kind === "list") {
+    const ul = document.createElement("ul");
+    for (const row of result.rows) {
+      const li = document.createElement("li");
+      li.appendChild(titleCell(row.title));
+      ul.appendChild(li);
+    }
+    container.appendChild(ul);
+    return container;
+  }
+
+  const table = document.createElement("table");
+  const head = table.createTHead().insertRow();
+  for (const column of ["title", ...result.columns]) {
+    const th = document.createElement("th");
+    th.textContent = column;
+    head.appendChild(th);
+  }
+  const body = table.createTBody();
+  for (const row of result.rows) {
+    const tr = body.insertRow();
+    tr.insertCell().appendChild(titleCell(row.title));
+    for (const value of row.values) {
+      tr.insertCell().textContent = formatCell(value);
+    }
+  }
+  container.appendChild(table);
+  return container;
+}
+
+function buildDecorations(
+  doc: PMNode,
+  results: NoteQueryResult[],
+  onResultClick?: (title: string) => void,
+): DecorationSet {
+  const decorations: Decoration[] = [];
+  let index = 0;
+  doc.descendants((node, pos) => {
+    if (node.type.name !== "codeBlock") return true;
+    if (node.attrs.language === QUERY_LANG) {
+      const blockIndex = index++;
+      const stored = results.find((r) => r.block_index === blockIndex);
+      // Stale results are hidden until the backend re-evaluates the edited query
+      const current =
+        stored && stored.query.trim() === node.textContent.trim()
+          ? stored.result
+          : undefined;
+      decorations.push(
+        Decoration.widget(
+          pos + node.nodeSize,
+          () => renderResult(current, onResultClick),
+          { side: -1, key: `query-${blockIndex}-${stored?.evaluated_at ?? ""}` },
+        ),
+      );
+    }
+    return false;
+  });
+  return DecorationSet.create(doc, decorations);
+}
+
+/**
+ * Renders the stored results of ```bruin-query blocks below each block.
+ * Results are evaluated by the backend and pushed in with `setQueryResults`.
+ */
+export const QueryBlock = Extension.create<QueryBlockOptions>({
+  name: "queryBlock",
+
+  addOptions() {
+    return {
+      onResultClick: undefined,
+    };
+  },
+
+  addStorage() {
+    return {
+      results: [] as NoteQueryResult[],
+    };
+  },
+
+  addCommands() {
+    return {
+      setQueryResults:
+        (results: NoteQueryResult[]) =>
+        ({ tr, dispatch }) => {
+          this.storage.results = results;
+          if (dispatch) {
+            tr.setMeta(queryBlockKey, true);
+            tr.setMeta("addToHistory", false);
+          }
+          return true;
+        },
+    };
+  },
+
+  addProseMirrorPlugins() {
+    const storage = this.storage;
+    const onResultClick = this.options.onResultClick;
+    return [
+      new Plugin({
+        key: queryBlockKey,
+        state: {
+          init: (_, state) =>
+            buildDecorations(state.doc, storage.results, onResultClick),
+          apply: (tr, old) => {
+            if (!tr.docChanged && !tr.getMeta(queryBlockKey)) return old;
+            return buildDecorations(tr.doc, storage.results, onResultClick);
+          },
+        },
+        props: {
+          decorations(state) {
+            return queryBlockKey.getState(state);
+          },
+        },
+      }),
+    ];
+  },
+});
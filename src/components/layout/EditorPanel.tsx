@@ -6,6 +6,9 @@ import { useNotes } from "../../hooks/useNotes";
 import { useTags } from "../../hooks/useTags";
 import { useSettingsStore } from "../../stores/settingsStore";
 import { useToastStore } from "../../stores/toastStore";
+import { useUIStore } from "../../stores/uiStore";
+import { getThemeById } from "../../lib/themes";
+import { exportDocument } from "../../lib/documentExport";
 import { MarkdownEditor } from "../editor/MarkdownEditor";
 import { EmptyState } from "../common/EmptyState";
 import { ConfirmDialog } from "../ui/ConfirmDialog";
@@ -47,14 +50,18 @@ export function EditorPanel() {
   const [confirmDelete, setConfirmDelete] = useState(false);
   const saveTimerRef = useRef<ReturnType<typeof setTimeout>>(undefined);
   const noteIdRef = useRef<string | null>(null);
+  const savePendingRef = useRef(false);
 
-  // Sync local state when note changes
+  // Sync local state when note changes, or when the open note was changed
+  // elsewhere (e.g. a linked task was completed) and there are no unsaved edits
   useEffect(() => {
     if (currentNote) {
       if (noteIdRef.current !== currentNote.id) {
         setTitle(currentNote.title);
         setContent(currentNote.content);
         noteIdRef.current = currentNote.id;
+      } else if (!savePendingRef.current) {
+        setContent(currentNote.content);
       }
     } else {
       setTitle("");
@@ -70,7 +77,9 @@ export function EditorPanel() {
     (newTitle: string, newContent: string) => {
       if (!currentNote) return;
       clearTimeout(saveTimerRef.current);
+      savePendingRef.current = true;
       saveTimerRef.current = setTimeout(() => {
+        savePendingRef.current = false;
         updateNote({
           id: currentNote.id,
           title: newTitle,
@@ -101,16 +110,44 @@ export function EditorPanel() {
     loadTags();
   };
 
-  const handleWikiLinkClick = (noteTitle: string) => {
-    const target = notes.find(
-      (n) => n.title.toLowerCase() === noteTitle.toLowerCase(),
-    );
-    if (target) {
-      selectNote(target.id);
-    }
+  // Heading to scroll to once a `[[Note#Heading]]` target has loaded
+  const [pendingHeading, setPendingHeading] = useState<string | null>(null);
+
+  const handleWikiLinkClick = (linkTarget: string) => {
+    tauri
+      .resolveWikiLink(linkTarget, currentNote?.id)
+      .then((resolved) => {
+        if (resolved.note_id !== currentNote?.id) {
+          selectNote(resolved.note_id);
+        }
+        setPendingHeading(resolved.section?.heading ?? null);
+      })
+      .catch(() => {
+        const target = notes.find(
+          (n) => n.title.toLowerCase() === linkTarget.toLowerCase(),
+        );
+        if (target) {
+          selectNote(target.id);
+        }
+      });
   };
 
-  const handleExport = async (fmt: "markdown" | "html" | "pdf") => {
+  useEffect(() => {
+    if (!pendingHeading) return;
+    const frame = requestAnimationFrame(() => {
+      const headings = document.querySelectorAll<HTMLElement>(
+        "[data-testid='editor-body'] :is(h1, h2, h3, h4, h5, h6)",
+      );
+      const match = Array.from(headings).find(
+        (h) => h.textContent?.trim() === pendingHeading,
+      );
+      match?.scrollIntoView({ behavior: "smooth", block: "start" });
+      setPendingHeading(null);
+    });
+    return () => cancelAnimationFrame(frame);
+  }, [pendingHeading, content]);
+
+  const handleExport = async (fmt: "markdown" | "html" | "pdf" | "epub") => {
     if (!currentNote) return;
     setExportOpen(false);
 
@@ -126,7 +163,9 @@ export function EditorPanel() {
           addToast({ type: "success", message: "Exported as Markdown" });
         }
       } else if (fmt === "html") {
-        const html = await tauri.exportNoteHtml(currentNote.id);
+        const html = await tauri.exportNoteHtml(currentNote.id, {
+          theme: getThemeById(useUIStore.getState().theme),
+        });
         const path = await save({
           defaultPath: `${currentNote.title || "note"}.html`,
           filters: [{ name: "HTML", extensions: ["html"] }],
@@ -135,15 +174,8 @@ export function EditorPanel() {
           await writeTextFile(path, html);
           addToast({ type: "success", message: "Exported as HTML" });
         }
-      } else if (fmt === "pdf") {
-        const html = await tauri.exportNoteHtml(currentNote.id);
-        const iframe = document.createElement("iframe");
-        iframe.style.display = "none";
-        document.body.appendChild(iframe);
-        iframe.contentDocument?.write(html);
-        iframe.contentDocument?.close();
-        iframe.contentWindow?.print();
-        setTimeout(() => document.body.removeChild(iframe), 1000);
+      } else {
+        await exportDocument(fmt, { note_id: currentNote.id }, currentNote.title || "note");
       }
     } catch (err) {
       addToast({ type: "error", message: `Export failed: ${err}` });
@@ -348,6 +380,8 @@ export function EditorPanel() {
           onTagClick={handleTagClick}
           onWikiLinkClick={handleWikiLinkClick}
           wordCount={wordCount}
+          noteId={currentNote.id}
+          noteVersion={currentNote.version}
         />
       </div>
 
@@ -355,6 +389,10 @@ export function EditorPanel() {
       <div data-testid="editor-statusbar" className="px-8 py-2 border-t border-bear-border/50 flex items-center justify-between text-[11px] text-bear-text-muted">
         <span data-testid="editor-word-count">
           {wordCount} {wordCount === 1 ? "word" : "words"}
+          {currentNote.char_count !== undefined && (
+            <> · {currentNote.char_count} {currentNote.char_count === 1 ? "character" : "characters"}</>
+          )}
+          {!!currentNote.reading_minutes && <> · {currentNote.reading_minutes} min read</>}
         </span>
         <div className="flex items-center gap-3">
           {/* Export dropdown */}
@@ -388,6 +426,12 @@ export function EditorPanel() {
                     >
                       PDF
                     </button>
+                    <button
+                      onClick={() => handleExport("epub")}
+                      className="w-full text-left px-3 py-1.5 text-[12px] text-bear-text hover:bg-bear-hover transition-colors"
+                    >
+                      EPUB
+                    </button>
                   </div>
                 </>
               )}
@@ -1,5 +1,6 @@
 import { useState, useMemo, useCallback, useRef, useEffect, memo } from "react";
 import clsx from "clsx";
+import { convertFileSrc } from "@tauri-apps/api/core";
 import { formatDistanceToNow } from "date-fns";
 import { useNotes } from "../../hooks/useNotes";
 import { useTagStore } from "../../stores/tagStore";
@@ -70,9 +71,19 @@ const NoteItem = memo(function NoteItem({
           {note.title || "Untitled"}
         </span>
       </div>
-      <p className="text-[12px] text-bear-text-secondary mt-0.5 line-clamp-2 leading-relaxed">
-        {note.preview || "No content"}
-      </p>
+      <div className="flex items-start gap-2 mt-0.5">
+        <p className="text-[12px] text-bear-text-secondary line-clamp-2 leading-relaxed flex-1 min-w-0">
+          {note.preview || "No content"}
+        </p>
+        {note.thumbnail && (
+          <img
+            src={convertFileSrc(note.thumbnail)}
+            alt=""
+            loading="lazy"
+            className="w-9 h-9 rounded object-cover shrink-0"
+          />
+        )}
+      </div>
       <div className="flex items-center gap-2 mt-1">
         <span className="text-[11px] text-bear-text-muted">
           {formatDistanceToNow(new Date(note.updated_at), {
@@ -10,6 +10,8 @@ import { WorkspaceSelector } from "../sidebar/WorkspaceSelector";
 import { ContextMenu, type ContextMenuItem } from "../ui/ContextMenu";
 import { ConfirmDialog } from "../ui/ConfirmDialog";
 import { importMarkdownFiles, getSyncStatus } from "../../lib/tauri";
+import { exportDocument } from "../../lib/documentExport";
+import { useToastStore } from "../../stores/toastStore";
 import type { SyncState } from "../../types/sync";
 import type { TagTreeNode } from "../../types/tag";
 
@@ -26,6 +28,7 @@ export function Sidebar() {
   const isTaskPanelOpen = useUIStore((s) => s.isTaskPanelOpen);
   const toggleAgentDashboard = useUIStore((s) => s.toggleAgentDashboard);
   const isAgentDashboardOpen = useUIStore((s) => s.isAgentDashboardOpen);
+  const addToast = useToastStore((s) => s.addToast);
 
   const [syncStatus, setSyncStatus] = useState<SyncState>({
     is_syncing: false,
@@ -91,15 +94,34 @@ export function Sidebar() {
     }
   };
 
-  const handleImport = async () => {
-    const selected = await open({
-      multiple: true,
-      filters: [{ name: "Markdown", extensions: ["md"] }],
-    });
+  // Alt-click imports a whole folder; subfolders become nested tags.
+  // TextBundles, TextPacks and Bear backups keep their images.
+  const handleImport = async (e: React.MouseEvent) => {
+    const selected = await open(
+      e.altKey
+        ? { directory: true }
+        : {
+            multiple: true,
+            filters: [
+              { name: "Markdown", extensions: ["md", "markdown"] },
+              { name: "Bear / TextBundle", extensions: ["bear2bk", "textbundle", "textpack"] },
+              { name: "Evernote / Joplin", extensions: ["enex", "jex"] },
+              { name: "Notion export", extensions: ["zip"] },
+            ],
+          },
+    );
 
     if (selected) {
       const paths = Array.isArray(selected) ? selected : [selected];
-      await importMarkdownFiles(paths);
+      try {
+        const report = await importMarkdownFiles(paths);
+        const updated = report.updated > 0 ? `, updated ${report.updated}` : "";
+        const skipped = report.skipped > 0 ? `, skipped ${report.skipped}` : "";
+        const tasks = report.tasks > 0 ? ` (${report.tasks} to-dos)` : "";
+        addToast({ type: "success", message: `Imported ${report.imported} notes${tasks}${updated}${skipped}` });
+      } catch (err) {
+        addToast({ type: "error", message: `Import failed: ${err}` });
+      }
       loadNotes({ trashed: false, sort_by: "updated_at", sort_order: "desc" });
     }
   };
@@ -147,6 +169,21 @@ export function Sidebar() {
         setTimeout(() => renameInputRef.current?.focus(), 50);
       },
     },
+    {
+      label: "Export as PDF...",
+      icon: "\u21e9",
+      action: () => {
+        exportDocument("pdf", { tag: tagContextMenu.node.fullPath }, tagContextMenu.node.name);
+      },
+      separator: true,
+    },
+    {
+      label: "Export as EPUB...",
+      icon: "\u21e9",
+      action: () => {
+        exportDocument("epub", { tag: tagContextMenu.node.fullPath }, tagContextMenu.node.name);
+      },
+    },
     {
       label: "Delete Tag",
       icon: "\u{1F5D1}",
@@ -407,7 +444,7 @@ export function Sidebar() {
           <button
             data-testid="btn-import"
             onClick={handleImport}
-            title="Import Markdown Files"
+            title="Import Notes (Alt-click for a folder)"
             className="w-6 h-6 flex items-center justify-center rounded text-bear-text-muted hover:text-bear-text hover:bg-bear-hover transition-colors duration-150"
           >
             <svg
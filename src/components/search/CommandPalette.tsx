@@ -3,9 +3,12 @@ import { Command } from "cmdk";
 import { useSearch } from "../../hooks/useSearch";
 import { useNoteStore } from "../../stores/noteStore";
 import { useUIStore } from "../../stores/uiStore";
+import { useWorkspaceStore } from "../../stores/workspaceStore";
 import { useToastStore } from "../../stores/toastStore";
 import * as tauri from "../../lib/tauri";
-import { save } from "@tauri-apps/plugin-dialog";
+import { getThemeById } from "../../lib/themes";
+import { exportDocument } from "../../lib/documentExport";
+import { open, save } from "@tauri-apps/plugin-dialog";
 import { writeTextFile } from "@tauri-apps/plugin-fs";
 
 interface PaletteCommand {
@@ -22,6 +25,8 @@ export function CommandPalette() {
   const createNote = useNoteStore((s) => s.createNote);
   const trashNote = useNoteStore((s) => s.trashNote);
   const selectedNoteId = useNoteStore((s) => s.selectedNoteId);
+  const currentNoteTitle = useNoteStore((s) => s.currentNote?.title);
+  const currentWorkspaceId = useWorkspaceStore((s) => s.currentWorkspaceId);
   const addToast = useToastStore((s) => s.addToast);
 
   const { query, setQuery, results, isSearching } = useSearch();
@@ -97,6 +102,108 @@ export function CommandPalette() {
       icon: "\u2b24",
       action: () => { useUIStore.getState().toggleAgentDashboard(); toggleCommandPalette(); },
     },
+    {
+      id: "publish-site",
+      label: "Publish Site...",
+      icon: "\u2197",
+      action: async () => {
+        toggleCommandPalette();
+        try {
+          const dir = await open({ directory: true, title: "Choose a folder for the site" });
+          if (typeof dir !== "string") return;
+          const result = await tauri.publishSite({
+            output_dir: dir,
+            workspace_id: useWorkspaceStore.getState().currentWorkspaceId,
+            theme: getThemeById(useUIStore.getState().theme),
+          });
+          addToast({
+            type: "success",
+            message: `Published site: ${result.pages_written} pages updated, ${result.pages_unchanged} unchanged`,
+          });
+        } catch (err) { addToast({ type: "error", message: `Publish failed: ${err}` }); }
+      },
+    },
+    {
+      id: "clip-clipboard",
+      label: "Clip Web Page from Clipboard",
+      icon: "\u2702",
+      action: async () => {
+        toggleCommandPalette();
+        try {
+          // A copied address is fetched; anything else is taken as the page's HTML
+          const text = (await navigator.clipboard.readText()).trim();
+          const isUrl = /^https?:\/\/\S+$/.test(text);
+          const note = await tauri.clipWebPage({
+            url: isUrl ? text : null,
+            html: isUrl ? null : text,
+            workspace_id: currentWorkspaceId,
+          });
+          addToast({ type: "success", message: `Clipped "${note.title}"` });
+          await useNoteStore.getState().loadNotes();
+          selectNote(note.id);
+        } catch (err) { addToast({ type: "error", message: `Clip failed: ${err}` }); }
+      },
+    },
+    {
+      id: "clip-file",
+      label: "Clip Saved Web Page...",
+      icon: "\u2702",
+      action: async () => {
+        toggleCommandPalette();
+        try {
+          const path = await open({ filters: [{ name: "Web page", extensions: ["html", "htm"] }] });
+          if (typeof path !== "string") return;
+          const note = await tauri.clipWebPage({ path, workspace_id: currentWorkspaceId });
+          addToast({ type: "success", message: `Clipped "${note.title}"` });
+          await useNoteStore.getState().loadNotes();
+          selectNote(note.id);
+        } catch (err) { addToast({ type: "error", message: `Clip failed: ${err}` }); }
+      },
+    },
+    {
+      id: "export-vault",
+      label: "Export Vault...",
+      icon: "\u21e9",
+      action: async () => {
+        toggleCommandPalette();
+        try {
+          const path = await save({
+            defaultPath: "Bruin Vault.zip",
+            filters: [{ name: "Zip", extensions: ["zip"] }],
+          });
+          if (!path) return;
+          const result = await tauri.exportVault({
+            output_path: path,
+            workspace_id: currentWorkspaceId,
+          });
+          addToast({
+            type: "success",
+            message: `Exported ${result.notes} notes and ${result.attachments} attachments`
+              + (result.missing_attachments ? ` (${result.missing_attachments} images not found)` : ""),
+          });
+        } catch (err) { addToast({ type: "error", message: `Export failed: ${err}` }); }
+      },
+    },
+    ...(currentWorkspaceId ? [
+      {
+        id: "export-workspace-pdf",
+        label: "Export Workspace as PDF...",
+        icon: "\u21e9",
+        action: () => {
+          toggleCommandPalette();
+          exportDocument("pdf", { workspace_id: currentWorkspaceId }, "workspace");
+        },
+      },
+      {
+        id: "export-workspace-epub",
+        label: "Export Workspace as EPUB...",
+        icon: "\u21e9",
+        action: () => {
+          toggleCommandPalette();
+          exportDocument("epub", { workspace_id: currentWorkspaceId }, "workspace");
+        },
+      },
+    ] : []),
     ...(selectedNoteId ? [
       {
         id: "export-md",
@@ -118,12 +225,26 @@ export function CommandPalette() {
         action: async () => {
           toggleCommandPalette();
           try {
-            const html = await tauri.exportNoteHtml(selectedNoteId);
+            const html = await tauri.exportNoteHtml(selectedNoteId, {
+              theme: getThemeById(useUIStore.getState().theme),
+            });
             const path = await save({ filters: [{ name: "HTML", extensions: ["html"] }] });
             if (path) { await writeTextFile(path, html); addToast({ type: "success", message: "Exported as HTML" }); }
           } catch (err) { addToast({ type: "error", message: `Export failed: ${err}` }); }
         },
       },
+      {
+        id: "export-pdf",
+        label: "Export as PDF",
+        icon: "\u21e9",
+        action: () => { toggleCommandPalette(); exportDocument("pdf", { note_id: selectedNoteId }, currentNoteTitle || "note"); },
+      },
+      {
+        id: "export-epub",
+        label: "Export as EPUB",
+        icon: "\u21e9",
+        action: () => { toggleCommandPalette(); exportDocument("epub", { note_id: selectedNoteId }, currentNoteTitle || "note"); },
+      },
     ] : []),
   ];
 
@@ -206,6 +327,13 @@ export function CommandPalette() {
                     <span className="text-[11px] text-bear-text-secondary line-clamp-1">
                       {note.preview}
                     </span>
+                    {note.attachment_match && (
+                      <span className="text-[11px] text-bear-text-muted line-clamp-1">
+                        Matched in attachment {note.attachment_match.name}
+                        {note.attachment_match.page != null && `, page ${note.attachment_match.page}`}
+                        {note.preview !== note.attachment_match.snippet && `: ${note.attachment_match.snippet}`}
+                      </span>
+                    )}
                   </Command.Item>
                 ))}
               </Command.Group>
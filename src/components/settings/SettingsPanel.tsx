@@ -17,6 +17,25 @@ const AUTO_SAVE_OPTIONS = [
   { label: "5 seconds", value: "5000" },
 ];
 
+const IMAGE_SIZE_OPTIONS = [
+  { label: "Original size", value: "0" },
+  { label: "1280 px", value: "1280" },
+  { label: "2560 px", value: "2560" },
+  { label: "4096 px", value: "4096" },
+];
+
+const IMAGE_FORMAT_OPTIONS = [
+  { label: "Keep format", value: "original" },
+  { label: "JPEG", value: "jpeg" },
+  { label: "PNG", value: "png" },
+  { label: "WebP (lossless)", value: "webp" },
+];
+
+const SEARCH_TOKENIZER_OPTIONS = [
+  { label: "Whole words", value: "unicode61" },
+  { label: "Any text (Chinese, Japanese)", value: "trigram" },
+];
+
 export function SettingsPanel() {
   const isOpen = useUIStore((s) => s.isSettingsOpen);
   const toggleSettings = useUIStore((s) => s.toggleSettings);
@@ -121,6 +140,70 @@ export function SettingsPanel() {
             </div>
           </section>
 
+          {/* Search */}
+          <section>
+            <h3 className="text-[12px] uppercase tracking-wider text-bear-text-muted font-medium mb-3">
+              Search
+            </h3>
+            <SettingRow label="Match">
+              <select
+                data-testid="settings-search-tokenizer"
+                value={settings.searchTokenizer}
+                onChange={(e) => settings.setSetting("search_tokenizer", e.target.value)}
+                className="bg-bear-hover border border-bear-border rounded px-2 py-1 text-[13px] text-bear-text outline-none"
+              >
+                {SEARCH_TOKENIZER_OPTIONS.map((opt) => (
+                  <option key={opt.value} value={opt.value}>
+                    {opt.label}
+                  </option>
+                ))}
+              </select>
+            </SettingRow>
+          </section>
+
+          {/* Images */}
+          <section>
+            <h3 className="text-[12px] uppercase tracking-wider text-bear-text-muted font-medium mb-3">
+              Images
+            </h3>
+            <div className="flex flex-col gap-3">
+              <SettingRow label="Maximum Size">
+                <select
+                  data-testid="settings-image-max-dimension"
+                  value={String(settings.imageMaxDimension)}
+                  onChange={(e) => settings.setSetting("image_max_dimension", e.target.value)}
+                  className="bg-bear-hover border border-bear-border rounded px-2 py-1 text-[13px] text-bear-text outline-none"
+                >
+                  {IMAGE_SIZE_OPTIONS.map((opt) => (
+                    <option key={opt.value} value={opt.value}>
+                      {opt.label}
+                    </option>
+                  ))}
+                </select>
+              </SettingRow>
+              <SettingRow label="Save As">
+                <select
+                  data-testid="settings-image-format"
+                  value={settings.imageFormat}
+                  onChange={(e) => settings.setSetting("image_format", e.target.value)}
+                  className="bg-bear-hover border border-bear-border rounded px-2 py-1 text-[13px] text-bear-text outline-none"
+                >
+                  {IMAGE_FORMAT_OPTIONS.map((opt) => (
+                    <option key={opt.value} value={opt.value}>
+                      {opt.label}
+                    </option>
+                  ))}
+                </select>
+              </SettingRow>
+              <SettingRow label="Remove Location & Camera Data">
+                <Toggle
+                  checked={settings.imageStripMetadata}
+                  onChange={(v) => settings.setSetting("image_strip_metadata", String(v))}
+                />
+              </SettingRow>
+            </div>
+          </section>
+
           {/* Agents */}
           <section>
             <h3 className="text-[12px] uppercase tracking-wider text-bear-text-muted font-medium mb-3">
@@ -0,0 +1,37 @@
+// Save a note, a tag or a workspace as a PDF or EPUB file
+
+import { save } from "@tauri-apps/plugin-dialog";
+import { useToastStore } from "../stores/toastStore";
+import * as tauri from "./tauri";
+
+export type DocumentFormat = "pdf" | "epub";
+
+export type DocumentSource =
+  | { note_id: string }
+  | { tag: string }
+  | { workspace_id: string };
+
+export async function exportDocument(format: DocumentFormat, source: DocumentSource, name: string) {
+  const { addToast } = useToastStore.getState();
+  try {
+    const path = await save({
+      defaultPath: `${name || "export"}.${format}`,
+      filters: [{ name: format === "pdf" ? "PDF" : "EPUB", extensions: [format] }],
+    });
+    if (!path) return;
+    const params = { ...source, output_path: path };
+    const result = format === "pdf" ? await tauri.exportPdf(params) : await tauri.exportEpub(params);
+    const notes = result.notes === 1 ? "1 note" : `${result.notes} notes`;
+    addToast({
+      type: "success",
+      message: result.pages !== null
+        ? `Exported ${notes} as PDF (${result.pages} pages)`
+        : `Exported ${notes} as EPUB`,
+    });
+    for (const warning of result.warnings) {
+      addToast({ type: "info", message: warning });
+    }
+  } catch (err) {
+    addToast({ type: "error", message: `Export failed: ${err}` });
+  }
+}
@@ -5,19 +5,35 @@ import type {
   NoteState,
   CreateNoteParams,
   UpdateNoteParams,
+  PatchNoteParams,
   ListNotesParams,
   SearchNotesParams,
 } from "../types/note";
-import type { Tag } from "../types/tag";
+import type { Tag, TagAlias, TagChange, UndoTagChangeResult } from "../types/tag";
 import type { ActivityEvent } from "../types/activity";
 import type { SyncState } from "../types/sync";
 import type { Template } from "../types/template";
 import type { Workspace } from "../types/workspace";
-import type { KnowledgeGraph } from "../types/graph";
+import type { KnowledgeGraph, ExportGraphParams } from "../types/graph";
 import type { Agent } from "../types/agent";
 import type { Task, TaskStatus, TaskPriority } from "../types/task";
 import type { WorkflowTemplate, WorkflowStep } from "../types/workflow";
 import type { Webhook, WebhookLog } from "../types/webhook";
+import type {
+  PropertyDefinition,
+  DefinePropertyParams,
+  NoteProperty,
+  PropertyValue,
+} from "../types/property";
+import type { QueryResult, NoteQueryResult } from "../types/query";
+import type { OutlineEntry, NoteSection, ResolvedLink } from "../types/outline";
+import type { RenderedEmbed } from "../types/embed";
+import type { ClipParams, ImportOptions, ImportReport } from "../types/import";
+import type { HtmlExportOptions } from "../types/export";
+import type { PublishSiteParams, PublishSiteResult } from "../types/publish";
+import type { DocumentExportParams, DocumentExportResult } from "../types/document";
+import type { VaultExportParams, VaultExportResult } from "../types/vault";
+import type { Attachment, AttachmentDetails, AttachmentGcReport } from "../types/attachment";
 
 // Note commands
 export async function createNote(params: CreateNoteParams): Promise<Note> {
@@ -32,6 +48,10 @@ export async function updateNote(params: UpdateNoteParams): Promise<Note> {
   return invoke("update_note", { params });
 }
 
+export async function patchNote(params: PatchNoteParams): Promise<Note> {
+  return invoke("patch_note", { params });
+}
+
 export async function deleteNote(
   id: string,
   permanent: boolean = false,
@@ -86,6 +106,42 @@ export async function deleteTag(name: string): Promise<void> {
   return invoke("delete_tag", { name });
 }
 
+export async function mergeTags(source: string, target: string): Promise<void> {
+  return invoke("merge_tags", { source, target });
+}
+
+export async function updateTagMetadata(
+  name: string,
+  metadata: { description?: string; color?: string; icon?: string },
+): Promise<void> {
+  return invoke("update_tag_metadata", {
+    name,
+    description: metadata.description ?? null,
+    color: metadata.color ?? null,
+    icon: metadata.icon ?? null,
+  });
+}
+
+export async function setTagAlias(alias: string, tagName: string): Promise<TagAlias> {
+  return invoke("set_tag_alias", { alias, tagName });
+}
+
+export async function listTagAliases(): Promise<TagAlias[]> {
+  return invoke("list_tag_aliases");
+}
+
+export async function removeTagAlias(alias: string): Promise<void> {
+  return invoke("remove_tag_alias", { alias });
+}
+
+export async function listTagChanges(limit?: number): Promise<TagChange[]> {
+  return invoke("list_tag_changes", { limit: limit ?? 50 });
+}
+
+export async function undoTagChange(id: string): Promise<UndoTagChangeResult> {
+  return invoke("undo_tag_change", { id });
+}
+
 // Search commands
 export async function searchNotes(
   params: SearchNotesParams,
@@ -93,6 +149,11 @@ export async function searchNotes(
   return invoke("search_notes", { params });
 }
 
+/** Rebuild the search index with "unicode61" (words) or "trigram" (any text, for CJK) */
+export async function setSearchTokenizer(tokenizer: string): Promise<void> {
+  return invoke("set_search_tokenizer", { tokenizer });
+}
+
 // Sync commands
 export async function triggerSync(): Promise<void> {
   return invoke("trigger_sync");
@@ -181,8 +242,13 @@ export async function semanticSearch(
 // Import commands
 export async function importMarkdownFiles(
   paths: string[],
-): Promise<{ imported: number; skipped: number }> {
-  return invoke("import_markdown_files", { paths });
+  options?: ImportOptions,
+): Promise<ImportReport> {
+  return invoke("import_markdown_files", { paths, options });
+}
+
+export async function clipWebPage(params: ClipParams): Promise<Note> {
+  return invoke("clip_web_page", { params });
 }
 
 // Settings commands
@@ -202,12 +268,24 @@ export async function getAllSettings(): Promise<Record<string, string>> {
 export async function exportNoteMarkdown(
   id: string,
   stripFrontmatter?: boolean,
+  flatten?: boolean,
+): Promise<string> {
+  return invoke("export_note_markdown", {
+    id,
+    stripFrontmatter: stripFrontmatter ?? false,
+    flatten: flatten ?? false,
+  });
+}
+
+export async function exportNoteHtml(
+  id: string,
+  options?: HtmlExportOptions,
 ): Promise<string> {
-  return invoke("export_note_markdown", { id, stripFrontmatter: stripFrontmatter ?? false });
+  return invoke("export_note_html", { id, options });
 }
 
-export async function exportNoteHtml(id: string): Promise<string> {
-  return invoke("export_note_html", { id });
+export async function exportKnowledgeGraph(params: ExportGraphParams): Promise<string> {
+  return invoke("export_knowledge_graph", { params });
 }
 
 // Agent commands
@@ -416,3 +494,103 @@ export async function saveImage(
 ): Promise<string> {
   return invoke("save_image", { data, filename });
 }
+
+// Attachment commands
+export async function listNoteAttachments(noteId: string): Promise<Attachment[]> {
+  return invoke("list_note_attachments", { noteId });
+}
+
+export async function getAttachment(id: string): Promise<AttachmentDetails> {
+  return invoke("get_attachment", { id });
+}
+
+/** Delete attachments unused for `graceDays` (default: the
+ * `attachment_gc_grace_days` setting, or 30) */
+export async function collectAttachmentGarbage(graceDays?: number): Promise<AttachmentGcReport> {
+  return invoke("collect_attachment_garbage", { graceDays });
+}
+
+// Property commands
+export async function defineProperty(
+  params: DefinePropertyParams,
+): Promise<PropertyDefinition> {
+  return invoke("define_property", { params });
+}
+
+export async function listPropertyDefinitions(
+  workspaceId?: string,
+): Promise<PropertyDefinition[]> {
+  return invoke("list_property_definitions", { workspaceId });
+}
+
+export async function deletePropertyDefinition(id: string): Promise<void> {
+  return invoke("delete_property_definition", { id });
+}
+
+export async function getNoteProperties(id: string): Promise<NoteProperty[]> {
+  return invoke("get_note_properties", { id });
+}
+
+export async function setNoteProperty(
+  id: string,
+  name: string,
+  value: PropertyValue,
+  expectedVersion?: number,
+): Promise<Note> {
+  return invoke("set_note_property", { id, name, value, expectedVersion });
+}
+
+// Live query commands
+export async function runQuery(query: string): Promise<QueryResult> {
+  return invoke("run_query", { query });
+}
+
+export async function getNoteQueryResults(
+  id: string,
+): Promise<NoteQueryResult[]> {
+  return invoke("get_note_query_results", { id });
+}
+
+// Outline commands
+export async function getNoteOutline(id: string): Promise<OutlineEntry[]> {
+  return invoke("get_note_outline", { id });
+}
+
+export async function getNoteSection(
+  id: string,
+  anchor?: string,
+  headingPath?: string,
+): Promise<NoteSection> {
+  return invoke("get_note_section", { id, anchor, headingPath });
+}
+
+export async function resolveWikiLink(
+  target: string,
+  fromNoteId?: string,
+): Promise<ResolvedLink> {
+  return invoke("resolve_wiki_link", { target, fromNoteId });
+}
+
+// Embed commands
+export async function renderNoteEmbeds(id: string): Promise<RenderedEmbed[]> {
+  return invoke("render_note_embeds", { id });
+}
+
+// Publish commands
+export async function publishSite(params: PublishSiteParams): Promise<PublishSiteResult> {
+  return invoke("publish_site", { params });
+}
+
+// Document export commands
+export async function exportPdf(params: DocumentExportParams): Promise<DocumentExportResult> {
+  return invoke("export_pdf", { params });
+}
+
+export async function exportEpub(params: DocumentExportParams): Promise<DocumentExportResult> {
+  return invoke("export_epub", { params });
+}
+
+// Vault commands
+export async function exportVault(params: VaultExportParams): Promise<VaultExportResult> {
+  return invoke("export_vault", { params });
+}
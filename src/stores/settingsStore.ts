@@ -8,6 +8,13 @@ interface SettingsState {
   spellCheck: boolean;
   showLineNumbers: boolean;
   defaultWorkspaceId: string | null;
+  /** Longest side saved images are scaled down to; 0 keeps their size */
+  imageMaxDimension: number;
+  /** "original", "jpeg", "png" or "webp" */
+  imageFormat: string;
+  imageStripMetadata: boolean;
+  /** "unicode61" matches words; "trigram" any text, for Chinese and Japanese */
+  searchTokenizer: string;
   loaded: boolean;
   loadSettings: () => Promise<void>;
   setSetting: (key: string, value: string) => Promise<void>;
@@ -20,6 +27,11 @@ const DEFAULTS: Record<string, string> = {
   spellCheck: "true",
   showLineNumbers: "false",
   defaultWorkspaceId: "",
+  // Read by the backend when images are saved, hence the snake_case keys
+  image_max_dimension: "2560",
+  image_format: "original",
+  image_strip_metadata: "true",
+  search_tokenizer: "unicode61",
 };
 
 export const useSettingsStore = create<SettingsState>((set) => ({
@@ -29,6 +41,10 @@ export const useSettingsStore = create<SettingsState>((set) => ({
   spellCheck: true,
   showLineNumbers: false,
   defaultWorkspaceId: null,
+  imageMaxDimension: 2560,
+  imageFormat: "original",
+  imageStripMetadata: true,
+  searchTokenizer: "unicode61",
   loaded: false,
 
   loadSettings: async () => {
@@ -41,6 +57,10 @@ export const useSettingsStore = create<SettingsState>((set) => ({
         spellCheck: (all.spellCheck ?? DEFAULTS.spellCheck) === "true",
         showLineNumbers: (all.showLineNumbers ?? DEFAULTS.showLineNumbers) === "true",
         defaultWorkspaceId: all.defaultWorkspaceId || null,
+        imageMaxDimension: parseInt(all.image_max_dimension ?? DEFAULTS.image_max_dimension, 10),
+        imageFormat: all.image_format ?? DEFAULTS.image_format,
+        imageStripMetadata: (all.image_strip_metadata ?? DEFAULTS.image_strip_metadata) === "true",
+        searchTokenizer: all.search_tokenizer ?? DEFAULTS.search_tokenizer,
         loaded: true,
       });
     } catch {
@@ -50,7 +70,12 @@ export const useSettingsStore = create<SettingsState>((set) => ({
 
   setSetting: async (key: string, value: string) => {
     try {
-      await tauri.setSetting(key, value);
+      // Changing the tokenizer rebuilds the search index
+      if (key === "search_tokenizer") {
+        await tauri.setSearchTokenizer(value);
+      } else {
+        await tauri.setSetting(key, value);
+      }
       // Update local state
       set((state) => {
         const updates: Partial<SettingsState> = {};
@@ -73,6 +98,18 @@ export const useSettingsStore = create<SettingsState>((set) => ({
           case "defaultWorkspaceId":
             updates.defaultWorkspaceId = value || null;
             break;
+          case "image_max_dimension":
+            updates.imageMaxDimension = parseInt(value, 10);
+            break;
+          case "image_format":
+            updates.imageFormat = value;
+            break;
+          case "image_strip_metadata":
+            updates.imageStripMetadata = value === "true";
+            break;
+          case "search_tokenizer":
+            updates.searchTokenizer = value;
+            break;
         }
         return { ...state, ...updates };
       });
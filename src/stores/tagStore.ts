@@ -30,7 +30,7 @@ function buildTree(tags: Tag[]): TagTreeNode[] {
     nodeMap.set(tag.name, {
       name: tag.name.split("/").pop() ?? tag.name,
       fullPath: tag.name,
-      noteCount: tag.note_count,
+      noteCount: tag.total_count,
       isPinned: tag.is_pinned,
       children: [],
     });
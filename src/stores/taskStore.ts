@@ -1,6 +1,16 @@
 import { create } from "zustand";
 import type { Task, TaskStatus, TaskPriority } from "../types/task";
 import * as tauri from "../lib/tauri";
+import { useNoteStore } from "./noteStore";
+
+// Completing or reopening a task ticks its checkbox in the linked note, so
+// reload that note if it is open.
+function refreshLinkedNote(task: Task) {
+  const { currentNote, selectNote } = useNoteStore.getState();
+  if (task.linked_note_id && currentNote?.id === task.linked_note_id) {
+    selectNote(task.linked_note_id);
+  }
+}
 
 interface TaskState {
   tasks: Task[];
@@ -38,11 +48,13 @@ export const useTaskStore = create<TaskState>((set, get) => ({
   completeTask: async (id) => {
     const updated = await tauri.completeTask(id);
     set({ tasks: get().tasks.map((t) => (t.id === id ? updated : t)) });
+    refreshLinkedNote(updated);
   },
 
   updateTask: async (id, updates) => {
     const updated = await tauri.updateTask(id, updates.title, undefined, updates.status, updates.priority);
     set({ tasks: get().tasks.map((t) => (t.id === id ? updated : t)) });
+    refreshLinkedNote(updated);
   },
 
   deleteTask: async (id) => {
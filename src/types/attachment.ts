@@ -0,0 +1,40 @@
+/** A file in the images store, stored once per content */
+export interface Attachment {
+  id: string;
+  /** SHA-256 of the file, hex */
+  hash: string;
+  /** Name in the images store */
+  file_name: string;
+  /** Name the file was saved or imported with */
+  original_name: string;
+  mime_type: string;
+  size: number;
+  created_at: string;
+  /** Since when no note refers to it; deleted after a grace period */
+  orphaned_at: string | null;
+  path: string;
+  /** Notes referring to it, trashed ones included */
+  note_count: number;
+  /** Path of its preview, for images */
+  thumbnail: string | null;
+}
+
+export interface AttachmentNote {
+  id: string;
+  title: string;
+  is_trashed: boolean;
+}
+
+export interface AttachmentDetails {
+  attachment: Attachment;
+  notes: AttachmentNote[];
+  /** Whether the file is still in the images store */
+  exists: boolean;
+}
+
+export interface AttachmentGcReport {
+  registered: number;
+  orphaned: number;
+  removed: number;
+  freed_bytes: number;
+}
@@ -0,0 +1,19 @@
+/** Notes for a PDF or EPUB export: exactly one of note_id, tag (with its
+ *  subtags) or workspace_id */
+export interface DocumentExportParams {
+  note_id?: string;
+  tag?: string;
+  workspace_id?: string;
+  output_path: string;
+  /** Defaults to the note title, tag or workspace name */
+  title?: string;
+}
+
+export interface DocumentExportResult {
+  output_path: string;
+  notes: number;
+  /** Page count, for PDFs */
+  pages: number | null;
+  /** Problems that did not stop the export */
+  warnings: string[];
+}
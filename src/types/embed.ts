@@ -0,0 +1,8 @@
+export interface RenderedEmbed {
+  /** What is between the brackets of `![[...]]` */
+  target: string;
+  note_id: string | null;
+  /** Rendered content, embeds inside it included */
+  html: string;
+  error: string | null;
+}
@@ -0,0 +1,14 @@
+export interface HtmlExportTheme {
+  id: string;
+  /** Theme CSS variables, e.g. `--bear-text` */
+  colors: Record<string, string>;
+}
+
+export interface HtmlExportOptions {
+  /** Colors for the page; defaults to a light style */
+  theme?: HtmlExportTheme;
+  /** "inline" embeds local images as data URIs; "copy" copies them into a
+   *  `<name>_files` folder next to `output_path` */
+  images?: "inline" | "copy";
+  output_path?: string;
+}
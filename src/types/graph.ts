@@ -15,3 +15,15 @@ export interface KnowledgeGraph {
   nodes: GraphNode[];
   edges: GraphEdge[];
 }
+
+export type GraphExportFormat = "graphml" | "dot" | "jsonld";
+
+export interface ExportGraphParams {
+  format: GraphExportFormat;
+  center_note_id?: string;
+  depth?: number;
+  max_nodes?: number;
+  tag?: string;
+  workspace_id?: string;
+  state?: string;
+}
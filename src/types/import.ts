@@ -0,0 +1,44 @@
+export interface ImportOptions {
+  /** Also import from subfolders of selected folders (default true) */
+  recursive?: boolean;
+  /** "tags" (default) tags notes with their folder path, "workspaces" maps
+   * top-level folders to workspaces, "none" ignores folders */
+  folders?: "tags" | "workspaces" | "none";
+  /** Workspace for notes that folder mapping does not place */
+  workspace_id?: string | null;
+  /** Only return the plan; nothing is written */
+  dry_run?: boolean;
+}
+
+export interface ImportFilePlan {
+  path: string;
+  action: "import" | "update" | "skip";
+  reason: string;
+  title: string;
+  /** The new or updated note, or the existing note a duplicate matches */
+  note_id: string | null;
+  tags: string[];
+  workspace: string | null;
+  /** Images and files copied along with the note */
+  attachments: number;
+}
+
+export interface ImportReport {
+  dry_run: boolean;
+  imported: number;
+  updated: number;
+  skipped: number;
+  /** To-dos written to the tasks table (Joplin to-dos, Evernote reminders) */
+  tasks: number;
+  files: ImportFilePlan[];
+}
+
+export interface ClipParams {
+  /** Page to fetch, or the address of the given HTML */
+  url?: string | null;
+  /** Page source, e.g. from the clipboard */
+  html?: string | null;
+  /** A saved web page; images next to it are clipped too */
+  path?: string | null;
+  workspace_id?: string | null;
+}
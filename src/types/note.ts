@@ -1,3 +1,5 @@
+import type { PropertyFilter } from "./property";
+
 export type NoteState = "draft" | "review" | "published";
 
 export interface Note {
@@ -15,6 +17,11 @@ export interface Note {
   state: NoteState;
   workspace_id: string | null;
   version: number;
+  extra_frontmatter?: string | null;
+  /** Characters other than whitespace */
+  char_count?: number;
+  /** Estimated minutes to read the note */
+  reading_minutes?: number;
 }
 
 export interface NoteListItem {
@@ -28,6 +35,19 @@ export interface NoteListItem {
   tags: string[];
   state: NoteState;
   workspace_id: string | null;
+  /** Set when a search matched text in one of the note's attachments */
+  attachment_match?: AttachmentMatch | null;
+  /** Path of a preview of the first image in the note */
+  thumbnail?: string | null;
+}
+
+/** Where a search matched the extracted text of an attachment */
+export interface AttachmentMatch {
+  attachment_id: string;
+  name: string;
+  /** 1-based page, for PDFs */
+  page: number | null;
+  snippet: string;
 }
 
 export interface CreateNoteParams {
@@ -43,6 +63,21 @@ export interface UpdateNoteParams {
   tags?: string[];
 }
 
+/** Heading paths name nested headings separated by ">", e.g. "Projects > Alpha". */
+export type PatchOperation =
+  | { op: "append"; heading?: string; text: string }
+  | { op: "prepend"; heading?: string; text: string }
+  | { op: "replace_section"; heading: string; text: string }
+  | { op: "insert_after"; after: string; text: string }
+  | { op: "replace"; find: string; replace: string; regex?: boolean; count?: number }
+  | { op: "toggle_checklist"; item: string; checked?: boolean };
+
+export interface PatchNoteParams {
+  id: string;
+  operations: PatchOperation[];
+  expected_version?: number;
+}
+
 export interface ListNotesParams {
   tag?: string;
   tags?: string[];
@@ -52,6 +87,8 @@ export interface ListNotesParams {
   limit?: number;
   offset?: number;
   workspace_id?: string;
+  property_filters?: PropertyFilter[];
+  sort_property?: string;
 }
 
 export interface SearchNotesParams {
@@ -0,0 +1,30 @@
+export interface OutlineEntry {
+  level: number;
+  heading: string;
+  /** Unique within the note; usable as `[[Note#anchor]]` */
+  anchor: string;
+  /** Byte offsets into the note content */
+  start: number;
+  end: number;
+  /** Words in the section, subsections included */
+  word_count: number;
+}
+
+export interface NoteSection {
+  note_id: string;
+  note_title: string;
+  level: number;
+  heading: string;
+  anchor: string;
+  /** The section's markdown, heading line included */
+  markdown: string;
+  start: number;
+  end: number;
+  word_count: number;
+}
+
+export interface ResolvedLink {
+  note_id: string;
+  note_title: string;
+  section: NoteSection | null;
+}
@@ -0,0 +1,50 @@
+export type PropertyType =
+  | "text"
+  | "number"
+  | "date"
+  | "select"
+  | "checkbox"
+  | "note";
+
+export type PropertyValue = string | number | boolean | null;
+
+export interface PropertyDefinition {
+  id: string;
+  workspace_id: string | null;
+  name: string;
+  property_type: PropertyType;
+  options: string[];
+  created_at: string;
+  updated_at: string;
+}
+
+export interface DefinePropertyParams {
+  workspace_id?: string | null;
+  name: string;
+  property_type: PropertyType;
+  options?: string[];
+}
+
+export interface NoteProperty {
+  name: string;
+  property_type: PropertyType;
+  value: PropertyValue;
+}
+
+export type PropertyFilterOp =
+  | "eq"
+  | "ne"
+  | "lt"
+  | "lte"
+  | "gt"
+  | "gte"
+  | "contains"
+  | "is_empty"
+  | "is_not_empty";
+
+export interface PropertyFilter {
+  name: string;
+  op: PropertyFilterOp;
+  /** Dates also accept "today", "tomorrow" and "yesterday". */
+  value?: PropertyValue;
+}
@@ -0,0 +1,22 @@
+import type { HtmlExportTheme } from "./export";
+
+export interface PublishSiteParams {
+  output_dir: string;
+  /** Only publish notes in this workspace; all workspaces if unset */
+  workspace_id?: string | null;
+  /** Defaults to the workspace name */
+  site_title?: string;
+  /** Absolute URL the site is served from, used for feed links */
+  base_url?: string;
+  theme?: HtmlExportTheme;
+  /** Render every page again, even those that have not changed */
+  full_rebuild?: boolean;
+}
+
+export interface PublishSiteResult {
+  output_dir: string;
+  pages_written: number;
+  pages_unchanged: number;
+  pages_removed: number;
+  tag_pages: number;
+}
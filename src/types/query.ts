@@ -0,0 +1,25 @@
+export type QueryKind = "list" | "table" | "tasks";
+
+export type QueryCell = string | number | boolean | null;
+
+export interface QueryRow {
+  /** Note id, or task id for `tasks` queries */
+  id: string;
+  note_id: string | null;
+  title: string;
+  values: QueryCell[];
+}
+
+export interface QueryResult {
+  kind: QueryKind;
+  columns: string[];
+  rows: QueryRow[];
+  error: string | null;
+}
+
+export interface NoteQueryResult {
+  block_index: number;
+  query: string;
+  result: QueryResult;
+  evaluated_at: string;
+}
@@ -3,7 +3,33 @@ export interface Tag {
   name: string;
   parent_name: string | null;
   note_count: number;
+  /** Notes tagged with this tag or any descendant. */
+  total_count: number;
   is_pinned: boolean;
+  description: string;
+  color: string | null;
+  icon: string | null;
+}
+
+export interface TagAlias {
+  alias: string;
+  tag_name: string;
+  created_at: string;
+}
+
+export interface TagChange {
+  id: string;
+  operation: "rename" | "delete" | "merge";
+  old_name: string;
+  new_name: string | null;
+  note_count: number;
+  created_at: string;
+  undone_at: string | null;
+}
+
+export interface UndoTagChangeResult {
+  restored: number;
+  skipped_note_ids: string[];
 }
 
 export interface TagTreeNode {
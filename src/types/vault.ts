@@ -0,0 +1,16 @@
+export interface VaultExportParams {
+  /** Zip file to write, or an empty folder for "directory" */
+  output_path: string;
+  /** "zip" (default) or "directory" */
+  format?: "zip" | "directory";
+  /** Only export this workspace's notes; all notes if unset */
+  workspace_id?: string | null;
+}
+
+export interface VaultExportResult {
+  output_path: string;
+  notes: number;
+  attachments: number;
+  /** Local images referenced by notes that could not be found */
+  missing_attachments: number;
+}